@@ -0,0 +1,88 @@
+//! How `validate --threads` scales over a batch of payload files.
+//!
+//! Runs the built binary on the same batch at each thread count:
+//!
+//! ```bash
+//! cargo bench --bench validate_batch
+//! ```
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use serde_json::json;
+
+const PAYLOADS: usize = 400;
+const LINE_ITEMS: usize = 200;
+
+/// A checkout schema and `PAYLOADS` valid checkouts to validate against it.
+fn write_batch(dir: &Path) {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "id": { "type": "string", "ucp_request": { "create": "omit" } },
+            "line_items": {
+                "type": "array",
+                "ucp_request": "required",
+                "items": {
+                    "type": "object",
+                    "required": ["sku", "quantity"],
+                    "properties": {
+                        "sku": { "type": "string", "pattern": "^SKU-[0-9]{5}$" },
+                        "quantity": { "type": "integer", "minimum": 1 }
+                    }
+                }
+            }
+        }
+    });
+    std::fs::write(dir.join("schema.json"), schema.to_string()).unwrap();
+
+    let line_items: Vec<_> = (0..LINE_ITEMS)
+        .map(|i| json!({ "sku": format!("SKU-{:05}", i), "quantity": i + 1 }))
+        .collect();
+    let payloads = dir.join("payloads");
+    std::fs::create_dir(&payloads).unwrap();
+    for i in 0..PAYLOADS {
+        let payload = json!({ "line_items": line_items });
+        std::fs::write(
+            payloads.join(format!("p{:04}.json", i)),
+            payload.to_string(),
+        )
+        .unwrap();
+    }
+}
+
+fn validate_batch(c: &mut Criterion) {
+    let dir = tempfile::TempDir::new().unwrap();
+    write_batch(dir.path());
+    let schema = dir.path().join("schema.json");
+    let payloads = dir.path().join("payloads").join("*.json");
+
+    let mut group = c.benchmark_group("validate_batch");
+    group.sample_size(10);
+    for threads in [1, 2, 4, 8] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(threads),
+            &threads,
+            |b, threads| {
+                b.iter(|| {
+                    let status = Command::new(env!("CARGO_BIN_EXE_ucp-schema"))
+                        .arg("validate")
+                        .arg(&payloads)
+                        .arg("--schema")
+                        .arg(&schema)
+                        .args(["--request", "--op", "create", "--threads"])
+                        .arg(threads.to_string())
+                        .stdout(Stdio::null())
+                        .status()
+                        .unwrap();
+                    assert!(status.success());
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, validate_batch);
+criterion_main!(benches);
@@ -0,0 +1,59 @@
+//! Embed build metadata for `ucp-schema version --json`.
+//!
+//! Exposes `UCP_SCHEMA_GIT_SHA` (commit the binary was built from, or
+//! "unknown" outside a git checkout) and `UCP_SCHEMA_BUILT` (UTC build date,
+//! honoring `SOURCE_DATE_EPOCH` for reproducible builds).
+
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let sha = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=UCP_SCHEMA_GIT_SHA={}", sha);
+
+    let epoch = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs())
+        });
+    println!("cargo:rustc-env=UCP_SCHEMA_BUILT={}", iso_date(epoch));
+
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    // Rebuild when HEAD moves, including new commits on the current branch.
+    // Outside a git checkout (e.g., a crates.io build) there's nothing to
+    // watch, and naming a missing path would rerun this script every build
+    if let Ok(head) = std::fs::read_to_string(".git/HEAD") {
+        println!("cargo:rerun-if-changed=.git/HEAD");
+        if let Some(branch) = head.trim().strip_prefix("ref: ") {
+            if std::path::Path::new(".git").join(branch).exists() {
+                println!("cargo:rerun-if-changed=.git/{}", branch);
+            }
+        }
+    }
+}
+
+/// Format seconds since the Unix epoch as a UTC `YYYY-MM-DD` date.
+fn iso_date(epoch: u64) -> String {
+    // Civil-from-days (Howard Hinnant), valid for dates after 1970
+    let days = (epoch / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
@@ -2,16 +2,39 @@
 //!
 //! Command-line interface for resolving and validating UCP schemas.
 
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufWriter, IsTerminal, Write};
+use std::net::TcpListener;
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
 
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use rayon::prelude::*;
+use regex::Regex;
 use ucp_schema::{
-    bundle_refs, bundle_refs_with_url_mapping, compose_from_payload, compose_schema,
-    detect_direction, extract_capabilities, extract_capabilities_from_profile,
-    extract_jsonrpc_payload, is_url, lint, load_schema, load_schema_auto, resolve, validate,
-    ComposeError, DetectedDirection, Direction, FileStatus, ResolveError, ResolveOptions,
-    SchemaBaseConfig, ValidateError,
+    annotate_payload, annotated_operations, annotation_matrix, bundle_refs, bundle_refs_as_defs,
+    bundle_refs_with_source_map, bundle_refs_with_url_mapping, cache_entries,
+    capabilities_from_files, capability_matches, check_profile, close_allof_branches,
+    compose_from_payload_with_options, compose_schema, compose_schema_with_options,
+    declared_operations, default_operation_rules, detect_direction, detect_orphans, diff_schemas,
+    endpoint_key, explain_validation, extract_capabilities, extract_capabilities_from_profile,
+    extract_jsonrpc_payload, file_stats, find_stray_annotations, format_files, fuzz_payloads,
+    generate_code, generate_profile, har_exchanges, html_site, import_openapi, infer_operation,
+    is_annotation, is_iso_date, is_url, jsonrpc_envelope, junit_xml, lint, load_input,
+    load_schema_auto, meta_schema, migrate_transitions, monotonicity_violations, normalize_schema,
+    openapi_document, prune_cache, redact_keywords, redact_payload, resolve, resolve_library,
+    resolve_with_report, run_schema_tests, scaffold, schema_coverage, schema_graph, scrub_payload,
+    upgrade_files, validate, validate_against_schema, vendor_schemas, Capability, CapabilityDoc,
+    CodegenOptions, CodegenTarget, CompiledValidator, ComposeError, ComposeOptions, CoverageKind,
+    Denullify, DetectedDirection, Direction, Explorer, FetchOptions, FetchRecorder, FileStatus,
+    InputFormat, JunitCase, JunitOutcome, JunitSuite, LibraryOptions, Lockfile, OpenApiOptions,
+    OperationRule, ProblemDetails, RedactMode, ResolutionReport, ResolveError, ResolveOptions,
+    SchemaBaseConfig, SchemaError, Server, ServerConfig, UrlMapping, ValidateError, CACHE_DIR_ENV,
+    LOCK_FILE, NO_NETWORK_ENV, REDACTED, TESTS_KEY, TESTS_SUFFIX,
 };
 
 /// Errors with associated CLI exit codes.
@@ -69,7 +92,10 @@ fn determine_direction(
 }
 
 #[cfg(feature = "remote")]
-use ucp_schema::bundle_refs_remote;
+use ucp_schema::{
+    bundle_refs_remote, default_method, probe, pull_schema, push_schemas, ProbeRequest,
+    ProbeResult, PushStatus, RegistryError,
+};
 
 #[derive(Parser)]
 #[command(name = "ucp-schema")]
@@ -78,6 +104,26 @@ use ucp_schema::bundle_refs_remote;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Exit with code 4 if any warning was emitted (hard errors keep their codes)
+    #[arg(long, global = true)]
+    fail_on_warning: bool,
+
+    /// Fail any remote fetch immediately (also: UCP_SCHEMA_NO_NETWORK=1)
+    #[arg(long, global = true)]
+    no_network: bool,
+
+    /// Verify every remote fetch against this lockfile (written by `lock`)
+    #[arg(long, global = true, value_name = "FILE")]
+    lockfile: Option<PathBuf>,
+
+    /// Cache remote fetches in this directory (also: UCP_SCHEMA_CACHE_DIR)
+    #[arg(long, global = true, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// Input format: json, yaml, or auto (by file extension, then content)
+    #[arg(long, global = true, default_value = "auto", value_parser = parse_input_format)]
+    input_format: InputFormat,
 }
 
 #[derive(Subcommand)]
@@ -85,7 +131,7 @@ enum Commands {
     /// Resolve a schema for a specific direction and operation.
     /// Accepts a schema file or a self-describing payload (auto-composes if payload detected).
     Resolve {
-        /// Schema or payload source: file path or URL (http:// or https://)
+        /// Schema or payload source: file path, URL (http:// or https://), or - for stdin
         schema: String,
 
         /// Resolve for request direction (auto-inferred for payloads)
@@ -96,22 +142,93 @@ enum Commands {
         #[arg(long, conflicts_with = "request")]
         response: bool,
 
+        /// Resolve for both directions (with --output-dir)
+        #[arg(long, conflicts_with_all = ["request", "response"], requires = "output_dir")]
+        both: bool,
+
         /// Operation to resolve for (e.g., create, update, read)
-        #[arg(long, short)]
-        op: String,
+        #[arg(long, short, required_unless_present_any = ["ops", "all_ops", "check_monotonicity_all_ops"])]
+        op: Option<String>,
+
+        /// Comma-separated operations to resolve, one file each (with --output-dir)
+        #[arg(
+            long,
+            value_delimiter = ',',
+            conflicts_with = "op",
+            requires = "output_dir"
+        )]
+        ops: Vec<String>,
+
+        /// Resolve every operation the annotations name or x-ucp-operations declares
+        /// (a JSON object keyed by operation, or one file each with --output-dir)
+        #[arg(long, conflicts_with_all = ["op", "ops", "emit_source_map"])]
+        all_ops: bool,
 
         /// Output file (stdout if not specified)
         #[arg(long)]
         output: Option<PathBuf>,
 
+        /// Write one file per operation × direction into this directory
+        #[arg(long, conflicts_with = "output")]
+        output_dir: Option<PathBuf>,
+
+        /// File names in --output-dir (placeholders: {name}, {op}, {direction})
+        /// [default: {name}-{op}-{direction}.json]
+        #[arg(long, value_parser = NameTemplate::parse, requires = "output_dir")]
+        name_template: Option<NameTemplate>,
+
+        /// Write a JSON report of the --output-dir jobs (outputs, status, timing, failure reasons)
+        #[arg(long, requires = "output_dir")]
+        summary_json: Option<PathBuf>,
+
+        /// Write a JSON report of what resolution did to each property (annotation, visibility, required, transition)
+        #[arg(long, conflicts_with = "output_dir")]
+        report: Option<PathBuf>,
+
         /// Pretty-print JSON output
         #[arg(long)]
         pretty: bool,
 
-        /// Dereference all $ref pointers (bundle into single schema; schema input only)
+        /// Dereference all $ref pointers (bundle into single schema)
         #[arg(long)]
         bundle: bool,
 
+        /// Write a sidecar JSON mapping inlined regions to their source file and pointer
+        #[arg(long, requires = "bundle", conflicts_with = "output_dir")]
+        emit_source_map: Option<PathBuf>,
+
+        /// Comma-separated value keywords to remove from output (e.g., default,examples)
+        #[arg(long, value_delimiter = ',')]
+        redact: Vec<String>,
+
+        /// UCP annotation to keep in the output while still applying it (repeatable, e.g., ucp_request)
+        #[arg(long, value_delimiter = ',', value_parser = parse_keep_annotation)]
+        keep_annotation: Vec<String>,
+
+        /// Keep the other UCP annotations as x-ucp-request/x-ucp-response instead of stripping them
+        #[arg(long)]
+        preserve_annotations: bool,
+
+        /// Omit ucp_sensitive properties from response schemas
+        #[arg(long)]
+        omit_sensitive: bool,
+
+        /// Propagate annotations across anyOf/oneOf branches, as across allOf
+        #[arg(long)]
+        propagate_variants: bool,
+
+        /// Rewrite nullable array types like ["string","null"]: openapi (type + nullable: true) or drop-null
+        #[arg(long, value_parser = parse_denullify)]
+        denullify: Option<Denullify>,
+
+        /// Write the local files read (input, composed schemas, inlined refs), one per line
+        #[arg(long)]
+        print_deps: Option<PathBuf>,
+
+        /// Write the files read as a Makefile rule for --output
+        #[arg(long, requires = "output")]
+        deps_make: Option<PathBuf>,
+
         /// Local directory containing schema files (used when input is a payload)
         #[arg(long)]
         schema_local_base: Option<PathBuf>,
@@ -120,10 +237,44 @@ enum Commands {
         #[arg(long, requires = "schema_local_base")]
         schema_remote_base: Option<String>,
 
+        /// Map a URL prefix to a local subtree, PREFIX=DIR (repeatable; tried in order)
+        #[arg(
+            long = "map",
+            value_name = "PREFIX=DIR",
+            value_parser = parse_url_mapping,
+            conflicts_with = "schema_remote_base"
+        )]
+        map: Vec<UrlMapping>,
+
+        /// Warn when capability schema URLs don't start with --schema-remote-base
+        #[arg(long, requires = "schema_remote_base")]
+        warn_on_unused_schema_base: bool,
+
+        /// Compose extensions whose parent capability is absent (payload input only)
+        #[arg(long)]
+        allow_orphan_extensions: bool,
+
+        /// Fail (instead of warn) when a schema file declares a different version than its capability advertises (payload input only)
+        #[arg(long)]
+        strict_version: bool,
+
+        /// Before resolving, check every annotated operation (both directions) for
+        /// extensions weakening base-required fields; exit 2 listing all violations
+        #[arg(long)]
+        check_monotonicity_all_ops: bool,
+
+        /// Drop duplicate and empty allOf branches, collapsing single-branch allOf
+        #[arg(long)]
+        dedupe_allof: bool,
+
         /// Strict mode: set additionalProperties=false to reject unknown fields (default: false)
         #[arg(long, default_value_t = false, action = clap::ArgAction::Set)]
         strict: bool,
 
+        /// Close only capabilities matching these globs, e.g. dev.ucp.shopping.*; implies strict mode for them, --strict not needed (payload input only)
+        #[arg(long, value_delimiter = ',')]
+        strict_only: Vec<String>,
+
         /// Print pipeline stages to stderr for debugging
         #[arg(long, short)]
         verbose: bool,
@@ -131,10 +282,12 @@ enum Commands {
 
     /// Validate a payload against a resolved schema
     Validate {
-        /// Payload file to validate
-        payload: PathBuf,
+        /// Payload files to validate (- for stdin); several files or a glob like
+        /// 'payloads/*.json' validate each against one --schema or --profile
+        #[arg(required = true)]
+        payloads: Vec<PathBuf>,
 
-        /// Explicit schema (default: infer from payload's UCP metadata)
+        /// Explicit schema: file path, URL, or - for stdin (default: infer from payload's UCP metadata)
         #[arg(long)]
         schema: Option<String>,
 
@@ -146,6 +299,31 @@ enum Commands {
         #[arg(long, requires = "schema_local_base")]
         schema_remote_base: Option<String>,
 
+        /// Map a URL prefix to a local subtree, PREFIX=DIR (repeatable; tried in order)
+        #[arg(
+            long = "map",
+            value_name = "PREFIX=DIR",
+            value_parser = parse_url_mapping,
+            conflicts_with = "schema_remote_base"
+        )]
+        map: Vec<UrlMapping>,
+
+        /// Warn when capability schema URLs don't start with --schema-remote-base
+        #[arg(long, requires = "schema_remote_base")]
+        warn_on_unused_schema_base: bool,
+
+        /// Fail (instead of warn) when a schema file declares a different version than its capability advertises
+        #[arg(long)]
+        strict_version: bool,
+
+        /// Compose only capabilities whose name matches this regex, plus the capabilities they extend (not with --schema)
+        #[arg(long, value_name = "REGEX", value_parser = parse_capability_filter)]
+        capability_filter: Option<Regex>,
+
+        /// JSON Pointer to the payload inside a transport wrapper (e.g., '#/body')
+        #[arg(long)]
+        unwrap_pointer: Option<String>,
+
         /// Agent profile URL (REST pattern: profile via header, payload is raw object)
         #[arg(long, conflicts_with = "schema")]
         profile: Option<String>,
@@ -159,17 +337,64 @@ enum Commands {
         response: bool,
 
         /// Operation to validate for (e.g., create, update, read)
-        #[arg(long, short)]
-        op: String,
+        #[arg(long, short, required_unless_present = "op_any")]
+        op: Option<String>,
+
+        /// Comma-separated operations; passes if the payload is valid for any of them
+        #[arg(long, value_delimiter = ',', conflicts_with = "op")]
+        op_any: Vec<String>,
 
         /// Output results as JSON (for automation)
         #[arg(long)]
         json: bool,
 
+        /// Output format: text (default), json (same as --json), junit
+        /// (JUnit XML, one test case per payload; needs --schema or --profile),
+        /// or problem-json (an RFC 9457 problem object on failure)
+        #[arg(long, default_value = "text", value_parser = ["text", "json", "junit", "problem-json"], conflicts_with = "json")]
+        format: String,
+
+        /// Render each error with a template, e.g. '{path}: {keyword}: {message}'
+        /// (placeholders: {path}, {schemaPath}, {keyword}, {message})
+        #[arg(long, value_parser = ErrorFormat::parse, conflicts_with = "json")]
+        error_format: Option<ErrorFormat>,
+
         /// Strict mode: reject unknown fields (default: false)
         #[arg(long, default_value_t = false, action = clap::ArgAction::Set)]
         strict: bool,
 
+        /// After a lenient pass, also validate strictly and warn about fields
+        /// only strict mode would reject
+        #[arg(long)]
+        show_strict_delta: bool,
+
+        /// Read newline-delimited JSON payloads from the payload file (or - for stdin)
+        /// and print one result object per line
+        #[arg(long, conflicts_with_all = ["json", "format", "error_format", "annotate_payload", "op_any", "show_strict_delta"])]
+        ndjson: bool,
+
+        /// Validate several payload files on this many threads (0 = one per CPU);
+        /// results are still printed in input order
+        #[arg(long, default_value_t = 1, conflicts_with = "ndjson")]
+        threads: usize,
+
+        /// Print the payload with an x-ucp-status map (ok, unexpected,
+        /// type-error, invalid) on every object
+        #[arg(long, conflicts_with_all = ["json", "error_format", "op_any"])]
+        annotate_payload: bool,
+
+        /// Expand internal `{"$ref": "#/..."}` references in the payload data before validating
+        #[arg(long)]
+        deref_payload: bool,
+
+        /// Reject request payloads that include readOnly properties
+        #[arg(long)]
+        reject_readonly: bool,
+
+        /// Propagate annotations across anyOf/oneOf branches, as across allOf
+        #[arg(long)]
+        propagate_variants: bool,
+
         /// Print pipeline stages to stderr for debugging
         #[arg(long, short)]
         verbose: bool,
@@ -177,7 +402,7 @@ enum Commands {
 
     /// Compose capability schemas from a self-describing payload (annotations preserved)
     Compose {
-        /// Payload file with UCP capabilities metadata
+        /// Payload file with UCP capabilities metadata (- for stdin)
         payload: PathBuf,
 
         /// Local directory containing schema files
@@ -188,6 +413,63 @@ enum Commands {
         #[arg(long, requires = "schema_local_base")]
         schema_remote_base: Option<String>,
 
+        /// Map a URL prefix to a local subtree, PREFIX=DIR (repeatable; tried in order)
+        #[arg(
+            long = "map",
+            value_name = "PREFIX=DIR",
+            value_parser = parse_url_mapping,
+            conflicts_with = "schema_remote_base"
+        )]
+        map: Vec<UrlMapping>,
+
+        /// Warn when capability schema URLs don't start with --schema-remote-base
+        #[arg(long, requires = "schema_remote_base")]
+        warn_on_unused_schema_base: bool,
+
+        /// Compose extensions whose parent capability is absent (with a warning)
+        #[arg(long)]
+        allow_orphan_extensions: bool,
+
+        /// Drop capabilities whose version (ISO date) is older than this, e.g. 2026-01-01
+        #[arg(long, value_parser = parse_iso_date)]
+        min_version: Option<String>,
+
+        /// Fail (instead of warn) when a schema file declares a different version than its capability advertises
+        #[arg(long)]
+        strict_version: bool,
+
+        /// Drop duplicate and empty allOf branches, collapsing single-branch allOf
+        #[arg(long)]
+        dedupe_allof: bool,
+
+        /// Compose only the root capability, skipping extensions (base object schema, no allOf)
+        #[arg(long)]
+        root_only: bool,
+
+        /// Compose only capabilities whose name matches this regex, plus the capabilities they extend
+        #[arg(long, value_name = "REGEX", value_parser = parse_capability_filter)]
+        capability_filter: Option<Regex>,
+
+        /// Resolve the composed schema for --op (annotations stripped), like `resolve` on a payload
+        #[arg(long, group = "resolve_mode", requires = "op")]
+        resolve: bool,
+
+        /// Resolve the composed schema for --op in both directions: {"request": ..., "response": ...}
+        #[arg(long, group = "resolve_mode", requires = "op")]
+        split_directions: bool,
+
+        /// Operation to resolve for (with --resolve or --split-directions)
+        #[arg(long, short, requires = "resolve_mode")]
+        op: Option<String>,
+
+        /// Resolve for request direction (with --resolve; auto-inferred otherwise)
+        #[arg(long, conflicts_with_all = ["response", "split_directions"], requires = "resolve")]
+        request: bool,
+
+        /// Resolve for response direction (with --resolve; auto-inferred otherwise)
+        #[arg(long, conflicts_with_all = ["request", "split_directions"], requires = "resolve")]
+        response: bool,
+
         /// Output file (stdout if not specified)
         #[arg(long)]
         output: Option<PathBuf>,
@@ -201,541 +483,7084 @@ enum Commands {
         verbose: bool,
     },
 
-    /// Lint schema files for errors (syntax, broken refs, invalid annotations)
-    Lint {
-        /// File or directory to lint
-        path: PathBuf,
+    /// Inline external $refs into one self-contained schema (annotations preserved)
+    Bundle {
+        /// Schema source: file path, URL (http:// or https://), or - for stdin
+        schema: String,
 
-        /// Output format: text (default) or json
-        #[arg(long, default_value = "text")]
-        format: String,
+        /// Local directory containing schema files (maps absolute $ref URLs, with --schema-remote-base)
+        #[arg(long)]
+        schema_local_base: Option<PathBuf>,
 
-        /// Treat warnings as errors
+        /// URL prefix to strip when mapping to local (e.g., https://ucp.dev/draft)
+        #[arg(long, requires = "schema_local_base")]
+        schema_remote_base: Option<String>,
+
+        /// Output file (default: stdout)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+
+        /// Pretty-print JSON output
         #[arg(long)]
-        strict: bool,
+        pretty: bool,
 
-        /// Suppress progress output, only show errors
+        /// Write a sidecar JSON mapping inlined regions to their source file and pointer
+        #[arg(long)]
+        emit_source_map: Option<PathBuf>,
+
+        /// inline: copy referenced schemas into place; defs: add each file once under $defs and point refs at it
+        #[arg(long, default_value = "inline", value_parser = ["inline", "defs"])]
+        bundle_mode: String,
+
+        /// Print pipeline stages to stderr for debugging
         #[arg(long, short)]
-        quiet: bool,
+        verbose: bool,
     },
-}
 
-fn main() -> ExitCode {
-    let cli = Cli::parse();
+    /// Compose capability schema files into one allOf schema, without a payload
+    Merge {
+        /// Capability schema files (root and extensions)
+        #[arg(required = true)]
+        schemas: Vec<PathBuf>,
 
-    let result = match cli.command {
-        Commands::Resolve {
-            schema,
-            request,
-            response,
-            op,
-            output,
-            pretty,
-            bundle,
-            schema_local_base,
-            schema_remote_base,
-            strict,
-            verbose,
-        } => run_resolve(
-            &schema,
-            request,
-            response,
-            op,
-            output,
-            pretty,
-            bundle,
-            schema_local_base,
-            schema_remote_base,
-            strict,
-            verbose,
-        ),
+        /// Declare that EXT extends PARENT (full name, short name or file stem; repeatable)
+        #[arg(long, value_name = "EXT=PARENT", value_parser = parse_extends)]
+        extends: Vec<(String, String)>,
 
-        Commands::Compose {
-            payload,
-            schema_local_base,
-            schema_remote_base,
-            output,
-            pretty,
-            verbose,
-        } => run_compose(
-            &payload,
-            schema_local_base,
-            schema_remote_base,
-            output,
-            pretty,
-            verbose,
-        ),
+        /// Drop duplicate and empty allOf branches from the merged schema
+        #[arg(long)]
+        dedupe_allof: bool,
 
-        Commands::Validate {
-            payload,
-            schema,
-            schema_local_base,
-            schema_remote_base,
-            profile,
-            request,
-            response,
-            op,
-            json,
-            strict,
-            verbose,
-        } => run_validate(ValidateArgs {
-            payload,
-            schema,
-            schema_local_base,
-            schema_remote_base,
-            profile,
-            request,
-            response,
-            op,
-            json_output: json,
-            strict,
-            verbose,
-        }),
+        /// Output file (default: stdout)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
 
-        Commands::Lint {
-            path,
-            format,
-            strict,
-            quiet,
-        } => run_lint(&path, &format, strict, quiet),
-    };
+        /// Pretty-print JSON output
+        #[arg(long)]
+        pretty: bool,
 
-    match result {
-        Ok(()) => ExitCode::SUCCESS,
-        Err(code) => ExitCode::from(code),
-    }
-}
+        /// Print pipeline stages to stderr for debugging
+        #[arg(long, short)]
+        verbose: bool,
+    },
 
-/// Resolve a schema for a specific direction and operation.
-///
-/// Auto-detects input type: if the input is a self-describing payload (has
-/// ucp.capabilities or meta.profile), composes schemas first then resolves.
-/// Otherwise resolves the schema directly.
-#[allow(clippy::too_many_arguments)]
-fn run_resolve(
-    schema_source: &str,
-    request: bool,
-    response: bool,
-    op: String,
-    output: Option<PathBuf>,
-    pretty: bool,
-    bundle: bool,
-    schema_local_base: Option<PathBuf>,
-    schema_remote_base: Option<String>,
-    strict: bool,
-    verbose: bool,
-) -> Result<(), u8> {
-    if verbose {
-        eprintln!("[load] reading {}", schema_source);
-    }
-    let mut input = load_schema_auto(schema_source).map_err(cli_err(false))?;
+    /// Pin every remote schema and profile a payload or schema URL fetches
+    Lock {
+        /// Self-describing payloads, profiles or schema URLs to lock
+        #[arg(required = true)]
+        sources: Vec<String>,
 
-    // Auto-detect: is this a payload (needs compose) or a schema (resolve directly)?
-    let detected = detect_direction(&input);
+        /// Lockfile to write
+        #[arg(long, short, default_value = LOCK_FILE)]
+        output: PathBuf,
 
-    // Flag validation: reject flags that don't apply to the detected input type
-    if detected.is_some() {
-        if bundle {
-            report_error(false, "--bundle does not apply to payload input (schemas are auto-composed from capabilities). Remove --bundle, or pass a schema file instead of a payload.");
-            return Err(2);
-        }
-    } else if schema_local_base.is_some() || schema_remote_base.is_some() {
-        report_error(false, "--schema-local-base/--schema-remote-base only apply to payload input. Remove these flags, or pass a self-describing payload instead of a schema file.");
-        return Err(2);
-    }
+        /// Print pipeline stages to stderr for debugging
+        #[arg(long, short)]
+        verbose: bool,
+    },
 
-    let schema = if detected.is_some() {
-        // Input is a self-describing payload — compose schemas from capabilities
-        let config = SchemaBaseConfig {
-            local_base: schema_local_base.as_deref(),
-            remote_base: schema_remote_base.as_deref(),
-        };
-        if verbose {
-            verbose_capabilities(&input, &config);
-            eprintln!("[compose] composing schemas from payload capabilities");
-        }
-        compose_from_payload(&input, &config).map_err(cli_err(false))?
-    } else {
-        if verbose {
-            eprintln!("[detect] input is a schema file (no ucp.capabilities)");
-        }
-        // Input is a schema file — bundle $refs if requested
-        if bundle {
-            if verbose {
-                eprintln!("[bundle] inlining $ref pointers");
-            }
-            let base_dir = Path::new(schema_source).parent().unwrap_or(Path::new("."));
-            bundle_refs(&mut input, base_dir).map_err(cli_err_ctx(false, "bundling refs"))?;
-        }
-        input
-    };
+    /// Download the profile, capability schemas and their refs for offline use
+    Vendor {
+        /// Self-describing payload: file path, URL, or - for stdin
+        payload: String,
 
-    // Direction: explicit flag > auto-inferred from payload > require explicit
-    let direction = determine_direction(request, response, detected.map(Direction::from))
-        .ok_or_else(|| {
-            report_error(
-                false,
-                "--request or --response is required for schema input",
-            );
-            2u8
-        })?;
+        /// Directory to mirror into (use it as --schema-local-base)
+        #[arg(long, value_name = "DIR")]
+        out: PathBuf,
 
-    let options = ResolveOptions::new(direction, &op).strict(strict);
+        /// Profile URL, for payloads without ucp.capabilities or meta.profile
+        #[arg(long)]
+        profile: Option<String>,
+
+        /// Print pipeline stages to stderr for debugging
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Publish the capability schemas in a directory to a schema registry
+    #[cfg(feature = "remote")]
+    Push {
+        /// Schema directory to scan for capability schemas
+        dir: PathBuf,
+
+        /// Registry base URL (schemas go to <registry>/<name>/<version>)
+        #[arg(long)]
+        registry: String,
+
+        /// Bearer token for the registry (also: UCP_SCHEMA_REGISTRY_TOKEN)
+        #[arg(long)]
+        token: Option<String>,
+
+        /// Only report what would be published
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Output format: text (default) or json
+        #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+        format: String,
+    },
+
+    /// Fetch capability schemas from a schema registry, verifying their hashes
+    #[cfg(feature = "remote")]
+    Pull {
+        /// Schemas to fetch, as NAME@VERSION (e.g., dev.ucp.shopping.checkout@2026-01-11)
+        #[arg(required = true, value_name = "NAME@VERSION", value_parser = parse_name_version)]
+        capabilities: Vec<(String, String)>,
+
+        /// Registry base URL (schemas are read from <registry>/<name>/<version>)
+        #[arg(long)]
+        registry: String,
+
+        /// Bearer token for the registry (also: UCP_SCHEMA_REGISTRY_TOKEN)
+        #[arg(long)]
+        token: Option<String>,
+
+        /// Directory to write <name>/<version>.json into
+        #[arg(long, value_name = "DIR", default_value = ".")]
+        out: PathBuf,
+
+        /// Expected SHA-256 of the schema (with a single NAME@VERSION)
+        #[arg(long)]
+        sha256: Option<String>,
+    },
+
+    /// Inspect and manage the cache of remote fetches (--cache-dir)
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+
+    /// Print the capability payload nested in a JSON-RPC request envelope
+    Extract {
+        /// JSON-RPC request envelope (- for stdin)
+        payload: PathBuf,
+
+        /// Profile URL (default: the envelope's meta.profile)
+        #[arg(long)]
+        profile: Option<String>,
+
+        /// Local directory containing schema files
+        #[arg(long)]
+        schema_local_base: Option<PathBuf>,
+
+        /// URL prefix to strip when mapping to local (e.g., https://ucp.dev/draft)
+        #[arg(long, requires = "schema_local_base")]
+        schema_remote_base: Option<String>,
+
+        /// Map a URL prefix to a local subtree, PREFIX=DIR (repeatable; tried in order)
+        #[arg(
+            long = "map",
+            value_name = "PREFIX=DIR",
+            value_parser = parse_url_mapping,
+            conflicts_with = "schema_remote_base"
+        )]
+        map: Vec<UrlMapping>,
+
+        /// Print {"key": ..., "payload": ...} instead of the bare payload
+        #[arg(long)]
+        with_key: bool,
+
+        /// Output file (default: stdout)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+
+        /// Pretty-print JSON output
+        #[arg(long)]
+        pretty: bool,
+
+        /// Print pipeline stages to stderr for debugging
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Generate and check agent profiles
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommand,
+    },
+
+    /// Wrap a raw capability payload in a JSON-RPC request (inverse of extract)
+    Envelope {
+        /// Capability payload (- for stdin)
+        payload: PathBuf,
+
+        /// Profile URL, set as params.meta.profile
+        #[arg(long)]
+        profile: String,
+
+        /// Operation, used in the method name (e.g., create -> create_checkout)
+        #[arg(long, short)]
+        op: String,
+
+        /// Root capability to wrap for, full or short name (default: the profile's only root)
+        #[arg(long)]
+        capability: Option<String>,
+
+        /// JSON-RPC request id (numeric ids are emitted as numbers)
+        #[arg(long, default_value = "1")]
+        id: String,
+
+        /// Local directory containing schema files
+        #[arg(long)]
+        schema_local_base: Option<PathBuf>,
+
+        /// URL prefix to strip when mapping to local (e.g., https://ucp.dev/draft)
+        #[arg(long, requires = "schema_local_base")]
+        schema_remote_base: Option<String>,
+
+        /// Map a URL prefix to a local subtree, PREFIX=DIR (repeatable; tried in order)
+        #[arg(
+            long = "map",
+            value_name = "PREFIX=DIR",
+            value_parser = parse_url_mapping,
+            conflicts_with = "schema_remote_base"
+        )]
+        map: Vec<UrlMapping>,
+
+        /// Output file (default: stdout)
+        #[arg(long)]
+        output: Option<PathBuf>,
+
+        /// Pretty-print JSON output
+        #[arg(long)]
+        pretty: bool,
+
+        /// Print pipeline stages to stderr for debugging
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Send requests to a running API and validate its responses against
+    /// the response schema composed from a profile
+    #[cfg(feature = "remote")]
+    Probe {
+        /// Endpoint URL (also the base for request templates' "path")
+        url: String,
+
+        /// Profile URL declaring the capabilities the API implements
+        #[arg(long)]
+        profile: String,
+
+        /// Operation the requests perform (create, read, update, complete, ...)
+        #[arg(long, short)]
+        op: String,
+
+        /// Request template file: {"method", "url" | "path", "headers", "body"},
+        /// or an array of them (repeatable; default: one request to URL)
+        #[arg(long = "template", value_name = "FILE")]
+        templates: Vec<PathBuf>,
+
+        /// HTTP method (default: POST for create/complete, PUT for update,
+        /// DELETE for cancel, GET otherwise)
+        #[arg(long)]
+        method: Option<String>,
+
+        /// JSON request body file (when no templates are given)
+        #[arg(long, conflicts_with = "templates")]
+        body: Option<PathBuf>,
+
+        /// Extra header "Name: value" sent with every request (repeatable)
+        #[arg(long = "header", value_name = "NAME: VALUE", value_parser = parse_header)]
+        headers: Vec<(String, String)>,
+
+        /// Local directory containing schema files
+        #[arg(long)]
+        schema_local_base: Option<PathBuf>,
+
+        /// URL prefix to strip when mapping to local (e.g., https://ucp.dev/draft)
+        #[arg(long, requires = "schema_local_base")]
+        schema_remote_base: Option<String>,
+
+        /// Map a URL prefix to a local subtree, PREFIX=DIR (repeatable; tried in order)
+        #[arg(
+            long = "map",
+            value_name = "PREFIX=DIR",
+            value_parser = parse_url_mapping,
+            conflicts_with = "schema_remote_base"
+        )]
+        map: Vec<UrlMapping>,
+
+        /// Report format
+        #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+        format: String,
+
+        /// Print pipeline stages to stderr for debugging
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Validate the bodies of recorded traffic (a HAR capture) and summarize
+    /// failures by endpoint
+    Replay {
+        /// HAR file (- for stdin)
+        har: PathBuf,
+
+        /// Operation rule "[METHOD ]PATTERN=OP" matched against URL paths,
+        /// tried before the defaults (repeatable; e.g. "POST /orders$=create")
+        #[arg(long = "rule", value_name = "RULE", value_parser = parse_operation_rule)]
+        rules: Vec<OperationRule>,
+
+        /// Profile URL for REST requests without a UCP-Agent header
+        #[arg(long)]
+        profile: Option<String>,
+
+        /// Local directory containing schema files
+        #[arg(long)]
+        schema_local_base: Option<PathBuf>,
+
+        /// URL prefix to strip when mapping to local (e.g., https://ucp.dev/draft)
+        #[arg(long, requires = "schema_local_base")]
+        schema_remote_base: Option<String>,
+
+        /// Map a URL prefix to a local subtree, PREFIX=DIR (repeatable; tried in order)
+        #[arg(
+            long = "map",
+            value_name = "PREFIX=DIR",
+            value_parser = parse_url_mapping,
+            conflicts_with = "schema_remote_base"
+        )]
+        map: Vec<UrlMapping>,
+
+        /// Report format
+        #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+        format: String,
+
+        /// Print why exchanges were skipped to stderr
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Rewrite a schema in canonical form (sorted keys, expanded annotations)
+    Normalize {
+        /// Schema source: file path, URL (http:// or https://), or - for stdin
+        schema: String,
+
+        /// Output file (default: stdout)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+
+        /// Pretty-print JSON output
+        #[arg(long)]
+        pretty: bool,
+    },
+
+    /// Resolve every operation × direction into one `$defs`-keyed schema library
+    Library {
+        /// Schema source: file path or URL (http:// or https://)
+        schema: String,
+
+        /// Comma-separated operations to resolve (e.g., create,read,update)
+        #[arg(long, value_delimiter = ',', required = true)]
+        ops: Vec<String>,
+
+        /// Base name for $defs entries (default: schema title, else file name)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Dereference all $ref pointers before resolving
+        #[arg(long)]
+        bundle: bool,
+
+        /// Replace entries identical to an earlier one with a $ref to it
+        #[arg(long)]
+        dedupe: bool,
+
+        /// Strict mode: set additionalProperties=false to reject unknown fields (default: false)
+        #[arg(long, default_value_t = false, action = clap::ArgAction::Set)]
+        strict: bool,
+
+        /// Output file (stdout if not specified)
+        #[arg(long)]
+        output: Option<PathBuf>,
+
+        /// Write a JSON report of the job (input, output, status, timing, failure reason)
+        #[arg(long)]
+        summary_json: Option<PathBuf>,
+
+        /// Pretty-print JSON output
+        #[arg(long)]
+        pretty: bool,
+
+        /// Print pipeline stages to stderr for debugging
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Generate typed bindings (one module per operation × direction) from a schema
+    Codegen {
+        /// Target language: rust or go
+        #[arg(value_parser = parse_codegen_target)]
+        target: CodegenTarget,
+
+        /// Schema source: file path, URL (http:// or https://), or - for stdin
+        schema: String,
+
+        /// Comma-separated operations to generate (e.g., create,read,update)
+        #[arg(long, value_delimiter = ',', required = true)]
+        ops: Vec<String>,
+
+        /// Root type name (default: schema title, else file name)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Dereference all $ref pointers before resolving
+        #[arg(long)]
+        bundle: bool,
+
+        /// Strict mode: resolve with additionalProperties=false so types reject unknown fields
+        #[arg(long, default_value_t = false, action = clap::ArgAction::Set)]
+        strict: bool,
+
+        /// Output file (stdout if not specified)
+        #[arg(long)]
+        output: Option<PathBuf>,
+
+        /// Print pipeline stages to stderr for debugging
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Convert between UCP-annotated schemas and OpenAPI 3.1
+    Convert {
+        /// Capability schemas (--to), or one OpenAPI spec (--from); file paths or URLs
+        #[arg(required = true)]
+        schemas: Vec<String>,
+
+        /// Export resolved schemas to this format
+        #[arg(long, value_parser = ["openapi"], required_unless_present = "from", conflicts_with = "from")]
+        to: Option<String>,
+
+        /// Import a spec in this format into one annotated schema
+        #[arg(long, value_parser = ["openapi"])]
+        from: Option<String>,
+
+        /// Resource path to import, e.g. /checkouts (required if the spec has several)
+        #[arg(long, requires = "from")]
+        resource: Option<String>,
+
+        /// Comma-separated operations to resolve (e.g., create,read,update)
+        #[arg(
+            long,
+            value_delimiter = ',',
+            required_unless_present = "from",
+            conflicts_with = "from"
+        )]
+        ops: Vec<String>,
+
+        /// info.title of the generated document (with --from: the schema title)
+        #[arg(long)]
+        title: Option<String>,
+
+        /// info.version of the generated document
+        #[arg(long, default_value = "1.0.0", conflicts_with = "from")]
+        api_version: String,
+
+        /// Dereference all $ref pointers before resolving
+        #[arg(long, conflicts_with = "from")]
+        bundle: bool,
+
+        /// Strict mode: set additionalProperties=false to reject unknown fields (default: false)
+        #[arg(long, default_value_t = false, action = clap::ArgAction::Set, conflicts_with = "from")]
+        strict: bool,
+
+        /// Output file (stdout if not specified)
+        #[arg(long)]
+        output: Option<PathBuf>,
+
+        /// Pretty-print JSON output
+        #[arg(long)]
+        pretty: bool,
+
+        /// Print pipeline stages to stderr for debugging
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Run an HTTP service exposing /validate, /resolve and /compose
+    Serve {
+        /// Local directory containing schema files (schema= paths are relative to it)
+        #[arg(long)]
+        schema_local_base: Option<PathBuf>,
+
+        /// URL prefix to strip when mapping to local (e.g., https://ucp.dev/draft)
+        #[arg(long, requires = "schema_local_base")]
+        schema_remote_base: Option<String>,
+
+        /// Map a URL prefix to a local subtree, PREFIX=DIR (repeatable; tried in order)
+        #[arg(
+            long = "map",
+            value_name = "PREFIX=DIR",
+            value_parser = parse_url_mapping,
+            conflicts_with = "schema_remote_base"
+        )]
+        map: Vec<UrlMapping>,
+
+        /// Let clients make the server fetch URLs (schema=, payload schemas and profiles)
+        #[arg(long)]
+        allow_remote_schemas: bool,
+
+        /// Schemas, and separately compiled validators, kept in memory before
+        /// the least recently used are evicted
+        #[arg(long, default_value_t = ucp_schema::DEFAULT_CACHE_SIZE)]
+        cache_size: usize,
+
+        /// Connections served at once; further ones wait to be accepted
+        #[arg(long, default_value_t = ucp_schema::DEFAULT_WORKERS)]
+        workers: usize,
+
+        /// Seconds a read or write on a connection may block before it's closed
+        /// (also ends idle keep-alive connections)
+        #[arg(long, value_name = "SECS", default_value_t = ucp_schema::DEFAULT_TIMEOUT.as_secs())]
+        timeout: u64,
+
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        /// Port to listen on (0 picks a free port)
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+
+        /// Log each request to stderr
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Re-run resolve, lint or validate whenever files under a path change
+    Watch {
+        /// File or directory to watch (hidden entries are ignored)
+        path: PathBuf,
+
+        /// Quiet period after the last change before re-running, in milliseconds
+        #[arg(long, default_value_t = 300)]
+        debounce: u64,
+
+        /// How often to check for changes, in milliseconds
+        #[arg(long, default_value_t = 500)]
+        interval: u64,
+
+        /// Command to run, after -- (e.g., -- lint schemas/)
+        #[arg(last = true, required = true, value_name = "COMMAND")]
+        command: Vec<String>,
+    },
+
+    /// Explain each validation failure: schema fragment, allOf branch and UCP annotation
+    Explain {
+        /// Payload file (- for stdin)
+        payload: PathBuf,
+
+        /// Schema source: file path, URL (http:// or https://), or - for stdin
+        #[arg(long)]
+        schema: String,
+
+        /// Validate as a request
+        #[arg(
+            long,
+            conflicts_with = "response",
+            required_unless_present = "response"
+        )]
+        request: bool,
+
+        /// Validate as a response
+        #[arg(long, conflicts_with = "request")]
+        response: bool,
+
+        /// Operation (create, read, update, complete, ...)
+        #[arg(long, short)]
+        op: String,
+
+        /// Dereference all $ref pointers before resolving
+        #[arg(long)]
+        bundle: bool,
+
+        /// Output format: text (default) or json
+        #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+        format: String,
+
+        /// Print pipeline stages to stderr for debugging
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Remove the fields a direction and operation's schema doesn't declare
+    /// from a payload
+    Scrub {
+        /// Payload file (- for stdin)
+        payload: PathBuf,
+
+        /// Schema source: file path, URL (http:// or https://), or - for stdin
+        #[arg(long)]
+        schema: String,
+
+        /// Scrub for the request schema
+        #[arg(
+            long,
+            conflicts_with = "response",
+            required_unless_present = "response"
+        )]
+        request: bool,
+
+        /// Scrub for the response schema
+        #[arg(long, conflicts_with = "request")]
+        response: bool,
+
+        /// Operation (create, read, update, complete, ...)
+        #[arg(long, short)]
+        op: String,
+
+        /// Dereference all $ref pointers before resolving
+        #[arg(long)]
+        bundle: bool,
+
+        /// Output file (stdout if not specified)
+        #[arg(long)]
+        output: Option<PathBuf>,
+
+        /// Pretty-print JSON output
+        #[arg(long)]
+        pretty: bool,
+
+        /// Print pipeline stages and removed fields to stderr
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Print one fragment of a composed, bundled or resolved schema
+    Query {
+        /// Schema or payload source: file path, URL (http:// or https://), or - for stdin
+        schema: String,
+
+        /// JSON Pointer to print (e.g., /properties/line_items; empty for the whole document)
+        #[arg(long)]
+        path: String,
+
+        /// Resolve for this operation before querying (otherwise query the schema as composed)
+        #[arg(long, short)]
+        op: Option<String>,
+
+        /// Resolve for request direction (auto-inferred for payloads)
+        #[arg(long, conflicts_with = "response", requires = "op")]
+        request: bool,
+
+        /// Resolve for response direction (auto-inferred for payloads)
+        #[arg(long, conflicts_with = "request", requires = "op")]
+        response: bool,
+
+        /// Dereference all $ref pointers before querying
+        #[arg(long)]
+        bundle: bool,
+
+        /// Local directory containing schema files (used when input is a payload)
+        #[arg(long)]
+        schema_local_base: Option<PathBuf>,
+
+        /// URL prefix to strip when mapping to local (e.g., https://ucp.dev/draft)
+        #[arg(long, requires = "schema_local_base")]
+        schema_remote_base: Option<String>,
+
+        /// Map a URL prefix to a local subtree, PREFIX=DIR (repeatable; tried in order)
+        #[arg(
+            long = "map",
+            value_name = "PREFIX=DIR",
+            value_parser = parse_url_mapping,
+            conflicts_with = "schema_remote_base"
+        )]
+        map: Vec<UrlMapping>,
+
+        /// Print a string result without JSON quotes
+        #[arg(long)]
+        raw: bool,
+
+        /// Pretty-print JSON output
+        #[arg(long)]
+        pretty: bool,
+
+        /// Print pipeline stages to stderr for debugging
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Browse a schema interactively: list properties with their visibility,
+    /// step into them and follow $refs, switching operation and direction as
+    /// you go. Reads one command per line from stdin (`help` lists them)
+    Explore {
+        /// Schema or payload source: file path or URL (http:// or https://)
+        schema: String,
+
+        /// Start with request visibility (the default)
+        #[arg(long, conflicts_with = "response")]
+        request: bool,
+
+        /// Start with response visibility (inferred for payloads)
+        #[arg(long, conflicts_with = "request")]
+        response: bool,
+
+        /// Operation to start with
+        #[arg(long, short, default_value = "create")]
+        op: String,
+
+        /// Dereference all $ref pointers first, so external refs can be browsed
+        #[arg(long)]
+        bundle: bool,
+
+        /// Local directory containing schema files (used when input is a payload)
+        #[arg(long)]
+        schema_local_base: Option<PathBuf>,
+
+        /// URL prefix to strip when mapping to local (e.g., https://ucp.dev/draft)
+        #[arg(long, requires = "schema_local_base")]
+        schema_remote_base: Option<String>,
+
+        /// Map a URL prefix to a local subtree, PREFIX=DIR (repeatable; tried in order)
+        #[arg(
+            long = "map",
+            value_name = "PREFIX=DIR",
+            value_parser = parse_url_mapping,
+            conflicts_with = "schema_remote_base"
+        )]
+        map: Vec<UrlMapping>,
+    },
+
+    /// Mask or remove the values of ucp_sensitive fields in a payload, e.g.
+    /// before logging it
+    Redact {
+        /// Payload file (- for stdin)
+        payload: PathBuf,
+
+        /// Schema source: file path, URL (http:// or https://), or - for stdin
+        #[arg(long)]
+        schema: String,
+
+        /// Replacement for sensitive values
+        #[arg(long, default_value = REDACTED)]
+        mask: String,
+
+        /// Drop sensitive fields instead of masking them
+        #[arg(long, conflicts_with = "mask")]
+        remove: bool,
+
+        /// Dereference all $ref pointers first, so sensitive fields in
+        /// referenced files are found
+        #[arg(long)]
+        bundle: bool,
+
+        /// Output file (stdout if not specified)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+
+        /// Pretty-print JSON output
+        #[arg(long)]
+        pretty: bool,
+
+        /// Print pipeline stages and redacted fields to stderr
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Report which properties, enum values and oneOf branches payloads exercise
+    Coverage {
+        /// Schema source: file path, URL (http:// or https://), or - for stdin
+        #[arg(long)]
+        schema: String,
+
+        /// Payload files
+        #[arg(required = true)]
+        payloads: Vec<PathBuf>,
+
+        /// Measure against the request schema
+        #[arg(
+            long,
+            conflicts_with = "response",
+            required_unless_present = "response"
+        )]
+        request: bool,
+
+        /// Measure against the response schema
+        #[arg(long, conflicts_with = "request")]
+        response: bool,
+
+        /// Operation (create, read, update, complete, ...)
+        #[arg(long, short)]
+        op: String,
+
+        /// Dereference all $ref pointers before resolving
+        #[arg(long)]
+        bundle: bool,
+
+        /// Output format: text (default) or json
+        #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+        format: String,
+
+        /// Print pipeline stages to stderr for debugging
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Print each property's visibility per direction and operation
+    Inspect {
+        /// Schema source: file path, URL (http:// or https://), or - for stdin
+        schema: String,
+
+        /// Operation column to show (repeatable; default: every operation the schema uses)
+        #[arg(long, short)]
+        op: Vec<String>,
+
+        /// Dereference all $ref pointers before inspecting
+        #[arg(long)]
+        bundle: bool,
+
+        /// Output format: text (default) or json
+        #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+        format: String,
+
+        /// Print pipeline stages to stderr for debugging
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Mutate valid examples into near-miss invalid payloads
+    Fuzz {
+        /// Schema source: file path, URL (http:// or https://), or - for stdin
+        schema: String,
+
+        /// Valid example payload to mutate (repeatable; the schema's root `examples` are also used)
+        #[arg(long = "example", value_name = "PATH")]
+        examples: Vec<PathBuf>,
+
+        /// Fuzz the request schema
+        #[arg(
+            long,
+            conflicts_with = "response",
+            required_unless_present = "response"
+        )]
+        request: bool,
+
+        /// Fuzz the response schema
+        #[arg(long, conflicts_with = "request")]
+        response: bool,
+
+        /// Operation (create, read, update, complete, ...)
+        #[arg(long, short)]
+        op: String,
+
+        /// Maximum number of mutations
+        #[arg(long, default_value_t = 100)]
+        count: usize,
+
+        /// Report which mutations the schema rejects; exit 1 if any is accepted
+        #[arg(long)]
+        validate: bool,
+
+        /// Dereference all $ref pointers before resolving
+        #[arg(long)]
+        bundle: bool,
+
+        /// Output format for --validate: text (default) or json
+        #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+        format: String,
+
+        /// Output file for the mutated payloads (default: stdout)
+        #[arg(long, conflicts_with = "validate")]
+        output: Option<PathBuf>,
+
+        /// Print pipeline stages to stderr for debugging
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Run the example payloads declared in x-ucp-tests blocks and *.tests.json files
+    Test {
+        /// Schema file or directory
+        path: PathBuf,
+
+        /// Output format: text (default), json, or junit (JUnit XML)
+        #[arg(long, default_value = "text", value_parser = ["text", "json", "junit"])]
+        format: String,
+
+        /// Only show failures
+        #[arg(long, short)]
+        quiet: bool,
+    },
+
+    /// Graph schema dependencies ($ref and extends) as DOT or Mermaid
+    Graph {
+        /// Schema file or directory
+        path: PathBuf,
+
+        /// Output format
+        #[arg(long, default_value = "dot", value_parser = ["dot", "mermaid"])]
+        format: String,
+
+        /// Output file (default: stdout)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// Complete schema transitions: rewrite each to its `to` visibility
+    Migrate {
+        /// Schema file with transition annotations
+        schema: PathBuf,
+
+        /// Write the migrated schema (default: show the changes only)
+        #[arg(long)]
+        apply: bool,
+
+        /// With --apply, write here instead of over the input file
+        #[arg(long, short, requires = "apply")]
+        output: Option<PathBuf>,
+
+        /// Print each transition's description
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Rewrite older annotation syntax (shorthand strings and transitions,
+    /// mixed-case operations) into the current per-operation form
+    Upgrade {
+        /// Schema file or directory
+        path: PathBuf,
+
+        /// Report what would change without writing
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Show each annotation before and after
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Scaffold a new capability project: schema, types, profile and example payloads
+    Init {
+        /// Capability name (letters, digits, - and _), e.g. gift-card
+        name: String,
+
+        /// Directory to create (default: ./<name>)
+        #[arg(long)]
+        dir: Option<PathBuf>,
+
+        /// Reverse-domain prefix for the capability name
+        #[arg(long, default_value = "com.example")]
+        namespace: String,
+    },
+
+    /// Generate documentation for every capability in a payload or profile
+    Docs {
+        /// Payload or profile file with UCP capabilities metadata (- for stdin)
+        payload: PathBuf,
+
+        /// Local directory containing schema files
+        #[arg(long)]
+        schema_local_base: Option<PathBuf>,
+
+        /// URL prefix to strip when mapping to local (e.g., https://ucp.dev/draft)
+        #[arg(long, requires = "schema_local_base")]
+        schema_remote_base: Option<String>,
+
+        /// Map a URL prefix to a local subtree, PREFIX=DIR (repeatable; tried in order)
+        #[arg(
+            long = "map",
+            value_name = "PREFIX=DIR",
+            value_parser = parse_url_mapping,
+            conflicts_with = "schema_remote_base"
+        )]
+        map: Vec<UrlMapping>,
+
+        /// Output format: html (one page per capability, linked along extends)
+        #[arg(long, default_value = "html", value_parser = ["html"])]
+        format: String,
+
+        /// Directory to write the site into (created if missing)
+        #[arg(long)]
+        output_dir: PathBuf,
+
+        /// Print pipeline stages to stderr for debugging
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Compare two schemas resolved for one direction and operation, flagging breaking changes
+    Diff {
+        /// Old schema source: file path, URL (http:// or https://), or - for stdin
+        old: String,
+
+        /// New schema source: file path, URL (http:// or https://), or - for stdin
+        new: String,
+
+        /// Compare request schemas
+        #[arg(
+            long,
+            conflicts_with = "response",
+            required_unless_present = "response"
+        )]
+        request: bool,
+
+        /// Compare response schemas
+        #[arg(long, conflicts_with = "request")]
+        response: bool,
+
+        /// Operation to compare (create, read, update, complete, ...)
+        #[arg(long, short)]
+        op: String,
+
+        /// Dereference all $ref pointers in both schemas before resolving
+        #[arg(long)]
+        bundle: bool,
+
+        /// Strict mode: set additionalProperties=false to reject unknown fields (default: false)
+        #[arg(long, default_value_t = false, action = clap::ArgAction::Set)]
+        strict: bool,
+
+        /// Output format: text (default) or json
+        #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+        format: String,
+
+        /// Print pipeline stages to stderr for debugging
+        #[arg(long, short)]
+        verbose: bool,
+    },
+
+    /// Report complexity metrics of schema files (for tracking growth across releases)
+    Stats {
+        /// File or directory to measure
+        path: PathBuf,
+
+        /// Output format: text (default) or json
+        #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+        format: String,
+    },
+
+    /// Rewrite schema files in the standard layout
+    Fmt {
+        /// File or directory to format
+        path: PathBuf,
+
+        /// Don't write; exit 1 if any file isn't formatted (for CI)
+        #[arg(long)]
+        check: bool,
+
+        /// Only print files that changed or failed
+        #[arg(long, short)]
+        quiet: bool,
+    },
+
+    /// Lint schema files for errors (syntax, broken refs, invalid annotations,
+    /// meta-schema conformance)
+    Lint {
+        /// File or directory to lint
+        path: PathBuf,
+
+        /// Output format: text (default) or json
+        #[arg(long, default_value = "text")]
+        format: String,
+
+        /// Treat warnings as errors
+        #[arg(long)]
+        strict: bool,
+
+        /// Suppress progress output, only show errors
+        #[arg(long, short)]
+        quiet: bool,
+
+        /// Warn (UCP240) about schemas in --schema-local-base unreachable from any capability
+        #[arg(long, requires = "schema_local_base")]
+        detect_orphans: bool,
+
+        /// Schema base directory for --detect-orphans
+        #[arg(long)]
+        schema_local_base: Option<PathBuf>,
+    },
+
+    /// Print the JSON Schema describing valid UCP annotations
+    MetaSchema {
+        /// Output file (default: stdout)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+
+        /// Pretty-print JSON output
+        #[arg(long)]
+        pretty: bool,
+    },
+
+    /// Print a shell completion script (completes --op from the schema on the command line)
+    Completions {
+        /// Shell to generate for: bash, zsh, fish or powershell
+        shell: Shell,
+    },
+
+    /// List the operations of the schema named in a partial command line
+    #[command(name = "__complete-ops", hide = true)]
+    CompleteOps {
+        /// Words of the command line after the program name
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        words: Vec<String>,
+    },
+
+    /// Print version and build metadata
+    Version {
+        /// Output {name, version, git_sha, built} as JSON (for automation)
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// List cached documents with their URL, hash, age and size
+    Ls {
+        /// Output format: text (default) or json
+        #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+        format: String,
+    },
+
+    /// Remove every cached document
+    Clear,
+
+    /// Remove cached documents older than a maximum age
+    Prune {
+        /// Maximum age: a number with s, m, h or d (e.g., 7d, 12h)
+        #[arg(long, value_parser = parse_max_age)]
+        max_age: Duration,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfileCommand {
+    /// Build a profile listing the capability schemas in a directory
+    Generate {
+        /// Schema directory to scan
+        dir: PathBuf,
+
+        /// URL the directory is published under; schema URLs are this plus the file path
+        #[arg(long)]
+        base_url: String,
+
+        /// ucp.version of the profile (default: the newest capability version)
+        #[arg(long)]
+        ucp_version: Option<String>,
+
+        /// Output file (default: stdout)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+
+        /// Pretty-print JSON output
+        #[arg(long)]
+        pretty: bool,
+    },
+
+    /// Check a profile: entries, versions, extends targets, and that its schemas compose
+    Check {
+        /// Profile document: file path, URL, or - for stdin
+        profile: String,
+
+        /// Local directory containing schema files
+        #[arg(long)]
+        schema_local_base: Option<PathBuf>,
+
+        /// URL prefix to strip when mapping to local (e.g., https://ucp.dev/draft)
+        #[arg(long, requires = "schema_local_base")]
+        schema_remote_base: Option<String>,
+
+        /// Map a URL prefix to a local subtree, PREFIX=DIR (repeatable; tried in order)
+        #[arg(
+            long = "map",
+            value_name = "PREFIX=DIR",
+            value_parser = parse_url_mapping,
+            conflicts_with = "schema_remote_base"
+        )]
+        map: Vec<UrlMapping>,
+
+        /// Output format: text (default) or json
+        #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+        format: String,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let no_network = cli.no_network
+        || std::env::var(NO_NETWORK_ENV).is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+    let mut fetch = FetchOptions::new().network_disabled(no_network);
+    let cache_dir = cli.cache_dir.clone().or_else(|| {
+        std::env::var_os(CACHE_DIR_ENV)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+    });
+    if let Some(dir) = cache_dir {
+        fetch = fetch.cache_dir(dir);
+    }
+    // `lock` re-pins what changed, so it doesn't verify against the old lockfile
+    if let Some(path) = cli.lockfile.as_deref() {
+        if !matches!(cli.command, Commands::Lock { .. }) {
+            match Lockfile::load(path) {
+                Ok(lockfile) => fetch = fetch.lockfile(lockfile),
+                Err(e) => return ExitCode::from(cli_err_ctx(false, "loading lockfile")(e)),
+            }
+        }
+    }
+    let _ = FETCH.set(fetch);
+
+    let result = match cli.command {
+        Commands::Resolve {
+            schema,
+            request,
+            response,
+            both,
+            op,
+            ops,
+            all_ops,
+            output,
+            output_dir,
+            name_template,
+            summary_json,
+            report,
+            pretty,
+            bundle,
+            emit_source_map,
+            redact,
+            keep_annotation,
+            preserve_annotations,
+            omit_sensitive,
+            propagate_variants,
+            denullify,
+            print_deps,
+            deps_make,
+            schema_local_base,
+            schema_remote_base,
+            map,
+            warn_on_unused_schema_base,
+            allow_orphan_extensions,
+            strict_version,
+            check_monotonicity_all_ops,
+            dedupe_allof,
+            strict,
+            strict_only,
+            verbose,
+        } => run_resolve(ResolveArgs {
+            schema,
+            request,
+            response,
+            both,
+            ops: op.into_iter().chain(ops).collect(),
+            all_ops,
+            output,
+            output_dir,
+            name_template,
+            summary_json,
+            report,
+            pretty,
+            bundle,
+            emit_source_map,
+            redact,
+            keep_annotation,
+            preserve_annotations,
+            omit_sensitive,
+            propagate_variants,
+            denullify,
+            print_deps,
+            deps_make,
+            schema_local_base,
+            schema_remote_base,
+            map,
+            warn_on_unused_schema_base,
+            allow_orphan_extensions,
+            strict_version,
+            check_monotonicity_all_ops,
+            dedupe_allof,
+            strict,
+            strict_only,
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Compose {
+            payload,
+            schema_local_base,
+            schema_remote_base,
+            map,
+            warn_on_unused_schema_base,
+            allow_orphan_extensions,
+            min_version,
+            strict_version,
+            dedupe_allof,
+            root_only,
+            capability_filter,
+            resolve,
+            split_directions,
+            op,
+            request,
+            response,
+            output,
+            pretty,
+            verbose,
+        } => run_compose(ComposeArgs {
+            payload,
+            schema_local_base,
+            schema_remote_base,
+            map,
+            warn_on_unused_schema_base,
+            allow_orphan_extensions,
+            min_version,
+            strict_version,
+            dedupe_allof,
+            root_only,
+            capability_filter,
+            resolve,
+            split_directions,
+            op,
+            request,
+            response,
+            output,
+            pretty,
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Validate {
+            payloads,
+            schema,
+            schema_local_base,
+            schema_remote_base,
+            map,
+            warn_on_unused_schema_base,
+            strict_version,
+            capability_filter,
+            unwrap_pointer,
+            profile,
+            request,
+            response,
+            op,
+            op_any,
+            json,
+            format,
+            error_format,
+            strict,
+            show_strict_delta,
+            ndjson,
+            threads,
+            annotate_payload,
+            deref_payload,
+            reject_readonly,
+            propagate_variants,
+            verbose,
+        } => run_validate(ValidateArgs {
+            payloads,
+            schema,
+            schema_local_base,
+            schema_remote_base,
+            map,
+            warn_on_unused_schema_base,
+            strict_version,
+            capability_filter,
+            unwrap_pointer,
+            profile,
+            request,
+            response,
+            op,
+            op_any,
+            json_output: json || format == "json",
+            junit: format == "junit",
+            problem_json: format == "problem-json",
+            error_format,
+            strict,
+            show_strict_delta,
+            ndjson,
+            threads,
+            annotate_payload,
+            deref_payload,
+            reject_readonly,
+            propagate_variants,
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Bundle {
+            schema,
+            schema_local_base,
+            schema_remote_base,
+            output,
+            pretty,
+            emit_source_map,
+            bundle_mode,
+            verbose,
+        } => run_bundle(BundleArgs {
+            schema,
+            schema_local_base,
+            schema_remote_base,
+            output,
+            pretty,
+            emit_source_map,
+            defs: bundle_mode == "defs",
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Merge {
+            schemas,
+            extends,
+            dedupe_allof,
+            output,
+            pretty,
+            verbose,
+        } => run_merge(MergeArgs {
+            schemas,
+            extends,
+            dedupe_allof,
+            output,
+            pretty,
+            verbose,
+        }),
+
+        Commands::Lock {
+            sources,
+            output,
+            verbose,
+        } => run_lock(&sources, &output, verbose, cli.input_format),
+
+        Commands::Vendor {
+            payload,
+            out,
+            profile,
+            verbose,
+        } => run_vendor(
+            &payload,
+            &out,
+            profile.as_deref(),
+            verbose,
+            cli.input_format,
+        ),
+
+        #[cfg(feature = "remote")]
+        Commands::Probe {
+            url,
+            profile,
+            op,
+            templates,
+            method,
+            body,
+            headers,
+            schema_local_base,
+            schema_remote_base,
+            map,
+            format,
+            verbose,
+        } => run_probe(ProbeArgs {
+            url,
+            profile,
+            op,
+            templates,
+            method,
+            body,
+            headers,
+            schema_local_base,
+            schema_remote_base,
+            map,
+            json_output: format == "json",
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Replay {
+            har,
+            rules,
+            profile,
+            schema_local_base,
+            schema_remote_base,
+            map,
+            format,
+            verbose,
+        } => run_replay(ReplayArgs {
+            har,
+            rules,
+            profile,
+            schema_local_base,
+            schema_remote_base,
+            map,
+            json_output: format == "json",
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Normalize {
+            schema,
+            output,
+            pretty,
+        } => run_normalize(&schema, output, pretty, cli.input_format),
+
+        Commands::Library {
+            schema,
+            ops,
+            name,
+            bundle,
+            dedupe,
+            strict,
+            output,
+            summary_json,
+            pretty,
+            verbose,
+        } => run_library(LibraryArgs {
+            schema,
+            ops,
+            name,
+            bundle,
+            dedupe,
+            strict,
+            output,
+            summary_json,
+            pretty,
+            verbose,
+        }),
+
+        Commands::Codegen {
+            target,
+            schema,
+            ops,
+            name,
+            bundle,
+            strict,
+            output,
+            verbose,
+        } => run_codegen(CodegenArgs {
+            target,
+            schema,
+            ops,
+            name,
+            bundle,
+            strict,
+            output,
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Convert {
+            schemas,
+            to: _,
+            from,
+            resource,
+            ops,
+            title,
+            api_version,
+            bundle,
+            strict,
+            output,
+            pretty,
+            verbose,
+        } => run_convert(ConvertArgs {
+            schemas,
+            from,
+            resource,
+            ops,
+            title,
+            api_version,
+            bundle,
+            strict,
+            output,
+            pretty,
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Serve {
+            schema_local_base,
+            schema_remote_base,
+            map,
+            allow_remote_schemas,
+            cache_size,
+            workers,
+            timeout,
+            host,
+            port,
+            verbose,
+        } => run_serve(ServeArgs {
+            schema_local_base,
+            schema_remote_base,
+            map,
+            allow_remote_schemas,
+            cache_size,
+            workers,
+            timeout: Duration::from_secs(timeout),
+            host,
+            port,
+            verbose,
+        }),
+
+        Commands::Watch {
+            path,
+            debounce,
+            interval,
+            command,
+        } => run_watch(WatchArgs {
+            path,
+            debounce: Duration::from_millis(debounce),
+            interval: Duration::from_millis(interval),
+            command,
+            no_network: cli.no_network,
+        }),
+
+        Commands::Explain {
+            payload,
+            schema,
+            request,
+            response,
+            op,
+            bundle,
+            format,
+            verbose,
+        } => run_explain(ExplainArgs {
+            payload,
+            schema,
+            direction: Direction::from_request_flag(request && !response),
+            op,
+            bundle,
+            json_output: format == "json",
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Query {
+            schema,
+            path,
+            op,
+            request,
+            response,
+            bundle,
+            schema_local_base,
+            schema_remote_base,
+            map,
+            raw,
+            pretty,
+            verbose,
+        } => run_query(QueryArgs {
+            schema,
+            path,
+            op,
+            request,
+            response,
+            bundle,
+            schema_local_base,
+            schema_remote_base,
+            map,
+            raw,
+            pretty,
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Explore {
+            schema,
+            request,
+            response,
+            op,
+            bundle,
+            schema_local_base,
+            schema_remote_base,
+            map,
+        } => run_explore(ExploreArgs {
+            schema,
+            request,
+            response,
+            op,
+            bundle,
+            schema_local_base,
+            schema_remote_base,
+            map,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Scrub {
+            payload,
+            schema,
+            request,
+            response,
+            op,
+            bundle,
+            output,
+            pretty,
+            verbose,
+        } => run_scrub(ScrubArgs {
+            payload,
+            schema,
+            direction: Direction::from_request_flag(request && !response),
+            op,
+            bundle,
+            output,
+            pretty,
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Redact {
+            payload,
+            schema,
+            mask,
+            remove,
+            bundle,
+            output,
+            pretty,
+            verbose,
+        } => run_redact(RedactArgs {
+            payload,
+            schema,
+            mode: if remove {
+                RedactMode::Remove
+            } else {
+                RedactMode::Mask(mask)
+            },
+            bundle,
+            output,
+            pretty,
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Coverage {
+            schema,
+            payloads,
+            request,
+            response,
+            op,
+            bundle,
+            format,
+            verbose,
+        } => run_coverage(CoverageArgs {
+            schema,
+            payloads,
+            direction: Direction::from_request_flag(request && !response),
+            op,
+            bundle,
+            json_output: format == "json",
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Inspect {
+            schema,
+            op,
+            bundle,
+            format,
+            verbose,
+        } => run_inspect(InspectArgs {
+            schema,
+            ops: op,
+            bundle,
+            json_output: format == "json",
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Fuzz {
+            schema,
+            examples,
+            request,
+            response,
+            op,
+            count,
+            validate,
+            bundle,
+            format,
+            output,
+            verbose,
+        } => run_fuzz(FuzzArgs {
+            schema,
+            examples,
+            direction: Direction::from_request_flag(request && !response),
+            op,
+            count,
+            validate,
+            bundle,
+            json_output: format == "json",
+            output,
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Test {
+            path,
+            format,
+            quiet,
+        } => run_test(&path, &format, quiet),
+
+        Commands::Graph {
+            path,
+            format,
+            output,
+        } => run_graph(&path, &format, output),
+
+        Commands::Migrate {
+            schema,
+            apply,
+            output,
+            verbose,
+        } => run_migrate(MigrateArgs {
+            schema,
+            apply,
+            output,
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Upgrade {
+            path,
+            dry_run,
+            verbose,
+        } => run_upgrade(&path, dry_run, verbose),
+
+        Commands::Init {
+            name,
+            dir,
+            namespace,
+        } => run_init(&name, dir, &namespace),
+
+        #[cfg(feature = "remote")]
+        Commands::Push {
+            dir,
+            registry,
+            token,
+            dry_run,
+            format,
+        } => run_push(&dir, &registry, token, dry_run, format == "json"),
+
+        #[cfg(feature = "remote")]
+        Commands::Pull {
+            capabilities,
+            registry,
+            token,
+            out,
+            sha256,
+        } => run_pull(&capabilities, &registry, token, &out, sha256.as_deref()),
+
+        Commands::Cache { command } => run_cache(command),
+
+        Commands::Extract {
+            payload,
+            profile,
+            schema_local_base,
+            schema_remote_base,
+            map,
+            with_key,
+            output,
+            pretty,
+            verbose,
+        } => run_extract(ExtractArgs {
+            payload,
+            profile,
+            schema_local_base,
+            schema_remote_base,
+            map,
+            with_key,
+            output,
+            pretty,
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Profile {
+            command:
+                ProfileCommand::Generate {
+                    dir,
+                    base_url,
+                    ucp_version,
+                    output,
+                    pretty,
+                },
+        } => run_profile_generate(&dir, &base_url, ucp_version.as_deref(), output, pretty),
+
+        Commands::Profile {
+            command:
+                ProfileCommand::Check {
+                    profile,
+                    schema_local_base,
+                    schema_remote_base,
+                    map,
+                    format,
+                },
+        } => {
+            let config = SchemaBaseConfig {
+                local_base: schema_local_base.as_deref(),
+                remote_base: schema_remote_base.as_deref(),
+                mappings: &map,
+                fetch: fetch_options(),
+                confine: false,
+            };
+            run_profile_check(&profile, &config, &format, cli.input_format)
+        }
+
+        Commands::Envelope {
+            payload,
+            profile,
+            op,
+            capability,
+            id,
+            schema_local_base,
+            schema_remote_base,
+            map,
+            output,
+            pretty,
+            verbose,
+        } => run_envelope(EnvelopeArgs {
+            payload,
+            profile,
+            op,
+            capability,
+            id,
+            schema_local_base,
+            schema_remote_base,
+            map,
+            output,
+            pretty,
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Docs {
+            payload,
+            schema_local_base,
+            schema_remote_base,
+            map,
+            format: _,
+            output_dir,
+            verbose,
+        } => run_docs(DocsArgs {
+            payload,
+            schema_local_base,
+            schema_remote_base,
+            map,
+            output_dir,
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Diff {
+            old,
+            new,
+            request,
+            response,
+            op,
+            bundle,
+            strict,
+            format,
+            verbose,
+        } => run_diff(DiffArgs {
+            old,
+            new,
+            direction: Direction::from_request_flag(request && !response),
+            op,
+            bundle,
+            strict,
+            json_output: format == "json",
+            verbose,
+            input_format: cli.input_format,
+        }),
+
+        Commands::Stats { path, format } => run_stats(&path, format == "json"),
+
+        Commands::Fmt { path, check, quiet } => run_fmt(&path, check, quiet),
+
+        Commands::Lint {
+            path,
+            format,
+            strict,
+            quiet,
+            detect_orphans,
+            schema_local_base,
+        } => {
+            let orphans_base = schema_local_base.filter(|_| detect_orphans);
+            run_lint(&path, &format, strict, quiet, orphans_base.as_deref())
+        }
+
+        Commands::MetaSchema { output, pretty } => {
+            write_json_output(&meta_schema(), output, pretty)
+        }
+
+        Commands::Completions { shell } => run_completions(shell),
+
+        Commands::CompleteOps { words } => {
+            complete_ops(&words);
+            Ok(())
+        }
+
+        Commands::Version { json } => {
+            run_version(json);
+            Ok(())
+        }
+    };
+
+    let code = match result {
+        Ok(()) if cli.fail_on_warning && warning_count() > 0 => {
+            report_error(
+                false,
+                &format!("{} warning(s) emitted (--fail-on-warning)", warning_count()),
+            );
+            4
+        }
+        Ok(()) => 0,
+        Err(code) => code,
+    };
+    if code != 0 {
+        print_problem(code);
+    }
+    ExitCode::from(code)
+}
+
+/// How this run fetches remote documents, from the global flags and the
+/// environment; set once at the start of `main`.
+static FETCH: OnceLock<FetchOptions> = OnceLock::new();
+
+fn fetch_options() -> &'static FetchOptions {
+    FETCH.get_or_init(FetchOptions::new)
+}
+
+/// Warnings emitted so far by any command, for --fail-on-warning.
+static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+
+/// Emit a non-fatal warning to stderr and count it.
+fn warn(message: impl std::fmt::Display) {
+    WARNINGS.fetch_add(1, Ordering::Relaxed);
+    eprintln!("Warning: {}", message);
+}
+
+fn warning_count() -> usize {
+    WARNINGS.load(Ordering::Relaxed)
+}
+
+/// Set by `validate --format problem-json`: errors are then also recorded
+/// in PROBLEM, and printed as one problem object once the exit code is known.
+static PROBLEM_JSON: AtomicBool = AtomicBool::new(false);
+
+/// The first error reported, with its validation errors if it was a failed
+/// validation.
+static PROBLEM: Mutex<Option<(String, Vec<SchemaError>)>> = Mutex::new(None);
+
+/// Record an error for the problem object (first one wins).
+fn record_problem(detail: &str, errors: &[SchemaError]) {
+    if !PROBLEM_JSON.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut problem = PROBLEM.lock().unwrap();
+    if problem.is_none() {
+        *problem = Some((detail.to_string(), errors.to_vec()));
+    }
+}
+
+/// Print the recorded error as an RFC 9457 problem object for exit `code`.
+fn print_problem(code: u8) {
+    if !PROBLEM_JSON.load(Ordering::Relaxed) {
+        return;
+    }
+    let problem = match PROBLEM.lock().unwrap().take() {
+        Some((_, errors)) if code == 1 && !errors.is_empty() => {
+            ProblemDetails::validation_failed(&errors)
+        }
+        Some((detail, _)) => ProblemDetails::for_exit_code(code, detail),
+        None => ProblemDetails::for_exit_code(code, format!("exited with code {}", code)),
+    };
+    println!("{}", serde_json::to_string(&problem).unwrap());
+}
+
+struct ResolveArgs {
+    schema: String,
+    request: bool,
+    response: bool,
+    both: bool,
+    ops: Vec<String>,
+    all_ops: bool,
+    output: Option<PathBuf>,
+    output_dir: Option<PathBuf>,
+    name_template: Option<NameTemplate>,
+    summary_json: Option<PathBuf>,
+    report: Option<PathBuf>,
+    pretty: bool,
+    bundle: bool,
+    emit_source_map: Option<PathBuf>,
+    redact: Vec<String>,
+    keep_annotation: Vec<String>,
+    preserve_annotations: bool,
+    omit_sensitive: bool,
+    propagate_variants: bool,
+    denullify: Option<Denullify>,
+    print_deps: Option<PathBuf>,
+    deps_make: Option<PathBuf>,
+    schema_local_base: Option<PathBuf>,
+    schema_remote_base: Option<String>,
+    map: Vec<UrlMapping>,
+    warn_on_unused_schema_base: bool,
+    allow_orphan_extensions: bool,
+    strict_version: bool,
+    check_monotonicity_all_ops: bool,
+    dedupe_allof: bool,
+    strict: bool,
+    strict_only: Vec<String>,
+    verbose: bool,
+    input_format: InputFormat,
+}
+
+/// Resolve a schema for a specific direction and operation.
+///
+/// Auto-detects input type: if the input is a self-describing payload (has
+/// ucp.capabilities or meta.profile), composes schemas first then resolves.
+/// Otherwise resolves the schema directly.
+fn run_resolve(args: ResolveArgs) -> Result<(), u8> {
+    let ResolveArgs {
+        schema: schema_source,
+        request,
+        response,
+        both,
+        ops,
+        all_ops,
+        output,
+        output_dir,
+        name_template,
+        summary_json,
+        report,
+        pretty,
+        bundle,
+        emit_source_map,
+        redact,
+        keep_annotation,
+        preserve_annotations,
+        omit_sensitive,
+        propagate_variants,
+        denullify,
+        print_deps,
+        deps_make,
+        schema_local_base,
+        schema_remote_base,
+        map,
+        warn_on_unused_schema_base,
+        allow_orphan_extensions,
+        strict_version,
+        check_monotonicity_all_ops,
+        dedupe_allof,
+        strict,
+        strict_only,
+        verbose,
+        input_format,
+    } = args;
+    let schema_source = schema_source.as_str();
+
+    if verbose {
+        eprintln!("[load] reading {}", schema_source);
+    }
+    let mut input =
+        load_input(schema_source, input_format, fetch_options()).map_err(cli_err(false))?;
+
+    // Auto-detect: is this a payload (needs compose) or a schema (resolve directly)?
+    let detected = detect_direction(&input);
+
+    // Flag validation: reject flags that don't apply to the detected input type
+    if detected.is_some() {
+        if emit_source_map.is_some() {
+            report_error(false, "--emit-source-map only applies to schema input (composed regions come from several capability files). Remove it, or pass a schema file instead of a payload.");
+            return Err(2);
+        }
+    } else if schema_local_base.is_some() || schema_remote_base.is_some() || !map.is_empty() {
+        report_error(false, "--schema-local-base/--schema-remote-base/--map only apply to payload input. Remove these flags, or pass a self-describing payload instead of a schema file.");
+        return Err(2);
+    } else if allow_orphan_extensions {
+        report_error(false, "--allow-orphan-extensions only applies to payload input. Remove this flag, or pass a self-describing payload instead of a schema file.");
+        return Err(2);
+    } else if strict_version {
+        report_error(false, "--strict-version only applies to payload input (it checks composed capability schemas). Remove this flag, or pass a self-describing payload instead of a schema file.");
+        return Err(2);
+    } else if !strict_only.is_empty() {
+        report_error(false, "--strict-only only applies to payload input (it selects composed capabilities). Use --strict=true for schema files.");
+        return Err(2);
+    }
+
+    let mut source_map = None;
+    let mut origins = Vec::new();
+    // Files read while producing the output, for --print-deps/--deps-make
+    let mut deps = Vec::new();
+    if !is_url(schema_source) && schema_source != "-" {
+        deps.push(PathBuf::from(schema_source));
+    }
+    let schema = if detected.is_some() {
+        // Input is a self-describing payload — compose schemas from capabilities
+        let config = SchemaBaseConfig {
+            local_base: schema_local_base.as_deref(),
+            remote_base: schema_remote_base.as_deref(),
+            mappings: &map,
+            fetch: fetch_options(),
+            confine: false,
+        };
+        if verbose {
+            verbose_capabilities(&input, &config);
+            eprintln!("[compose] composing schemas from payload capabilities");
+        }
+        if warn_on_unused_schema_base {
+            warn_unmapped_payload_capabilities(&input, &config);
+        }
+        let options = ComposeOptions::new()
+            .allow_orphan_extensions(allow_orphan_extensions)
+            .strict_version(strict_version);
+        let mut composed =
+            compose_from_payload_with_options(&input, &config, &options).map_err(cli_err(false))?;
+        print_warnings(&composed.warnings);
+        if dedupe_allof {
+            if verbose {
+                eprintln!("[compose] removing duplicate and empty allOf branches");
+            }
+            composed.dedupe_allof();
+        }
+        origins = composed.origins;
+        deps.extend(composed.deps);
+        let mut schema = composed.schema;
+        if bundle {
+            // Capability files are bundled as they load; this pass inlines
+            // whatever composition left behind, against the same base
+            if verbose {
+                eprintln!("[bundle] inlining $ref pointers in the composed schema");
+            }
+            let base_dir = schema_local_base.as_deref().unwrap_or(Path::new("."));
+            match schema_remote_base.as_deref() {
+                Some(remote) => {
+                    bundle_refs_with_url_mapping(&mut schema, base_dir, base_dir, remote)
+                }
+                None => bundle_refs(&mut schema, base_dir),
+            }
+            .map_err(cli_err_ctx(false, "bundling refs"))?;
+        }
+        schema
+    } else {
+        if verbose {
+            eprintln!("[detect] input is a schema file (no ucp.capabilities)");
+        }
+        // Input is a schema file — bundle $refs if requested
+        if bundle {
+            if verbose {
+                eprintln!("[bundle] inlining $ref pointers");
+            }
+            if emit_source_map.is_some() || print_deps.is_some() || deps_make.is_some() {
+                let entries =
+                    bundle_refs_with_source_map(&mut input, Path::new(schema_source), None)
+                        .map_err(cli_err_ctx(false, "bundling refs"))?;
+                deps.extend(entries.iter().map(|e| PathBuf::from(&e.source)));
+                source_map = Some(entries);
+            } else {
+                let base_dir = Path::new(schema_source).parent().unwrap_or(Path::new("."));
+                bundle_refs(&mut input, base_dir).map_err(cli_err_ctx(false, "bundling refs"))?;
+            }
+        }
+        if dedupe_allof {
+            if verbose {
+                eprintln!("[dedupe] removing duplicate and empty allOf branches");
+            }
+            ucp_schema::dedupe_allof(&mut input);
+        }
+        input
+    };
+
+    warn_stray_annotations(&schema);
+
+    if check_monotonicity_all_ops {
+        check_monotonicity(&schema, verbose)?;
+        if ops.is_empty() && !all_ops {
+            return Ok(());
+        }
+    }
+    let ops = match all_ops {
+        true => annotated_operations(&schema),
+        false => ops,
+    };
+    if all_ops && verbose {
+        eprintln!("[resolve] operations: {}", ops.join(", "));
+    }
+
+    // Direction: explicit flag > auto-inferred from payload > require explicit
+    let directions = if both {
+        vec![Direction::Request, Direction::Response]
+    } else {
+        let direction = determine_direction(request, response, detected.map(Direction::from))
+            .ok_or_else(|| {
+                report_error(
+                    false,
+                    "--request or --response is required for schema input",
+                );
+                2u8
+            })?;
+        vec![direction]
+    };
+
+    // --strict-only narrows strict mode to matching capabilities, so resolve
+    // open and close the selected branches afterwards
+    let strict_all = strict && strict_only.is_empty();
+    // With --report, each resolution also reports its property decisions
+    let resolve_one = |op: &str,
+                       direction: Direction|
+     -> Result<(serde_json::Value, Option<ResolutionReport>), ResolveError> {
+        let options = ResolveOptions::new(direction, op)
+            .strict(strict_all)
+            .denullify(denullify)
+            .keep_annotations(keep_annotation.clone())
+            .preserve_annotations(preserve_annotations)
+            .omit_sensitive(omit_sensitive)
+            .propagate_variants(propagate_variants);
+        if verbose {
+            eprintln!(
+                "[resolve] resolving for {}/{}{}",
+                direction.as_str(),
+                options.operation,
+                if strict_all { " (strict)" } else { "" }
+            );
+        }
+        let (mut resolved, resolution) = match report {
+            Some(_) => resolve_with_report(&schema, &options).map(|(r, rep)| (r, Some(rep)))?,
+            None => (resolve(&schema, &options)?, None),
+        };
+        if !strict_only.is_empty() {
+            let branches: Vec<usize> = origins
+                .iter()
+                .enumerate()
+                .filter(|(_, name)| strict_only.iter().any(|p| capability_matches(p, name)))
+                .map(|(i, _)| i)
+                .collect();
+            if verbose {
+                let names: Vec<&str> = branches.iter().map(|&i| origins[i].as_str()).collect();
+                eprintln!("[resolve] strict for: {}", names.join(", "));
+            }
+            close_allof_branches(&mut resolved, &branches);
+        }
+        if !redact.is_empty() {
+            if verbose {
+                eprintln!("[redact] removing {}", redact.join(", "));
+            }
+            resolved = redact_keywords(&resolved, &redact);
+        }
+        Ok((resolved, resolution))
+    };
+
+    if let Some(dir) = output_dir {
+        let template = match name_template {
+            Some(template) => template,
+            None => NameTemplate::parse(NameTemplate::DEFAULT).expect("default template"),
+        };
+        let name = schema_stem(schema_source);
+        let mut files: Vec<(String, Direction, PathBuf)> = Vec::new();
+        for op in &ops {
+            for &direction in &directions {
+                let path = dir.join(template.render(&name, op, direction));
+                if files.iter().any(|(_, _, p)| *p == path) {
+                    report_error(
+                        false,
+                        &format!(
+                            "--name-template produces {} more than once; include {{op}} and {{direction}} to keep outputs apart",
+                            path.display()
+                        ),
+                    );
+                    return Err(2);
+                }
+                files.push((op.clone(), direction, path));
+            }
+        }
+
+        std::fs::create_dir_all(&dir).map_err(|e| {
+            eprintln!("Error creating {}: {}", dir.display(), e);
+            3u8
+        })?;
+        // With --summary-json, a failed job is recorded and the rest still
+        // run; the first failure's exit code is returned at the end
+        let batch_started = Instant::now();
+        let mut jobs = Vec::new();
+        let mut first_failure = None;
+        for (op, direction, path) in files {
+            let started = Instant::now();
+            let result = match resolve_one(&op, direction) {
+                Ok((resolved, _)) => {
+                    if verbose {
+                        eprintln!("[output] writing {}", path.display());
+                    }
+                    write_json_output(&resolved, Some(path.clone()), pretty)
+                        .map_err(|code| (code, format!("cannot write {}", path.display())))
+                }
+                Err(e) => {
+                    report_error(false, &e.to_string());
+                    Err((CliExitCode::exit_code(&e), e.to_string()))
+                }
+            };
+            if let Err((code, _)) = &result {
+                if summary_json.is_none() {
+                    return Err(*code);
+                }
+                first_failure.get_or_insert(*code);
+            }
+            let mut job = serde_json::json!({
+                "input": schema_source,
+                "op": op,
+                "direction": direction.as_str(),
+            });
+            summarize_job(&mut job, &[path], result, started);
+            jobs.push(job);
+        }
+        if let Some(summary_path) = summary_json {
+            write_summary(jobs, summary_path, batch_started)?;
+        }
+        if print_deps.is_some() {
+            write_deps(&unique_deps(deps), print_deps, None, None)?;
+        }
+        return first_failure.map_or(Ok(()), Err);
+    }
+
+    // --all-ops reports are keyed by operation, like the output
+    let (resolved, resolution) = if all_ops {
+        let mut by_op = serde_json::Map::new();
+        let mut reports = serde_json::Map::new();
+        for op in &ops {
+            let (resolved, resolution) = resolve_one(op, directions[0]).map_err(cli_err(false))?;
+            by_op.insert(op.clone(), resolved);
+            reports.insert(op.clone(), serde_json::json!(resolution));
+        }
+        (
+            serde_json::Value::Object(by_op),
+            serde_json::Value::Object(reports),
+        )
+    } else {
+        let (resolved, resolution) = resolve_one(&ops[0], directions[0]).map_err(cli_err(false))?;
+        (resolved, serde_json::json!(resolution))
+    };
+
+    if let Some(path) = report {
+        if verbose {
+            eprintln!("[report] writing {}", path.display());
+        }
+        write_json_output(&resolution, Some(path), true)?;
+    }
+
+    if let (Some(entries), Some(path)) = (source_map, emit_source_map) {
+        // Drop regions that resolution removed (e.g., omitted fields)
+        let entries: Vec<_> = entries
+            .into_iter()
+            .filter(|e| resolved.pointer(&e.pointer).is_some())
+            .collect();
+        if verbose {
+            eprintln!(
+                "[bundle] writing {} source map entries to {}",
+                entries.len(),
+                path.display()
+            );
+        }
+        write_json_output(&serde_json::json!(entries), Some(path), true)?;
+    }
+
+    if print_deps.is_some() || deps_make.is_some() {
+        let unique = unique_deps(deps);
+        if verbose {
+            eprintln!("[deps] {} files read", unique.len());
+        }
+        write_deps(&unique, print_deps, deps_make, output.as_deref())?;
+    }
+
+    write_json_output(&resolved, output, pretty)
+}
+
+/// Fill in a `--summary-json` job entry: its outputs (on success), status,
+/// failure reason and exit code, and how long it took.
+fn summarize_job(
+    job: &mut serde_json::Value,
+    outputs: &[PathBuf],
+    result: Result<(), (u8, String)>,
+    started: Instant,
+) {
+    let entry = job.as_object_mut().expect("job entry is an object");
+    match result {
+        Ok(()) => {
+            let outputs: Vec<_> = outputs.iter().map(|p| p.display().to_string()).collect();
+            entry.insert("outputs".into(), serde_json::json!(outputs));
+            entry.insert("status".into(), "ok".into());
+        }
+        Err((code, reason)) => {
+            entry.insert("outputs".into(), serde_json::json!([]));
+            entry.insert("status".into(), "failed".into());
+            entry.insert("error".into(), reason.into());
+            entry.insert("exit_code".into(), code.into());
+        }
+    }
+    entry.insert(
+        "duration_ms".into(),
+        (started.elapsed().as_millis() as u64).into(),
+    );
+}
+
+/// Write the `--summary-json` report: per-job entries plus batch totals.
+fn write_summary(jobs: Vec<serde_json::Value>, path: PathBuf, started: Instant) -> Result<(), u8> {
+    let failed = jobs.iter().filter(|j| j["status"] == "failed").count();
+    let summary = serde_json::json!({
+        "total": jobs.len(),
+        "ok": jobs.len() - failed,
+        "failed": failed,
+        "duration_ms": started.elapsed().as_millis() as u64,
+        "jobs": jobs,
+    });
+    write_json_output(&summary, Some(path), true)
+}
+
+/// `--check-monotonicity-all-ops`: run the monotonicity check for every
+/// annotated operation in both directions, reporting all violations.
+fn check_monotonicity(schema: &serde_json::Value, verbose: bool) -> Result<(), u8> {
+    let ops = annotated_operations(schema);
+    if verbose {
+        eprintln!("[check] monotonicity for {}", ops.join(", "));
+    }
+    let mut violations = 0;
+    for op in &ops {
+        for direction in [Direction::Request, Direction::Response] {
+            let options = ResolveOptions::new(direction, op);
+            for violation in monotonicity_violations(schema, &options).map_err(cli_err(false))? {
+                report_error(
+                    false,
+                    &format!("{}/{}: {}", direction.as_str(), op, violation),
+                );
+                violations += 1;
+            }
+        }
+    }
+    if violations > 0 {
+        eprintln!(
+            "{} monotonicity violation(s) across {} operation(s)",
+            violations,
+            ops.len()
+        );
+        return Err(2);
+    }
+    Ok(())
+}
+
+fn unique_deps(deps: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut unique: Vec<PathBuf> = Vec::new();
+    for dep in deps {
+        if !unique.contains(&dep) {
+            unique.push(dep);
+        }
+    }
+    unique
+}
+
+/// File stem of a schema path or URL, for `{name}` in `--name-template`
+/// (`stdin` for `-`).
+fn schema_stem(source: &str) -> String {
+    if source == "-" {
+        return "stdin".to_string();
+    }
+    let last = source
+        .split(['?', '#'])
+        .next()
+        .unwrap_or(source)
+        .rsplit('/')
+        .next()
+        .unwrap_or(source);
+    Path::new(last)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| last.to_string())
+}
+
+/// Write dependency lists for build systems: newline-separated to `list`,
+/// and/or a Makefile rule `target: dep1 dep2` to `make`.
+fn write_deps(
+    deps: &[PathBuf],
+    list: Option<PathBuf>,
+    make: Option<PathBuf>,
+    target: Option<&Path>,
+) -> Result<(), u8> {
+    let write = |path: &Path, contents: String| {
+        std::fs::write(path, contents).map_err(|e| {
+            eprintln!("Error writing to {}: {}", path.display(), e);
+            3u8
+        })
+    };
+
+    if let Some(path) = list {
+        let lines: String = deps.iter().map(|d| format!("{}\n", d.display())).collect();
+        write(&path, lines)?;
+    }
+    if let (Some(path), Some(target)) = (make, target) {
+        // Make splits prerequisites on whitespace; escape spaces in paths
+        let escape = |p: &Path| p.display().to_string().replace(' ', "\\ ");
+        let prereqs: Vec<String> = deps.iter().map(|d| escape(d)).collect();
+        write(
+            &path,
+            format!("{}: {}\n", escape(target), prereqs.join(" ")),
+        )?;
+    }
+    Ok(())
+}
+
+struct ComposeArgs {
+    payload: PathBuf,
+    schema_local_base: Option<PathBuf>,
+    schema_remote_base: Option<String>,
+    map: Vec<UrlMapping>,
+    warn_on_unused_schema_base: bool,
+    allow_orphan_extensions: bool,
+    min_version: Option<String>,
+    strict_version: bool,
+    dedupe_allof: bool,
+    root_only: bool,
+    capability_filter: Option<Regex>,
+    resolve: bool,
+    split_directions: bool,
+    op: Option<String>,
+    request: bool,
+    response: bool,
+    output: Option<PathBuf>,
+    pretty: bool,
+    verbose: bool,
+    input_format: InputFormat,
+}
+
+/// Composition: merge capability schemas from a self-describing payload.
+/// Output preserves UCP annotations unless `--resolve` runs the resolve step.
+fn run_compose(args: ComposeArgs) -> Result<(), u8> {
+    let ComposeArgs {
+        payload: payload_path,
+        schema_local_base,
+        schema_remote_base,
+        map,
+        warn_on_unused_schema_base,
+        allow_orphan_extensions,
+        min_version,
+        strict_version,
+        dedupe_allof,
+        root_only,
+        capability_filter,
+        resolve: resolve_output,
+        split_directions,
+        op,
+        request,
+        response,
+        output,
+        pretty,
+        verbose,
+        input_format,
+    } = args;
+    let payload_path = payload_path.as_path();
+
+    if verbose {
+        eprintln!("[load] reading {}", payload_path.display());
+    }
+    let payload = load_input(
+        &payload_path.to_string_lossy(),
+        input_format,
+        fetch_options(),
+    )
+    .map_err(cli_err_ctx(false, "loading payload"))?;
+
+    // Verify input is a self-describing payload
+    if detect_direction(&payload).is_none() {
+        report_error(false, "input is not a self-describing payload (missing ucp.capabilities or meta.profile). Use `resolve` for schema files.");
+        return Err(2);
+    }
+
+    let config = SchemaBaseConfig {
+        local_base: schema_local_base.as_deref(),
+        remote_base: schema_remote_base.as_deref(),
+        mappings: &map,
+        fetch: fetch_options(),
+        confine: false,
+    };
+    if verbose {
+        verbose_capabilities(&payload, &config);
+        eprintln!("[compose] composing schemas (annotations preserved)");
+    }
+    if warn_on_unused_schema_base {
+        warn_unmapped_payload_capabilities(&payload, &config);
+    }
+    let mut options = ComposeOptions::new()
+        .allow_orphan_extensions(allow_orphan_extensions)
+        .strict_version(strict_version)
+        .root_only(root_only);
+    if let Some(filter) = capability_filter {
+        options = options.capability_filter(filter);
+    }
+    if let Some(min_version) = min_version {
+        if verbose {
+            eprintln!("[compose] dropping capabilities older than {}", min_version);
+        }
+        options = options.min_version(min_version);
+    }
+    let mut composed =
+        compose_from_payload_with_options(&payload, &config, &options).map_err(cli_err(false))?;
+    print_warnings(&composed.warnings);
+    let reason = if root_only {
+        "--root-only"
+    } else {
+        "--capability-filter"
+    };
+    note_skipped(&composed.skipped, reason);
+    if dedupe_allof {
+        if verbose {
+            eprintln!("[compose] removing duplicate and empty allOf branches");
+        }
+        composed.dedupe_allof();
+    }
+    warn_stray_annotations(&composed.schema);
+
+    let Some(op) = op.filter(|_| resolve_output || split_directions) else {
+        return write_json_output(&composed.schema, output, pretty);
+    };
+    if split_directions {
+        let mut split = serde_json::Map::new();
+        for direction in [Direction::Request, Direction::Response] {
+            let options = ResolveOptions::new(direction, op.clone());
+            if verbose {
+                eprintln!(
+                    "[resolve] resolving for {}/{}",
+                    direction.as_str(),
+                    options.operation
+                );
+            }
+            let resolved = resolve(&composed.schema, &options).map_err(cli_err(false))?;
+            split.insert(direction.as_str().to_string(), resolved);
+        }
+        return write_json_output(&serde_json::Value::Object(split), output, pretty);
+    }
+    let detected = detect_direction(&payload).map(Direction::from);
+    let direction = determine_direction(request, response, detected).unwrap_or(Direction::Response);
+    let options = ResolveOptions::new(direction, op);
+    if verbose {
+        eprintln!(
+            "[resolve] resolving for {}/{}",
+            direction.as_str(),
+            options.operation
+        );
+    }
+    let resolved = resolve(&composed.schema, &options).map_err(cli_err(false))?;
+
+    write_json_output(&resolved, output, pretty)
+}
+
+fn parse_denullify(s: &str) -> Result<Denullify, String> {
+    Denullify::parse(s).ok_or_else(|| "expected openapi or drop-null".to_string())
+}
+
+fn parse_input_format(s: &str) -> Result<InputFormat, String> {
+    InputFormat::parse(s).ok_or_else(|| "expected json, yaml, or auto".to_string())
+}
+
+fn parse_codegen_target(s: &str) -> Result<CodegenTarget, String> {
+    CodegenTarget::parse(s).ok_or_else(|| "expected rust or go".to_string())
+}
+
+fn parse_capability_filter(s: &str) -> Result<Regex, String> {
+    Regex::new(s).map_err(|e| e.to_string())
+}
+
+fn parse_keep_annotation(s: &str) -> Result<String, String> {
+    if is_annotation(s) {
+        Ok(s.to_string())
+    } else {
+        Err(
+            "expected a UCP annotation (ucp_request, ucp_response, ucp_enum, ucp_description, ucp_property_names, ucp_sensitive)"
+                .to_string(),
+        )
+    }
+}
+
+fn parse_url_mapping(s: &str) -> Result<UrlMapping, String> {
+    UrlMapping::parse(s).ok_or_else(|| "expected PREFIX=DIR".to_string())
+}
+
+/// Clap value parser for `--header "NAME: VALUE"`.
+#[cfg(feature = "remote")]
+fn parse_header(s: &str) -> Result<(String, String), String> {
+    match s.split_once(':') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err("expected \"NAME: VALUE\"".to_string()),
+    }
+}
+
+/// Clap value parser for `--rule "[METHOD ]PATTERN=OP"`.
+fn parse_operation_rule(s: &str) -> Result<OperationRule, String> {
+    OperationRule::parse(s)
+}
+
+/// Clap value parser for `--max-age` durations (`30s`, `45m`, `12h`, `7d`).
+fn parse_max_age(s: &str) -> Result<Duration, String> {
+    let (number, unit) = s.split_at(s.len().saturating_sub(1));
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => return Err("expected a number with s, m, h or d (e.g., 7d)".to_string()),
+    };
+    number
+        .parse::<u64>()
+        .map(|n| Duration::from_secs(n * seconds))
+        .map_err(|_| "expected a number with s, m, h or d (e.g., 7d)".to_string())
+}
+
+/// Clap value parser for `NAME@VERSION`.
+#[cfg(feature = "remote")]
+fn parse_name_version(s: &str) -> Result<(String, String), String> {
+    match s.split_once('@') {
+        Some((name, version)) if !name.is_empty() && !version.is_empty() => {
+            Ok((name.to_string(), version.to_string()))
+        }
+        _ => Err("expected NAME@VERSION".to_string()),
+    }
+}
+
+/// Clap value parser for `--extends EXT=PARENT`.
+fn parse_extends(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((ext, parent)) if !ext.is_empty() && !parent.is_empty() => {
+            Ok((ext.to_string(), parent.to_string()))
+        }
+        _ => Err("expected EXT=PARENT".to_string()),
+    }
+}
+
+/// Clap value parser for ISO-date versions (YYYY-MM-DD).
+fn parse_iso_date(s: &str) -> Result<String, String> {
+    if is_iso_date(s) {
+        Ok(s.to_string())
+    } else {
+        Err("expected an ISO date (YYYY-MM-DD)".to_string())
+    }
+}
+
+struct BundleArgs {
+    schema: String,
+    schema_local_base: Option<PathBuf>,
+    schema_remote_base: Option<String>,
+    output: Option<PathBuf>,
+    pretty: bool,
+    emit_source_map: Option<PathBuf>,
+    defs: bool,
+    verbose: bool,
+    input_format: InputFormat,
+}
+
+/// Bundle every external `$ref` (inlined, or into `$defs`) and write the schema, annotations intact.
+fn run_bundle(args: BundleArgs) -> Result<(), u8> {
+    let BundleArgs {
+        schema: source,
+        schema_local_base,
+        schema_remote_base,
+        output,
+        pretty,
+        emit_source_map,
+        defs,
+        verbose,
+        input_format,
+    } = args;
+
+    if verbose {
+        eprintln!("[load] reading schema {}", source);
+    }
+    let mut schema = load_input(&source, input_format, fetch_options())
+        .map_err(cli_err_ctx(false, "loading schema"))?;
+
+    if defs {
+        if is_url(&source) || source == "-" {
+            report_error(false, "--bundle-mode defs needs a local schema file");
+            return Err(2);
+        }
+        if verbose {
+            eprintln!("[bundle] moving referenced schemas into $defs");
+        }
+        let url_mapping = schema_local_base
+            .as_deref()
+            .zip(schema_remote_base.as_deref());
+        let entries = bundle_refs_as_defs(&mut schema, Path::new(&source), url_mapping)
+            .map_err(cli_err_ctx(false, "bundling refs"))?;
+        if let Some(map_path) = emit_source_map {
+            write_json_output(&serde_json::json!(entries), Some(map_path), true)?;
+        }
+        return write_json_output(&schema, output, pretty);
+    }
+
+    if verbose {
+        eprintln!("[bundle] inlining $ref pointers");
+    }
+    #[cfg(feature = "remote")]
+    if is_url(&source) {
+        if emit_source_map.is_some() {
+            report_error(false, "--emit-source-map needs a local schema file");
+            return Err(2);
+        }
+        bundle_refs_remote(&mut schema, &source, fetch_options())
+            .map_err(cli_err_ctx(false, "bundling refs"))?;
+        return write_json_output(&schema, output, pretty);
+    }
+
+    if let Some(map_path) = emit_source_map {
+        let url_mapping = schema_local_base
+            .as_deref()
+            .zip(schema_remote_base.as_deref());
+        let entries = bundle_refs_with_source_map(&mut schema, Path::new(&source), url_mapping)
+            .map_err(cli_err_ctx(false, "bundling refs"))?;
+        if verbose {
+            eprintln!(
+                "[bundle] writing {} source map entries to {}",
+                entries.len(),
+                map_path.display()
+            );
+        }
+        write_json_output(&serde_json::json!(entries), Some(map_path), true)?;
+    } else {
+        bundle_local_refs(
+            &mut schema,
+            &source,
+            &schema_local_base,
+            &schema_remote_base,
+            false,
+        )?;
+    }
+
+    write_json_output(&schema, output, pretty)
+}
+
+struct MergeArgs {
+    schemas: Vec<PathBuf>,
+    extends: Vec<(String, String)>,
+    dedupe_allof: bool,
+    output: Option<PathBuf>,
+    pretty: bool,
+    verbose: bool,
+}
+
+/// Compose schema files as the capabilities `--extends` declares them.
+fn run_merge(args: MergeArgs) -> Result<(), u8> {
+    let MergeArgs {
+        schemas,
+        extends,
+        dedupe_allof,
+        output,
+        pretty,
+        verbose,
+    } = args;
+
+    let capabilities = capabilities_from_files(&schemas, &extends).map_err(cli_err(false))?;
+    if verbose {
+        for cap in &capabilities {
+            match &cap.extends {
+                Some(parents) => eprintln!(
+                    "[merge] {} extends {} ({})",
+                    cap.name,
+                    parents.join(", "),
+                    cap.schema_url
+                ),
+                None => eprintln!("[merge] {} ({})", cap.name, cap.schema_url),
+            }
+        }
+    }
+
+    let mut composed = compose_schema_with_options(
+        &capabilities,
+        &SchemaBaseConfig {
+            fetch: fetch_options(),
+            ..Default::default()
+        },
+        &ComposeOptions::default(),
+    )
+    .map_err(cli_err(false))?;
+    print_warnings(&composed.warnings);
+    if dedupe_allof {
+        if verbose {
+            eprintln!("[merge] removing duplicate and empty allOf branches");
+        }
+        composed.dedupe_allof();
+    }
+    warn_stray_annotations(&composed.schema);
+
+    write_json_output(&composed.schema, output, pretty)
+}
+
+/// Fetch everything `sources` reference and write a lockfile pinning it.
+fn run_lock(
+    sources: &[String],
+    output: &Path,
+    verbose: bool,
+    input_format: InputFormat,
+) -> Result<(), u8> {
+    let recorder = FetchRecorder::new();
+    let fetch = fetch_options().clone().recorder(recorder.clone());
+    for source in sources {
+        if verbose {
+            eprintln!("[load] reading {}", source);
+        }
+        let document = load_input(source, input_format, &fetch).map_err(cli_err(false))?;
+        if detect_direction(&document).is_some() {
+            if verbose {
+                eprintln!("[compose] fetching capability schemas for {}", source);
+            }
+            compose_from_payload_with_options(
+                &document,
+                &SchemaBaseConfig {
+                    fetch: &fetch,
+                    ..Default::default()
+                },
+                &ComposeOptions::default(),
+            )
+            .map_err(cli_err(false))?;
+        } else if is_url(source) {
+            if verbose {
+                eprintln!("[bundle] fetching refs of {}", source);
+            }
+            #[cfg(feature = "remote")]
+            {
+                let mut schema = document;
+                bundle_refs_remote(&mut schema, source, &fetch).map_err(cli_err(false))?;
+            }
+        } else {
+            warn(format!(
+                "{} is neither a schema URL nor a self-describing payload; nothing to lock",
+                source
+            ));
+        }
+    }
+
+    let lockfile = recorder.lockfile();
+    if verbose {
+        for entry in &lockfile.schemas {
+            eprintln!("[lock] {} sha256:{}", entry.url, entry.sha256);
+        }
+    }
+    if lockfile.schemas.is_empty() {
+        warn("no remote schemas were fetched; the lockfile is empty");
+    }
+    if let Err(e) = std::fs::write(output, lockfile.to_json_string()) {
+        report_error(false, &format!("writing {}: {}", output.display(), e));
+        return Err(3);
+    }
+    println!(
+        "Locked {} remote schema(s) in {}",
+        lockfile.schemas.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// Mirror everything a payload's schemas are built from into `out`.
+fn run_vendor(
+    source: &str,
+    out: &Path,
+    profile: Option<&str>,
+    verbose: bool,
+    input_format: InputFormat,
+) -> Result<(), u8> {
+    if verbose {
+        eprintln!("[load] reading {}", source);
+    }
+    let payload = load_input(source, input_format, fetch_options())
+        .map_err(cli_err_ctx(false, "loading payload"))?;
+    let files = vendor_schemas(&payload, profile, fetch_options()).map_err(cli_err(false))?;
+
+    for file in &files {
+        let path = out.join(&file.path);
+        if verbose {
+            eprintln!("[vendor] {} -> {}", file.url, path.display());
+        }
+        let mut content = serde_json::to_string_pretty(&file.content).unwrap_or_default();
+        content.push('\n');
+        let written = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::write(&path, content));
+        if let Err(e) = written {
+            report_error(false, &format!("writing {}: {}", path.display(), e));
+            return Err(3);
+        }
+    }
+    println!("Vendored {} file(s) into {}", files.len(), out.display());
+    Ok(())
+}
+
+struct ExtractArgs {
+    payload: PathBuf,
+    profile: Option<String>,
+    schema_local_base: Option<PathBuf>,
+    schema_remote_base: Option<String>,
+    map: Vec<UrlMapping>,
+    with_key: bool,
+    output: Option<PathBuf>,
+    pretty: bool,
+    verbose: bool,
+    input_format: InputFormat,
+}
+
+/// Print the payload a JSON-RPC envelope carries under its root capability's
+/// short name, found through the envelope's (or --profile's) capabilities.
+fn run_extract(args: ExtractArgs) -> Result<(), u8> {
+    if args.verbose {
+        eprintln!("[load] reading {}", args.payload.display());
+    }
+    let envelope = load_input(
+        &args.payload.to_string_lossy(),
+        args.input_format,
+        fetch_options(),
+    )
+    .map_err(cli_err_ctx(false, "loading payload"))?;
+
+    let profile = match args.profile.as_deref() {
+        Some(profile) => profile,
+        None => envelope
+            .get("meta")
+            .and_then(|m| m.get("profile"))
+            .and_then(|p| p.as_str())
+            .ok_or_else(|| {
+                report_error(
+                    false,
+                    "JSONRPC request missing meta.profile (pass --profile to supply one)",
+                );
+                2u8
+            })?,
+    };
+    if args.verbose {
+        eprintln!("[detect] fetching profile {}", profile);
+    }
+    let config = SchemaBaseConfig {
+        local_base: args.schema_local_base.as_deref(),
+        remote_base: args.schema_remote_base.as_deref(),
+        mappings: &args.map,
+        fetch: fetch_options(),
+        confine: false,
+    };
+    let capabilities =
+        extract_capabilities_from_profile(profile, &config).map_err(cli_err(false))?;
+    let (payload, key) =
+        extract_jsonrpc_payload(&envelope, &capabilities).map_err(cli_err(false))?;
+    if args.verbose {
+        eprintln!("[extract] payload found under \"{}\"", key);
+    }
+
+    let value = if args.with_key {
+        serde_json::json!({ "key": key, "payload": payload })
+    } else {
+        payload.clone()
+    };
+    write_json_output(&value, args.output, args.pretty)
+}
+
+/// Write a profile for the capability schemas under `dir`.
+fn run_profile_generate(
+    dir: &Path,
+    base_url: &str,
+    ucp_version: Option<&str>,
+    output: Option<PathBuf>,
+    pretty: bool,
+) -> Result<(), u8> {
+    if !dir.is_dir() {
+        report_error(false, &format!("not a directory: {}", dir.display()));
+        return Err(2);
+    }
+    let generated = generate_profile(dir, base_url, ucp_version).map_err(cli_err(false))?;
+    print_warnings(&generated.warnings);
+    write_json_output(&generated.profile, output, pretty)
+}
+
+/// Check a profile document and report its issues; exits 1 if it has any.
+fn run_profile_check(
+    source: &str,
+    config: &SchemaBaseConfig,
+    format: &str,
+    input_format: InputFormat,
+) -> Result<(), u8> {
+    let profile = load_input(source, input_format, fetch_options())
+        .map_err(cli_err_ctx(false, "loading profile"))?;
+    let check = check_profile(&profile, config);
+
+    if format == "json" {
+        println!("{}", serde_json::to_string_pretty(&check).unwrap());
+    } else {
+        println!("Checking {} ...\n", source);
+        for issue in &check.issues {
+            println!(
+                "  \x1b[31m✗\x1b[0m {}: {}",
+                issue.capability.as_deref().unwrap_or("profile"),
+                issue.message
+            );
+        }
+        if check.is_ok() {
+            println!(
+                "\x1b[32m✓ {} capabilities checked, no issues\x1b[0m",
+                check.capabilities
+            );
+        } else {
+            println!(
+                "\n\x1b[31m✗ {} capabilities checked: {} issue(s)\x1b[0m",
+                check.capabilities,
+                check.issues.len()
+            );
+        }
+    }
+
+    if check.is_ok() {
+        Ok(())
+    } else {
+        Err(1)
+    }
+}
+
+#[cfg(feature = "remote")]
+struct ProbeArgs {
+    url: String,
+    profile: String,
+    op: String,
+    templates: Vec<PathBuf>,
+    method: Option<String>,
+    body: Option<PathBuf>,
+    headers: Vec<(String, String)>,
+    schema_local_base: Option<PathBuf>,
+    schema_remote_base: Option<String>,
+    map: Vec<UrlMapping>,
+    json_output: bool,
+    verbose: bool,
+    input_format: InputFormat,
+}
+
+/// Probe a live API and report how its responses conform; exits 1 if any
+/// probe failed.
+#[cfg(feature = "remote")]
+fn run_probe(args: ProbeArgs) -> Result<(), u8> {
+    let json = args.json_output;
+    let config = SchemaBaseConfig {
+        local_base: args.schema_local_base.as_deref(),
+        remote_base: args.schema_remote_base.as_deref(),
+        mappings: &args.map,
+        fetch: fetch_options(),
+        confine: false,
+    };
+    if args.verbose {
+        eprintln!("[detect] fetching profile {}", args.profile);
+    }
+    let capabilities =
+        extract_capabilities_from_profile(&args.profile, &config).map_err(cli_err(json))?;
+    if args.verbose {
+        eprintln!("[compose] composing schemas from profile capabilities");
+    }
+    let composed = compose_schema_with_options(&capabilities, &config, &ComposeOptions::new())
+        .map_err(cli_err(json))?;
+    print_warnings(&composed.warnings);
+    let validator = CompiledValidator::new(
+        &composed.schema,
+        &ResolveOptions::new(Direction::Response, &args.op),
+    )
+    .map_err(|e| report_validate_error(json, e))?;
+
+    let method = args
+        .method
+        .as_deref()
+        .unwrap_or(default_method(&args.op))
+        .to_uppercase();
+    let mut requests = Vec::new();
+    for path in &args.templates {
+        let template = load_input(&path.to_string_lossy(), args.input_format, fetch_options())
+            .map_err(cli_err_ctx(json, "loading request template"))?;
+        let templates = match template {
+            serde_json::Value::Array(items) => items,
+            template => vec![template],
+        };
+        for template in &templates {
+            let request =
+                ProbeRequest::from_template(template, &args.url, &method).map_err(|message| {
+                    report_error(json, &format!("{}: {}", path.display(), message));
+                    2u8
+                })?;
+            requests.push(request);
+        }
+    }
+    if requests.is_empty() {
+        let body = match &args.body {
+            Some(path) => Some(
+                load_input(&path.to_string_lossy(), args.input_format, fetch_options())
+                    .map_err(cli_err_ctx(json, "loading request body"))?,
+            ),
+            None => None,
+        };
+        requests.push(ProbeRequest {
+            method,
+            url: args.url.clone(),
+            headers: Vec::new(),
+            body,
+        });
+    }
+    for request in &mut requests {
+        request.headers.extend(args.headers.iter().cloned());
+    }
+
+    let results: Vec<ProbeResult> = requests
+        .iter()
+        .map(|request| {
+            if args.verbose {
+                eprintln!("[probe] {} {}", request.method, request.url);
+            }
+            probe(request, &validator, fetch_options())
+        })
+        .collect();
+    let failed = results.iter().filter(|r| !r.passed()).count();
+
+    if json {
+        let report = serde_json::json!({
+            "operation": args.op,
+            "probed": results.len(),
+            "failed": failed,
+            "results": results,
+        });
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        println!(
+            "Probing {} responses against {} ...\n",
+            args.op, args.profile
+        );
+        for result in &results {
+            let status = result
+                .status
+                .map(|s| format!(" ({})", s))
+                .unwrap_or_default();
+            if result.passed() {
+                println!(
+                    "  \x1b[32m✓\x1b[0m {} {}{}",
+                    result.method, result.url, status
+                );
+                continue;
+            }
+            println!(
+                "  \x1b[31m✗\x1b[0m {} {}{}",
+                result.method, result.url, status
+            );
+            if let Some(error) = &result.error {
+                println!("      {}", error);
+            }
+            for violation in &result.violations {
+                println!("      {}", violation);
+            }
+        }
+        if failed == 0 {
+            println!(
+                "\n\x1b[32m✓ {} probed, all conformant\x1b[0m",
+                results.len()
+            );
+        } else {
+            println!(
+                "\n\x1b[31m✗ {} probed: {} failed\x1b[0m",
+                results.len(),
+                failed
+            );
+        }
+    }
+
+    if failed == 0 {
+        Ok(())
+    } else {
+        Err(1)
+    }
+}
+
+struct ReplayArgs {
+    har: PathBuf,
+    rules: Vec<OperationRule>,
+    profile: Option<String>,
+    schema_local_base: Option<PathBuf>,
+    schema_remote_base: Option<String>,
+    map: Vec<UrlMapping>,
+    json_output: bool,
+    verbose: bool,
+    input_format: InputFormat,
+}
+
+/// Schemas composed for replayed traffic, cached by profile URL or
+/// capability set, and their compiled validators.
+struct ReplaySchemas<'a> {
+    config: SchemaBaseConfig<'a>,
+    composed: HashMap<String, Result<(Vec<Capability>, serde_json::Value), String>>,
+    validators: HashMap<(String, Direction, String), Result<CompiledValidator, String>>,
+}
+
+impl ReplaySchemas<'_> {
+    /// Capabilities and composed schema for `key`, loading them with `load`
+    /// the first time.
+    fn compose(
+        &mut self,
+        key: &str,
+        load: impl FnOnce(&SchemaBaseConfig) -> Result<Vec<Capability>, ComposeError>,
+    ) -> Result<&(Vec<Capability>, serde_json::Value), String> {
+        let config = &self.config;
+        self.composed
+            .entry(key.to_string())
+            .or_insert_with(|| {
+                let capabilities = load(config).map_err(|e| e.to_string())?;
+                let schema = compose_schema(&capabilities, config).map_err(|e| e.to_string())?;
+                Ok((capabilities, schema))
+            })
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    /// Validate `payload` against the schema composed for `key`.
+    fn validate(
+        &mut self,
+        key: &str,
+        direction: Direction,
+        op: &str,
+        payload: &serde_json::Value,
+    ) -> Result<Vec<SchemaError>, String> {
+        let schema = match self.composed.get(key) {
+            Some(Ok((_, schema))) => schema,
+            _ => return Err(format!("no schema composed for {}", key)),
+        };
+        let validator = self
+            .validators
+            .entry((key.to_string(), direction, op.to_string()))
+            .or_insert_with(|| {
+                CompiledValidator::new(schema, &ResolveOptions::new(direction, op))
+                    .map_err(|e| e.to_string())
+            })
+            .as_ref()
+            .map_err(Clone::clone)?;
+        match validator.validate(payload) {
+            Ok(()) => Ok(Vec::new()),
+            Err(ValidateError::Invalid { errors }) => Ok(errors),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Check a request body against the schema composed from its profile:
+    /// `params.meta.profile` for JSON-RPC requests, otherwise `profile` (from
+    /// the UCP-Agent header or --profile). `None` if there's no profile.
+    fn check_request(
+        &mut self,
+        body: &serde_json::Value,
+        profile: Option<&str>,
+        op: &str,
+    ) -> Option<Result<Vec<SchemaError>, String>> {
+        let params = body.get("jsonrpc").and_then(|_| body.get("params"));
+        let profile = params
+            .and_then(|p| p["meta"]["profile"].as_str())
+            .or(profile)?;
+        let capabilities = match self.compose(profile, |config| {
+            extract_capabilities_from_profile(profile, config)
+        }) {
+            Ok((capabilities, _)) => capabilities,
+            Err(e) => return Some(Err(e)),
+        };
+        let payload = match params {
+            Some(params) => match extract_jsonrpc_payload(params, capabilities) {
+                Ok((payload, _)) => payload.clone(),
+                Err(e) => return Some(Err(e.to_string())),
+            },
+            None => body.clone(),
+        };
+        Some(self.validate(profile, Direction::Request, op, &payload))
+    }
+}
+
+/// Validate the bodies of every exchange in a HAR capture; exits 1 if any
+/// failed.
+fn run_replay(args: ReplayArgs) -> Result<(), u8> {
+    let json = args.json_output;
+    let har = load_input(
+        &args.har.to_string_lossy(),
+        args.input_format,
+        fetch_options(),
+    )
+    .map_err(cli_err_ctx(json, "loading HAR"))?;
+    let exchanges = har_exchanges(&har).map_err(|message| {
+        report_error(json, &format!("{}: {}", args.har.display(), message));
+        2u8
+    })?;
+    let mut rules = args.rules.clone();
+    rules.extend(default_operation_rules());
+    let mut schemas = ReplaySchemas {
+        config: SchemaBaseConfig {
+            local_base: args.schema_local_base.as_deref(),
+            remote_base: args.schema_remote_base.as_deref(),
+            mappings: &args.map,
+            fetch: fetch_options(),
+            confine: false,
+        },
+        composed: HashMap::new(),
+        validators: HashMap::new(),
+    };
+    let skip = |i: usize, reason: &str| {
+        if args.verbose {
+            eprintln!("[replay] #{} skipped: {}", i + 1, reason);
+        }
+    };
+
+    // endpoint -> (exchanges, failed, skipped)
+    let mut endpoints: BTreeMap<String, (usize, usize, usize)> = BTreeMap::new();
+    let mut failures = Vec::new();
+    for (i, exchange) in exchanges.iter().enumerate() {
+        let counts = endpoints.entry(endpoint_key(exchange)).or_default();
+        counts.0 += 1;
+        let Some(op) = infer_operation(&rules, exchange) else {
+            skip(i, "no operation rule matches");
+            counts.2 += 1;
+            continue;
+        };
+
+        let mut checks = Vec::new();
+        if let Some(body) = &exchange.request_body {
+            let profile = exchange.agent_profile().or(args.profile.as_deref());
+            match schemas.check_request(body, profile, &op) {
+                Some(result) => checks.push((Direction::Request, result)),
+                None => skip(i, "request has no profile (pass --profile)"),
+            }
+        }
+        let response = exchange
+            .response_body
+            .as_ref()
+            .filter(|_| (200..300).contains(&exchange.status))
+            .map(|body| match body.get("jsonrpc") {
+                Some(_) => body.get("result").unwrap_or(body),
+                None => body,
+            });
+        if let Some(body) = response {
+            if detect_direction(body) == Some(DetectedDirection::Response) {
+                let key = body["ucp"]["capabilities"].to_string();
+                let result = schemas
+                    .compose(&key, |config| extract_capabilities(body, config))
+                    .map(|_| ())
+                    .and_then(|()| schemas.validate(&key, Direction::Response, &op, body));
+                checks.push((Direction::Response, result));
+            } else {
+                skip(i, "response has no ucp.capabilities");
+            }
+        }
+        if checks.is_empty() {
+            counts.2 += 1;
+            continue;
+        }
+
+        let mut failed = false;
+        for (direction, result) in checks {
+            let (error, errors) = match result {
+                Ok(errors) if errors.is_empty() => continue,
+                Ok(errors) => (None, errors),
+                Err(error) => (Some(error), Vec::new()),
+            };
+            failed = true;
+            failures.push(serde_json::json!({
+                "exchange": i + 1,
+                "method": exchange.method,
+                "url": exchange.url,
+                "operation": op,
+                "direction": direction.as_str(),
+                "error": error,
+                "errors": errors,
+            }));
+        }
+        if failed {
+            counts.1 += 1;
+        }
+    }
+
+    let failed: usize = endpoints.values().map(|c| c.1).sum();
+    let skipped: usize = endpoints.values().map(|c| c.2).sum();
+    if json {
+        let endpoints: Vec<_> = endpoints
+            .iter()
+            .map(|(endpoint, (total, failed, skipped))| {
+                serde_json::json!({
+                    "endpoint": endpoint,
+                    "exchanges": total,
+                    "failed": failed,
+                    "skipped": skipped,
+                })
+            })
+            .collect();
+        let report = serde_json::json!({
+            "exchanges": exchanges.len(),
+            "failed": failed,
+            "skipped": skipped,
+            "endpoints": endpoints,
+            "failures": failures,
+        });
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        println!(
+            "Replaying {} ({} exchanges) ...\n",
+            args.har.display(),
+            exchanges.len()
+        );
+        for failure in &failures {
+            println!(
+                "  \x1b[31m✗\x1b[0m #{} {} {} ({} {})",
+                failure["exchange"],
+                failure["method"].as_str().unwrap_or_default(),
+                failure["url"].as_str().unwrap_or_default(),
+                failure["operation"].as_str().unwrap_or_default(),
+                failure["direction"].as_str().unwrap_or_default()
+            );
+            if let Some(error) = failure["error"].as_str() {
+                println!("      {}", error);
+            }
+            for error in failure["errors"].as_array().into_iter().flatten() {
+                println!(
+                    "      {}: {}",
+                    error["path"].as_str().unwrap_or_default(),
+                    error["message"].as_str().unwrap_or_default()
+                );
+            }
+        }
+        if !failures.is_empty() {
+            println!();
+        }
+        let width = endpoints.keys().map(String::len).max().unwrap_or(0).max(8);
+        println!(
+            "  {:width$}  exchanges  failed  skipped",
+            "endpoint",
+            width = width
+        );
+        for (endpoint, (total, failed, skipped)) in &endpoints {
+            println!(
+                "  {:width$}  {:>9}  {:>6}  {:>7}",
+                endpoint,
+                total,
+                failed,
+                skipped,
+                width = width
+            );
+        }
+        if failed == 0 {
+            println!(
+                "\n\x1b[32m✓ {} exchanges replayed, no failures ({} skipped)\x1b[0m",
+                exchanges.len(),
+                skipped
+            );
+        } else {
+            println!(
+                "\n\x1b[31m✗ {} exchanges replayed: {} failed ({} skipped)\x1b[0m",
+                exchanges.len(),
+                failed,
+                skipped
+            );
+        }
+    }
+
+    if failed == 0 {
+        Ok(())
+    } else {
+        Err(1)
+    }
+}
+
+/// Environment variable with the registry bearer token for `push`/`pull`.
+#[cfg(feature = "remote")]
+const REGISTRY_TOKEN_ENV: &str = "UCP_SCHEMA_REGISTRY_TOKEN";
+
+#[cfg(feature = "remote")]
+impl CliExitCode for RegistryError {
+    fn exit_code(&self) -> u8 {
+        RegistryError::exit_code(self) as u8
+    }
+}
+
+/// Push a directory's capability schemas and report each one's status.
+#[cfg(feature = "remote")]
+fn run_push(
+    dir: &Path,
+    registry: &str,
+    token: Option<String>,
+    dry_run: bool,
+    json_output: bool,
+) -> Result<(), u8> {
+    if !dir.is_dir() {
+        report_error(json_output, &format!("not a directory: {}", dir.display()));
+        return Err(2);
+    }
+    let token = token.or_else(|| std::env::var(REGISTRY_TOKEN_ENV).ok());
+    let (pushed, warnings) =
+        push_schemas(dir, registry, token.as_deref(), dry_run, fetch_options())
+            .map_err(cli_err(json_output))?;
+    print_warnings(&warnings);
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&pushed).unwrap());
+        return Ok(());
+    }
+    for schema in &pushed {
+        let status = match schema.status {
+            PushStatus::Published => "published",
+            PushStatus::Unchanged => "unchanged",
+            PushStatus::New => "would publish",
+        };
+        println!(
+            "{:13} {} {} (sha256 {})",
+            status,
+            schema.name,
+            schema.version,
+            &schema.sha256[..12]
+        );
+    }
+    Ok(())
+}
+
+/// Pull schemas into `<out>/<name>/<version>.json`.
+#[cfg(feature = "remote")]
+fn run_pull(
+    capabilities: &[(String, String)],
+    registry: &str,
+    token: Option<String>,
+    out: &Path,
+    sha256: Option<&str>,
+) -> Result<(), u8> {
+    if sha256.is_some() && capabilities.len() > 1 {
+        report_error(false, "--sha256 needs a single NAME@VERSION");
+        return Err(2);
+    }
+    let token = token.or_else(|| std::env::var(REGISTRY_TOKEN_ENV).ok());
+    for (name, version) in capabilities {
+        let pulled = pull_schema(
+            registry,
+            name,
+            version,
+            token.as_deref(),
+            sha256,
+            fetch_options(),
+        )
+        .map_err(cli_err(false))?;
+        let path = out.join(name).join(format!("{}.json", version));
+        let written = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::write(&path, &pulled.body));
+        if let Err(e) = written {
+            report_error(false, &format!("cannot write {}: {}", path.display(), e));
+            return Err(3);
+        }
+        println!(
+            "pulled {} {} -> {} (sha256 {})",
+            name,
+            version,
+            path.display(),
+            &pulled.sha256[..12]
+        );
+    }
+    Ok(())
+}
+
+/// Run a `cache` subcommand against the configured cache directory.
+fn run_cache(command: CacheCommand) -> Result<(), u8> {
+    let Some(dir) = fetch_options().cache_dir.clone() else {
+        report_error(
+            false,
+            "no cache directory: pass --cache-dir or set UCP_SCHEMA_CACHE_DIR",
+        );
+        return Err(2);
+    };
+    match command {
+        CacheCommand::Ls { format } => {
+            let entries = cache_entries(&dir).map_err(cli_err(false))?;
+            if format == "json" {
+                println!("{}", serde_json::to_string_pretty(&entries).unwrap());
+                return Ok(());
+            }
+            if entries.is_empty() {
+                println!("{} is empty", dir.display());
+                return Ok(());
+            }
+            let width = entries.iter().map(|e| e.url.len()).max().unwrap_or(0);
+            for entry in &entries {
+                println!(
+                    "{:width$}  {}  {:>4}  {} bytes",
+                    entry.url,
+                    &entry.sha256[..12.min(entry.sha256.len())],
+                    format_age(entry.age()),
+                    entry.size,
+                    width = width
+                );
+            }
+            Ok(())
+        }
+        CacheCommand::Clear => {
+            let removed = prune_cache(&dir, None).map_err(cli_err(false))?;
+            println!("removed {} cache entries", removed.len());
+            Ok(())
+        }
+        CacheCommand::Prune { max_age } => {
+            let removed = prune_cache(&dir, Some(max_age)).map_err(cli_err(false))?;
+            for entry in &removed {
+                println!("removed {} ({} old)", entry.url, format_age(entry.age()));
+            }
+            println!(
+                "removed {} cache entries older than {}",
+                removed.len(),
+                format_age(max_age)
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Largest whole unit of a duration: `40s`, `12m`, `5h`, `3d`.
+fn format_age(age: Duration) -> String {
+    let seconds = age.as_secs();
+    match seconds {
+        s if s >= 86400 => format!("{}d", s / 86400),
+        s if s >= 3600 => format!("{}h", s / 3600),
+        s if s >= 60 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+struct EnvelopeArgs {
+    payload: PathBuf,
+    profile: String,
+    op: String,
+    capability: Option<String>,
+    id: String,
+    schema_local_base: Option<PathBuf>,
+    schema_remote_base: Option<String>,
+    map: Vec<UrlMapping>,
+    output: Option<PathBuf>,
+    pretty: bool,
+    verbose: bool,
+    input_format: InputFormat,
+}
+
+/// Wrap a payload in a JSON-RPC request for the profile's root capability.
+fn run_envelope(args: EnvelopeArgs) -> Result<(), u8> {
+    if args.verbose {
+        eprintln!("[load] reading {}", args.payload.display());
+    }
+    let payload = load_input(
+        &args.payload.to_string_lossy(),
+        args.input_format,
+        fetch_options(),
+    )
+    .map_err(cli_err_ctx(false, "loading payload"))?;
+    if args.verbose {
+        eprintln!("[detect] fetching profile {}", args.profile);
+    }
+    let config = SchemaBaseConfig {
+        local_base: args.schema_local_base.as_deref(),
+        remote_base: args.schema_remote_base.as_deref(),
+        mappings: &args.map,
+        fetch: fetch_options(),
+        confine: false,
+    };
+    let capabilities =
+        extract_capabilities_from_profile(&args.profile, &config).map_err(cli_err(false))?;
+    let id = args
+        .id
+        .parse::<i64>()
+        .map_or_else(|_| serde_json::Value::from(args.id.clone()), Into::into);
+    let request = jsonrpc_envelope(
+        &payload,
+        &capabilities,
+        &args.profile,
+        &args.op,
+        args.capability.as_deref(),
+        id,
+    )
+    .map_err(cli_err(false))?;
+    if args.verbose {
+        eprintln!("[envelope] method {}", request["method"]);
+    }
+    write_json_output(&request, args.output, args.pretty)
+}
+
+struct LibraryArgs {
+    schema: String,
+    ops: Vec<String>,
+    name: Option<String>,
+    bundle: bool,
+    dedupe: bool,
+    strict: bool,
+    output: Option<PathBuf>,
+    summary_json: Option<PathBuf>,
+    pretty: bool,
+    verbose: bool,
+}
+
+/// Resolve each operation in both directions and emit them as one `$defs` library.
+fn run_library(args: LibraryArgs) -> Result<(), u8> {
+    let started = Instant::now();
+    let result = match build_library(&args) {
+        Ok(library) => {
+            write_json_output(&library, args.output.clone(), args.pretty).map_err(|code| {
+                let target = args.output.as_ref().map(|p| p.display().to_string());
+                (
+                    code,
+                    format!("cannot write {}", target.as_deref().unwrap_or("output")),
+                )
+            })
+        }
+        Err((code, reason)) => {
+            report_error(false, &reason);
+            Err((code, reason))
+        }
+    };
+    let Some(summary_path) = args.summary_json else {
+        return result.map_err(|(code, _)| code);
+    };
+
+    let outputs = args.output.clone().unwrap_or_else(|| PathBuf::from("-"));
+    let exit = result.as_ref().map_err(|(code, _)| *code).copied();
+    let mut job = serde_json::json!({ "input": args.schema, "ops": args.ops });
+    summarize_job(&mut job, &[outputs], result, started);
+    write_summary(vec![job], summary_path, started)?;
+    exit
+}
+
+/// Load (and optionally bundle) the schema, then resolve the library.
+/// Errors carry their exit code and message, for reporting or a summary.
+fn build_library(args: &LibraryArgs) -> Result<serde_json::Value, (u8, String)> {
+    let schema_source = &args.schema;
+    let failed = |e: ResolveError| (CliExitCode::exit_code(&e), e.to_string());
+
+    if args.verbose {
+        eprintln!("[load] reading {}", schema_source);
+    }
+    let mut schema = load_schema_auto(schema_source, fetch_options()).map_err(failed)?;
+    if args.bundle {
+        if args.verbose {
+            eprintln!("[bundle] inlining $ref pointers");
+        }
+        let base_dir = Path::new(schema_source).parent().unwrap_or(Path::new("."));
+        bundle_refs(&mut schema, base_dir)
+            .map_err(|e| (CliExitCode::exit_code(&e), format!("bundling refs: {}", e)))?;
+    }
+
+    let name = type_base_name(args.name.clone(), &schema, schema_source);
+
+    let options = LibraryOptions::new(name, args.ops.clone())
+        .strict(args.strict)
+        .dedupe(args.dedupe);
+    if args.verbose {
+        eprintln!(
+            "[resolve] resolving {} for {} (request + response){}",
+            options.name,
+            options.operations.join(", "),
+            if args.strict { " (strict)" } else { "" }
+        );
+    }
+    warn_stray_annotations(&schema);
+    resolve_library(&schema, &options).map_err(failed)
+}
+
+/// Base name for generated entries: explicit > schema title > file stem.
+fn type_base_name(name: Option<String>, schema: &serde_json::Value, source: &str) -> String {
+    name.or_else(|| {
+        schema
+            .get("title")
+            .and_then(|t| t.as_str())
+            .map(String::from)
+    })
+    .unwrap_or_else(|| {
+        let trimmed = source.trim_end_matches('/');
+        let file = trimmed.rsplit('/').next().unwrap_or(trimmed);
+        file.split('.').next().unwrap_or(file).to_string()
+    })
+}
+
+struct CodegenArgs {
+    target: CodegenTarget,
+    schema: String,
+    ops: Vec<String>,
+    name: Option<String>,
+    bundle: bool,
+    strict: bool,
+    output: Option<PathBuf>,
+    verbose: bool,
+    input_format: InputFormat,
+}
+
+/// Resolve each operation in both directions and render typed bindings.
+fn run_codegen(args: CodegenArgs) -> Result<(), u8> {
+    let CodegenArgs {
+        target,
+        schema: schema_source,
+        ops,
+        name,
+        bundle,
+        strict,
+        output,
+        verbose,
+        input_format,
+    } = args;
+
+    if verbose {
+        eprintln!("[load] reading {}", schema_source);
+    }
+    let mut schema =
+        load_input(&schema_source, input_format, fetch_options()).map_err(cli_err(false))?;
+    if bundle {
+        if verbose {
+            eprintln!("[bundle] inlining $ref pointers");
+        }
+        let base_dir = Path::new(&schema_source).parent().unwrap_or(Path::new("."));
+        bundle_refs(&mut schema, base_dir).map_err(cli_err_ctx(false, "bundling refs"))?;
+    }
+
+    let name = type_base_name(name, &schema, &schema_source);
+    let options = CodegenOptions::new(name, ops).strict(strict);
+    if verbose {
+        eprintln!(
+            "[codegen] generating {} for {} (request + response){}",
+            options.name,
+            options.operations.join(", "),
+            if strict { " (strict)" } else { "" }
+        );
+    }
+    warn_stray_annotations(&schema);
+    let code = generate_code(&schema, target, &options).map_err(cli_err(false))?;
+
+    match output {
+        Some(path) => std::fs::write(&path, code).map_err(|e| {
+            eprintln!("Error writing to {}: {}", path.display(), e);
+            3u8
+        }),
+        None => {
+            print!("{}", code);
+            Ok(())
+        }
+    }
+}
+
+struct ValidateArgs {
+    payloads: Vec<PathBuf>,
+    schema: Option<String>,
+    schema_local_base: Option<PathBuf>,
+    schema_remote_base: Option<String>,
+    map: Vec<UrlMapping>,
+    warn_on_unused_schema_base: bool,
+    strict_version: bool,
+    capability_filter: Option<Regex>,
+    unwrap_pointer: Option<String>,
+    profile: Option<String>,
+    request: bool,
+    response: bool,
+    op: Option<String>,
+    op_any: Vec<String>,
+    json_output: bool,
+    junit: bool,
+    problem_json: bool,
+    error_format: Option<ErrorFormat>,
+    strict: bool,
+    show_strict_delta: bool,
+    ndjson: bool,
+    threads: usize,
+    annotate_payload: bool,
+    deref_payload: bool,
+    reject_readonly: bool,
+    propagate_variants: bool,
+    verbose: bool,
+    input_format: InputFormat,
+}
+
+/// Reject composition flags alongside an explicit --schema (composition is
+/// bypassed, so they would silently do nothing).
+fn check_explicit_schema_flags(args: &ValidateArgs) -> Result<(), u8> {
+    if args.schema.is_none() {
+        return Ok(());
+    }
+    let json_output = args.json_output;
+    if args.schema_local_base.is_some() || args.schema_remote_base.is_some() || !args.map.is_empty()
+    {
+        report_error(json_output, "--schema-local-base/--schema-remote-base/--map do not apply with explicit --schema (composition is bypassed). Remove these flags, or remove --schema to use self-describing mode.");
+        return Err(2);
+    }
+    if args.strict_version {
+        report_error(json_output, "--strict-version does not apply with explicit --schema (composition is bypassed). Remove this flag, or remove --schema to use self-describing mode.");
+        return Err(2);
+    }
+    if args.capability_filter.is_some() {
+        report_error(json_output, "--capability-filter does not apply with explicit --schema (composition is bypassed). Remove this flag, or remove --schema to use self-describing mode.");
+        return Err(2);
+    }
+    Ok(())
+}
+
+fn run_validate(args: ValidateArgs) -> Result<(), u8> {
+    if args.problem_json {
+        PROBLEM_JSON.store(true, Ordering::Relaxed);
+        if args.annotate_payload {
+            report_error(false, "--annotate-payload prints the payload, not a report; it doesn't apply with --format problem-json");
+            return Err(2);
+        }
+    }
+    check_explicit_schema_flags(&args)?;
+    let stdin_inputs = args
+        .payloads
+        .iter()
+        .map(|p| p.as_os_str())
+        .chain(args.schema.as_deref().map(std::ffi::OsStr::new))
+        .filter(|source| *source == "-")
+        .count();
+    if stdin_inputs > 1 {
+        report_error(
+            args.json_output,
+            "only one input can be read from stdin (-). Pass the others as files.",
+        );
+        return Err(2);
+    }
+    if args.ndjson {
+        return run_validate_ndjson(args);
+    }
+    let batch = args.payloads.len() > 1 || args.payloads.iter().any(|p| is_glob(p));
+    if batch && args.problem_json {
+        report_error(false, "--format problem-json describes a single payload. Validate the files one at a time, or use --format json or junit.");
+        return Err(2);
+    }
+    if args.junit || batch {
+        return run_validate_batch(args);
+    }
+
+    let ValidateArgs {
+        payloads,
+        schema: schema_source,
+        schema_local_base,
+        schema_remote_base,
+        map,
+        warn_on_unused_schema_base,
+        strict_version,
+        capability_filter,
+        unwrap_pointer,
+        profile: profile_url,
+        request,
+        response,
+        op,
+        op_any,
+        json_output,
+        error_format,
+        strict,
+        show_strict_delta,
+        junit: _,
+        problem_json,
+        ndjson: _,
+        threads: _,
+        annotate_payload,
+        deref_payload,
+        reject_readonly,
+        propagate_variants,
+        verbose,
+        input_format,
+    } = args;
+    let payload_path = &payloads[0];
+
+    let config = SchemaBaseConfig {
+        local_base: schema_local_base.as_deref(),
+        remote_base: schema_remote_base.as_deref(),
+        mappings: &map,
+        fetch: fetch_options(),
+        confine: false,
+    };
+    let mut compose_options = ComposeOptions::new().strict_version(strict_version);
+    if let Some(filter) = capability_filter {
+        compose_options = compose_options.capability_filter(filter);
+    }
+
+    let payload_file = load_payload(
+        payload_path,
+        unwrap_pointer.as_deref(),
+        deref_payload,
+        input_format,
+        verbose,
+    )
+    .map_err(|(code, message)| {
+        report_error(json_output, &message);
+        code
+    })?;
+
+    // Determine validation mode and extract actual payload to validate:
+    // 1. --profile: REST pattern, payload is raw object
+    // 2. --schema: explicit schema, payload is raw object
+    // 3. JSONRPC: meta.profile in payload, extract nested payload
+    // 4. Response: ucp.capabilities in payload, payload is self-describing
+    let (schema, payload, direction) = if let Some(ref profile) = profile_url {
+        // REST pattern: --profile flag provides profile URL, payload is raw
+        if verbose {
+            eprintln!("[detect] REST pattern: using --profile {}", profile);
+        }
+        let direction = determine_direction(request, response, None).unwrap_or(Direction::Request);
+        let schema = compose_profile_schema(
+            profile,
+            &config,
+            &compose_options,
+            warn_on_unused_schema_base,
+            verbose,
+            json_output,
+        )?;
+        (schema, payload_file, direction)
+    } else if let Some(ref source) = schema_source {
+        // Explicit schema: try to infer direction from payload
+        if verbose {
+            eprintln!("[load] using explicit schema: {}", source);
+        }
+        let inferred = detect_direction(&payload_file).map(Direction::from);
+        let direction =
+            determine_direction(request, response, inferred).unwrap_or(Direction::Request);
+
+        let schema = load_explicit_schema(source, input_format, json_output)?;
+        (schema, payload_file, direction)
+    } else {
+        // Self-describing mode - detect from payload structure
+        match detect_direction(&payload_file) {
+            Some(DetectedDirection::Response) => {
+                // Response: ucp.capabilities, compose and validate full payload
+                if verbose {
+                    verbose_capabilities(&payload_file, &config);
+                    eprintln!("[compose] composing schemas from payload capabilities");
+                }
+                let direction = determine_direction(request, response, Some(Direction::Response))
+                    .unwrap_or(Direction::Response);
+                if warn_on_unused_schema_base {
+                    warn_unmapped_payload_capabilities(&payload_file, &config);
+                }
+                let composed =
+                    compose_from_payload_with_options(&payload_file, &config, &compose_options)
+                        .map_err(cli_err(json_output))?;
+                print_warnings(&composed.warnings);
+                if verbose {
+                    note_skipped(&composed.skipped, "--capability-filter");
+                }
+                let schema = composed.schema;
+                (schema, payload_file, direction)
+            }
+            Some(DetectedDirection::Request) => {
+                // JSONRPC request: meta.profile, extract nested payload
+                let direction = determine_direction(request, response, Some(Direction::Request))
+                    .unwrap_or(Direction::Request);
+
+                // Get profile URL from meta.profile
+                let profile = payload_file
+                    .get("meta")
+                    .and_then(|m| m.get("profile"))
+                    .and_then(|p| p.as_str())
+                    .ok_or_else(|| {
+                        report_error(json_output, "JSONRPC request missing meta.profile");
+                        2u8
+                    })?;
+
+                if verbose {
+                    eprintln!("[detect] JSONRPC request: fetching profile {}", profile);
+                }
+
+                let capabilities = extract_capabilities_from_profile(profile, &config)
+                    .map_err(cli_err(json_output))?;
+                if warn_on_unused_schema_base {
+                    warn_unmapped_capabilities(&capabilities, &config);
+                }
+
+                // Extract actual payload from envelope (e.g., "checkout" key)
+                let (nested_payload, _key) = extract_jsonrpc_payload(&payload_file, &capabilities)
+                    .map_err(cli_err(json_output))?;
+
+                if verbose {
+                    eprintln!(
+                        "[compose] composing {} capability schemas from profile",
+                        capabilities.len()
+                    );
+                }
+                let composed =
+                    compose_schema_with_options(&capabilities, &config, &compose_options)
+                        .map_err(cli_err(json_output))?;
+                print_warnings(&composed.warnings);
+                if verbose {
+                    note_skipped(&composed.skipped, "--capability-filter");
+                }
+                let schema = composed.schema;
+
+                (schema, nested_payload.clone(), direction)
+            }
+            None => {
+                report_error(
+                    json_output,
+                    "cannot infer direction: payload has no ucp.capabilities (response) or meta.profile (request). Use --schema, --profile, --request, or --response.",
+                );
+                return Err(2);
+            }
+        }
+    };
+
+    warn_stray_annotations(&schema);
+
+    let make_options = |op: &str| {
+        ResolveOptions::new(direction, op)
+            .strict(strict)
+            .reject_readonly(reject_readonly)
+            .propagate_variants(propagate_variants)
+    };
+    // --op-any reports which ops matched, even when it lists just one
+    let Some(op) = op else {
+        let matched = validate_any_op(
+            &schema,
+            &payload,
+            &op_any,
+            make_options,
+            json_output,
+            error_format.as_ref(),
+            verbose,
+        )?;
+        if show_strict_delta {
+            warn_strict_delta(&schema, &payload, &matched, verbose);
+        }
+        return Ok(());
+    };
+
+    let options = make_options(&op);
+    if verbose {
+        eprintln!(
+            "[resolve] resolving for {}/{}",
+            direction.as_str(),
+            options.operation
+        );
+        eprintln!("[validate] validating payload against resolved schema");
+    }
+
+    if annotate_payload {
+        return annotate_validation(&schema, &payload, &options);
+    }
+
+    match validate(&schema, &payload, &options) {
+        Ok(()) => {
+            if json_output {
+                println!(r#"{{"valid":true}}"#);
+            } else if !problem_json {
+                println!("Valid");
+            }
+            if show_strict_delta {
+                warn_strict_delta(&schema, &payload, &options, verbose);
+            }
+            Ok(())
+        }
+        Err(ValidateError::Invalid { errors, .. }) => {
+            record_problem("", &errors);
+            if json_output {
+                let output = serde_json::json!({
+                    "valid": false,
+                    "errors": errors
+                });
+                println!("{}", output);
+            } else if let Some(format) = &error_format {
+                for error in &errors {
+                    eprintln!("{}", format.render(error));
+                }
+            } else {
+                eprintln!("Validation failed:");
+                for error in errors {
+                    eprintln!("  {}", error);
+                }
+            }
+            Err(1)
+        }
+        Err(ValidateError::Resolve(e)) => {
+            report_error(json_output, &e.to_string());
+            Err(e.exit_code() as u8)
+        }
+    }
+}
+
+/// A batch payload's direction and validation errors, or why it wasn't
+/// validated.
+type PayloadOutcome = Result<(Direction, Vec<SchemaError>), String>;
+
+/// A [`PayloadOutcome`] that still carries the exit code of a load failure.
+type CheckedPayload = Result<(Direction, Vec<SchemaError>), (u8, String)>;
+
+/// Validate several payloads against one schema (from --schema or
+/// --profile), compiled once per direction, and summarize the results.
+fn run_validate_batch(args: ValidateArgs) -> Result<(), u8> {
+    let json_output = args.json_output;
+    if args.annotate_payload || !args.op_any.is_empty() || args.show_strict_delta {
+        let message = if args.junit {
+            "--annotate-payload, --op-any and --show-strict-delta don't apply with --format junit."
+        } else {
+            "--annotate-payload, --op-any and --show-strict-delta take a single payload. Validate the files one at a time to use them."
+        };
+        report_error(json_output, message);
+        return Err(2);
+    }
+    let paths = expand_payload_globs(&args.payloads).map_err(|message| {
+        report_error(json_output, &message);
+        2u8
+    })?;
+    let mode = if args.junit {
+        "--format junit"
+    } else {
+        "validating several payloads"
+    };
+    let shared = SharedSchema::load(&args, mode)?;
+
+    // Each file is loaded and validated independently against the shared
+    // compiled schemas; collecting keeps input order whatever the thread count
+    let check = |path: &PathBuf| -> Result<CheckedPayload, u8> {
+        let payload = match load_payload(
+            path,
+            args.unwrap_pointer.as_deref(),
+            args.deref_payload,
+            args.input_format,
+            args.verbose,
+        ) {
+            Ok(payload) => payload,
+            Err(failure) => return Ok(Err(failure)),
+        };
+        if args.verbose {
+            eprintln!("[validate] validating {}", path.display());
+        }
+        shared.validate(&payload, &args).map(Ok)
+    };
+    let outcomes: Vec<_> = if args.threads == 1 {
+        paths.iter().map(check).collect()
+    } else {
+        let run = || paths.par_iter().map(check).collect();
+        match rayon::ThreadPoolBuilder::new()
+            .num_threads(args.threads)
+            .build()
+        {
+            Ok(pool) => pool.install(run),
+            // Pool creation only fails on resource exhaustion; the global pool still works
+            Err(_) => run(),
+        }
+    };
+
+    let mut results: Vec<(&Path, PayloadOutcome)> = Vec::with_capacity(paths.len());
+    let mut first_failure = None;
+    for (path, outcome) in paths.iter().zip(outcomes) {
+        match outcome? {
+            Ok((direction, errors)) => {
+                if !errors.is_empty() {
+                    first_failure.get_or_insert(1);
+                }
+                results.push((path, Ok((direction, errors))));
+            }
+            Err((code, message)) => {
+                first_failure.get_or_insert(code);
+                results.push((path, Err(message)));
+            }
+        }
+    }
+
+    let failed = results
+        .iter()
+        .filter(|(_, result)| !matches!(result, Ok((_, errors)) if errors.is_empty()))
+        .count();
+    if args.junit {
+        print!("{}", validate_junit(&results, &args));
+        return first_failure.map_or(Ok(()), Err);
+    }
+    if json_output {
+        let files: Vec<serde_json::Value> = results
+            .iter()
+            .map(|(path, result)| match result {
+                Ok((direction, errors)) => serde_json::json!({
+                    "file": path.display().to_string(),
+                    "direction": direction.as_str(),
+                    "valid": errors.is_empty(),
+                    "errors": errors,
+                }),
+                Err(message) => serde_json::json!({
+                    "file": path.display().to_string(),
+                    "valid": false,
+                    "error": message,
+                }),
+            })
+            .collect();
+        let output = serde_json::json!({
+            "valid": failed == 0,
+            "total": results.len(),
+            "passed": results.len() - failed,
+            "failed": failed,
+            "files": files,
+        });
+        println!("{}", output);
+        return first_failure.map_or(Ok(()), Err);
+    }
+
+    for (path, result) in &results {
+        match result {
+            Ok((_, errors)) if errors.is_empty() => {
+                println!("  \x1b[32m✓\x1b[0m {}", path.display());
+            }
+            Ok((_, errors)) => {
+                println!("  \x1b[31m✗\x1b[0m {}", path.display());
+                for error in errors {
+                    match &args.error_format {
+                        Some(format) => println!("    {}", format.render(error)),
+                        None => println!("    {}", error),
+                    }
+                }
+            }
+            Err(message) => {
+                println!("  \x1b[31m✗\x1b[0m {}", path.display());
+                println!("    \x1b[31merror\x1b[0m: {}", message);
+            }
+        }
+    }
+    println!();
+    if failed == 0 {
+        println!(
+            "\x1b[32m✓ {} payload(s) validated, all valid\x1b[0m",
+            results.len()
+        );
+    } else {
+        println!(
+            "\x1b[31m✗ {} payload(s) validated: {} valid, {} failed\x1b[0m",
+            results.len(),
+            results.len() - failed,
+            failed
+        );
+    }
+    first_failure.map_or(Ok(()), Err)
+}
+
+/// Render batch results as JUnit XML: one test suite, with a test case per
+/// payload classed by direction and operation.
+fn validate_junit(results: &[(&Path, PayloadOutcome)], args: &ValidateArgs) -> String {
+    let op = args.op.as_deref().unwrap_or_default();
+    let cases = results
+        .iter()
+        .map(|(path, result)| {
+            let (classname, outcome) = match result {
+                Ok((direction, errors)) => {
+                    let classname = format!("{}/{}", direction.as_str(), op);
+                    if errors.is_empty() {
+                        (classname, JunitOutcome::Passed)
+                    } else {
+                        let details: Vec<String> = errors
+                            .iter()
+                            .map(|error| match &args.error_format {
+                                Some(format) => format.render(error),
+                                None => error.to_string(),
+                            })
+                            .collect();
+                        let message = format!(
+                            "{} validation error{}",
+                            errors.len(),
+                            if errors.len() == 1 { "" } else { "s" }
+                        );
+                        (
+                            classname,
+                            JunitOutcome::Failure {
+                                message,
+                                details: details.join("\n"),
+                            },
+                        )
+                    }
+                }
+                Err(message) => (
+                    op.to_string(),
+                    JunitOutcome::Error {
+                        message: message.clone(),
+                    },
+                ),
+            };
+            JunitCase {
+                name: path.display().to_string(),
+                classname,
+                outcome,
+            }
+        })
+        .collect();
+    junit_xml(
+        "ucp-schema validate",
+        &[JunitSuite {
+            name: args
+                .schema
+                .clone()
+                .or_else(|| args.profile.clone())
+                .unwrap_or_default(),
+            cases,
+        }],
+    )
+}
+
+/// Validate each line of an NDJSON stream, printing one result per line:
+/// `{"line", "direction", "valid", "errors"}`, or `{"line", "valid": false,
+/// "error"}` for a record that isn't a JSON payload.
+fn run_validate_ndjson(args: ValidateArgs) -> Result<(), u8> {
+    use std::io::BufRead;
+
+    let source = match args.payloads.as_slice() {
+        [source] if !is_glob(source) => source,
+        _ => {
+            report_error(false, "--ndjson reads a single file (or - for stdin)");
+            return Err(2);
+        }
+    };
+    let shared = SharedSchema::load(&args, "--ndjson")?;
+    let reader: Box<dyn BufRead> = if source.as_os_str() == "-" {
+        Box::new(std::io::stdin().lock())
+    } else {
+        let file = std::fs::File::open(source).map_err(|e| {
+            report_error(false, &format!("cannot read {}: {}", source.display(), e));
+            3u8
+        })?;
+        Box::new(std::io::BufReader::new(file))
+    };
+    if args.verbose {
+        eprintln!("[validate] streaming records from {}", source.display());
+    }
+
+    let mut out = BufWriter::new(std::io::stdout().lock());
+    let (mut total, mut failed) = (0usize, 0usize);
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| {
+            report_error(false, &format!("cannot read {}: {}", source.display(), e));
+            3u8
+        })?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        total += 1;
+        let payload = serde_json::from_str(&line)
+            .map_err(|e| format!("invalid JSON: {}", e))
+            .and_then(|payload| {
+                prepare_payload(
+                    payload,
+                    args.unwrap_pointer.as_deref(),
+                    args.deref_payload,
+                    false,
+                )
+                .map_err(|(_, message)| message)
+            });
+        let result = match payload {
+            Ok(payload) => {
+                let (direction, errors) = shared.validate(&payload, &args)?;
+                serde_json::json!({
+                    "line": i + 1,
+                    "direction": direction.as_str(),
+                    "valid": errors.is_empty(),
+                    "errors": errors,
+                })
+            }
+            Err(message) => serde_json::json!({
+                "line": i + 1,
+                "valid": false,
+                "error": message,
+            }),
+        };
+        if result["valid"] == false {
+            failed += 1;
+        }
+        if writeln!(out, "{}", result).is_err() {
+            // Downstream closed the pipe (e.g. `| head`)
+            return Err(1);
+        }
+    }
+    if out.flush().is_err() {
+        return Err(1);
+    }
+
+    eprintln!("Validated {} record(s): {} failed", total, failed);
+    if failed > 0 {
+        return Err(1);
+    }
+    Ok(())
+}
+
+/// The --schema or --profile schema every payload of a batch or NDJSON run
+/// is validated against, resolved and compiled once per direction.
+struct SharedSchema {
+    schema: serde_json::Value,
+    /// Infer each payload's direction (--schema) instead of defaulting to
+    /// request (--profile payloads are raw objects)
+    infer_direction: bool,
+    /// Compiled on first use, shared by all threads: [request, response].
+    /// A schema that fails to compile keeps its exit code, reported once
+    compiled: [OnceLock<Result<CompiledValidator, u8>>; 2],
+}
+
+impl SharedSchema {
+    /// Load the schema `args` names; `mode` describes the run for the error
+    /// when there is none.
+    fn load(args: &ValidateArgs, mode: &str) -> Result<Self, u8> {
+        let json_output = args.json_output;
+        let schema = match (&args.profile, &args.schema) {
+            (Some(profile), _) => {
+                let config = SchemaBaseConfig {
+                    local_base: args.schema_local_base.as_deref(),
+                    remote_base: args.schema_remote_base.as_deref(),
+                    mappings: &args.map,
+                    fetch: fetch_options(),
+                    confine: false,
+                };
+                let mut compose_options = ComposeOptions::new().strict_version(args.strict_version);
+                if let Some(filter) = &args.capability_filter {
+                    compose_options = compose_options.capability_filter(filter.clone());
+                }
+                compose_profile_schema(
+                    profile,
+                    &config,
+                    &compose_options,
+                    args.warn_on_unused_schema_base,
+                    args.verbose,
+                    json_output,
+                )?
+            }
+            (None, Some(source)) => {
+                if args.verbose {
+                    eprintln!("[load] using explicit schema: {}", source);
+                }
+                load_explicit_schema(source, args.input_format, json_output)?
+            }
+            (None, None) => {
+                report_error(json_output, &format!("{} needs --schema or --profile (self-describing payloads each compose their own schema, so validate those one at a time).", mode));
+                return Err(2);
+            }
+        };
+        warn_stray_annotations(&schema);
+        Ok(Self {
+            schema,
+            infer_direction: args.profile.is_none(),
+            compiled: [OnceLock::new(), OnceLock::new()],
+        })
+    }
+
+    /// Validate `payload`, returning its direction and errors. Failing to
+    /// resolve or compile the schema is reported and ends the run.
+    fn validate(
+        &self,
+        payload: &serde_json::Value,
+        args: &ValidateArgs,
+    ) -> Result<(Direction, Vec<SchemaError>), u8> {
+        let inferred = match self.infer_direction {
+            true => detect_direction(payload).map(Direction::from),
+            false => None,
+        };
+        let direction = determine_direction(args.request, args.response, inferred)
+            .unwrap_or(Direction::Request);
+        let op = args
+            .op
+            .as_deref()
+            .expect("--op is required without --op-any");
+
+        let slot = &self.compiled[(direction == Direction::Response) as usize];
+        let validator = slot
+            .get_or_init(|| {
+                if args.verbose {
+                    eprintln!("[resolve] resolving for {}/{}", direction.as_str(), op);
+                }
+                let options = ResolveOptions::new(direction, op)
+                    .strict(args.strict)
+                    .reject_readonly(args.reject_readonly)
+                    .propagate_variants(args.propagate_variants);
+                CompiledValidator::new(&self.schema, &options)
+                    .map_err(|e| report_validate_error(args.json_output, e))
+            })
+            .as_ref()
+            .map_err(|code| *code)?;
+        match validator.validate(payload) {
+            Ok(()) => Ok((direction, Vec::new())),
+            Err(ValidateError::Invalid { errors, .. }) => Ok((direction, errors)),
+            Err(e) => Err(report_validate_error(args.json_output, e)),
+        }
+    }
+}
+
+/// Report a schema-level validation error, returning its exit code.
+fn report_validate_error(json_output: bool, e: ValidateError) -> u8 {
+    report_error(json_output, &e.to_string());
+    match e {
+        ValidateError::Resolve(e) => e.exit_code() as u8,
+        ValidateError::Invalid { .. } => 1,
+    }
+}
+
+/// Whether `path` has a `*` pattern in its file name.
+fn is_glob(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().contains('*'))
+}
+
+/// Expand file-name globs (`payloads/*.json`) into the matching files,
+/// sorted; other paths are kept as given.
+fn expand_payload_globs(paths: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+    let mut expanded = Vec::new();
+    for path in paths {
+        if !is_glob(path) {
+            expanded.push(path.clone());
+            continue;
+        }
+        let pattern = path.file_name().unwrap_or_default().to_string_lossy();
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let listed = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let entries = std::fs::read_dir(listed)
+            .map_err(|e| format!("cannot read {}: {}", listed.display(), e))?;
+        let mut matched: Vec<PathBuf> = entries
+            .flatten()
+            .filter(|entry| entry.path().is_file())
+            .filter(|entry| capability_matches(&pattern, &entry.file_name().to_string_lossy()))
+            .map(|entry| dir.join(entry.file_name()))
+            .collect();
+        if matched.is_empty() {
+            return Err(format!("no files match {}", path.display()));
+        }
+        matched.sort();
+        expanded.extend(matched);
+    }
+    Ok(expanded)
+}
+
+/// Read a payload to validate, strip the transport wrapper at
+/// `unwrap_pointer`, and expand its internal `$ref`s when `deref` is set.
+///
+/// Errors come with their exit code and message, for the caller to report.
+fn load_payload(
+    path: &Path,
+    unwrap_pointer: Option<&str>,
+    deref: bool,
+    input_format: InputFormat,
+    verbose: bool,
+) -> Result<serde_json::Value, (u8, String)> {
+    if verbose {
+        eprintln!("[load] reading payload {}", path.display());
+    }
+    let payload =
+        load_input(&path.to_string_lossy(), input_format, fetch_options()).map_err(|e| {
+            (
+                CliExitCode::exit_code(&e),
+                format!("loading payload: {}", e),
+            )
+        })?;
+    prepare_payload(payload, unwrap_pointer, deref, verbose)
+}
+
+/// Apply --unwrap-pointer and --deref-payload to a loaded payload.
+fn prepare_payload(
+    mut payload: serde_json::Value,
+    unwrap_pointer: Option<&str>,
+    deref: bool,
+    verbose: bool,
+) -> Result<serde_json::Value, (u8, String)> {
+    // Strip a transport wrapper so detection sees the UCP payload itself
+    if let Some(pointer) = unwrap_pointer {
+        if verbose {
+            eprintln!("[load] unwrapping payload at {}", pointer);
+        }
+        payload = payload
+            .pointer(pointer.trim_start_matches('#'))
+            .cloned()
+            .ok_or_else(|| {
+                (
+                    2,
+                    format!("--unwrap-pointer {} not found in payload", pointer),
+                )
+            })?;
+    }
+
+    if deref {
+        if verbose {
+            eprintln!("[load] expanding $ref pointers in the payload");
+        }
+        payload = ucp_schema::deref_payload(&payload).map_err(|e| {
+            (
+                CliExitCode::exit_code(&e),
+                format!("loading payload: {}", e),
+            )
+        })?;
+    }
+    Ok(payload)
+}
+
+/// Compose the capability schemas an agent profile advertises.
+fn compose_profile_schema(
+    profile: &str,
+    config: &SchemaBaseConfig,
+    compose_options: &ComposeOptions,
+    warn_on_unused_schema_base: bool,
+    verbose: bool,
+    json_output: bool,
+) -> Result<serde_json::Value, u8> {
+    let capabilities =
+        extract_capabilities_from_profile(profile, config).map_err(cli_err(json_output))?;
+    if warn_on_unused_schema_base {
+        warn_unmapped_capabilities(&capabilities, config);
+    }
+
+    if verbose {
+        eprintln!(
+            "[compose] composing {} capability schemas from profile",
+            capabilities.len()
+        );
+    }
+    let composed = compose_schema_with_options(&capabilities, config, compose_options)
+        .map_err(cli_err(json_output))?;
+    print_warnings(&composed.warnings);
+    if verbose {
+        note_skipped(&composed.skipped, "--capability-filter");
+    }
+    Ok(composed.schema)
+}
+
+/// Load a --schema source with its `$ref`s bundled (fetched when the source
+/// is a URL).
+fn load_explicit_schema(
+    source: &str,
+    input_format: InputFormat,
+    json_output: bool,
+) -> Result<serde_json::Value, u8> {
+    let mut schema = load_input(source, input_format, fetch_options())
+        .map_err(cli_err_ctx(json_output, "loading schema"))?;
+
+    #[cfg(feature = "remote")]
+    if is_url(source) {
+        bundle_refs_remote(&mut schema, source, fetch_options())
+            .map_err(cli_err_ctx(json_output, "bundling refs"))?;
+        return Ok(schema);
+    }
+    let schema_dir = Path::new(source).parent().unwrap_or(Path::new("."));
+    bundle_refs(&mut schema, schema_dir).map_err(cli_err_ctx(json_output, "bundling refs"))?;
+    Ok(schema)
+}
+
+/// Validate, then print the payload with per-field status markers.
+///
+/// Errors still go to stderr and set the exit code; stdout carries only the
+/// annotated payload.
+fn annotate_validation(
+    schema: &serde_json::Value,
+    payload: &serde_json::Value,
+    options: &ResolveOptions,
+) -> Result<(), u8> {
+    let resolved = resolve(schema, options).map_err(cli_err(false))?;
+    let errors = match validate_against_schema(&resolved, payload) {
+        Ok(()) => Vec::new(),
+        Err(ValidateError::Invalid { errors, .. }) => errors,
+        Err(ValidateError::Resolve(e)) => {
+            report_error(false, &e.to_string());
+            return Err(e.exit_code() as u8);
+        }
+    };
+
+    write_json_output(&annotate_payload(&resolved, payload, &errors), None, true)?;
+    if errors.is_empty() {
+        return Ok(());
+    }
+    eprintln!("Validation failed:");
+    for error in errors {
+        eprintln!("  {}", error);
+    }
+    Err(1)
+}
+
+/// Validate against each operation's resolved schema, passing if any matches.
+///
+/// Every operation is tried so all matches can be reported (`matched_ops`);
+/// returns the options of the first one.
+///
+/// On failure, errors are reported per attempted operation so callers can see
+/// why each candidate was rejected.
+fn validate_any_op(
+    schema: &serde_json::Value,
+    payload: &serde_json::Value,
+    ops: &[String],
+    make_options: impl Fn(&str) -> ResolveOptions,
+    json_output: bool,
+    error_format: Option<&ErrorFormat>,
+    verbose: bool,
+) -> Result<ResolveOptions, u8> {
+    let mut attempts = Vec::new();
+    let mut matched: Vec<ResolveOptions> = Vec::new();
+    for op in ops {
+        let options = make_options(op);
+        if verbose {
+            eprintln!("[validate] trying op {}", options.operation);
+        }
+        match validate(schema, payload, &options) {
+            Ok(()) => matched.push(options),
+            Err(ValidateError::Invalid { errors, .. }) => {
+                attempts.push((options.operation, errors))
+            }
+            Err(ValidateError::Resolve(e)) => {
+                report_error(json_output, &format!("op {}: {}", options.operation, e));
+                return Err(e.exit_code() as u8);
+            }
+        }
+    }
+
+    let matched_ops: Vec<&str> = matched.iter().map(|o| o.operation.as_str()).collect();
+    if !matched.is_empty() {
+        if json_output {
+            let output = serde_json::json!({
+                "valid": true,
+                "matched_op": matched_ops[0],
+                "matched_ops": matched_ops
+            });
+            println!("{}", output);
+        } else if !PROBLEM_JSON.load(Ordering::Relaxed) {
+            println!("Valid ({})", matched_ops.join(", "));
+        }
+        return Ok(matched.swap_remove(0));
+    }
+
+    let all_errors: Vec<SchemaError> = attempts
+        .iter()
+        .flat_map(|(_, errors)| errors.iter().cloned())
+        .collect();
+    record_problem("", &all_errors);
+
+    if json_output {
+        let per_op: Vec<_> = attempts
+            .iter()
+            .map(|(op, errs)| serde_json::json!({ "op": op, "errors": errs }))
+            .collect();
+        let output = serde_json::json!({
+            "valid": false,
+            "matched_op": null,
+            "matched_ops": [],
+            "errors": all_errors,
+            "attempts": per_op
+        });
+        println!("{}", output);
+    } else if let Some(format) = error_format {
+        for (_, errors) in &attempts {
+            for error in errors {
+                eprintln!("{}", format.render(error));
+            }
+        }
+    } else {
+        eprintln!("Validation failed for every operation:");
+        for (op, errors) in &attempts {
+            eprintln!("  [{}]", op);
+            for error in errors {
+                eprintln!("    {}", error);
+            }
+        }
+    }
+    Err(1)
+}
+
+/// Warn about each error strict mode reports for a payload that passed leniently.
+///
+/// Resolves the same operation again with `strict` on, so the findings are
+/// exactly the undeclared fields the lenient pass let through.
+fn warn_strict_delta(
+    schema: &serde_json::Value,
+    payload: &serde_json::Value,
+    options: &ResolveOptions,
+    verbose: bool,
+) {
+    if options.strict {
+        return;
+    }
+    if verbose {
+        eprintln!("[validate] re-validating strictly for --show-strict-delta");
+    }
+    if let Err(ValidateError::Invalid { errors, .. }) =
+        validate(schema, payload, &options.clone().strict(true))
+    {
+        for error in errors {
+            warn(format!("strict mode would reject: {}", error));
+        }
+    }
+}
+
+/// A `{placeholder}` template; `{{` and `}}` are literal braces.
+#[derive(Debug, Clone)]
+struct Template(Vec<TemplatePart>);
+
+#[derive(Debug, Clone)]
+enum TemplatePart {
+    Literal(String),
+    Placeholder(&'static str),
+}
+
+impl Template {
+    /// Parse a template, accepting only the given placeholder names.
+    fn parse(template: &str, placeholders: &[&'static str]) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => {
+                                return Err(format!(
+                                    "unclosed placeholder {{{} (use '{{{{' for a literal brace)",
+                                    name
+                                ))
+                            }
+                        }
+                    }
+                    let Some(&placeholder) = placeholders.iter().find(|p| **p == name) else {
+                        let expected: Vec<String> =
+                            placeholders.iter().map(|p| format!("{{{}}}", p)).collect();
+                        let (last, rest) = expected.split_last().expect("placeholders");
+                        return Err(format!(
+                            "unknown placeholder {{{}}} (expected {} or {})",
+                            name,
+                            rest.join(", "),
+                            last
+                        ));
+                    };
+                    if !literal.is_empty() {
+                        parts.push(TemplatePart::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(TemplatePart::Placeholder(placeholder));
+                }
+                '}' => return Err("unmatched '}' (use '}}' for a literal brace)".to_string()),
+                _ => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(TemplatePart::Literal(literal));
+        }
+        Ok(Self(parts))
+    }
+
+    fn render<'a>(&'a self, value: impl Fn(&str) -> &'a str) -> String {
+        self.0
+            .iter()
+            .map(|part| match part {
+                TemplatePart::Literal(s) => s.as_str(),
+                TemplatePart::Placeholder(name) => value(name),
+            })
+            .collect()
+    }
+}
+
+/// User-supplied layout for validation error lines (`--error-format`).
+#[derive(Debug, Clone)]
+struct ErrorFormat(Template);
+
+impl ErrorFormat {
+    fn parse(template: &str) -> Result<Self, String> {
+        Template::parse(template, &["path", "schemaPath", "keyword", "message"]).map(Self)
+    }
+
+    fn render(&self, error: &SchemaError) -> String {
+        self.0.render(|name| match name {
+            "path" => error.path.as_str(),
+            "schemaPath" => error.schema_path.as_str(),
+            "keyword" => error.keyword.as_str(),
+            _ => error.message.as_str(),
+        })
+    }
+}
+
+/// Output file names for batch resolve (`--name-template`).
+#[derive(Debug, Clone)]
+struct NameTemplate(Template);
+
+impl NameTemplate {
+    const DEFAULT: &'static str = "{name}-{op}-{direction}.json";
+
+    fn parse(template: &str) -> Result<Self, String> {
+        Template::parse(template, &["name", "op", "direction"]).map(Self)
+    }
+
+    fn render(&self, name: &str, op: &str, direction: Direction) -> String {
+        let direction = direction.as_str();
+        self.0.render(|placeholder| match placeholder {
+            "name" => name,
+            "op" => op,
+            _ => direction,
+        })
+    }
+}
+
+/// Shared helper: serialize JSON and write to output or stdout.
+fn write_json_output(
+    value: &serde_json::Value,
+    output: Option<PathBuf>,
+    pretty: bool,
+) -> Result<(), u8> {
+    // Serialize straight into the destination: no intermediate String, so a
+    // large schema isn't held in memory twice
+    let result = match &output {
+        Some(path) => std::fs::File::create(path)
+            .map_err(serde_json::Error::io)
+            .and_then(|file| write_json(BufWriter::new(file), value, pretty, false)),
+        None => write_json(
+            BufWriter::new(std::io::stdout().lock()),
+            value,
+            pretty,
+            true,
+        ),
+    };
+
+    result.map_err(|e| {
+        if !e.is_io() {
+            eprintln!("Error serializing output: {}", e);
+            return 2u8;
+        }
+        match &output {
+            Some(path) => eprintln!("Error writing to {}: {}", path.display(), e),
+            None => eprintln!("Error writing output: {}", e),
+        }
+        3u8
+    })
+}
+
+/// Serialize `value` to `writer`, optionally followed by a newline.
+fn write_json(
+    mut writer: impl Write,
+    value: &serde_json::Value,
+    pretty: bool,
+    newline: bool,
+) -> serde_json::Result<()> {
+    if pretty {
+        serde_json::to_writer_pretty(&mut writer, value)?;
+    } else {
+        serde_json::to_writer(&mut writer, value)?;
+    }
+    if newline {
+        writer.write_all(b"\n").map_err(serde_json::Error::io)?;
+    }
+    writer.flush().map_err(serde_json::Error::io)
+}
+
+/// Print capability details to stderr for --verbose mode.
+/// Best-effort: silently skips if extraction fails (errors surface later).
+fn verbose_capabilities(payload: &serde_json::Value, config: &SchemaBaseConfig) {
+    if let Ok(caps) = extract_capabilities(payload, config) {
+        let roots: Vec<_> = caps.iter().filter(|c| c.extends.is_none()).collect();
+        let exts: Vec<_> = caps.iter().filter(|c| c.extends.is_some()).collect();
+        eprintln!(
+            "[detect] payload with {} capabilities ({} root, {} extensions)",
+            caps.len(),
+            roots.len(),
+            exts.len()
+        );
+        for cap in &caps {
+            let kind = if cap.extends.is_some() { "ext" } else { "root" };
+            eprintln!("[detect]   {} {} → {}", kind, cap.name, cap.schema_url);
+        }
+    }
+}
+
+/// Print non-fatal library warnings to stderr.
+/// Note capabilities composition left out on purpose (not warnings).
+fn note_skipped(skipped: &[String], reason: &str) {
+    for name in skipped {
+        eprintln!("Note: skipping extension {} ({})", name, reason);
+    }
+}
+
+fn print_warnings(warnings: &[String]) {
+    for warning in warnings {
+        warn(warning);
+    }
+}
+
+/// Warn about annotations resolution will ignore (outside property schemas).
+fn warn_stray_annotations(schema: &serde_json::Value) {
+    for path in find_stray_annotations(schema) {
+        warn(format!(
+            "{} has no effect (annotations only apply to property schemas)",
+            path
+        ));
+    }
+}
+
+/// Warn about capability schema URLs not covered by --schema-remote-base.
+///
+/// Unmatched URLs fall back to host-stripped path mapping, which silently
+/// ignores the configured base — usually a typo or a stale base.
+fn warn_unmapped_capabilities(capabilities: &[Capability], config: &SchemaBaseConfig) {
+    let Some(remote_base) = config.remote_base else {
+        return;
+    };
+    for cap in capabilities {
+        if !config.matches_remote_base(&cap.schema_url) {
+            warn(format!(
+                "schema URL {} does not start with remote base {}; check --schema-remote-base",
+                cap.schema_url, remote_base
+            ));
+        }
+    }
+}
+
+/// Like warn_unmapped_capabilities, extracting capabilities from a payload.
+/// Best-effort: silently skips if extraction fails (errors surface later).
+fn warn_unmapped_payload_capabilities(payload: &serde_json::Value, config: &SchemaBaseConfig) {
+    if let Ok(caps) = extract_capabilities(payload, config) {
+        warn_unmapped_capabilities(&caps, config);
+    }
+}
+
+/// Bundle refs for a local schema file.
+fn bundle_local_refs(
+    schema: &mut serde_json::Value,
+    source: &str,
+    schema_local_base: &Option<PathBuf>,
+    schema_remote_base: &Option<String>,
+    json_output: bool,
+) -> Result<(), u8> {
+    let schema_dir = Path::new(source).parent().unwrap_or(Path::new("."));
+
+    if let (Some(local_base), Some(remote_base)) = (schema_local_base, schema_remote_base) {
+        bundle_refs_with_url_mapping(schema, schema_dir, local_base, remote_base)
+            .map_err(cli_err_ctx(json_output, "bundling refs"))?;
+    } else {
+        bundle_refs(schema, schema_dir).map_err(cli_err_ctx(json_output, "bundling refs"))?;
+    }
+
+    Ok(())
+}
+
+/// Output an error message in plain text or JSON format.
+///
+/// Uses same shape as validation errors for consistent API:
+/// `{"valid": false, "errors": [{"path": "", "message": "..."}]}`
+fn report_error(json_output: bool, msg: &str) {
+    record_problem(msg, &[]);
+    if json_output {
+        let output = serde_json::json!({
+            "valid": false,
+            "errors": [{"path": "", "message": msg}]
+        });
+        println!("{}", output);
+    } else {
+        eprintln!("Error: {}", msg);
+    }
+}
+
+/// Print clap's completion script for `shell`, hooked to complete `--op`
+/// values through `__complete-ops`.
+fn run_completions(shell: Shell) -> Result<(), u8> {
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut Cli::command(), "ucp-schema", &mut script);
+    let mut script = String::from_utf8_lossy(&script).into_owned();
+    match shell {
+        Shell::Bash => script.push_str(BASH_OPS_HOOK),
+        Shell::Zsh => script.push_str(ZSH_OPS_HOOK),
+        Shell::Fish => script.push_str(FISH_OPS_HOOK),
+        // One native completer per command, so the hook goes inside clap's
+        Shell::PowerShell => {
+            let param = "param($wordToComplete, $commandAst, $cursorPosition)\n";
+            if let Some(at) = script.find(param) {
+                script.insert_str(at + param.len(), POWERSHELL_OPS_HOOK);
+            }
+        }
+        _ => {}
+    }
+    std::io::stdout()
+        .lock()
+        .write_all(script.as_bytes())
+        .map_err(|e| {
+            eprintln!("Error writing output: {}", e);
+            3u8
+        })
+}
+
+// Each hook asks `__complete-ops` for the operations after `--op`, falling
+// back to the generated completion when it prints nothing.
+
+const BASH_OPS_HOOK: &str = r#"
+_ucp_schema_ops() {
+    if [[ "${COMP_WORDS[COMP_CWORD-1]}" == "--op" ]]; then
+        local ops
+        ops="$(ucp-schema __complete-ops "${COMP_WORDS[@]:1:COMP_CWORD-1}" 2>/dev/null)"
+        if [[ -n "$ops" ]]; then
+            COMPREPLY=($(compgen -W "$ops" -- "${COMP_WORDS[COMP_CWORD]}"))
+            return 0
+        fi
+    fi
+    _ucp-schema "$@"
+}
+complete -F _ucp_schema_ops -o bashdefault -o default ucp-schema
+"#;
+
+const ZSH_OPS_HOOK: &str = r#"
+_ucp_schema_ops() {
+    if [[ "${words[CURRENT-1]}" == "--op" ]]; then
+        local -a ops
+        ops=(${(f)"$(ucp-schema __complete-ops ${words[2,CURRENT-1]} 2>/dev/null)"})
+        if (( ${#ops} )); then
+            compadd -a ops
+            return
+        fi
+    fi
+    _ucp-schema "$@"
+}
+compdef _ucp_schema_ops ucp-schema
+"#;
+
+const FISH_OPS_HOOK: &str = r#"
+complete -c ucp-schema -l op -x -a '(ucp-schema __complete-ops (commandline -opc)[2..-1] 2>/dev/null)'
+"#;
+
+const POWERSHELL_OPS_HOOK: &str = r#"
+    $ucpWords = @($commandAst.CommandElements |
+        Where-Object { $_.Extent.EndOffset -lt $cursorPosition } |
+        ForEach-Object { $_.ToString() })
+    if ($ucpWords.Count -gt 1 -and $ucpWords[-1] -eq '--op') {
+        $ucpArgs = @($ucpWords | Select-Object -Skip 1)
+        $ops = @(& ucp-schema __complete-ops @ucpArgs 2>$null)
+        if ($ops.Count -gt 0) {
+            return $ops | Where-Object { $_ -like "$wordToComplete*" } | ForEach-Object {
+                [CompletionResult]::new($_, $_, [CompletionResultType]::ParameterValue, $_)
+            }
+        }
+    }
+"#;
+
+/// Print the operations of the schema a partial command line names, one
+/// per line: the value of `--schema`, or else the first argument that is a
+/// schema file. Prints nothing when there isn't one (completion falls back
+/// to static values), and never fetches over the network.
+fn complete_ops(words: &[String]) {
+    let flagged = words
+        .iter()
+        .position(|w| w == "--schema")
+        .and_then(|i| words.get(i + 1));
+    let candidates = flagged.into_iter().chain(
+        words
+            .iter()
+            .skip(1)
+            .filter(|w| !w.starts_with('-') && Path::new(w.as_str()).is_file()),
+    );
+    for candidate in candidates {
+        if is_url(candidate) {
+            continue;
+        }
+        let Ok(schema) = load_input(candidate, InputFormat::Auto, fetch_options()) else {
+            continue;
+        };
+        if detect_direction(&schema).is_some() {
+            continue;
+        }
+        for op in declared_operations(&schema) {
+            println!("{}", op);
+        }
+        return;
+    }
+}
+
+fn run_version(json_output: bool) {
+    let name = env!("CARGO_PKG_NAME");
+    let version = env!("CARGO_PKG_VERSION");
+    let git_sha = env!("UCP_SCHEMA_GIT_SHA");
+    let built = env!("UCP_SCHEMA_BUILT");
+
+    if json_output {
+        let output = serde_json::json!({
+            "name": name,
+            "version": version,
+            "git_sha": git_sha,
+            "built": built
+        });
+        println!("{}", output);
+    } else {
+        println!("{} {} ({} {})", name, version, git_sha, built);
+    }
+}
+
+struct ConvertArgs {
+    schemas: Vec<String>,
+    from: Option<String>,
+    resource: Option<String>,
+    ops: Vec<String>,
+    title: Option<String>,
+    api_version: String,
+    bundle: bool,
+    strict: bool,
+    output: Option<PathBuf>,
+    pretty: bool,
+    verbose: bool,
+    input_format: InputFormat,
+}
+
+/// Resolve each capability schema for every operation and direction and emit
+/// the results as OpenAPI 3.1 `components.schemas`, or with `--from`, import
+/// an OpenAPI resource as one annotated schema.
+fn run_convert(args: ConvertArgs) -> Result<(), u8> {
+    let ConvertArgs {
+        schemas: sources,
+        from,
+        resource,
+        ops,
+        title,
+        api_version,
+        bundle,
+        strict,
+        output,
+        pretty,
+        verbose,
+        input_format,
+    } = args;
+
+    if from.is_some() {
+        let [spec_source] = sources.as_slice() else {
+            report_error(false, "--from openapi takes exactly one spec");
+            return Err(2);
+        };
+        if verbose {
+            eprintln!("[load] reading {}", spec_source);
+        }
+        let spec = load_input(spec_source, input_format, fetch_options())
+            .map_err(cli_err_ctx(false, "loading OpenAPI spec"))?;
+        let mut imported = import_openapi(&spec, resource.as_deref()).map_err(cli_err(false))?;
+        print_warnings(&imported.warnings);
+        if verbose {
+            eprintln!(
+                "[import] {}: {}",
+                imported.resource,
+                imported.operations.join(", ")
+            );
+        }
+        if let (Some(title), Some(schema)) = (title, imported.schema.as_object_mut()) {
+            schema.insert("title".to_string(), serde_json::Value::String(title));
+        }
+        return write_json_output(&imported.schema, output, pretty);
+    }
+
+    let mut schemas = Vec::new();
+    for source in &sources {
+        if verbose {
+            eprintln!("[load] reading {}", source);
+        }
+        let mut schema = load_schema_auto(source, fetch_options()).map_err(cli_err(false))?;
+        if bundle {
+            if verbose {
+                eprintln!("[bundle] inlining $ref pointers");
+            }
+            let base_dir = Path::new(source).parent().unwrap_or(Path::new("."));
+            bundle_refs(&mut schema, base_dir).map_err(cli_err_ctx(false, "bundling refs"))?;
+        }
+        warn_stray_annotations(&schema);
+        let name = type_base_name(None, &schema, source);
+        schemas.push((name, schema));
+    }
+
+    let title = title.unwrap_or_else(|| "UCP Schemas".to_string());
+    let options = OpenApiOptions::new(title, ops)
+        .version(api_version)
+        .strict(strict);
     if verbose {
         eprintln!(
-            "[resolve] resolving for {}/{}{}",
-            direction
-                .annotation_key()
-                .strip_prefix("ucp_")
-                .unwrap_or(direction.annotation_key()),
-            op,
+            "[resolve] resolving {} schema(s) for {} (request + response){}",
+            schemas.len(),
+            options.operations.join(", "),
             if strict { " (strict)" } else { "" }
         );
     }
-    let resolved = resolve(&schema, &options).map_err(cli_err(false))?;
+    let document = openapi_document(&schemas, &options).map_err(cli_err(false))?;
+    write_json_output(&document, output, pretty)
+}
+
+struct ServeArgs {
+    schema_local_base: Option<PathBuf>,
+    schema_remote_base: Option<String>,
+    map: Vec<UrlMapping>,
+    allow_remote_schemas: bool,
+    cache_size: usize,
+    workers: usize,
+    timeout: Duration,
+    host: String,
+    port: u16,
+    verbose: bool,
+}
+
+/// Serve validation over HTTP until the process is stopped.
+fn run_serve(args: ServeArgs) -> Result<(), u8> {
+    let ServeArgs {
+        schema_local_base,
+        schema_remote_base,
+        map,
+        allow_remote_schemas,
+        cache_size,
+        workers,
+        timeout,
+        host,
+        port,
+        verbose,
+    } = args;
+
+    let mut config = ServerConfig::new()
+        .mappings(map)
+        .allow_remote_schemas(allow_remote_schemas)
+        .fetch(fetch_options().clone())
+        .cache_size(cache_size)
+        .workers(workers)
+        .timeout(timeout)
+        .verbose(verbose);
+    if let Some(base) = schema_local_base {
+        config = config.local_base(base);
+    }
+    if let Some(base) = schema_remote_base {
+        config = config.remote_base(base);
+    }
+
+    let listener = TcpListener::bind((host.as_str(), port)).map_err(|e| {
+        report_error(false, &format!("cannot listen on {}:{}: {}", host, port, e));
+        3u8
+    })?;
+    if let Ok(addr) = listener.local_addr() {
+        eprintln!("Listening on http://{}", addr);
+    }
+    Arc::new(Server::new(config)).serve(listener).map_err(|e| {
+        report_error(false, &format!("accepting connections: {}", e));
+        3u8
+    })
+}
+
+struct WatchArgs {
+    path: PathBuf,
+    debounce: Duration,
+    interval: Duration,
+    command: Vec<String>,
+    no_network: bool,
+}
+
+/// Subcommands `watch` can re-run.
+const WATCHABLE: &[&str] = &["resolve", "lint", "validate"];
+
+/// Poll `path` and re-run the command after each settled change, printing its
+/// output only when it differs from the previous run.
+fn run_watch(args: WatchArgs) -> Result<(), u8> {
+    let WatchArgs {
+        path,
+        debounce,
+        interval,
+        command,
+        no_network,
+    } = args;
+
+    if !WATCHABLE.contains(&command[0].as_str()) {
+        report_error(
+            false,
+            &format!(
+                "watch runs one of: {} (got {})",
+                WATCHABLE.join(", "),
+                command[0]
+            ),
+        );
+        return Err(2);
+    }
+    if !path.exists() {
+        report_error(
+            false,
+            &format!("cannot watch {}: not found", path.display()),
+        );
+        return Err(3);
+    }
+    let exe = std::env::current_exe().map_err(|e| {
+        report_error(
+            false,
+            &format!("cannot locate ucp-schema executable: {}", e),
+        );
+        3u8
+    })?;
+
+    let mut snapshot = watch_snapshot(&path);
+    let mut last: Option<std::process::Output> = None;
+    let mut changed: Vec<PathBuf> = Vec::new();
+    loop {
+        let mut child = std::process::Command::new(&exe);
+        child.args(&command);
+        if no_network {
+            child.env(NO_NETWORK_ENV, "1");
+        }
+        let output = child.output().map_err(|e| {
+            report_error(false, &format!("running {}: {}", command[0], e));
+            3u8
+        })?;
+        let same = last
+            .as_ref()
+            .is_some_and(|prev| prev.stdout == output.stdout && prev.stderr == output.stderr);
+        if !same {
+            let code = output
+                .status
+                .code()
+                .map_or("signal".to_string(), |c| c.to_string());
+            if changed.is_empty() {
+                eprintln!("[watch] {} (exit {})", command.join(" "), code);
+            } else {
+                let names: Vec<String> = changed.iter().map(|p| p.display().to_string()).collect();
+                eprintln!(
+                    "[watch] {} changed; {} (exit {})",
+                    names.join(", "),
+                    command.join(" "),
+                    code
+                );
+            }
+            let _ = std::io::stdout().write_all(&output.stdout);
+            let _ = std::io::stdout().flush();
+            let _ = std::io::stderr().write_all(&output.stderr);
+        }
+        last = Some(output);
+
+        // Wait for a change, then for the tree to stay still for `debounce`
+        let mut current = snapshot.clone();
+        while current == snapshot {
+            std::thread::sleep(interval);
+            current = watch_snapshot(&path);
+        }
+        let mut settled = Instant::now();
+        loop {
+            std::thread::sleep(interval.min(debounce));
+            let next = watch_snapshot(&path);
+            if next != current {
+                current = next;
+                settled = Instant::now();
+            } else if settled.elapsed() >= debounce {
+                break;
+            }
+        }
+        changed = current
+            .iter()
+            .filter(|(p, stamp)| snapshot.get(*p) != Some(*stamp))
+            .map(|(p, _)| p.clone())
+            .chain(
+                snapshot
+                    .keys()
+                    .filter(|p| !current.contains_key(*p))
+                    .cloned(),
+            )
+            .collect();
+        snapshot = current;
+    }
+}
+
+/// Modification time and size of every non-hidden file under `path`.
+fn watch_snapshot(path: &Path) -> BTreeMap<PathBuf, (Option<SystemTime>, u64)> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(next) = pending.pop() {
+        let Ok(meta) = std::fs::metadata(&next) else {
+            continue;
+        };
+        if meta.is_dir() {
+            let Ok(entries) = std::fs::read_dir(&next) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                if !entry.file_name().to_string_lossy().starts_with('.') {
+                    pending.push(entry.path());
+                }
+            }
+        } else {
+            files.insert(next, (meta.modified().ok(), meta.len()));
+        }
+    }
+    files
+}
+
+/// Load a schema, composing it first when `source` is a self-describing
+/// payload, and inline its $refs with `bundle`. Also returns the payload's
+/// direction.
+fn load_schema_or_payload(
+    source: &str,
+    config: &SchemaBaseConfig,
+    bundle: bool,
+    verbose: bool,
+    input_format: InputFormat,
+) -> Result<(serde_json::Value, Option<DetectedDirection>), u8> {
+    if verbose {
+        eprintln!("[load] reading {}", source);
+    }
+    let input = load_input(source, input_format, fetch_options()).map_err(cli_err(false))?;
+    let detected = detect_direction(&input);
+
+    let mut schema = match detected {
+        Some(_) => {
+            if verbose {
+                eprintln!("[compose] composing schemas from payload capabilities");
+            }
+            let composed =
+                compose_from_payload_with_options(&input, config, &ComposeOptions::new())
+                    .map_err(cli_err(false))?;
+            print_warnings(&composed.warnings);
+            composed.schema
+        }
+        None if config.local_base.is_some() || !config.mappings.is_empty() => {
+            report_error(false, "--schema-local-base/--schema-remote-base/--map only apply to payload input. Remove these flags, or pass a self-describing payload instead of a schema file.");
+            return Err(2);
+        }
+        None => input,
+    };
+
+    if bundle {
+        if verbose {
+            eprintln!("[bundle] inlining $ref pointers");
+        }
+        let base_dir = match detected {
+            Some(_) => config
+                .local_base
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| PathBuf::from(".")),
+            None => Path::new(source)
+                .parent()
+                .unwrap_or(Path::new("."))
+                .to_path_buf(),
+        };
+        bundle_refs(&mut schema, &base_dir).map_err(cli_err_ctx(false, "bundling refs"))?;
+    }
+
+    Ok((schema, detected))
+}
+
+struct QueryArgs {
+    schema: String,
+    path: String,
+    op: Option<String>,
+    request: bool,
+    response: bool,
+    bundle: bool,
+    schema_local_base: Option<PathBuf>,
+    schema_remote_base: Option<String>,
+    map: Vec<UrlMapping>,
+    raw: bool,
+    pretty: bool,
+    verbose: bool,
+    input_format: InputFormat,
+}
+
+/// Print the value at a JSON Pointer of the schema the pipeline produces:
+/// composed (for payloads), then bundled with --bundle, then resolved with
+/// --op.
+fn run_query(args: QueryArgs) -> Result<(), u8> {
+    let config = SchemaBaseConfig {
+        local_base: args.schema_local_base.as_deref(),
+        remote_base: args.schema_remote_base.as_deref(),
+        mappings: &args.map,
+        fetch: fetch_options(),
+        confine: false,
+    };
+    let (mut schema, detected) = load_schema_or_payload(
+        &args.schema,
+        &config,
+        args.bundle,
+        args.verbose,
+        args.input_format,
+    )?;
+
+    if let Some(op) = &args.op {
+        let direction =
+            determine_direction(args.request, args.response, detected.map(Direction::from))
+                .ok_or_else(|| {
+                    report_error(
+                        false,
+                        "--request or --response is required for schema input",
+                    );
+                    2u8
+                })?;
+        if args.verbose {
+            eprintln!("[resolve] resolving for {}/{}", direction.as_str(), op);
+        }
+        schema = resolve(&schema, &ResolveOptions::new(direction, op)).map_err(cli_err(false))?;
+    }
+
+    let pointer = args.path.trim_start_matches('#');
+    let Some(value) = schema.pointer(pointer) else {
+        report_error(false, &format!("path {} not found", args.path));
+        return Err(2);
+    };
+    match value {
+        serde_json::Value::String(s) if args.raw => {
+            println!("{}", s);
+            Ok(())
+        }
+        _ => write_json_output(value, None, args.pretty),
+    }
+}
+
+struct ExploreArgs {
+    schema: String,
+    request: bool,
+    response: bool,
+    op: String,
+    bundle: bool,
+    schema_local_base: Option<PathBuf>,
+    schema_remote_base: Option<String>,
+    map: Vec<UrlMapping>,
+    input_format: InputFormat,
+}
+
+/// Run explorer commands read from stdin, prompting when it's a terminal.
+fn run_explore(args: ExploreArgs) -> Result<(), u8> {
+    if args.schema == "-" {
+        report_error(
+            false,
+            "explore reads its commands from stdin; pass the schema as a file or URL",
+        );
+        return Err(2);
+    }
+    let config = SchemaBaseConfig {
+        local_base: args.schema_local_base.as_deref(),
+        remote_base: args.schema_remote_base.as_deref(),
+        mappings: &args.map,
+        fetch: fetch_options(),
+        confine: false,
+    };
+    let (schema, detected) =
+        load_schema_or_payload(&args.schema, &config, args.bundle, false, args.input_format)?;
+    let direction = determine_direction(args.request, args.response, detected.map(Direction::from))
+        .unwrap_or(Direction::Request);
+
+    let mut explorer = Explorer::new(schema, direction, &args.op);
+    let stdin = std::io::stdin();
+    let interactive = stdin.is_terminal();
+    let mut first = Some("ls".to_string());
+    let mut lines = stdin.lines();
+    loop {
+        let line = match first.take() {
+            Some(line) => line,
+            None => {
+                if interactive {
+                    print!("{}", explorer.prompt());
+                    let _ = std::io::stdout().flush();
+                }
+                match lines.next() {
+                    Some(Ok(line)) => line,
+                    Some(Err(e)) => {
+                        report_error(false, &format!("cannot read stdin: {}", e));
+                        return Err(3);
+                    }
+                    None => break,
+                }
+            }
+        };
+        let command = line.trim();
+        if command.is_empty() {
+            continue;
+        }
+        if matches!(command, "quit" | "exit" | "q") {
+            break;
+        }
+        match explorer.run(command) {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => println!("{}", output),
+            Err(message) => eprintln!("{}", message),
+        }
+    }
+    Ok(())
+}
+
+struct ScrubArgs {
+    payload: PathBuf,
+    schema: String,
+    direction: Direction,
+    op: String,
+    bundle: bool,
+    output: Option<PathBuf>,
+    pretty: bool,
+    verbose: bool,
+    input_format: InputFormat,
+}
+
+/// Write the payload with the fields its resolved schema doesn't declare
+/// removed.
+fn run_scrub(args: ScrubArgs) -> Result<(), u8> {
+    let ScrubArgs {
+        payload: payload_path,
+        schema: source,
+        direction,
+        op,
+        bundle,
+        output,
+        pretty,
+        verbose,
+        input_format,
+    } = args;
+
+    if verbose {
+        eprintln!("[load] reading schema {}", source);
+    }
+    let mut schema = load_input(&source, input_format, fetch_options())
+        .map_err(cli_err_ctx(false, "loading schema"))?;
+    if bundle {
+        if verbose {
+            eprintln!("[bundle] inlining $ref pointers");
+        }
+        let base_dir = Path::new(&source).parent().unwrap_or(Path::new("."));
+        bundle_refs(&mut schema, base_dir).map_err(cli_err_ctx(false, "bundling refs"))?;
+    }
+    if verbose {
+        eprintln!("[load] reading payload {}", payload_path.display());
+    }
+    let payload = load_input(
+        &payload_path.to_string_lossy(),
+        input_format,
+        fetch_options(),
+    )
+    .map_err(cli_err_ctx(false, "loading payload"))?;
+
+    let options = ResolveOptions::new(direction, op);
+    if verbose {
+        eprintln!(
+            "[resolve] resolving for {}/{}",
+            direction.as_str(),
+            options.operation
+        );
+    }
+    let resolved = resolve(&schema, &options).map_err(cli_err(false))?;
+    let scrubbed = scrub_payload(&resolved, &payload);
+    if verbose {
+        for path in &scrubbed.removed {
+            eprintln!("[scrub] removed {}", path);
+        }
+    }
+    write_json_output(&scrubbed.payload, output, pretty)
+}
+
+struct RedactArgs {
+    payload: PathBuf,
+    schema: String,
+    mode: RedactMode,
+    bundle: bool,
+    output: Option<PathBuf>,
+    pretty: bool,
+    verbose: bool,
+    input_format: InputFormat,
+}
+
+/// Write the payload with its ucp_sensitive values masked or removed.
+fn run_redact(args: RedactArgs) -> Result<(), u8> {
+    let RedactArgs {
+        payload: payload_path,
+        schema: source,
+        mode,
+        bundle,
+        output,
+        pretty,
+        verbose,
+        input_format,
+    } = args;
+
+    if verbose {
+        eprintln!("[load] reading schema {}", source);
+    }
+    let mut schema = load_input(&source, input_format, fetch_options())
+        .map_err(cli_err_ctx(false, "loading schema"))?;
+    if bundle {
+        if verbose {
+            eprintln!("[bundle] inlining $ref pointers");
+        }
+        let base_dir = Path::new(&source).parent().unwrap_or(Path::new("."));
+        bundle_refs(&mut schema, base_dir).map_err(cli_err_ctx(false, "bundling refs"))?;
+    }
+    if verbose {
+        eprintln!("[load] reading payload {}", payload_path.display());
+    }
+    let payload = load_input(
+        &payload_path.to_string_lossy(),
+        input_format,
+        fetch_options(),
+    )
+    .map_err(cli_err_ctx(false, "loading payload"))?;
+
+    let redacted = redact_payload(&schema, &payload, &mode).map_err(cli_err(false))?;
+    if verbose {
+        for path in &redacted.redacted {
+            eprintln!("[redact] redacted {}", path);
+        }
+    }
+    write_json_output(&redacted.payload, output, pretty)
+}
+
+struct ExplainArgs {
+    payload: PathBuf,
+    schema: String,
+    direction: Direction,
+    op: String,
+    bundle: bool,
+    json_output: bool,
+    verbose: bool,
+    input_format: InputFormat,
+}
+
+/// Validate a payload and trace each error back through the schema.
+/// Exits 1 when the payload is invalid.
+fn run_explain(args: ExplainArgs) -> Result<(), u8> {
+    let ExplainArgs {
+        payload: payload_path,
+        schema: source,
+        direction,
+        op,
+        bundle,
+        json_output,
+        verbose,
+        input_format,
+    } = args;
+
+    if verbose {
+        eprintln!("[load] reading schema {}", source);
+    }
+    let mut schema = load_input(&source, input_format, fetch_options())
+        .map_err(cli_err_ctx(json_output, "loading schema"))?;
+    if bundle {
+        if verbose {
+            eprintln!("[bundle] inlining $ref pointers");
+        }
+        let base_dir = Path::new(&source).parent().unwrap_or(Path::new("."));
+        bundle_refs(&mut schema, base_dir).map_err(cli_err_ctx(json_output, "bundling refs"))?;
+    }
+    if verbose {
+        eprintln!("[load] reading payload {}", payload_path.display());
+    }
+    let payload = load_input(
+        &payload_path.to_string_lossy(),
+        input_format,
+        fetch_options(),
+    )
+    .map_err(cli_err_ctx(json_output, "loading payload"))?;
+
+    let options = ResolveOptions::new(direction, op);
+    let explanations =
+        explain_validation(&schema, &payload, &options).map_err(cli_err(json_output))?;
+
+    if json_output {
+        let output = serde_json::json!({
+            "valid": explanations.is_empty(),
+            "errors": explanations,
+        });
+        println!("{}", serde_json::to_string_pretty(&output).unwrap());
+    } else if explanations.is_empty() {
+        println!("Valid for {}/{}", direction.as_str(), options.operation);
+    } else {
+        let or_root = |path: &str| {
+            if path.is_empty() {
+                "(root)".to_string()
+            } else {
+                path.to_string()
+            }
+        };
+        for (i, explanation) in explanations.iter().enumerate() {
+            let error = &explanation.error;
+            println!("{}. {}: {}", i + 1, or_root(&error.path), error.message);
+            println!(
+                "   keyword:    {} at {}",
+                error.keyword,
+                or_root(&error.schema_path)
+            );
+            if let Some(location) = &explanation.location {
+                println!("   schema:     {}", or_root(location));
+            }
+            if let Some(fragment) = &explanation.fragment {
+                println!("   fragment:   {}", truncate_json(fragment, 300));
+            }
+            if let Some(branch) = &explanation.branch {
+                match &branch.title {
+                    Some(title) => println!("   allOf:      {} ({})", branch.path, title),
+                    None => println!("   allOf:      {}", branch.path),
+                }
+            }
+            for cause in &explanation.annotations {
+                println!(
+                    "   annotation: {} {} by {} {} at {}",
+                    cause.property,
+                    if cause.visibility == "omit" {
+                        "omitted"
+                    } else {
+                        "required"
+                    },
+                    cause.key,
+                    cause.annotation,
+                    cause.path
+                );
+            }
+            println!();
+        }
+        println!(
+            "{} error(s) for {}/{}",
+            explanations.len(),
+            direction.as_str(),
+            options.operation
+        );
+    }
 
-    write_json_output(&resolved, output, pretty)
+    if explanations.is_empty() {
+        Ok(())
+    } else {
+        Err(1)
+    }
 }
 
-/// Pure composition: merge capability schemas from a self-describing payload.
-/// Output preserves UCP annotations (no resolve step).
-fn run_compose(
-    payload_path: &Path,
-    schema_local_base: Option<PathBuf>,
-    schema_remote_base: Option<String>,
+/// Compact JSON, cut to `max` characters.
+fn truncate_json(value: &serde_json::Value, max: usize) -> String {
+    let json = value.to_string();
+    match json.char_indices().nth(max) {
+        Some((end, _)) => format!("{}…", &json[..end]),
+        None => json,
+    }
+}
+
+struct CoverageArgs {
+    schema: String,
+    payloads: Vec<PathBuf>,
+    direction: Direction,
+    op: String,
+    bundle: bool,
+    json_output: bool,
+    verbose: bool,
+    input_format: InputFormat,
+}
+
+/// Report how much of the resolved schema the payloads exercise.
+fn run_coverage(args: CoverageArgs) -> Result<(), u8> {
+    let CoverageArgs {
+        schema: source,
+        payloads: payload_paths,
+        direction,
+        op,
+        bundle,
+        json_output,
+        verbose,
+        input_format,
+    } = args;
+
+    if verbose {
+        eprintln!("[load] reading schema {}", source);
+    }
+    let mut schema = load_input(&source, input_format, fetch_options())
+        .map_err(cli_err_ctx(json_output, "loading schema"))?;
+    if bundle {
+        if verbose {
+            eprintln!("[bundle] inlining $ref pointers");
+        }
+        let base_dir = Path::new(&source).parent().unwrap_or(Path::new("."));
+        bundle_refs(&mut schema, base_dir).map_err(cli_err_ctx(json_output, "bundling refs"))?;
+    }
+
+    let mut payloads = Vec::with_capacity(payload_paths.len());
+    for path in &payload_paths {
+        if verbose {
+            eprintln!("[load] reading payload {}", path.display());
+        }
+        let payload = load_input(&path.to_string_lossy(), input_format, fetch_options()).map_err(
+            cli_err_ctx(json_output, &format!("loading payload {}", path.display())),
+        )?;
+        payloads.push(payload);
+    }
+
+    let options = ResolveOptions::new(direction, op);
+    let coverage = schema_coverage(&schema, &payloads, &options).map_err(cli_err(json_output))?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&coverage).unwrap());
+        return Ok(());
+    }
+
+    println!(
+        "Coverage of {} for {}/{} by {} payload(s)\n",
+        source,
+        direction.as_str(),
+        coverage.operation,
+        coverage.payloads
+    );
+    for (kind, label) in [
+        (CoverageKind::Property, "properties"),
+        (CoverageKind::EnumValue, "enum values"),
+        (CoverageKind::OneOfBranch, "oneOf branches"),
+    ] {
+        let (covered, total) = coverage.summary(kind);
+        let percent = match total {
+            0 => "-".to_string(),
+            _ => format!("{:.1}%", covered as f64 * 100.0 / total as f64),
+        };
+        println!("  {:<16}{:>5}/{:<5}{:>7}", label, covered, total, percent);
+    }
+
+    let mut uncovered = coverage.uncovered().peekable();
+    if uncovered.peek().is_some() {
+        println!("\nNot exercised:");
+    }
+    for entry in uncovered {
+        let kind = match entry.kind {
+            CoverageKind::Property => "property",
+            CoverageKind::EnumValue => "enum value",
+            CoverageKind::OneOfBranch => "oneOf branch",
+        };
+        match &entry.value {
+            Some(value) => println!("  {:<14}{} ({})", kind, entry.path, value),
+            None => println!("  {:<14}{}", kind, entry.path),
+        }
+    }
+    Ok(())
+}
+
+struct InspectArgs {
+    schema: String,
+    ops: Vec<String>,
+    bundle: bool,
+    json_output: bool,
+    verbose: bool,
+    input_format: InputFormat,
+}
+
+/// Print the annotation matrix: property × operation for each direction.
+fn run_inspect(args: InspectArgs) -> Result<(), u8> {
+    let InspectArgs {
+        schema: source,
+        ops,
+        bundle,
+        json_output,
+        verbose,
+        input_format,
+    } = args;
+
+    if verbose {
+        eprintln!("[load] reading schema {}", source);
+    }
+    let mut schema = load_input(&source, input_format, fetch_options())
+        .map_err(cli_err_ctx(json_output, "loading schema"))?;
+    if bundle {
+        if verbose {
+            eprintln!("[bundle] inlining $ref pointers");
+        }
+        let base_dir = Path::new(&source).parent().unwrap_or(Path::new("."));
+        bundle_refs(&mut schema, base_dir).map_err(cli_err_ctx(json_output, "bundling refs"))?;
+    }
+
+    let ops: Vec<String> = match ops.is_empty() {
+        true => declared_operations(&schema),
+        false => ops.iter().map(|op| op.to_lowercase()).collect(),
+    };
+    if verbose {
+        eprintln!("[inspect] operations: {}", ops.join(", "));
+    }
+    let matrix = annotation_matrix(&schema, &ops).map_err(cli_err(json_output))?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&matrix).unwrap());
+        return Ok(());
+    }
+    if matrix.properties.is_empty() {
+        println!("No properties in {}", source);
+        return Ok(());
+    }
+
+    let name_width = matrix
+        .properties
+        .iter()
+        .map(|row| row.name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("property".len());
+    for (i, direction) in [Direction::Request, Direction::Response]
+        .into_iter()
+        .enumerate()
+    {
+        let rows: Vec<(&str, Vec<String>)> = matrix
+            .properties
+            .iter()
+            .map(|row| {
+                let cells = row.cells(direction).iter().map(|cell| {
+                    let value = match &cell.transition {
+                        Some(t) => format!("{}→{}", t.from, t.to),
+                        None => cell.visibility.clone(),
+                    };
+                    match cell.annotated {
+                        true => value,
+                        false => format!("({})", value),
+                    }
+                });
+                (row.name.as_str(), cells.collect())
+            })
+            .collect();
+        let widths: Vec<usize> = ops
+            .iter()
+            .enumerate()
+            .map(|(col, op)| {
+                rows.iter()
+                    .map(|(_, cells)| cells[col].chars().count())
+                    .chain([op.chars().count()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        if i > 0 {
+            println!();
+        }
+        println!("{}:", direction.as_str());
+        let pad = |s: &str, width: usize| format!("{}{}", s, " ".repeat(width - s.chars().count()));
+        let mut header = format!("  {}", pad("property", name_width));
+        for (op, width) in ops.iter().zip(&widths) {
+            header.push_str(&format!("  {}", pad(op, *width)));
+        }
+        println!("{}", header.trim_end());
+        for (name, cells) in &rows {
+            let mut line = format!("  {}", pad(name, name_width));
+            for (cell, width) in cells.iter().zip(&widths) {
+                line.push_str(&format!("  {}", pad(cell, *width)));
+            }
+            println!("{}", line.trim_end());
+        }
+    }
+    println!("\n(...) not annotated: follows the schema's required list");
+    Ok(())
+}
+
+struct FuzzArgs {
+    schema: String,
+    examples: Vec<PathBuf>,
+    direction: Direction,
+    op: String,
+    count: usize,
+    validate: bool,
+    bundle: bool,
+    json_output: bool,
     output: Option<PathBuf>,
-    pretty: bool,
     verbose: bool,
-) -> Result<(), u8> {
+    input_format: InputFormat,
+}
+
+/// Write near-miss mutations of the examples, or with `validate` report
+/// which ones the schema accepts.
+fn run_fuzz(args: FuzzArgs) -> Result<(), u8> {
+    let FuzzArgs {
+        schema: source,
+        examples: example_paths,
+        direction,
+        op,
+        count,
+        validate,
+        bundle,
+        json_output,
+        output,
+        verbose,
+        input_format,
+    } = args;
+
     if verbose {
-        eprintln!("[load] reading {}", payload_path.display());
+        eprintln!("[load] reading schema {}", source);
+    }
+    let mut schema = load_input(&source, input_format, fetch_options())
+        .map_err(cli_err_ctx(json_output, "loading schema"))?;
+    if bundle {
+        if verbose {
+            eprintln!("[bundle] inlining $ref pointers");
+        }
+        let base_dir = Path::new(&source).parent().unwrap_or(Path::new("."));
+        bundle_refs(&mut schema, base_dir).map_err(cli_err_ctx(json_output, "bundling refs"))?;
     }
-    let payload = load_schema(payload_path).map_err(cli_err_ctx(false, "loading payload"))?;
 
-    // Verify input is a self-describing payload
-    if detect_direction(&payload).is_none() {
-        report_error(false, "input is not a self-describing payload (missing ucp.capabilities or meta.profile). Use `resolve` for schema files.");
+    let mut examples = Vec::new();
+    let mut labels = Vec::new();
+    for path in &example_paths {
+        if verbose {
+            eprintln!("[load] reading example {}", path.display());
+        }
+        let example = load_input(&path.to_string_lossy(), input_format, fetch_options()).map_err(
+            cli_err_ctx(json_output, &format!("loading example {}", path.display())),
+        )?;
+        examples.push(example);
+        labels.push(path.display().to_string());
+    }
+    if let Some(serde_json::Value::Array(embedded)) = schema.get("examples") {
+        for (i, example) in embedded.iter().enumerate() {
+            examples.push(example.clone());
+            labels.push(format!("{}#/examples/{}", source, i));
+        }
+    }
+    if examples.is_empty() {
+        report_error(
+            json_output,
+            "no examples to mutate: pass --example or add root `examples` to the schema",
+        );
         return Err(2);
     }
 
-    let config = SchemaBaseConfig {
-        local_base: schema_local_base.as_deref(),
-        remote_base: schema_remote_base.as_deref(),
-    };
+    let options = ResolveOptions::new(direction, op);
+    let run = fuzz_payloads(&schema, &examples, &options, count).map_err(cli_err(json_output))?;
+    for &i in &run.invalid_examples {
+        warn(format!(
+            "example {} is not valid for {}/{}; skipped",
+            labels[i],
+            direction.as_str(),
+            options.operation
+        ));
+    }
     if verbose {
-        verbose_capabilities(&payload, &config);
-        eprintln!("[compose] composing schemas (annotations preserved)");
+        eprintln!("[fuzz] {} mutation(s)", run.mutations.len());
+    }
+
+    if !validate {
+        let payloads: Vec<&serde_json::Value> = run.mutations.iter().map(|m| &m.payload).collect();
+        return write_json_output(&serde_json::json!(payloads), output, true);
+    }
+
+    let missed = run.missed().count();
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&run).unwrap());
+    } else {
+        for mutation in &run.mutations {
+            let status = if mutation.caught {
+                "\x1b[32m✓\x1b[0m"
+            } else {
+                "\x1b[31m✗ accepted\x1b[0m"
+            };
+            let path = if mutation.path.is_empty() {
+                "(root)"
+            } else {
+                mutation.path.as_str()
+            };
+            println!("  {} {}: {}", status, path, mutation.description);
+        }
+        println!(
+            "\n{} of {} mutation(s) rejected for {}/{}",
+            run.mutations.len() - missed,
+            run.mutations.len(),
+            direction.as_str(),
+            options.operation
+        );
+    }
+    if missed > 0 {
+        return Err(1);
+    }
+    Ok(())
+}
+
+/// Run every schema test suite under `path`; exit 1 if any case fails.
+fn run_test(path: &Path, format: &str, quiet: bool) -> Result<(), u8> {
+    if !path.exists() {
+        eprintln!("Error: path not found: {}", path.display());
+        return Err(2);
+    }
+
+    let report = run_schema_tests(path);
+    if report.files_checked == 0 {
+        warn(format!(
+            "no {} blocks or *{} files under {}",
+            TESTS_KEY,
+            TESTS_SUFFIX,
+            path.display()
+        ));
+    }
+
+    if format == "json" {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else if format == "junit" {
+        print!("{}", report.to_junit());
+    } else {
+        if !quiet {
+            println!("Testing {} ...\n", path.display());
+        }
+
+        for file in &report.results {
+            let ok = file.failed == 0 && file.error.is_none();
+            if quiet && ok {
+                continue;
+            }
+            let icon = if ok {
+                "\x1b[32m✓\x1b[0m"
+            } else {
+                "\x1b[31m✗\x1b[0m"
+            };
+            println!(
+                "  {} {} ({} passed, {} failed)",
+                icon,
+                file.file.display(),
+                file.passed,
+                file.failed
+            );
+            if let Some(error) = &file.error {
+                println!("    \x1b[31merror\x1b[0m: {}", error);
+            }
+            for case in file.cases.iter().filter(|c| !c.passed) {
+                println!("    \x1b[31m✗\x1b[0m {}", case.name);
+                if let Some(message) = &case.message {
+                    println!("        {}", message);
+                }
+                for error in &case.errors {
+                    println!("        {}: {}", error.path, error.message);
+                }
+            }
+        }
+
+        println!();
+        let total = report.passed + report.failed;
+        if report.is_ok() {
+            println!(
+                "\x1b[32m✓ {} tests in {} files, all passed\x1b[0m",
+                total, report.files_checked
+            );
+        } else {
+            println!(
+                "\x1b[31m✗ {} tests in {} files: {} passed, {} failed ({} files with errors)\x1b[0m",
+                total, report.files_checked, report.passed, report.failed, report.errors
+            );
+        }
+    }
+
+    if report.is_ok() {
+        Ok(())
+    } else {
+        Err(1)
+    }
+}
+
+/// Write the dependency graph of the schema files under `path`.
+fn run_graph(path: &Path, format: &str, output: Option<PathBuf>) -> Result<(), u8> {
+    if !path.exists() {
+        report_error(false, &format!("{} not found", path.display()));
+        return Err(3);
+    }
+    let graph = schema_graph(path);
+    let rendered = match format {
+        "mermaid" => graph.to_mermaid(),
+        _ => graph.to_dot(),
+    };
+    match output {
+        Some(file) => std::fs::write(&file, rendered).map_err(|e| {
+            report_error(false, &format!("writing {}: {}", file.display(), e));
+            3
+        }),
+        None => {
+            print!("{}", rendered);
+            Ok(())
+        }
+    }
+}
+
+struct MigrateArgs {
+    schema: PathBuf,
+    apply: bool,
+    output: Option<PathBuf>,
+    verbose: bool,
+    input_format: InputFormat,
+}
+
+/// Show the changes completing every schema transition would make, and with
+/// `--apply` write the migrated schema.
+fn run_migrate(args: MigrateArgs) -> Result<(), u8> {
+    let MigrateArgs {
+        schema: schema_path,
+        apply,
+        output,
+        verbose,
+        input_format,
+    } = args;
+
+    let schema = load_input(
+        &schema_path.to_string_lossy(),
+        input_format,
+        fetch_options(),
+    )
+    .map_err(cli_err_ctx(false, "loading schema"))?;
+    let migrated = migrate_transitions(&schema).map_err(cli_err(false))?;
+
+    for change in &migrated.applied {
+        println!("  {}", change.path);
+        println!("-   {}", change.before);
+        println!("+   {}", serde_json::Value::String(change.to.clone()));
+        if verbose {
+            println!("    ({})", change.description);
+        }
+    }
+    print_warnings(&migrated.skipped);
+
+    let count = migrated.applied.len();
+    if count == 0 {
+        println!("No schema transitions to apply");
+        return Ok(());
+    }
+    if !apply {
+        println!(
+            "{} transition(s) to apply; re-run with --apply to write",
+            count
+        );
+        return Ok(());
+    }
+
+    let target = output.unwrap_or(schema_path);
+    write_json_output(&migrated.schema, Some(target.clone()), true)?;
+    println!("Applied {} transition(s) to {}", count, target.display());
+    Ok(())
+}
+
+/// Rewrite the annotations of every schema file under `path` into the
+/// current syntax.
+fn run_upgrade(path: &Path, dry_run: bool, verbose: bool) -> Result<(), u8> {
+    if !path.exists() {
+        eprintln!("Error: path not found: {}", path.display());
+        return Err(2);
+    }
+
+    let results = upgrade_files(path, !dry_run);
+    let mut annotations = 0;
+    let mut files = 0;
+    let mut failed = 0;
+    for result in &results {
+        let file = result.file.display();
+        if let Some(error) = &result.error {
+            failed += 1;
+            eprintln!("Error: {}: {}", file, error);
+            continue;
+        }
+        if result.changes.is_empty() {
+            continue;
+        }
+        files += 1;
+        annotations += result.changes.len();
+        let verb = if dry_run { "Would upgrade" } else { "Upgraded" };
+        println!("{} {} ({} annotation(s))", verb, file, result.changes.len());
+        if verbose {
+            for change in &result.changes {
+                println!("  {}", change.path);
+                println!("-   {}", change.before);
+                println!("+   {}", change.after);
+            }
+        }
+    }
+
+    if dry_run && annotations > 0 {
+        println!(
+            "{} annotation(s) in {} file(s) to upgrade; re-run without --dry-run to write",
+            annotations, files
+        );
+    } else {
+        println!(
+            "{} annotation(s) in {} file(s) upgraded, {} file(s) already current",
+            annotations,
+            files,
+            results.len() - files - failed
+        );
+    }
+    if failed > 0 {
+        return Err(1);
+    }
+    Ok(())
+}
+
+/// Write the canonical form of a schema.
+fn run_normalize(
+    source: &str,
+    output: Option<PathBuf>,
+    pretty: bool,
+    input_format: InputFormat,
+) -> Result<(), u8> {
+    let schema = load_input(source, input_format, fetch_options())
+        .map_err(cli_err_ctx(false, "loading schema"))?;
+    write_json_output(&normalize_schema(&schema), output, pretty)
+}
+
+/// Write a new capability project into `dir`, which must be absent or empty.
+fn run_init(name: &str, dir: Option<PathBuf>, namespace: &str) -> Result<(), u8> {
+    let valid_name = name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid_name {
+        report_error(
+            false,
+            &format!(
+                "invalid capability name {:?}: use letters, digits, - and _, starting with a letter",
+                name
+            ),
+        );
+        return Err(2);
+    }
+
+    let dir = dir.unwrap_or_else(|| PathBuf::from(name));
+    let occupied = std::fs::read_dir(&dir).is_ok_and(|mut entries| entries.next().is_some());
+    if occupied {
+        report_error(
+            false,
+            &format!("{} already exists and is not empty", dir.display()),
+        );
+        return Err(2);
+    }
+
+    for file in scaffold(name, namespace) {
+        let path = dir.join(&file.path);
+        let written = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::write(&path, &file.content));
+        if let Err(e) = written {
+            report_error(false, &format!("writing {}: {}", path.display(), e));
+            return Err(3);
+        }
+        println!("  created {}", path.display());
     }
-    let schema = compose_from_payload(&payload, &config).map_err(cli_err(false))?;
-
-    write_json_output(&schema, output, pretty)
+    println!(
+        "Scaffolded {}.{} in {}",
+        namespace,
+        name.replace('-', "_"),
+        dir.display()
+    );
+    Ok(())
 }
 
-struct ValidateArgs {
+struct DocsArgs {
     payload: PathBuf,
-    schema: Option<String>,
     schema_local_base: Option<PathBuf>,
     schema_remote_base: Option<String>,
-    profile: Option<String>,
-    request: bool,
-    response: bool,
-    op: String,
-    json_output: bool,
-    strict: bool,
+    map: Vec<UrlMapping>,
+    output_dir: PathBuf,
     verbose: bool,
+    input_format: InputFormat,
 }
 
-fn run_validate(args: ValidateArgs) -> Result<(), u8> {
-    let ValidateArgs {
+/// Compose each capability with the capabilities it extends and write an HTML
+/// page for it, plus an index.
+fn run_docs(args: DocsArgs) -> Result<(), u8> {
+    let DocsArgs {
         payload: payload_path,
-        schema: schema_source,
         schema_local_base,
         schema_remote_base,
-        profile: profile_url,
-        request,
-        response,
-        op,
-        json_output,
-        strict,
+        map,
+        output_dir,
         verbose,
+        input_format,
     } = args;
 
-    // Flag validation: --schema-local-base/--schema-remote-base don't apply with
-    // explicit --schema (composition is bypassed, so these would silently do nothing)
-    if schema_source.is_some() && (schema_local_base.is_some() || schema_remote_base.is_some()) {
-        report_error(json_output, "--schema-local-base/--schema-remote-base do not apply with explicit --schema (composition is bypassed). Remove these flags, or remove --schema to use self-describing mode.");
-        return Err(2);
+    if verbose {
+        eprintln!("[load] reading {}", payload_path.display());
     }
-
+    let payload = load_input(
+        &payload_path.to_string_lossy(),
+        input_format,
+        fetch_options(),
+    )
+    .map_err(cli_err_ctx(false, "loading payload"))?;
     let config = SchemaBaseConfig {
         local_base: schema_local_base.as_deref(),
         remote_base: schema_remote_base.as_deref(),
+        mappings: &map,
+        fetch: fetch_options(),
+        confine: false,
     };
+    let capabilities = extract_capabilities(&payload, &config).map_err(cli_err(false))?;
 
-    // Load payload file
-    if verbose {
-        eprintln!("[load] reading payload {}", payload_path.display());
-    }
-    let payload_file =
-        load_schema(&payload_path).map_err(cli_err_ctx(json_output, "loading payload"))?;
-
-    // Determine validation mode and extract actual payload to validate:
-    // 1. --profile: REST pattern, payload is raw object
-    // 2. --schema: explicit schema, payload is raw object
-    // 3. JSONRPC: meta.profile in payload, extract nested payload
-    // 4. Response: ucp.capabilities in payload, payload is self-describing
-    let (schema, payload, direction) = if let Some(ref profile) = profile_url {
-        // REST pattern: --profile flag provides profile URL, payload is raw
+    let mut docs = Vec::new();
+    for cap in &capabilities {
         if verbose {
-            eprintln!("[detect] REST pattern: using --profile {}", profile);
+            eprintln!("[compose] composing {} with its parents", cap.name);
         }
-        let direction = determine_direction(request, response, None).unwrap_or(Direction::Request);
-
-        let capabilities =
-            extract_capabilities_from_profile(profile, &config).map_err(cli_err(json_output))?;
+        let filter = Regex::new(&format!("^{}$", regex::escape(&cap.name)))
+            .expect("escaped capability name is a valid regex");
+        let options = ComposeOptions::new().capability_filter(filter);
+        let composed = compose_schema_with_options(&capabilities, &config, &options)
+            .map_err(cli_err(false))?;
+        print_warnings(&composed.warnings);
+        docs.push(CapabilityDoc {
+            capability: cap.clone(),
+            schema: composed.schema,
+        });
+    }
 
+    let pages = html_site(&docs).map_err(cli_err(false))?;
+    std::fs::create_dir_all(&output_dir).map_err(|e| {
+        eprintln!("Error creating {}: {}", output_dir.display(), e);
+        3u8
+    })?;
+    for page in &pages {
+        let path = output_dir.join(&page.path);
         if verbose {
-            eprintln!(
-                "[compose] composing {} capability schemas from profile",
-                capabilities.len()
-            );
+            eprintln!("[docs] writing {}", path.display());
         }
-        let schema = compose_schema(&capabilities, &config).map_err(cli_err(json_output))?;
+        std::fs::write(&path, &page.content).map_err(|e| {
+            eprintln!("Error writing to {}: {}", path.display(), e);
+            3u8
+        })?;
+    }
+    Ok(())
+}
 
-        (schema, payload_file, direction)
-    } else if let Some(ref source) = schema_source {
-        // Explicit schema: try to infer direction from payload
-        if verbose {
-            eprintln!("[load] using explicit schema: {}", source);
-        }
-        let inferred = detect_direction(&payload_file).map(Direction::from);
-        let direction =
-            determine_direction(request, response, inferred).unwrap_or(Direction::Request);
+struct DiffArgs {
+    old: String,
+    new: String,
+    direction: Direction,
+    op: String,
+    bundle: bool,
+    strict: bool,
+    json_output: bool,
+    verbose: bool,
+    input_format: InputFormat,
+}
 
-        let mut schema =
-            load_schema_auto(source).map_err(cli_err_ctx(json_output, "loading schema"))?;
+/// Resolve both schemas for the same direction/op and report what changed.
+/// Exits 1 when any change is breaking, so CI can gate on it.
+fn run_diff(args: DiffArgs) -> Result<(), u8> {
+    let DiffArgs {
+        old,
+        new,
+        direction,
+        op,
+        bundle,
+        strict,
+        json_output,
+        verbose,
+        input_format,
+    } = args;
 
-        // Bundle refs based on source type and available mappings
-        #[cfg(feature = "remote")]
-        {
-            if is_url(source) {
-                bundle_refs_remote(&mut schema, source)
-                    .map_err(cli_err_ctx(json_output, "bundling refs"))?;
-            } else {
-                bundle_local_refs(
-                    &mut schema,
-                    source,
-                    &schema_local_base,
-                    &schema_remote_base,
-                    json_output,
-                )?;
-            }
-        }
-        #[cfg(not(feature = "remote"))]
-        {
-            bundle_local_refs(
-                &mut schema,
-                source,
-                &schema_local_base,
-                &schema_remote_base,
-                json_output,
-            )?;
+    let load = |source: &str, label: &str| -> Result<serde_json::Value, u8> {
+        if verbose {
+            eprintln!("[load] reading {} schema {}", label, source);
         }
-
-        (schema, payload_file, direction)
-    } else {
-        // Self-describing mode - detect from payload structure
-        match detect_direction(&payload_file) {
-            Some(DetectedDirection::Response) => {
-                // Response: ucp.capabilities, compose and validate full payload
-                if verbose {
-                    verbose_capabilities(&payload_file, &config);
-                    eprintln!("[compose] composing schemas from payload capabilities");
-                }
-                let direction = determine_direction(request, response, Some(Direction::Response))
-                    .unwrap_or(Direction::Response);
-                let schema =
-                    compose_from_payload(&payload_file, &config).map_err(cli_err(json_output))?;
-                (schema, payload_file, direction)
-            }
-            Some(DetectedDirection::Request) => {
-                // JSONRPC request: meta.profile, extract nested payload
-                let direction = determine_direction(request, response, Some(Direction::Request))
-                    .unwrap_or(Direction::Request);
-
-                // Get profile URL from meta.profile
-                let profile = payload_file
-                    .get("meta")
-                    .and_then(|m| m.get("profile"))
-                    .and_then(|p| p.as_str())
-                    .ok_or_else(|| {
-                        report_error(json_output, "JSONRPC request missing meta.profile");
-                        2u8
-                    })?;
-
-                if verbose {
-                    eprintln!("[detect] JSONRPC request: fetching profile {}", profile);
-                }
-
-                let capabilities = extract_capabilities_from_profile(profile, &config)
-                    .map_err(cli_err(json_output))?;
-
-                // Extract actual payload from envelope (e.g., "checkout" key)
-                let (nested_payload, _key) = extract_jsonrpc_payload(&payload_file, &capabilities)
-                    .map_err(cli_err(json_output))?;
-
-                if verbose {
-                    eprintln!(
-                        "[compose] composing {} capability schemas from profile",
-                        capabilities.len()
-                    );
-                }
-                let schema =
-                    compose_schema(&capabilities, &config).map_err(cli_err(json_output))?;
-
-                (schema, nested_payload.clone(), direction)
-            }
-            None => {
-                report_error(
-                    json_output,
-                    "cannot infer direction: payload has no ucp.capabilities (response) or meta.profile (request). Use --schema, --profile, --request, or --response.",
-                );
-                return Err(2);
+        let mut schema = load_input(source, input_format, fetch_options()).map_err(cli_err_ctx(
+            json_output,
+            &format!("loading {} schema", label),
+        ))?;
+        if bundle {
+            if verbose {
+                eprintln!("[bundle] inlining $ref pointers in {} schema", label);
             }
+            let base_dir = Path::new(source).parent().unwrap_or(Path::new("."));
+            bundle_refs(&mut schema, base_dir)
+                .map_err(cli_err_ctx(json_output, "bundling refs"))?;
         }
+        Ok(schema)
     };
+    let old_schema = load(&old, "old")?;
+    let new_schema = load(&new, "new")?;
 
     let options = ResolveOptions::new(direction, op).strict(strict);
     if verbose {
         eprintln!(
-            "[resolve] resolving for {}/{}",
-            direction
-                .annotation_key()
-                .strip_prefix("ucp_")
-                .unwrap_or(direction.annotation_key()),
+            "[diff] comparing {}/{}",
+            direction.as_str(),
             options.operation
         );
-        eprintln!("[validate] validating payload against resolved schema");
     }
+    let diff = diff_schemas(&old_schema, &new_schema, &options).map_err(cli_err(json_output))?;
 
-    match validate(&schema, &payload, &options) {
-        Ok(()) => {
-            if json_output {
-                println!(r#"{{"valid":true}}"#);
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&diff).unwrap());
+    } else if diff.changes.is_empty() {
+        println!("No changes for {}/{}", direction.as_str(), diff.operation);
+    } else {
+        for change in &diff.changes {
+            let class = if change.breaking {
+                "breaking"
             } else {
-                println!("Valid");
-            }
-            Ok(())
-        }
-        Err(ValidateError::Invalid { errors, .. }) => {
-            if json_output {
-                let output = serde_json::json!({
-                    "valid": false,
-                    "errors": errors
-                });
-                println!("{}", output);
+                "non-breaking"
+            };
+            let path = if change.path.is_empty() {
+                "(root)"
             } else {
-                eprintln!("Validation failed:");
-                for error in errors {
-                    eprintln!("  {}", error);
-                }
-            }
-            Err(1)
-        }
-        Err(ValidateError::Resolve(e)) => {
-            report_error(json_output, &e.to_string());
-            Err(e.exit_code() as u8)
+                &change.path
+            };
+            println!("{:<13} {}: {}", class, path, change.kind);
         }
+        println!(
+            "\n{} change(s) for {}/{}: {} breaking, {} non-breaking",
+            diff.changes.len(),
+            direction.as_str(),
+            diff.operation,
+            diff.breaking,
+            diff.non_breaking
+        );
     }
-}
 
-/// Shared helper: serialize JSON and write to output or stdout.
-fn write_json_output(
-    value: &serde_json::Value,
-    output: Option<PathBuf>,
-    pretty: bool,
-) -> Result<(), u8> {
-    let json = if pretty {
-        serde_json::to_string_pretty(value)
+    if diff.has_breaking() {
+        Err(1)
     } else {
-        serde_json::to_string(value)
+        Ok(())
     }
-    .map_err(|e| {
-        eprintln!("Error serializing output: {}", e);
-        2u8
-    })?;
+}
 
-    match output {
-        Some(path) => {
-            std::fs::write(&path, &json).map_err(|e| {
-                eprintln!("Error writing to {}: {}", path.display(), e);
-                3u8
-            })?;
-        }
-        None => {
-            println!("{}", json);
-        }
+/// Format schema files in place, or with `check` report the ones that
+/// need it.
+fn run_stats(path: &Path, json_output: bool) -> Result<(), u8> {
+    if !path.exists() {
+        report_error(json_output, &format!("path not found: {}", path.display()));
+        return Err(2);
     }
 
-    Ok(())
-}
+    let results = file_stats(path);
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&results).unwrap());
+        return if failed > 0 { Err(1) } else { Ok(()) };
+    }
 
-/// Print capability details to stderr for --verbose mode.
-/// Best-effort: silently skips if extraction fails (errors surface later).
-fn verbose_capabilities(payload: &serde_json::Value, config: &SchemaBaseConfig) {
-    if let Ok(caps) = extract_capabilities(payload, config) {
-        let roots: Vec<_> = caps.iter().filter(|c| c.extends.is_none()).collect();
-        let exts: Vec<_> = caps.iter().filter(|c| c.extends.is_some()).collect();
-        eprintln!(
-            "[detect] payload with {} capabilities ({} root, {} extensions)",
-            caps.len(),
-            roots.len(),
-            exts.len()
+    for (i, result) in results.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{}", result.file.display());
+        let Some(stats) = &result.stats else {
+            println!("  error: {}", result.error.as_deref().unwrap_or_default());
+            continue;
+        };
+        println!("  properties   {}", stats.properties);
+        println!("  max depth    {}", stats.max_depth);
+        println!(
+            "  $refs        {} local, {} remote",
+            stats.local_refs, stats.remote_refs
         );
-        for cap in &caps {
-            let kind = if cap.extends.is_some() { "ext" } else { "root" };
-            eprintln!("[detect]   {} {} → {}", kind, cap.name, cap.schema_url);
+        println!(
+            "  annotations  {} request, {} response",
+            stats.request_annotations, stats.response_annotations
+        );
+        println!("  resolved size (bytes):");
+        let width = stats
+            .resolved
+            .iter()
+            .map(|r| r.operation.len())
+            .max()
+            .unwrap_or(0);
+        for size in &stats.resolved {
+            println!(
+                "    {:<width$}  request {:>7}  response {:>7}",
+                size.operation,
+                size.request,
+                size.response,
+                width = width
+            );
         }
     }
+    println!(
+        "\n{} file(s) measured, {} failed",
+        results.len() - failed,
+        failed
+    );
+    if failed > 0 {
+        return Err(1);
+    }
+    Ok(())
 }
 
-/// Bundle refs for a local schema file.
-fn bundle_local_refs(
-    schema: &mut serde_json::Value,
-    source: &str,
-    schema_local_base: &Option<PathBuf>,
-    schema_remote_base: &Option<String>,
-    json_output: bool,
-) -> Result<(), u8> {
-    let schema_dir = Path::new(source).parent().unwrap_or(Path::new("."));
-
-    if let (Some(local_base), Some(remote_base)) = (schema_local_base, schema_remote_base) {
-        bundle_refs_with_url_mapping(schema, schema_dir, local_base, remote_base)
-            .map_err(cli_err_ctx(json_output, "bundling refs"))?;
-    } else {
-        bundle_refs(schema, schema_dir).map_err(cli_err_ctx(json_output, "bundling refs"))?;
+fn run_fmt(path: &Path, check: bool, quiet: bool) -> Result<(), u8> {
+    if !path.exists() {
+        eprintln!("Error: path not found: {}", path.display());
+        return Err(2);
     }
 
-    Ok(())
-}
+    let results = format_files(path, !check);
+    let mut changed = 0;
+    let mut failed = 0;
+    for result in &results {
+        let file = result.file.display();
+        if let Some(error) = &result.error {
+            failed += 1;
+            eprintln!("Error: {}: {}", file, error);
+        } else if result.changed {
+            changed += 1;
+            if check {
+                println!("Would reformat {}", file);
+            } else {
+                println!("Formatted {}", file);
+            }
+        }
+    }
 
-/// Output an error message in plain text or JSON format.
-///
-/// Uses same shape as validation errors for consistent API:
-/// `{"valid": false, "errors": [{"path": "", "message": "..."}]}`
-fn report_error(json_output: bool, msg: &str) {
-    if json_output {
-        let output = serde_json::json!({
-            "valid": false,
-            "errors": [{"path": "", "message": msg}]
-        });
-        println!("{}", output);
-    } else {
-        eprintln!("Error: {}", msg);
+    if !quiet {
+        let verb = if check {
+            "would be reformatted"
+        } else {
+            "reformatted"
+        };
+        println!(
+            "{} file(s) {}, {} unchanged",
+            changed,
+            verb,
+            results.len() - changed - failed
+        );
+    }
+    if failed > 0 || (check && changed > 0) {
+        return Err(1);
     }
+    Ok(())
 }
 
-fn run_lint(path: &Path, format: &str, strict: bool, quiet: bool) -> Result<(), u8> {
+fn run_lint(
+    path: &Path,
+    format: &str,
+    strict: bool,
+    quiet: bool,
+    orphans_base: Option<&Path>,
+) -> Result<(), u8> {
     use ucp_schema::Severity;
 
     if !path.exists() {
@@ -743,7 +7568,16 @@ fn run_lint(path: &Path, format: &str, strict: bool, quiet: bool) -> Result<(),
         return Err(2);
     }
 
-    let result = lint(path, strict);
+    let mut result = lint(path, strict);
+    if let Some(base) = orphans_base {
+        if !base.is_dir() {
+            eprintln!("Error: schema base not found: {}", base.display());
+            return Err(2);
+        }
+        result = detect_orphans(result, base, strict);
+    }
+    // Lint reports its own warnings; count them for --fail-on-warning
+    WARNINGS.fetch_add(result.warnings, Ordering::Relaxed);
 
     if format == "json" {
         println!("{}", serde_json::to_string_pretty(&result).unwrap());
@@ -0,0 +1,203 @@
+//! On-disk cache of remote fetches - schemas and profiles fetched over HTTP
+//! are kept by URL, so later runs reuse them instead of fetching again.
+//!
+//! Caching is off until a directory is given ([`FetchOptions::cache_dir`];
+//! the CLI also reads `UCP_SCHEMA_CACHE_DIR`). Each entry is one JSON file named after the
+//! SHA-256 of its URL:
+//!
+//! ```json
+//! {
+//!   "url": "https://ucp.dev/schemas/shopping/checkout.json",
+//!   "sha256": "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+//!   "fetched_at": 1767225600,
+//!   "body": "{ ... }"
+//! }
+//! ```
+//!
+//! Cached entries are served until removed (`ucp-schema cache clear` or
+//! `cache prune`), without revalidation, and are still checked against the
+//! fetch's lockfile.
+//!
+//! [`FetchOptions::cache_dir`]: crate::FetchOptions::cache_dir
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::ResolveError;
+use crate::lock::sha256_hex;
+
+/// Environment variable the CLI reads the cache directory from when
+/// `--cache-dir` isn't given.
+pub const CACHE_DIR_ENV: &str = "UCP_SCHEMA_CACHE_DIR";
+
+/// One cached document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CacheEntry {
+    pub url: String,
+    /// Lowercase hex SHA-256 of the body.
+    pub sha256: String,
+    /// When the body was fetched, in seconds since the Unix epoch.
+    pub fetched_at: u64,
+    /// Body size in bytes.
+    pub size: usize,
+    pub file: PathBuf,
+}
+
+impl CacheEntry {
+    /// Time since the body was fetched.
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(now().saturating_sub(self.fetched_at))
+    }
+}
+
+/// An entry file as stored on disk.
+#[derive(Serialize, Deserialize)]
+struct StoredEntry {
+    url: String,
+    sha256: String,
+    fetched_at: u64,
+    body: String,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+fn entry_file(dir: &Path, url: &str) -> PathBuf {
+    dir.join(format!("{}.json", sha256_hex(url.as_bytes())))
+}
+
+fn read_entry(file: &Path) -> Option<StoredEntry> {
+    serde_json::from_str(&std::fs::read_to_string(file).ok()?).ok()
+}
+
+/// The cached body for `url` in `dir`, if it has one.
+#[cfg_attr(not(feature = "remote"), allow(dead_code))]
+pub(crate) fn cached_body(dir: &Path, url: &str) -> Option<String> {
+    let entry = read_entry(&entry_file(dir, url))?;
+    (entry.url == url).then_some(entry.body)
+}
+
+/// Cache a fetched body in `dir`. Caching is best-effort: a cache that
+/// can't be written doesn't fail the fetch.
+#[cfg_attr(not(feature = "remote"), allow(dead_code))]
+pub(crate) fn store(dir: &Path, url: &str, body: &str) {
+    let entry = StoredEntry {
+        url: url.to_string(),
+        sha256: sha256_hex(body.as_bytes()),
+        fetched_at: now(),
+        body: body.to_string(),
+    };
+    if std::fs::create_dir_all(dir).is_ok() {
+        let json = serde_json::to_string(&entry).unwrap_or_default();
+        let _ = std::fs::write(entry_file(dir, url), json);
+    }
+}
+
+/// Every entry in the cache directory `dir`, sorted by URL. Files that
+/// aren't cache entries are ignored; a missing directory is an empty cache.
+///
+/// # Errors
+///
+/// Returns `ResolveError::ReadError` if `dir` exists but can't be listed.
+pub fn cache_entries(dir: &Path) -> Result<Vec<CacheEntry>, ResolveError> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let read_error = |source| ResolveError::ReadError {
+        path: dir.to_path_buf(),
+        source,
+    };
+    let mut entries = Vec::new();
+    for file in std::fs::read_dir(dir).map_err(read_error)? {
+        let file = file.map_err(read_error)?.path();
+        if file.extension().is_some_and(|ext| ext == "json") {
+            if let Some(stored) = read_entry(&file) {
+                entries.push(CacheEntry {
+                    url: stored.url,
+                    sha256: stored.sha256,
+                    fetched_at: stored.fetched_at,
+                    size: stored.body.len(),
+                    file,
+                });
+            }
+        }
+    }
+    entries.sort_by(|a, b| a.url.cmp(&b.url));
+    Ok(entries)
+}
+
+/// Remove the cache entries older than `max_age` (all of them with `None`),
+/// returning the removed entries.
+///
+/// # Errors
+///
+/// Returns `ResolveError::ReadError` if the directory can't be listed or an
+/// entry can't be removed.
+pub fn prune_cache(dir: &Path, max_age: Option<Duration>) -> Result<Vec<CacheEntry>, ResolveError> {
+    let mut removed = Vec::new();
+    for entry in cache_entries(dir)? {
+        if max_age.map_or(true, |max_age| entry.age() > max_age) {
+            std::fs::remove_file(&entry.file).map_err(|source| ResolveError::ReadError {
+                path: entry.file.clone(),
+                source,
+            })?;
+            removed.push(entry);
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_entries_list_and_prune_by_age() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let write = |url: &str, fetched_at: u64| {
+            let entry = StoredEntry {
+                url: url.to_string(),
+                sha256: sha256_hex(b"{}"),
+                fetched_at,
+                body: "{}".to_string(),
+            };
+            let json = serde_json::to_string(&entry).unwrap();
+            std::fs::write(entry_file(dir.path(), url), json).unwrap();
+        };
+        write("https://ucp.dev/b.json", now());
+        write("https://ucp.dev/a.json", now() - 3 * 86400);
+        std::fs::write(dir.path().join("notes.txt"), "not an entry").unwrap();
+
+        let urls: Vec<String> = cache_entries(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|e| e.url)
+            .collect();
+        assert_eq!(urls, ["https://ucp.dev/a.json", "https://ucp.dev/b.json"]);
+
+        let pruned = prune_cache(dir.path(), Some(Duration::from_secs(86400))).unwrap();
+        assert_eq!(pruned.len(), 1);
+        assert_eq!(pruned[0].url, "https://ucp.dev/a.json");
+        assert_eq!(prune_cache(dir.path(), None).unwrap().len(), 1);
+        assert!(cache_entries(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn store_and_cached_body_use_the_given_dir() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let other = tempfile::TempDir::new().unwrap();
+        let url = "https://ucp.dev/schemas/a.json";
+        store(dir.path(), url, "{\"a\":1}");
+        assert_eq!(cached_body(dir.path(), url).as_deref(), Some("{\"a\":1}"));
+        assert_eq!(cached_body(other.path(), url), None);
+        assert_eq!(
+            cached_body(dir.path(), "https://ucp.dev/schemas/b.json"),
+            None
+        );
+    }
+}
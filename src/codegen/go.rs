@@ -0,0 +1,316 @@
+//! Go backend: structs with json tags in one package.
+//!
+//! Go has no nested modules, so every type is suffixed with its operation and
+//! direction (`Checkout` in the create request becomes `CheckoutCreateRequest`).
+
+use std::fmt::Write;
+
+use super::{CodegenModel, EnumDef, Field, FieldType, StructDef, TypeDef};
+use crate::library::pascal_case;
+
+/// Words golint expects in all caps.
+const INITIALISMS: &[&str] = &[
+    "api", "http", "https", "id", "ip", "json", "sku", "uri", "url", "uuid",
+];
+
+pub(super) fn render(model: &CodegenModel) -> String {
+    let mut out = format!(
+        "// Code generated by ucp-schema codegen from {}. DO NOT EDIT.\n\npackage {}\n",
+        model.name,
+        package_name(&model.name)
+    );
+    for module in &model.modules {
+        let suffix = format!(
+            "{}{}",
+            pascal_case(&module.operation),
+            pascal_case(module.direction.as_str())
+        );
+        let names = Names { suffix: &suffix };
+        for def in &module.types {
+            out.push('\n');
+            match def {
+                TypeDef::Struct(s) => render_struct(&mut out, s, &names),
+                TypeDef::Enum(e) => render_enum(&mut out, e, &names),
+                TypeDef::Alias { name, ty } => {
+                    let _ = writeln!(
+                        out,
+                        "type {} {}",
+                        names.type_name(name),
+                        names.go_type(ty, name)
+                    );
+                }
+            }
+        }
+    }
+    out
+}
+
+struct Names<'a> {
+    suffix: &'a str,
+}
+
+impl Names<'_> {
+    fn type_name(&self, name: &str) -> String {
+        format!("{}{}", name, self.suffix)
+    }
+
+    /// Go type for `ty`; a field of its own struct's type must be a pointer.
+    fn go_type(&self, ty: &FieldType, owner: &str) -> String {
+        match ty {
+            FieldType::String => "string".to_string(),
+            FieldType::Integer => "int64".to_string(),
+            FieldType::Number => "float64".to_string(),
+            FieldType::Boolean => "bool".to_string(),
+            FieldType::Any => "any".to_string(),
+            FieldType::Array(item) => format!("[]{}", self.go_type(item, "")),
+            FieldType::Map(value) => format!("map[string]{}", self.go_type(value, "")),
+            FieldType::Named(name) if name == owner => format!("*{}", self.type_name(name)),
+            FieldType::Named(name) => self.type_name(name),
+        }
+    }
+
+    fn field_type(&self, field: &Field, owner: &str) -> String {
+        let ty = self.go_type(&field.ty, owner);
+        // Slices, maps and any already have a nil "absent" value
+        let nilable = ty.starts_with('*')
+            || matches!(
+                field.ty,
+                FieldType::Array(_) | FieldType::Map(_) | FieldType::Any
+            );
+        if field.is_optional() && !nilable {
+            format!("*{}", ty)
+        } else {
+            ty
+        }
+    }
+}
+
+fn render_struct(out: &mut String, def: &StructDef, names: &Names) {
+    let name = names.type_name(&def.name);
+    doc_comment(out, "", &name, def.description.as_deref());
+    if def.fields.is_empty() {
+        let _ = writeln!(out, "type {} struct{{}}", name);
+        return;
+    }
+    let _ = writeln!(out, "type {} struct {{", name);
+
+    let mut used = Vec::new();
+    let rows: Vec<(Option<&str>, [String; 3])> = def
+        .fields
+        .iter()
+        .map(|field| {
+            let ident = unique(go_ident(&field.name), &mut used);
+            let omit = if field.required { "" } else { ",omitempty" };
+            let tag = format!("`json:\"{}{}\"`", field.name, omit);
+            (
+                field.description.as_deref(),
+                [ident, names.field_type(field, &def.name), tag],
+            )
+        })
+        .collect();
+
+    // Align columns the way gofmt does, restarting after each doc comment
+    let mut start = 0;
+    while start < rows.len() {
+        let mut end = start + 1;
+        while end < rows.len() && rows[end].0.is_none() {
+            end += 1;
+        }
+        let run = &rows[start..end];
+        let width = |col: usize| run.iter().map(|(_, r)| r[col].len()).max().unwrap_or(0);
+        let (name_width, type_width) = (width(0), width(1));
+        for (doc, [ident, ty, tag]) in run {
+            doc_comment(out, "\t", ident, *doc);
+            let _ = writeln!(
+                out,
+                "\t{:<nw$} {:<tw$} {}",
+                ident,
+                ty,
+                tag,
+                nw = name_width,
+                tw = type_width
+            );
+        }
+        start = end;
+    }
+    out.push_str("}\n");
+}
+
+fn render_enum(out: &mut String, def: &EnumDef, names: &Names) {
+    let name = names.type_name(&def.name);
+    doc_comment(out, "", &name, def.description.as_deref());
+    let _ = writeln!(out, "type {} string", name);
+    if def.values.is_empty() {
+        return;
+    }
+
+    let mut used = Vec::new();
+    let consts: Vec<String> = def
+        .values
+        .iter()
+        .map(|v| unique(format!("{}{}", name, go_ident(v)), &mut used))
+        .collect();
+    let width = consts.iter().map(String::len).max().unwrap_or(0);
+    out.push_str("\nconst (\n");
+    for (constant, value) in consts.iter().zip(&def.values) {
+        let _ = writeln!(out, "\t{:<w$} {} = {:?}", constant, name, value, w = width);
+    }
+    out.push_str(")\n");
+}
+
+/// Go doc comments start with the name they document.
+fn doc_comment(out: &mut String, indent: &str, name: &str, text: Option<&str>) {
+    let Some(text) = text else {
+        return;
+    };
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        match (i, line.is_empty()) {
+            (0, _) => {
+                let _ = writeln!(out, "{}// {} is {}", indent, name, lower_first(line));
+            }
+            (_, true) => {
+                let _ = writeln!(out, "{}//", indent);
+            }
+            _ => {
+                let _ = writeln!(out, "{}// {}", indent, line);
+            }
+        }
+    }
+}
+
+fn lower_first(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        // Keep acronyms ("SKU of ...") as written
+        Some(c) if !chars.clone().next().is_some_and(char::is_uppercase) => {
+            c.to_lowercase().collect::<String>() + chars.as_str()
+        }
+        _ => s.to_string(),
+    }
+}
+
+/// Exported identifier for a JSON property or enum value ("line_item_id" -> "LineItemID").
+fn go_ident(name: &str) -> String {
+    let mut ident = String::new();
+    for word in split_words(name) {
+        let lower = word.to_ascii_lowercase();
+        if INITIALISMS.contains(&lower.as_str()) {
+            ident.push_str(&lower.to_ascii_uppercase());
+        } else {
+            let mut chars = word.chars();
+            if let Some(first) = chars.next() {
+                ident.push(first.to_ascii_uppercase());
+                ident.push_str(chars.as_str());
+            }
+        }
+    }
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        ident = format!("X{}", ident);
+    }
+    ident
+}
+
+/// Split on non-alphanumerics and lower-to-upper case changes ("lineItemId" -> line, Item, Id).
+fn split_words(s: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut prev_lower = false;
+    for c in s.chars() {
+        if !c.is_ascii_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            prev_lower = false;
+            continue;
+        }
+        if c.is_ascii_uppercase() && prev_lower && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        current.push(c);
+        prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// Lowercase letters and digits only, as Go package names are.
+fn package_name(name: &str) -> String {
+    let package: String = name
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    if package.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        package
+    } else {
+        format!("ucp{}", package)
+    }
+}
+
+fn unique(base: String, used: &mut Vec<String>) -> String {
+    let mut name = base.clone();
+    let mut n = 2;
+    while used.contains(&name) {
+        name = format!("{}{}", base, n);
+        n += 1;
+    }
+    used.push(name.clone());
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::{build_model, CodegenOptions};
+    use crate::test_support::checkout;
+    use serde_json::json;
+
+    #[test]
+    fn render_suffixes_types_per_operation() {
+        let model = build_model(
+            &checkout(),
+            &CodegenOptions::new("checkout", vec!["create", "update"]),
+        )
+        .unwrap();
+        let code = render(&model);
+
+        assert!(code.starts_with(
+            "// Code generated by ucp-schema codegen from checkout. DO NOT EDIT.\n\npackage checkout\n"
+        ));
+        assert!(code.contains(
+            "type CheckoutCreateRequest struct {\n\tLineItems []LineItemCreateRequest     `json:\"line_items\"`\n\tStatus    CheckoutStatusCreateRequest `json:\"status\"`\n\tNote      *string                     `json:\"note,omitempty\"`\n\tTotal     *int64                      `json:\"total,omitempty\"`\n\tBuyer     *BuyerCreateRequest         `json:\"buyer,omitempty\"`\n\tMetadata  map[string]any              `json:\"metadata,omitempty\"`\n}\n"
+        ));
+        assert!(code.contains(
+            "\t// ID is unique identifier.\n\tID        string                      `json:\"id\"`\n"
+        ));
+        assert!(code.contains(
+            "type CheckoutStatusUpdateResponse string\n\nconst (\n\tCheckoutStatusUpdateResponseOpen   CheckoutStatusUpdateResponse = \"open\"\n\tCheckoutStatusUpdateResponseClosed CheckoutStatusUpdateResponse = \"closed\"\n)\n"
+        ));
+        assert!(
+            code.contains("type LineItemUpdateResponse struct {\n\tSKU string `json:\"sku\"`\n}\n")
+        );
+    }
+
+    #[test]
+    fn render_pointer_for_self_reference() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "parent": { "$ref": "#" } },
+            "required": ["parent"]
+        });
+        let model = build_model(&schema, &CodegenOptions::new("node", vec!["read"])).unwrap();
+        assert!(render(&model).contains("\tParent *NodeReadRequest `json:\"parent\"`\n"));
+    }
+
+    #[test]
+    fn go_ident_variants() {
+        assert_eq!(go_ident("line_item_id"), "LineItemID");
+        assert_eq!(go_ident("lineItemId"), "LineItemID");
+        assert_eq!(go_ident("express-2day"), "Express2day");
+        assert_eq!(go_ident("3ds"), "X3ds");
+        assert_eq!(package_name("dev.ucp.Checkout"), "devucpcheckout");
+    }
+}
@@ -0,0 +1,574 @@
+//! Code generation - typed bindings for resolved schemas.
+//!
+//! Each operation × direction is resolved and lowered into a small
+//! language-neutral model (`CodegenModel`): named structs, string enums and
+//! field types. Backends only render that model, so every target agrees on
+//! type names, optionality and nesting.
+
+mod go;
+mod rust;
+
+use std::collections::{HashMap, HashSet};
+
+use serde_json::Value;
+
+use crate::error::ResolveError;
+use crate::library::pascal_case;
+use crate::resolver::resolve;
+use crate::types::{Direction, ResolveOptions};
+
+/// Target language for generated code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodegenTarget {
+    /// serde-annotated Rust structs, one module per operation/direction.
+    Rust,
+    /// Go structs with json tags, types suffixed per operation/direction.
+    Go,
+}
+
+impl CodegenTarget {
+    /// Parse a target name ("rust" or "go").
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "rust" => Some(CodegenTarget::Rust),
+            "go" => Some(CodegenTarget::Go),
+            _ => None,
+        }
+    }
+}
+
+/// Options for code generation.
+#[derive(Debug, Clone)]
+pub struct CodegenOptions {
+    /// Name of the root type in every module (e.g., "checkout" -> `Checkout`).
+    pub name: String,
+    /// Operations to generate, in output order. Normalized to lowercase.
+    pub operations: Vec<String>,
+    /// Resolve in strict mode, so generated structs reject unknown fields.
+    pub strict: bool,
+}
+
+impl CodegenOptions {
+    /// Create codegen options with strict mode disabled.
+    pub fn new<S: Into<String>>(name: impl Into<String>, operations: Vec<S>) -> Self {
+        Self {
+            name: name.into(),
+            operations: operations
+                .into_iter()
+                .map(|op| op.into().to_lowercase())
+                .collect(),
+            strict: false,
+        }
+    }
+
+    /// Set strict mode for every resolution.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+}
+
+/// Every generated module, in operation order with request before response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodegenModel {
+    /// Schema name the model was generated from.
+    pub name: String,
+    pub modules: Vec<TypeModule>,
+}
+
+/// Types for one operation and direction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeModule {
+    pub operation: String,
+    pub direction: Direction,
+    /// Name of the type describing the whole payload.
+    pub root: String,
+    /// Named types, root first, then in order of first use.
+    pub types: Vec<TypeDef>,
+}
+
+/// A named type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeDef {
+    Struct(StructDef),
+    Enum(EnumDef),
+    /// A name for a non-object type (e.g., a root that is an array).
+    Alias {
+        name: String,
+        ty: FieldType,
+    },
+}
+
+impl TypeDef {
+    pub fn name(&self) -> &str {
+        match self {
+            TypeDef::Struct(s) => &s.name,
+            TypeDef::Enum(e) => &e.name,
+            TypeDef::Alias { name, .. } => name,
+        }
+    }
+}
+
+/// An object schema with known properties.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructDef {
+    pub name: String,
+    pub description: Option<String>,
+    pub fields: Vec<Field>,
+    /// `additionalProperties: false` (strict mode): unknown fields are rejected.
+    pub deny_unknown_fields: bool,
+}
+
+/// A struct field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    /// Property name as it appears in JSON.
+    pub name: String,
+    pub ty: FieldType,
+    /// Listed in the schema's `required`.
+    pub required: bool,
+    /// The value may be `null`.
+    pub nullable: bool,
+    pub description: Option<String>,
+}
+
+impl Field {
+    /// Absent or null values are possible, so the target needs an optional type.
+    pub fn is_optional(&self) -> bool {
+        !self.required || self.nullable
+    }
+}
+
+/// A string schema with `enum`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDef {
+    pub name: String,
+    pub description: Option<String>,
+    pub values: Vec<String>,
+}
+
+/// Field type in the model.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldType {
+    String,
+    Integer,
+    Number,
+    Boolean,
+    /// No usable type information (e.g., `oneOf`, external `$ref`).
+    Any,
+    Array(Box<FieldType>),
+    /// Object without `properties`, keyed by string.
+    Map(Box<FieldType>),
+    /// A `TypeDef` in the same module.
+    Named(String),
+}
+
+/// Resolve `schema` for every operation × direction and build the model.
+///
+/// # Errors
+///
+/// Returns the first `ResolveError` encountered.
+pub fn build_model(schema: &Value, options: &CodegenOptions) -> Result<CodegenModel, ResolveError> {
+    let mut modules = Vec::new();
+    for op in &options.operations {
+        for direction in [Direction::Request, Direction::Response] {
+            let resolve_options = ResolveOptions::new(direction, op).strict(options.strict);
+            let resolved = resolve(schema, &resolve_options)?;
+            let root = pascal_case(&options.name);
+            let types = ModuleBuilder::new(&resolved, &root).build();
+            modules.push(TypeModule {
+                operation: op.clone(),
+                direction,
+                root,
+                types,
+            });
+        }
+    }
+    Ok(CodegenModel {
+        name: options.name.clone(),
+        modules,
+    })
+}
+
+/// Resolve `schema` and render it as source code for `target`.
+///
+/// # Errors
+///
+/// Returns the first `ResolveError` encountered.
+pub fn generate_code(
+    schema: &Value,
+    target: CodegenTarget,
+    options: &CodegenOptions,
+) -> Result<String, ResolveError> {
+    let model = build_model(schema, options)?;
+    Ok(match target {
+        CodegenTarget::Rust => rust::render(&model),
+        CodegenTarget::Go => go::render(&model),
+    })
+}
+
+/// Lowers one resolved schema into named types.
+struct ModuleBuilder<'a> {
+    schema: &'a Value,
+    root: String,
+    /// Slots so a parent is listed before the types its fields introduce.
+    types: Vec<Option<TypeDef>>,
+    names: HashSet<String>,
+    /// `#/...` ref -> type already built (or being built) for it.
+    refs: HashMap<String, FieldType>,
+}
+
+impl<'a> ModuleBuilder<'a> {
+    fn new(schema: &'a Value, root: &str) -> Self {
+        Self {
+            schema,
+            root: root.to_string(),
+            types: Vec::new(),
+            names: HashSet::new(),
+            refs: HashMap::new(),
+        }
+    }
+
+    fn build(mut self) -> Vec<TypeDef> {
+        let root = self.root.clone();
+        self.refs
+            .insert("#".to_string(), FieldType::Named(root.clone()));
+        let (ty, _) = self.field_type(self.schema, &root);
+        if ty != FieldType::Named(root.clone()) {
+            self.names.insert(root.clone());
+            self.types
+                .insert(0, Some(TypeDef::Alias { name: root, ty }));
+        }
+        self.types.into_iter().flatten().collect()
+    }
+
+    /// Type of `schema`; named types it needs are called `hint` (made unique).
+    /// Also returns whether `null` is allowed.
+    fn field_type(&mut self, schema: &Value, hint: &str) -> (FieldType, bool) {
+        if let Some(r) = schema.get("$ref").and_then(Value::as_str) {
+            return (self.ref_type(r), false);
+        }
+        if let Some(branches) = schema
+            .get("anyOf")
+            .or_else(|| schema.get("oneOf"))
+            .and_then(Value::as_array)
+        {
+            // [X, {type: null}] is a nullable X; anything else is untyped
+            let non_null: Vec<&Value> = branches
+                .iter()
+                .filter(|b| b.get("type").and_then(Value::as_str) != Some("null"))
+                .collect();
+            return match non_null.as_slice() {
+                [only] if non_null.len() < branches.len() => (self.field_type(only, hint).0, true),
+                _ => (FieldType::Any, false),
+            };
+        }
+
+        let (types, nullable) = type_names(schema);
+        let has_properties = schema.get("properties").is_some() || schema.get("allOf").is_some();
+        let kind = match types.as_slice() {
+            [single] => single.as_str(),
+            [] if has_properties => "object",
+            [] if string_enum(schema).is_some() => "string",
+            _ => return (FieldType::Any, nullable),
+        };
+
+        let ty = match kind {
+            "string" => match string_enum(schema) {
+                Some(values) => self.enum_type(schema, hint, values),
+                None => FieldType::String,
+            },
+            "integer" => FieldType::Integer,
+            "number" => FieldType::Number,
+            "boolean" => FieldType::Boolean,
+            "array" => {
+                let item = match schema.get("items") {
+                    Some(items) => self.field_type(items, &format!("{}Item", hint)).0,
+                    None => FieldType::Any,
+                };
+                FieldType::Array(Box::new(item))
+            }
+            "object" if has_properties => self.struct_type(schema, hint),
+            "object" => {
+                let value = match schema.get("additionalProperties") {
+                    Some(ap) if ap.is_object() => self.field_type(ap, &format!("{}Value", hint)).0,
+                    _ => FieldType::Any,
+                };
+                FieldType::Map(Box::new(value))
+            }
+            _ => FieldType::Any,
+        };
+        (ty, nullable)
+    }
+
+    /// Internal refs become the type built for their target; others are untyped.
+    fn ref_type(&mut self, reference: &str) -> FieldType {
+        if let Some(ty) = self.refs.get(reference) {
+            return ty.clone();
+        }
+        let Some(pointer) = reference.strip_prefix('#') else {
+            return FieldType::Any;
+        };
+        let Some(target) = self.schema.pointer(pointer) else {
+            return FieldType::Any;
+        };
+        let hint = pascal_case(pointer.rsplit('/').next().unwrap_or(pointer));
+        // Placeholder so a self-referencing definition terminates
+        let name = self.unique_name(&hint);
+        self.names.remove(&name);
+        self.refs
+            .insert(reference.to_string(), FieldType::Named(name.clone()));
+        let (ty, _) = self.field_type(target, &name);
+        self.refs.insert(reference.to_string(), ty.clone());
+        ty
+    }
+
+    fn struct_type(&mut self, schema: &Value, hint: &str) -> FieldType {
+        let name = self.unique_name(hint);
+        let slot = self.types.len();
+        self.types.push(None);
+
+        let (properties, required, deny_unknown_fields) = self.merged_object(schema);
+        let mut fields = Vec::new();
+        for (prop, prop_schema) in &properties {
+            let (ty, nullable) =
+                self.field_type(prop_schema, &format!("{}{}", name, pascal_case(prop)));
+            fields.push(Field {
+                name: prop.clone(),
+                ty,
+                required: required.contains(prop),
+                nullable,
+                description: description(prop_schema),
+            });
+        }
+
+        self.types[slot] = Some(TypeDef::Struct(StructDef {
+            name: name.clone(),
+            description: description(schema),
+            fields,
+            deny_unknown_fields,
+        }));
+        FieldType::Named(name)
+    }
+
+    /// Properties and required names of an object, folding in `allOf` branches
+    /// (and internal refs among them) in order.
+    fn merged_object(&self, schema: &Value) -> (Vec<(String, Value)>, HashSet<String>, bool) {
+        let mut properties: Vec<(String, Value)> = Vec::new();
+        let mut required = HashSet::new();
+        let mut deny = schema.get("additionalProperties") == Some(&Value::Bool(false));
+
+        let mut pending = vec![schema];
+        let mut seen = 0;
+        while let Some(current) = pending.pop() {
+            seen += 1;
+            if seen > 64 {
+                break;
+            }
+            let current = match current.get("$ref").and_then(Value::as_str) {
+                Some(r) => match r.strip_prefix('#').and_then(|p| self.schema.pointer(p)) {
+                    Some(target) => target,
+                    None => continue,
+                },
+                None => current,
+            };
+            if let Some(props) = current.get("properties").and_then(Value::as_object) {
+                for (name, prop) in props {
+                    match properties.iter_mut().find(|(n, _)| n == name) {
+                        Some(existing) => existing.1 = prop.clone(),
+                        None => properties.push((name.clone(), prop.clone())),
+                    }
+                }
+            }
+            if let Some(names) = current.get("required").and_then(Value::as_array) {
+                required.extend(names.iter().filter_map(|n| n.as_str().map(String::from)));
+            }
+            deny |= current.get("additionalProperties") == Some(&Value::Bool(false));
+            if let Some(branches) = current.get("allOf").and_then(Value::as_array) {
+                pending.extend(branches.iter().rev());
+            }
+        }
+        (properties, required, deny)
+    }
+
+    fn enum_type(&mut self, schema: &Value, hint: &str, values: Vec<String>) -> FieldType {
+        let name = self.unique_name(hint);
+        self.types.push(Some(TypeDef::Enum(EnumDef {
+            name: name.clone(),
+            description: description(schema),
+            values,
+        })));
+        FieldType::Named(name)
+    }
+
+    fn unique_name(&mut self, hint: &str) -> String {
+        let base = if hint.is_empty() { "Type" } else { hint };
+        let mut name = base.to_string();
+        let mut n = 2;
+        while self.names.contains(&name) {
+            name = format!("{}{}", base, n);
+            n += 1;
+        }
+        self.names.insert(name.clone());
+        name
+    }
+}
+
+/// Non-null type names and whether `null` is allowed.
+fn type_names(schema: &Value) -> (Vec<String>, bool) {
+    let names: Vec<String> = match schema.get("type") {
+        Some(Value::String(s)) => vec![s.clone()],
+        Some(Value::Array(arr)) => arr
+            .iter()
+            .filter_map(|v| v.as_str().map(String::from))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let nullable = names.iter().any(|n| n == "null");
+    let mut non_null: Vec<String> = names.into_iter().filter(|n| n != "null").collect();
+    // integer is a subset of number
+    if non_null.len() == 2
+        && non_null.contains(&"integer".into())
+        && non_null.contains(&"number".into())
+    {
+        non_null = vec!["number".to_string()];
+    }
+    (non_null, nullable)
+}
+
+fn string_enum(schema: &Value) -> Option<Vec<String>> {
+    let values = schema.get("enum")?.as_array()?;
+    values
+        .iter()
+        .filter(|v| !v.is_null())
+        .map(|v| v.as_str().map(String::from))
+        .collect()
+}
+
+fn description(schema: &Value) -> Option<String> {
+    schema
+        .get("description")
+        .and_then(Value::as_str)
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::checkout;
+    use serde_json::json;
+
+    fn module<'m>(model: &'m CodegenModel, op: &str, direction: Direction) -> &'m TypeModule {
+        model
+            .modules
+            .iter()
+            .find(|m| m.operation == op && m.direction == direction)
+            .unwrap()
+    }
+
+    #[test]
+    fn model_has_module_per_op_and_direction() {
+        let options = CodegenOptions::new("checkout", vec!["create", "update"]);
+        let model = build_model(&checkout(), &options).unwrap();
+        let modules: Vec<(&str, Direction)> = model
+            .modules
+            .iter()
+            .map(|m| (m.operation.as_str(), m.direction))
+            .collect();
+        assert_eq!(
+            modules,
+            [
+                ("create", Direction::Request),
+                ("create", Direction::Response),
+                ("update", Direction::Request),
+                ("update", Direction::Response)
+            ]
+        );
+    }
+
+    #[test]
+    fn model_types_follow_resolution() {
+        let options = CodegenOptions::new("checkout", vec!["create", "update"]);
+        let model = build_model(&checkout(), &options).unwrap();
+
+        let names: Vec<&str> = module(&model, "create", Direction::Request)
+            .types
+            .iter()
+            .map(TypeDef::name)
+            .collect();
+        assert_eq!(names, ["Checkout", "LineItem", "CheckoutStatus", "Buyer"]);
+
+        let TypeDef::Struct(root) = &module(&model, "update", Direction::Request).types[0] else {
+            panic!("root is not a struct");
+        };
+        let field = |name: &str| root.fields.iter().find(|f| f.name == name).unwrap();
+        assert!(field("id").required);
+        assert_eq!(field("id").ty, FieldType::String);
+        assert!(field("note").nullable && field("note").is_optional());
+        assert_eq!(field("buyer").ty, FieldType::Named("Buyer".into()));
+        assert_eq!(
+            field("line_items").ty,
+            FieldType::Array(Box::new(FieldType::Named("LineItem".into())))
+        );
+        assert_eq!(
+            field("metadata").ty,
+            FieldType::Map(Box::new(FieldType::Any))
+        );
+        assert!(!root.deny_unknown_fields);
+    }
+
+    #[test]
+    fn model_strict_denies_unknown_fields() {
+        let options = CodegenOptions::new("checkout", vec!["read"]).strict(true);
+        let model = build_model(&checkout(), &options).unwrap();
+        let TypeDef::Struct(root) = &model.modules[0].types[0] else {
+            panic!("root is not a struct");
+        };
+        assert!(root.deny_unknown_fields);
+    }
+
+    #[test]
+    fn model_merges_allof_branches() {
+        let schema = json!({
+            "allOf": [
+                { "type": "object", "properties": { "id": { "type": "string" } }, "required": ["id"] },
+                { "type": "object", "properties": { "discounts": { "type": "array" } } }
+            ]
+        });
+        let model = build_model(&schema, &CodegenOptions::new("checkout", vec!["read"])).unwrap();
+        let TypeDef::Struct(root) = &model.modules[0].types[0] else {
+            panic!("root is not a struct");
+        };
+        let fields: Vec<(&str, bool)> = root
+            .fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.required))
+            .collect();
+        assert_eq!(fields, [("id", true), ("discounts", false)]);
+    }
+
+    #[test]
+    fn model_self_reference_terminates() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "children": { "type": "array", "items": { "$ref": "#" } },
+                "parent": { "$ref": "#/$defs/node" }
+            },
+            "$defs": {
+                "node": {
+                    "type": "object",
+                    "properties": { "next": { "$ref": "#/$defs/node" } }
+                }
+            }
+        });
+        let model = build_model(&schema, &CodegenOptions::new("tree", vec!["read"])).unwrap();
+        let types = &model.modules[0].types;
+        let names: Vec<&str> = types.iter().map(TypeDef::name).collect();
+        assert_eq!(names, ["Tree", "Node"]);
+        let TypeDef::Struct(node) = &types[1] else {
+            panic!("node is not a struct");
+        };
+        assert_eq!(node.fields[0].ty, FieldType::Named("Node".into()));
+    }
+}
@@ -0,0 +1,264 @@
+//! Rust backend: serde-annotated structs, one module per operation/direction.
+
+use std::fmt::Write;
+
+use super::{CodegenModel, EnumDef, Field, FieldType, StructDef, TypeDef, TypeModule};
+use crate::library::pascal_case;
+
+/// Keywords usable as raw identifiers (`r#type`).
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern", "false",
+    "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where", "while",
+    "abstract", "become", "box", "do", "final", "macro", "override", "priv", "try", "typeof",
+    "unsized", "virtual", "yield",
+];
+
+/// Keywords that can't be raw identifiers.
+const RESERVED: &[&str] = &["crate", "self", "super", "Self"];
+
+pub(super) fn render(model: &CodegenModel) -> String {
+    let mut out = format!(
+        "// Generated by ucp-schema codegen from {}. Do not edit.\n",
+        model.name
+    );
+    for module in &model.modules {
+        out.push('\n');
+        render_module(&mut out, module);
+    }
+    out
+}
+
+fn render_module(out: &mut String, module: &TypeModule) {
+    let direction = module.direction.as_str();
+    let _ = writeln!(out, "/// Types for the {} {}.", module.operation, direction);
+    let _ = writeln!(
+        out,
+        "pub mod {}_{} {{",
+        snake_case(&module.operation),
+        direction
+    );
+    let _ = writeln!(out, "    use serde::{{Deserialize, Serialize}};");
+    for def in &module.types {
+        out.push('\n');
+        match def {
+            TypeDef::Struct(s) => render_struct(out, s),
+            TypeDef::Enum(e) => render_enum(out, e),
+            TypeDef::Alias { name, ty } => {
+                let _ = writeln!(out, "    pub type {} = {};", name, rust_type(ty, name));
+            }
+        }
+    }
+    out.push_str("}\n");
+}
+
+fn render_struct(out: &mut String, def: &StructDef) {
+    doc_comment(out, "    ", def.description.as_deref());
+    out.push_str("    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n");
+    if def.deny_unknown_fields {
+        out.push_str("    #[serde(deny_unknown_fields)]\n");
+    }
+    if def.fields.is_empty() {
+        let _ = writeln!(out, "    pub struct {} {{}}", def.name);
+        return;
+    }
+    let _ = writeln!(out, "    pub struct {} {{", def.name);
+
+    let mut used = Vec::new();
+    for field in &def.fields {
+        let ident = unique(field_ident(&field.name), &mut used);
+        doc_comment(out, "        ", field.description.as_deref());
+
+        let mut serde_args = Vec::new();
+        if ident.trim_start_matches("r#") != field.name {
+            serde_args.push(format!("rename = {:?}", field.name));
+        }
+        if !field.required {
+            serde_args.push("default, skip_serializing_if = \"Option::is_none\"".to_string());
+        }
+        if !serde_args.is_empty() {
+            let _ = writeln!(out, "        #[serde({})]", serde_args.join(", "));
+        }
+        let _ = writeln!(
+            out,
+            "        pub {}: {},",
+            ident,
+            field_type(field, &def.name)
+        );
+    }
+    out.push_str("    }\n");
+}
+
+fn render_enum(out: &mut String, def: &EnumDef) {
+    doc_comment(out, "    ", def.description.as_deref());
+    out.push_str(
+        "    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]\n",
+    );
+    let _ = writeln!(out, "    pub enum {} {{", def.name);
+    let mut used = Vec::new();
+    for value in &def.values {
+        let mut variant = pascal_case(value);
+        if !variant.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            variant = format!("V{}", variant);
+        }
+        let variant = unique(variant, &mut used);
+        if variant != *value {
+            let _ = writeln!(out, "        #[serde(rename = {:?})]", value);
+        }
+        let _ = writeln!(out, "        {},", variant);
+    }
+    out.push_str("    }\n");
+}
+
+fn field_type(field: &Field, owner: &str) -> String {
+    let ty = rust_type(&field.ty, owner);
+    if field.is_optional() {
+        format!("Option<{}>", ty)
+    } else {
+        ty
+    }
+}
+
+/// Rust type for `ty`; a field of its own struct's type is boxed.
+fn rust_type(ty: &FieldType, owner: &str) -> String {
+    match ty {
+        FieldType::String => "String".to_string(),
+        FieldType::Integer => "i64".to_string(),
+        FieldType::Number => "f64".to_string(),
+        FieldType::Boolean => "bool".to_string(),
+        FieldType::Any => "serde_json::Value".to_string(),
+        FieldType::Array(item) => format!("Vec<{}>", rust_type(item, "")),
+        FieldType::Map(value) => format!(
+            "std::collections::BTreeMap<String, {}>",
+            rust_type(value, "")
+        ),
+        FieldType::Named(name) if name == owner => format!("Box<{}>", name),
+        FieldType::Named(name) => name.clone(),
+    }
+}
+
+fn doc_comment(out: &mut String, indent: &str, text: Option<&str>) {
+    for line in text.into_iter().flat_map(str::lines) {
+        let line = line.trim_end();
+        if line.is_empty() {
+            let _ = writeln!(out, "{}///", indent);
+        } else {
+            let _ = writeln!(out, "{}/// {}", indent, line);
+        }
+    }
+}
+
+/// Field identifier for a JSON property name, escaped if it is a keyword.
+fn field_ident(name: &str) -> String {
+    let mut ident = snake_case(name);
+    if ident.is_empty() {
+        ident = "field".to_string();
+    } else if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident = format!("_{}", ident);
+    }
+    if RESERVED.contains(&ident.as_str()) {
+        format!("{}_", ident)
+    } else if KEYWORDS.contains(&ident.as_str()) {
+        format!("r#{}", ident)
+    } else {
+        ident
+    }
+}
+
+/// Convert "lineItems", "line-items" or "Line Items" to "line_items".
+fn snake_case(s: &str) -> String {
+    let mut out = String::new();
+    let mut prev_lower = false;
+    for c in s.chars() {
+        if c.is_ascii_alphanumeric() {
+            if c.is_ascii_uppercase() && prev_lower {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+            prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        } else {
+            if !out.is_empty() && !out.ends_with('_') {
+                out.push('_');
+            }
+            prev_lower = false;
+        }
+    }
+    out.trim_end_matches('_').to_string()
+}
+
+fn unique(base: String, used: &mut Vec<String>) -> String {
+    let mut name = base.clone();
+    let mut n = 2;
+    while used.contains(&name) {
+        name = format!("{}{}", base, n);
+        n += 1;
+    }
+    used.push(name.clone());
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::{build_model, CodegenOptions};
+    use serde_json::json;
+
+    #[test]
+    fn render_structs_and_enums() {
+        let schema = json!({
+            "type": "object",
+            "description": "A checkout session.",
+            "properties": {
+                "id": { "type": "string", "ucp_request": { "create": "omit", "update": "required" } },
+                "lineItems": {
+                    "type": "array",
+                    "items": { "type": "object", "properties": { "qty": { "type": "integer" } } }
+                },
+                "type": { "type": "string", "enum": ["standard", "express-2day"] },
+                "total": { "type": ["number", "null"] }
+            },
+            "required": ["lineItems", "total"]
+        });
+        let options = CodegenOptions::new("checkout", vec!["update"]).strict(true);
+        let code = render(&build_model(&schema, &options).unwrap());
+
+        assert!(code.contains("pub mod update_request {"));
+        assert!(code.contains("pub mod update_response {"));
+        assert!(code.contains(
+            "    /// A checkout session.\n    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n    #[serde(deny_unknown_fields)]\n    pub struct Checkout {"
+        ));
+        assert!(code.contains("        pub id: String,\n"));
+        assert!(code.contains(
+            "        #[serde(rename = \"lineItems\")]\n        pub line_items: Vec<CheckoutLineItemsItem>,\n"
+        ));
+        assert!(code.contains(
+            "        #[serde(default, skip_serializing_if = \"Option::is_none\")]\n        pub r#type: Option<CheckoutType>,\n"
+        ));
+        // Required but nullable: Option without skip
+        assert!(code.contains("        pub total: Option<f64>,\n"));
+        assert!(
+            code.contains("        #[serde(rename = \"express-2day\")]\n        Express2day,\n")
+        );
+    }
+
+    #[test]
+    fn render_boxes_self_reference() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "parent": { "$ref": "#" } }
+        });
+        let code =
+            render(&build_model(&schema, &CodegenOptions::new("node", vec!["read"])).unwrap());
+        assert!(code.contains("pub parent: Option<Box<Node>>,"));
+    }
+
+    #[test]
+    fn field_ident_variants() {
+        assert_eq!(field_ident("lineItems"), "line_items");
+        assert_eq!(field_ident("line-items"), "line_items");
+        assert_eq!(field_ident("type"), "r#type");
+        assert_eq!(field_ident("self"), "self_");
+        assert_eq!(field_ident("3ds"), "_3ds");
+        assert_eq!(field_ident("HTTPStatus"), "httpstatus");
+    }
+}
@@ -39,12 +39,15 @@
 //! simulate this pattern.
 
 use std::collections::{HashMap, HashSet};
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 
+use regex::Regex;
 use serde_json::{json, Value};
 
 use crate::error::ComposeError;
-use crate::loader::{bundle_refs, bundle_refs_with_url_mapping, is_url, load_schema};
+use crate::loader::{
+    bundle_refs_with_source_map_within, is_url, load_schema, FetchOptions, SourceMapEntry,
+};
 use crate::types::Direction;
 
 #[cfg(feature = "remote")]
@@ -60,12 +63,196 @@ use crate::loader::{bundle_refs_remote, load_schema_url};
 /// - `local_base`: `source`
 /// - URL: `https://ucp.dev/draft/schemas/checkout.json`
 /// - Result: `source/schemas/checkout.json`
-#[derive(Debug, Clone, Default)]
+///
+/// For compositions spanning several hosts, `mappings` take over from
+/// `remote_base`: each URL is mapped by the first matching prefix, and a URL
+/// matching none is an error.
+#[derive(Debug, Clone)]
 pub struct SchemaBaseConfig<'a> {
     /// Local directory containing schema files.
     pub local_base: Option<&'a Path>,
     /// URL prefix to strip when mapping to local paths.
     pub remote_base: Option<&'a str>,
+    /// URL prefix to local directory mappings, tried in order.
+    pub mappings: &'a [UrlMapping],
+    /// How profiles and schemas that aren't mapped to local files are fetched.
+    pub fetch: &'a FetchOptions,
+    /// Only read files under `local_base` or the mapped directories: a URL
+    /// whose path leads outside them (through `..` or a symlink), a `$ref`
+    /// that does, or a plain path with neither configured is an error. For
+    /// URLs taken from untrusted payloads.
+    pub confine: bool,
+}
+
+impl Default for SchemaBaseConfig<'_> {
+    fn default() -> Self {
+        static FETCH: FetchOptions = FetchOptions::new();
+        Self {
+            local_base: None,
+            remote_base: None,
+            mappings: &[],
+            fetch: &FETCH,
+            confine: false,
+        }
+    }
+}
+
+/// Maps schema URLs under `prefix` to files under `local`.
+///
+/// `local` is joined onto `local_base` when one is set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlMapping {
+    pub prefix: String,
+    pub local: PathBuf,
+}
+
+impl UrlMapping {
+    /// Parse a `prefix=local` pair, e.g. `https://ucp.dev/versioned=schemas/ucp`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let (prefix, local) = s.split_once('=')?;
+        if prefix.is_empty() || local.is_empty() {
+            return None;
+        }
+        Some(Self {
+            prefix: prefix.to_string(),
+            local: PathBuf::from(local),
+        })
+    }
+}
+
+impl SchemaBaseConfig<'_> {
+    /// Returns true if `url` is covered by the configured remote base.
+    ///
+    /// Always true when no remote base is set (there is nothing to mismatch).
+    pub fn matches_remote_base(&self, url: &str) -> bool {
+        self.remote_base.map_or(true, |base| url.starts_with(base))
+    }
+
+    /// When confined, check that `relative` (the part of `url` mapped under
+    /// `root`) stays inside `root`, and return the canonical root for
+    /// bundling to stay inside too.
+    fn confined_root(
+        &self,
+        url: &str,
+        root: &Path,
+        relative: &str,
+    ) -> Result<Option<PathBuf>, ComposeError> {
+        if !self.confine {
+            return Ok(None);
+        }
+        let outside = || ComposeError::SchemaFetch {
+            url: url.to_string(),
+            message: format!("resolves outside {}", root.display()),
+        };
+        let relative = Path::new(relative);
+        if relative.has_root() || relative.components().any(|c| c == Component::ParentDir) {
+            return Err(outside());
+        }
+        let real_root = root.canonicalize().map_err(|e| ComposeError::SchemaFetch {
+            url: url.to_string(),
+            message: format!("reading {}: {}", root.display(), e),
+        })?;
+        if let Ok(real) = root.join(relative).canonicalize() {
+            if !real.starts_with(&real_root) {
+                return Err(outside());
+            }
+        }
+        Ok(Some(real_root))
+    }
+}
+
+/// Options controlling schema composition.
+///
+/// Defaults are strict: every extension must connect to the root capability.
+#[derive(Debug, Clone, Default)]
+pub struct ComposeOptions {
+    /// Compose extensions whose parent capability is absent as standalone
+    /// branches (with a warning) instead of failing.
+    pub allow_orphan_extensions: bool,
+    /// Drop capabilities whose ISO-date version is older than this
+    /// (e.g., "2026-01-01") before composing.
+    pub min_version: Option<String>,
+    /// Fail when a loaded schema declares a different version than the
+    /// capability advertises (otherwise a warning).
+    pub strict_version: bool,
+    /// Compose only root capabilities (no `extends`), skipping extensions.
+    pub root_only: bool,
+    /// Compose only capabilities whose name matches, plus their ancestors.
+    pub capability_filter: Option<Regex>,
+}
+
+impl ComposeOptions {
+    /// Create options with strict defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow extensions with an absent parent (degraded composition).
+    pub fn allow_orphan_extensions(mut self, allow: bool) -> Self {
+        self.allow_orphan_extensions = allow;
+        self
+    }
+
+    /// Only compose capabilities at or above `version` (ISO date).
+    pub fn min_version(mut self, version: impl Into<String>) -> Self {
+        self.min_version = Some(version.into());
+        self
+    }
+
+    /// Treat advertised/declared version mismatches as errors.
+    pub fn strict_version(mut self, strict: bool) -> Self {
+        self.strict_version = strict;
+        self
+    }
+
+    /// Compose just the root capability, leaving extensions out.
+    pub fn root_only(mut self, root_only: bool) -> Self {
+        self.root_only = root_only;
+        self
+    }
+
+    /// Only compose capabilities whose name matches `filter`, along with the
+    /// capabilities they (transitively) extend.
+    pub fn capability_filter(mut self, filter: Regex) -> Self {
+        self.capability_filter = Some(filter);
+        self
+    }
+}
+
+/// Result of composition: the schema plus any non-fatal warnings.
+#[derive(Debug, Clone)]
+pub struct Composed {
+    /// The composed schema (annotations preserved).
+    pub schema: Value,
+    /// Problems tolerated under the given options, in discovery order.
+    pub warnings: Vec<String>,
+    /// Capability each top-level `allOf` branch came from, in branch order.
+    /// For a root-only composition (no `allOf`), holds just the root name.
+    pub origins: Vec<String>,
+    /// Local files read while composing (capability schemas and the files
+    /// their `$ref`s inlined), in first-read order. Remote fetches aren't listed.
+    pub deps: Vec<PathBuf>,
+    /// Capabilities left out by [`ComposeOptions::capability_filter`] or
+    /// [`ComposeOptions::root_only`], in payload order.
+    pub skipped: Vec<String>,
+}
+
+impl Composed {
+    /// Apply [`dedupe_allof`], keeping `origins` aligned with the top-level
+    /// branches that remain.
+    pub fn dedupe_allof(&mut self) {
+        let branch_count = self
+            .schema
+            .get("allOf")
+            .and_then(|b| b.as_array())
+            .map(Vec::len);
+        let kept = dedupe_allof_recursive(&mut self.schema);
+        if let (Some(kept), Some(count)) = (kept, branch_count) {
+            if count == self.origins.len() {
+                self.origins = kept.iter().map(|&i| self.origins[i].clone()).collect();
+            }
+        }
+    }
 }
 
 /// Capability declaration extracted from UCP metadata.
@@ -184,26 +371,100 @@ pub fn extract_jsonrpc_payload<'a>(
     envelope: &'a Value,
     capabilities: &[Capability],
 ) -> Result<(&'a Value, String), ComposeError> {
-    // Find root capability (no extends)
-    let root = capabilities
+    // Payload keys are short names (last segment of dotted name) of root capabilities
+    let candidates: Vec<String> = capabilities
         .iter()
-        .find(|c| c.extends.is_none())
-        .ok_or(ComposeError::NoRootCapability)?;
-
-    // Derive short name from capability name (last segment of dotted name)
-    let short_name = capability_short_name(&root.name);
-
-    // Extract payload from envelope using short name as key
-    let payload = envelope
-        .get(&short_name)
-        .ok_or_else(|| ComposeError::InvalidEnvelope {
-            message: format!(
-                "JSONRPC envelope missing '{}' key (derived from capability '{}')",
-                short_name, root.name
-            ),
-        })?;
+        .filter(|c| c.extends.is_none())
+        .map(|c| capability_short_name(&c.name))
+        .collect();
+    if candidates.is_empty() {
+        return Err(ComposeError::NoRootCapability);
+    }
+
+    // Extract payload from envelope using the first candidate present
+    candidates
+        .iter()
+        .find_map(|key| envelope.get(key).map(|payload| (payload, key.clone())))
+        .ok_or(ComposeError::NoMatchingParams {
+            expected: candidates,
+        })
+}
 
-    Ok((payload, short_name))
+/// Wrap a payload in a JSONRPC request, the inverse of
+/// [`extract_jsonrpc_payload`].
+///
+/// The payload goes under the short name of a root capability: `capability`
+/// (full or short name) when given, otherwise the profile's only root. The
+/// method is `<operation>_<short name>` (e.g., "create_checkout"), and
+/// `params.meta.profile` is `profile_url`.
+pub fn jsonrpc_envelope(
+    payload: &Value,
+    capabilities: &[Capability],
+    profile_url: &str,
+    operation: &str,
+    capability: Option<&str>,
+    id: Value,
+) -> Result<Value, ComposeError> {
+    let roots: Vec<&Capability> = capabilities
+        .iter()
+        .filter(|c| c.extends.is_none())
+        .collect();
+    let root = match (capability, roots.as_slice()) {
+        (Some(name), _) => roots
+            .iter()
+            .find(|c| c.name == name || capability_short_name(&c.name) == name)
+            .ok_or_else(|| ComposeError::InvalidCapability {
+                name: name.to_string(),
+                message: format!(
+                    "not a root capability of the profile (expected one of: {})",
+                    roots
+                        .iter()
+                        .map(|c| c.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            })?,
+        (None, []) => return Err(ComposeError::NoRootCapability),
+        (None, [root]) => root,
+        (None, _) => {
+            return Err(ComposeError::MultipleRootCapabilities {
+                names: roots.iter().map(|c| c.name.clone()).collect(),
+            })
+        }
+    };
+
+    let key = capability_short_name(&root.name);
+    let mut params = serde_json::Map::new();
+    params.insert("meta".to_string(), json!({ "profile": profile_url }));
+    params.insert(key.clone(), payload.clone());
+    Ok(json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": format!("{}_{}", operation, key),
+        "params": params,
+    }))
+}
+
+/// Match a capability name against a glob pattern where `*` matches any run
+/// of characters (e.g., `dev.ucp.shopping.*`).
+pub fn capability_matches(pattern: &str, name: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == name;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !name.starts_with(first) || name.len() < first.len() + last.len() {
+        return false;
+    }
+    let mut rest = &name[first.len()..name.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    name.ends_with(last)
 }
 
 /// Derive short name from a capability name.
@@ -214,8 +475,96 @@ pub fn capability_short_name(name: &str) -> String {
     name.rsplit('.').next().unwrap_or(name).to_string()
 }
 
+/// Build capability declarations from schema files, to compose without a
+/// self-describing payload.
+///
+/// A file's capability name is its `name` (e.g., "dev.ucp.shopping.checkout"),
+/// or its file stem when it has none. `extends` holds `(extension, parent)`
+/// pairs; each side may be a full name, a short name ("checkout") or a file
+/// stem. Versions are taken from the schemas themselves.
+pub fn capabilities_from_files(
+    files: &[PathBuf],
+    extends: &[(String, String)],
+) -> Result<Vec<Capability>, ComposeError> {
+    let mut capabilities: Vec<Capability> = Vec::new();
+    let mut stems = Vec::new();
+    for file in files {
+        let url = file.display().to_string();
+        let schema = load_schema(file).map_err(|e| ComposeError::SchemaFetch {
+            url: url.clone(),
+            message: e.to_string(),
+        })?;
+        let stem = file
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let name = schema
+            .get("name")
+            .and_then(Value::as_str)
+            .map_or_else(|| stem.clone(), str::to_string);
+        if let Some(other) = capabilities.iter().find(|c| c.name == name) {
+            return Err(ComposeError::InvalidCapability {
+                name,
+                message: format!("declared by both {} and {}", other.schema_url, url),
+            });
+        }
+        let version = schema
+            .get("x-ucp-version")
+            .or_else(|| schema.get("version"))
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        capabilities.push(Capability {
+            name,
+            version,
+            schema_url: url,
+            extends: None,
+        });
+        stems.push(stem);
+    }
+
+    let lookup = |alias: &str| -> Result<usize, ComposeError> {
+        let matches: Vec<usize> = (0..capabilities.len())
+            .filter(|&i| {
+                let name = &capabilities[i].name;
+                name == alias || capability_short_name(name) == alias || stems[i] == alias
+            })
+            .collect();
+        match matches.as_slice() {
+            [i] => Ok(*i),
+            [] => Err(ComposeError::InvalidCapability {
+                name: alias.to_string(),
+                message: "matches none of the given schema files".to_string(),
+            }),
+            _ => Err(ComposeError::InvalidCapability {
+                name: alias.to_string(),
+                message: format!(
+                    "ambiguous; matches {}",
+                    matches
+                        .iter()
+                        .map(|&i| capabilities[i].name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            }),
+        }
+    };
+    let mut parents: Vec<Vec<String>> = vec![Vec::new(); capabilities.len()];
+    for (extension, parent) in extends {
+        let (child, parent) = (lookup(extension)?, lookup(parent)?);
+        parents[child].push(capabilities[parent].name.clone());
+    }
+
+    for (capability, parents) in capabilities.iter_mut().zip(parents) {
+        if !parents.is_empty() {
+            capability.extends = Some(parents);
+        }
+    }
+    Ok(capabilities)
+}
+
 /// Parse a capabilities object into a list of Capability structs.
-fn parse_capabilities_object(caps: &Value) -> Result<Vec<Capability>, ComposeError> {
+pub(crate) fn parse_capabilities_object(caps: &Value) -> Result<Vec<Capability>, ComposeError> {
     let obj = caps.as_object().ok_or(ComposeError::EmptyCapabilities)?;
 
     if obj.is_empty() {
@@ -304,6 +653,20 @@ fn fetch_profile(url: &str, schema_base: &SchemaBaseConfig) -> Result<Value, Com
     })
 }
 
+/// Wrap a schema resolution error as a fetch failure for `url`.
+///
+/// Remote base mismatches pass through unchanged so the configuration hint
+/// isn't buried inside a generic fetch message.
+fn wrap_fetch_error(url: &str, e: ComposeError) -> ComposeError {
+    match e {
+        ComposeError::RemoteBaseMismatch { .. } | ComposeError::UnmappedSchemaUrl { .. } => e,
+        e => ComposeError::SchemaFetch {
+            url: url.to_string(),
+            message: e.to_string(),
+        },
+    }
+}
+
 /// Compose schema from capability declarations.
 ///
 /// 1. Finds root capability (no extends)
@@ -314,9 +677,52 @@ pub fn compose_schema(
     capabilities: &[Capability],
     schema_base: &SchemaBaseConfig,
 ) -> Result<Value, ComposeError> {
+    compose_schema_with_options(capabilities, schema_base, &ComposeOptions::default())
+        .map(|c| c.schema)
+}
+
+/// Like `compose_schema`, with options and warnings.
+///
+/// With `allow_orphan_extensions`, an extension whose parent is absent is
+/// composed as a standalone branch: its `$defs` entry is taken for the root
+/// name if present, otherwise for its (missing) parent. When no root exists
+/// at all, the orphans alone form the composition.
+pub fn compose_schema_with_options(
+    capabilities: &[Capability],
+    schema_base: &SchemaBaseConfig,
+    options: &ComposeOptions,
+) -> Result<Composed, ComposeError> {
+    let mut warnings = Vec::new();
+    let filtered;
+    let capabilities = match &options.min_version {
+        Some(min_version) => {
+            filtered = filter_min_version(capabilities, min_version, options, &mut warnings)?;
+            filtered.as_slice()
+        }
+        None => capabilities,
+    };
     if capabilities.is_empty() {
         return Err(ComposeError::EmptyCapabilities);
     }
+    let mut skipped = Vec::new();
+    let selected: Vec<Capability>;
+    let capabilities = match &options.capability_filter {
+        Some(filter) => {
+            selected = filter_capabilities(capabilities, filter, &mut skipped)?;
+            selected.as_slice()
+        }
+        None => capabilities,
+    };
+    let roots_only: Vec<Capability>;
+    let capabilities = if options.root_only {
+        let (kept, extensions): (Vec<&Capability>, Vec<&Capability>) =
+            capabilities.iter().partition(|c| c.extends.is_none());
+        skipped.extend(extensions.iter().map(|c| c.name.clone()));
+        roots_only = kept.into_iter().cloned().collect();
+        roots_only.as_slice()
+    } else {
+        capabilities
+    };
 
     // Build name -> capability map for lookups
     let cap_map: HashMap<&str, &Capability> =
@@ -328,9 +734,18 @@ pub fn compose_schema(
         .filter(|c| c.extends.is_none())
         .collect();
 
+    // Extensions whose parent is absent from the capability set
+    let unknown_parents: Vec<(&Capability, &String)> = capabilities
+        .iter()
+        .flat_map(|c| c.extends.iter().flatten().map(move |p| (c, p)))
+        .filter(|(_, p)| !cap_map.contains_key(p.as_str()))
+        .collect();
+    let allow_orphans = options.allow_orphan_extensions && !unknown_parents.is_empty();
+
     let root = match roots.len() {
+        0 if allow_orphans => None,
         0 => return Err(ComposeError::NoRootCapability),
-        1 => roots[0],
+        1 => Some(roots[0]),
         _ => {
             return Err(ComposeError::MultipleRootCapabilities {
                 names: roots.iter().map(|c| c.name.clone()).collect(),
@@ -339,25 +754,35 @@ pub fn compose_schema(
     };
 
     // Validate graph: all extends references must exist in capabilities
-    for cap in capabilities {
-        if let Some(parents) = &cap.extends {
-            for parent in parents {
-                if !cap_map.contains_key(parent.as_str()) {
-                    return Err(ComposeError::UnknownParent {
-                        extension: cap.name.clone(),
-                        parent: parent.clone(),
-                    });
-                }
-            }
+    let mut orphans: HashSet<&str> = HashSet::new();
+    for (cap, parent) in unknown_parents {
+        if !options.allow_orphan_extensions {
+            return Err(ComposeError::UnknownParent {
+                extension: cap.name.clone(),
+                parent: parent.clone(),
+            });
         }
+        warnings.push(format!(
+            "extension '{}' extends unknown parent '{}'; composing as standalone branch",
+            cap.name, parent
+        ));
+        orphans.insert(cap.name.as_str());
     }
 
     // Validate graph connectivity: all extensions must reach root
+    // (orphans are already reported and composed standalone)
     for cap in capabilities {
-        if cap.extends.is_some() && !reaches_root(cap, &cap_map, &root.name) {
+        if cap.extends.is_none() || orphans.contains(cap.name.as_str()) {
+            continue;
+        }
+        let connected = root.is_some_and(|r| reaches_root(cap, &cap_map, &r.name));
+        if !connected {
+            if options.allow_orphan_extensions && reaches_orphan(cap, &cap_map, &orphans) {
+                continue;
+            }
             return Err(ComposeError::OrphanExtension {
                 extension: cap.name.clone(),
-                root: root.name.clone(),
+                root: root.map(|r| r.name.clone()).unwrap_or_default(),
             });
         }
     }
@@ -369,12 +794,17 @@ pub fn compose_schema(
         .collect();
 
     // If no extensions, just return the root schema
-    if extensions.is_empty() {
-        return resolve_schema_url(&root.schema_url, schema_base).map_err(|e| {
-            ComposeError::SchemaFetch {
-                url: root.schema_url.clone(),
-                message: e.to_string(),
-            }
+    let mut deps = Vec::new();
+    if let (Some(root), true) = (root, extensions.is_empty()) {
+        let schema = load_capability_schema(&root.schema_url, schema_base, &mut deps)
+            .map_err(|e| wrap_fetch_error(&root.schema_url, e))?;
+        check_version(root, &schema, options, &mut warnings)?;
+        return Ok(Composed {
+            schema,
+            warnings,
+            origins: vec![root.name.clone()],
+            deps,
+            skipped,
         });
     }
 
@@ -382,27 +812,31 @@ pub fn compose_schema(
     let mut all_of_schemas = Vec::new();
 
     for ext in &extensions {
-        let ext_schema = resolve_schema_url(&ext.schema_url, schema_base).map_err(|e| {
-            ComposeError::SchemaFetch {
-                url: ext.schema_url.clone(),
-                message: e.to_string(),
-            }
-        })?;
+        let ext_schema = load_capability_schema(&ext.schema_url, schema_base, &mut deps)
+            .map_err(|e| wrap_fetch_error(&ext.schema_url, e))?;
+        check_version(ext, &ext_schema, options, &mut warnings)?;
+
+        // Orphans may define their base under the missing parent's name
+        let mut keys: Vec<&str> = root.map(|r| r.name.as_str()).into_iter().collect();
+        if root.is_none() || orphans.contains(ext.name.as_str()) {
+            keys.extend(ext.extends.iter().flatten().map(String::as_str));
+        }
+        let expected_key = keys.first().copied().unwrap_or_default().to_string();
 
         // Extract $defs[root.name] and inline any internal refs
         let defs = ext_schema
             .get("$defs")
             .ok_or_else(|| ComposeError::MissingDefEntry {
                 extension: ext.name.clone(),
-                expected_key: root.name.clone(),
+                expected_key: expected_key.clone(),
             })?;
 
-        let ext_def = defs
-            .get(&root.name)
-            .ok_or_else(|| ComposeError::MissingDefEntry {
+        let ext_def = keys.iter().find_map(|k| defs.get(*k)).ok_or_else(|| {
+            ComposeError::MissingDefEntry {
                 extension: ext.name.clone(),
-                expected_key: root.name.clone(),
-            })?;
+                expected_key,
+            }
+        })?;
 
         // Inline internal #/$defs/... refs so the extracted def is self-contained
         let mut inlined = ext_def.clone();
@@ -411,8 +845,141 @@ pub fn compose_schema(
         all_of_schemas.push(inlined);
     }
 
+    merge_array_items(&mut all_of_schemas);
+
     // Compose into single schema with allOf
-    Ok(json!({ "allOf": all_of_schemas }))
+    Ok(Composed {
+        schema: json!({ "allOf": all_of_schemas }),
+        warnings,
+        origins: extensions.iter().map(|e| e.name.clone()).collect(),
+        deps,
+        skipped,
+    })
+}
+
+/// Remove duplicate and empty `allOf` branches throughout a schema.
+///
+/// Structurally identical branches keep their first occurrence; `{}` and
+/// `true` branches are dropped. An `allOf` left with one branch on an
+/// otherwise empty object collapses into that branch, and one left with none
+/// is removed. Validation outcomes are unchanged.
+pub fn dedupe_allof(schema: &mut Value) {
+    dedupe_allof_recursive(schema);
+}
+
+/// Returns the original indices of the kept branches of `value`'s own `allOf`.
+fn dedupe_allof_recursive(value: &mut Value) -> Option<Vec<usize>> {
+    let kept = match value {
+        Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                if !matches!(key.as_str(), "const" | "enum" | "default" | "examples") {
+                    dedupe_allof_recursive(child);
+                }
+            }
+            let Some(Value::Array(branches)) = map.get_mut("allOf") else {
+                return None;
+            };
+            let mut kept = Vec::new();
+            let mut unique: Vec<Value> = Vec::new();
+            for (i, branch) in branches.drain(..).enumerate() {
+                let empty = branch == Value::Bool(true) || branch == json!({});
+                if !empty && !unique.contains(&branch) {
+                    kept.push(i);
+                    unique.push(branch);
+                }
+            }
+            *branches = unique;
+            kept
+        }
+        Value::Array(arr) => {
+            for item in arr {
+                dedupe_allof_recursive(item);
+            }
+            return None;
+        }
+        _ => return None,
+    };
+
+    if let Value::Object(map) = value {
+        let remaining = map.get("allOf").and_then(|b| b.as_array()).map(Vec::len);
+        match remaining {
+            Some(0) => {
+                map.remove("allOf");
+            }
+            Some(1) if map.len() == 1 => {
+                if let Some(Value::Array(mut branches)) = map.remove("allOf") {
+                    *value = branches.remove(0);
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(kept)
+}
+
+/// Merge per-item schemas of same-named array properties across branches.
+///
+/// allOf merges at the object level: when the base declares `line_items` as
+/// an array of `{sku, quantity}` and an extension adds `discount` to each
+/// item, each branch still checks items against its own item schema only.
+/// Once two or more distinct `items` schemas are declared for a top-level
+/// property (in any branch, including branches' own `allOf`), every
+/// declaration's `items` becomes `{ "allOf": [<each item schema>] }` so all
+/// item constraints apply together — and strict mode can close items with
+/// `unevaluatedProperties` across them.
+fn merge_array_items(branches: &mut [Value]) {
+    let mut items: Vec<(String, Vec<Value>)> = Vec::new();
+    for branch in branches.iter() {
+        collect_array_items(branch, &mut items);
+    }
+
+    for (name, schemas) in items.iter().filter(|(_, s)| s.len() > 1) {
+        let merged = json!({ "allOf": schemas });
+        for branch in branches.iter_mut() {
+            replace_array_items(branch, name, &merged);
+        }
+    }
+}
+
+/// Collect distinct `properties/<name>/items` object schemas, recursing into `allOf`.
+fn collect_array_items(schema: &Value, out: &mut Vec<(String, Vec<Value>)>) {
+    if let Some(props) = schema.get("properties").and_then(|p| p.as_object()) {
+        for (name, prop) in props {
+            let Some(item) = prop.get("items").filter(|i| i.is_object()) else {
+                continue;
+            };
+            match out.iter_mut().find(|(n, _)| n == name) {
+                Some((_, schemas)) if !schemas.contains(item) => schemas.push(item.clone()),
+                Some(_) => {}
+                None => out.push((name.clone(), vec![item.clone()])),
+            }
+        }
+    }
+    for branch in schema
+        .get("allOf")
+        .and_then(|a| a.as_array())
+        .into_iter()
+        .flatten()
+    {
+        collect_array_items(branch, out);
+    }
+}
+
+fn replace_array_items(schema: &mut Value, name: &str, merged: &Value) {
+    if let Some(prop) = schema
+        .get_mut("properties")
+        .and_then(|p| p.get_mut(name))
+        .and_then(|p| p.as_object_mut())
+    {
+        if prop.get("items").is_some_and(Value::is_object) {
+            prop.insert("items".to_string(), merged.clone());
+        }
+    }
+    if let Some(branches) = schema.get_mut("allOf").and_then(|a| a.as_array_mut()) {
+        for branch in branches {
+            replace_array_items(branch, name, merged);
+        }
+    }
 }
 
 /// Inline internal `#/$defs/...` refs from the parent schema.
@@ -502,6 +1069,32 @@ fn reaches_root(cap: &Capability, cap_map: &HashMap<&str, &Capability>, root_nam
     false
 }
 
+/// Check if a capability's extends chain leads to a known orphan extension.
+fn reaches_orphan(
+    cap: &Capability,
+    cap_map: &HashMap<&str, &Capability>,
+    orphans: &HashSet<&str>,
+) -> bool {
+    let mut visited = HashSet::new();
+    let mut queue = vec![cap];
+
+    while let Some(current) = queue.pop() {
+        if !visited.insert(current.name.as_str()) {
+            continue;
+        }
+        for parent_name in current.extends.iter().flatten() {
+            if orphans.contains(parent_name.as_str()) {
+                return true;
+            }
+            if let Some(parent) = cap_map.get(parent_name.as_str()) {
+                queue.push(parent);
+            }
+        }
+    }
+
+    false
+}
+
 /// Convenience: extract capabilities and compose schema in one call.
 pub fn compose_from_payload(
     payload: &Value,
@@ -511,6 +1104,142 @@ pub fn compose_from_payload(
     compose_schema(&capabilities, schema_base)
 }
 
+/// Like `compose_from_payload`, with options and warnings.
+pub fn compose_from_payload_with_options(
+    payload: &Value,
+    schema_base: &SchemaBaseConfig,
+    options: &ComposeOptions,
+) -> Result<Composed, ComposeError> {
+    let capabilities = extract_capabilities(payload, schema_base)?;
+    compose_schema_with_options(&capabilities, schema_base, options)
+}
+
+/// Drop capabilities older than `min_version`, noting each in `warnings`.
+///
+/// Versions are ISO dates, so they compare lexicographically. Capabilities
+/// with a non-date version can't be compared and are kept (with a note).
+/// Dropping a parent that a kept extension still extends is an error unless
+/// orphan extensions are allowed.
+fn filter_min_version(
+    capabilities: &[Capability],
+    min_version: &str,
+    options: &ComposeOptions,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<Capability>, ComposeError> {
+    let (kept, dropped): (Vec<&Capability>, Vec<&Capability>) =
+        capabilities.iter().partition(|c| {
+            if !is_iso_date(&c.version) {
+                warnings.push(format!(
+                    "{} version '{}' is not an ISO date; kept regardless of minimum version",
+                    c.name, c.version
+                ));
+                return true;
+            }
+            c.version.as_str() >= min_version
+        });
+
+    for cap in &dropped {
+        warnings.push(format!(
+            "dropped {} (version {} is older than {})",
+            cap.name, cap.version, min_version
+        ));
+    }
+
+    if !options.allow_orphan_extensions {
+        for ext in &kept {
+            for parent in ext.extends.iter().flatten() {
+                if dropped.iter().any(|d| &d.name == parent) {
+                    return Err(ComposeError::ParentBelowMinVersion {
+                        extension: ext.name.clone(),
+                        parent: parent.clone(),
+                        min_version: min_version.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(kept.into_iter().cloned().collect())
+}
+
+/// Keep capabilities whose name matches `filter` and every capability they
+/// transitively extend; the names of the rest go to `skipped`.
+fn filter_capabilities(
+    capabilities: &[Capability],
+    filter: &Regex,
+    skipped: &mut Vec<String>,
+) -> Result<Vec<Capability>, ComposeError> {
+    let mut keep: HashSet<&str> = HashSet::new();
+    let mut pending: Vec<&str> = capabilities
+        .iter()
+        .map(|c| c.name.as_str())
+        .filter(|name| filter.is_match(name))
+        .collect();
+    if pending.is_empty() {
+        return Err(ComposeError::NoCapabilityMatchesFilter {
+            pattern: filter.as_str().to_string(),
+        });
+    }
+    while let Some(name) = pending.pop() {
+        if !keep.insert(name) {
+            continue;
+        }
+        for cap in capabilities.iter().filter(|c| c.name == name) {
+            pending.extend(cap.extends.iter().flatten().map(String::as_str));
+        }
+    }
+
+    let (kept, dropped): (Vec<&Capability>, Vec<&Capability>) = capabilities
+        .iter()
+        .partition(|c| keep.contains(c.name.as_str()));
+    skipped.extend(dropped.iter().map(|c| c.name.clone()));
+    Ok(kept.into_iter().cloned().collect())
+}
+
+/// Compare a capability's advertised version with the one its schema declares.
+///
+/// The declared version is `x-ucp-version`, else `version`; schemas declaring
+/// neither pass. A mismatch usually means `--schema-local-base` maps the URL
+/// to a stale file.
+fn check_version(
+    cap: &Capability,
+    schema: &Value,
+    options: &ComposeOptions,
+    warnings: &mut Vec<String>,
+) -> Result<(), ComposeError> {
+    let declared = schema
+        .get("x-ucp-version")
+        .or_else(|| schema.get("version"))
+        .and_then(|v| v.as_str());
+    let Some(declared) = declared.filter(|d| *d != cap.version) else {
+        return Ok(());
+    };
+
+    if options.strict_version {
+        return Err(ComposeError::VersionMismatch {
+            name: cap.name.clone(),
+            advertised: cap.version.clone(),
+            declared: declared.to_string(),
+            url: cap.schema_url.clone(),
+        });
+    }
+    warnings.push(format!(
+        "{} advertises version {} but its schema ({}) declares {}",
+        cap.name, cap.version, cap.schema_url, declared
+    ));
+    Ok(())
+}
+
+/// Returns true for `YYYY-MM-DD` shaped strings.
+pub fn is_iso_date(s: &str) -> bool {
+    let bytes = s.as_bytes();
+    bytes.len() == 10
+        && bytes.iter().enumerate().all(|(i, b)| match i {
+            4 | 7 => *b == b'-',
+            _ => b.is_ascii_digit(),
+        })
+}
+
 /// Resolve a schema URL to a Value, bundling any $ref pointers.
 ///
 /// If `schema_base.local_base` is provided, maps URL paths to local files.
@@ -522,7 +1251,56 @@ pub fn compose_from_payload(
 /// This is necessary because extension schemas often have relative refs like
 /// `$ref: "checkout.json"` that need resolution before composition.
 fn resolve_schema_url(url: &str, schema_base: &SchemaBaseConfig) -> Result<Value, ComposeError> {
-    if let Some(base) = schema_base.local_base {
+    load_capability_schema(url, schema_base, &mut Vec::new())
+}
+
+/// `resolve_schema_url`, additionally appending every local file read to `deps`.
+pub(crate) fn load_capability_schema(
+    url: &str,
+    schema_base: &SchemaBaseConfig,
+    deps: &mut Vec<PathBuf>,
+) -> Result<Value, ComposeError> {
+    if !schema_base.mappings.is_empty() && is_url(url) {
+        let (mapping, remainder) = schema_base
+            .mappings
+            .iter()
+            .find_map(|m| url.strip_prefix(m.prefix.as_str()).map(|rest| (m, rest)))
+            .ok_or_else(|| ComposeError::UnmappedSchemaUrl {
+                url: url.to_string(),
+                prefixes: schema_base
+                    .mappings
+                    .iter()
+                    .map(|m| m.prefix.clone())
+                    .collect(),
+            })?;
+        let root = match schema_base.local_base {
+            Some(base) => base.join(&mapping.local),
+            None => mapping.local.clone(),
+        };
+        let relative = remainder.trim_start_matches('/');
+        let confine_to = schema_base.confined_root(url, &root, relative)?;
+        let local_path = root.join(relative);
+        let mut schema = load_schema(&local_path).map_err(|_| ComposeError::SchemaFetch {
+            url: url.to_string(),
+            message: format!("file not found: {}", local_path.display()),
+        })?;
+
+        // Absolute refs under the same prefix map into the same subtree
+        let url_mapping = Some((root.as_path(), mapping.prefix.as_str()));
+        let sources = bundle_refs_with_source_map_within(
+            &mut schema,
+            &local_path,
+            url_mapping,
+            confine_to.as_deref(),
+        )
+        .map_err(|e| ComposeError::SchemaFetch {
+            url: url.to_string(),
+            message: format!("bundling refs: {}", e),
+        })?;
+        record_deps(deps, &local_path, &sources);
+
+        Ok(schema)
+    } else if let Some(base) = schema_base.local_base {
         // Map URL to local path
         let path = if let Some(remote_base) = schema_base.remote_base {
             // Strip remote_base prefix if URL starts with it
@@ -538,44 +1316,56 @@ fn resolve_schema_url(url: &str, schema_base: &SchemaBaseConfig) -> Result<Value
             extract_url_path(url)?
         };
 
-        let local_path = base.join(path.trim_start_matches('/'));
-        let mut schema = load_schema(&local_path).map_err(|_| ComposeError::SchemaFetch {
-            url: url.to_string(),
-            message: format!("file not found: {}", local_path.display()),
-        })?;
-
-        // Bundle refs - use URL-aware version if remote mapping is configured
-        let schema_dir = local_path.parent().unwrap_or(base);
-        if let Some(remote_base) = schema_base.remote_base {
-            // URL mapping configured - internal refs may also be absolute URLs
-            bundle_refs_with_url_mapping(&mut schema, schema_dir, base, remote_base).map_err(
-                |e| ComposeError::SchemaFetch {
+        let relative = path.trim_start_matches('/');
+        let confine_to = schema_base.confined_root(url, base, relative)?;
+        let local_path = base.join(relative);
+        let mut schema = load_schema(&local_path).map_err(|_| match schema_base.remote_base {
+            // The fallback path only exists because the prefix didn't match —
+            // point at the likely misconfiguration rather than the missing file
+            Some(remote_base) if !schema_base.matches_remote_base(url) => {
+                ComposeError::RemoteBaseMismatch {
                     url: url.to_string(),
-                    message: format!("bundling refs: {}", e),
-                },
-            )?;
-        } else {
-            // No URL mapping - use simple relative path bundling
-            bundle_refs(&mut schema, schema_dir).map_err(|e| ComposeError::SchemaFetch {
+                    remote_base: remote_base.to_string(),
+                }
+            }
+            _ => ComposeError::SchemaFetch {
                 url: url.to_string(),
-                message: format!("bundling refs: {}", e),
-            })?;
-        }
+                message: format!("file not found: {}", local_path.display()),
+            },
+        })?;
+
+        // Bundle refs - with URL mapping if configured, since internal refs
+        // may also be absolute URLs
+        let url_mapping = schema_base.remote_base.map(|remote| (base, remote));
+        let sources = bundle_refs_with_source_map_within(
+            &mut schema,
+            &local_path,
+            url_mapping,
+            confine_to.as_deref(),
+        )
+        .map_err(|e| ComposeError::SchemaFetch {
+            url: url.to_string(),
+            message: format!("bundling refs: {}", e),
+        })?;
+        record_deps(deps, &local_path, &sources);
 
         Ok(schema)
     } else if is_url(url) {
         // HTTP fetch with remote bundling
         #[cfg(feature = "remote")]
         {
-            let mut schema = load_schema_url(url).map_err(|e| ComposeError::SchemaFetch {
-                url: url.to_string(),
-                message: e.to_string(),
-            })?;
+            let mut schema =
+                load_schema_url(url, schema_base.fetch).map_err(|e| ComposeError::SchemaFetch {
+                    url: url.to_string(),
+                    message: e.to_string(),
+                })?;
 
             // Bundle refs using the URL as base for resolving relative refs
-            bundle_refs_remote(&mut schema, url).map_err(|e| ComposeError::SchemaFetch {
-                url: url.to_string(),
-                message: format!("bundling refs: {}", e),
+            bundle_refs_remote(&mut schema, url, schema_base.fetch).map_err(|e| {
+                ComposeError::SchemaFetch {
+                    url: url.to_string(),
+                    message: format!("bundling refs: {}", e),
+                }
             })?;
 
             Ok(schema)
@@ -587,6 +1377,11 @@ fn resolve_schema_url(url: &str, schema_base: &SchemaBaseConfig) -> Result<Value
                 message: "HTTP fetching requires 'remote' feature".to_string(),
             })
         }
+    } else if schema_base.confine {
+        Err(ComposeError::SchemaFetch {
+            url: url.to_string(),
+            message: "not a URL, and there's no local base to read paths under".to_string(),
+        })
     } else {
         // Treat as local file path
         let local_path = Path::new(url);
@@ -596,21 +1391,32 @@ fn resolve_schema_url(url: &str, schema_base: &SchemaBaseConfig) -> Result<Value
         })?;
 
         // Bundle refs using the schema's directory as base
-        if let Some(schema_dir) = local_path.parent() {
-            bundle_refs(&mut schema, schema_dir).map_err(|e| ComposeError::SchemaFetch {
+        let sources = bundle_refs_with_source_map_within(&mut schema, local_path, None, None)
+            .map_err(|e| ComposeError::SchemaFetch {
                 url: url.to_string(),
                 message: format!("bundling refs: {}", e),
             })?;
-        }
+        record_deps(deps, local_path, &sources);
 
         Ok(schema)
     }
 }
 
+/// Append `file` and the files its bundling read to `deps`, skipping repeats.
+fn record_deps(deps: &mut Vec<PathBuf>, file: &Path, sources: &[SourceMapEntry]) {
+    let files =
+        std::iter::once(file.to_path_buf()).chain(sources.iter().map(|e| PathBuf::from(&e.source)));
+    for path in files {
+        if !deps.contains(&path) {
+            deps.push(path);
+        }
+    }
+}
+
 /// Extract the path portion from a URL.
 ///
 /// E.g., "https://ucp.dev/schemas/shopping/checkout.json" -> "/schemas/shopping/checkout.json"
-fn extract_url_path(url: &str) -> Result<String, ComposeError> {
+pub(crate) fn extract_url_path(url: &str) -> Result<String, ComposeError> {
     // Try stripping http:// or https:// prefix
     let rest = url
         .strip_prefix("https://")
@@ -700,6 +1506,39 @@ mod tests {
         assert_eq!(detect_direction(&payload), None);
     }
 
+    #[test]
+    fn capabilities_from_files_resolves_aliases() {
+        let dir = "tests/fixtures/compose/schemas/shopping";
+        let files: Vec<PathBuf> = ["checkout.json", "discount.json", "fulfillment.json"]
+            .iter()
+            .map(|f| Path::new(dir).join(f))
+            .collect();
+        let extends = [
+            ("discount".to_string(), "checkout".to_string()),
+            (
+                "dev.ucp.shopping.fulfillment".to_string(),
+                "checkout".to_string(),
+            ),
+        ];
+        let caps = capabilities_from_files(&files, &extends).unwrap();
+
+        assert_eq!(caps[0].name, "dev.ucp.shopping.checkout");
+        assert_eq!(caps[0].version, "2026-01-11");
+        assert!(caps[0].extends.is_none());
+        assert_eq!(
+            caps[1].extends,
+            Some(vec!["dev.ucp.shopping.checkout".to_string()])
+        );
+        assert_eq!(
+            caps[2].extends,
+            Some(vec!["dev.ucp.shopping.checkout".to_string()])
+        );
+
+        let unknown = [("discount".to_string(), "cart".to_string())];
+        let err = capabilities_from_files(&files, &unknown).unwrap_err();
+        assert!(err.to_string().contains("'cart'"));
+    }
+
     #[test]
     fn parse_capabilities_single_root() {
         let caps = json!({
@@ -820,11 +1659,121 @@ mod tests {
         let config = SchemaBaseConfig {
             local_base: Some(Path::new("/nonexistent")),
             remote_base: None,
+            ..Default::default()
         };
         let result = compose_schema(&[checkout], &config);
         assert!(matches!(result, Err(ComposeError::SchemaFetch { .. })));
     }
 
+    #[test]
+    fn filter_capabilities_keeps_ancestors() {
+        let cap = |name: &str, extends: Option<&str>| Capability {
+            name: name.to_string(),
+            version: "2026-01-11".to_string(),
+            schema_url: format!("{}.json", name),
+            extends: extends.map(|p| vec![p.to_string()]),
+        };
+        let caps = vec![
+            cap("dev.ucp.shopping.checkout", None),
+            cap(
+                "dev.ucp.shopping.discount",
+                Some("dev.ucp.shopping.checkout"),
+            ),
+            cap(
+                "dev.ucp.shopping.fulfillment",
+                Some("dev.ucp.shopping.checkout"),
+            ),
+        ];
+
+        let mut skipped = Vec::new();
+        let filter = Regex::new("discount$").unwrap();
+        let kept = filter_capabilities(&caps, &filter, &mut skipped).unwrap();
+        let names: Vec<&str> = kept.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["dev.ucp.shopping.checkout", "dev.ucp.shopping.discount"]
+        );
+        assert_eq!(skipped, ["dev.ucp.shopping.fulfillment"]);
+
+        let filter = Regex::new("^com\\.example").unwrap();
+        assert!(matches!(
+            filter_capabilities(&caps, &filter, &mut skipped),
+            Err(ComposeError::NoCapabilityMatchesFilter { .. })
+        ));
+    }
+
+    #[test]
+    fn compose_remote_base_mismatch_error() {
+        let checkout = Capability {
+            name: "dev.ucp.shopping.checkout".to_string(),
+            version: "2026-01-11".to_string(),
+            schema_url: "https://ucp.dev/draft/schemas/checkout.json".to_string(),
+            extends: None,
+        };
+
+        let config = SchemaBaseConfig {
+            local_base: Some(Path::new("/nonexistent")),
+            remote_base: Some("https://ucp.dev/2026-01-11"),
+            ..Default::default()
+        };
+        let result = compose_schema(&[checkout], &config);
+        assert!(matches!(
+            result,
+            Err(ComposeError::RemoteBaseMismatch { ref remote_base, .. })
+                if remote_base == "https://ucp.dev/2026-01-11"
+        ));
+    }
+
+    #[test]
+    fn matches_remote_base() {
+        let config = SchemaBaseConfig {
+            local_base: Some(Path::new("schemas")),
+            remote_base: Some("https://ucp.dev/draft"),
+            ..Default::default()
+        };
+        assert!(config.matches_remote_base("https://ucp.dev/draft/schemas/checkout.json"));
+        assert!(!config.matches_remote_base("https://ucp.dev/schemas/checkout.json"));
+        assert!(SchemaBaseConfig::default().matches_remote_base("https://ucp.dev/x.json"));
+    }
+
+    #[test]
+    fn url_mapping_parse() {
+        let mapping = UrlMapping::parse("https://ucp.dev/versioned=schemas/ucp").unwrap();
+        assert_eq!(mapping.prefix, "https://ucp.dev/versioned");
+        assert_eq!(mapping.local, PathBuf::from("schemas/ucp"));
+        assert!(UrlMapping::parse("https://ucp.dev").is_none());
+        assert!(UrlMapping::parse("=schemas").is_none());
+    }
+
+    #[test]
+    fn dedupe_allof_keeps_origins_aligned() {
+        let checkout = json!({"type": "object", "properties": {"id": {"type": "string"}}});
+        let discount = json!({"properties": {"discounts": {"type": "array"}}});
+        let mut composed = Composed {
+            schema: json!({"allOf": [checkout, {}, discount, checkout]}),
+            warnings: vec![],
+            origins: vec![
+                "checkout".to_string(),
+                "empty".to_string(),
+                "discount".to_string(),
+                "checkout-again".to_string(),
+            ],
+            deps: vec![],
+            skipped: vec![],
+        };
+        composed.dedupe_allof();
+        assert_eq!(composed.schema, json!({"allOf": [checkout, discount]}));
+        assert_eq!(composed.origins, ["checkout", "discount"]);
+
+        let mut nested = json!({
+            "properties": {"a": {"allOf": [{"type": "string"}, true, {"type": "string"}]}},
+            "default": {"allOf": [{}, {}]}
+        });
+        dedupe_allof(&mut nested);
+        assert_eq!(nested["properties"]["a"], json!({"type": "string"}));
+        assert_eq!(nested["default"], json!({"allOf": [{}, {}]}));
+    }
+
     #[test]
     fn compose_no_root_error() {
         let discount = Capability {
@@ -883,6 +1832,183 @@ mod tests {
         assert!(matches!(result, Err(ComposeError::UnknownParent { .. })));
     }
 
+    #[test]
+    fn compose_allow_orphan_extensions_with_root() {
+        let checkout = Capability {
+            name: "dev.ucp.shopping.checkout".to_string(),
+            version: "2026-01-11".to_string(),
+            schema_url: "https://ucp.dev/schemas/shopping/checkout.json".to_string(),
+            extends: None,
+        };
+        let discount = Capability {
+            name: "dev.ucp.shopping.discount".to_string(),
+            version: "2026-01-11".to_string(),
+            schema_url: "https://ucp.dev/schemas/shopping/discount.json".to_string(),
+            extends: Some(vec!["dev.ucp.shopping.nonexistent".to_string()]),
+        };
+
+        let config = SchemaBaseConfig {
+            local_base: Some(Path::new("tests/fixtures/compose")),
+            remote_base: None,
+            ..Default::default()
+        };
+        let options = ComposeOptions::new().allow_orphan_extensions(true);
+        let composed = compose_schema_with_options(&[checkout, discount], &config, &options)
+            .expect("orphan should compose");
+
+        assert_eq!(composed.warnings.len(), 1);
+        assert!(composed.warnings[0].contains("dev.ucp.shopping.nonexistent"));
+        let all_of = composed.schema["allOf"].as_array().unwrap();
+        assert_eq!(all_of.len(), 1);
+        assert!(all_of[0]["properties"]["discounts"].is_object());
+    }
+
+    #[test]
+    fn compose_min_version_drops_older_parent() {
+        let checkout = Capability {
+            name: "dev.ucp.shopping.checkout".to_string(),
+            version: "2025-06-01".to_string(),
+            schema_url: "https://ucp.dev/schemas/shopping/checkout.json".to_string(),
+            extends: None,
+        };
+        let discount = Capability {
+            name: "dev.ucp.shopping.discount".to_string(),
+            version: "2026-01-11".to_string(),
+            schema_url: "https://ucp.dev/schemas/shopping/discount.json".to_string(),
+            extends: Some(vec!["dev.ucp.shopping.checkout".to_string()]),
+        };
+        let config = SchemaBaseConfig {
+            local_base: Some(Path::new("tests/fixtures/compose")),
+            remote_base: None,
+            ..Default::default()
+        };
+        let caps = [checkout, discount];
+
+        let options = ComposeOptions::new().min_version("2026-01-01");
+        let result = compose_schema_with_options(&caps, &config, &options);
+        assert!(matches!(
+            result,
+            Err(ComposeError::ParentBelowMinVersion { ref parent, .. })
+                if parent == "dev.ucp.shopping.checkout"
+        ));
+
+        // Degraded composition keeps the extension as a standalone branch
+        let options = options.allow_orphan_extensions(true);
+        let composed = compose_schema_with_options(&caps, &config, &options).unwrap();
+        assert_eq!(composed.origins, ["dev.ucp.shopping.discount"]);
+        assert!(composed.warnings[0].starts_with("dropped dev.ucp.shopping.checkout"));
+    }
+
+    #[test]
+    fn compose_checks_declared_version() {
+        let checkout = Capability {
+            name: "dev.ucp.shopping.checkout".to_string(),
+            version: "2026-04-08".to_string(),
+            schema_url: "https://ucp.dev/schemas/shopping/checkout.json".to_string(),
+            extends: None,
+        };
+        let config = SchemaBaseConfig {
+            local_base: Some(Path::new("tests/fixtures/compose")),
+            remote_base: None,
+            ..Default::default()
+        };
+        let caps = [checkout];
+
+        let composed = compose_schema_with_options(&caps, &config, &ComposeOptions::new()).unwrap();
+        assert_eq!(composed.warnings.len(), 1);
+        assert!(composed.warnings[0].contains("declares 2026-01-11"));
+
+        let options = ComposeOptions::new().strict_version(true);
+        let result = compose_schema_with_options(&caps, &config, &options);
+        assert!(matches!(
+            result,
+            Err(ComposeError::VersionMismatch { ref declared, .. }) if declared == "2026-01-11"
+        ));
+    }
+
+    #[test]
+    fn compose_merges_extension_array_items() {
+        let checkout = Capability {
+            name: "dev.ucp.shopping.checkout".to_string(),
+            version: "2026-01-11".to_string(),
+            schema_url: "https://ucp.dev/schemas/shopping/checkout.json".to_string(),
+            extends: None,
+        };
+        let item_discount = Capability {
+            name: "dev.ucp.shopping.item_discount".to_string(),
+            version: "2026-01-11".to_string(),
+            schema_url: "https://ucp.dev/schemas/shopping/item_discount.json".to_string(),
+            extends: Some(vec!["dev.ucp.shopping.checkout".to_string()]),
+        };
+        let config = SchemaBaseConfig {
+            local_base: Some(Path::new("tests/fixtures/compose")),
+            remote_base: None,
+            ..Default::default()
+        };
+        let schema = compose_schema(&[checkout, item_discount], &config).unwrap();
+
+        // Both the base and the extension declaration see the merged item schema
+        let branches = schema["allOf"][0]["allOf"].as_array().unwrap();
+        assert_eq!(branches.len(), 2);
+        for branch in branches {
+            let items = branch["properties"]["line_items"]["items"]["allOf"]
+                .as_array()
+                .unwrap();
+            assert_eq!(items.len(), 2);
+            assert!(items[0]["properties"]["sku"].is_object());
+            assert!(items[1]["properties"]["discount"].is_object());
+        }
+    }
+
+    #[test]
+    fn merge_array_items_skips_single_declaration() {
+        let item = json!({ "type": "object", "properties": { "sku": { "type": "string" } } });
+        let mut branches = vec![
+            json!({ "properties": { "line_items": { "type": "array", "items": item } } }),
+            json!({ "properties": { "line_items": { "type": "array", "items": item } } }),
+        ];
+        let before = branches.clone();
+        merge_array_items(&mut branches);
+        assert_eq!(branches, before);
+    }
+
+    #[test]
+    fn is_iso_date_shapes() {
+        assert!(is_iso_date("2026-01-11"));
+        assert!(!is_iso_date("2026-1-11"));
+        assert!(!is_iso_date("v2"));
+        assert!(!is_iso_date("2026/01/11"));
+    }
+
+    #[test]
+    fn capability_matches_globs() {
+        assert!(capability_matches(
+            "dev.ucp.shopping.*",
+            "dev.ucp.shopping.checkout"
+        ));
+        assert!(capability_matches(
+            "*.discount",
+            "dev.ucp.shopping.discount"
+        ));
+        assert!(capability_matches(
+            "dev.*.checkout",
+            "dev.ucp.shopping.checkout"
+        ));
+        assert!(capability_matches(
+            "dev.ucp.shopping.checkout",
+            "dev.ucp.shopping.checkout"
+        ));
+        assert!(!capability_matches(
+            "dev.ucp.shopping.*",
+            "com.example.loyalty"
+        ));
+        assert!(!capability_matches(
+            "dev.ucp.shopping.checkout",
+            "dev.ucp.shopping.checkouts"
+        ));
+        assert!(!capability_matches("a*a", "a"));
+    }
+
     #[test]
     fn reaches_root_direct() {
         let checkout = Capability {
@@ -1049,6 +2175,55 @@ mod tests {
         }];
 
         let result = extract_jsonrpc_payload(&envelope, &capabilities);
-        assert!(matches!(result, Err(ComposeError::InvalidEnvelope { .. })));
+        assert!(matches!(
+            result,
+            Err(ComposeError::NoMatchingParams { ref expected }) if expected == &["checkout"]
+        ));
+    }
+
+    #[test]
+    fn jsonrpc_envelope_round_trips_through_extract() {
+        let capability = |name: &str, extends: Option<&str>| Capability {
+            name: name.to_string(),
+            version: "2026-01-26".to_string(),
+            schema_url: "https://example.com/checkout.json".to_string(),
+            extends: extends.map(|p| vec![p.to_string()]),
+        };
+        let capabilities = vec![
+            capability("dev.ucp.shopping.checkout", None),
+            capability(
+                "dev.ucp.shopping.discount",
+                Some("dev.ucp.shopping.checkout"),
+            ),
+        ];
+        let payload = json!({"line_items": []});
+
+        let request = jsonrpc_envelope(
+            &payload,
+            &capabilities,
+            "https://example.com/profile",
+            "create",
+            None,
+            json!(1),
+        )
+        .unwrap();
+        assert_eq!(request["method"], "create_checkout");
+        assert_eq!(
+            request["params"]["meta"]["profile"],
+            "https://example.com/profile"
+        );
+        let (extracted, key) = extract_jsonrpc_payload(&request["params"], &capabilities).unwrap();
+        assert_eq!((extracted, key.as_str()), (&payload, "checkout"));
+
+        let err = jsonrpc_envelope(
+            &payload,
+            &capabilities,
+            "https://example.com/profile",
+            "create",
+            Some("discount"),
+            json!(1),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ComposeError::InvalidCapability { .. }));
     }
 }
@@ -0,0 +1,294 @@
+//! Payload coverage - which parts of a resolved schema a corpus of payloads
+//! exercises.
+//!
+//! Coverage is tracked for each property, each `enum` value and each `oneOf`
+//! branch. Locations are JSON pointers into the resolved schema; `$ref`
+//! targets are tracked where they are defined, so a shared type is covered
+//! by any payload reaching it through any reference.
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::ResolveError;
+use crate::loader::escape_pointer_token;
+use crate::resolver::resolve;
+use crate::types::{Direction, ResolveOptions};
+
+/// What a coverage entry tracks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoverageKind {
+    /// A payload contains the property.
+    Property,
+    /// A payload value equals this `enum` value.
+    EnumValue,
+    /// A payload value matches this `oneOf` branch.
+    OneOfBranch,
+}
+
+/// One schema location and how many payloads exercised it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CoverageEntry {
+    pub kind: CoverageKind,
+    /// JSON pointer in the resolved schema: the property schema
+    /// (e.g., "/properties/buyer/properties/email"), the schema holding the
+    /// `enum`, or the `oneOf` branch (e.g., "/properties/payment/oneOf/1").
+    pub path: String,
+    /// The enum value, or the branch `title` when it has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    /// Number of payloads exercising this location.
+    pub hits: usize,
+}
+
+/// Coverage of a resolved schema by a set of payloads.
+#[derive(Debug, Clone, Serialize)]
+pub struct Coverage {
+    pub direction: Direction,
+    pub operation: String,
+    pub payloads: usize,
+    /// Every tracked location, in schema order.
+    pub entries: Vec<CoverageEntry>,
+}
+
+impl Coverage {
+    /// `(covered, total)` entries of `kind`.
+    pub fn summary(&self, kind: CoverageKind) -> (usize, usize) {
+        let entries = self.entries.iter().filter(|e| e.kind == kind);
+        let total = entries.clone().count();
+        (entries.filter(|e| e.hits > 0).count(), total)
+    }
+
+    /// Entries no payload exercised.
+    pub fn uncovered(&self) -> impl Iterator<Item = &CoverageEntry> {
+        self.entries.iter().filter(|e| e.hits == 0)
+    }
+}
+
+/// Resolve `schema` with `options` and measure how much of it `payloads`
+/// exercise.
+///
+/// Walks `properties`, `items`, `allOf`, `anyOf`, `oneOf` and local `$ref`s.
+/// A payload descends into a `oneOf`/`anyOf` branch only when it validates
+/// against that branch. External `$ref`s aren't followed; bundle first.
+///
+/// # Errors
+///
+/// Returns the `ResolveError` from resolving the schema.
+pub fn schema_coverage(
+    schema: &Value,
+    payloads: &[Value],
+    options: &ResolveOptions,
+) -> Result<Coverage, ResolveError> {
+    let resolved = resolve(schema, options)?;
+    let mut walker = Walker {
+        root: &resolved,
+        entries: Vec::new(),
+        index: HashMap::new(),
+        refs: Vec::new(),
+        branches: HashMap::new(),
+        hit: BTreeSet::new(),
+    };
+
+    // Register every location, then count each payload's hits once
+    walker.visit(&resolved, None, "");
+    for payload in payloads {
+        walker.visit(&resolved, Some(payload), "");
+        for i in std::mem::take(&mut walker.hit) {
+            walker.entries[i].hits += 1;
+        }
+    }
+
+    Ok(Coverage {
+        direction: options.direction,
+        operation: options.operation.clone(),
+        payloads: payloads.len(),
+        entries: walker.entries,
+    })
+}
+
+struct Walker<'a> {
+    root: &'a Value,
+    entries: Vec<CoverageEntry>,
+    index: HashMap<(CoverageKind, String, Option<String>), usize>,
+    /// `$ref`s being followed and the instance each was entered with, to
+    /// stop at recursive types once the payload stops descending.
+    refs: Vec<(&'a str, *const Value)>,
+    /// Compiled `oneOf`/`anyOf` branches by path (`None` if they don't compile).
+    branches: HashMap<String, Option<jsonschema::Validator>>,
+    /// Entries the current payload exercised.
+    hit: BTreeSet<usize>,
+}
+
+impl<'a> Walker<'a> {
+    fn visit(&mut self, schema: &'a Value, instance: Option<&Value>, path: &str) {
+        let Value::Object(map) = schema else {
+            return;
+        };
+
+        if let Some(Value::String(reference)) = map.get("$ref") {
+            if let Some(target) = reference.strip_prefix('#') {
+                let entry = (
+                    target,
+                    instance.map_or(std::ptr::null(), |v| v as *const Value),
+                );
+                if !self.refs.contains(&entry) {
+                    if let Some(def) = self.root.pointer(target) {
+                        self.refs.push(entry);
+                        self.visit(def, instance, target);
+                        self.refs.pop();
+                    }
+                }
+            }
+        }
+
+        if let Some(Value::Array(values)) = map.get("enum") {
+            for value in values {
+                let hit = instance == Some(value);
+                self.record(CoverageKind::EnumValue, path, Some(value.clone()), hit);
+            }
+        }
+
+        if let Some(Value::Array(branches)) = map.get("allOf") {
+            for (i, branch) in branches.iter().enumerate() {
+                self.visit(branch, instance, &format!("{}/allOf/{}", path, i));
+            }
+        }
+        for keyword in ["anyOf", "oneOf"] {
+            let Some(Value::Array(branches)) = map.get(keyword) else {
+                continue;
+            };
+            for (i, branch) in branches.iter().enumerate() {
+                let branch_path = format!("{}/{}/{}", path, keyword, i);
+                let matched = instance.filter(|v| self.matches(branch, &branch_path, v));
+                if keyword == "oneOf" {
+                    let title = branch.get("title").cloned();
+                    self.record(
+                        CoverageKind::OneOfBranch,
+                        &branch_path,
+                        title,
+                        matched.is_some(),
+                    );
+                }
+                self.visit(branch, matched, &branch_path);
+            }
+        }
+
+        if let Some(Value::Object(properties)) = map.get("properties") {
+            for (name, property) in properties {
+                let property_path = format!("{}/properties/{}", path, escape_pointer_token(name));
+                let value = instance.and_then(|v| v.get(name));
+                self.record(
+                    CoverageKind::Property,
+                    &property_path,
+                    None,
+                    value.is_some(),
+                );
+                self.visit(property, value, &property_path);
+            }
+        }
+
+        if let Some(items) = map.get("items") {
+            let items_path = format!("{}/items", path);
+            match instance {
+                Some(Value::Array(elements)) if !elements.is_empty() => {
+                    for element in elements {
+                        self.visit(items, Some(element), &items_path);
+                    }
+                }
+                _ => self.visit(items, None, &items_path),
+            }
+        }
+    }
+
+    fn record(&mut self, kind: CoverageKind, path: &str, value: Option<Value>, hit: bool) {
+        let key = (kind, path.to_string(), value.as_ref().map(Value::to_string));
+        let next = self.entries.len();
+        let i = *self.index.entry(key).or_insert(next);
+        if i == next {
+            self.entries.push(CoverageEntry {
+                kind,
+                path: path.to_string(),
+                value,
+                hits: 0,
+            });
+        }
+        if hit {
+            self.hit.insert(i);
+        }
+    }
+
+    /// Whether `instance` validates against a branch of the resolved schema.
+    fn matches(&mut self, branch: &Value, path: &str, instance: &Value) -> bool {
+        let root = self.root;
+        let validator = self.branches.entry(path.to_string()).or_insert_with(|| {
+            // Carry the root's definitions so local refs in the branch resolve
+            let mut schema = branch.clone();
+            if let (Value::Object(map), Some(defs)) = (&mut schema, root.get("$defs")) {
+                map.entry("$defs").or_insert_with(|| defs.clone());
+            }
+            jsonschema::validator_for(&schema).ok()
+        });
+        validator.as_ref().is_some_and(|v| v.is_valid(instance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn coverage_counts_payloads_per_location() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "ucp_request": { "create": "omit" } },
+                "status": { "type": "string", "enum": ["open", "closed"] },
+                "items": { "type": "array", "items": { "$ref": "#/$defs/item" } },
+                "payment": {
+                    "oneOf": [
+                        { "title": "Card", "type": "object", "properties": { "card": { "type": "string" } }, "required": ["card"] },
+                        { "type": "object", "properties": { "iban": { "type": "string" } }, "required": ["iban"] }
+                    ]
+                }
+            },
+            "$defs": {
+                "item": { "type": "object", "properties": { "sku": {}, "children": { "type": "array", "items": { "$ref": "#/$defs/item" } } } }
+            }
+        });
+        let payloads = [
+            json!({ "status": "open", "items": [{ "sku": "A" }], "payment": { "card": "4242" } }),
+            json!({ "status": "open", "items": [{ "children": [{ "sku": "B" }] }] }),
+        ];
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let coverage = schema_coverage(&schema, &payloads, &options).unwrap();
+
+        let hits: Vec<(&str, Option<&Value>, usize)> = coverage
+            .entries
+            .iter()
+            .map(|e| (e.path.as_str(), e.value.as_ref(), e.hits))
+            .collect();
+        assert_eq!(
+            hits,
+            [
+                ("/properties/status", None, 2),
+                ("/properties/status", Some(&json!("open")), 2),
+                ("/properties/status", Some(&json!("closed")), 0),
+                ("/properties/items", None, 2),
+                ("/$defs/item/properties/sku", None, 2),
+                ("/$defs/item/properties/children", None, 1),
+                ("/properties/payment", None, 1),
+                ("/properties/payment/oneOf/0", Some(&json!("Card")), 1),
+                ("/properties/payment/oneOf/0/properties/card", None, 1),
+                ("/properties/payment/oneOf/1", None, 0),
+                ("/properties/payment/oneOf/1/properties/iban", None, 0),
+            ]
+        );
+        assert_eq!(coverage.summary(CoverageKind::Property), (6, 7));
+        assert_eq!(coverage.summary(CoverageKind::EnumValue), (1, 2));
+        assert_eq!(coverage.uncovered().count(), 3);
+    }
+}
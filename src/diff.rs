@@ -0,0 +1,444 @@
+//! Schema diffs - what changed between two versions of a schema for one
+//! direction and operation.
+//!
+//! Both schemas are resolved first, so the diff reflects what a client of
+//! that operation actually sees. Each change is classified from the client's
+//! side: in a request, anything the client must now send (or may no longer
+//! send) is breaking; in a response, anything the client could rely on that
+//! may now be missing is breaking.
+
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::ResolveError;
+use crate::resolver::resolve;
+use crate::types::{Direction, ResolveOptions};
+
+/// Whether a property appears in a resolved schema, and if so whether it is required.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Presence {
+    Omitted,
+    Optional,
+    Required,
+}
+
+impl fmt::Display for Presence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Presence::Omitted => "omitted",
+            Presence::Optional => "optional",
+            Presence::Required => "required",
+        })
+    }
+}
+
+/// What changed at a path.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// Property added to the schema.
+    Added { required: bool },
+    /// Property removed from the schema.
+    Removed,
+    /// `type` changed; `null` stands for no `type` (any value).
+    TypeChanged { from: Value, to: Value },
+    /// Property moved into or out of `required`.
+    RequiredChanged { required: bool },
+    /// The property's `ucp_request`/`ucp_response` annotation changed how it resolves.
+    VisibilityChanged { from: Presence, to: Presence },
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangeKind::Added { required: true } => write!(f, "added (required)"),
+            ChangeKind::Added { required: false } => write!(f, "added (optional)"),
+            ChangeKind::Removed => write!(f, "removed"),
+            ChangeKind::TypeChanged { from, to } => write!(f, "type changed {} -> {}", from, to),
+            ChangeKind::RequiredChanged { required: true } => write!(f, "now required"),
+            ChangeKind::RequiredChanged { required: false } => write!(f, "no longer required"),
+            ChangeKind::VisibilityChanged { from, to } => {
+                write!(f, "visibility changed {} -> {}", from, to)
+            }
+        }
+    }
+}
+
+/// A single difference between the two resolved schemas.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SchemaChange {
+    /// JSON path in the resolved schema (e.g., "/properties/buyer/properties/email")
+    pub path: String,
+    #[serde(flatten)]
+    pub kind: ChangeKind,
+    pub breaking: bool,
+}
+
+/// All differences between two schemas for one direction and operation.
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaDiff {
+    pub direction: Direction,
+    pub operation: String,
+    pub breaking: usize,
+    pub non_breaking: usize,
+    pub changes: Vec<SchemaChange>,
+}
+
+impl SchemaDiff {
+    /// Returns true if any change breaks existing clients.
+    pub fn has_breaking(&self) -> bool {
+        self.breaking > 0
+    }
+}
+
+/// Resolve `old` and `new` with `options` and report how they differ.
+///
+/// Walks `properties`, `items`, `allOf` branches (by index) and `$defs`
+/// entries present in both schemas; `$ref` targets are compared where they
+/// are defined, not where they are used. Changes are listed in schema order.
+///
+/// # Errors
+///
+/// Returns the `ResolveError` from resolving either schema.
+pub fn diff_schemas(
+    old: &Value,
+    new: &Value,
+    options: &ResolveOptions,
+) -> Result<SchemaDiff, ResolveError> {
+    let old_resolved = resolve(old, options)?;
+    let new_resolved = resolve(new, options)?;
+
+    let walker = Walker {
+        direction: options.direction,
+        annotation: options.direction.annotation_key(),
+    };
+    let mut changes = Vec::new();
+    walker.diff_node(
+        (Some(old), Some(new)),
+        (&old_resolved, &new_resolved),
+        "",
+        &mut changes,
+    );
+
+    let breaking = changes.iter().filter(|c| c.breaking).count();
+    Ok(SchemaDiff {
+        direction: options.direction,
+        operation: options.operation.clone(),
+        breaking,
+        non_breaking: changes.len() - breaking,
+        changes,
+    })
+}
+
+struct Walker {
+    direction: Direction,
+    annotation: &'static str,
+}
+
+impl Walker {
+    /// Compare one schema node. `raw` holds the unresolved counterparts (when
+    /// they exist) so annotation changes can be told apart from edits to
+    /// `properties` or `required`.
+    fn diff_node(
+        &self,
+        raw: (Option<&Value>, Option<&Value>),
+        resolved: (&Value, &Value),
+        path: &str,
+        changes: &mut Vec<SchemaChange>,
+    ) {
+        let (old, new) = resolved;
+
+        let (old_type, new_type) = (old.get("type"), new.get("type"));
+        if old_type != new_type {
+            let breaking = match self.direction {
+                Direction::Request => !type_subset(old_type, new_type),
+                Direction::Response => !type_subset(new_type, old_type),
+            };
+            changes.push(SchemaChange {
+                path: path.to_string(),
+                kind: ChangeKind::TypeChanged {
+                    from: old_type.cloned().unwrap_or(Value::Null),
+                    to: new_type.cloned().unwrap_or(Value::Null),
+                },
+                breaking,
+            });
+        }
+
+        self.diff_properties(raw, resolved, path, changes);
+
+        if let (Some(old_items), Some(new_items)) = (old.get("items"), new.get("items")) {
+            self.diff_node(
+                (child(raw.0, "items"), child(raw.1, "items")),
+                (old_items, new_items),
+                &format!("{}/items", path),
+                changes,
+            );
+        }
+
+        if let (Some(Value::Array(old_all)), Some(Value::Array(new_all))) =
+            (old.get("allOf"), new.get("allOf"))
+        {
+            for (i, (old_branch, new_branch)) in old_all.iter().zip(new_all).enumerate() {
+                self.diff_node(
+                    (
+                        child(raw.0, "allOf").and_then(|a| a.get(i)),
+                        child(raw.1, "allOf").and_then(|a| a.get(i)),
+                    ),
+                    (old_branch, new_branch),
+                    &format!("{}/allOf/{}", path, i),
+                    changes,
+                );
+            }
+        }
+
+        if let (Some(Value::Object(old_defs)), Some(Value::Object(new_defs))) =
+            (old.get("$defs"), new.get("$defs"))
+        {
+            for (name, old_def) in old_defs {
+                let Some(new_def) = new_defs.get(name) else {
+                    continue;
+                };
+                self.diff_node(
+                    (
+                        child(child(raw.0, "$defs"), name),
+                        child(child(raw.1, "$defs"), name),
+                    ),
+                    (old_def, new_def),
+                    &format!("{}/$defs/{}", path, name),
+                    changes,
+                );
+            }
+        }
+    }
+
+    fn diff_properties(
+        &self,
+        raw: (Option<&Value>, Option<&Value>),
+        resolved: (&Value, &Value),
+        path: &str,
+        changes: &mut Vec<SchemaChange>,
+    ) {
+        let (old, new) = resolved;
+        let old_props = old.get("properties").and_then(Value::as_object);
+        let new_props = new.get("properties").and_then(Value::as_object);
+        if old_props.is_none() && new_props.is_none() {
+            return;
+        }
+
+        // Old order first, then properties only the new schema has
+        let mut names: Vec<&String> = old_props.into_iter().flat_map(|p| p.keys()).collect();
+        for name in new_props.into_iter().flat_map(|p| p.keys()) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+
+        for name in names {
+            let prop_path = format!("{}/properties/{}", path, name);
+            let old_raw = child(child(raw.0, "properties"), name);
+            let new_raw = child(child(raw.1, "properties"), name);
+            let from = presence(old, name);
+            let to = presence(new, name);
+
+            if from != to {
+                let annotation_changed = match (old_raw, new_raw) {
+                    (Some(o), Some(n)) => o.get(self.annotation) != n.get(self.annotation),
+                    _ => false,
+                };
+                let kind = if annotation_changed {
+                    ChangeKind::VisibilityChanged { from, to }
+                } else if from == Presence::Omitted {
+                    ChangeKind::Added {
+                        required: to == Presence::Required,
+                    }
+                } else if to == Presence::Omitted {
+                    ChangeKind::Removed
+                } else {
+                    ChangeKind::RequiredChanged {
+                        required: to == Presence::Required,
+                    }
+                };
+                changes.push(SchemaChange {
+                    path: prop_path.clone(),
+                    kind,
+                    breaking: self.presence_breaking(from, to),
+                });
+            }
+
+            let old_schema = old_props.and_then(|p| p.get(name));
+            let new_schema = new_props.and_then(|p| p.get(name));
+            if let (Some(o), Some(n)) = (old_schema, new_schema) {
+                self.diff_node((old_raw, new_raw), (o, n), &prop_path, changes);
+            }
+        }
+    }
+
+    /// Request: the client must now send it, or may no longer send it.
+    /// Response: the client may no longer get something it could count on.
+    fn presence_breaking(&self, from: Presence, to: Presence) -> bool {
+        match self.direction {
+            Direction::Request => {
+                (to == Presence::Required && from != Presence::Required)
+                    || (to == Presence::Omitted && from != Presence::Omitted)
+            }
+            Direction::Response => to < from,
+        }
+    }
+}
+
+fn child<'a>(value: Option<&'a Value>, key: &str) -> Option<&'a Value> {
+    value.and_then(|v| v.get(key))
+}
+
+fn presence(schema: &Value, name: &str) -> Presence {
+    let listed = schema.get("properties").and_then(|p| p.get(name)).is_some();
+    let required = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .is_some_and(|r| r.iter().any(|n| n.as_str() == Some(name)));
+    match (listed, required) {
+        (false, _) => Presence::Omitted,
+        (true, true) => Presence::Required,
+        (true, false) => Presence::Optional,
+    }
+}
+
+/// Whether every value allowed by type `a` is allowed by type `b` (absent = any).
+fn type_subset(a: Option<&Value>, b: Option<&Value>) -> bool {
+    let names = |t: &Value| -> Vec<String> {
+        match t {
+            Value::String(s) => vec![s.clone()],
+            Value::Array(arr) => arr
+                .iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect(),
+            _ => Vec::new(),
+        }
+    };
+    match (a, b) {
+        (_, None) => true,
+        (None, Some(_)) => false,
+        (Some(a), Some(b)) => {
+            let b = names(b);
+            names(a)
+                .iter()
+                .all(|t| b.contains(t) || (t == "integer" && b.iter().any(|n| n == "number")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn checkout_v1() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": {
+                    "type": "string",
+                    "ucp_request": { "create": "omit", "update": "required" },
+                    "ucp_response": "required"
+                },
+                "note": { "type": "string" },
+                "total": { "type": "integer" },
+                "buyer": {
+                    "type": "object",
+                    "properties": {
+                        "email": { "type": "string" }
+                    }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn diff_identical_schemas_is_empty() {
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let diff = diff_schemas(&checkout_v1(), &checkout_v1(), &options).unwrap();
+        assert!(diff.changes.is_empty());
+        assert!(!diff.has_breaking());
+    }
+
+    #[test]
+    fn diff_classifies_request_changes() {
+        let mut v2 = checkout_v1();
+        v2["properties"]["note"]["ucp_request"] = json!("omit");
+        v2["properties"]["total"]["type"] = json!("number");
+        v2["properties"]["buyer"]["required"] = json!(["email"]);
+        v2["properties"]["gift"] = json!({ "type": "boolean" });
+
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let diff = diff_schemas(&checkout_v1(), &v2, &options).unwrap();
+        let summary: Vec<(String, String, bool)> = diff
+            .changes
+            .iter()
+            .map(|c| (c.path.clone(), c.kind.to_string(), c.breaking))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "/properties/note".to_string(),
+                    "visibility changed optional -> omitted".to_string(),
+                    true
+                ),
+                (
+                    "/properties/total".to_string(),
+                    "type changed \"integer\" -> \"number\"".to_string(),
+                    false
+                ),
+                (
+                    "/properties/buyer/properties/email".to_string(),
+                    "now required".to_string(),
+                    true
+                ),
+                (
+                    "/properties/gift".to_string(),
+                    "added (optional)".to_string(),
+                    false
+                ),
+            ]
+        );
+        assert_eq!((diff.breaking, diff.non_breaking), (2, 2));
+    }
+
+    #[test]
+    fn diff_response_breaks_on_lost_guarantees() {
+        let mut v2 = checkout_v1();
+        v2["properties"]["id"]["ucp_response"] = json!("optional");
+        v2["properties"]["total"]["type"] = json!("number");
+        v2["properties"]["gift"] = json!({ "type": "boolean" });
+
+        let options = ResolveOptions::new(Direction::Response, "read");
+        let diff = diff_schemas(&checkout_v1(), &v2, &options).unwrap();
+        let breaking: Vec<&str> = diff
+            .changes
+            .iter()
+            .filter(|c| c.breaking)
+            .map(|c| c.path.as_str())
+            .collect();
+        // Widening integer -> number breaks clients reading an integer
+        assert_eq!(breaking, ["/properties/id", "/properties/total"]);
+        assert_eq!(diff.non_breaking, 1);
+    }
+
+    #[test]
+    fn type_subset_treats_absent_as_any() {
+        assert!(type_subset(Some(&json!("string")), None));
+        assert!(!type_subset(None, Some(&json!("string"))));
+        assert!(type_subset(
+            Some(&json!("string")),
+            Some(&json!(["string", "null"]))
+        ));
+        assert!(type_subset(Some(&json!("integer")), Some(&json!("number"))));
+        assert!(!type_subset(
+            Some(&json!("number")),
+            Some(&json!("integer"))
+        ));
+    }
+}
@@ -0,0 +1,389 @@
+//! Schema documentation - a browsable site for a capability set.
+//!
+//! Each capability gets a page with its metadata, links along `extends`
+//! (both directions) and one resolved view per operation × direction, laid
+//! out from the same model the code generators use.
+
+use std::fmt::Write;
+
+use serde_json::Value;
+
+use crate::codegen::{build_model, CodegenOptions, FieldType, TypeDef, TypeModule};
+use crate::compose::{capability_short_name, Capability};
+use crate::error::ResolveError;
+use crate::resolver::annotated_operations;
+
+/// A capability and its schema composed with every capability it extends.
+#[derive(Debug, Clone)]
+pub struct CapabilityDoc {
+    pub capability: Capability,
+    pub schema: Value,
+}
+
+/// A generated file, relative to the site root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocsPage {
+    pub path: String,
+    pub content: String,
+}
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:60rem;margin:2rem auto;padding:0 1rem;color:#222}\
+a{color:#0645ad}code{background:#f3f3f3;padding:0 .2em}\
+table{border-collapse:collapse;width:100%;margin-bottom:1.5rem}\
+th,td{border:1px solid #ddd;padding:.3rem .5rem;text-align:left;vertical-align:top}\
+th{background:#f7f7f7}dt{font-weight:bold}section{margin-top:2rem}";
+
+/// Render `index.html` plus one page per capability (`<name>.html`).
+///
+/// Views cover the operations annotated in each composed schema (every
+/// operation if none are). Pages link only to each other, with styles inline,
+/// so the output directory can be served or opened as-is.
+///
+/// # Errors
+///
+/// Returns the first `ResolveError` from resolving a view.
+pub fn html_site(docs: &[CapabilityDoc]) -> Result<Vec<DocsPage>, ResolveError> {
+    let mut pages = vec![DocsPage {
+        path: "index.html".to_string(),
+        content: index_page(docs),
+    }];
+    for doc in docs {
+        pages.push(DocsPage {
+            path: page_path(&doc.capability.name),
+            content: capability_page(doc, docs)?,
+        });
+    }
+    Ok(pages)
+}
+
+fn page_path(name: &str) -> String {
+    format!("{}.html", name)
+}
+
+fn index_page(docs: &[CapabilityDoc]) -> String {
+    let mut body = String::from("<h1>Capabilities</h1>\n");
+    let roots: Vec<&CapabilityDoc> = docs
+        .iter()
+        .filter(|d| {
+            // Extensions of a capability outside the set are listed at the top level
+            d.capability
+                .extends
+                .iter()
+                .flatten()
+                .all(|p| find(docs, p).is_none())
+        })
+        .collect();
+    capability_tree(&mut body, &roots, docs, &mut Vec::new());
+    page("Capabilities", &body)
+}
+
+/// Nested list of `level` and (recursively) the capabilities extending each.
+fn capability_tree<'a>(
+    out: &mut String,
+    level: &[&'a CapabilityDoc],
+    docs: &'a [CapabilityDoc],
+    path: &mut Vec<&'a str>,
+) {
+    out.push_str("<ul>\n");
+    for doc in level {
+        let cap = &doc.capability;
+        let _ = write!(
+            out,
+            "<li>{} <small>{}</small>",
+            link(cap),
+            escape(&cap.version)
+        );
+        if !path.contains(&cap.name.as_str()) {
+            path.push(&cap.name);
+            let children = extended_by(docs, &cap.name);
+            if !children.is_empty() {
+                out.push('\n');
+                capability_tree(out, &children, docs, path);
+            }
+            path.pop();
+        }
+        out.push_str("</li>\n");
+    }
+    out.push_str("</ul>\n");
+}
+
+fn capability_page(doc: &CapabilityDoc, docs: &[CapabilityDoc]) -> Result<String, ResolveError> {
+    let cap = &doc.capability;
+    let operations = annotated_operations(&doc.schema);
+    let options = CodegenOptions::new(capability_short_name(&cap.name), operations);
+    let model = build_model(&doc.schema, &options)?;
+
+    let mut body = String::from("<nav><a href=\"index.html\">All capabilities</a></nav>\n");
+    let _ = writeln!(body, "<h1>{}</h1>", escape(&cap.name));
+    let _ = writeln!(
+        body,
+        "<dl>\n<dt>Version</dt><dd>{}</dd>\n<dt>Schema</dt><dd><code>{}</code></dd>",
+        escape(&cap.version),
+        escape(&cap.schema_url)
+    );
+    if let Some(parents) = &cap.extends {
+        let links: Vec<String> = parents
+            .iter()
+            .map(|p| match find(docs, p) {
+                Some(parent) => link(&parent.capability),
+                None => format!("<code>{}</code>", escape(p)),
+            })
+            .collect();
+        let _ = writeln!(body, "<dt>Extends</dt><dd>{}</dd>", links.join(", "));
+    }
+    let children = extended_by(docs, &cap.name);
+    if !children.is_empty() {
+        let links: Vec<String> = children.iter().map(|c| link(&c.capability)).collect();
+        let _ = writeln!(body, "<dt>Extended by</dt><dd>{}</dd>", links.join(", "));
+    }
+    body.push_str("</dl>\n");
+
+    let views: Vec<String> = model
+        .modules
+        .iter()
+        .map(|m| format!("<a href=\"#{}\">{}</a>", view_id(m), view_title(m)))
+        .collect();
+    let _ = writeln!(body, "<p>Views: {}</p>", views.join(" · "));
+    for module in &model.modules {
+        render_view(&mut body, module);
+    }
+    Ok(page(&cap.name, &body))
+}
+
+fn render_view(out: &mut String, module: &TypeModule) {
+    let id = view_id(module);
+    let _ = writeln!(
+        out,
+        "<section id=\"{}\">\n<h2>{}</h2>",
+        id,
+        view_title(module)
+    );
+    for def in &module.types {
+        let _ = writeln!(
+            out,
+            "<h3 id=\"{}-{}\">{}</h3>",
+            id,
+            escape(def.name()),
+            escape(def.name())
+        );
+        match def {
+            TypeDef::Struct(s) => {
+                if let Some(description) = &s.description {
+                    let _ = writeln!(out, "<p>{}</p>", escape(description));
+                }
+                if s.fields.is_empty() {
+                    out.push_str("<p>No properties.</p>\n");
+                    continue;
+                }
+                out.push_str("<table>\n<tr><th>Property</th><th>Type</th><th>Required</th><th>Description</th></tr>\n");
+                for field in &s.fields {
+                    let mut ty = type_html(&field.ty, &id);
+                    if field.nullable {
+                        ty.push_str(" or null");
+                    }
+                    let _ = writeln!(
+                        out,
+                        "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                        escape(&field.name),
+                        ty,
+                        if field.required {
+                            "required"
+                        } else {
+                            "optional"
+                        },
+                        escape(field.description.as_deref().unwrap_or(""))
+                    );
+                }
+                out.push_str("</table>\n");
+            }
+            TypeDef::Enum(e) => {
+                if let Some(description) = &e.description {
+                    let _ = writeln!(out, "<p>{}</p>", escape(description));
+                }
+                let values: Vec<String> = e
+                    .values
+                    .iter()
+                    .map(|v| format!("<code>{}</code>", escape(v)))
+                    .collect();
+                let _ = writeln!(out, "<p>One of: {}</p>", values.join(", "));
+            }
+            TypeDef::Alias { ty, .. } => {
+                let _ = writeln!(out, "<p>{}</p>", type_html(ty, &id));
+            }
+        }
+    }
+    out.push_str("</section>\n");
+}
+
+fn type_html(ty: &FieldType, view: &str) -> String {
+    match ty {
+        FieldType::String => "string".to_string(),
+        FieldType::Integer => "integer".to_string(),
+        FieldType::Number => "number".to_string(),
+        FieldType::Boolean => "boolean".to_string(),
+        FieldType::Any => "any".to_string(),
+        FieldType::Array(item) => format!("array of {}", type_html(item, view)),
+        FieldType::Map(value) => format!("map of {}", type_html(value, view)),
+        FieldType::Named(name) => format!(
+            "<a href=\"#{}-{}\">{}</a>",
+            view,
+            escape(name),
+            escape(name)
+        ),
+    }
+}
+
+fn view_id(module: &TypeModule) -> String {
+    format!(
+        "{}-{}",
+        escape(&module.operation),
+        module.direction.as_str()
+    )
+}
+
+fn view_title(module: &TypeModule) -> String {
+    format!(
+        "{} {}",
+        escape(&module.operation),
+        module.direction.as_str()
+    )
+}
+
+fn find<'a>(docs: &'a [CapabilityDoc], name: &str) -> Option<&'a CapabilityDoc> {
+    docs.iter().find(|d| d.capability.name == name)
+}
+
+fn extended_by<'a>(docs: &'a [CapabilityDoc], name: &str) -> Vec<&'a CapabilityDoc> {
+    docs.iter()
+        .filter(|d| d.capability.extends.iter().flatten().any(|p| p == name))
+        .collect()
+}
+
+fn link(cap: &Capability) -> String {
+    format!(
+        "<a href=\"{}\">{}</a>",
+        escape(&page_path(&cap.name)),
+        escape(&cap.name)
+    )
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        STYLE,
+        body
+    )
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn cap(name: &str, extends: Option<&str>) -> Capability {
+        Capability {
+            name: name.to_string(),
+            version: "2026-01-11".to_string(),
+            schema_url: format!("https://ucp.dev/schemas/{}.json", name),
+            extends: extends.map(|p| vec![p.to_string()]),
+        }
+    }
+
+    fn site() -> Vec<DocsPage> {
+        let checkout = json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "ucp_request": { "create": "omit", "update": "required" } },
+                "status": { "type": "string", "enum": ["open", "closed"] }
+            }
+        });
+        let mut discount = checkout.clone();
+        discount["properties"]["discounts"] =
+            json!({ "type": "array", "items": { "type": "string" } });
+        html_site(&[
+            CapabilityDoc {
+                capability: cap("dev.ucp.shopping.checkout", None),
+                schema: checkout,
+            },
+            CapabilityDoc {
+                capability: cap(
+                    "dev.ucp.shopping.discount",
+                    Some("dev.ucp.shopping.checkout"),
+                ),
+                schema: discount,
+            },
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn site_has_index_and_page_per_capability() {
+        let pages = site();
+        let paths: Vec<&str> = pages.iter().map(|p| p.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "index.html",
+                "dev.ucp.shopping.checkout.html",
+                "dev.ucp.shopping.discount.html"
+            ]
+        );
+        // Discount is nested under checkout in the index
+        assert!(pages[0].content.contains(
+            "<a href=\"dev.ucp.shopping.checkout.html\">dev.ucp.shopping.checkout</a> <small>2026-01-11</small>\n<ul>\n<li><a href=\"dev.ucp.shopping.discount.html\">"
+        ));
+    }
+
+    #[test]
+    fn capability_page_links_extends_both_ways() {
+        let pages = site();
+        assert!(pages[1].content.contains(
+            "<dt>Extended by</dt><dd><a href=\"dev.ucp.shopping.discount.html\">dev.ucp.shopping.discount</a></dd>"
+        ));
+        assert!(pages[2].content.contains(
+            "<dt>Extends</dt><dd><a href=\"dev.ucp.shopping.checkout.html\">dev.ucp.shopping.checkout</a></dd>"
+        ));
+    }
+
+    #[test]
+    fn capability_page_has_resolved_views() {
+        let pages = site();
+        let checkout = &pages[1].content;
+        // Annotated ops only, request and response each
+        assert!(checkout.contains("<section id=\"create-request\">"));
+        assert!(checkout.contains("<section id=\"update-response\">"));
+        assert!(!checkout.contains("<section id=\"read-request\">"));
+
+        let create = &checkout[checkout.find("id=\"create-request\"").unwrap()
+            ..checkout.find("id=\"create-response\"").unwrap()];
+        assert!(!create.contains("<code>id</code>"));
+        assert!(create.contains(
+            "<td><code>status</code></td><td><a href=\"#create-request-CheckoutStatus\">CheckoutStatus</a></td>"
+        ));
+        assert!(create.contains("One of: <code>open</code>, <code>closed</code>"));
+        assert!(pages[2]
+            .content
+            .contains("<td><code>discounts</code></td><td>array of string</td>"));
+    }
+
+    #[test]
+    fn escape_html() {
+        assert_eq!(escape("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+    }
+}
@@ -12,9 +12,15 @@ pub enum ComposeError {
     #[error("no capabilities declared in ucp.capabilities")]
     EmptyCapabilities,
 
+    #[error("no capability schemas (dotted name and a version) found in {}", dir.display())]
+    NoCapabilitySchemas { dir: PathBuf },
+
     #[error("invalid JSONRPC envelope: {message}")]
     InvalidEnvelope { message: String },
 
+    #[error("JSONRPC request has no params matching any capability (expected one of: {})", expected.join(", "))]
+    NoMatchingParams { expected: Vec<String> },
+
     #[error("no root capability found (all capabilities have 'extends')")]
     NoRootCapability,
 
@@ -24,6 +30,24 @@ pub enum ComposeError {
     #[error("extension '{extension}' references unknown parent '{parent}'")]
     UnknownParent { extension: String, parent: String },
 
+    #[error("no capability name matches --capability-filter {pattern}")]
+    NoCapabilityMatchesFilter { pattern: String },
+
+    #[error("extension '{extension}' extends '{parent}', which is older than minimum version {min_version}")]
+    ParentBelowMinVersion {
+        extension: String,
+        parent: String,
+        min_version: String,
+    },
+
+    #[error("{name} advertises version {advertised} but its schema ({url}) declares {declared}")]
+    VersionMismatch {
+        name: String,
+        advertised: String,
+        declared: String,
+        url: String,
+    },
+
     #[error("extension '{extension}' does not connect to root '{root}'")]
     OrphanExtension { extension: String, root: String },
 
@@ -36,6 +60,12 @@ pub enum ComposeError {
     #[error("failed to fetch schema from {url}: {message}")]
     SchemaFetch { url: String, message: String },
 
+    #[error("schema URL {url} does not start with remote base {remote_base}; check --schema-remote-base")]
+    RemoteBaseMismatch { url: String, remote_base: String },
+
+    #[error("schema URL {url} matches no --map prefix (tried: {})", prefixes.join(", "))]
+    UnmappedSchemaUrl { url: String, prefixes: Vec<String> },
+
     #[error("failed to fetch profile from {url}: {message}")]
     ProfileFetch { url: String, message: String },
 
@@ -50,8 +80,70 @@ impl ComposeError {
     /// Returns the exit code for this error type.
     pub fn exit_code(&self) -> i32 {
         match self {
-            Self::SchemaFetch { .. } | Self::ProfileFetch { .. } => 3, // IO
-            _ => 2,                                                    // Schema/composition error
+            Self::SchemaFetch { .. }
+            | Self::ProfileFetch { .. }
+            | Self::RemoteBaseMismatch { .. } => 3, // IO
+            _ => 2, // Schema/composition error
+        }
+    }
+}
+
+/// Errors talking to a schema registry.
+#[derive(Debug, Error)]
+pub enum RegistryError {
+    #[error("{method} {url} failed: {message}")]
+    Request {
+        method: String,
+        url: String,
+        message: String,
+    },
+
+    #[error("{method} {url} returned HTTP {status}")]
+    Status {
+        method: String,
+        url: String,
+        status: u16,
+    },
+
+    #[error("network access disabled; refusing to contact {url}")]
+    NetworkDisabled { url: String },
+
+    #[error("{name} {version} is already published with different content (sha256 {published}); registry versions are immutable, so publish a new version")]
+    Immutable {
+        name: String,
+        version: String,
+        published: String,
+    },
+
+    #[error("{url}: content sha256 {actual} does not match {expected}")]
+    HashMismatch {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("{url} is not the schema for {name} {version}: {message}")]
+    UnexpectedSchema {
+        url: String,
+        name: String,
+        version: String,
+        message: String,
+    },
+
+    #[error(transparent)]
+    Compose(#[from] ComposeError),
+}
+
+impl RegistryError {
+    /// Returns the exit code for this error type.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Request { .. }
+            | Self::Status { .. }
+            | Self::NetworkDisabled { .. }
+            | Self::HashMismatch { .. } => 3, // IO
+            Self::Compose(e) => e.exit_code(),
+            _ => 2,
         }
     }
 }
@@ -78,6 +170,19 @@ pub enum ResolveError {
         source: reqwest::Error,
     },
 
+    #[error("network access disabled: refusing to fetch {url}")]
+    NetworkDisabled { url: String },
+
+    #[error("{url} is not in the lockfile; run `ucp-schema lock` to update it")]
+    NotLocked { url: String },
+
+    #[error("{url} does not match the lockfile: expected sha256 {expected}, got {actual}")]
+    LockMismatch {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+
     // Parse errors (exit code 2)
     #[error("invalid JSON: {source}")]
     InvalidJson {
@@ -85,16 +190,40 @@ pub enum ResolveError {
         source: serde_json::Error,
     },
 
+    #[error("invalid YAML: {source}")]
+    InvalidYaml {
+        #[source]
+        source: serde_yaml::Error,
+    },
+
+    #[error("invalid lockfile {path}: {message}")]
+    InvalidLockfile { path: PathBuf, message: String },
+
     // Schema errors (exit code 2)
     #[error("invalid annotation at {path}: expected string or object, got {actual}")]
     InvalidAnnotationType { path: String, actual: String },
 
-    #[error("unknown visibility \"{value}\" at {path}: expected omit, required, or optional")]
+    #[error("unknown visibility \"{value}\" at {path}: expected omit, required, optional, readonly, or writeonly")]
     UnknownVisibility { path: String, value: String },
 
+    #[error("invalid ucp_enum at {path}: {message}")]
+    InvalidEnumAnnotation { path: String, message: String },
+
+    #[error("invalid ucp_description at {path}: {message}")]
+    InvalidDescriptionAnnotation { path: String, message: String },
+
+    #[error("invalid ucp_sensitive at {path}: expected true or false, got {actual}")]
+    InvalidSensitiveAnnotation { path: String, actual: String },
+
+    #[error("invalid ucp_property_names at {path}: {message}")]
+    InvalidPropertyNamesAnnotation { path: String, message: String },
+
     #[error("invalid schema transition at {path}: {message}")]
     InvalidSchemaTransition { path: String, message: String },
 
+    #[error("invalid operation group at {path}: {message}")]
+    InvalidOperationGroup { path: String, message: String },
+
     /// allOf extension tries to weaken a field that base declares as required.
     /// Monotonicity rule: extensions can narrow (optional→omit) or strengthen
     /// (optional→required) but never weaken required fields.
@@ -109,16 +238,32 @@ pub enum ResolveError {
         attempted: String,
     },
 
-    /// allOf branches declare contradictory types on the same property.
+    /// allOf branches declare contradictory types on the same property:
+    /// `ext_branch`'s type shares none with what the earlier branches
+    /// together admit (`base_type`, as last narrowed by `base_branch`).
     #[error(
-        "type conflict at {path}: base declares \"{base_type}\" but extension declares \"{ext_type}\""
+        "type conflict at {path}: allOf branch {ext_branch} declares {ext_type}, but the \
+         branches before it (last: branch {base_branch}) only admit {base_type}"
     )]
     TypeConflict {
         path: String,
+        base_branch: usize,
+        /// The types the earlier branches admit, as JSON (`"string"`,
+        /// `["string","null"]`).
         base_type: String,
+        ext_branch: usize,
         ext_type: String,
     },
 
+    /// `--denullify` found a nullable type with several non-null members,
+    /// which a single type name can't express.
+    #[error("cannot denullify type at {path}: {types} has more than one non-null member")]
+    AmbiguousNullableType { path: String, types: String },
+
+    /// `--deref-payload` met a data-level `$ref` it can't expand.
+    #[error("invalid payload $ref at {path}: {message}")]
+    InvalidPayloadRef { path: String, message: String },
+
     #[error("invalid schema: {message}")]
     InvalidSchema { message: String },
 
@@ -141,6 +286,11 @@ pub enum ValidateError {
 pub struct SchemaError {
     /// JSON Pointer (RFC 6901) to the invalid field.
     pub path: String,
+    /// JSON Pointer to the failing keyword within the resolved schema.
+    #[serde(rename = "schemaPath")]
+    pub schema_path: String,
+    /// Failing JSON Schema keyword (e.g., "required", "type").
+    pub keyword: String,
     /// Human-readable error message.
     pub message: String,
 }
@@ -155,7 +305,11 @@ impl ResolveError {
     /// Returns the exit code for this error type.
     pub fn exit_code(&self) -> i32 {
         match self {
-            ResolveError::FileNotFound { .. } | ResolveError::ReadError { .. } => 3,
+            ResolveError::FileNotFound { .. }
+            | ResolveError::ReadError { .. }
+            | ResolveError::NetworkDisabled { .. }
+            | ResolveError::NotLocked { .. }
+            | ResolveError::LockMismatch { .. } => 3,
             #[cfg(feature = "remote")]
             ResolveError::NetworkError { .. } => 3,
             _ => 2,
@@ -202,6 +356,8 @@ mod tests {
         let err = ValidateError::Invalid {
             errors: vec![SchemaError {
                 path: "/id".into(),
+                schema_path: "/required".into(),
+                keyword: "required".into(),
                 message: "missing required field".into(),
             }],
         };
@@ -212,6 +368,8 @@ mod tests {
     fn schema_error_display() {
         let err = SchemaError {
             path: "/buyer/email".into(),
+            schema_path: "/properties/buyer/properties/email/type".into(),
+            keyword: "type".into(),
             message: "expected string, got number".into(),
         };
         assert_eq!(err.to_string(), "/buyer/email: expected string, got number");
@@ -0,0 +1,338 @@
+//! Validation failure explanations - where in a resolved (and often composed)
+//! schema each error comes from, and which UCP annotation caused it.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::error::{ResolveError, SchemaError, ValidateError};
+use crate::loader::escape_pointer_token;
+use crate::resolver::{get_visibility_from_annotation, resolve};
+use crate::types::{ResolveOptions, Visibility};
+use crate::validator::validate_against_schema;
+
+/// The `allOf` branch an error was raised in (the innermost, when nested).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AllOfBranch {
+    /// JSON pointer to the branch in the resolved schema (e.g., "/allOf/1").
+    pub path: String,
+    /// The branch's `title`, or its `$id`, when it has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// A UCP annotation that made a property required or omitted for the
+/// operation, leading to the error.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AnnotationCause {
+    pub property: String,
+    /// JSON pointer to the property schema (e.g., "/properties/id").
+    pub path: String,
+    /// `ucp_request` or `ucp_response`.
+    pub key: String,
+    /// The annotation as written in the schema.
+    pub annotation: Value,
+    /// What it resolved to: "required" or "omit".
+    pub visibility: String,
+}
+
+/// One validation error, traced back through the schema.
+#[derive(Debug, Clone, Serialize)]
+pub struct Explanation {
+    #[serde(flatten)]
+    pub error: SchemaError,
+    /// JSON pointer to the schema holding the failing keyword, with `$ref`s
+    /// followed (e.g., "/$defs/item" for an error under `items.$ref`).
+    /// `None` when the error's schema path can't be traced.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// The resolved schema at `location`, without `$defs`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fragment: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<AllOfBranch>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<AnnotationCause>,
+}
+
+/// Validate `payload` against `schema` resolved with `options`, and explain
+/// each error. Returns an empty list when the payload is valid.
+///
+/// Annotation causes are found by reading `schema` at the same location as
+/// the failing keyword, so they are reported for `required` and
+/// `additionalProperties` errors on properties the resolver kept in place;
+/// annotations inherited across `allOf` branches aren't traced.
+///
+/// # Errors
+///
+/// Returns the `ResolveError` from resolving or compiling the schema.
+pub fn explain_validation(
+    schema: &Value,
+    payload: &Value,
+    options: &ResolveOptions,
+) -> Result<Vec<Explanation>, ResolveError> {
+    let resolved = resolve(schema, options)?;
+    let errors = match validate_against_schema(&resolved, payload) {
+        Ok(()) => return Ok(Vec::new()),
+        Err(ValidateError::Invalid { errors }) => errors,
+        Err(ValidateError::Resolve(e)) => return Err(e),
+    };
+
+    let key = options.direction.annotation_key();
+    Ok(errors
+        .into_iter()
+        .map(|error| {
+            let trace = trace(&resolved, &error);
+            let mut explanation = Explanation {
+                error,
+                location: None,
+                fragment: None,
+                branch: None,
+                annotations: Vec::new(),
+            };
+            let Some(trace) = trace else {
+                return explanation;
+            };
+
+            let instance = payload.pointer(&explanation.error.path);
+            let names = offending_properties(&explanation.error, trace.holder, instance);
+            explanation.annotations = names
+                .into_iter()
+                .filter_map(|(name, expected)| {
+                    let path = format!(
+                        "{}/properties/{}",
+                        trace.location,
+                        escape_pointer_token(&name)
+                    );
+                    let annotation = schema.pointer(&path)?.get(key)?;
+                    let (visibility, _) = get_visibility_from_annotation(
+                        annotation,
+                        options.direction,
+                        &options.operation,
+                        &path,
+                    )
+                    .ok()?;
+                    (visibility == expected).then(|| AnnotationCause {
+                        property: name,
+                        path,
+                        key: key.to_string(),
+                        annotation: annotation.clone(),
+                        visibility: match visibility {
+                            Visibility::Omit => "omit",
+                            _ => "required",
+                        }
+                        .to_string(),
+                    })
+                })
+                .collect();
+
+            let mut fragment = trace.holder.clone();
+            if let Value::Object(map) = &mut fragment {
+                map.remove("$defs");
+            }
+            explanation.location = Some(trace.location);
+            explanation.fragment = Some(fragment);
+            explanation.branch = trace.branch;
+            explanation
+        })
+        .collect())
+}
+
+/// Where an error's keyword lives in the resolved schema.
+struct Trace<'a> {
+    location: String,
+    holder: &'a Value,
+    branch: Option<AllOfBranch>,
+}
+
+/// Follow an error's schema path through the resolved schema.
+///
+/// Local `$ref` hops jump to their target; a token missing from a schema
+/// with a local `$ref` is also looked up in the target, for paths that elide
+/// the hop.
+fn trace<'a>(root: &'a Value, error: &SchemaError) -> Option<Trace<'a>> {
+    let mut tokens: Vec<&str> = error.schema_path.split('/').skip(1).collect();
+    // A `false` property schema fails itself: explain from the object holding
+    // it. Other errors point at a keyword in the schema holding it.
+    let keyword = match tokens.as_slice() {
+        [.., "properties", _] if error.keyword == "false" => {
+            tokens.truncate(tokens.len() - 2);
+            None
+        }
+        _ if error.keyword == "false" => None,
+        _ => tokens.pop(),
+    };
+
+    let mut current = root;
+    let mut location = String::new();
+    let mut branch = None;
+    let mut previous = "";
+    for token in tokens {
+        if token == "$ref" {
+            let target = current.get("$ref")?.as_str()?.strip_prefix('#')?;
+            current = root.pointer(target)?;
+            location = target.to_string();
+            continue;
+        }
+        let next = step(root, &mut current, &mut location, token)?;
+        location = format!("{}/{}", location, token);
+        if previous == "allOf" {
+            let title = ["title", "$id"]
+                .iter()
+                .find_map(|k| next.get(*k).and_then(Value::as_str))
+                .map(str::to_string);
+            branch = Some(AllOfBranch {
+                path: location.clone(),
+                title,
+            });
+        }
+        previous = token;
+        current = next;
+    }
+    if let Some(keyword) = keyword {
+        // Lands `current` on the schema that has the keyword
+        step(root, &mut current, &mut location, keyword)?;
+    }
+
+    Some(Trace {
+        location,
+        holder: current,
+        branch,
+    })
+}
+
+/// Look up `token` in `current`, first following local `$ref`s (updating
+/// `current` and `location` to the target) until it is found.
+fn step<'a>(
+    root: &'a Value,
+    current: &mut &'a Value,
+    location: &mut String,
+    token: &str,
+) -> Option<&'a Value> {
+    let name = token.replace("~1", "/").replace("~0", "~");
+    for _ in 0..32 {
+        if let Some(next) = child(current, &name) {
+            return Some(next);
+        }
+        let target = current.get("$ref")?.as_str()?.strip_prefix('#')?;
+        *current = root.pointer(target)?;
+        *location = target.to_string();
+    }
+    None
+}
+
+fn child<'a>(schema: &'a Value, token: &str) -> Option<&'a Value> {
+    match schema {
+        Value::Object(map) => map.get(token),
+        Value::Array(items) => items.get(token.parse::<usize>().ok()?),
+        _ => None,
+    }
+}
+
+/// Properties an error is about, with the visibility an annotation would
+/// have needed to cause it: missing ones for `required`, unexpected ones for
+/// `additionalProperties: false` and `false` property schemas.
+fn offending_properties(
+    error: &SchemaError,
+    holder: &Value,
+    instance: Option<&Value>,
+) -> Vec<(String, Visibility)> {
+    let empty = Map::new();
+    let fields = instance.and_then(Value::as_object).unwrap_or(&empty);
+    match error.keyword.as_str() {
+        "required" => holder
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .filter(|name| !fields.contains_key(*name))
+            .map(|name| (name.to_string(), Visibility::Required))
+            .collect(),
+        "additionalProperties" => {
+            let declared = holder.get("properties").and_then(Value::as_object);
+            fields
+                .keys()
+                .filter(|name| !declared.is_some_and(|d| d.contains_key(*name)))
+                .map(|name| (name.clone(), Visibility::Omit))
+                .collect()
+        }
+        "false" => error
+            .schema_path
+            .rsplit('/')
+            .next()
+            .map(|name| vec![(name.replace("~1", "/").replace("~0", "~"), Visibility::Omit)])
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Direction;
+    use serde_json::json;
+
+    #[test]
+    fn explain_traces_refs_branches_and_annotations() {
+        let schema = json!({
+            "type": "object",
+            "additionalProperties": false,
+            "properties": {
+                "id": { "type": "string", "ucp_request": { "create": "omit", "update": "required" } },
+                "items": { "type": "array", "items": { "$ref": "#/$defs/item" } }
+            },
+            "allOf": [
+                { "title": "Discount", "properties": { "code": { "type": "string", "ucp_request": "required" } } }
+            ],
+            "$defs": {
+                "item": { "type": "object", "required": ["sku"], "properties": { "sku": { "type": "string" } } }
+            }
+        });
+        let payload = json!({ "id": "x", "items": [{}] });
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let explanations = explain_validation(&schema, &payload, &options).unwrap();
+        assert_eq!(explanations.len(), 3);
+
+        let item = &explanations[0];
+        assert_eq!(item.error.path, "/items/0");
+        assert_eq!(item.location.as_deref(), Some("/$defs/item"));
+        assert_eq!(item.fragment.as_ref().unwrap()["required"], json!(["sku"]));
+        assert!(item.branch.is_none());
+        assert!(item.annotations.is_empty());
+
+        let extra = &explanations[1];
+        assert_eq!(extra.error.keyword, "additionalProperties");
+        assert_eq!(extra.location.as_deref(), Some(""));
+        assert!(extra.fragment.as_ref().unwrap().get("$defs").is_none());
+        assert_eq!(
+            extra.annotations,
+            [AnnotationCause {
+                property: "id".to_string(),
+                path: "/properties/id".to_string(),
+                key: "ucp_request".to_string(),
+                annotation: json!({ "create": "omit", "update": "required" }),
+                visibility: "omit".to_string(),
+            }]
+        );
+
+        let code = &explanations[2];
+        assert_eq!(
+            code.branch,
+            Some(AllOfBranch {
+                path: "/allOf/0".to_string(),
+                title: Some("Discount".to_string()),
+            })
+        );
+        assert_eq!(code.annotations[0].property, "code");
+        assert_eq!(code.annotations[0].visibility, "required");
+    }
+
+    #[test]
+    fn explain_valid_payload_is_empty() {
+        let schema = json!({ "type": "object" });
+        let options = ResolveOptions::new(Direction::Response, "read");
+        assert!(explain_validation(&schema, &json!({}), &options)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,302 @@
+//! Schema explorer - the navigation behind `ucp-schema explore`: listing an
+//! object's properties with their visibility for the current direction and
+//! operation, stepping into them, and following `$ref`s.
+
+use std::fmt::Write;
+
+use serde_json::Value;
+
+use crate::inspect::cell;
+use crate::loader::escape_pointer_token;
+use crate::types::Direction;
+
+/// Help text for [`Explorer::run`]'s commands.
+pub const EXPLORE_HELP: &str = "\
+ls                   list properties (visibility for the current direction/op; * = annotated)
+cd <name>            step into a property; also `cd items`, `cd <relative pointer>`
+cd /<pointer>        jump to an absolute JSON Pointer (`cd /` for the root)
+cd ..                go back to where you came from
+ref                  follow the current node's local $ref
+show                 print the current node
+op <operation>       switch operation (e.g., op update)
+request | response   switch direction
+pwd                  print the current pointer
+quit                 leave";
+
+/// A cursor over a schema with a direction and operation to show
+/// visibility for.
+#[derive(Debug, Clone)]
+pub struct Explorer {
+    schema: Value,
+    /// JSON Pointer of the current node ("" for the root).
+    pointer: String,
+    /// Pointers visited before the current one, for `cd ..`.
+    history: Vec<String>,
+    direction: Direction,
+    operation: String,
+}
+
+/// A property listed by `ls`.
+struct Entry<'a> {
+    name: &'a str,
+    pointer: String,
+    schema: &'a Value,
+    /// In the `required` list of its object.
+    listed: bool,
+}
+
+impl Explorer {
+    pub fn new(schema: Value, direction: Direction, operation: &str) -> Self {
+        Explorer {
+            schema,
+            pointer: String::new(),
+            history: Vec::new(),
+            direction,
+            operation: operation.to_lowercase(),
+        }
+    }
+
+    /// JSON Pointer of the current node ("/" for the root).
+    pub fn pointer(&self) -> &str {
+        if self.pointer.is_empty() {
+            "/"
+        } else {
+            &self.pointer
+        }
+    }
+
+    /// Prompt showing where the cursor is, e.g. `/properties/buyer [request/create]> `.
+    pub fn prompt(&self) -> String {
+        format!(
+            "{} [{}/{}]> ",
+            self.pointer(),
+            self.direction.as_str(),
+            self.operation
+        )
+    }
+
+    /// Run one command line (see [`EXPLORE_HELP`]) and return its output.
+    /// `quit` is left to the caller.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message for unknown commands and targets that don't exist.
+    pub fn run(&mut self, line: &str) -> Result<String, String> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("ls");
+        let argument = words.next();
+        match (command, argument) {
+            ("ls", None) => Ok(self.list()),
+            ("cd", Some(target)) => self.cd(target).map(|()| self.list()),
+            ("cd", None) => self.cd("/").map(|()| self.list()),
+            ("ref", None) => self.follow_ref().map(|()| self.list()),
+            ("show", None) => Ok(serde_json::to_string_pretty(self.node()).unwrap_or_default()),
+            ("op", Some(op)) => {
+                self.operation = op.to_lowercase();
+                Ok(self.list())
+            }
+            ("request", None) => {
+                self.direction = Direction::Request;
+                Ok(self.list())
+            }
+            ("response", None) => {
+                self.direction = Direction::Response;
+                Ok(self.list())
+            }
+            ("pwd", None) => Ok(self.pointer().to_string()),
+            ("help", None) => Ok(EXPLORE_HELP.to_string()),
+            _ => Err(format!("unknown command \"{}\" (try help)", line.trim())),
+        }
+    }
+
+    fn node(&self) -> &Value {
+        self.schema.pointer(&self.pointer).unwrap_or(&Value::Null)
+    }
+
+    fn go(&mut self, pointer: String) {
+        let previous = std::mem::replace(&mut self.pointer, pointer);
+        self.history.push(previous);
+    }
+
+    fn cd(&mut self, target: &str) -> Result<(), String> {
+        if target == ".." {
+            self.pointer = self.history.pop().unwrap_or_default();
+            return Ok(());
+        }
+        if let Some(absolute) = target.strip_prefix('/') {
+            let pointer = match absolute {
+                "" => String::new(),
+                _ => target.to_string(),
+            };
+            if self.schema.pointer(&pointer).is_none() {
+                return Err(format!("{} not found", target));
+            }
+            self.go(pointer);
+            return Ok(());
+        }
+        if let Some(entry) = self.entries().into_iter().find(|e| e.name == target) {
+            let pointer = entry.pointer;
+            self.go(pointer);
+            return Ok(());
+        }
+        let relative = format!("{}/{}", self.pointer, target);
+        if self.schema.pointer(&relative).is_none() {
+            return Err(format!("no property or pointer \"{}\" here", target));
+        }
+        self.go(relative);
+        Ok(())
+    }
+
+    fn follow_ref(&mut self) -> Result<(), String> {
+        let Some(reference) = self.node().get("$ref").and_then(Value::as_str) else {
+            return Err("no $ref here".to_string());
+        };
+        let Some(fragment) = reference.strip_prefix('#') else {
+            return Err(format!(
+                "external $ref {}; explore a bundled schema (--bundle) to follow it",
+                reference
+            ));
+        };
+        if self.schema.pointer(fragment).is_none() {
+            return Err(format!("$ref target {} not found", reference));
+        }
+        self.go(fragment.to_string());
+        Ok(())
+    }
+
+    /// Properties of the current node and of its composition branches.
+    fn entries(&self) -> Vec<Entry<'_>> {
+        let node = self.node();
+        let required = |schema: &Value, name: &str| {
+            schema
+                .get("required")
+                .and_then(Value::as_array)
+                .is_some_and(|r| r.iter().any(|v| v == name))
+        };
+        let mut containers = vec![(self.pointer.clone(), node)];
+        for keyword in ["allOf", "anyOf", "oneOf"] {
+            for (i, branch) in node
+                .get(keyword)
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .enumerate()
+            {
+                containers.push((format!("{}/{}/{}", self.pointer, keyword, i), branch));
+            }
+        }
+
+        let mut entries = Vec::new();
+        for (pointer, container) in containers {
+            let Some(properties) = container.get("properties").and_then(Value::as_object) else {
+                continue;
+            };
+            for (name, schema) in properties {
+                entries.push(Entry {
+                    name,
+                    pointer: format!("{}/properties/{}", pointer, escape_pointer_token(name)),
+                    schema,
+                    listed: required(container, name) || required(node, name),
+                });
+            }
+        }
+        entries
+    }
+
+    fn list(&self) -> String {
+        let node = self.node();
+        let mut out = String::new();
+        if let Some(reference) = node.get("$ref").and_then(Value::as_str) {
+            let _ = writeln!(out, "$ref {} (`ref` to follow)", reference);
+        }
+        let entries = self.entries();
+        if entries.is_empty() {
+            if node.get("items").is_some() {
+                out.push_str("array (`cd items` to browse its items)\n");
+            } else if out.is_empty() {
+                let _ = writeln!(out, "{} (no properties)", type_name(node));
+            }
+            return out.trim_end().to_string();
+        }
+
+        let width = entries.iter().map(|e| e.name.len()).max().unwrap_or(0);
+        for entry in &entries {
+            let visibility = match cell(
+                entry.schema,
+                self.direction,
+                &self.operation,
+                &entry.pointer,
+                entry.listed,
+            ) {
+                Ok(cell) if cell.annotated => format!("{}*", cell.visibility),
+                Ok(cell) => cell.visibility,
+                Err(e) => format!("invalid ({})", e),
+            };
+            let _ = write!(
+                out,
+                "  {:width$}  {:8}  {}",
+                entry.name,
+                type_name(entry.schema),
+                visibility,
+                width = width
+            );
+            if let Some(reference) = entry.schema.get("$ref").and_then(Value::as_str) {
+                let _ = write!(out, "  -> {}", reference);
+            }
+            out.push('\n');
+        }
+        out.trim_end().to_string()
+    }
+}
+
+fn type_name(schema: &Value) -> String {
+    match schema.get("type") {
+        Some(Value::String(t)) => t.clone(),
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join("|"),
+        _ if schema.get("$ref").is_some() => "$ref".to_string(),
+        _ if schema.get("properties").is_some() => "object".to_string(),
+        _ => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn explorer_lists_navigates_and_follows_refs() {
+        let schema = json!({
+            "type": "object",
+            "required": ["buyer"],
+            "properties": {
+                "id": { "type": "string", "ucp_request": { "create": "omit", "update": "required" } },
+                "buyer": { "$ref": "#/$defs/buyer" }
+            },
+            "$defs": {
+                "buyer": { "allOf": [{ "properties": { "email": { "type": "string" } } }] }
+            }
+        });
+        let mut explorer = Explorer::new(schema, Direction::Request, "create");
+
+        let listing = explorer.run("ls").unwrap();
+        assert!(listing.contains("id     string    omit*"), "{}", listing);
+        assert!(listing.contains("buyer  $ref      required  -> #/$defs/buyer"));
+        assert!(explorer.run("op update").unwrap().contains("required*"));
+
+        explorer.run("cd buyer").unwrap();
+        assert_eq!(explorer.prompt(), "/properties/buyer [request/update]> ");
+        assert!(explorer.run("ref").unwrap().contains("email"));
+        explorer.run("cd email").unwrap();
+        assert_eq!(explorer.pointer(), "/$defs/buyer/allOf/0/properties/email");
+
+        explorer.run("cd ..").unwrap();
+        assert_eq!(explorer.pointer(), "/$defs/buyer");
+        assert!(explorer.run("cd nope").is_err());
+        assert!(explorer.run("frobnicate").is_err());
+    }
+}
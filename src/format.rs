@@ -0,0 +1,222 @@
+//! Schema file formatter - one layout for every schema in a repository.
+//!
+//! Formatted schemas are indented by two spaces, with each schema object's
+//! keys in a fixed order: identity and documentation first (`$schema`,
+//! `$id`, `title`, `type`, `description`, ...), then other keywords as
+//! written, then UCP annotations, with `required`, `properties` and `$defs`
+//! last. The order of property names and `$defs` entries is kept. Arrays of
+//! scalars fit on one line when short enough. Formatting is idempotent.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::linter::collect_schema_files;
+use crate::loader::load_schema;
+use crate::types::UCP_ANNOTATIONS;
+
+/// Keys placed first, in this order.
+const LEADING_KEYS: &[&str] = &[
+    "$schema",
+    "$id",
+    "$ref",
+    "name",
+    "version",
+    "title",
+    "type",
+    "description",
+];
+
+/// Keys placed last, in this order (after UCP annotations).
+const TRAILING_KEYS: &[&str] = &[
+    "required",
+    "properties",
+    "patternProperties",
+    "additionalProperties",
+    "$defs",
+    "definitions",
+];
+
+/// Keywords whose value maps names to schemas; their key order is the
+/// author's and is kept.
+const SCHEMA_MAPS: &[&str] = &[
+    "properties",
+    "patternProperties",
+    "$defs",
+    "definitions",
+    "dependentSchemas",
+];
+
+/// Widest line an inline scalar array may produce.
+const MAX_WIDTH: usize = 100;
+
+/// Formatting outcome for one file.
+#[derive(Debug, Clone, Serialize)]
+pub struct FormattedFile {
+    pub file: PathBuf,
+    /// The file's content differs from its formatted form.
+    pub changed: bool,
+    /// Set when the file couldn't be read or parsed (it is left untouched).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Format a schema as a string, with a trailing newline.
+pub fn format_schema(schema: &Value) -> String {
+    let mut out = String::new();
+    write_value(&mut out, schema, 0, 0, false);
+    out.push('\n');
+    out
+}
+
+/// Format every schema file under `path`, rewriting changed files when
+/// `write` is set (otherwise only reporting them).
+pub fn format_files(path: &Path, write: bool) -> Vec<FormattedFile> {
+    collect_schema_files(path)
+        .into_iter()
+        .map(|file| {
+            let mut result = FormattedFile {
+                file: file.clone(),
+                changed: false,
+                error: None,
+            };
+            let formatted = load_schema(&file)
+                .map(|schema| format_schema(&schema))
+                .map_err(|e| e.to_string());
+            match formatted {
+                Ok(formatted) => {
+                    let current = std::fs::read_to_string(&file).unwrap_or_default();
+                    result.changed = current != formatted;
+                    if result.changed && write {
+                        if let Err(e) = std::fs::write(&file, formatted) {
+                            result.error = Some(format!("cannot write: {}", e));
+                        }
+                    }
+                }
+                Err(message) => result.error = Some(message),
+            }
+            result
+        })
+        .collect()
+}
+
+/// Rank of a key in a schema object: leading keys, others, annotations,
+/// trailing keys.
+fn key_rank(key: &str) -> (usize, usize) {
+    if let Some(i) = LEADING_KEYS.iter().position(|k| *k == key) {
+        (0, i)
+    } else if let Some(i) = UCP_ANNOTATIONS.iter().position(|k| *k == key) {
+        (2, i)
+    } else if let Some(i) = TRAILING_KEYS.iter().position(|k| *k == key) {
+        (3, i)
+    } else {
+        (1, 0)
+    }
+}
+
+/// `names` is set when `value` maps names to schemas (keys keep their
+/// order); `column` is where the value starts on its line.
+fn write_value(out: &mut String, value: &Value, indent: usize, column: usize, names: bool) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            if !names {
+                // Stable sort: keys of equal rank keep their order
+                entries.sort_by_key(|(key, _)| key_rank(key));
+            }
+            out.push_str("{\n");
+            for (i, (key, child)) in entries.iter().enumerate() {
+                let prefix = format!("{}{}: ", "  ".repeat(indent + 1), Value::from(key.as_str()));
+                out.push_str(&prefix);
+                let child_names = !names && SCHEMA_MAPS.contains(&key.as_str());
+                write_value(out, child, indent + 1, prefix.len(), child_names);
+                out.push_str(if i + 1 < entries.len() { ",\n" } else { "\n" });
+            }
+            out.push_str(&"  ".repeat(indent));
+            out.push('}');
+        }
+        Value::Array(items) if !items.is_empty() => {
+            let scalars = items.iter().all(|v| !v.is_object() && !v.is_array());
+            if scalars {
+                let inline: Vec<String> = items.iter().map(Value::to_string).collect();
+                let inline = format!("[{}]", inline.join(", "));
+                // Room for a trailing comma
+                if column + inline.len() < MAX_WIDTH {
+                    out.push_str(&inline);
+                    return;
+                }
+            }
+            out.push_str("[\n");
+            for (i, item) in items.iter().enumerate() {
+                let pad = "  ".repeat(indent + 1);
+                out.push_str(&pad);
+                write_value(out, item, indent + 1, pad.len(), false);
+                out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+            }
+            out.push_str(&"  ".repeat(indent));
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn format_orders_keys_and_inlines_short_arrays() {
+        let schema = json!({
+            "properties": {
+                "zeta": { "ucp_request": "omit", "type": "string" },
+                "alpha": { "enum": ["a", "b"], "description": "First" }
+            },
+            "required": ["zeta"],
+            "additionalProperties": false,
+            "ucp_response": "required",
+            "minProperties": 1,
+            "type": "object",
+            "$id": "https://ucp.dev/x.json",
+            "examples": [{}]
+        });
+        let formatted = format_schema(&schema);
+
+        assert_eq!(
+            formatted,
+            r#"{
+  "$id": "https://ucp.dev/x.json",
+  "type": "object",
+  "minProperties": 1,
+  "examples": [
+    {}
+  ],
+  "ucp_response": "required",
+  "required": ["zeta"],
+  "properties": {
+    "zeta": {
+      "type": "string",
+      "ucp_request": "omit"
+    },
+    "alpha": {
+      "description": "First",
+      "enum": ["a", "b"]
+    }
+  },
+  "additionalProperties": false
+}
+"#
+        );
+        let reparsed: Value = serde_json::from_str(&formatted).unwrap();
+        assert_eq!(reparsed, schema);
+        assert_eq!(format_schema(&reparsed), formatted);
+    }
+
+    #[test]
+    fn format_wraps_long_arrays() {
+        let values: Vec<String> = (0..20).map(|i| format!("value{}", i)).collect();
+        let formatted = format_schema(&json!({ "enum": values }));
+        assert!(formatted.starts_with("{\n  \"enum\": [\n    \"value0\",\n"));
+    }
+}
@@ -0,0 +1,439 @@
+//! Negative payload generation - near-miss mutations of valid examples.
+//!
+//! Each mutation breaks one constraint of the resolved schema at one
+//! location of a valid example: a value of the wrong type, a missing
+//! required property, an extra property, a value just
+//! past a numeric, length or item-count bound, or a value outside an `enum`
+//! or `const`. A mutation the schema still accepts points at a constraint
+//! that isn't enforced, most often an object left open by omission.
+//!
+//! Mutations are enumerated in schema order, so runs are reproducible.
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::error::{ResolveError, ValidateError};
+use crate::loader::escape_pointer_token;
+use crate::resolver::resolve;
+use crate::types::ResolveOptions;
+use crate::validator::validate_against_schema;
+
+/// Property name used for extra-field mutations.
+const EXTRA_PROPERTY: &str = "x_fuzz_extra";
+
+/// What a mutation breaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MutationKind {
+    WrongType,
+    MissingRequired,
+    ExtraField,
+    Boundary,
+    Enum,
+}
+
+/// One near-miss payload.
+#[derive(Debug, Clone, Serialize)]
+pub struct Mutation {
+    pub kind: MutationKind,
+    /// Index of the example it was derived from.
+    pub example: usize,
+    /// JSON pointer in the payload to the mutated value (or the object a
+    /// property was removed from or added to).
+    pub path: String,
+    /// What was changed (e.g., "wrong type: string instead of integer").
+    pub description: String,
+    pub payload: Value,
+    /// Whether the resolved schema rejects the payload.
+    pub caught: bool,
+}
+
+/// Mutations of a set of examples.
+#[derive(Debug, Clone, Serialize)]
+pub struct FuzzRun {
+    pub mutations: Vec<Mutation>,
+    /// Examples skipped because they don't validate.
+    pub invalid_examples: Vec<usize>,
+}
+
+impl FuzzRun {
+    /// Mutations the schema accepted.
+    pub fn missed(&self) -> impl Iterator<Item = &Mutation> {
+        self.mutations.iter().filter(|m| !m.caught)
+    }
+}
+
+/// Resolve `schema` with `options` and derive up to `count` mutations from
+/// the valid `examples`, validating each one.
+///
+/// Walks `properties`, `items` (through the first element of each array),
+/// `allOf` and local `$ref`s; `oneOf`/`anyOf` branches aren't mutated.
+/// Extra-field mutations are made on objects declaring `properties`, unless
+/// they are explicitly open (`additionalProperties` set to `true` or a
+/// schema), so objects left open by omission show up as missed.
+///
+/// # Errors
+///
+/// Returns the `ResolveError` from resolving or compiling the schema.
+pub fn fuzz_payloads(
+    schema: &Value,
+    examples: &[Value],
+    options: &ResolveOptions,
+    count: usize,
+) -> Result<FuzzRun, ResolveError> {
+    let resolved = resolve(schema, options)?;
+    let mut run = FuzzRun {
+        mutations: Vec::new(),
+        invalid_examples: Vec::new(),
+    };
+
+    for (i, example) in examples.iter().enumerate() {
+        if run.mutations.len() >= count {
+            break;
+        }
+        match validate_against_schema(&resolved, example) {
+            Ok(()) => {}
+            Err(ValidateError::Invalid { .. }) => {
+                run.invalid_examples.push(i);
+                continue;
+            }
+            Err(ValidateError::Resolve(e)) => return Err(e),
+        }
+
+        let mut walker = Walker {
+            root: &resolved,
+            example,
+            refs: Vec::new(),
+            seen: HashSet::new(),
+            found: Vec::new(),
+        };
+        walker.visit(&resolved, example, "");
+        for (kind, path, description, payload) in walker.found {
+            if run.mutations.len() >= count {
+                break;
+            }
+            let caught = matches!(
+                validate_against_schema(&resolved, &payload),
+                Err(ValidateError::Invalid { .. })
+            );
+            run.mutations.push(Mutation {
+                kind,
+                example: i,
+                path,
+                description,
+                payload,
+                caught,
+            });
+        }
+    }
+    Ok(run)
+}
+
+struct Walker<'a> {
+    root: &'a Value,
+    example: &'a Value,
+    /// `$ref`s being followed, to stop at recursive types.
+    refs: Vec<&'a str>,
+    /// (path, description) already produced, across `allOf` branches.
+    seen: HashSet<(String, String)>,
+    found: Vec<(MutationKind, String, String, Value)>,
+}
+
+impl<'a> Walker<'a> {
+    fn visit(&mut self, schema: &'a Value, instance: &Value, path: &str) {
+        let Value::Object(map) = schema else {
+            return;
+        };
+
+        if let Some(Value::String(reference)) = map.get("$ref") {
+            if let Some(target) = reference.strip_prefix('#') {
+                if !self.refs.contains(&target) {
+                    if let Some(def) = self.root.pointer(target) {
+                        self.refs.push(target);
+                        self.visit(def, instance, path);
+                        self.refs.pop();
+                    }
+                }
+            }
+        }
+        if let Some(Value::Array(branches)) = map.get("allOf") {
+            for branch in branches {
+                self.visit(branch, instance, path);
+            }
+        }
+
+        self.wrong_type(map.get("type"), path);
+        self.enum_violation(map, instance, path);
+        self.boundaries(map, instance, path);
+
+        match instance {
+            Value::Object(fields) => {
+                for name in map
+                    .get("required")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .filter(|name| fields.contains_key(*name))
+                {
+                    let payload = self.edit(path, |target| {
+                        if let Value::Object(obj) = target {
+                            obj.remove(name);
+                        }
+                    });
+                    let description = format!("missing required property \"{}\"", name);
+                    self.push(MutationKind::MissingRequired, path, description, payload);
+                }
+
+                let open = ["additionalProperties", "unevaluatedProperties"]
+                    .iter()
+                    .any(|k| map.get(*k).is_some_and(|v| v != &Value::Bool(false)));
+                if map.contains_key("properties") && !open && !fields.contains_key(EXTRA_PROPERTY) {
+                    let payload = self.edit(path, |target| {
+                        if let Value::Object(obj) = target {
+                            obj.insert(EXTRA_PROPERTY.to_string(), Value::Bool(true));
+                        }
+                    });
+                    let description = format!("extra property \"{}\"", EXTRA_PROPERTY);
+                    self.push(MutationKind::ExtraField, path, description, payload);
+                }
+
+                if let Some(Value::Object(properties)) = map.get("properties") {
+                    for (name, property) in properties {
+                        if let Some(value) = fields.get(name) {
+                            let child = format!("{}/{}", path, escape_pointer_token(name));
+                            self.visit(property, value, &child);
+                        }
+                    }
+                }
+            }
+            Value::Array(elements) => {
+                if let (Some(items), Some(first)) = (map.get("items"), elements.first()) {
+                    self.visit(items, first, &format!("{}/0", path));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn wrong_type(&mut self, types: Option<&Value>, path: &str) {
+        let allowed: Vec<&str> = match types {
+            Some(Value::String(t)) => vec![t.as_str()],
+            Some(Value::Array(ts)) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => return,
+        };
+        let candidates = [
+            ("string", json!("fuzz")),
+            ("number", json!(0.5)),
+            ("boolean", json!(true)),
+            ("null", Value::Null),
+            ("object", json!({})),
+            ("array", json!([])),
+        ];
+        // 0.5 is never an integer, so it's a wrong type for "integer" too
+        let Some((name, value)) = candidates
+            .into_iter()
+            .find(|(name, _)| !allowed.contains(name))
+        else {
+            return;
+        };
+        let payload = self.edit(path, |target| *target = value);
+        let description = format!("wrong type: {} instead of {}", name, allowed.join(" or "));
+        self.push(MutationKind::WrongType, path, description, payload);
+    }
+
+    fn enum_violation(
+        &mut self,
+        map: &serde_json::Map<String, Value>,
+        instance: &Value,
+        path: &str,
+    ) {
+        let allowed: Vec<&Value> = match (map.get("enum"), map.get("const")) {
+            (Some(Value::Array(values)), _) => values.iter().collect(),
+            (_, Some(value)) => vec![value],
+            _ => return,
+        };
+        // Same type as the valid value, so only the enum can reject it
+        let replacement = match instance {
+            Value::String(s) => json!(format!("{}_fuzz", s)),
+            Value::Number(n) => json!(n.as_f64().unwrap_or(0.0).floor() + 1000.0),
+            Value::Bool(b) => json!(!b),
+            _ => json!("fuzz"),
+        };
+        if allowed.contains(&&replacement) {
+            return;
+        }
+        let keyword = if map.contains_key("enum") {
+            "enum"
+        } else {
+            "const"
+        };
+        let payload = self.edit(path, |target| *target = replacement);
+        self.push(
+            MutationKind::Enum,
+            path,
+            format!("value outside {}", keyword),
+            payload,
+        );
+    }
+
+    fn boundaries(&mut self, map: &serde_json::Map<String, Value>, instance: &Value, path: &str) {
+        let number = |key: &str| map.get(key).and_then(Value::as_f64);
+        let length = |key: &str| map.get(key).and_then(Value::as_u64);
+        let mut edits: Vec<(String, Value)> = Vec::new();
+
+        match instance {
+            Value::Number(n) => {
+                let integral = n.is_i64() || n.is_u64();
+                let past = |bound: f64, step: f64| {
+                    let value = bound + step;
+                    if integral && value.fract() == 0.0 {
+                        json!(value as i64)
+                    } else {
+                        json!(value)
+                    }
+                };
+                if let Some(min) = number("minimum") {
+                    edits.push((format!("below minimum {}", min), past(min, -1.0)));
+                }
+                if let Some(min) = number("exclusiveMinimum") {
+                    edits.push((format!("at exclusiveMinimum {}", min), past(min, 0.0)));
+                }
+                if let Some(max) = number("maximum") {
+                    edits.push((format!("above maximum {}", max), past(max, 1.0)));
+                }
+                if let Some(max) = number("exclusiveMaximum") {
+                    edits.push((format!("at exclusiveMaximum {}", max), past(max, 0.0)));
+                }
+            }
+            Value::String(s) => {
+                if let Some(min) = length("minLength").filter(|&min| min > 0) {
+                    let shorter: String = s.chars().take(min as usize - 1).collect();
+                    edits.push((format!("shorter than minLength {}", min), json!(shorter)));
+                }
+                if let Some(max) = length("maxLength").filter(|&max| max < 10_000) {
+                    let longer = "a".repeat(max as usize + 1);
+                    edits.push((format!("longer than maxLength {}", max), json!(longer)));
+                }
+            }
+            Value::Array(items) => {
+                if let Some(min) = length("minItems").filter(|&min| min > 0) {
+                    let fewer: Vec<Value> = items.iter().take(min as usize - 1).cloned().collect();
+                    edits.push((format!("fewer than minItems {}", min), json!(fewer)));
+                }
+                if let Some(max) = length("maxItems").filter(|&max| max < 1_000) {
+                    let filler = items.first().cloned().unwrap_or(Value::Null);
+                    let more = vec![filler; max as usize + 1];
+                    edits.push((format!("more than maxItems {}", max), json!(more)));
+                }
+            }
+            _ => {}
+        }
+
+        for (description, value) in edits {
+            let payload = self.edit(path, |target| *target = value);
+            self.push(MutationKind::Boundary, path, description, payload);
+        }
+    }
+
+    /// The example with `change` applied to the value at `path`.
+    fn edit(&self, path: &str, change: impl FnOnce(&mut Value)) -> Value {
+        let mut payload = self.example.clone();
+        if let Some(target) = payload.pointer_mut(path) {
+            change(target);
+        }
+        payload
+    }
+
+    fn push(&mut self, kind: MutationKind, path: &str, description: String, payload: Value) {
+        if self.seen.insert((path.to_string(), description.clone())) {
+            self.found
+                .push((kind, path.to_string(), description, payload));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Direction;
+
+    #[test]
+    fn fuzz_mutates_each_constraint() {
+        let schema = json!({
+            "type": "object",
+            "additionalProperties": false,
+            "required": ["qty"],
+            "properties": {
+                "id": { "type": "string", "ucp_request": { "create": "omit" } },
+                "qty": { "type": "integer", "minimum": 1 },
+                "status": { "type": "string", "enum": ["open", "closed"] },
+                "tags": { "type": "array", "items": { "$ref": "#/$defs/tag" } }
+            },
+            "$defs": { "tag": { "type": "string", "maxLength": 3 } }
+        });
+        let examples = [json!({ "qty": 2, "status": "open", "tags": ["a"] })];
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let run = fuzz_payloads(&schema, &examples, &options, 100).unwrap();
+
+        let found: Vec<(&str, &str)> = run
+            .mutations
+            .iter()
+            .map(|m| (m.path.as_str(), m.description.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("", "wrong type: string instead of object"),
+                ("", "missing required property \"qty\""),
+                ("", "extra property \"x_fuzz_extra\""),
+                ("/qty", "wrong type: string instead of integer"),
+                ("/qty", "below minimum 1"),
+                ("/status", "wrong type: number instead of string"),
+                ("/status", "value outside enum"),
+                ("/tags", "wrong type: string instead of array"),
+                ("/tags/0", "wrong type: number instead of string"),
+                ("/tags/0", "longer than maxLength 3"),
+            ]
+        );
+        assert_eq!(run.mutations[4].payload["qty"], 0);
+        assert!(run.mutations.iter().all(|m| m.caught));
+        assert!(run.invalid_examples.is_empty());
+    }
+
+    #[test]
+    fn fuzz_reports_missed_mutations_and_invalid_examples() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "code": { "type": "string" },
+                "metadata": { "type": "object", "additionalProperties": true, "properties": {} }
+            }
+        });
+        let examples = [
+            json!({ "code": 5 }),
+            json!({ "code": "SAVE10", "metadata": {} }),
+        ];
+        let options = ResolveOptions::new(Direction::Response, "read");
+        let run = fuzz_payloads(&schema, &examples, &options, 4).unwrap();
+
+        assert_eq!(run.invalid_examples, [0]);
+        let found: Vec<(&str, &str, bool)> = run
+            .mutations
+            .iter()
+            .map(|m| (m.path.as_str(), m.description.as_str(), m.caught))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("", "wrong type: string instead of object", true),
+                ("", "extra property \"x_fuzz_extra\"", false),
+                ("/code", "wrong type: number instead of string", true),
+                ("/metadata", "wrong type: string instead of object", true),
+            ]
+        );
+        assert_eq!(run.missed().count(), 1);
+    }
+}
@@ -0,0 +1,401 @@
+//! Schema dependency graph - `$ref`s and capability `extends` between files.
+//!
+//! Nodes are the schema files under a directory, plus any reference target
+//! outside it. Edges point from the dependent schema to its dependency, so a
+//! node's fan-in is the number of schemas that would be affected by changing it.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::linter::{collect_refs, collect_schema_files, strip_fragment};
+use crate::loader::load_schema;
+
+/// How one schema depends on another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EdgeKind {
+    /// A `$ref` to another file (relative path or absolute `$id`).
+    Ref,
+    /// An extension capability extends its parent: either a `$defs` entry
+    /// keyed by the parent's name, or an `extends` capability declaration.
+    Extends,
+}
+
+/// A schema file, or a reference target outside the scanned files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphNode {
+    /// Path relative to the scanned directory, or the URL / capability name
+    /// for targets not found there.
+    pub label: String,
+    /// True for targets not among the scanned files.
+    pub external: bool,
+}
+
+/// A dependency from `from` onto `to` (indices into `SchemaGraph::nodes`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GraphEdge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// Dependencies between schema files.
+#[derive(Debug, Clone, Default)]
+pub struct SchemaGraph {
+    pub nodes: Vec<GraphNode>,
+    /// Deduplicated and sorted.
+    pub edges: Vec<GraphEdge>,
+}
+
+impl SchemaGraph {
+    /// Number of distinct nodes depending on `node`.
+    pub fn fan_in(&self, node: usize) -> usize {
+        self.edges
+            .iter()
+            .filter(|e| e.to == node)
+            .map(|e| e.from)
+            .collect::<BTreeSet<_>>()
+            .len()
+    }
+
+    /// Render as Graphviz DOT. Extends edges are dashed, external nodes
+    /// dotted, and nodes with a fan-in above one are labelled with it.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph schemas {\n    rankdir=LR;\n    node [shape=box];\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            let style = if node.external { ", style=dotted" } else { "" };
+            let _ = writeln!(
+                out,
+                "    n{} [label={}{}];",
+                i,
+                dot_string(&self.label(i)),
+                style
+            );
+        }
+        for edge in &self.edges {
+            let attrs = match edge.kind {
+                EdgeKind::Ref => "",
+                EdgeKind::Extends => " [style=dashed, label=\"extends\"]",
+            };
+            let _ = writeln!(out, "    n{} -> n{}{};", edge.from, edge.to, attrs);
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Render as a Mermaid flowchart, with the same conventions as `to_dot`.
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::from("flowchart LR\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            let label = self.label(i).replace('"', "#quot;").replace('\n', "<br/>");
+            if node.external {
+                let _ = writeln!(out, "    n{}([\"{}\"])", i, label);
+            } else {
+                let _ = writeln!(out, "    n{}[\"{}\"]", i, label);
+            }
+        }
+        for edge in &self.edges {
+            let arrow = match edge.kind {
+                EdgeKind::Ref => "-->",
+                EdgeKind::Extends => "-. extends .->",
+            };
+            let _ = writeln!(out, "    n{} {} n{}", edge.from, arrow, edge.to);
+        }
+        out
+    }
+
+    fn label(&self, node: usize) -> String {
+        let label = &self.nodes[node].label;
+        match self.fan_in(node) {
+            n if n > 1 => format!("{}\n(fan-in {})", label, n),
+            _ => label.clone(),
+        }
+    }
+}
+
+/// Build the dependency graph of the `.json` schema files under `path`.
+///
+/// Relative `$ref`s resolve against the referring file; absolute ones match
+/// another file's `$id`, or become an external node. Extension schemas
+/// (`$defs` keyed by another file's capability `name`) and `extends`
+/// declarations in profiles add `Extends` edges. Files that fail to parse
+/// are left out.
+pub fn schema_graph(path: &Path) -> SchemaGraph {
+    let base = if path.is_file() {
+        path.parent().unwrap_or(Path::new("."))
+    } else {
+        path
+    };
+    let canonical = |p: &Path| std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+    let canonical_base = canonical(base);
+    let relative = |p: &Path| {
+        p.strip_prefix(&canonical_base)
+            .unwrap_or(p)
+            .to_string_lossy()
+            .replace('\\', "/")
+    };
+
+    let mut graph = SchemaGraph::default();
+    let mut by_path: HashMap<PathBuf, usize> = HashMap::new();
+    let mut by_id: HashMap<String, usize> = HashMap::new();
+    let mut by_name: HashMap<String, usize> = HashMap::new();
+    let mut schemas: Vec<(usize, PathBuf, Value)> = Vec::new();
+
+    for file in collect_schema_files(path) {
+        let Ok(schema) = load_schema(&file) else {
+            continue;
+        };
+        let file = canonical(&file);
+        let index = graph.nodes.len();
+        graph.nodes.push(GraphNode {
+            label: relative(&file),
+            external: false,
+        });
+        by_path.insert(file.clone(), index);
+        if let Some(id) = schema.get("$id").and_then(Value::as_str) {
+            by_id.insert(strip_fragment(id).to_string(), index);
+        }
+        if let Some(name) = capability_name(&schema) {
+            by_name.insert(name.to_string(), index);
+        }
+        schemas.push((index, file, schema));
+    }
+
+    let mut external: HashMap<String, usize> = HashMap::new();
+    let mut node_for = |graph: &mut SchemaGraph, label: String| {
+        *external.entry(label.clone()).or_insert_with(|| {
+            graph.nodes.push(GraphNode {
+                label,
+                external: true,
+            });
+            graph.nodes.len() - 1
+        })
+    };
+
+    let mut edges = BTreeSet::new();
+    for (from, file, schema) in &schemas {
+        let dir = file.parent().unwrap_or(Path::new("."));
+        let mut refs = Vec::new();
+        collect_refs(schema, &mut refs);
+        for r in refs {
+            let target = strip_fragment(&r);
+            if target.is_empty() {
+                continue;
+            }
+            let to = if target.starts_with("http://") || target.starts_with("https://") {
+                match by_id.get(target) {
+                    Some(&to) => to,
+                    None => node_for(&mut graph, target.to_string()),
+                }
+            } else {
+                let target = canonical(&dir.join(target));
+                match by_path.get(&target) {
+                    Some(&to) => to,
+                    None => node_for(&mut graph, relative(&target)),
+                }
+            };
+            edges.insert(GraphEdge {
+                from: *from,
+                to,
+                kind: EdgeKind::Ref,
+            });
+        }
+
+        // Extension schema: $defs keyed by the capabilities it extends
+        if capability_name(schema).is_some() {
+            let parents = schema
+                .get("$defs")
+                .and_then(Value::as_object)
+                .into_iter()
+                .flat_map(|defs| defs.keys())
+                .filter_map(|key| by_name.get(key));
+            for &to in parents {
+                if to != *from {
+                    edges.insert(GraphEdge {
+                        from: *from,
+                        to,
+                        kind: EdgeKind::Extends,
+                    });
+                }
+            }
+        }
+
+        // Profile or payload: capability declarations with `extends`
+        let declarations = schema
+            .pointer("/ucp/capabilities")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten();
+        for (name, entries) in declarations {
+            let entries = match entries {
+                Value::Array(items) => items.iter().collect(),
+                other => vec![other],
+            };
+            for entry in entries {
+                let parents = match entry.get("extends") {
+                    Some(Value::String(parent)) => vec![parent.as_str()],
+                    Some(Value::Array(parents)) => {
+                        parents.iter().filter_map(Value::as_str).collect()
+                    }
+                    _ => continue,
+                };
+                let child = match by_name.get(name.as_str()) {
+                    Some(&child) => child,
+                    None => node_for(&mut graph, name.clone()),
+                };
+                for parent in parents {
+                    let to = match by_name.get(parent) {
+                        Some(&to) => to,
+                        None => node_for(&mut graph, parent.to_string()),
+                    };
+                    edges.insert(GraphEdge {
+                        from: child,
+                        to,
+                        kind: EdgeKind::Extends,
+                    });
+                }
+            }
+        }
+    }
+
+    graph.edges = edges.into_iter().collect();
+    graph
+}
+
+/// Reverse-domain capability name (e.g., "dev.ucp.shopping.checkout").
+fn capability_name(schema: &Value) -> Option<&str> {
+    schema
+        .get("name")
+        .and_then(Value::as_str)
+        .filter(|name| name.contains('.'))
+}
+
+fn dot_string(s: &str) -> String {
+    format!(
+        "\"{}\"",
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn graph_follows_refs_and_extends() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("types")).unwrap();
+        let write = |name: &str, value: Value| {
+            fs::write(dir.path().join(name), value.to_string()).unwrap();
+        };
+        write(
+            "checkout.json",
+            serde_json::json!({
+                "$id": "https://ucp.dev/schemas/checkout.json",
+                "name": "dev.ucp.shopping.checkout",
+                "properties": {
+                    "items": { "items": { "$ref": "types/item.json" } },
+                    "total": { "$ref": "types/amount.json#/$defs/cents" }
+                }
+            }),
+        );
+        write(
+            "discount.json",
+            serde_json::json!({
+                "name": "dev.ucp.shopping.discount",
+                "$defs": {
+                    "dev.ucp.shopping.checkout": {
+                        "allOf": [{ "$ref": "https://ucp.dev/schemas/checkout.json" }],
+                        "properties": { "amount": { "$ref": "types/amount.json" } }
+                    }
+                }
+            }),
+        );
+        write(
+            "types/item.json",
+            serde_json::json!({ "properties": { "price": { "$ref": "amount.json" } } }),
+        );
+        write(
+            "types/amount.json",
+            serde_json::json!({ "$ref": "https://example.com/money.json" }),
+        );
+
+        let graph = schema_graph(dir.path());
+        let labels: Vec<&str> = graph.nodes.iter().map(|n| n.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            [
+                "checkout.json",
+                "discount.json",
+                "types/amount.json",
+                "types/item.json",
+                "https://example.com/money.json"
+            ]
+        );
+        assert!(graph.nodes[4].external);
+
+        let edge = |from, to, kind| GraphEdge { from, to, kind };
+        assert_eq!(
+            graph.edges,
+            [
+                edge(0, 2, EdgeKind::Ref),
+                edge(0, 3, EdgeKind::Ref),
+                edge(1, 0, EdgeKind::Ref),
+                edge(1, 0, EdgeKind::Extends),
+                edge(1, 2, EdgeKind::Ref),
+                edge(2, 4, EdgeKind::Ref),
+                edge(3, 2, EdgeKind::Ref),
+            ]
+        );
+        assert_eq!(graph.fan_in(2), 3);
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("n2 [label=\"types/amount.json\\n(fan-in 3)\"];"));
+        assert!(dot.contains("n1 -> n0 [style=dashed, label=\"extends\"];"));
+        let mermaid = graph.to_mermaid();
+        assert!(mermaid.contains("n4([\"https://example.com/money.json\"])"));
+        assert!(mermaid.contains("n1 -. extends .-> n0"));
+    }
+
+    #[test]
+    fn graph_reads_extends_declarations() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("profile.json"),
+            serde_json::json!({
+                "ucp": { "capabilities": {
+                    "dev.ucp.shopping.checkout": [{ "version": "2026-01-11" }],
+                    "dev.ucp.shopping.combo": [{
+                        "version": "2026-01-11",
+                        "extends": ["dev.ucp.shopping.checkout", "dev.ucp.shopping.discount"]
+                    }]
+                } }
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let graph = schema_graph(dir.path());
+        let labels: Vec<&str> = graph.nodes.iter().map(|n| n.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            [
+                "profile.json",
+                "dev.ucp.shopping.combo",
+                "dev.ucp.shopping.checkout",
+                "dev.ucp.shopping.discount"
+            ]
+        );
+        assert_eq!(graph.edges.len(), 2);
+        assert!(graph
+            .edges
+            .iter()
+            .all(|e| e.from == 1 && e.kind == EdgeKind::Extends));
+    }
+}
@@ -0,0 +1,250 @@
+//! Annotation matrix - the effective visibility of every property for each
+//! direction and operation, without resolving each combination.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::ResolveError;
+use crate::loader::escape_pointer_token;
+use crate::resolver::{expand_operation_groups, get_visibility};
+use crate::types::{Direction, SchemaTransitionInfo, Visibility};
+
+/// A property's visibility for one operation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MatrixCell {
+    pub operation: String,
+    /// "required", "optional", "omit", or "conditional" (decided by a `when`
+    /// schema on the parent object).
+    pub visibility: String,
+    /// Set when an annotation decides the visibility; otherwise it follows
+    /// the schema's own `required` list.
+    pub annotated: bool,
+    /// The transition in progress; `visibility` is its `from` side.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transition: Option<SchemaTransitionInfo>,
+}
+
+/// One property's row of the matrix.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MatrixRow {
+    /// Dotted name, e.g. "buyer.email", "line_items[].sku", "$defs.item.sku".
+    pub name: String,
+    /// JSON pointer to the property schema.
+    pub path: String,
+    pub request: Vec<MatrixCell>,
+    pub response: Vec<MatrixCell>,
+}
+
+/// Visibility of every property × operation × direction.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AnnotationMatrix {
+    pub operations: Vec<String>,
+    /// Rows in schema order.
+    pub properties: Vec<MatrixRow>,
+}
+
+impl MatrixRow {
+    /// The row's cells for `direction`.
+    pub fn cells(&self, direction: Direction) -> &[MatrixCell] {
+        match direction {
+            Direction::Request => &self.request,
+            Direction::Response => &self.response,
+        }
+    }
+}
+
+/// Build the annotation matrix of `schema` for `operations`.
+///
+/// Walks `properties`, `items`, `allOf`/`anyOf`/`oneOf` branches and `$defs`;
+/// `$ref`s aren't followed, since their targets get rows of their own.
+/// Properties in a composition branch are named as if on the parent object.
+///
+/// # Errors
+///
+/// Returns the `ResolveError` of the first malformed annotation.
+pub fn annotation_matrix(
+    schema: &Value,
+    operations: &[String],
+) -> Result<AnnotationMatrix, ResolveError> {
+    let schema = expand_operation_groups(schema)?;
+    let mut properties = Vec::new();
+    visit(&schema, "", "", operations, &mut properties)?;
+    Ok(AnnotationMatrix {
+        operations: operations.to_vec(),
+        properties,
+    })
+}
+
+fn visit(
+    schema: &Value,
+    path: &str,
+    name: &str,
+    operations: &[String],
+    rows: &mut Vec<MatrixRow>,
+) -> Result<(), ResolveError> {
+    let Value::Object(map) = schema else {
+        return Ok(());
+    };
+    let join = |child: &str| match name {
+        "" => child.to_string(),
+        _ => format!("{}.{}", name, child),
+    };
+
+    if let Some(Value::Object(properties)) = map.get("properties") {
+        let required = map.get("required").and_then(Value::as_array);
+        for (key, property) in properties {
+            let property_path = format!("{}/properties/{}", path, escape_pointer_token(key));
+            let listed = required.is_some_and(|r| r.iter().any(|v| v == key));
+            let cells = |direction| {
+                operations
+                    .iter()
+                    .map(|op| cell(property, direction, op, &property_path, listed))
+                    .collect::<Result<Vec<_>, _>>()
+            };
+            rows.push(MatrixRow {
+                name: join(key),
+                path: property_path.clone(),
+                request: cells(Direction::Request)?,
+                response: cells(Direction::Response)?,
+            });
+            visit(property, &property_path, &join(key), operations, rows)?;
+        }
+    }
+
+    if let Some(items) = map.get("items") {
+        let items_name = format!("{}[]", name);
+        visit(
+            items,
+            &format!("{}/items", path),
+            &items_name,
+            operations,
+            rows,
+        )?;
+    }
+    for keyword in ["allOf", "anyOf", "oneOf"] {
+        if let Some(Value::Array(branches)) = map.get(keyword) {
+            for (i, branch) in branches.iter().enumerate() {
+                let branch_path = format!("{}/{}/{}", path, keyword, i);
+                visit(branch, &branch_path, name, operations, rows)?;
+            }
+        }
+    }
+    if let Some(Value::Object(defs)) = map.get("$defs") {
+        for (key, def) in defs {
+            let def_path = format!("{}/$defs/{}", path, escape_pointer_token(key));
+            visit(def, &def_path, &format!("$defs.{}", key), operations, rows)?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn cell(
+    property: &Value,
+    direction: Direction,
+    operation: &str,
+    path: &str,
+    listed: bool,
+) -> Result<MatrixCell, ResolveError> {
+    let (visibility, transition) = get_visibility(property, direction, operation, path)?;
+    let conditional = property
+        .get(direction.annotation_key())
+        .and_then(|a| a.get(operation))
+        .is_some_and(|entry| entry.get("when").is_some());
+    let visibility = match visibility {
+        _ if conditional => "conditional",
+        Visibility::Omit => "omit",
+        Visibility::Required => "required",
+        Visibility::Optional => "optional",
+        _ if listed => "required",
+        _ => "optional",
+    };
+    Ok(MatrixCell {
+        operation: operation.to_string(),
+        visibility: visibility.to_string(),
+        annotated: conditional
+            || transition.is_some()
+            || visibility_is_annotated(property, direction, operation),
+        transition,
+    })
+}
+
+/// Whether `property`'s annotation for `direction` covers `operation`, or,
+/// without one, the other direction's marks it `readonly`/`writeonly`.
+fn visibility_is_annotated(property: &Value, direction: Direction, operation: &str) -> bool {
+    match property.get(direction.annotation_key()) {
+        Some(Value::String(_)) => true,
+        Some(Value::Object(map)) => map.contains_key(operation) || map.contains_key("transition"),
+        Some(_) => false,
+        None => {
+            let value = match property.get(direction.opposite().annotation_key()) {
+                Some(Value::Object(map)) => map.get(operation),
+                annotation => annotation,
+            };
+            value
+                .and_then(Value::as_str)
+                .and_then(Visibility::parse)
+                .is_some_and(Visibility::is_bidirectional)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn matrix_covers_annotations_defaults_and_transitions() {
+        let schema = json!({
+            "type": "object",
+            "required": ["status"],
+            "properties": {
+                "id": { "type": "string", "ucp_request": { "create": "omit", "update": "required" }, "ucp_response": "required" },
+                "status": { "type": "string" },
+                "line_items": {
+                    "type": "array",
+                    "items": {
+                        "properties": {
+                            "sku": {
+                                "ucp_request": {
+                                    "transition": { "from": "required", "to": "omit", "description": "use product_id" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        let ops = vec!["create".to_string(), "update".to_string()];
+        let matrix = annotation_matrix(&schema, &ops).unwrap();
+
+        let names: Vec<&str> = matrix.properties.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["id", "status", "line_items", "line_items[].sku"]);
+
+        let summary = |row: &MatrixRow, direction| {
+            row.cells(direction)
+                .iter()
+                .map(|c| (c.visibility.clone(), c.annotated))
+                .collect::<Vec<_>>()
+        };
+        let id = &matrix.properties[0];
+        assert_eq!(
+            summary(id, Direction::Request),
+            [("omit".into(), true), ("required".into(), true)]
+        );
+        assert_eq!(
+            summary(id, Direction::Response),
+            [("required".into(), true), ("required".into(), true)]
+        );
+        assert_eq!(
+            summary(&matrix.properties[1], Direction::Request),
+            [("required".into(), false), ("required".into(), false)]
+        );
+
+        let sku = &matrix.properties[3];
+        assert_eq!(sku.path, "/properties/line_items/items/properties/sku");
+        assert_eq!(sku.request[0].visibility, "required");
+        assert_eq!(sku.request[0].transition.as_ref().unwrap().to, "omit");
+        assert_eq!(sku.response[0].visibility, "optional");
+    }
+}
@@ -0,0 +1,162 @@
+//! JUnit XML reports, for CI systems that display test results from
+//! `<testsuites>` files.
+
+use std::fmt::Write;
+
+/// A named group of test cases, rendered as one `<testsuite>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JunitSuite {
+    pub name: String,
+    pub cases: Vec<JunitCase>,
+}
+
+/// One `<testcase>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JunitCase {
+    pub name: String,
+    pub classname: String,
+    pub outcome: JunitOutcome,
+}
+
+/// How a test case ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JunitOutcome {
+    Passed,
+    /// The case ran and failed; `details` becomes the element's text.
+    Failure {
+        message: String,
+        details: String,
+    },
+    /// The case couldn't be run (e.g., the payload didn't parse).
+    Error {
+        message: String,
+    },
+}
+
+impl JunitSuite {
+    fn count(&self, failures: bool) -> usize {
+        self.cases
+            .iter()
+            .filter(|case| match case.outcome {
+                JunitOutcome::Passed => false,
+                JunitOutcome::Failure { .. } => failures,
+                JunitOutcome::Error { .. } => !failures,
+            })
+            .count()
+    }
+}
+
+/// Render `suites` as a JUnit XML document named `name`.
+pub fn junit_xml(name: &str, suites: &[JunitSuite]) -> String {
+    let tests: usize = suites.iter().map(|s| s.cases.len()).sum();
+    let failures: usize = suites.iter().map(|s| s.count(true)).sum();
+    let errors: usize = suites.iter().map(|s| s.count(false)).sum();
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        out,
+        "<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\">",
+        escape(name),
+        tests,
+        failures,
+        errors
+    );
+    for suite in suites {
+        let _ = writeln!(
+            out,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\">",
+            escape(&suite.name),
+            suite.cases.len(),
+            suite.count(true),
+            suite.count(false)
+        );
+        for case in &suite.cases {
+            let _ = write!(
+                out,
+                "    <testcase name=\"{}\" classname=\"{}\"",
+                escape(&case.name),
+                escape(&case.classname)
+            );
+            match &case.outcome {
+                JunitOutcome::Passed => out.push_str("/>\n"),
+                JunitOutcome::Failure { message, details } => {
+                    let _ = writeln!(
+                        out,
+                        ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>",
+                        escape(message),
+                        escape(details)
+                    );
+                }
+                JunitOutcome::Error { message } => {
+                    let _ = writeln!(
+                        out,
+                        ">\n      <error message=\"{}\"/>\n    </testcase>",
+                        escape(message)
+                    );
+                }
+            }
+        }
+        out.push_str("  </testsuite>\n");
+    }
+    out.push_str("</testsuites>\n");
+    out
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn junit_counts_and_escapes() {
+        let suite = JunitSuite {
+            name: "checkout.json".into(),
+            cases: vec![
+                JunitCase {
+                    name: "ok.json".into(),
+                    classname: "request/create".into(),
+                    outcome: JunitOutcome::Passed,
+                },
+                JunitCase {
+                    name: "bad.json".into(),
+                    classname: "request/create".into(),
+                    outcome: JunitOutcome::Failure {
+                        message: "1 validation error".into(),
+                        details: "/id: \"x\" is not of type <integer>".into(),
+                    },
+                },
+                JunitCase {
+                    name: "broken.json".into(),
+                    classname: "request/create".into(),
+                    outcome: JunitOutcome::Error {
+                        message: "expected value & more".into(),
+                    },
+                },
+            ],
+        };
+        let xml = junit_xml("validate", &[suite]);
+
+        assert!(
+            xml.contains("<testsuites name=\"validate\" tests=\"3\" failures=\"1\" errors=\"1\">")
+        );
+        assert!(xml.contains("<testcase name=\"ok.json\" classname=\"request/create\"/>"));
+        assert!(xml.contains(
+            "<failure message=\"1 validation error\">/id: &quot;x&quot; is not of type &lt;integer&gt;</failure>"
+        ));
+        assert!(xml.contains("<error message=\"expected value &amp; more\"/>"));
+    }
+}
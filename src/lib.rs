@@ -40,8 +40,16 @@
 //! | `"omit"` | Remove field | Remove from required |
 //! | `"required"` | Keep field | Add to required |
 //! | `"optional"` | Keep field | Remove from required |
+//! | `"readonly"` | Remove from requests, keep in responses | Remove in requests, preserve in responses |
+//! | `"writeonly"` | Keep in requests, remove from responses | Preserve in requests, remove in responses |
 //! | (none) | Keep field | Preserve original |
 //!
+//! `readonly` and `writeonly` describe both directions, so they may be written
+//! on either annotation. A property without an annotation for the direction
+//! being resolved falls back to the other direction's, if that one is
+//! `readonly` or `writeonly`: `"ucp_response": "readonly"` alone also omits
+//! the field from requests.
+//!
 //! # Annotation Format
 //!
 //! Annotations can be shorthand (applies to all operations):
@@ -54,28 +62,122 @@
 //! { "ucp_request": { "create": "omit", "update": "required" } }
 //! ```
 
+mod cache;
+mod codegen;
 mod compose;
+mod coverage;
+mod diff;
+mod docs;
 mod error;
+mod explain;
+mod explore;
+mod format;
+mod fuzz;
+mod graph;
+mod inspect;
+mod junit;
+mod library;
 mod linter;
 mod loader;
+mod lock;
+mod meta;
+mod migrate;
+mod normalize;
+mod openapi;
+#[cfg(feature = "remote")]
+mod probe;
+mod problem;
+mod profile;
+mod redact;
+#[cfg(feature = "remote")]
+mod registry;
+mod replay;
 mod resolver;
+mod scaffold;
+mod server;
+mod stats;
+mod suite;
+#[cfg(test)]
+mod test_support;
 mod types;
+mod upgrade;
 mod validator;
+mod vendor;
 
+pub use cache::{cache_entries, prune_cache, CacheEntry, CACHE_DIR_ENV};
+pub use codegen::{
+    build_model, generate_code, CodegenModel, CodegenOptions, CodegenTarget, EnumDef, Field,
+    FieldType, StructDef, TypeDef, TypeModule,
+};
 pub use compose::{
-    capability_short_name, compose_from_payload, compose_schema, detect_direction,
-    extract_capabilities, extract_capabilities_from_profile, extract_jsonrpc_payload, Capability,
-    DetectedDirection, SchemaBaseConfig,
+    capabilities_from_files, capability_matches, capability_short_name, compose_from_payload,
+    compose_from_payload_with_options, compose_schema, compose_schema_with_options, dedupe_allof,
+    detect_direction, extract_capabilities, extract_capabilities_from_profile,
+    extract_jsonrpc_payload, is_iso_date, jsonrpc_envelope, Capability, ComposeOptions, Composed,
+    DetectedDirection, SchemaBaseConfig, UrlMapping,
+};
+pub use coverage::{schema_coverage, Coverage, CoverageEntry, CoverageKind};
+pub use diff::{diff_schemas, ChangeKind, Presence, SchemaChange, SchemaDiff};
+pub use docs::{html_site, CapabilityDoc, DocsPage};
+pub use error::{ComposeError, RegistryError, ResolveError, SchemaError, ValidateError};
+pub use explain::{explain_validation, AllOfBranch, AnnotationCause, Explanation};
+pub use explore::{Explorer, EXPLORE_HELP};
+pub use format::{format_files, format_schema, FormattedFile};
+pub use fuzz::{fuzz_payloads, FuzzRun, Mutation, MutationKind};
+pub use graph::{schema_graph, EdgeKind, GraphEdge, GraphNode, SchemaGraph};
+pub use inspect::{annotation_matrix, AnnotationMatrix, MatrixCell, MatrixRow};
+pub use junit::{junit_xml, JunitCase, JunitOutcome, JunitSuite};
+pub use library::{resolve_library, LibraryOptions};
+pub use linter::{
+    detect_orphans, find_orphan_schemas, lint, lint_file, Diagnostic, FileResult, FileStatus,
+    LintResult, Severity,
 };
-pub use error::{ComposeError, ResolveError, SchemaError, ValidateError};
-pub use linter::{lint, lint_file, Diagnostic, FileResult, FileStatus, LintResult, Severity};
 pub use loader::{
-    bundle_refs, bundle_refs_with_url_mapping, is_url, load_schema, load_schema_auto,
-    load_schema_str, navigate_fragment,
+    bundle_refs, bundle_refs_as_defs, bundle_refs_with_source_map, bundle_refs_with_url_mapping,
+    is_url, load_input, load_schema, load_schema_auto, load_schema_str, load_schema_str_as,
+    navigate_fragment, FetchOptions, InputFormat, SourceMapEntry, NO_NETWORK_ENV,
+};
+pub use lock::{sha256_hex, FetchRecorder, LockedSchema, Lockfile, LOCK_FILE, LOCK_VERSION};
+pub use meta::{check_meta_schema, meta_schema, META_SCHEMA_ID};
+pub use migrate::{migrate_transitions, AppliedTransition, Migrated};
+pub use normalize::normalize_schema;
+pub use openapi::{
+    import_openapi, openapi_document, OpenApiImport, OpenApiOptions, OPENAPI_VERSION,
+};
+pub use problem::{ProblemDetails, ProblemError, PROBLEM_CONTENT_TYPE};
+pub use profile::{check_profile, generate_profile, GeneratedProfile, ProfileCheck, ProfileIssue};
+pub use redact::{redact_payload, RedactMode, Redacted, REDACTED};
+pub use replay::{
+    default_operation_rules, endpoint_key, har_exchanges, infer_operation, HarExchange,
+    OperationRule,
 };
-pub use resolver::{resolve, strip_annotations};
-pub use types::{Direction, ResolveOptions, Visibility};
-pub use validator::{validate, validate_against_schema};
+pub use resolver::{
+    annotated_operations, close_allof_branches, declared_operations, expand_operation_groups,
+    find_stray_annotations, is_annotation, monotonicity_violations, redact_keywords, resolve,
+    resolve_with_report, strip_annotations,
+};
+pub use scaffold::{scaffold, ScaffoldFile};
+pub use server::{
+    Response, Server, ServerConfig, DEFAULT_CACHE_SIZE, DEFAULT_TIMEOUT, DEFAULT_WORKERS,
+};
+pub use stats::{file_stats, schema_stats, FileStats, ResolvedSize, SchemaStats};
+pub use suite::{run_schema_tests, CaseResult, FileTests, TestReport, TESTS_KEY, TESTS_SUFFIX};
+pub use types::{
+    Denullify, Direction, PropertyResolution, ResolutionReport, ResolveOptions, Visibility,
+    UCP_OPERATIONS, UCP_OPERATION_GROUPS, UCP_SENSITIVE,
+};
+pub use upgrade::{upgrade_annotations, upgrade_files, AnnotationUpgrade, Upgraded, UpgradedFile};
+pub use validator::{
+    annotate_payload, deref_payload, scrub_payload, validate, validate_against_schema,
+    CompiledValidator, Scrubbed,
+};
+pub use vendor::{vendor_schemas, VendoredFile};
 
 #[cfg(feature = "remote")]
 pub use loader::{bundle_refs_remote, load_schema_url};
+#[cfg(feature = "remote")]
+pub use probe::{default_method, probe, ProbeRequest, ProbeResult};
+#[cfg(feature = "remote")]
+pub use registry::{
+    pull_schema, push_schemas, registry_url, PulledSchema, PushStatus, PushedSchema, SHA256_HEADER,
+};
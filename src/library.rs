@@ -0,0 +1,223 @@
+//! Schema libraries - every operation/direction resolution in one document.
+//!
+//! Resolves a schema for each operation in both directions and assembles the
+//! results under `$defs` with canonical names (e.g., `CheckoutCreateRequest`),
+//! producing a single reusable file for code generators and documentation.
+
+use serde_json::{json, Map, Value};
+
+use crate::error::ResolveError;
+use crate::resolver::{resolve, DATA_KEYWORDS};
+use crate::types::{Direction, ResolveOptions};
+
+/// Options for building a schema library.
+#[derive(Debug, Clone)]
+pub struct LibraryOptions {
+    /// Base name for `$defs` entries (e.g., "checkout" -> `CheckoutCreateRequest`).
+    pub name: String,
+    /// Operations to resolve, in output order. Normalized to lowercase.
+    pub operations: Vec<String>,
+    /// Resolve in strict mode (see `ResolveOptions::strict`).
+    pub strict: bool,
+    /// Replace entries identical to an earlier entry with a `$ref` to it.
+    pub dedupe: bool,
+}
+
+impl LibraryOptions {
+    /// Create library options with strict mode and deduplication disabled.
+    pub fn new<S: Into<String>>(name: impl Into<String>, operations: Vec<S>) -> Self {
+        Self {
+            name: name.into(),
+            operations: operations
+                .into_iter()
+                .map(|op| op.into().to_lowercase())
+                .collect(),
+            strict: false,
+            dedupe: false,
+        }
+    }
+
+    /// Set strict mode for every resolution.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Deduplicate identical resolved schemas.
+    pub fn dedupe(mut self, dedupe: bool) -> Self {
+        self.dedupe = dedupe;
+        self
+    }
+}
+
+/// Resolve `schema` for every operation × direction and assemble a `$defs` library.
+///
+/// Entries are ordered by operation, request before response. Internal refs
+/// (`#/...`) inside each entry are re-rooted under that entry so they keep
+/// pointing at the entry's own definitions.
+///
+/// # Errors
+///
+/// Returns the first `ResolveError` encountered.
+pub fn resolve_library(schema: &Value, options: &LibraryOptions) -> Result<Value, ResolveError> {
+    let base = pascal_case(&options.name);
+    let mut defs = Map::new();
+    let mut seen: Vec<(String, Value)> = Vec::new();
+
+    for op in &options.operations {
+        for direction in [Direction::Request, Direction::Response] {
+            let name = format!(
+                "{}{}{}",
+                base,
+                pascal_case(op),
+                pascal_case(direction.as_str())
+            );
+            let resolve_options = ResolveOptions::new(direction, op).strict(options.strict);
+            let resolved = resolve(schema, &resolve_options)?;
+
+            if options.dedupe {
+                if let Some((first, _)) = seen.iter().find(|(_, v)| *v == resolved) {
+                    defs.insert(name, json!({ "$ref": format!("#/$defs/{}", first) }));
+                    continue;
+                }
+                seen.push((name.clone(), resolved.clone()));
+            }
+
+            let mut entry = resolved;
+            reroot_internal_refs(&mut entry, &format!("#/$defs/{}", name));
+            defs.insert(name, entry);
+        }
+    }
+
+    Ok(json!({ "$defs": defs }))
+}
+
+/// Convert "line_item", "line-item", "Line Item" or "line.item" to "LineItem".
+pub(crate) fn pascal_case(s: &str) -> String {
+    s.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut chars = w.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Rewrite `#`-relative refs so they resolve from inside `prefix`. Data
+/// keywords hold instance values, so a `$ref` key inside them is left alone.
+fn reroot_internal_refs(value: &mut Value, prefix: &str) {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::String(r)) = map.get_mut("$ref") {
+                if let Some(rest) = r.strip_prefix('#') {
+                    *r = format!("{}{}", prefix, rest);
+                }
+            }
+            for (k, child) in map.iter_mut() {
+                if !DATA_KEYWORDS.contains(&k.as_str()) {
+                    reroot_internal_refs(child, prefix);
+                }
+            }
+        }
+        Value::Array(arr) => {
+            for item in arr {
+                reroot_internal_refs(item, prefix);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::checkout;
+
+    #[test]
+    fn library_names_and_strips_annotations() {
+        let options = LibraryOptions::new("checkout", vec!["create", "update"]);
+        let library = resolve_library(&checkout(), &options).unwrap();
+        let defs = library["$defs"].as_object().unwrap();
+
+        let names: Vec<&str> = defs.keys().map(String::as_str).collect();
+        assert_eq!(
+            names,
+            [
+                "CheckoutCreateRequest",
+                "CheckoutCreateResponse",
+                "CheckoutUpdateRequest",
+                "CheckoutUpdateResponse"
+            ]
+        );
+        assert!(defs["CheckoutCreateRequest"]["properties"]
+            .get("id")
+            .is_none());
+        assert_eq!(
+            defs["CheckoutUpdateRequest"]["required"],
+            json!(["line_items", "status", "id"])
+        );
+        assert!(!library.to_string().contains("ucp_"));
+    }
+
+    #[test]
+    fn library_reroots_internal_refs() {
+        let options = LibraryOptions::new("checkout", vec!["create"]);
+        let library = resolve_library(&checkout(), &options).unwrap();
+
+        assert_eq!(
+            library["$defs"]["CheckoutCreateRequest"]["properties"]["buyer"]["$ref"],
+            "#/$defs/CheckoutCreateRequest/$defs/buyer"
+        );
+    }
+
+    #[test]
+    fn library_leaves_ref_shaped_data_alone() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "link": {
+                    "type": "object",
+                    "const": { "$ref": "#/definitions/x" },
+                    "examples": [{ "$ref": "#/definitions/y" }]
+                }
+            }
+        });
+        let options = LibraryOptions::new("doc", vec!["create"]);
+        let library = resolve_library(&schema, &options).unwrap();
+
+        let link = &library["$defs"]["DocCreateRequest"]["properties"]["link"];
+        assert_eq!(link["const"], json!({ "$ref": "#/definitions/x" }));
+        assert_eq!(link["examples"], json!([{ "$ref": "#/definitions/y" }]));
+    }
+
+    #[test]
+    fn library_dedupe_refs_identical_entries() {
+        let options = LibraryOptions::new("checkout", vec!["create", "update"]).dedupe(true);
+        let library = resolve_library(&checkout(), &options).unwrap();
+
+        // Responses are identical across ops, and an update request (id
+        // required) matches them too; only the create request differs
+        for name in ["CheckoutUpdateRequest", "CheckoutUpdateResponse"] {
+            assert_eq!(
+                library["$defs"][name],
+                json!({ "$ref": "#/$defs/CheckoutCreateResponse" })
+            );
+        }
+        assert!(library["$defs"]["CheckoutCreateRequest"]["properties"].is_object());
+        assert!(library["$defs"]["CheckoutCreateResponse"]["properties"].is_object());
+    }
+
+    #[test]
+    fn pascal_case_variants() {
+        assert_eq!(pascal_case("checkout"), "Checkout");
+        assert_eq!(pascal_case("line_item"), "LineItem");
+        assert_eq!(pascal_case("Line Item"), "LineItem");
+        assert_eq!(
+            pascal_case("dev.ucp.shopping.checkout"),
+            "DevUcpShoppingCheckout"
+        );
+    }
+}
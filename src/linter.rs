@@ -3,16 +3,24 @@
 //! Validates schema files for:
 //! - JSON syntax errors
 //! - Broken $ref references (file not found, anchor not found)
-//! - Invalid ucp_* annotation values
+//! - Invalid ucp_* annotation values and operation groups
+//! - Annotations that don't conform to the UCP meta-schema (see `meta`)
+//! - Schemas unreachable from any capability (opt-in, see `detect_orphans`)
 
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
 use serde::Serialize;
-use serde_json::Value;
+use serde_json::{Map, Value};
 
+use crate::error::ResolveError;
 use crate::loader::{load_schema, navigate_fragment};
+use crate::meta::check_meta_schema;
+use crate::resolver::expand_operation_groups;
+use crate::suite::TESTS_SUFFIX;
 use crate::types::{
-    is_valid_schema_transition, json_type_name, Visibility, UCP_ANNOTATIONS, VALID_OPERATIONS,
+    is_valid_schema_transition, json_type_name, Visibility, UCP_ANNOTATIONS, UCP_OPERATION_GROUPS,
+    VALID_OPERATIONS,
 };
 
 /// Severity level for diagnostics.
@@ -79,27 +87,26 @@ impl LintResult {
 pub fn lint(path: &Path, strict: bool) -> LintResult {
     let files = collect_schema_files(path);
     let mut results = Vec::new();
-    let mut total_errors = 0;
-    let mut total_warnings = 0;
 
     for file in &files {
-        let file_result = lint_file(file, path);
-        let file_errors = file_result
-            .diagnostics
-            .iter()
-            .filter(|d| d.severity == Severity::Error)
-            .count();
-        let file_warnings = file_result
-            .diagnostics
-            .iter()
-            .filter(|d| d.severity == Severity::Warning)
-            .count();
-
-        total_errors += file_errors;
-        total_warnings += file_warnings;
-        results.push(file_result);
+        results.push(lint_file(file, path));
     }
 
+    tally(path, results, strict)
+}
+
+/// Aggregate per-file results into a `LintResult`.
+fn tally(path: &Path, results: Vec<FileResult>, strict: bool) -> LintResult {
+    let count = |severity: Severity| {
+        results
+            .iter()
+            .flat_map(|r| &r.diagnostics)
+            .filter(|d| d.severity == severity)
+            .count()
+    };
+    let total_errors = count(Severity::Error);
+    let total_warnings = count(Severity::Warning);
+
     let failed = results
         .iter()
         .filter(|r| {
@@ -113,8 +120,8 @@ pub fn lint(path: &Path, strict: bool) -> LintResult {
 
     LintResult {
         path: path.to_path_buf(),
-        files_checked: files.len(),
-        passed: files.len() - failed,
+        files_checked: results.len(),
+        passed: results.len() - failed,
         failed,
         errors: total_errors,
         warnings: total_warnings,
@@ -122,6 +129,147 @@ pub fn lint(path: &Path, strict: bool) -> LintResult {
     }
 }
 
+/// Add a `UCP240` warning for every schema under `base` that no capability
+/// schema reaches (see `find_orphan_schemas`).
+///
+/// Orphans outside the linted path are added as extra file results.
+pub fn detect_orphans(result: LintResult, base: &Path, strict: bool) -> LintResult {
+    let LintResult {
+        path, mut results, ..
+    } = result;
+
+    for orphan in find_orphan_schemas(base) {
+        let diagnostic = Diagnostic {
+            severity: Severity::Warning,
+            code: "UCP240".to_string(),
+            file: orphan.clone(),
+            path: "/".to_string(),
+            message: format!(
+                "schema is not reachable from any capability schema in {}",
+                base.display()
+            ),
+        };
+        let canonical = |p: &Path| std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+        let target = canonical(&orphan);
+        match results
+            .iter_mut()
+            .find(|r| canonical(&path.join(&r.file)) == target)
+        {
+            Some(existing) => {
+                existing.diagnostics.push(diagnostic);
+                if existing.status == FileStatus::Ok {
+                    existing.status = FileStatus::Warning;
+                }
+            }
+            None => results.push(FileResult {
+                file: orphan.strip_prefix(&path).unwrap_or(&orphan).to_path_buf(),
+                status: FileStatus::Warning,
+                diagnostics: vec![diagnostic],
+            }),
+        }
+    }
+
+    tally(&path, results, strict)
+}
+
+/// Find schema files under `base` that no capability schema reaches.
+///
+/// Capability entry schemas are files whose `name` is a reverse-domain
+/// capability name (e.g., "dev.ucp.shopping.checkout"). Reachability follows
+/// relative file `$ref`s and absolute `$ref` URLs matching another file's
+/// `$id`. Files that fail to parse are never entry points. Returned paths
+/// are as found under `base`, sorted.
+pub fn find_orphan_schemas(base: &Path) -> Vec<PathBuf> {
+    let files = collect_schema_files(base);
+    let canonical = |p: &Path| std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+
+    let mut schemas: Vec<(PathBuf, Option<Value>)> = Vec::new();
+    let mut by_id: HashMap<String, PathBuf> = HashMap::new();
+    for file in &files {
+        let schema = load_schema(file).ok();
+        if let Some(id) = schema
+            .as_ref()
+            .and_then(|s| s.get("$id"))
+            .and_then(|v| v.as_str())
+        {
+            by_id.insert(strip_fragment(id).to_string(), canonical(file));
+        }
+        schemas.push((canonical(file), schema));
+    }
+
+    let mut pending: Vec<PathBuf> = schemas
+        .iter()
+        .filter(|(_, schema)| {
+            schema
+                .as_ref()
+                .and_then(|s| s.get("name"))
+                .and_then(|n| n.as_str())
+                .is_some_and(|n| n.contains('.'))
+        })
+        .map(|(path, _)| path.clone())
+        .collect();
+    let mut reached: HashSet<PathBuf> = HashSet::new();
+
+    while let Some(file) = pending.pop() {
+        if !reached.insert(file.clone()) {
+            continue;
+        }
+        let Some(schema) = schemas
+            .iter()
+            .find(|(p, _)| *p == file)
+            .and_then(|(_, s)| s.as_ref())
+        else {
+            continue;
+        };
+        let dir = file.parent().unwrap_or(Path::new("."));
+        let mut refs = Vec::new();
+        collect_refs(schema, &mut refs);
+        for r in refs {
+            let target = strip_fragment(&r);
+            if target.is_empty() {
+                continue;
+            }
+            let next = if target.starts_with("http://") || target.starts_with("https://") {
+                by_id.get(target).cloned()
+            } else {
+                Some(canonical(&dir.join(target)))
+            };
+            pending.extend(next);
+        }
+    }
+
+    files
+        .into_iter()
+        .zip(schemas)
+        .filter(|(_, (path, _))| !reached.contains(path))
+        .map(|(file, _)| file)
+        .collect()
+}
+
+pub(crate) fn strip_fragment(reference: &str) -> &str {
+    reference.split('#').next().unwrap_or(reference)
+}
+
+/// Collect every `$ref` string in a schema.
+pub(crate) fn collect_refs(value: &Value, refs: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::String(r)) = map.get("$ref") {
+                refs.push(r.clone());
+            }
+            for child in map.values() {
+                collect_refs(child, refs);
+            }
+        }
+        Value::Array(arr) => {
+            for item in arr {
+                collect_refs(item, refs);
+            }
+        }
+        _ => {}
+    }
+}
+
 /// Lint a single schema file.
 pub fn lint_file(file: &Path, base_path: &Path) -> FileResult {
     let mut diagnostics = Vec::new();
@@ -149,8 +297,27 @@ pub fn lint_file(file: &Path, base_path: &Path) -> FileResult {
     let file_dir = file.parent().unwrap_or(Path::new("."));
     check_refs(&schema, file, file_dir, "", &schema, &mut diagnostics);
 
-    // Check ucp_* annotations
-    check_annotations(&schema, file, "", &mut diagnostics);
+    // Check ucp_* annotations; declared operation groups are known keys
+    let groups: Vec<String> = schema
+        .get(UCP_OPERATION_GROUPS)
+        .and_then(Value::as_object)
+        .map(|groups| groups.keys().cloned().collect())
+        .unwrap_or_default();
+    check_annotations(&schema, file, "", &groups, &mut diagnostics);
+    if let Err(ResolveError::InvalidOperationGroup { path, message }) =
+        expand_operation_groups(&schema)
+    {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            code: "E007".to_string(),
+            file: file.to_path_buf(),
+            path,
+            message: format!("invalid operation group: {}", message),
+        });
+    }
+
+    // Check against the meta-schema, skipping what the checks above reported
+    check_meta(&schema, file, &mut diagnostics);
 
     // Check for missing $id (warning)
     if schema.get("$id").is_none() {
@@ -282,34 +449,63 @@ fn check_single_ref(
 }
 
 /// Recursively check ucp_* annotation values.
-fn check_annotations(value: &Value, file: &Path, path: &str, diagnostics: &mut Vec<Diagnostic>) {
+fn check_annotations(
+    value: &Value,
+    file: &Path,
+    path: &str,
+    groups: &[String],
+    diagnostics: &mut Vec<Diagnostic>,
+) {
     if let Value::Object(map) = value {
         // Check all UCP annotations
         for &annotation_key in UCP_ANNOTATIONS {
             if let Some(annotation) = map.get(annotation_key) {
-                check_annotation_value(annotation, annotation_key, file, path, diagnostics);
+                check_annotation_value(annotation, annotation_key, file, path, groups, diagnostics);
             }
         }
 
         // Recurse
         for (key, val) in map {
             let child_path = format!("{}/{}", path, key);
-            check_annotations(val, file, &child_path, diagnostics);
+            check_annotations(val, file, &child_path, groups, diagnostics);
         }
     } else if let Value::Array(arr) = value {
         for (i, item) in arr.iter().enumerate() {
             let child_path = format!("{}/{}", path, i);
-            check_annotations(item, file, &child_path, diagnostics);
+            check_annotations(item, file, &child_path, groups, diagnostics);
         }
     }
 }
 
+/// Report meta-schema errors at paths no earlier diagnostic covers, so an
+/// invalid `ucp_request` isn't reported twice.
+fn check_meta(schema: &Value, file: &Path, diagnostics: &mut Vec<Diagnostic>) {
+    let covers = |a: &str, b: &str| a == b || b.starts_with(&format!("{}/", a));
+    let found: Vec<Diagnostic> = check_meta_schema(schema)
+        .into_iter()
+        .filter(|e| {
+            !diagnostics
+                .iter()
+                .any(|d| covers(&d.path, &e.path) || covers(&e.path, &d.path))
+        })
+        .map(|e| Diagnostic {
+            severity: Severity::Error,
+            code: "E006".to_string(),
+            file: file.to_path_buf(),
+            path: e.path,
+            message: format!("does not conform to the UCP meta-schema: {}", e.message),
+        })
+        .collect();
+    diagnostics.extend(found);
+}
+
 /// Check a single ucp_* annotation value is valid.
 fn check_annotation_value(
     annotation: &Value,
     key: &str,
     file: &Path,
     path: &str,
+    groups: &[String],
     diagnostics: &mut Vec<Diagnostic>,
 ) {
     let annotation_path = format!("{}/{}", path, key);
@@ -323,7 +519,7 @@ fn check_annotation_value(
                     file: file.to_path_buf(),
                     path: annotation_path,
                     message: format!(
-                        "invalid {} value \"{}\": expected omit, required, or optional",
+                        "invalid {} value \"{}\": expected omit, required, optional, readonly, or writeonly",
                         key, s
                     ),
                 });
@@ -341,7 +537,7 @@ fn check_annotation_value(
                 }
 
                 // Warn on unknown operations
-                if !VALID_OPERATIONS.contains(&op.as_str()) {
+                if !VALID_OPERATIONS.contains(&op.as_str()) && !groups.contains(op) {
                     diagnostics.push(Diagnostic {
                         severity: Severity::Warning,
                         code: "W003".to_string(),
@@ -365,7 +561,7 @@ fn check_annotation_value(
                                 file: file.to_path_buf(),
                                 path: op_path,
                                 message: format!(
-                                    "invalid {} value \"{}\": expected omit, required, or optional",
+                                    "invalid {} value \"{}\": expected omit, required, optional, readonly, or writeonly",
                                     key, s
                                 ),
                             });
@@ -375,6 +571,9 @@ fn check_annotation_value(
                         // Per-operation transition: { "update": { "transition": { ... } } }
                         if let Some(t) = obj.get("transition") {
                             check_transition_object(t, key, file, &op_path, diagnostics);
+                        } else if obj.contains_key("when") {
+                            // Conditional: { "create": { "when": {...}, "then": ..., "else": ... } }
+                            check_conditional_object(obj, key, file, &op_path, diagnostics);
                         } else {
                             diagnostics.push(Diagnostic {
                                 severity: Severity::Error,
@@ -421,6 +620,60 @@ fn check_annotation_value(
     }
 }
 
+/// Validate a conditional visibility object { "when", "then", "else" }.
+fn check_conditional_object(
+    obj: &Map<String, Value>,
+    key: &str,
+    file: &Path,
+    path: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if let Some(when) = obj
+        .get("when")
+        .filter(|w| !w.is_object() && !w.is_boolean())
+    {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            code: "E005".to_string(),
+            file: file.to_path_buf(),
+            path: format!("{}/when", path),
+            message: format!(
+                "invalid {} condition: expected schema object, got {}",
+                key,
+                json_type_name(when)
+            ),
+        });
+    }
+
+    if !obj.contains_key("then") {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            code: "E004".to_string(),
+            file: file.to_path_buf(),
+            path: path.to_string(),
+            message: format!("invalid {} condition: missing required field \"then\"", key),
+        });
+    }
+
+    for branch in ["then", "else"] {
+        let Some(val) = obj.get(branch) else {
+            continue;
+        };
+        if val.as_str().and_then(Visibility::parse).is_none() {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Error,
+                code: "E004".to_string(),
+                file: file.to_path_buf(),
+                path: format!("{}/{}", path, branch),
+                message: format!(
+                    "invalid {} condition {}: expected omit, required, optional, readonly, or writeonly, got {}",
+                    key, branch, val
+                ),
+            });
+        }
+    }
+}
+
 /// Validate a schema transition object { "from", "to", "description" }.
 fn check_transition_object(
     value: &Value,
@@ -479,7 +732,10 @@ fn check_transition_object(
 }
 
 /// Collect all .json files in a path (file or directory).
-fn collect_schema_files(path: &Path) -> Vec<PathBuf> {
+///
+/// Test suites next to schemas (`*.tests.json`) aren't schemas and are
+/// skipped when walking a directory.
+pub(crate) fn collect_schema_files(path: &Path) -> Vec<PathBuf> {
     if path.is_file() {
         if path.extension().map(|e| e == "json").unwrap_or(false) {
             return vec![path.to_path_buf()];
@@ -502,7 +758,9 @@ fn collect_files_recursive(dir: &Path, files: &mut Vec<PathBuf>) {
         let path = entry.path();
         if path.is_dir() {
             collect_files_recursive(&path, files);
-        } else if path.extension().map(|e| e == "json").unwrap_or(false) {
+        } else if path.extension().map(|e| e == "json").unwrap_or(false)
+            && !path.to_string_lossy().ends_with(TESTS_SUFFIX)
+        {
             files.push(path);
         }
     }
@@ -662,6 +920,64 @@ mod tests {
         assert!(result.diagnostics.is_empty());
     }
 
+    #[test]
+    fn lint_conditional_visibility() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            r#"{{
+            "$id": "https://example.com/test.json",
+            "properties": {{
+                "tax_id": {{
+                    "type": "string",
+                    "ucp_request": {{
+                        "create": {{
+                            "when": {{ "properties": {{ "type": {{ "const": "business" }} }} }},
+                            "then": "required",
+                            "else": "optional"
+                        }},
+                        "update": {{ "when": true, "then": "sometimes" }}
+                    }}
+                }}
+            }}
+        }}"#
+        )
+        .unwrap();
+
+        let result = lint_file(file.path(), file.path().parent().unwrap());
+        assert_eq!(result.status, FileStatus::Error);
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].code, "E004");
+        assert!(result.diagnostics[0].path.ends_with("/update/then"));
+    }
+
+    #[test]
+    fn lint_operation_groups() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            r#"{{
+            "$id": "https://example.com/test.json",
+            "x-ucp-operation-groups": {{ "write": ["create", "update"] }},
+            "properties": {{
+                "id": {{ "type": "string", "ucp_request": {{ "write": "omit" }} }},
+                "note": {{ "type": "string", "ucp_request": {{ "write": "omit", "create": "required" }} }}
+            }}
+        }}"#
+        )
+        .unwrap();
+
+        // The group is a known key, but "note" sets "create" twice
+        let result = lint_file(file.path(), file.path().parent().unwrap());
+        assert_eq!(result.status, FileStatus::Error);
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].code, "E007");
+        assert_eq!(
+            result.diagnostics[0].path,
+            "/properties/note/ucp_request/write"
+        );
+    }
+
     #[test]
     fn lint_invalid_schema_transition() {
         let mut file = NamedTempFile::new().unwrap();
@@ -782,6 +1098,53 @@ mod tests {
         assert!(!result.is_ok());
     }
 
+    #[test]
+    fn orphans_follow_relative_and_id_refs() {
+        let dir = tempdir().unwrap();
+        let write = |name: &str, content: &str| {
+            let path = dir.path().join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write(
+            "checkout.json",
+            r#"{"$id": "https://example.com/checkout.json", "name": "dev.example.checkout",
+                "properties": {"item": {"$ref": "types/item.json#/$defs/item"}}}"#,
+        );
+        write(
+            "types/item.json",
+            r#"{"$id": "https://example.com/types/item.json",
+                "$defs": {"item": {"properties": {"price": {"$ref": "https://example.com/types/money.json"}}}}}"#,
+        );
+        write(
+            "types/money.json",
+            r#"{"$id": "https://example.com/types/money.json", "type": "integer"}"#,
+        );
+        write(
+            "types/unused.json",
+            r#"{"$id": "https://example.com/types/unused.json", "type": "string"}"#,
+        );
+
+        let orphans = find_orphan_schemas(dir.path());
+        assert_eq!(orphans, [dir.path().join("types/unused.json")]);
+
+        let result = detect_orphans(lint(dir.path(), false), dir.path(), false);
+        assert_eq!(result.files_checked, 4);
+        assert_eq!(result.warnings, 1);
+        assert!(result.is_ok());
+        let unused = result
+            .results
+            .iter()
+            .find(|r| r.file == Path::new("types/unused.json"))
+            .unwrap();
+        assert_eq!(unused.status, FileStatus::Warning);
+        assert_eq!(unused.diagnostics[0].code, "UCP240");
+
+        // Strict lint fails on the orphan warning
+        let result = detect_orphans(lint(dir.path(), true), dir.path(), true);
+        assert_eq!(result.failed, 1);
+    }
+
     #[test]
     fn lint_strict_mode() {
         let dir = tempdir().unwrap();
@@ -2,18 +2,176 @@
 //!
 //! Handles loading schemas from files, strings, and HTTP URLs.
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+use serde::Serialize;
 use serde_json::Value;
 
 use crate::error::ResolveError;
+use crate::lock::{FetchRecorder, Lockfile};
 
 #[cfg(feature = "remote")]
 use std::time::Duration;
 
 /// Default timeout for HTTP requests (10 seconds).
 #[cfg(feature = "remote")]
-const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+pub(crate) const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Environment variable the CLI reads to disable network access, when set
+/// to `1` or `true`.
+pub const NO_NETWORK_ENV: &str = "UCP_SCHEMA_NO_NETWORK";
+
+/// How remote documents are fetched.
+///
+/// Every function that may fetch takes these explicitly (composition reads
+/// them from [`SchemaBaseConfig::fetch`](crate::SchemaBaseConfig)), so callers
+/// in one process can fetch under different settings. The default fetches
+/// freely.
+#[derive(Debug, Clone, Default)]
+pub struct FetchOptions {
+    /// Fail every remote fetch with `ResolveError::NetworkDisabled` before
+    /// any connection is attempted.
+    pub network_disabled: bool,
+    /// Lockfile every fetched body is checked against.
+    pub lockfile: Option<Arc<Lockfile>>,
+    /// Records every fetched body, to write a lockfile from.
+    pub recorder: Option<FetchRecorder>,
+    /// Directory fetched bodies are cached in (no caching with `None`).
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl FetchOptions {
+    /// Options that fetch freely.
+    pub const fn new() -> Self {
+        Self {
+            network_disabled: false,
+            lockfile: None,
+            recorder: None,
+            cache_dir: None,
+        }
+    }
+
+    /// Disable (or re-enable) network access.
+    pub fn network_disabled(mut self, disabled: bool) -> Self {
+        self.network_disabled = disabled;
+        self
+    }
+
+    /// Check every fetched body against `lockfile`.
+    pub fn lockfile(mut self, lockfile: Lockfile) -> Self {
+        self.lockfile = Some(Arc::new(lockfile));
+        self
+    }
+
+    /// Record every fetched body in `recorder`.
+    pub fn recorder(mut self, recorder: FetchRecorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Cache fetched bodies in `dir`, and serve them from there.
+    pub fn cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+}
+
+/// Document format for schema and payload input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InputFormat {
+    /// Guess from the file extension (`.json`, `.yaml`, `.yml`), falling
+    /// back to content: JSON if it starts with `{` or `[`, YAML otherwise.
+    #[default]
+    Auto,
+    /// Parse strictly as JSON.
+    Json,
+    /// Parse as YAML.
+    Yaml,
+}
+
+impl InputFormat {
+    /// Parse a format name ("json", "yaml" or "auto").
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "auto" => Some(InputFormat::Auto),
+            "json" => Some(InputFormat::Json),
+            "yaml" => Some(InputFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Settle `Auto` for a document named `name` (path or URL) with `content`.
+    fn detect(self, name: &str, content: &str) -> Self {
+        if self != InputFormat::Auto {
+            return self;
+        }
+        let path = name.split(['?', '#']).next().unwrap_or(name);
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("json") => InputFormat::Json,
+            Some("yaml" | "yml") => InputFormat::Yaml,
+            _ if content.trim_start().starts_with(['{', '[']) => InputFormat::Json,
+            _ => InputFormat::Yaml,
+        }
+    }
+}
+
+/// Load a schema or payload from a file path, URL, or `-` for stdin.
+///
+/// Unlike [`load_schema_auto`], the document may be YAML: `format` forces
+/// the parser, or [`InputFormat::Auto`] picks one from the name and content.
+///
+/// # Errors
+///
+/// Returns `ResolveError::InvalidJson` or `ResolveError::InvalidYaml` for the
+/// format that was attempted, or an IO error if the source can't be read.
+pub fn load_input(
+    source: &str,
+    format: InputFormat,
+    fetch: &FetchOptions,
+) -> Result<Value, ResolveError> {
+    let content = if source == "-" {
+        let mut content = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut content).map_err(|source| {
+            ResolveError::ReadError {
+                path: "<stdin>".into(),
+                source,
+            }
+        })?;
+        content
+    } else if is_url(source) {
+        fetch_text(source, fetch)?
+    } else {
+        let path = Path::new(source);
+        if !path.exists() {
+            return Err(ResolveError::FileNotFound {
+                path: path.to_path_buf(),
+            });
+        }
+        std::fs::read_to_string(path).map_err(|source| ResolveError::ReadError {
+            path: path.to_path_buf(),
+            source,
+        })?
+    };
+    load_schema_str_as(&content, format.detect(source, &content))
+}
+
+/// Load a schema from a JSON or YAML string.
+///
+/// `InputFormat::Auto` goes by content alone (see [`InputFormat::Auto`]).
+///
+/// # Errors
+///
+/// Returns `ResolveError::InvalidJson` or `ResolveError::InvalidYaml` for the
+/// format that was attempted.
+pub fn load_schema_str_as(content: &str, format: InputFormat) -> Result<Value, ResolveError> {
+    match format.detect("", content) {
+        InputFormat::Yaml => {
+            serde_yaml::from_str(content).map_err(|source| ResolveError::InvalidYaml { source })
+        }
+        _ => load_schema_str(content),
+    }
+}
 
 /// Load a schema from a file path.
 ///
@@ -51,10 +209,25 @@ pub fn load_schema_str(content: &str) -> Result<Value, ResolveError> {
 ///
 /// # Errors
 ///
-/// Returns `ResolveError::NetworkError` if the request fails,
+/// Returns `ResolveError::NetworkDisabled` if network access is disabled,
+/// `ResolveError::NetworkError` if the request fails,
+/// `ResolveError::NotLocked` or `ResolveError::LockMismatch` if the body
+/// doesn't match the lockfile in `fetch`,
 /// or `ResolveError::InvalidJson` if the response isn't valid JSON.
 #[cfg(feature = "remote")]
-pub fn load_schema_url(url: &str) -> Result<Value, ResolveError> {
+pub fn load_schema_url(url: &str, fetch: &FetchOptions) -> Result<Value, ResolveError> {
+    load_schema_str(&fetch_text(url, fetch)?)
+}
+
+/// Send a GET request, failing on transport and HTTP errors.
+#[cfg(feature = "remote")]
+fn get_url(url: &str, fetch: &FetchOptions) -> Result<reqwest::blocking::Response, ResolveError> {
+    if fetch.network_disabled {
+        return Err(ResolveError::NetworkDisabled {
+            url: url.to_string(),
+        });
+    }
+
     let client = reqwest::blocking::Client::builder()
         .timeout(HTTP_TIMEOUT)
         .build()
@@ -72,19 +245,44 @@ pub fn load_schema_url(url: &str) -> Result<Value, ResolveError> {
         })?;
 
     // Check for HTTP errors before parsing
-    let response = response
+    response
         .error_for_status()
         .map_err(|source| ResolveError::NetworkError {
             url: url.to_string(),
             source,
-        })?;
+        })
+}
 
-    response
-        .json()
-        .map_err(|source| ResolveError::NetworkError {
-            url: url.to_string(),
-            source,
+/// Fetch a URL's body as text (for parsing as JSON or YAML), checking it
+/// against the lockfile in `fetch`. A body in `fetch`'s cache directory is
+/// served from there, even while network access is disabled.
+fn fetch_text(url: &str, fetch: &FetchOptions) -> Result<String, ResolveError> {
+    #[cfg(feature = "remote")]
+    {
+        let cache_dir = fetch.cache_dir.as_deref();
+        if let Some(body) = cache_dir.and_then(|dir| crate::cache::cached_body(dir, url)) {
+            crate::lock::check_fetch(url, &body, fetch)?;
+            return Ok(body);
+        }
+        let body = get_url(url, fetch)?
+            .text()
+            .map_err(|source| ResolveError::NetworkError {
+                url: url.to_string(),
+                source,
+            })?;
+        crate::lock::check_fetch(url, &body, fetch)?;
+        if let Some(dir) = cache_dir {
+            crate::cache::store(dir, url, &body);
+        }
+        Ok(body)
+    }
+    #[cfg(not(feature = "remote"))]
+    {
+        let _ = fetch;
+        Err(ResolveError::FileNotFound {
+            path: std::path::PathBuf::from(url),
         })
+    }
 }
 
 /// Check if a string looks like a URL (starts with http:// or https://).
@@ -128,13 +326,37 @@ pub fn navigate_fragment(schema: &Value, fragment: &str) -> Result<Value, Resolv
 pub fn bundle_refs(schema: &mut Value, base_dir: &Path) -> Result<(), ResolveError> {
     // Snapshot root schema so internal #/$defs/ refs can resolve against it.
     let root_snapshot = schema.clone();
+    let mut ctx = BundleContext::default();
     bundle_refs_inner(
         schema,
         base_dir,
         Some(&root_snapshot),
-        None,
-        None,
-        &mut std::collections::HashSet::new(),
+        base_dir,
+        "",
+        &mut ctx,
+    )
+}
+
+/// Like `bundle_refs`, but fails on a `$ref` to a file outside `root`
+/// (after following symlinks), so a schema can't pull in arbitrary files.
+/// `root` must already be canonical.
+pub(crate) fn bundle_refs_within(
+    schema: &mut Value,
+    base_dir: &Path,
+    root: &Path,
+) -> Result<(), ResolveError> {
+    let root_snapshot = schema.clone();
+    let mut ctx = BundleContext {
+        confine_to: Some(root),
+        ..Default::default()
+    };
+    bundle_refs_inner(
+        schema,
+        base_dir,
+        Some(&root_snapshot),
+        base_dir,
+        "",
+        &mut ctx,
     )
 }
 
@@ -157,23 +379,110 @@ pub fn bundle_refs_with_url_mapping(
     remote_base: &str,
 ) -> Result<(), ResolveError> {
     let root_snapshot = schema.clone();
+    let mut ctx = BundleContext {
+        url_local_base: Some(local_base),
+        url_remote_base: Some(remote_base),
+        ..Default::default()
+    };
     bundle_refs_inner(
         schema,
         base_dir,
         Some(&root_snapshot),
-        Some(local_base),
-        Some(remote_base),
-        &mut std::collections::HashSet::new(),
+        base_dir,
+        "",
+        &mut ctx,
     )
 }
 
+/// Where an inlined region of a bundled schema came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SourceMapEntry {
+    /// JSON Pointer of the inlined region in the bundled output.
+    pub pointer: String,
+    /// File the region was loaded from.
+    pub source: String,
+    /// JSON Pointer within `source` ("" for the whole file).
+    pub source_pointer: String,
+}
+
+/// Like `bundle_refs`, additionally recording where each inlined `$ref` came from.
+///
+/// `source` is the path of the file `schema` was loaded from; relative refs
+/// resolve against its directory. Pass `url_mapping` as `(local_base, remote_base)`
+/// to map absolute URL refs as in `bundle_refs_with_url_mapping`.
+///
+/// Returns one entry per inlined ref, in document order. Nested inlines each
+/// get their own entry, so the innermost entry enclosing a pointer is its origin.
+pub fn bundle_refs_with_source_map(
+    schema: &mut Value,
+    source: &Path,
+    url_mapping: Option<(&Path, &str)>,
+) -> Result<Vec<SourceMapEntry>, ResolveError> {
+    bundle_refs_with_source_map_within(schema, source, url_mapping, None)
+}
+
+/// `bundle_refs_with_source_map`, failing on a `$ref` to a file outside
+/// `confine_to` as in `bundle_refs_within` when one is given.
+pub(crate) fn bundle_refs_with_source_map_within(
+    schema: &mut Value,
+    source: &Path,
+    url_mapping: Option<(&Path, &str)>,
+    confine_to: Option<&Path>,
+) -> Result<Vec<SourceMapEntry>, ResolveError> {
+    let root_snapshot = schema.clone();
+    let base_dir = source.parent().unwrap_or(Path::new("."));
+    let mut ctx = BundleContext {
+        url_local_base: url_mapping.map(|(local, _)| local),
+        url_remote_base: url_mapping.map(|(_, remote)| remote),
+        confine_to,
+        source_map: Some(Vec::new()),
+        ..Default::default()
+    };
+    bundle_refs_inner(schema, base_dir, Some(&root_snapshot), source, "", &mut ctx)?;
+    Ok(ctx.source_map.unwrap_or_default())
+}
+
+/// State shared across one local bundling pass.
+#[derive(Default)]
+struct BundleContext<'a> {
+    url_local_base: Option<&'a Path>,
+    url_remote_base: Option<&'a str>,
+    /// Directory every referenced file must resolve under, if confined.
+    confine_to: Option<&'a Path>,
+    visited: std::collections::HashSet<String>,
+    /// Number of external files being inlined around the current value
+    /// (0 while in the root schema).
+    external_depth: usize,
+    /// Provenance of inlined refs; only collected when requested.
+    source_map: Option<Vec<SourceMapEntry>>,
+}
+
+impl BundleContext<'_> {
+    fn record(&mut self, pointer: &str, source: &Path, fragment: Option<&str>) {
+        if let Some(map) = self.source_map.as_mut() {
+            map.push(SourceMapEntry {
+                pointer: pointer.to_string(),
+                source: source.display().to_string(),
+                source_pointer: fragment.unwrap_or("").trim_start_matches('#').to_string(),
+            });
+        }
+    }
+}
+
+/// Escape a key for use as a JSON Pointer reference token (RFC 6901).
+pub(crate) fn escape_pointer_token(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// `source` is the file `file_root` came from and `pointer` is this value's
+/// location in the bundled output; both only feed the optional source map.
 fn bundle_refs_inner(
     schema: &mut Value,
     base_dir: &Path,
     file_root: Option<&Value>, // Root of external file for resolving internal refs
-    url_local_base: Option<&Path>,
-    url_remote_base: Option<&str>,
-    visited: &mut std::collections::HashSet<String>,
+    source: &Path,
+    pointer: &str,
+    ctx: &mut BundleContext,
 ) -> Result<(), ResolveError> {
     match schema {
         Value::Object(obj) => {
@@ -185,16 +494,23 @@ fn bundle_refs_inner(
                     if ref_val == "#" {
                         // Leave as-is - can't inline recursive self-reference
                     } else if let Some(root) = file_root {
+                        let visit_key = format!("{}|{}", source.display(), ref_val);
+                        if ctx.visited.contains(&visit_key) {
+                            // A recursive root definition stays a ref; the root
+                            // keeps its $defs for the validator to follow
+                            if ctx.external_depth == 0 {
+                                return Ok(());
+                            }
+                            return Err(ResolveError::BundleError {
+                                message: format!("circular reference detected: {}", ref_val),
+                            });
+                        }
                         let mut target = navigate_fragment(root, ref_val)?;
+                        ctx.record(pointer, source, Some(ref_val));
                         // Recursively process (may have nested refs)
-                        bundle_refs_inner(
-                            &mut target,
-                            base_dir,
-                            file_root,
-                            url_local_base,
-                            url_remote_base,
-                            visited,
-                        )?;
+                        ctx.visited.insert(visit_key.clone());
+                        bundle_refs_inner(&mut target, base_dir, file_root, source, pointer, ctx)?;
+                        ctx.visited.remove(&visit_key);
                         // Inline the resolved definition
                         obj.remove("$ref");
                         if let Value::Object(ref_obj) = target {
@@ -213,13 +529,28 @@ fn bundle_refs_inner(
                     };
 
                     // Resolve ref to local path, handling URL mapping if configured
-                    let ref_path =
-                        resolve_ref_to_path(file_part, base_dir, url_local_base, url_remote_base);
-
+                    let ref_path = resolve_ref_to_path(
+                        file_part,
+                        base_dir,
+                        ctx.url_local_base,
+                        ctx.url_remote_base,
+                    );
+
+                    if let (Some(root), Ok(real)) = (ctx.confine_to, ref_path.canonicalize()) {
+                        if !real.starts_with(root) {
+                            return Err(ResolveError::BundleError {
+                                message: format!(
+                                    "$ref {} resolves outside {}",
+                                    ref_val,
+                                    root.display()
+                                ),
+                            });
+                        }
+                    }
                     let canonical = ref_path.canonicalize().unwrap_or(ref_path.clone());
                     let visit_key = format!("{}|{}", canonical.display(), fragment.unwrap_or(""));
 
-                    if visited.contains(&visit_key) {
+                    if ctx.visited.contains(&visit_key) {
                         return Err(ResolveError::BundleError {
                             message: format!("circular reference detected: {}", ref_val),
                         });
@@ -232,19 +563,22 @@ fn bundle_refs_inner(
                     } else {
                         loaded.clone()
                     };
+                    ctx.record(pointer, &ref_path, fragment);
 
-                    visited.insert(visit_key.clone());
+                    ctx.visited.insert(visit_key.clone());
+                    ctx.external_depth += 1;
                     let ref_dir = ref_path.parent().unwrap_or(base_dir);
                     // Pass loaded file as file_root so internal refs resolve against it
                     bundle_refs_inner(
                         &mut target,
                         ref_dir,
                         Some(&loaded),
-                        url_local_base,
-                        url_remote_base,
-                        visited,
+                        &ref_path,
+                        pointer,
+                        ctx,
                     )?;
-                    visited.remove(&visit_key);
+                    ctx.external_depth -= 1;
+                    ctx.visited.remove(&visit_key);
 
                     obj.remove("$ref");
                     if let Value::Object(ref_obj) = target {
@@ -257,27 +591,15 @@ fn bundle_refs_inner(
             }
 
             // Recurse into all values
-            for value in obj.values_mut() {
-                bundle_refs_inner(
-                    value,
-                    base_dir,
-                    file_root,
-                    url_local_base,
-                    url_remote_base,
-                    visited,
-                )?;
+            for (key, value) in obj.iter_mut() {
+                let child = format!("{}/{}", pointer, escape_pointer_token(key));
+                bundle_refs_inner(value, base_dir, file_root, source, &child, ctx)?;
             }
         }
         Value::Array(arr) => {
-            for item in arr {
-                bundle_refs_inner(
-                    item,
-                    base_dir,
-                    file_root,
-                    url_local_base,
-                    url_remote_base,
-                    visited,
-                )?;
+            for (i, item) in arr.iter_mut().enumerate() {
+                let child = format!("{}/{}", pointer, i);
+                bundle_refs_inner(item, base_dir, file_root, source, &child, ctx)?;
             }
         }
         _ => {}
@@ -308,6 +630,173 @@ fn resolve_ref_to_path(
     base_dir.join(ref_val)
 }
 
+/// Bundle external $ref pointers into the root's `$defs` instead of inlining them.
+///
+/// Each external file is added once, as `$defs/<name>` where `name` is the
+/// file stem (suffixed `_2`, `_3`, ... when taken), and every `$ref` to it
+/// becomes an internal pointer (`types/buyer.json#/$defs/addr` ->
+/// `#/$defs/buyer/$defs/addr`). Shared types aren't duplicated, and
+/// recursive or mutually recursive files bundle without error. Refs back to
+/// the root file become `#` pointers.
+///
+/// Embedded files lose their `$id` and `$schema`, so their internal refs
+/// resolve against the bundled root. `source` and `url_mapping` are as in
+/// `bundle_refs_with_source_map`.
+///
+/// Returns one entry per added definition, recording the file it came from.
+pub fn bundle_refs_as_defs(
+    schema: &mut Value,
+    source: &Path,
+    url_mapping: Option<(&Path, &str)>,
+) -> Result<Vec<SourceMapEntry>, ResolveError> {
+    let Value::Object(root) = schema else {
+        return Err(ResolveError::BundleError {
+            message: "schema root must be an object to hold $defs".to_string(),
+        });
+    };
+    let existing = match root.get("$defs") {
+        None => Vec::new(),
+        Some(Value::Object(defs)) => defs.keys().cloned().collect(),
+        Some(_) => {
+            return Err(ResolveError::BundleError {
+                message: "$defs must be an object".to_string(),
+            })
+        }
+    };
+
+    let mut ctx = DefsContext {
+        url_local_base: url_mapping.map(|(local, _)| local),
+        url_remote_base: url_mapping.map(|(_, remote)| remote),
+        root: source.canonicalize().unwrap_or(source.to_path_buf()),
+        names: std::collections::HashMap::new(),
+        taken: existing.into_iter().collect(),
+        files: Vec::new(),
+    };
+    let base_dir = source.parent().unwrap_or(Path::new("."));
+    rewrite_refs_as_defs(schema, base_dir, None, &mut ctx);
+
+    // Files discovered while rewriting are queued behind the current one
+    let mut defs = Vec::new();
+    let mut i = 0;
+    while let Some((path, name)) = ctx.files.get(i).cloned() {
+        let mut loaded = load_schema(&path)?;
+        if let Value::Object(obj) = &mut loaded {
+            obj.remove("$id");
+            obj.remove("$schema");
+        }
+        let dir = path.parent().unwrap_or(Path::new("."));
+        rewrite_refs_as_defs(&mut loaded, dir, Some(&name), &mut ctx);
+        defs.push((name, path, loaded));
+        i += 1;
+    }
+
+    let mut entries = Vec::new();
+    if !defs.is_empty() {
+        let target = schema
+            .as_object_mut()
+            .and_then(|root| {
+                root.entry("$defs")
+                    .or_insert_with(|| Value::Object(Default::default()))
+                    .as_object_mut()
+            })
+            .expect("root and $defs checked above");
+        for (name, path, def) in defs {
+            entries.push(SourceMapEntry {
+                pointer: format!("/$defs/{}", escape_pointer_token(&name)),
+                source: path.display().to_string(),
+                source_pointer: String::new(),
+            });
+            target.insert(name, def);
+        }
+    }
+    Ok(entries)
+}
+
+/// State shared across one `$defs` bundling pass.
+struct DefsContext<'a> {
+    url_local_base: Option<&'a Path>,
+    url_remote_base: Option<&'a str>,
+    /// The root file, whose refs become `#` pointers.
+    root: std::path::PathBuf,
+    /// Definition name by canonical file path.
+    names: std::collections::HashMap<std::path::PathBuf, String>,
+    taken: std::collections::HashSet<String>,
+    /// Files to embed, in discovery order, with the path they load from.
+    files: Vec<(std::path::PathBuf, String)>,
+}
+
+impl DefsContext<'_> {
+    /// Pointer prefix for a file's definition, registering it on first use.
+    fn prefix_for(&mut self, path: &Path) -> String {
+        let canonical = path.canonicalize().unwrap_or(path.to_path_buf());
+        if canonical == self.root {
+            return String::new();
+        }
+        if let Some(name) = self.names.get(&canonical) {
+            return format!("/$defs/{}", escape_pointer_token(name));
+        }
+
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "schema".to_string());
+        let mut name = stem.clone();
+        let mut n = 1;
+        while self.taken.contains(&name) {
+            n += 1;
+            name = format!("{}_{}", stem, n);
+        }
+        self.taken.insert(name.clone());
+        self.names.insert(canonical, name.clone());
+        self.files.push((path.to_path_buf(), name.clone()));
+        format!("/$defs/{}", escape_pointer_token(&name))
+    }
+}
+
+/// Rewrite every `$ref` in `schema` to an internal pointer. `def` is the
+/// definition `schema` is embedded as (`None` for the root), which its
+/// internal refs are re-rooted under.
+fn rewrite_refs_as_defs(
+    schema: &mut Value,
+    base_dir: &Path,
+    def: Option<&str>,
+    ctx: &mut DefsContext,
+) {
+    match schema {
+        Value::Object(obj) => {
+            if let Some(Value::String(ref_val)) = obj.get_mut("$ref") {
+                if let Some(fragment) = ref_val.strip_prefix('#') {
+                    if let Some(name) = def {
+                        *ref_val = format!("#/$defs/{}{}", escape_pointer_token(name), fragment);
+                    }
+                } else {
+                    let (file_part, fragment) = match ref_val.find('#') {
+                        Some(idx) => (&ref_val[..idx], &ref_val[idx + 1..]),
+                        None => (ref_val.as_str(), ""),
+                    };
+                    let path = resolve_ref_to_path(
+                        file_part,
+                        base_dir,
+                        ctx.url_local_base,
+                        ctx.url_remote_base,
+                    );
+                    let fragment = fragment.to_string();
+                    *ref_val = format!("#{}{}", ctx.prefix_for(&path), fragment);
+                }
+            }
+            for value in obj.values_mut() {
+                rewrite_refs_as_defs(value, base_dir, def, ctx);
+            }
+        }
+        Value::Array(arr) => {
+            for item in arr {
+                rewrite_refs_as_defs(item, base_dir, def, ctx);
+            }
+        }
+        _ => {}
+    }
+}
+
 /// Bundle external $ref pointers by fetching from remote URLs.
 ///
 /// Like `bundle_refs`, but fetches external refs via HTTP instead of local files.
@@ -318,13 +807,18 @@ fn resolve_ref_to_path(
 /// * `schema` - The schema to process (modified in place)
 /// * `base_url` - Base URL for resolving relative refs (typically the schema's $id)
 #[cfg(feature = "remote")]
-pub fn bundle_refs_remote(schema: &mut Value, base_url: &str) -> Result<(), ResolveError> {
+pub fn bundle_refs_remote(
+    schema: &mut Value,
+    base_url: &str,
+    fetch: &FetchOptions,
+) -> Result<(), ResolveError> {
     // Snapshot root schema so internal #/$defs/ refs can resolve against it.
     let root_snapshot = schema.clone();
     bundle_refs_remote_inner(
         schema,
         base_url,
         Some(&root_snapshot),
+        fetch,
         &mut std::collections::HashSet::new(),
     )
 }
@@ -334,6 +828,7 @@ fn bundle_refs_remote_inner(
     schema: &mut Value,
     base_url: &str,
     file_root: Option<&Value>,
+    fetch: &FetchOptions,
     visited: &mut std::collections::HashSet<String>,
 ) -> Result<(), ResolveError> {
     match schema {
@@ -345,7 +840,7 @@ fn bundle_refs_remote_inner(
                         // Self-reference, leave as-is
                     } else if let Some(root) = file_root {
                         let mut target = navigate_fragment(root, ref_val)?;
-                        bundle_refs_remote_inner(&mut target, base_url, file_root, visited)?;
+                        bundle_refs_remote_inner(&mut target, base_url, file_root, fetch, visited)?;
                         obj.remove("$ref");
                         if let Value::Object(ref_obj) = target {
                             for (k, v) in ref_obj {
@@ -373,7 +868,7 @@ fn bundle_refs_remote_inner(
                     }
 
                     // Fetch the referenced schema
-                    let loaded = load_schema_url(&resolved_url)?;
+                    let loaded = load_schema_url(&resolved_url, fetch)?;
                     let mut target = if let Some(frag) = fragment {
                         navigate_fragment(&loaded, frag)?
                     } else {
@@ -382,7 +877,13 @@ fn bundle_refs_remote_inner(
 
                     visited.insert(visit_key.clone());
                     // Recursively bundle with new base URL
-                    bundle_refs_remote_inner(&mut target, &resolved_url, Some(&loaded), visited)?;
+                    bundle_refs_remote_inner(
+                        &mut target,
+                        &resolved_url,
+                        Some(&loaded),
+                        fetch,
+                        visited,
+                    )?;
                     visited.remove(&visit_key);
 
                     obj.remove("$ref");
@@ -397,12 +898,12 @@ fn bundle_refs_remote_inner(
 
             // Recurse into all values
             for value in obj.values_mut() {
-                bundle_refs_remote_inner(value, base_url, file_root, visited)?;
+                bundle_refs_remote_inner(value, base_url, file_root, fetch, visited)?;
             }
         }
         Value::Array(arr) => {
             for item in arr {
-                bundle_refs_remote_inner(item, base_url, file_root, visited)?;
+                bundle_refs_remote_inner(item, base_url, file_root, fetch, visited)?;
             }
         }
         _ => {}
@@ -411,8 +912,7 @@ fn bundle_refs_remote_inner(
 }
 
 /// Resolve a potentially relative URL against a base URL.
-#[cfg(feature = "remote")]
-fn resolve_url(url: &str, base: &str) -> String {
+pub(crate) fn resolve_url(url: &str, base: &str) -> String {
     if is_url(url) {
         // Already absolute
         url.to_string()
@@ -435,14 +935,15 @@ fn resolve_url(url: &str, base: &str) -> String {
 /// # Errors
 ///
 /// Returns appropriate errors based on the source type.
-pub fn load_schema_auto(source: &str) -> Result<Value, ResolveError> {
+pub fn load_schema_auto(source: &str, fetch: &FetchOptions) -> Result<Value, ResolveError> {
     if is_url(source) {
         #[cfg(feature = "remote")]
         {
-            load_schema_url(source)
+            load_schema_url(source, fetch)
         }
         #[cfg(not(feature = "remote"))]
         {
+            let _ = fetch;
             Err(ResolveError::FileNotFound {
                 path: std::path::PathBuf::from(source),
             })
@@ -494,6 +995,37 @@ mod tests {
         assert!(matches!(result, Err(ResolveError::InvalidJson { .. })));
     }
 
+    #[test]
+    fn load_schema_str_as_formats() {
+        let yaml = "type: object\nrequired: [id]\n";
+        let schema = load_schema_str_as(yaml, InputFormat::Auto).unwrap();
+        assert_eq!(schema["required"][0], "id");
+        assert!(matches!(
+            load_schema_str_as(yaml, InputFormat::Json),
+            Err(ResolveError::InvalidJson { .. })
+        ));
+        assert!(matches!(
+            load_schema_str_as("{\"a\": [1,", InputFormat::Yaml),
+            Err(ResolveError::InvalidYaml { .. })
+        ));
+    }
+
+    #[test]
+    fn input_format_detect() {
+        let json = r#"{"type": "object"}"#;
+        assert_eq!(InputFormat::Auto.detect("a.yaml", json), InputFormat::Yaml);
+        assert_eq!(
+            InputFormat::Auto.detect("https://x.dev/a.yml?v=1", json),
+            InputFormat::Yaml
+        );
+        assert_eq!(InputFormat::Auto.detect("a.txt", json), InputFormat::Json);
+        assert_eq!(
+            InputFormat::Auto.detect("-", "type: object"),
+            InputFormat::Yaml
+        );
+        assert_eq!(InputFormat::Json.detect("a.yaml", ""), InputFormat::Json);
+    }
+
     #[test]
     fn is_url_https() {
         assert!(is_url("https://example.com/schema.json"));
@@ -516,7 +1048,7 @@ mod tests {
         let mut file = NamedTempFile::new().unwrap();
         writeln!(file, r#"{{"type": "string"}}"#).unwrap();
 
-        let schema = load_schema_auto(file.path().to_str().unwrap()).unwrap();
+        let schema = load_schema_auto(file.path().to_str().unwrap(), &FetchOptions::new()).unwrap();
         assert_eq!(schema["type"], "string");
     }
 
@@ -580,15 +1112,124 @@ mod tests {
         assert_eq!(path, Path::new("/local/schemas/foo.json"));
     }
 
+    #[test]
+    fn bundle_source_map_records_nested_inlines() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("buyer.json"),
+            r##"{"type":"object","properties":{"addr":{"$ref":"#/$defs/addr"}},
+                "$defs":{"addr":{"type":"string"}}}"##,
+        )
+        .unwrap();
+        let root = dir.path().join("root.json");
+        let mut schema = serde_json::json!({
+            "properties": { "a/b": { "$ref": "buyer.json" } }
+        });
+
+        let entries = bundle_refs_with_source_map(&mut schema, &root, None).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].pointer, "/properties/a~1b");
+        assert!(entries[0].source.ends_with("buyer.json"));
+        assert_eq!(entries[0].source_pointer, "");
+        assert_eq!(entries[1].pointer, "/properties/a~1b/properties/addr");
+        assert!(entries[1].source.ends_with("buyer.json"));
+        assert_eq!(entries[1].source_pointer, "/$defs/addr");
+        assert_eq!(
+            schema["properties"]["a/b"]["properties"]["addr"]["type"],
+            "string"
+        );
+    }
+
+    #[test]
+    fn bundle_keeps_recursive_root_refs() {
+        let mut schema = serde_json::json!({
+            "properties": { "tree": { "$ref": "#/$defs/node" } },
+            "$defs": { "node": { "properties": { "children": { "items": { "$ref": "#/$defs/node" } } } } }
+        });
+
+        bundle_refs(&mut schema, Path::new(".")).unwrap();
+
+        let tree = &schema["properties"]["tree"];
+        assert!(tree.get("$ref").is_none());
+        assert_eq!(
+            tree["properties"]["children"]["items"]["$ref"],
+            "#/$defs/node"
+        );
+    }
+
+    #[test]
+    fn bundle_as_defs_shares_types_and_keeps_cycles() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("types")).unwrap();
+        std::fs::write(
+            dir.path().join("types/buyer.json"),
+            r##"{"$id":"https://ucp.dev/buyer.json","type":"object",
+                "properties":{"addr":{"$ref":"#/$defs/addr"},"org":{"$ref":"org.json"}},
+                "$defs":{"addr":{"type":"string"}}}"##,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("types/org.json"),
+            r#"{"properties":{"owner":{"$ref":"buyer.json"},"root":{"$ref":"../root.json"}}}"#,
+        )
+        .unwrap();
+        let root = dir.path().join("root.json");
+        std::fs::write(&root, "{}").unwrap();
+        let mut schema = serde_json::json!({
+            "properties": {
+                "buyer": { "$ref": "types/buyer.json" },
+                "addr": { "$ref": "types/buyer.json#/$defs/addr" }
+            },
+            "$defs": { "buyer": { "type": "string" } }
+        });
+
+        let entries = bundle_refs_as_defs(&mut schema, &root, None).unwrap();
+
+        let pointers: Vec<&str> = entries.iter().map(|e| e.pointer.as_str()).collect();
+        assert_eq!(pointers, ["/$defs/buyer_2", "/$defs/org"]);
+        assert_eq!(schema["properties"]["buyer"]["$ref"], "#/$defs/buyer_2");
+        assert_eq!(
+            schema["properties"]["addr"]["$ref"],
+            "#/$defs/buyer_2/$defs/addr"
+        );
+        let buyer = &schema["$defs"]["buyer_2"];
+        assert!(buyer.get("$id").is_none());
+        assert_eq!(
+            buyer["properties"]["addr"]["$ref"],
+            "#/$defs/buyer_2/$defs/addr"
+        );
+        assert_eq!(buyer["properties"]["org"]["$ref"], "#/$defs/org");
+        let org = &schema["$defs"]["org"]["properties"];
+        assert_eq!(org["owner"]["$ref"], "#/$defs/buyer_2");
+        assert_eq!(org["root"]["$ref"], "#");
+        assert_eq!(schema["$defs"]["buyer"]["type"], "string");
+    }
+
     // Remote tests - require network, use httpbin.org for reliable testing
     #[cfg(feature = "remote")]
     mod remote {
         use super::*;
 
+        #[test]
+        fn network_disabled_applies_per_call() {
+            let url = "http://127.0.0.1:1/schema.json";
+            let offline = FetchOptions::new().network_disabled(true);
+            assert!(matches!(
+                load_schema_url(url, &offline),
+                Err(ResolveError::NetworkDisabled { .. })
+            ));
+            // Other callers in the process still reach the network
+            assert!(matches!(
+                load_schema_url(url, &FetchOptions::new()),
+                Err(ResolveError::NetworkError { .. })
+            ));
+        }
+
         #[test]
         fn load_schema_url_valid() {
             // httpbin.org/json returns a well-known JSON response
-            let result = load_schema_url("https://httpbin.org/json");
+            let result = load_schema_url("https://httpbin.org/json", &FetchOptions::new());
             assert!(result.is_ok());
             let schema = result.unwrap();
             // httpbin returns {"slideshow": {...}}
@@ -597,20 +1238,22 @@ mod tests {
 
         #[test]
         fn load_schema_url_404() {
-            let result = load_schema_url("https://httpbin.org/status/404");
+            let result = load_schema_url("https://httpbin.org/status/404", &FetchOptions::new());
             assert!(matches!(result, Err(ResolveError::NetworkError { .. })));
         }
 
         #[test]
         fn load_schema_url_invalid_host() {
-            let result =
-                load_schema_url("https://this-domain-does-not-exist-12345.invalid/schema.json");
+            let result = load_schema_url(
+                "https://this-domain-does-not-exist-12345.invalid/schema.json",
+                &FetchOptions::new(),
+            );
             assert!(matches!(result, Err(ResolveError::NetworkError { .. })));
         }
 
         #[test]
         fn load_schema_auto_url() {
-            let result = load_schema_auto("https://httpbin.org/json");
+            let result = load_schema_auto("https://httpbin.org/json", &FetchOptions::new());
             assert!(result.is_ok());
         }
     }
@@ -0,0 +1,269 @@
+//! Lockfiles for remote schemas - pinning the content of every schema and
+//! profile fetched over HTTP.
+//!
+//! A lockfile (`ucp-schema.lock`) lists each fetched URL with the version
+//! the document declares and the SHA-256 of its body:
+//!
+//! ```json
+//! {
+//!   "lock_version": 1,
+//!   "schemas": [
+//!     {
+//!       "url": "https://ucp.dev/schemas/shopping/checkout.json",
+//!       "version": "2026-01-11",
+//!       "sha256": "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! While a lockfile is set on the [`FetchOptions`](crate::FetchOptions) a
+//! fetch runs with, the fetch is checked against it: a URL it doesn't list,
+//! or a body with a different hash, fails the fetch. A [`FetchRecorder`]
+//! collects what was fetched, to write a lockfile from.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::error::ResolveError;
+use crate::loader::FetchOptions;
+
+/// Default lockfile name.
+pub const LOCK_FILE: &str = "ucp-schema.lock";
+
+/// Lockfile format version written by this crate.
+pub const LOCK_VERSION: u32 = 1;
+
+/// One pinned remote document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedSchema {
+    pub url: String,
+    /// The document's `x-ucp-version`, `version` or `ucp.version`, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Lowercase hex SHA-256 of the response body.
+    pub sha256: String,
+}
+
+/// Pinned remote schemas and profiles, sorted by URL.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub lock_version: u32,
+    pub schemas: Vec<LockedSchema>,
+}
+
+impl Default for Lockfile {
+    fn default() -> Self {
+        Lockfile {
+            lock_version: LOCK_VERSION,
+            schemas: Vec::new(),
+        }
+    }
+}
+
+impl Lockfile {
+    /// Read a lockfile.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ResolveError::FileNotFound` or `ResolveError::ReadError` if
+    /// the file can't be read, and `ResolveError::InvalidLockfile` if it isn't
+    /// a lockfile of a supported version.
+    pub fn load(path: &Path) -> Result<Self, ResolveError> {
+        if !path.exists() {
+            return Err(ResolveError::FileNotFound {
+                path: path.to_path_buf(),
+            });
+        }
+        let content = std::fs::read_to_string(path).map_err(|source| ResolveError::ReadError {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let invalid = |message: String| ResolveError::InvalidLockfile {
+            path: path.to_path_buf(),
+            message,
+        };
+        let lockfile: Lockfile =
+            serde_json::from_str(&content).map_err(|e| invalid(e.to_string()))?;
+        if lockfile.lock_version != LOCK_VERSION {
+            return Err(invalid(format!(
+                "unsupported lock_version {} (expected {})",
+                lockfile.lock_version, LOCK_VERSION
+            )));
+        }
+        Ok(lockfile)
+    }
+
+    /// The lockfile as written to disk: pretty JSON with a trailing newline.
+    pub fn to_json_string(&self) -> String {
+        let mut out = serde_json::to_string_pretty(self).unwrap_or_default();
+        out.push('\n');
+        out
+    }
+
+    /// The entry pinning `url`, if any.
+    pub fn get(&self, url: &str) -> Option<&LockedSchema> {
+        self.schemas.iter().find(|s| s.url == url)
+    }
+}
+
+/// Records the documents fetched with the [`FetchOptions`] it's set on.
+///
+/// Clones share one record, so keep one to read the lockfile back after
+/// fetching.
+#[derive(Debug, Clone, Default)]
+pub struct FetchRecorder {
+    fetched: Arc<Mutex<BTreeMap<String, LockedSchema>>>,
+}
+
+impl FetchRecorder {
+    /// A recorder with nothing recorded.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A lockfile pinning every document recorded so far.
+    pub fn lockfile(&self) -> Lockfile {
+        let fetched = self.fetched.lock().unwrap_or_else(|e| e.into_inner());
+        Lockfile {
+            lock_version: LOCK_VERSION,
+            schemas: fetched.values().cloned().collect(),
+        }
+    }
+
+    fn record(&self, entry: LockedSchema) {
+        let mut fetched = self.fetched.lock().unwrap_or_else(|e| e.into_inner());
+        fetched.insert(entry.url.clone(), entry);
+    }
+}
+
+/// Record a fetched body and check it against the lockfile, as `fetch` asks.
+#[cfg_attr(not(feature = "remote"), allow(dead_code))]
+pub(crate) fn check_fetch(url: &str, body: &str, fetch: &FetchOptions) -> Result<(), ResolveError> {
+    let sha256 = sha256_hex(body.as_bytes());
+
+    if let Some(recorder) = &fetch.recorder {
+        let version = serde_json::from_str::<Value>(body)
+            .ok()
+            .and_then(|doc| document_version(&doc));
+        recorder.record(LockedSchema {
+            url: url.to_string(),
+            version,
+            sha256: sha256.clone(),
+        });
+    }
+
+    let Some(lockfile) = fetch.lockfile.as_deref() else {
+        return Ok(());
+    };
+    match lockfile.get(url) {
+        None => Err(ResolveError::NotLocked {
+            url: url.to_string(),
+        }),
+        Some(locked) if locked.sha256 != sha256 => Err(ResolveError::LockMismatch {
+            url: url.to_string(),
+            expected: locked.sha256.clone(),
+            actual: sha256,
+        }),
+        Some(_) => Ok(()),
+    }
+}
+
+/// The version a schema (`x-ucp-version`, `version`) or profile
+/// (`ucp.version`) declares.
+#[cfg_attr(not(feature = "remote"), allow(dead_code))]
+fn document_version(doc: &Value) -> Option<String> {
+    ["/x-ucp-version", "/version", "/ucp/version"]
+        .iter()
+        .find_map(|pointer| doc.pointer(pointer).and_then(Value::as_str))
+        .map(str::to_string)
+}
+
+/// Lowercase hex SHA-256 digest of `data`.
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sha256_matches_known_digests() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            sha256_hex(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn lockfile_and_recorder_apply_to_their_own_fetches() {
+        let url = "https://ucp.dev/a.json";
+        let body = r#"{"version": "2026-01-11"}"#;
+        let recorder = FetchRecorder::new();
+        let recording = FetchOptions::new().recorder(recorder.clone());
+        let locked = FetchOptions::new().lockfile(Lockfile::default());
+
+        check_fetch(url, body, &recording).unwrap();
+        assert!(matches!(
+            check_fetch(url, body, &locked),
+            Err(ResolveError::NotLocked { .. })
+        ));
+        check_fetch("https://ucp.dev/b.json", "{}", &FetchOptions::new()).unwrap();
+
+        let pinned = recorder.lockfile();
+        assert_eq!(pinned.schemas.len(), 1);
+        assert_eq!(pinned.schemas[0].version.as_deref(), Some("2026-01-11"));
+        let verified = FetchOptions::new().lockfile(pinned);
+        check_fetch(url, body, &verified).unwrap();
+        assert!(matches!(
+            check_fetch(url, "{}", &verified),
+            Err(ResolveError::LockMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn lockfile_loads_and_rejects_other_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(LOCK_FILE);
+        let lockfile = Lockfile {
+            lock_version: LOCK_VERSION,
+            schemas: vec![LockedSchema {
+                url: "https://ucp.dev/a.json".to_string(),
+                version: Some("2026-01-11".to_string()),
+                sha256: sha256_hex(b"{}"),
+            }],
+        };
+        std::fs::write(&path, lockfile.to_json_string()).unwrap();
+        assert_eq!(Lockfile::load(&path).unwrap(), lockfile);
+        assert_eq!(
+            document_version(&json!({ "ucp": { "version": "2026-01-11" } })).as_deref(),
+            Some("2026-01-11")
+        );
+
+        std::fs::write(
+            &path,
+            json!({ "lock_version": 9, "schemas": [] }).to_string(),
+        )
+        .unwrap();
+        let err = Lockfile::load(&path).unwrap_err();
+        assert!(err.to_string().contains("unsupported lock_version 9"));
+    }
+}
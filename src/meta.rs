@@ -0,0 +1,218 @@
+//! UCP meta-schema - a JSON Schema describing valid UCP annotations, so
+//! tools outside this crate can check authored schemas without
+//! reimplementing the annotation rules.
+//!
+//! The meta-schema only constrains annotations: it walks every subschema
+//! position (`properties`, `items`, `allOf`, `$defs`, ...) and checks the
+//! `ucp_*` keywords, `x-ucp-operations` and `x-ucp-operation-groups` it finds. Rules a JSON Schema
+//! can't state - such as a transition's `from` and `to` differing - are
+//! left to `lint`.
+
+use std::sync::OnceLock;
+
+use serde_json::{json, Value};
+
+use crate::error::SchemaError;
+use crate::validator::schema_errors;
+
+/// `$id` of the meta-schema.
+pub const META_SCHEMA_ID: &str = "urn:ucp-schema:meta-schema";
+
+/// The UCP annotation meta-schema (JSON Schema draft 2020-12).
+pub fn meta_schema() -> Value {
+    let schema_ref = json!({ "$ref": "#/$defs/schema" });
+    let schema_map = json!({ "additionalProperties": { "$ref": "#/$defs/schema" } });
+
+    let mut positions = serde_json::Map::new();
+    for key in [
+        "properties",
+        "patternProperties",
+        "$defs",
+        "definitions",
+        "dependentSchemas",
+    ] {
+        positions.insert(key.to_string(), schema_map.clone());
+    }
+    for key in [
+        "additionalProperties",
+        "unevaluatedProperties",
+        "propertyNames",
+        "items",
+        "prefixItems",
+        "additionalItems",
+        "unevaluatedItems",
+        "contains",
+        "allOf",
+        "anyOf",
+        "oneOf",
+        "not",
+        "if",
+        "then",
+        "else",
+    ] {
+        positions.insert(key.to_string(), schema_ref.clone());
+    }
+    positions.insert(
+        "ucp_request".to_string(),
+        json!({ "$ref": "#/$defs/annotation" }),
+    );
+    positions.insert(
+        "ucp_response".to_string(),
+        json!({ "$ref": "#/$defs/annotation" }),
+    );
+    positions.insert("ucp_enum".to_string(), json!({ "$ref": "#/$defs/enum" }));
+    positions.insert(
+        "ucp_description".to_string(),
+        json!({ "$ref": "#/$defs/description" }),
+    );
+    positions.insert(
+        "ucp_property_names".to_string(),
+        json!({ "$ref": "#/$defs/propertyNames" }),
+    );
+    positions.insert("ucp_sensitive".to_string(), json!({ "type": "boolean" }));
+    positions.insert(
+        "x-ucp-operations".to_string(),
+        json!({ "type": "array", "items": { "type": "string", "minLength": 1 }, "uniqueItems": true }),
+    );
+    positions.insert(
+        "x-ucp-operation-groups".to_string(),
+        json!({
+            "type": "object",
+            "additionalProperties": {
+                "type": "array",
+                "items": { "type": "string", "minLength": 1 },
+                "minItems": 1,
+                "uniqueItems": true
+            }
+        }),
+    );
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": META_SCHEMA_ID,
+        "title": "UCP annotated JSON Schema",
+        "$ref": "#/$defs/schema",
+        "$defs": {
+            "schema": {
+                "description": "A schema or subschema; arrays hold subschemas (allOf, tuple items).",
+                "properties": positions,
+                "items": { "$ref": "#/$defs/schema" }
+            },
+            "visibility": { "enum": ["omit", "required", "optional", "readonly", "writeonly"] },
+            "transitionVisibility": { "enum": ["omit", "required", "optional"] },
+            "annotation": {
+                "description": "ucp_request / ucp_response: a visibility for every operation, or an object keyed by operation.",
+                "type": ["string", "object"],
+                "if": { "type": "string" },
+                "then": { "$ref": "#/$defs/visibility" },
+                "else": {
+                    "properties": { "transition": { "$ref": "#/$defs/transition" } },
+                    "additionalProperties": { "$ref": "#/$defs/operationEntry" }
+                }
+            },
+            "operationEntry": {
+                "type": ["string", "object"],
+                "if": { "type": "string" },
+                "then": { "$ref": "#/$defs/visibility" },
+                "else": {
+                    "if": { "required": ["transition"] },
+                    "then": {
+                        "properties": { "transition": { "$ref": "#/$defs/transition" } }
+                    },
+                    "else": { "$ref": "#/$defs/conditional" }
+                }
+            },
+            "transition": {
+                "type": "object",
+                "required": ["from", "to", "description"],
+                "properties": {
+                    "from": { "$ref": "#/$defs/transitionVisibility" },
+                    "to": { "$ref": "#/$defs/transitionVisibility" },
+                    "description": { "type": "string", "minLength": 1 }
+                }
+            },
+            "conditional": {
+                "type": "object",
+                "required": ["when", "then"],
+                "properties": {
+                    "when": { "type": ["object", "boolean"] },
+                    "then": { "$ref": "#/$defs/visibility" },
+                    "else": { "$ref": "#/$defs/visibility" }
+                }
+            },
+            "enum": {
+                "description": "An array for every direction and operation, or an object keyed by direction and/or operation.",
+                "type": ["array", "object"],
+                "additionalProperties": {
+                    "type": ["array", "object"],
+                    "additionalProperties": { "type": "array" }
+                }
+            },
+            "description": {
+                "type": "object",
+                "additionalProperties": { "type": "string" }
+            },
+            "propertyNames": {
+                "type": "object",
+                "additionalProperties": {
+                    "type": "object",
+                    "properties": {
+                        "pattern": { "type": "string", "format": "regex" },
+                        "minLength": { "type": "integer", "minimum": 0 },
+                        "maxLength": { "type": "integer", "minimum": 0 },
+                        "enum": { "type": "array" }
+                    },
+                    "additionalProperties": false
+                }
+            }
+        }
+    })
+}
+
+/// Check `schema`'s annotations against the meta-schema.
+///
+/// Each error's `path` points into `schema`, e.g.
+/// `/properties/id/ucp_request/create`.
+pub fn check_meta_schema(schema: &Value) -> Vec<SchemaError> {
+    static VALIDATOR: OnceLock<jsonschema::Validator> = OnceLock::new();
+    let validator = VALIDATOR
+        .get_or_init(|| jsonschema::validator_for(&meta_schema()).expect("meta-schema compiles"));
+    schema_errors(validator, schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn meta_schema_flags_invalid_annotations_anywhere() {
+        let schema = json!({
+            "properties": {
+                "id": {
+                    "ucp_request": { "create": "omit", "transition": { "from": "required", "to": "omit", "description": "x" } },
+                    "ucp_response": { "read": { "when": { "required": ["x"] }, "then": "required" } },
+                    "ucp_enum": { "response": { "read": ["a"] } },
+                    "ucp_sensitive": true
+                },
+                "items": {
+                    "type": "array",
+                    "items": { "allOf": [{ "properties": { "x": { "ucp_request": "hidden" } } }] }
+                },
+                "tags": { "ucp_property_names": { "create": { "format": "email" } } },
+                "ucp_request": { "type": "string" }
+            },
+            "x-ucp-tests": [{ "ucp_request": 5 }]
+        });
+        let paths: Vec<String> = check_meta_schema(&schema)
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "/properties/items/items/allOf/0/properties/x/ucp_request",
+                "/properties/tags/ucp_property_names/create"
+            ]
+        );
+    }
+}
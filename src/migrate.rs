@@ -0,0 +1,228 @@
+//! Schema migration - apply schema transitions to produce the next version.
+//!
+//! A transition annotation (`{ "transition": { "from", "to", "description" } }`)
+//! resolves to its `from` value while the change is announced. Migrating
+//! replaces it with its `to` value, completing the change.
+
+use serde_json::Value;
+
+use crate::error::ResolveError;
+use crate::loader::escape_pointer_token;
+use crate::resolver::parse_transition_info;
+
+/// One transition replaced by its target visibility.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedTransition {
+    /// JSON Pointer to the rewritten annotation value
+    /// (e.g., `/properties/id/ucp_request/update`).
+    pub path: String,
+    /// Annotation value before migration.
+    pub before: Value,
+    /// Visibility now in place (the transition's `to`).
+    pub to: String,
+    /// The transition's description.
+    pub description: String,
+}
+
+/// Result of migrating a schema.
+#[derive(Debug, Clone)]
+pub struct Migrated {
+    /// Schema with every applicable transition completed.
+    pub schema: Value,
+    /// Transitions applied, in document order.
+    pub applied: Vec<AppliedTransition>,
+    /// Transitions left in place, with the reason.
+    pub skipped: Vec<String>,
+}
+
+/// Replace every schema transition in `schema` with its `to` visibility.
+///
+/// Per-operation transitions become the `to` string for that operation; a
+/// shorthand transition (`"ucp_request": { "transition": ... }`) becomes the
+/// shorthand `to` string. A shorthand transition next to per-operation
+/// entries is skipped: the operations it covers aren't listed, so the result
+/// can't be written without guessing them.
+///
+/// # Errors
+///
+/// Returns `InvalidSchemaTransition` for a transition the resolver would
+/// reject (missing description, or `from`/`to` not distinct visibilities).
+pub fn migrate_transitions(schema: &Value) -> Result<Migrated, ResolveError> {
+    let mut migrated = Migrated {
+        schema: schema.clone(),
+        applied: Vec::new(),
+        skipped: Vec::new(),
+    };
+    walk(
+        &mut migrated.schema,
+        "",
+        &mut migrated.applied,
+        &mut migrated.skipped,
+    )?;
+    Ok(migrated)
+}
+
+fn walk(
+    value: &mut Value,
+    path: &str,
+    applied: &mut Vec<AppliedTransition>,
+    skipped: &mut Vec<String>,
+) -> Result<(), ResolveError> {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                let child_path = format!("{}/{}", path, escape_pointer_token(key));
+                if key == "ucp_request" || key == "ucp_response" {
+                    migrate_annotation(child, &child_path, applied, skipped)?;
+                } else {
+                    walk(child, &child_path, applied, skipped)?;
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                walk(item, &format!("{}/{}", path, i), applied, skipped)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn migrate_annotation(
+    annotation: &mut Value,
+    path: &str,
+    applied: &mut Vec<AppliedTransition>,
+    skipped: &mut Vec<String>,
+) -> Result<(), ResolveError> {
+    let Value::Object(map) = annotation else {
+        return Ok(());
+    };
+
+    if let Some(Value::Object(shorthand)) = map.get("transition") {
+        if map.len() == 1 {
+            let info = parse_transition_info(shorthand, path)?;
+            applied.push(AppliedTransition {
+                path: path.to_string(),
+                before: annotation.clone(),
+                to: info.to.clone(),
+                description: info.description,
+            });
+            *annotation = Value::String(info.to);
+            return Ok(());
+        }
+        skipped.push(format!(
+            "{}: shorthand transition alongside per-operation entries; list the operations it covers, then migrate",
+            path
+        ));
+    }
+
+    for (op, entry) in map.iter_mut() {
+        let Value::Object(obj) = entry else {
+            continue;
+        };
+        // Shorthand handled above; conditionals aren't transitions
+        if op == "transition" || obj.contains_key("when") {
+            continue;
+        }
+        let op_path = format!("{}/{}", path, escape_pointer_token(op));
+        let info = parse_transition_info(obj, &op_path)?;
+        applied.push(AppliedTransition {
+            path: op_path,
+            before: entry.clone(),
+            to: info.to.clone(),
+            description: info.description,
+        });
+        *entry = Value::String(info.to);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn migrate_per_operation_and_shorthand() {
+        let schema = json!({
+            "properties": {
+                "id": {
+                    "type": "string",
+                    "ucp_request": {
+                        "create": "omit",
+                        "update": { "transition": { "from": "required", "to": "optional", "description": "Inferred from path." } }
+                    }
+                },
+                "legacy": {
+                    "ucp_response": { "transition": { "from": "optional", "to": "omit", "description": "Removed in v2." } }
+                },
+                "items": {
+                    "type": "array",
+                    "items": {
+                        "properties": {
+                            "a/b": { "ucp_request": { "read": { "from": "omit", "to": "optional", "description": "Now readable." } } }
+                        }
+                    }
+                }
+            }
+        });
+        let migrated = migrate_transitions(&schema).unwrap();
+
+        assert_eq!(
+            migrated.schema["properties"]["id"]["ucp_request"],
+            json!({ "create": "omit", "update": "optional" })
+        );
+        assert_eq!(
+            migrated.schema["properties"]["legacy"]["ucp_response"],
+            "omit"
+        );
+        let paths: Vec<&str> = migrated.applied.iter().map(|a| a.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "/properties/id/ucp_request/update",
+                "/properties/legacy/ucp_response",
+                "/properties/items/items/properties/a~1b/ucp_request/read"
+            ]
+        );
+        assert_eq!(migrated.applied[1].description, "Removed in v2.");
+        assert!(migrated.skipped.is_empty());
+    }
+
+    #[test]
+    fn migrate_skips_mixed_shorthand_and_keeps_conditionals() {
+        let schema = json!({
+            "properties": {
+                "id": {
+                    "ucp_request": {
+                        "create": "omit",
+                        "transition": { "from": "required", "to": "optional", "description": "x" }
+                    }
+                },
+                "tax_id": {
+                    "ucp_request": { "create": { "when": { "required": ["country"] }, "then": "required" } }
+                }
+            }
+        });
+        let migrated = migrate_transitions(&schema).unwrap();
+
+        assert_eq!(migrated.schema, schema);
+        assert!(migrated.applied.is_empty());
+        assert_eq!(migrated.skipped.len(), 1);
+        assert!(migrated.skipped[0].starts_with("/properties/id/ucp_request: shorthand"));
+    }
+
+    #[test]
+    fn migrate_rejects_invalid_transition() {
+        let schema = json!({
+            "properties": {
+                "id": { "ucp_request": { "update": { "transition": { "from": "required", "to": "required", "description": "x" } } } }
+            }
+        });
+        let err = migrate_transitions(&schema).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("/properties/id/ucp_request/update"));
+    }
+}
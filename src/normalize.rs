@@ -0,0 +1,232 @@
+//! Canonical schema form - byte-stable output for content hashing and diffs.
+//!
+//! Normalizing a schema:
+//!
+//! - sorts object keys;
+//! - sorts `required` and drops duplicates;
+//! - expands shorthand annotations (`"ucp_request": "omit"`) to the
+//!   per-operation object form;
+//! - writes integral floats (`1.0`) as integers.
+//!
+//! Normalizing is idempotent, and schemas that differ only in these respects
+//! normalize to the same value.
+
+use serde_json::{Map, Number, Value};
+
+use crate::types::{UCP_ANNOTATIONS, UCP_OPERATIONS, VALID_OPERATIONS};
+
+/// Keywords whose value maps names to schemas (rather than being a schema).
+pub(crate) const SCHEMA_MAPS: &[&str] = &[
+    "properties",
+    "patternProperties",
+    "$defs",
+    "definitions",
+    "dependentSchemas",
+];
+
+/// Return the canonical form of `schema`.
+///
+/// Shorthand annotations are expanded over the standard operations plus
+/// every operation named by an object-form annotation or `x-ucp-operations`
+/// in `schema`; an operation named nowhere loses the shorthand's effect, so
+/// declare custom operations before normalizing. A shorthand transition
+/// next to per-operation entries fills in the operations not listed.
+pub fn normalize_schema(schema: &Value) -> Value {
+    normalize_value(schema, false, &expansion_operations(schema))
+}
+
+/// Operations shorthand annotations in `schema` expand over: the standard
+/// ones, then those named by object-form annotations or `x-ucp-operations`.
+pub(crate) fn expansion_operations(schema: &Value) -> Vec<String> {
+    let mut ops: Vec<String> = VALID_OPERATIONS.iter().map(|op| op.to_string()).collect();
+    collect_operations(schema, false, &mut ops);
+    ops
+}
+
+/// Add the operations object-form annotations name or `x-ucp-operations`
+/// declares to `ops`.
+fn collect_operations(value: &Value, names: bool, ops: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                if !names && UCP_ANNOTATIONS.contains(&key.as_str()) {
+                    let named = child.as_object().into_iter().flat_map(|m| m.keys());
+                    for op in named.filter(|op| *op != "transition") {
+                        if !ops.contains(op) {
+                            ops.push(op.clone());
+                        }
+                    }
+                } else if !names && key == UCP_OPERATIONS {
+                    for op in child
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(Value::as_str)
+                    {
+                        if !ops.iter().any(|o| o == op) {
+                            ops.push(op.to_string());
+                        }
+                    }
+                } else {
+                    let names = !names && SCHEMA_MAPS.contains(&key.as_str());
+                    collect_operations(child, names, ops);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_operations(item, false, ops);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `names` is set when `value` maps names to schemas, so its keys are never
+/// annotation keywords.
+fn normalize_value(value: &Value, names: bool, ops: &[String]) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            let mut out = Map::new();
+            for key in keys {
+                let child = &map[key];
+                let normalized = if names {
+                    normalize_value(child, false, ops)
+                } else if UCP_ANNOTATIONS.contains(&key.as_str()) {
+                    expand_annotation(child, ops)
+                } else if key == "required" {
+                    normalize_required(child)
+                } else {
+                    normalize_value(child, SCHEMA_MAPS.contains(&key.as_str()), ops)
+                };
+                out.insert(key.clone(), normalized);
+            }
+            Value::Object(out)
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| normalize_value(item, false, ops))
+                .collect(),
+        ),
+        Value::Number(n) => Value::Number(normalize_number(n)),
+        other => other.clone(),
+    }
+}
+
+fn expand_annotation(annotation: &Value, ops: &[String]) -> Value {
+    let expanded = match annotation {
+        Value::String(_) => ops
+            .iter()
+            .map(|op| (op.clone(), annotation.clone()))
+            .collect(),
+        Value::Object(map) => match map.get("transition") {
+            Some(transition) if transition.is_object() => {
+                let mut expanded: Map<String, Value> = map
+                    .iter()
+                    .filter(|(op, _)| *op != "transition")
+                    .map(|(op, entry)| (op.clone(), entry.clone()))
+                    .collect();
+                for op in ops {
+                    expanded
+                        .entry(op.clone())
+                        .or_insert_with(|| serde_json::json!({ "transition": transition }));
+                }
+                expanded
+            }
+            _ => map.clone(),
+        },
+        // Invalid annotations are left for the resolver to report
+        other => return other.clone(),
+    };
+    normalize_value(&Value::Object(expanded), true, ops)
+}
+
+fn normalize_required(required: &Value) -> Value {
+    let Value::Array(items) = required else {
+        return required.clone();
+    };
+    if !items.iter().all(Value::is_string) {
+        return required.clone();
+    }
+    let mut names: Vec<&str> = items.iter().filter_map(Value::as_str).collect();
+    names.sort_unstable();
+    names.dedup();
+    Value::Array(names.into_iter().map(Value::from).collect())
+}
+
+fn normalize_number(n: &Number) -> Number {
+    // Integral floats within f64's exact integer range print as integers
+    match n.as_f64() {
+        Some(f) if n.is_f64() && f.fract() == 0.0 && f.abs() <= 9_007_199_254_740_992.0 => {
+            Number::from(f as i64)
+        }
+        _ => n.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn normalize_sorts_and_expands() {
+        let schema = json!({
+            "type": "object",
+            "required": ["b", "a", "b"],
+            "properties": {
+                "ucp_request": { "type": "string", "maximum": 10.0, "ucp_request": "omit" },
+                "id": {
+                    "ucp_response": { "refund": "required" },
+                    "ucp_request": {
+                        "create": "omit",
+                        "transition": { "from": "required", "to": "optional", "description": "x" }
+                    }
+                }
+            }
+        });
+        let normalized = normalize_schema(&schema);
+
+        assert_eq!(
+            serde_json::to_string(&normalized).unwrap(),
+            json!({
+                "properties": {
+                    "id": {
+                        "ucp_request": {
+                            "complete": { "transition": { "description": "x", "from": "required", "to": "optional" } },
+                            "create": "omit",
+                            "read": { "transition": { "description": "x", "from": "required", "to": "optional" } },
+                            "refund": { "transition": { "description": "x", "from": "required", "to": "optional" } },
+                            "update": { "transition": { "description": "x", "from": "required", "to": "optional" } }
+                        },
+                        "ucp_response": { "refund": "required" }
+                    },
+                    "ucp_request": {
+                        "maximum": 10,
+                        "type": "string",
+                        "ucp_request": {
+                            "complete": "omit",
+                            "create": "omit",
+                            "read": "omit",
+                            "refund": "omit",
+                            "update": "omit"
+                        }
+                    }
+                },
+                "required": ["a", "b"],
+                "type": "object"
+            })
+            .to_string()
+        );
+        assert_eq!(normalize_schema(&normalized), normalized);
+    }
+
+    #[test]
+    fn normalize_keeps_non_string_required_and_invalid_annotations() {
+        let schema = json!({ "required": [1, "a"], "ucp_request": 5, "minimum": 0.5 });
+        assert_eq!(normalize_schema(&schema), schema);
+    }
+}
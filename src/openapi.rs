@@ -0,0 +1,756 @@
+//! OpenAPI interop - export resolved schemas, import annotated ones.
+//!
+//! Export resolves each capability schema for every operation in both
+//! directions, as for a schema library, and the entries become OpenAPI 3.1
+//! components named `{Capability}{Operation}{Direction}` (e.g.,
+//! `CheckoutCreateRequest`).
+//!
+//! Import goes the other way: the request and response bodies of one
+//! resource's CRUD paths are merged into a single schema, with
+//! `ucp_request`/`ucp_response` annotations inferred from which operations
+//! include and require each property.
+
+use std::collections::HashMap;
+
+use serde_json::{json, Map, Value};
+
+use crate::error::ResolveError;
+use crate::library::{resolve_library, LibraryOptions};
+use crate::types::Direction;
+
+const COMPONENTS_PREFIX: &str = "#/components/schemas/";
+
+/// OpenAPI version written to the `openapi` field.
+pub const OPENAPI_VERSION: &str = "3.1.0";
+
+/// Options for OpenAPI export.
+#[derive(Debug, Clone)]
+pub struct OpenApiOptions {
+    /// `info.title` of the document.
+    pub title: String,
+    /// `info.version` of the document.
+    pub version: String,
+    /// Operations to resolve, in output order. Normalized to lowercase.
+    pub operations: Vec<String>,
+    /// Resolve in strict mode (see `ResolveOptions::strict`).
+    pub strict: bool,
+}
+
+impl OpenApiOptions {
+    /// Create export options with version "1.0.0" and strict mode disabled.
+    pub fn new<S: Into<String>>(title: impl Into<String>, operations: Vec<S>) -> Self {
+        Self {
+            title: title.into(),
+            version: "1.0.0".to_string(),
+            operations: operations
+                .into_iter()
+                .map(|op| op.into().to_lowercase())
+                .collect(),
+            strict: false,
+        }
+    }
+
+    /// Set `info.version`.
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// Set strict mode for every resolution.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+}
+
+/// Build an OpenAPI 3.1 document from named capability schemas.
+///
+/// `schemas` pairs a base name (e.g., "checkout") with its schema. Components
+/// are ordered by capability, then operation, request before response.
+/// Internal refs are re-rooted under `#/components/schemas/`.
+///
+/// # Errors
+///
+/// Returns the first `ResolveError` encountered, or `InvalidSchema` if two
+/// capabilities produce the same component name.
+pub fn openapi_document(
+    schemas: &[(String, Value)],
+    options: &OpenApiOptions,
+) -> Result<Value, ResolveError> {
+    let mut components = Map::new();
+
+    for (name, schema) in schemas {
+        let library_options =
+            LibraryOptions::new(name.clone(), options.operations.clone()).strict(options.strict);
+        let library = resolve_library(schema, &library_options)?;
+        let Some(Value::Object(defs)) = library.get("$defs").cloned() else {
+            continue;
+        };
+        for (component, mut entry) in defs {
+            if components.contains_key(&component) {
+                return Err(ResolveError::InvalidSchema {
+                    message: format!("duplicate component name {} (from {})", component, name),
+                });
+            }
+            if let Value::Object(map) = &mut entry {
+                // A component with its own $id would resolve "#/components/..."
+                // against that $id instead of the document
+                map.remove("$id");
+                map.remove("$schema");
+            }
+            rewrite_ref_prefix(&mut entry, "#/$defs/", COMPONENTS_PREFIX);
+            components.insert(component, entry);
+        }
+    }
+
+    Ok(json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": options.title,
+            "version": options.version,
+        },
+        "components": { "schemas": components },
+    }))
+}
+
+/// Result of importing an OpenAPI resource.
+#[derive(Debug, Clone)]
+pub struct OpenApiImport {
+    /// Annotated schema covering every imported operation.
+    pub schema: Value,
+    /// Resource path the operations were taken from (e.g., "/checkouts").
+    pub resource: String,
+    /// Imported operations, in order of first appearance.
+    pub operations: Vec<String>,
+    /// Paths skipped and properties whose definitions disagreed.
+    pub warnings: Vec<String>,
+}
+
+/// One request or response body of an operation.
+struct Body {
+    operation: String,
+    direction: Direction,
+    properties: Map<String, Value>,
+    required: Vec<String>,
+}
+
+/// Merge the JSON bodies of one resource's operations into an annotated schema.
+///
+/// A resource is the literal path prefix before the first `{param}` segment.
+/// `resource` selects one; it may be omitted when the spec has only one.
+/// Operations come from `x-ucp-operation` when set, otherwise from the path
+/// shape: `POST /r` is create, `GET /r/{id}` read, `PUT`/`PATCH /r/{id}`
+/// update, `DELETE /r/{id}` delete, and `POST /r/{id}/action` is `action`.
+///
+/// For each direction, a property missing from an operation's body is `omit`
+/// for that operation, and present ones are `required` or `optional` per the
+/// body's `required`. Properties optional everywhere get no annotation.
+/// Referenced `#/components/schemas/` entries are copied into `$defs`.
+///
+/// # Errors
+///
+/// Returns `InvalidSchema` if the spec has no paths, the resource is missing
+/// or ambiguous, or none of its operations has a JSON body.
+pub fn import_openapi(spec: &Value, resource: Option<&str>) -> Result<OpenApiImport, ResolveError> {
+    let invalid = |message: String| ResolveError::InvalidSchema { message };
+    let paths = spec
+        .get("paths")
+        .and_then(Value::as_object)
+        .filter(|p| !p.is_empty())
+        .ok_or_else(|| invalid("OpenAPI document has no paths".to_string()))?;
+
+    let mut resources: Vec<String> = Vec::new();
+    for path in paths.keys() {
+        let (base, _) = split_resource(path);
+        if !resources.contains(&base) {
+            resources.push(base);
+        }
+    }
+    let resource = match resource {
+        Some(r) => {
+            let r = r.trim_end_matches('/').to_string();
+            if !resources.contains(&r) {
+                return Err(invalid(format!(
+                    "no paths under resource {} (found: {})",
+                    r,
+                    resources.join(", ")
+                )));
+            }
+            r
+        }
+        None if resources.len() == 1 => resources.remove(0),
+        None => {
+            return Err(invalid(format!(
+                "spec has several resources ({}); choose one",
+                resources.join(", ")
+            )))
+        }
+    };
+
+    let mut warnings = Vec::new();
+    let mut bodies = Vec::new();
+    let mut roots = Vec::new();
+    for (path, item) in paths {
+        let (base, rest) = split_resource(path);
+        if base != resource {
+            continue;
+        }
+        let Some(item) = item.as_object() else {
+            continue;
+        };
+        for (method, op_spec) in item {
+            if !HTTP_METHODS.contains(&method.as_str()) {
+                continue;
+            }
+            let operation = match op_spec.get("x-ucp-operation").and_then(Value::as_str) {
+                Some(op) => op.to_lowercase(),
+                None => match crud_operation(method, &rest) {
+                    Some(op) => op,
+                    None => {
+                        warnings.push(format!(
+                            "skipping {} {}: no matching UCP operation (set x-ucp-operation)",
+                            method.to_uppercase(),
+                            path
+                        ));
+                        continue;
+                    }
+                },
+            };
+
+            let request = op_spec.pointer("/requestBody/content").and_then(json_body);
+            let response = op_spec
+                .get("responses")
+                .and_then(Value::as_object)
+                .and_then(|responses| {
+                    responses
+                        .iter()
+                        .filter(|(status, _)| status.starts_with('2'))
+                        .find_map(|(_, r)| r.get("content").and_then(json_body))
+                });
+            for (direction, schema) in [
+                (Direction::Request, request),
+                (Direction::Response, response),
+            ] {
+                let Some(schema) = schema else {
+                    continue;
+                };
+                if let Some(name) = schema
+                    .get("$ref")
+                    .and_then(Value::as_str)
+                    .and_then(|r| r.strip_prefix(COMPONENTS_PREFIX))
+                {
+                    roots.push((direction, name.to_string()));
+                }
+                let mut properties = Map::new();
+                let mut required = Vec::new();
+                flatten_object(spec, schema, &mut properties, &mut required, 0);
+                if properties.is_empty() {
+                    warnings.push(format!(
+                        "skipping {} {} {} body: no object properties",
+                        method.to_uppercase(),
+                        path,
+                        direction.as_str()
+                    ));
+                    continue;
+                }
+                bodies.push(Body {
+                    operation: operation.clone(),
+                    direction,
+                    properties,
+                    required,
+                });
+            }
+        }
+    }
+    if bodies.is_empty() {
+        return Err(invalid(format!(
+            "no operation under {} has a JSON request or response body",
+            resource
+        )));
+    }
+
+    let mut operations: Vec<String> = Vec::new();
+    let mut properties = Map::new();
+    let mut sources: HashMap<String, String> = HashMap::new();
+    for body in &bodies {
+        if !operations.contains(&body.operation) {
+            operations.push(body.operation.clone());
+        }
+        let source = format!("{} {}", body.operation, body.direction.as_str());
+        for (name, schema) in &body.properties {
+            match properties.get(name) {
+                None => {
+                    properties.insert(name.clone(), schema.clone());
+                    sources.insert(name.clone(), source.clone());
+                }
+                Some(existing) if existing != schema => warnings.push(format!(
+                    "property {} differs between {} and {}; keeping the first",
+                    name, sources[name], source
+                )),
+                Some(_) => {}
+            }
+        }
+    }
+
+    for (name, schema) in properties.iter_mut() {
+        for direction in [Direction::Request, Direction::Response] {
+            let visibility: Vec<(&str, &str)> = bodies
+                .iter()
+                .filter(|b| b.direction == direction)
+                .map(|b| {
+                    let vis = if !b.properties.contains_key(name) {
+                        "omit"
+                    } else if b.required.contains(name) {
+                        "required"
+                    } else {
+                        "optional"
+                    };
+                    (b.operation.as_str(), vis)
+                })
+                .collect();
+            if let Some(annotation) = infer_annotation(&visibility) {
+                // A `true` schema can't carry keywords; `{}` is equivalent
+                if *schema == Value::Bool(true) {
+                    *schema = json!({});
+                }
+                if let Value::Object(map) = schema {
+                    map.insert(direction.annotation_key().to_string(), annotation);
+                }
+            }
+        }
+    }
+
+    let mut result = Map::new();
+    result.insert(
+        "$schema".to_string(),
+        json!("https://json-schema.org/draft/2020-12/schema"),
+    );
+    // Title after the resource's representation: a response body component
+    // if there is one, else a request body component
+    let root = roots
+        .iter()
+        .find(|(direction, _)| *direction == Direction::Response)
+        .or(roots.first())
+        .map(|(_, name)| name);
+    if let Some(root) = root {
+        result.insert("title".to_string(), json!(root));
+    }
+    result.insert("type".to_string(), json!("object"));
+    let mut properties = Value::Object(properties);
+    let defs = collect_components(spec, &properties);
+    rewrite_ref_prefix(&mut properties, COMPONENTS_PREFIX, "#/$defs/");
+    result.insert("properties".to_string(), properties);
+    if !defs.is_empty() {
+        let mut defs = Value::Object(defs);
+        rewrite_ref_prefix(&mut defs, COMPONENTS_PREFIX, "#/$defs/");
+        result.insert("$defs".to_string(), defs);
+    }
+
+    Ok(OpenApiImport {
+        schema: Value::Object(result),
+        resource,
+        operations,
+        warnings,
+    })
+}
+
+const HTTP_METHODS: &[&str] = &["get", "put", "post", "delete", "patch"];
+
+/// Split "/checkouts/{id}/complete" into ("/checkouts", ["{id}", "complete"]).
+fn split_resource(path: &str) -> (String, Vec<String>) {
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let literal = segments.iter().take_while(|s| !s.starts_with('{')).count();
+    (
+        format!("/{}", segments[..literal].join("/")),
+        segments[literal..].iter().map(|s| s.to_string()).collect(),
+    )
+}
+
+/// UCP operation for a method on the path below its resource.
+fn crud_operation(method: &str, rest: &[String]) -> Option<String> {
+    let op = match (method, rest) {
+        ("post", []) => "create",
+        ("get", [_]) => "read",
+        ("put" | "patch", [_]) => "update",
+        ("delete", [_]) => "delete",
+        ("post" | "put" | "patch", [_, action]) if !action.starts_with('{') => action,
+        _ => return None,
+    };
+    Some(op.to_lowercase())
+}
+
+/// Schema of the JSON entry in a `content` map.
+fn json_body(content: &Value) -> Option<&Value> {
+    let content = content.as_object()?;
+    content
+        .get("application/json")
+        .or_else(|| {
+            content
+                .iter()
+                .find(|(media, _)| media.ends_with("+json"))
+                .map(|(_, v)| v)
+        })?
+        .get("schema")
+}
+
+/// Collect the properties and required names of an object schema, following
+/// component refs and merging `allOf` branches.
+fn flatten_object(
+    spec: &Value,
+    schema: &Value,
+    properties: &mut Map<String, Value>,
+    required: &mut Vec<String>,
+    depth: usize,
+) {
+    // Guards against component ref cycles
+    if depth > 32 {
+        return;
+    }
+    if let Some(target) = schema
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|r| r.strip_prefix('#'))
+        .and_then(|pointer| spec.pointer(pointer))
+    {
+        flatten_object(spec, target, properties, required, depth + 1);
+    }
+    if let Some(props) = schema.get("properties").and_then(Value::as_object) {
+        for (name, prop) in props {
+            properties
+                .entry(name.clone())
+                .or_insert_with(|| prop.clone());
+        }
+    }
+    for name in schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+    {
+        if !required.iter().any(|r| r == name) {
+            required.push(name.to_string());
+        }
+    }
+    for branch in schema
+        .get("allOf")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        flatten_object(spec, branch, properties, required, depth + 1);
+    }
+}
+
+/// Annotation for per-operation visibility: a shorthand string when every
+/// operation agrees, otherwise an object of the non-optional entries. `None`
+/// when the property is optional everywhere (the unannotated default).
+fn infer_annotation(visibility: &[(&str, &str)]) -> Option<Value> {
+    let (_, first) = visibility.first()?;
+    if visibility.iter().all(|(_, v)| v == first) {
+        return (*first != "optional").then(|| json!(first));
+    }
+    let mut map = Map::new();
+    for (op, vis) in visibility {
+        if *vis != "optional" {
+            map.insert(op.to_string(), json!(vis));
+        }
+    }
+    Some(Value::Object(map))
+}
+
+/// Components referenced from `value`, transitively, in discovery order.
+fn collect_components(spec: &Value, value: &Value) -> Map<String, Value> {
+    let mut found = Map::new();
+    let mut pending = vec![value.clone()];
+    while let Some(next) = pending.pop() {
+        let mut refs = Vec::new();
+        component_refs(&next, &mut refs);
+        for name in refs {
+            if found.contains_key(&name) {
+                continue;
+            }
+            let Some(component) = spec
+                .get("components")
+                .and_then(|c| c.get("schemas"))
+                .and_then(|s| s.get(&name))
+            else {
+                continue;
+            };
+            found.insert(name, component.clone());
+            pending.push(component.clone());
+        }
+    }
+    found
+}
+
+fn component_refs(value: &Value, refs: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(name) = map
+                .get("$ref")
+                .and_then(Value::as_str)
+                .and_then(|r| r.strip_prefix(COMPONENTS_PREFIX))
+            {
+                // "#/components/schemas/Buyer/properties/x" targets "Buyer"
+                let name = name.split('/').next().unwrap_or(name);
+                refs.push(name.to_string());
+            }
+            for child in map.values() {
+                component_refs(child, refs);
+            }
+        }
+        Value::Array(arr) => {
+            for item in arr {
+                component_refs(item, refs);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replace a leading `from` with `to` in every `$ref`.
+fn rewrite_ref_prefix(value: &mut Value, from: &str, to: &str) {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::String(r)) = map.get_mut("$ref") {
+                if let Some(rest) = r.strip_prefix(from) {
+                    *r = format!("{}{}", to, rest);
+                }
+            }
+            for child in map.values_mut() {
+                rewrite_ref_prefix(child, from, to);
+            }
+        }
+        Value::Array(arr) => {
+            for item in arr {
+                rewrite_ref_prefix(item, from, to);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::checkout;
+
+    #[test]
+    fn document_names_components_per_capability() {
+        let schemas = vec![
+            ("checkout".to_string(), checkout()),
+            ("order".to_string(), json!({ "type": "object" })),
+        ];
+        let options = OpenApiOptions::new("Shop", vec!["create", "read"]).version("2026-01-01");
+        let doc = openapi_document(&schemas, &options).unwrap();
+
+        assert_eq!(doc["openapi"], "3.1.0");
+        assert_eq!(
+            doc["info"],
+            json!({ "title": "Shop", "version": "2026-01-01" })
+        );
+        let names: Vec<&str> = doc["components"]["schemas"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "CheckoutCreateRequest",
+                "CheckoutCreateResponse",
+                "CheckoutReadRequest",
+                "CheckoutReadResponse",
+                "OrderCreateRequest",
+                "OrderCreateResponse",
+                "OrderReadRequest",
+                "OrderReadResponse"
+            ]
+        );
+    }
+
+    #[test]
+    fn document_reroots_refs_and_drops_ids() {
+        let schemas = vec![("checkout".to_string(), checkout())];
+        let doc = openapi_document(&schemas, &OpenApiOptions::new("Shop", vec!["create"])).unwrap();
+        let request = &doc["components"]["schemas"]["CheckoutCreateRequest"];
+
+        assert_eq!(
+            request["properties"]["buyer"]["$ref"],
+            "#/components/schemas/CheckoutCreateRequest/$defs/buyer"
+        );
+        assert!(request.get("$id").is_none());
+        assert!(request.get("$schema").is_none());
+        assert!(request["properties"].get("id").is_none());
+    }
+
+    #[test]
+    fn document_rejects_duplicate_names() {
+        let schemas = vec![
+            ("checkout".to_string(), checkout()),
+            ("Checkout".to_string(), checkout()),
+        ];
+        let err =
+            openapi_document(&schemas, &OpenApiOptions::new("Shop", vec!["create"])).unwrap_err();
+        assert!(err.to_string().contains("duplicate component name"));
+    }
+
+    fn spec() -> Value {
+        let body = |name: &str| json!({ "content": { "application/json": { "schema": { "$ref": format!("#/components/schemas/{}", name) } } } });
+        json!({
+            "openapi": "3.1.0",
+            "paths": {
+                "/checkouts": {
+                    "get": { "responses": { "200": { "description": "list" } } },
+                    "post": {
+                        "requestBody": body("CheckoutCreate"),
+                        "responses": { "201": body("Checkout") }
+                    }
+                },
+                "/checkouts/{id}": {
+                    "parameters": [],
+                    "get": { "responses": { "200": body("Checkout") } },
+                    "patch": {
+                        "requestBody": body("CheckoutUpdate"),
+                        "responses": { "200": body("Checkout") }
+                    }
+                },
+                "/checkouts/{id}/complete": {
+                    "post": {
+                        "requestBody": {
+                            "content": { "application/json": { "schema": {
+                                "type": "object",
+                                "properties": { "payment": { "type": "string" } },
+                                "required": ["payment"]
+                            } } }
+                        },
+                        "responses": { "200": body("Checkout") }
+                    }
+                }
+            },
+            "components": { "schemas": {
+                "Checkout": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string" },
+                        "line_items": { "type": "array", "items": { "type": "string" } },
+                        "buyer": { "$ref": "#/components/schemas/Buyer" }
+                    },
+                    "required": ["id", "line_items"]
+                },
+                "CheckoutCreate": {
+                    "allOf": [{
+                        "type": "object",
+                        "properties": {
+                            "line_items": { "type": "array", "items": { "type": "string" } }
+                        },
+                        "required": ["line_items"]
+                    }, {
+                        "properties": { "buyer": { "$ref": "#/components/schemas/Buyer" } }
+                    }]
+                },
+                "CheckoutUpdate": {
+                    "type": "object",
+                    "properties": {
+                        "line_items": { "type": "array", "items": { "type": "string" } },
+                        "buyer": { "$ref": "#/components/schemas/Buyer" }
+                    }
+                },
+                "Buyer": {
+                    "type": "object",
+                    "properties": { "address": { "$ref": "#/components/schemas/Address" } }
+                },
+                "Address": { "type": "object" },
+                "Unused": { "type": "object" }
+            } }
+        })
+    }
+
+    #[test]
+    fn import_infers_annotations_per_operation() {
+        let imported = import_openapi(&spec(), None).unwrap();
+        let schema = &imported.schema;
+
+        assert_eq!(imported.resource, "/checkouts");
+        assert_eq!(
+            imported.operations,
+            ["create", "read", "update", "complete"]
+        );
+        assert_eq!(schema["title"], "Checkout");
+        assert_eq!(schema["properties"]["id"]["ucp_request"], "omit");
+        assert_eq!(schema["properties"]["id"]["ucp_response"], "required");
+        assert_eq!(
+            schema["properties"]["line_items"]["ucp_request"],
+            json!({ "create": "required", "complete": "omit" })
+        );
+        assert_eq!(schema["properties"]["payment"]["ucp_response"], "omit");
+        assert_eq!(schema["properties"]["buyer"]["$ref"], "#/$defs/Buyer");
+        assert_eq!(
+            schema["$defs"]["Buyer"]["properties"]["address"]["$ref"],
+            "#/$defs/Address"
+        );
+        assert!(schema["$defs"].get("Unused").is_none());
+        assert_eq!(imported.warnings.len(), 1);
+        assert!(imported.warnings[0].starts_with("skipping GET /checkouts:"));
+    }
+
+    #[test]
+    fn import_round_trips_through_resolve() {
+        use crate::resolver::resolve;
+        use crate::types::ResolveOptions;
+
+        let schema = import_openapi(&spec(), Some("/checkouts/")).unwrap().schema;
+        let shape = |direction, op| {
+            let resolved = resolve(&schema, &ResolveOptions::new(direction, op)).unwrap();
+            let mut props: Vec<String> = resolved["properties"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect();
+            props.sort();
+            (
+                props,
+                resolved.get("required").cloned().unwrap_or(json!([])),
+            )
+        };
+
+        assert_eq!(
+            shape(Direction::Request, "create"),
+            (
+                vec!["buyer".into(), "line_items".into()],
+                json!(["line_items"])
+            )
+        );
+        assert_eq!(
+            shape(Direction::Request, "update"),
+            (vec!["buyer".into(), "line_items".into()], json!([]))
+        );
+        assert_eq!(
+            shape(Direction::Request, "complete"),
+            (vec!["payment".into()], json!(["payment"]))
+        );
+        assert_eq!(
+            shape(Direction::Response, "read"),
+            (
+                vec!["buyer".into(), "id".into(), "line_items".into()],
+                json!(["line_items", "id"])
+            )
+        );
+    }
+
+    #[test]
+    fn import_requires_resource_choice() {
+        let mut spec = spec();
+        spec["paths"]["/orders"] = json!({});
+        let err = import_openapi(&spec, None).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("several resources (/checkouts, /orders)"));
+
+        let err = import_openapi(&spec, Some("/carts")).unwrap_err();
+        assert!(err.to_string().contains("no paths under resource /carts"));
+    }
+}
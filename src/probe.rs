@@ -0,0 +1,202 @@
+//! Live contract probing - sending requests to a running API and checking
+//! its responses against the composed response schema.
+//!
+//! Requires the `remote` feature (enabled by default).
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::{SchemaError, ValidateError};
+use crate::loader::{FetchOptions, HTTP_TIMEOUT};
+use crate::validator::CompiledValidator;
+
+/// One request to send.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    /// JSON body, sent with `Content-Type: application/json`.
+    pub body: Option<Value>,
+}
+
+impl ProbeRequest {
+    /// Build a request from a template:
+    /// `{"method", "url" | "path", "headers": {...}, "body": ...}`.
+    ///
+    /// `path` is appended to `base_url`; `method` defaults to
+    /// `default_method`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if the template isn't an object, names neither
+    /// `url` nor `path`, or has non-string headers.
+    pub fn from_template(
+        template: &Value,
+        base_url: &str,
+        default_method: &str,
+    ) -> Result<Self, String> {
+        let Some(fields) = template.as_object() else {
+            return Err("request template must be an object".to_string());
+        };
+        let url = match (
+            fields.get("url").and_then(Value::as_str),
+            fields.get("path").and_then(Value::as_str),
+        ) {
+            (Some(url), _) => url.to_string(),
+            (None, Some(path)) => format!(
+                "{}/{}",
+                base_url.trim_end_matches('/'),
+                path.trim_start_matches('/')
+            ),
+            (None, None) => return Err("request template needs \"url\" or \"path\"".to_string()),
+        };
+        let mut headers = Vec::new();
+        if let Some(map) = fields.get("headers") {
+            let Some(map) = map.as_object() else {
+                return Err("request template \"headers\" must be an object".to_string());
+            };
+            for (name, value) in map {
+                let Some(value) = value.as_str() else {
+                    return Err(format!("header \"{}\" must be a string", name));
+                };
+                headers.push((name.clone(), value.to_string()));
+            }
+        }
+        Ok(ProbeRequest {
+            method: fields
+                .get("method")
+                .and_then(Value::as_str)
+                .unwrap_or(default_method)
+                .to_uppercase(),
+            url,
+            headers,
+            body: fields.get("body").cloned(),
+        })
+    }
+}
+
+/// HTTP method conventionally used for a UCP operation.
+pub fn default_method(operation: &str) -> &'static str {
+    match operation {
+        "create" | "complete" => "POST",
+        "update" => "PUT",
+        "cancel" => "DELETE",
+        _ => "GET",
+    }
+}
+
+/// Outcome of one probe.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeResult {
+    pub method: String,
+    pub url: String,
+    /// HTTP status, unless the request failed before a response arrived.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// Why the response couldn't be checked (transport failure, error
+    /// status, non-JSON body).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Where the response body departs from the schema.
+    pub violations: Vec<SchemaError>,
+}
+
+impl ProbeResult {
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.violations.is_empty()
+    }
+}
+
+/// Send `request` and validate the response body with `validator`, which
+/// should hold the response schema for the probed operation. `fetch` only
+/// governs network access.
+///
+/// Failures are recorded in the result rather than returned, so one
+/// unreachable endpoint doesn't stop a run.
+pub fn probe(
+    request: &ProbeRequest,
+    validator: &CompiledValidator,
+    fetch: &FetchOptions,
+) -> ProbeResult {
+    let mut result = ProbeResult {
+        method: request.method.clone(),
+        url: request.url.clone(),
+        status: None,
+        error: None,
+        violations: Vec::new(),
+    };
+    match send(request, fetch) {
+        Err(message) => result.error = Some(message),
+        Ok((status, body)) => {
+            result.status = Some(status);
+            if !(200..300).contains(&status) {
+                result.error = Some(format!("HTTP {}", status));
+            } else {
+                match serde_json::from_str::<Value>(&body) {
+                    Err(e) => result.error = Some(format!("response is not JSON: {}", e)),
+                    Ok(payload) => match validator.validate(&payload) {
+                        Ok(()) => {}
+                        Err(ValidateError::Invalid { errors }) => result.violations = errors,
+                        Err(e) => result.error = Some(e.to_string()),
+                    },
+                }
+            }
+        }
+    }
+    result
+}
+
+fn send(request: &ProbeRequest, fetch: &FetchOptions) -> Result<(u16, String), String> {
+    if fetch.network_disabled {
+        return Err("network access is disabled".to_string());
+    }
+    let method = reqwest::Method::from_bytes(request.method.as_bytes())
+        .map_err(|_| format!("invalid HTTP method \"{}\"", request.method))?;
+    let client = reqwest::blocking::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut builder = client
+        .request(method, &request.url)
+        .header("Accept", "application/json");
+    for (name, value) in &request.headers {
+        builder = builder.header(name, value);
+    }
+    if let Some(body) = &request.body {
+        builder = builder.json(body);
+    }
+    let response = builder.send().map_err(|e| e.to_string())?;
+    let status = response.status().as_u16();
+    let body = response.text().map_err(|e| e.to_string())?;
+    Ok((status, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn request_templates_join_paths_and_default_the_method() {
+        let template = json!({
+            "path": "/checkouts/c_1",
+            "headers": { "Authorization": "Bearer t" }
+        });
+        let request = ProbeRequest::from_template(&template, "https://m.example/", "GET").unwrap();
+        assert_eq!(request.url, "https://m.example/checkouts/c_1");
+        assert_eq!(request.method, "GET");
+        assert_eq!(
+            request.headers,
+            [("Authorization".to_string(), "Bearer t".to_string())]
+        );
+
+        let template = json!({ "method": "post", "url": "https://other.example/x", "body": {} });
+        let request = ProbeRequest::from_template(&template, "https://m.example", "GET").unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.url, "https://other.example/x");
+        assert_eq!(request.body, Some(json!({})));
+
+        assert!(ProbeRequest::from_template(&json!({}), "https://m.example", "GET").is_err());
+    }
+}
@@ -0,0 +1,125 @@
+//! RFC 9457 Problem Details - validation and pipeline errors as
+//! `application/problem+json` objects, for forwarding to API clients.
+
+use serde::Serialize;
+
+use crate::error::{SchemaError, ValidateError};
+
+/// Media type of a serialized [`ProblemDetails`].
+pub const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
+/// A problem details object. `errors` is an extension member listing each
+/// validation error with a JSON pointer into the payload.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProblemDetails {
+    /// URI identifying the problem type, e.g.
+    /// "urn:ucp-schema:problem:validation-failed".
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    /// HTTP status code suggested for the response.
+    pub status: u16,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ProblemError>,
+}
+
+/// One validation error of a problem's `errors` extension.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProblemError {
+    /// JSON Pointer (RFC 6901) to the invalid field.
+    pub pointer: String,
+    pub detail: String,
+    pub keyword: String,
+    #[serde(rename = "schemaPath")]
+    pub schema_path: String,
+}
+
+impl From<&SchemaError> for ProblemError {
+    fn from(error: &SchemaError) -> Self {
+        ProblemError {
+            pointer: error.path.clone(),
+            detail: error.message.clone(),
+            keyword: error.keyword.clone(),
+            schema_path: error.schema_path.clone(),
+        }
+    }
+}
+
+impl ProblemDetails {
+    /// A payload that failed validation (422).
+    pub fn validation_failed(errors: &[SchemaError]) -> Self {
+        let mut problem = Self::for_exit_code(
+            1,
+            format!("validation failed with {} error(s)", errors.len()),
+        );
+        problem.errors = errors.iter().map(ProblemError::from).collect();
+        problem
+    }
+
+    /// The problem for a CLI exit code: 1 is a failed validation (422), 2 an
+    /// invalid schema, payload or argument (400), 3 a schema or payload that
+    /// couldn't be read or fetched (503), anything else an internal error
+    /// (500).
+    pub fn for_exit_code(code: u8, detail: impl Into<String>) -> Self {
+        let (slug, title, status) = match code {
+            1 => ("validation-failed", "Payload validation failed", 422),
+            2 => ("invalid-input", "Invalid schema or payload", 400),
+            3 => ("unavailable", "Schema or payload unavailable", 503),
+            _ => ("internal", "Internal error", 500),
+        };
+        ProblemDetails {
+            problem_type: format!("urn:ucp-schema:problem:{}", slug),
+            title: title.to_string(),
+            status,
+            detail: detail.into(),
+            errors: Vec::new(),
+        }
+    }
+}
+
+impl From<&ValidateError> for ProblemDetails {
+    fn from(error: &ValidateError) -> Self {
+        match error {
+            ValidateError::Invalid { errors } => Self::validation_failed(errors),
+            ValidateError::Resolve(e) => Self::for_exit_code(e.exit_code() as u8, e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn validation_problem_lists_pointers() {
+        let error = ValidateError::Invalid {
+            errors: vec![SchemaError {
+                path: "/id".into(),
+                schema_path: "/properties/id/type".into(),
+                keyword: "type".into(),
+                message: "5 is not of type \"string\"".into(),
+            }],
+        };
+        let problem = serde_json::to_value(ProblemDetails::from(&error)).unwrap();
+        assert_eq!(
+            problem,
+            json!({
+                "type": "urn:ucp-schema:problem:validation-failed",
+                "title": "Payload validation failed",
+                "status": 422,
+                "detail": "validation failed with 1 error(s)",
+                "errors": [{
+                    "pointer": "/id",
+                    "detail": "5 is not of type \"string\"",
+                    "keyword": "type",
+                    "schemaPath": "/properties/id/type"
+                }]
+            })
+        );
+
+        let problem = ProblemDetails::for_exit_code(3, "file not found");
+        assert_eq!((problem.status, problem.errors.len()), (503, 0));
+    }
+}
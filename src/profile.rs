@@ -0,0 +1,481 @@
+//! Profiles - generating a profile document from the capability schemas in
+//! a directory, and checking a profile before anything composes from it.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::compose::{
+    compose_schema_with_options, is_iso_date, load_capability_schema, parse_capabilities_object,
+    Capability, ComposeOptions, SchemaBaseConfig,
+};
+use crate::error::ComposeError;
+use crate::linter::collect_schema_files;
+use crate::loader::load_schema;
+
+/// A generated profile.
+#[derive(Debug, Clone)]
+pub struct GeneratedProfile {
+    /// The profile document (`{"ucp": {"version", "capabilities"}}`).
+    pub profile: Value,
+    /// Capability schemas left out, and why.
+    pub warnings: Vec<String>,
+}
+
+/// Build a profile listing every capability schema under `dir`.
+///
+/// Capability schemas are files whose `name` is a reverse-domain capability
+/// name (e.g., "dev.ucp.shopping.checkout"), as for `lint --detect-orphans`.
+/// A capability's version is its schema's `x-ucp-version` or `version`, its
+/// schema URL is `base_url` joined with its path under `dir`, and it extends
+/// every other capability its `$defs` has an entry for - the entry
+/// composition extracts. `version` sets `ucp.version`; by default it's the
+/// newest capability version. Capabilities are listed by name.
+///
+/// # Errors
+///
+/// Returns `ComposeError::InvalidCapability` if two schemas declare the same
+/// name, and `ComposeError::NoCapabilitySchemas` if `dir` has none.
+pub fn generate_profile(
+    dir: &Path,
+    base_url: &str,
+    version: Option<&str>,
+) -> Result<GeneratedProfile, ComposeError> {
+    let mut warnings = Vec::new();
+    let found: Vec<(String, String, String, Value)> = find_capability_schemas(dir, &mut warnings)?
+        .into_iter()
+        .map(|c| {
+            let url = format!("{}/{}", base_url.trim_end_matches('/'), c.relative);
+            (c.name, c.version, url, c.schema)
+        })
+        .collect();
+
+    let mut capabilities = Map::new();
+    for (name, capability_version, url, schema) in &found {
+        let parents: Vec<&str> = schema
+            .get("$defs")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flat_map(|defs| defs.keys())
+            .filter(|key| *key != name && found.iter().any(|(n, ..)| n == *key))
+            .map(String::as_str)
+            .collect();
+        let mut entry = json!({ "version": capability_version, "schema": url });
+        match parents.as_slice() {
+            [] => {}
+            [parent] => entry["extends"] = json!(parent),
+            _ => entry["extends"] = json!(parents),
+        }
+        capabilities.insert(name.clone(), json!([entry]));
+    }
+
+    let version = version.map(str::to_string).unwrap_or_else(|| {
+        found
+            .iter()
+            .map(|(_, v, ..)| v.clone())
+            .max()
+            .unwrap_or_default()
+    });
+    Ok(GeneratedProfile {
+        profile: json!({ "ucp": { "version": version, "capabilities": capabilities } }),
+        warnings,
+    })
+}
+
+/// A capability schema found under a directory.
+#[derive(Debug, Clone)]
+pub(crate) struct CapabilitySchema {
+    pub name: String,
+    /// The schema's `x-ucp-version` or `version`.
+    pub version: String,
+    /// Path under the scanned directory, `/`-separated.
+    pub relative: String,
+    #[cfg_attr(not(feature = "remote"), allow(dead_code))]
+    pub file: PathBuf,
+    pub schema: Value,
+}
+
+/// The capability schemas under `dir`, sorted by name: files whose `name`
+/// is a reverse-domain capability name. Schemas without a version are
+/// skipped with a warning.
+///
+/// # Errors
+///
+/// Returns `ComposeError::InvalidCapability` if two schemas declare the same
+/// name, and `ComposeError::NoCapabilitySchemas` if `dir` has none.
+pub(crate) fn find_capability_schemas(
+    dir: &Path,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<CapabilitySchema>, ComposeError> {
+    let mut found: Vec<CapabilitySchema> = Vec::new();
+    for file in collect_schema_files(dir) {
+        let Ok(schema) = load_schema(&file) else {
+            continue;
+        };
+        let Some(name) = schema
+            .get("name")
+            .and_then(Value::as_str)
+            .filter(|n| n.contains('.'))
+        else {
+            continue;
+        };
+        let relative = file.strip_prefix(dir).unwrap_or(&file);
+        let relative: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        let relative = relative.join("/");
+
+        if let Some(other) = found.iter().find(|c| c.name == name) {
+            return Err(ComposeError::InvalidCapability {
+                name: name.to_string(),
+                message: format!("declared by both {} and {}", other.relative, relative),
+            });
+        }
+        let Some(version) = schema
+            .get("x-ucp-version")
+            .or_else(|| schema.get("version"))
+            .and_then(Value::as_str)
+        else {
+            warnings.push(format!(
+                "skipping {} ({}): no version or x-ucp-version",
+                name, relative
+            ));
+            continue;
+        };
+        found.push(CapabilitySchema {
+            name: name.to_string(),
+            version: version.to_string(),
+            relative,
+            file,
+            schema,
+        });
+    }
+    if found.is_empty() {
+        return Err(ComposeError::NoCapabilitySchemas {
+            dir: dir.to_path_buf(),
+        });
+    }
+    found.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(found)
+}
+
+/// One problem found in a profile.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProfileIssue {
+    /// Capability the problem concerns; absent for the profile as a whole.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capability: Option<String>,
+    pub message: String,
+}
+
+/// Result of checking a profile.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileCheck {
+    pub capabilities: usize,
+    pub issues: Vec<ProfileIssue>,
+}
+
+impl ProfileCheck {
+    /// Returns true if the profile has no issues.
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Check a profile document without composing a payload against it.
+///
+/// Every capability entry must be well-formed with a `YYYY-MM-DD` version,
+/// every `extends` target must be listed, and every schema must load and
+/// declare the advertised version. When all of that holds, each root is
+/// composed with the extensions that reach it, and properties the
+/// extensions give different `type`s are reported as conflicts.
+pub fn check_profile(profile: &Value, schema_base: &SchemaBaseConfig) -> ProfileCheck {
+    let mut issues = Vec::new();
+
+    let ucp = profile.get("ucp");
+    if let Some(version) = ucp.and_then(|u| u.get("version")) {
+        if !version.as_str().is_some_and(is_iso_date) {
+            issue(
+                &mut issues,
+                None,
+                format!("ucp.version {} is not a YYYY-MM-DD date", version),
+            );
+        }
+    }
+    let Some(entries) = ucp
+        .and_then(|u| u.get("capabilities"))
+        .and_then(Value::as_object)
+        .filter(|caps| !caps.is_empty())
+    else {
+        issue(
+            &mut issues,
+            None,
+            "missing or empty ucp.capabilities".to_string(),
+        );
+        return ProfileCheck {
+            capabilities: 0,
+            issues,
+        };
+    };
+
+    let mut capabilities = Vec::new();
+    for (name, entry) in entries {
+        match parse_capabilities_object(&json!({ name.as_str(): entry })) {
+            Ok(parsed) => capabilities.extend(parsed),
+            Err(e) => issue(&mut issues, Some(name), e.to_string()),
+        }
+    }
+    for cap in &capabilities {
+        let name = Some(cap.name.as_str());
+        if !is_iso_date(&cap.version) {
+            issue(
+                &mut issues,
+                name,
+                format!("version \"{}\" is not a YYYY-MM-DD date", cap.version),
+            );
+        }
+        for parent in cap.extends.iter().flatten() {
+            if !capabilities.iter().any(|c| c.name == *parent) {
+                issue(
+                    &mut issues,
+                    name,
+                    format!("extends unknown capability '{}'", parent),
+                );
+            }
+        }
+        match load_capability_schema(&cap.schema_url, schema_base, &mut Vec::new()) {
+            Ok(schema) => {
+                let declared = schema
+                    .get("x-ucp-version")
+                    .or_else(|| schema.get("version"))
+                    .and_then(Value::as_str);
+                if let Some(declared) = declared.filter(|d| *d != cap.version) {
+                    issue(
+                        &mut issues,
+                        name,
+                        format!(
+                            "advertises version {} but its schema ({}) declares {}",
+                            cap.version, cap.schema_url, declared
+                        ),
+                    );
+                }
+            }
+            Err(e) => issue(&mut issues, name, e.to_string()),
+        }
+    }
+
+    if issues.is_empty() {
+        for root in capabilities.iter().filter(|c| c.extends.is_none()) {
+            let family = family_of(root, &capabilities);
+            match compose_schema_with_options(&family, schema_base, &ComposeOptions::default()) {
+                Ok(composed) => {
+                    for message in type_conflicts(&composed.schema, &composed.origins) {
+                        issue(&mut issues, Some(&root.name), message);
+                    }
+                }
+                Err(e) => issue(
+                    &mut issues,
+                    Some(&root.name),
+                    format!("does not compose: {}", e),
+                ),
+            }
+        }
+    }
+
+    ProfileCheck {
+        capabilities: entries.len(),
+        issues,
+    }
+}
+
+fn issue(issues: &mut Vec<ProfileIssue>, capability: Option<&str>, message: String) {
+    issues.push(ProfileIssue {
+        capability: capability.map(str::to_string),
+        message,
+    });
+}
+
+/// `root` and every capability whose `extends` chain reaches it, with
+/// `extends` narrowed to parents in that set.
+fn family_of(root: &Capability, capabilities: &[Capability]) -> Vec<Capability> {
+    let mut names = vec![root.name.as_str()];
+    loop {
+        let before = names.len();
+        for cap in capabilities {
+            let joins = cap
+                .extends
+                .iter()
+                .flatten()
+                .any(|p| names.contains(&p.as_str()));
+            if joins && !names.contains(&cap.name.as_str()) {
+                names.push(&cap.name);
+            }
+        }
+        if names.len() == before {
+            break;
+        }
+    }
+    capabilities
+        .iter()
+        .filter(|c| names.contains(&c.name.as_str()))
+        .map(|c| Capability {
+            extends: c.extends.as_ref().map(|parents| {
+                parents
+                    .iter()
+                    .filter(|p| names.contains(&p.as_str()))
+                    .cloned()
+                    .collect()
+            }),
+            ..c.clone()
+        })
+        .collect()
+}
+
+/// Top-level properties that two `allOf` branches of a composition give
+/// different `type`s, which no payload can satisfy.
+fn type_conflicts(composed: &Value, origins: &[String]) -> Vec<String> {
+    let Some(branches) = composed.get("allOf").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    let mut seen: Vec<(&str, &Value, &str)> = Vec::new();
+    let mut conflicts = Vec::new();
+    for (branch, origin) in branches.iter().zip(origins) {
+        let Some(properties) = branch.get("properties").and_then(Value::as_object) else {
+            continue;
+        };
+        for (property, schema) in properties {
+            let Some(ty) = schema.get("type") else {
+                continue;
+            };
+            match seen.iter().find(|(p, ..)| p == property) {
+                Some((_, other, other_origin)) if *other != ty => conflicts.push(format!(
+                    "property '{}' is {} in {} but {} in {}",
+                    property, other, other_origin, ty, origin
+                )),
+                Some(_) => {}
+                None => seen.push((property, ty, origin)),
+            }
+        }
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_profile_infers_extends_from_defs() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, schema: Value| {
+            let path = dir.path().join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, schema.to_string()).unwrap();
+        };
+        write(
+            "shopping/checkout.json",
+            json!({ "name": "dev.ucp.shopping.checkout", "version": "2026-01-11" }),
+        );
+        write(
+            "shopping/discount.json",
+            json!({
+                "name": "dev.ucp.shopping.discount",
+                "version": "2026-03-01",
+                "$defs": { "dev.ucp.shopping.checkout": {}, "code": {} }
+            }),
+        );
+        write("types/buyer.json", json!({ "type": "object" }));
+        write("draft.json", json!({ "name": "dev.ucp.shopping.draft" }));
+
+        let generated = generate_profile(dir.path(), "https://ucp.example/schemas/", None).unwrap();
+        assert_eq!(
+            generated.profile,
+            json!({ "ucp": { "version": "2026-03-01", "capabilities": {
+                "dev.ucp.shopping.checkout": [{
+                    "version": "2026-01-11",
+                    "schema": "https://ucp.example/schemas/shopping/checkout.json"
+                }],
+                "dev.ucp.shopping.discount": [{
+                    "version": "2026-03-01",
+                    "schema": "https://ucp.example/schemas/shopping/discount.json",
+                    "extends": "dev.ucp.shopping.checkout"
+                }]
+            } } })
+        );
+        assert_eq!(generated.warnings.len(), 1);
+        assert!(generated.warnings[0].contains("dev.ucp.shopping.draft"));
+    }
+
+    #[test]
+    fn check_profile_reports_entries_then_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, schema: Value| {
+            let path = dir.path().join("schemas").join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, schema.to_string()).unwrap();
+        };
+        let extension = |name: &str, ty: &str| {
+            json!({
+                "name": name,
+                "version": "2026-01-11",
+                "$defs": { "dev.ucp.shopping.checkout": {
+                    "properties": { "total": { "type": ty } }
+                } }
+            })
+        };
+        write(
+            "checkout.json",
+            json!({ "name": "dev.ucp.shopping.checkout", "version": "2026-01-11" }),
+        );
+        write(
+            "discount.json",
+            extension("dev.ucp.shopping.discount", "integer"),
+        );
+        write("loyalty.json", extension("com.example.loyalty", "string"));
+        let entry = |file: &str, extends: Option<&str>| {
+            let mut entry = json!({
+                "version": "2026-01-11",
+                "schema": format!("https://ucp.dev/schemas/{}", file)
+            });
+            if let Some(parent) = extends {
+                entry["extends"] = json!(parent);
+            }
+            json!([entry])
+        };
+        let config = SchemaBaseConfig {
+            local_base: Some(dir.path()),
+            remote_base: None,
+            ..Default::default()
+        };
+
+        let profile = json!({ "ucp": { "capabilities": {
+            "dev.ucp.shopping.checkout": entry("checkout.json", None),
+            "dev.ucp.shopping.discount": entry("discount.json", Some("dev.ucp.shopping.checkout")),
+            "com.example.loyalty": entry("loyalty.json", Some("dev.ucp.shopping.checkout"))
+        } } });
+        let check = check_profile(&profile, &config);
+        assert_eq!(check.capabilities, 3);
+        assert_eq!(check.issues.len(), 1, "{:?}", check.issues);
+        assert_eq!(
+            check.issues[0].capability.as_deref(),
+            Some("dev.ucp.shopping.checkout")
+        );
+        assert!(check.issues[0].message.contains("property 'total'"));
+
+        let profile = json!({ "ucp": { "version": "next", "capabilities": {
+            "dev.ucp.shopping.checkout": entry("missing.json", None),
+            "dev.ucp.shopping.discount": entry("discount.json", Some("dev.ucp.shopping.cart"))
+        } } });
+        let messages: Vec<String> = check_profile(&profile, &config)
+            .issues
+            .into_iter()
+            .map(|i| i.message)
+            .collect();
+        assert_eq!(messages.len(), 3, "{:?}", messages);
+        assert!(messages[0].contains("ucp.version"));
+        assert!(messages[1].contains("missing.json"));
+        assert!(messages[2].contains("unknown capability 'dev.ucp.shopping.cart'"));
+    }
+}
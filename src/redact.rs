@@ -0,0 +1,180 @@
+//! Sensitive-field redaction - masking or removing the values of
+//! `ucp_sensitive` properties in payloads before they're logged.
+
+use serde_json::{Map, Value};
+
+use crate::error::ResolveError;
+use crate::resolver::is_sensitive;
+use crate::validator::{collect_properties, follow_local_ref};
+
+/// Default replacement for a masked value.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// What happens to a sensitive value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RedactMode {
+    /// Replace the value with this string.
+    Mask(String),
+    /// Drop the field.
+    Remove,
+}
+
+impl Default for RedactMode {
+    fn default() -> Self {
+        RedactMode::Mask(REDACTED.to_string())
+    }
+}
+
+/// A payload with its sensitive values redacted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redacted {
+    pub payload: Value,
+    /// JSON pointers of the redacted fields, in payload order.
+    pub redacted: Vec<String>,
+}
+
+/// Redact the values of fields `schema` marks `"ucp_sensitive": true`.
+///
+/// `schema` is the annotated source schema, so one call covers every
+/// direction and operation. Properties are found as `validate
+/// --annotate-payload` finds them: through nested objects, array items,
+/// local `$ref`s and composition branches. Remote `$ref`s aren't followed;
+/// bundle the schema first.
+///
+/// # Errors
+///
+/// Returns `ResolveError::InvalidSensitiveAnnotation` if a `ucp_sensitive`
+/// the payload reaches isn't a boolean.
+pub fn redact_payload(
+    schema: &Value,
+    payload: &Value,
+    mode: &RedactMode,
+) -> Result<Redacted, ResolveError> {
+    let mut redacted = Vec::new();
+    let payload = redact_node(payload, Some(schema), schema, "", mode, &mut redacted)?;
+    Ok(Redacted { payload, redacted })
+}
+
+fn redact_node(
+    value: &Value,
+    schema: Option<&Value>,
+    root: &Value,
+    path: &str,
+    mode: &RedactMode,
+    redacted: &mut Vec<String>,
+) -> Result<Value, ResolveError> {
+    match value {
+        Value::Object(map) => {
+            let mut declared = Map::new();
+            if let Some(schema) = schema {
+                collect_properties(schema, root, &mut declared);
+            }
+
+            let mut result = Map::new();
+            for (key, child) in map {
+                let child_path = format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+                let child_schema = declared.get(key);
+                if let Some(child_schema) = child_schema {
+                    if sensitive(child_schema, root, &child_path)? {
+                        if let RedactMode::Mask(mask) = mode {
+                            result.insert(key.clone(), Value::String(mask.clone()));
+                        }
+                        redacted.push(child_path);
+                        continue;
+                    }
+                }
+                result.insert(
+                    key.clone(),
+                    redact_node(child, child_schema, root, &child_path, mode, redacted)?,
+                );
+            }
+            Ok(Value::Object(result))
+        }
+        Value::Array(arr) => {
+            let items = schema.and_then(|s| follow_local_ref(s, root).get("items").cloned());
+            arr.iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let item_path = format!("{}/{}", path, i);
+                    redact_node(item, items.as_ref(), root, &item_path, mode, redacted)
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array)
+        }
+        other => Ok(other.clone()),
+    }
+}
+
+/// Whether a property schema, or any `allOf` member or local `$ref` target
+/// of it, is marked sensitive.
+fn sensitive(schema: &Value, root: &Value, path: &str) -> Result<bool, ResolveError> {
+    if is_sensitive(schema, path)? {
+        return Ok(true);
+    }
+    let target = follow_local_ref(schema, root);
+    if target != *schema && is_sensitive(&target, path)? {
+        return Ok(true);
+    }
+    for member in target
+        .get("allOf")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        if sensitive(member, root, path)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn redact_masks_or_removes_sensitive_fields() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "email": { "type": "string", "ucp_sensitive": true },
+                "cards": { "type": "array", "items": { "$ref": "#/$defs/card" } }
+            },
+            "$defs": {
+                "card": {
+                    "properties": {
+                        "number": { "type": "string", "ucp_sensitive": true },
+                        "brand": { "type": "string" }
+                    }
+                }
+            }
+        });
+        let payload = json!({
+            "email": "a@example.com",
+            "cards": [{ "number": "4242", "brand": "visa" }],
+            "note": "kept"
+        });
+
+        let masked = redact_payload(&schema, &payload, &RedactMode::default()).unwrap();
+        assert_eq!(
+            masked.payload,
+            json!({
+                "email": REDACTED,
+                "cards": [{ "number": REDACTED, "brand": "visa" }],
+                "note": "kept"
+            })
+        );
+        assert_eq!(masked.redacted, ["/email", "/cards/0/number"]);
+
+        let removed = redact_payload(&schema, &payload, &RedactMode::Remove).unwrap();
+        assert_eq!(
+            removed.payload,
+            json!({ "cards": [{ "brand": "visa" }], "note": "kept" })
+        );
+
+        let bad = json!({ "properties": { "email": { "ucp_sensitive": "yes" } } });
+        let err = redact_payload(&bad, &payload, &RedactMode::Remove).unwrap_err();
+        assert!(err.to_string().contains("invalid ucp_sensitive at /email"));
+    }
+}
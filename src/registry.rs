@@ -0,0 +1,271 @@
+//! Schema registry - publishing capability schemas to an HTTP registry and
+//! fetching them back by capability name and version.
+//!
+//! The protocol is one resource per published version,
+//! `{registry}/{name}/{version}`:
+//!
+//! - `GET` returns the schema (404 if it isn't published);
+//! - `PUT` publishes it, with the body's SHA-256 in `X-Ucp-Schema-Sha256`.
+//!
+//! Published versions are immutable: pushing a version again is only
+//! accepted with identical content. Registries may send the hash header on
+//! `GET` too, and pulls verify the body against it.
+//!
+//! Requires the `remote` feature (enabled by default).
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::RegistryError;
+use crate::loader::{FetchOptions, HTTP_TIMEOUT};
+use crate::lock::sha256_hex;
+use crate::profile::find_capability_schemas;
+
+/// Header carrying the lowercase hex SHA-256 of a schema body.
+pub const SHA256_HEADER: &str = "X-Ucp-Schema-Sha256";
+
+/// URL of one published version.
+pub fn registry_url(registry: &str, name: &str, version: &str) -> String {
+    format!("{}/{}/{}", registry.trim_end_matches('/'), name, version)
+}
+
+/// What pushing a schema did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PushStatus {
+    /// Newly published.
+    Published,
+    /// Already published with the same content.
+    Unchanged,
+    /// Not published yet; nothing was sent (dry run).
+    New,
+}
+
+/// One capability schema pushed to a registry.
+#[derive(Debug, Clone, Serialize)]
+pub struct PushedSchema {
+    pub name: String,
+    pub version: String,
+    pub file: PathBuf,
+    pub sha256: String,
+    pub status: PushStatus,
+}
+
+/// Push every capability schema under `dir` (as `profile generate` finds
+/// them) to `registry`, returning what happened to each and warnings for
+/// schemas skipped. With `dry_run`, only checks what would be published.
+///
+/// `token`, if given, is sent as a bearer token. `fetch` only governs network
+/// access here; pushes aren't cached or locked.
+///
+/// # Errors
+///
+/// Returns `RegistryError::Compose` if `dir` has no capability schemas,
+/// `RegistryError::Immutable` if a version is already published with
+/// different content, and request/status errors if the registry fails.
+/// Schemas before the failing one stay pushed.
+pub fn push_schemas(
+    dir: &Path,
+    registry: &str,
+    token: Option<&str>,
+    dry_run: bool,
+    fetch: &FetchOptions,
+) -> Result<(Vec<PushedSchema>, Vec<String>), RegistryError> {
+    let mut warnings = Vec::new();
+    let mut pushed = Vec::new();
+    for capability in find_capability_schemas(dir, &mut warnings)? {
+        let url = registry_url(registry, &capability.name, &capability.version);
+        let body =
+            std::fs::read_to_string(&capability.file).map_err(|e| RegistryError::Request {
+                method: "PUT".to_string(),
+                url: url.clone(),
+                message: format!("cannot read {}: {}", capability.file.display(), e),
+            })?;
+        let sha256 = sha256_hex(body.as_bytes());
+
+        let status = match send("GET", &url, token, None, fetch)? {
+            (200, published) => {
+                let published = sha256_hex(published.as_bytes());
+                if published != sha256 {
+                    return Err(RegistryError::Immutable {
+                        name: capability.name,
+                        version: capability.version,
+                        published,
+                    });
+                }
+                PushStatus::Unchanged
+            }
+            (404, _) if dry_run => PushStatus::New,
+            (404, _) => match send("PUT", &url, token, Some((&body, &sha256)), fetch)? {
+                (200..=299, _) => PushStatus::Published,
+                (status, _) => {
+                    return Err(RegistryError::Status {
+                        method: "PUT".to_string(),
+                        url,
+                        status,
+                    })
+                }
+            },
+            (status, _) => {
+                return Err(RegistryError::Status {
+                    method: "GET".to_string(),
+                    url,
+                    status,
+                })
+            }
+        };
+        pushed.push(PushedSchema {
+            name: capability.name,
+            version: capability.version,
+            file: capability.file,
+            sha256,
+            status,
+        });
+    }
+    Ok((pushed, warnings))
+}
+
+/// A schema fetched from a registry.
+#[derive(Debug, Clone)]
+pub struct PulledSchema {
+    /// The body as the registry sent it.
+    pub body: String,
+    pub sha256: String,
+}
+
+/// Fetch `name` at `version` from `registry`.
+///
+/// The body's hash is checked against the registry's `X-Ucp-Schema-Sha256`
+/// header, if it sends one, and against `expected_sha256`, if given. The
+/// schema must declare the requested `name` and version.
+///
+/// # Errors
+///
+/// Returns `RegistryError::HashMismatch` if a hash differs,
+/// `RegistryError::UnexpectedSchema` if the body isn't the requested
+/// schema, and request/status errors if the registry fails.
+pub fn pull_schema(
+    registry: &str,
+    name: &str,
+    version: &str,
+    token: Option<&str>,
+    expected_sha256: Option<&str>,
+    fetch: &FetchOptions,
+) -> Result<PulledSchema, RegistryError> {
+    let url = registry_url(registry, name, version);
+    let (status, body, header) = request("GET", &url, token, None, fetch)?;
+    if status != 200 {
+        return Err(RegistryError::Status {
+            method: "GET".to_string(),
+            url,
+            status,
+        });
+    }
+    let sha256 = sha256_hex(body.as_bytes());
+    for expected in [header.as_deref(), expected_sha256].into_iter().flatten() {
+        if !expected.eq_ignore_ascii_case(&sha256) {
+            return Err(RegistryError::HashMismatch {
+                url,
+                expected: expected.to_string(),
+                actual: sha256,
+            });
+        }
+    }
+
+    let unexpected = |message: String| RegistryError::UnexpectedSchema {
+        url: url.clone(),
+        name: name.to_string(),
+        version: version.to_string(),
+        message,
+    };
+    let schema: Value = serde_json::from_str(&body).map_err(|e| unexpected(e.to_string()))?;
+    let declared_name = schema.get("name").and_then(Value::as_str);
+    let declared_version = schema
+        .get("x-ucp-version")
+        .or_else(|| schema.get("version"))
+        .and_then(Value::as_str);
+    if declared_name != Some(name) || declared_version != Some(version) {
+        return Err(unexpected(format!(
+            "it declares {} {}",
+            declared_name.unwrap_or("no name"),
+            declared_version.unwrap_or("no version")
+        )));
+    }
+    Ok(PulledSchema { body, sha256 })
+}
+
+fn send(
+    method: &str,
+    url: &str,
+    token: Option<&str>,
+    body: Option<(&str, &str)>,
+    fetch: &FetchOptions,
+) -> Result<(u16, String), RegistryError> {
+    request(method, url, token, body, fetch).map(|(status, body, _)| (status, body))
+}
+
+/// Send a request, returning the status, body and `X-Ucp-Schema-Sha256`
+/// header. `body` is the JSON text and its hash.
+fn request(
+    method: &str,
+    url: &str,
+    token: Option<&str>,
+    body: Option<(&str, &str)>,
+    fetch: &FetchOptions,
+) -> Result<(u16, String, Option<String>), RegistryError> {
+    if fetch.network_disabled {
+        return Err(RegistryError::NetworkDisabled {
+            url: url.to_string(),
+        });
+    }
+    let failed = |e: reqwest::Error| RegistryError::Request {
+        method: method.to_string(),
+        url: url.to_string(),
+        message: e.to_string(),
+    };
+    let client = reqwest::blocking::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .build()
+        .map_err(failed)?;
+    let mut builder = match method {
+        "PUT" => client.put(url),
+        _ => client.get(url),
+    };
+    if let Some(token) = token {
+        builder = builder.bearer_auth(token);
+    }
+    if let Some((body, sha256)) = body {
+        builder = builder
+            .header("Content-Type", "application/json")
+            .header(SHA256_HEADER, sha256)
+            .body(body.to_string());
+    }
+    let response = builder.send().map_err(failed)?;
+    let status = response.status().as_u16();
+    let header = response
+        .headers()
+        .get(SHA256_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let body = response.text().map_err(failed)?;
+    Ok((status, body, header))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_urls_join_name_and_version() {
+        assert_eq!(
+            registry_url(
+                "https://registry.example/",
+                "dev.ucp.shopping.checkout",
+                "2026-01-11"
+            ),
+            "https://registry.example/dev.ucp.shopping.checkout/2026-01-11"
+        );
+    }
+}
@@ -0,0 +1,253 @@
+//! Traffic replay - reading recorded HTTP exchanges from HAR captures and
+//! working out which operation each one performed, so `ucp-schema replay`
+//! can grade recorded bodies against the schemas.
+
+use regex::Regex;
+use serde_json::Value;
+
+/// One recorded request/response pair.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HarExchange {
+    pub method: String,
+    pub url: String,
+    pub request_headers: Vec<(String, String)>,
+    /// Request body, if it was recorded and parses as JSON.
+    pub request_body: Option<Value>,
+    pub status: u16,
+    /// Response body, if it was recorded and parses as JSON.
+    pub response_body: Option<Value>,
+}
+
+impl HarExchange {
+    /// Value of a request header, matched case-insensitively.
+    pub fn request_header(&self, name: &str) -> Option<&str> {
+        self.request_headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Profile URL from the `UCP-Agent` header (`profile="<url>"`), as REST
+    /// requests carry it.
+    pub fn agent_profile(&self) -> Option<&str> {
+        let header = self.request_header("UCP-Agent")?;
+        let start = header.find("profile=\"")? + "profile=\"".len();
+        let end = header[start..].find('"')?;
+        Some(&header[start..start + end])
+    }
+
+    /// URL path without scheme, host, query or fragment.
+    pub fn path(&self) -> &str {
+        url_path(&self.url)
+    }
+}
+
+/// Read the exchanges of a HAR document (`log.entries`), in capture order.
+///
+/// Bodies that are missing, base64-encoded or not JSON are left as `None`.
+///
+/// # Errors
+///
+/// Returns a message if `har` has no `log.entries` array or an entry lacks
+/// its request method or URL.
+pub fn har_exchanges(har: &Value) -> Result<Vec<HarExchange>, String> {
+    let entries = har
+        .pointer("/log/entries")
+        .and_then(Value::as_array)
+        .ok_or("not a HAR file: missing log.entries")?;
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let field = |pointer: &str| entry.pointer(pointer).and_then(Value::as_str);
+            let (Some(method), Some(url)) = (field("/request/method"), field("/request/url"))
+            else {
+                return Err(format!("entry {} has no request method or URL", i));
+            };
+            let request_headers = entry
+                .pointer("/request/headers")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|h| Some((h.get("name")?.as_str()?, h.get("value")?.as_str()?)))
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect();
+            Ok(HarExchange {
+                method: method.to_uppercase(),
+                url: url.to_string(),
+                request_headers,
+                request_body: json_body(entry.pointer("/request/postData")),
+                status: entry
+                    .pointer("/response/status")
+                    .and_then(Value::as_u64)
+                    .unwrap_or(0) as u16,
+                response_body: json_body(entry.pointer("/response/content")),
+            })
+        })
+        .collect()
+}
+
+fn json_body(content: Option<&Value>) -> Option<Value> {
+    let content = content?;
+    if content.get("encoding").and_then(Value::as_str) == Some("base64") {
+        return None;
+    }
+    serde_json::from_str(content.get("text")?.as_str()?).ok()
+}
+
+/// Maps requests to the operation they perform: a method (or any method)
+/// and a regex over the URL path.
+#[derive(Debug, Clone)]
+pub struct OperationRule {
+    /// Uppercase method, or `None` for any.
+    pub method: Option<String>,
+    pub pattern: Regex,
+    pub operation: String,
+}
+
+impl OperationRule {
+    /// Parse `[METHOD ]PATTERN=OPERATION`, e.g.
+    /// `POST /checkouts/[^/]+/complete$=complete` (`*` also means any method).
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if the `=` or operation is missing, or the pattern
+    /// isn't a valid regex.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let Some((matcher, operation)) = s.rsplit_once('=') else {
+            return Err("expected [METHOD ]PATTERN=OPERATION".to_string());
+        };
+        if operation.trim().is_empty() {
+            return Err("expected [METHOD ]PATTERN=OPERATION".to_string());
+        }
+        let (method, pattern) = match matcher.trim().split_once(' ') {
+            Some((method, pattern)) => (Some(method), pattern.trim()),
+            None => (None, matcher.trim()),
+        };
+        let pattern = Regex::new(pattern).map_err(|e| format!("invalid pattern: {}", e))?;
+        Ok(OperationRule {
+            method: method.filter(|m| *m != "*").map(str::to_uppercase),
+            pattern,
+            operation: operation.trim().to_lowercase(),
+        })
+    }
+
+    fn matches(&self, method: &str, path: &str) -> bool {
+        self.method.as_deref().map_or(true, |m| m == method) && self.pattern.is_match(path)
+    }
+}
+
+/// Rules for the conventional REST mapping: `POST .../complete` completes,
+/// `POST .../cancel` and `DELETE` cancel, other `POST`s create, `PUT` and
+/// `PATCH` update, and `GET` reads.
+pub fn default_operation_rules() -> Vec<OperationRule> {
+    [
+        "POST /complete$=complete",
+        "POST /cancel$=cancel",
+        "POST .=create",
+        "PUT .=update",
+        "PATCH .=update",
+        "DELETE .=cancel",
+        "GET .=read",
+    ]
+    .into_iter()
+    .map(|rule| OperationRule::parse(rule).expect("default rules parse"))
+    .collect()
+}
+
+/// Operation an exchange performed.
+///
+/// A JSON-RPC request names it in its method (`create_checkout`); otherwise
+/// the first of `rules` matching the method and URL path decides.
+pub fn infer_operation(rules: &[OperationRule], exchange: &HarExchange) -> Option<String> {
+    let rpc_method = exchange
+        .request_body
+        .as_ref()
+        .filter(|body| body.get("jsonrpc").is_some())
+        .and_then(|body| body.get("method"))
+        .and_then(Value::as_str);
+    if let Some(method) = rpc_method {
+        return method.split('_').next().map(str::to_lowercase);
+    }
+    rules
+        .iter()
+        .find(|rule| rule.matches(&exchange.method, exchange.path()))
+        .map(|rule| rule.operation.clone())
+}
+
+/// Endpoint an exchange hit, with identifier-like path segments (those
+/// containing a digit) replaced by `{id}`, e.g. `GET /checkouts/{id}`.
+pub fn endpoint_key(exchange: &HarExchange) -> String {
+    let path: Vec<&str> = exchange
+        .path()
+        .split('/')
+        .map(|segment| {
+            if segment.contains(|c: char| c.is_ascii_digit()) {
+                "{id}"
+            } else {
+                segment
+            }
+        })
+        .collect();
+    format!("{} {}", exchange.method, path.join("/"))
+}
+
+fn url_path(url: &str) -> &str {
+    let rest = match url.find("://") {
+        Some(i) => {
+            let after = &url[i + 3..];
+            after.find('/').map_or("/", |j| &after[j..])
+        }
+        None => url,
+    };
+    let end = rest.find(['?', '#']).unwrap_or(rest.len());
+    &rest[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn har_exchanges_infer_operations_and_endpoints() {
+        let har = json!({ "log": { "entries": [
+            {
+                "request": {
+                    "method": "POST",
+                    "url": "https://m.example/checkouts/c_12/complete?x=1",
+                    "headers": [{ "name": "ucp-agent", "value": "profile=\"https://a.example/p.json\"" }],
+                    "postData": { "mimeType": "application/json", "text": "{\"id\": \"c_12\"}" }
+                },
+                "response": { "status": 200, "content": { "text": "not json" } }
+            },
+            {
+                "request": {
+                    "method": "POST",
+                    "url": "https://m.example/rpc",
+                    "postData": { "text": "{\"jsonrpc\": \"2.0\", \"method\": \"update_checkout\"}" }
+                },
+                "response": { "status": 200, "content": { "text": "{}" } }
+            }
+        ] } });
+        let exchanges = har_exchanges(&har).unwrap();
+        let rules = default_operation_rules();
+
+        let rest = &exchanges[0];
+        assert_eq!(rest.request_body, Some(json!({ "id": "c_12" })));
+        assert_eq!(rest.response_body, None);
+        assert_eq!(rest.agent_profile(), Some("https://a.example/p.json"));
+        assert_eq!(infer_operation(&rules, rest).as_deref(), Some("complete"));
+        assert_eq!(endpoint_key(rest), "POST /checkouts/{id}/complete");
+        // The JSON-RPC method wins over the POST rule
+        assert_eq!(
+            infer_operation(&rules, &exchanges[1]).as_deref(),
+            Some("update")
+        );
+
+        let custom = [OperationRule::parse("post /checkouts/[^/]+/complete$=finalize").unwrap()];
+        assert_eq!(infer_operation(&custom, rest).as_deref(), Some("finalize"));
+        assert!(OperationRule::parse("GET /x").is_err());
+        assert!(har_exchanges(&json!({})).is_err());
+    }
+}
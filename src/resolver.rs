@@ -1,11 +1,15 @@
 //! Schema resolution - transforms UCP annotated schemas into standard JSON Schema.
 
-use serde_json::{Map, Value};
+use std::borrow::Cow;
+
+use serde_json::{json, Map, Value};
 
 use crate::error::ResolveError;
 use crate::types::{
-    is_valid_schema_transition, json_type_name, Direction, ResolveOptions, SchemaTransitionInfo,
-    Visibility, UCP_ANNOTATIONS,
+    is_valid_schema_transition, json_type_name, Denullify, Direction, PropertyResolution,
+    ResolutionReport, ResolveOptions, SchemaTransitionInfo, Visibility, PROPERTY_NAMES_KEYWORDS,
+    UCP_ANNOTATIONS, UCP_DESCRIPTION, UCP_ENUM, UCP_OPERATIONS, UCP_OPERATION_GROUPS,
+    UCP_PROPERTY_NAMES, UCP_SENSITIVE, VALID_OPERATIONS,
 };
 
 /// Resolve a schema for a specific direction and operation.
@@ -19,7 +23,37 @@ use crate::types::{
 ///
 /// Returns `ResolveError` if the schema contains invalid annotations.
 pub fn resolve(schema: &Value, options: &ResolveOptions) -> Result<Value, ResolveError> {
-    let mut resolved = resolve_value(schema, options, "")?;
+    resolve_recorded(schema, options, &mut Recorder::default())
+}
+
+/// Like `resolve`, additionally reporting what resolution did to each
+/// property: the annotation found, the visibility applied, whether it was
+/// and is required, and any transition emitted.
+///
+/// # Errors
+///
+/// Returns `ResolveError` if the schema contains invalid annotations.
+pub fn resolve_with_report(
+    schema: &Value,
+    options: &ResolveOptions,
+) -> Result<(Value, ResolutionReport), ResolveError> {
+    let mut recorder = Recorder(Some(Vec::new()));
+    let resolved = resolve_recorded(schema, options, &mut recorder)?;
+    let report = ResolutionReport {
+        direction: options.direction,
+        operation: options.operation.clone(),
+        properties: recorder.0.unwrap_or_default(),
+    };
+    Ok((resolved, report))
+}
+
+fn resolve_recorded(
+    schema: &Value,
+    options: &ResolveOptions,
+    recorder: &mut Recorder,
+) -> Result<Value, ResolveError> {
+    let schema = with_groups_expanded(schema)?;
+    let mut resolved = resolve_value(&schema, options, "", recorder)?;
 
     if options.strict {
         close_additional_properties(&mut resolved);
@@ -28,6 +62,46 @@ pub fn resolve(schema: &Value, options: &ResolveOptions) -> Result<Value, Resolv
     Ok(resolved)
 }
 
+/// Property decisions made during one resolution, kept only when a report
+/// was asked for.
+#[derive(Default)]
+struct Recorder(Option<Vec<PropertyResolution>>);
+
+impl Recorder {
+    /// Record a property decision, if reporting. `entry` is only built then.
+    fn record(&mut self, entry: impl FnOnce() -> PropertyResolution) {
+        if let Some(entries) = &mut self.0 {
+            entries.push(entry());
+        }
+    }
+}
+
+/// Apply strict-mode closure to selected top-level `allOf` branches only.
+///
+/// Used for mixed-trust compositions: branches at `branches` get their nested
+/// objects closed exactly as in strict mode, while the others stay open. The
+/// top level itself is only closed (via `unevaluatedProperties`) when every
+/// branch is selected, since unknown top-level fields can't be attributed to
+/// a single branch. A schema without `allOf` counts as one branch (index 0).
+pub fn close_allof_branches(schema: &mut Value, branches: &[usize]) {
+    let Some(all_of) = schema.get_mut("allOf").and_then(|v| v.as_array_mut()) else {
+        if branches.contains(&0) {
+            close_additional_properties(schema);
+        }
+        return;
+    };
+
+    if (0..all_of.len()).all(|i| branches.contains(&i)) {
+        close_additional_properties(schema);
+        return;
+    }
+    for (i, branch) in all_of.iter_mut().enumerate() {
+        if branches.contains(&i) {
+            close_additional_properties_inner(branch, true);
+        }
+    }
+}
+
 /// Recursively close object schemas to reject unknown properties.
 ///
 /// For simple object schemas: sets `additionalProperties: false`
@@ -42,14 +116,24 @@ fn close_additional_properties(value: &mut Value) {
 
 /// Inner implementation with context tracking.
 ///
-/// `in_composition_branch` is true when processing direct children of allOf/anyOf/oneOf.
-/// We skip setting additionalProperties on these because each branch is validated
-/// independently and doesn't see properties from sibling branches.
+/// `in_composition_branch` is true when processing direct children of allOf/anyOf/oneOf,
+/// `then`/`else` and `dependentSchemas` entries. We skip setting additionalProperties
+/// on these because each branch is validated independently and doesn't see properties
+/// from sibling branches or the parent.
 fn close_additional_properties_inner(value: &mut Value, in_composition_branch: bool) {
     if let Value::Object(map) = value {
-        // Check if this schema uses composition keywords
-        let has_composition =
-            map.contains_key("allOf") || map.contains_key("anyOf") || map.contains_key("oneOf");
+        // Check if this schema uses composition keywords (or other in-place
+        // applicators whose properties unevaluatedProperties sees)
+        let has_composition = [
+            "allOf",
+            "anyOf",
+            "oneOf",
+            "then",
+            "else",
+            "dependentSchemas",
+        ]
+        .iter()
+        .any(|k| map.contains_key(*k));
 
         // Check if this is an object schema (has "type": "object" or has "properties")
         let is_object_schema = map
@@ -98,10 +182,25 @@ fn close_additional_properties_inner(value: &mut Value, in_composition_branch: b
                         }
                     }
                 }
-                "items" | "additionalProperties" | "unevaluatedProperties" => {
+                "items" | "additionalProperties" | "unevaluatedProperties" | "contains" => {
                     // Schema values - recurse
                     close_additional_properties_inner(child, false);
                 }
+                "prefixItems" => {
+                    // Tuple positions - each is a schema of its own
+                    if let Value::Array(arr) = child {
+                        for item in arr {
+                            close_additional_properties_inner(item, false);
+                        }
+                    }
+                }
+                "patternProperties" => {
+                    if let Value::Object(patterns) = child {
+                        for pattern_value in patterns.values_mut() {
+                            close_additional_properties_inner(pattern_value, false);
+                        }
+                    }
+                }
                 "$defs" | "definitions" => {
                     // Definitions - recurse into each
                     if let Value::Object(defs) = child {
@@ -119,6 +218,19 @@ fn close_additional_properties_inner(value: &mut Value, in_composition_branch: b
                         }
                     }
                 }
+                "then" | "else" => {
+                    // Conditional branches apply to this object, like allOf branches
+                    close_additional_properties_inner(child, true);
+                }
+                "dependentSchemas" => {
+                    if let Value::Object(entries) = child {
+                        for entry in entries.values_mut() {
+                            close_additional_properties_inner(entry, true);
+                        }
+                    }
+                }
+                // `if` and `not` stay open: closing a test or a negation
+                // changes which payloads it matches
                 _ => {}
             }
         }
@@ -128,7 +240,10 @@ fn close_additional_properties_inner(value: &mut Value, in_composition_branch: b
 /// Get visibility for a single property.
 ///
 /// Looks up the appropriate annotation (`ucp_request` or `ucp_response`) and
-/// determines the visibility for the given operation.
+/// determines the visibility for the given operation. Without one, a
+/// `readonly`/`writeonly` value in the other direction's annotation applies,
+/// so either annotation can carry them. The result is never `readonly` or
+/// `writeonly`: those come back as `omit` or `include` for `direction`.
 ///
 /// # Errors
 ///
@@ -139,18 +254,52 @@ pub fn get_visibility(
     operation: &str,
     path: &str,
 ) -> Result<(Visibility, Option<SchemaTransitionInfo>), ResolveError> {
-    let key = direction.annotation_key();
-    let Some(annotation) = prop.get(key) else {
+    if let Some(annotation) = prop.get(direction.annotation_key()) {
+        return get_visibility_from_annotation(annotation, direction, operation, path);
+    }
+    let Some(annotation) = prop.get(direction.opposite().annotation_key()) else {
         return Ok((Visibility::Include, None));
     };
-    get_visibility_from_annotation(annotation, operation, path)
+    // Errors in the other annotation are reported when resolving its direction
+    match parse_annotation(annotation, operation, path) {
+        Ok((vis, None)) if vis.is_bidirectional() => Ok((vis.in_direction(direction), None)),
+        _ => Ok((Visibility::Include, None)),
+    }
+}
+
+/// The annotation `get_visibility` decides `prop`'s visibility from: the
+/// direction's own, or the other direction's if it's `readonly`/`writeonly`.
+fn decisive_annotation<'a>(
+    prop: &'a Value,
+    options: &ResolveOptions,
+    path: &str,
+) -> Option<&'a Value> {
+    let direction = options.direction;
+    prop.get(direction.annotation_key()).or_else(|| {
+        prop.get(direction.opposite().annotation_key())
+            .filter(|annotation| {
+                parse_annotation(annotation, &options.operation, path)
+                    .is_ok_and(|(vis, transition)| vis.is_bidirectional() && transition.is_none())
+            })
+    })
 }
 
-/// Parse visibility (and optional transition info) from a raw annotation value.
+/// Parse visibility (and optional transition info) from a raw annotation value,
+/// with `readonly`/`writeonly` applied for `direction`.
 ///
 /// Shared between `get_visibility` (which extracts annotation by direction key)
 /// and `inject_annotations` (which already has the annotation from allOf propagation).
-fn get_visibility_from_annotation(
+pub(crate) fn get_visibility_from_annotation(
+    annotation: &Value,
+    direction: Direction,
+    operation: &str,
+    path: &str,
+) -> Result<(Visibility, Option<SchemaTransitionInfo>), ResolveError> {
+    let (vis, transition) = parse_annotation(annotation, operation, path)?;
+    Ok((vis.in_direction(direction), transition))
+}
+
+fn parse_annotation(
     annotation: &Value,
     operation: &str,
     path: &str,
@@ -164,6 +313,12 @@ fn get_visibility_from_annotation(
             // Lookup operation (already lowercase from ResolveOptions)
             match map.get(operation) {
                 Some(Value::String(s)) => Ok((parse_visibility_string(s, path)?, None)),
+                Some(Value::Object(obj)) if obj.contains_key("when") => {
+                    // Conditional: kept but not statically required; the
+                    // resolver adds if/then/else on the parent object
+                    parse_conditional(obj, &format!("{}/{}", path, operation))?;
+                    Ok((Visibility::Optional, None))
+                }
                 Some(Value::Object(obj)) => {
                     parse_transition_value(obj, &format!("{}/{}", path, operation))
                 }
@@ -190,10 +345,111 @@ fn get_visibility_from_annotation(
     }
 }
 
+/// Visibility that depends on the parent object:
+/// `{ "when": <schema>, "then": <visibility>, "else": <visibility> }`.
+///
+/// `when` is evaluated against the object holding the property; `else`
+/// defaults to `optional`.
+struct ConditionalVisibility {
+    when: Value,
+    then: Visibility,
+    otherwise: Visibility,
+}
+
+impl ConditionalVisibility {
+    /// Build the `if`/`then`/`else` schema enforcing this on property `name`.
+    ///
+    /// `required` adds the name to `required`, `omit` forbids it with a
+    /// `false` property schema, and `optional` needs no branch at all.
+    /// Returns `None` when neither branch constrains anything.
+    fn to_schema(&self, name: &str, direction: Direction) -> Option<Value> {
+        let branch = |vis: Visibility| match vis.in_direction(direction) {
+            Visibility::Required => Some(json!({ "required": [name] })),
+            Visibility::Omit => Some(json!({ "properties": { name: false } })),
+            _ => None,
+        };
+        let then = branch(self.then);
+        let otherwise = branch(self.otherwise);
+        if then.is_none() && otherwise.is_none() {
+            return None;
+        }
+
+        let mut schema = Map::new();
+        schema.insert("if".to_string(), self.when.clone());
+        if let Some(then) = then {
+            schema.insert("then".to_string(), then);
+        }
+        if let Some(otherwise) = otherwise {
+            schema.insert("else".to_string(), otherwise);
+        }
+        Some(Value::Object(schema))
+    }
+}
+
+fn parse_conditional(
+    obj: &Map<String, Value>,
+    path: &str,
+) -> Result<ConditionalVisibility, ResolveError> {
+    let when = obj.get("when").cloned().unwrap_or(Value::Bool(true));
+    if !when.is_object() && !when.is_boolean() {
+        return Err(ResolveError::InvalidAnnotationType {
+            path: format!("{}/when", path),
+            actual: json_type_name(&when).to_string(),
+        });
+    }
+
+    let branch = |key: &str| match obj.get(key) {
+        None => Ok(Visibility::Optional),
+        Some(Value::String(s)) => parse_visibility_string(s, &format!("{}/{}", path, key)),
+        Some(other) => Err(ResolveError::InvalidAnnotationType {
+            path: format!("{}/{}", path, key),
+            actual: json_type_name(other).to_string(),
+        }),
+    };
+    if !obj.contains_key("then") {
+        return Err(ResolveError::InvalidSchema {
+            message: format!("conditional visibility at {} is missing \"then\"", path),
+        });
+    }
+
+    Ok(ConditionalVisibility {
+        when,
+        then: branch("then")?,
+        otherwise: branch("else")?,
+    })
+}
+
+/// Conditional visibility for a property under the current direction/operation, if any.
+fn get_conditional(
+    prop: &Value,
+    options: &ResolveOptions,
+    path: &str,
+) -> Result<Option<ConditionalVisibility>, ResolveError> {
+    let op_value = prop
+        .get(options.direction.annotation_key())
+        .and_then(|a| a.get(&options.operation));
+    match op_value {
+        Some(Value::Object(obj)) if obj.contains_key("when") => {
+            parse_conditional(obj, &format!("{}/{}", path, options.operation)).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
 fn parse_transition_value(
     obj: &Map<String, Value>,
     path: &str,
 ) -> Result<(Visibility, Option<SchemaTransitionInfo>), ResolveError> {
+    let info = parse_transition_info(obj, path)?;
+    let vis = parse_visibility_string(&info.from, path)?;
+    Ok((vis, Some(info)))
+}
+
+/// Parse a transition, either wrapped (`{ "transition": {...} }`) or bare.
+pub(crate) fn parse_transition_info(
+    obj: &Map<String, Value>,
+    path: &str,
+) -> Result<SchemaTransitionInfo, ResolveError> {
     let t = obj
         .get("transition")
         .and_then(|v| v.as_object())
@@ -219,35 +475,355 @@ fn parse_transition_value(
         });
     }
 
-    let vis = parse_visibility_string(from, path)?;
-    Ok((
-        vis,
-        Some(SchemaTransitionInfo {
-            from: from.to_string(),
-            to: to.to_string(),
-            description: description.to_string(),
-        }),
-    ))
+    Ok(SchemaTransitionInfo {
+        from: from.to_string(),
+        to: to.to_string(),
+        description: description.to_string(),
+    })
 }
 
 /// Strip all UCP annotations from a schema.
 ///
 /// Recursively removes `ucp_request` and `ucp_response`.
 pub fn strip_annotations(schema: &Value) -> Value {
-    strip_annotations_recursive(schema)
+    strip_annotations_recursive(schema, &[], false)
+}
+
+/// Remove value-bearing keywords (e.g., `default`, `examples`) from a schema.
+///
+/// Walks schema positions only: property and definition names are never
+/// treated as keywords, and literal data under `const`/`enum`/`default`/
+/// `examples` is left untouched unless that keyword itself is redacted.
+pub fn redact_keywords(schema: &Value, keywords: &[String]) -> Value {
+    redact_recursive(schema, keywords)
+}
+
+/// Find UCP annotations that resolution ignores.
+///
+/// Visibility annotations only take effect on property schemas
+/// (`properties/<name>`); placed anywhere else (the schema root, `items`,
+/// a `$defs` entry, a composition branch) they are silently dropped. Returns
+/// the path of each such annotation, in document order.
+pub fn find_stray_annotations(schema: &Value) -> Vec<String> {
+    let mut found = Vec::new();
+    find_stray_recursive(schema, "", false, &mut found);
+    found
+}
+
+/// Operations named by object-form visibility annotations or declared in
+/// `x-ucp-operations`, in document order.
+///
+/// When every annotation is a plain string (which applies to all operations)
+/// and none are declared, returns `VALID_OPERATIONS`.
+pub fn annotated_operations(schema: &Value) -> Vec<String> {
+    let schema = with_groups_expanded(schema).unwrap_or(Cow::Borrowed(schema));
+    let mut ops = Vec::new();
+    collect_operations(&schema, &mut ops);
+    if ops.is_empty() {
+        ops = VALID_OPERATIONS.iter().map(|op| op.to_string()).collect();
+    }
+    ops
+}
+
+/// Operations a schema can be resolved for: the standard operations, then
+/// any others its annotations name or `x-ucp-operations` declares, in
+/// document order.
+///
+/// Used for shell completion of `--op` values.
+pub fn declared_operations(schema: &Value) -> Vec<String> {
+    let schema = with_groups_expanded(schema).unwrap_or(Cow::Borrowed(schema));
+    let mut ops: Vec<String> = VALID_OPERATIONS.iter().map(|op| op.to_string()).collect();
+    collect_operations(&schema, &mut ops);
+    ops
+}
+
+/// Expand the operation groups declared in the root's
+/// `x-ucp-operation-groups` (e.g. `"write": ["create", "update", "delete"]`):
+/// every `ucp_request`/`ucp_response` object keyed by a group name gets that
+/// entry for each of the group's operations instead.
+///
+/// # Errors
+///
+/// Returns `ResolveError::InvalidOperationGroup` if the declaration isn't an
+/// object of operation arrays, a group is named like a standard operation, or
+/// an annotation sets an operation both directly and through a group (or
+/// through two groups).
+pub fn expand_operation_groups(schema: &Value) -> Result<Value, ResolveError> {
+    with_groups_expanded(schema).map(Cow::into_owned)
+}
+
+/// Find every monotonicity violation for one direction and operation.
+///
+/// Runs the check `resolve` applies when propagating annotations across
+/// `allOf` branches, but collects all violations instead of stopping at the
+/// first. Other errors (e.g., malformed annotations) are returned as `Err`.
+pub fn monotonicity_violations(
+    schema: &Value,
+    options: &ResolveOptions,
+) -> Result<Vec<ResolveError>, ResolveError> {
+    let mut found = Vec::new();
+    let schema = with_groups_expanded(schema)?;
+    collect_monotonicity_violations(&schema, options, "", &mut found)?;
+    Ok(found)
 }
 
 // --- Internal implementation ---
 
+/// `schema` with its operation groups expanded, or as-is if it declares none.
+fn with_groups_expanded(schema: &Value) -> Result<Cow<'_, Value>, ResolveError> {
+    let Some(declared) = schema.get(UCP_OPERATION_GROUPS) else {
+        return Ok(Cow::Borrowed(schema));
+    };
+    let path = format!("/{}", UCP_OPERATION_GROUPS);
+    let invalid = |path: String, message: &str| ResolveError::InvalidOperationGroup {
+        path,
+        message: message.to_string(),
+    };
+    let Some(declared) = declared.as_object() else {
+        return Err(invalid(path, "expected an object of operation arrays"));
+    };
+    let mut groups = Map::new();
+    for (group, ops) in declared {
+        let group_path = format!("{}/{}", path, group);
+        if VALID_OPERATIONS.contains(&group.as_str()) {
+            return Err(invalid(
+                group_path,
+                "a group can't be named like an operation",
+            ));
+        }
+        let members = ops
+            .as_array()
+            .filter(|ops| !ops.is_empty() && ops.iter().all(Value::is_string));
+        let Some(members) = members else {
+            return Err(invalid(
+                group_path,
+                "expected a non-empty array of operations",
+            ));
+        };
+        groups.insert(group.clone(), Value::Array(members.clone()));
+    }
+
+    let mut expanded = schema.clone();
+    expand_groups_recursive(&mut expanded, &groups, "")?;
+    Ok(Cow::Owned(expanded))
+}
+
+fn expand_groups_recursive(
+    value: &mut Value,
+    groups: &Map<String, Value>,
+    path: &str,
+) -> Result<(), ResolveError> {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                let child_path = format!("{}/{}", path, k);
+                if is_annotation(k) {
+                    if let Value::Object(annotation) = v {
+                        *annotation = expand_annotation_groups(annotation, groups, &child_path)?;
+                    }
+                } else if !DATA_KEYWORDS.contains(&k.as_str()) {
+                    expand_groups_recursive(v, groups, &child_path)?;
+                }
+            }
+        }
+        Value::Array(arr) => {
+            for (i, item) in arr.iter_mut().enumerate() {
+                expand_groups_recursive(item, groups, &format!("{}/{}", path, i))?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Replace group keys of one annotation object with their operations.
+fn expand_annotation_groups(
+    annotation: &Map<String, Value>,
+    groups: &Map<String, Value>,
+    path: &str,
+) -> Result<Map<String, Value>, ResolveError> {
+    let mut expanded = Map::new();
+    // Operation -> group that set it
+    let mut via: std::collections::HashMap<&str, &str> = std::collections::HashMap::new();
+    for (key, entry) in annotation {
+        let Some(members) = groups.get(key).and_then(Value::as_array) else {
+            expanded.insert(key.clone(), entry.clone());
+            continue;
+        };
+        for op in members.iter().filter_map(Value::as_str) {
+            let message = if annotation.contains_key(op) {
+                format!(
+                    "\"{}\" is set both directly and through group \"{}\"",
+                    op, key
+                )
+            } else if let Some(other) = via.get(op) {
+                format!("\"{}\" is in both group \"{}\" and \"{}\"", op, other, key)
+            } else {
+                via.insert(op, key);
+                expanded.insert(op.to_string(), entry.clone());
+                continue;
+            };
+            return Err(ResolveError::InvalidOperationGroup {
+                path: format!("{}/{}", path, key),
+                message,
+            });
+        }
+    }
+    Ok(expanded)
+}
+
+fn collect_operations(value: &Value, ops: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                if UCP_ANNOTATIONS.contains(&k.as_str()) {
+                    for op in v.as_object().into_iter().flat_map(|m| m.keys()) {
+                        if op != "transition" && !ops.contains(op) {
+                            ops.push(op.clone());
+                        }
+                    }
+                } else if k == UCP_OPERATIONS {
+                    for op in v.as_array().into_iter().flatten().filter_map(Value::as_str) {
+                        if !ops.iter().any(|o| o == op) {
+                            ops.push(op.to_string());
+                        }
+                    }
+                } else if !DATA_KEYWORDS.contains(&k.as_str()) {
+                    collect_operations(v, ops);
+                }
+            }
+        }
+        Value::Array(arr) => {
+            for item in arr {
+                collect_operations(item, ops);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn collect_monotonicity_violations(
+    value: &Value,
+    options: &ResolveOptions,
+    path: &str,
+    found: &mut Vec<ResolveError>,
+) -> Result<(), ResolveError> {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::Array(branches)) = map.get("allOf") {
+                let ann_key = options.direction.annotation_key();
+                let merged = collect_allof_annotations(branches, ann_key);
+                for (i, branch) in branches.iter().enumerate() {
+                    let branch_path = format!("{}/allOf/{}", path, i);
+                    let Some(props) = branch.get("properties").and_then(|p| p.as_object()) else {
+                        continue;
+                    };
+                    for name in required_names(branch) {
+                        let (Some(ann), Some(prop)) = (merged.get(&name), props.get(&name)) else {
+                            continue;
+                        };
+                        if prop.get(ann_key).is_some() {
+                            continue;
+                        }
+                        let prop_path = format!("{}/properties/{}", branch_path, name);
+                        found.extend(monotonicity_violation(&name, ann, options, &prop_path)?);
+                    }
+                }
+            }
+            for (k, v) in map {
+                if !DATA_KEYWORDS.contains(&k.as_str()) && !is_annotation(k) {
+                    collect_monotonicity_violations(v, options, &format!("{}/{}", path, k), found)?;
+                }
+            }
+        }
+        Value::Array(arr) => {
+            for (i, item) in arr.iter().enumerate() {
+                collect_monotonicity_violations(item, options, &format!("{}/{}", path, i), found)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Names listed in a schema's `required` array.
+fn required_names(schema: &Value) -> Vec<String> {
+    schema
+        .get("required")
+        .and_then(|r| r.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The violation, if any, of applying propagated annotation `ann` to a
+/// property its branch lists as `required`.
+fn monotonicity_violation(
+    name: &str,
+    ann: &Value,
+    options: &ResolveOptions,
+    path: &str,
+) -> Result<Option<ResolveError>, ResolveError> {
+    let (vis, _) =
+        get_visibility_from_annotation(ann, options.direction, &options.operation, path)?;
+    let attempted = match vis {
+        Visibility::Omit => "omit",
+        Visibility::Optional => "optional",
+        _ => return Ok(None),
+    };
+    Ok(Some(ResolveError::MonotonicityViolation {
+        path: path.to_string(),
+        field: name.to_string(),
+        base_status: "required".into(),
+        attempted: attempted.into(),
+    }))
+}
+
+fn find_stray_recursive(value: &Value, path: &str, is_property: bool, found: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                let child_path = format!("{}/{}", path, k);
+                if is_annotation(k) {
+                    // ucp_property_names belongs on the map-like object itself
+                    if !is_property && k != UCP_PROPERTY_NAMES {
+                        found.push(child_path);
+                    }
+                } else if let (true, Value::Object(entries)) =
+                    (SCHEMA_MAP_KEYWORDS.contains(&k.as_str()), v)
+                {
+                    for (name, sub) in entries {
+                        let entry_path = format!("{}/{}", child_path, name);
+                        let is_property = k == "properties" || k == "patternProperties";
+                        find_stray_recursive(sub, &entry_path, is_property, found);
+                    }
+                } else if !DATA_KEYWORDS.contains(&k.as_str()) {
+                    find_stray_recursive(v, &child_path, false, found);
+                }
+            }
+        }
+        Value::Array(arr) => {
+            for (i, item) in arr.iter().enumerate() {
+                find_stray_recursive(item, &format!("{}/{}", path, i), false, found);
+            }
+        }
+        _ => {}
+    }
+}
+
 fn resolve_value(
     value: &Value,
     options: &ResolveOptions,
     path: &str,
+    recorder: &mut Recorder,
 ) -> Result<Value, ResolveError> {
     match value {
-        Value::Object(map) => resolve_object(map, options, path),
-        Value::Array(arr) => resolve_array(arr, options, path),
-        // Primitives pass through unchanged
+        Value::Object(map) => resolve_object(map, options, path, recorder),
+        Value::Array(arr) => resolve_array(arr, options, path, recorder),
+        // Primitives pass through unchanged, including boolean schemas (true/false)
         other => Ok(other.clone()),
     }
 }
@@ -256,6 +832,7 @@ fn resolve_object(
     map: &Map<String, Value>,
     options: &ResolveOptions,
     path: &str,
+    recorder: &mut Recorder,
 ) -> Result<Value, ResolveError> {
     let mut result = Map::new();
 
@@ -271,10 +848,18 @@ fn resolve_object(
         .unwrap_or_default();
 
     let mut new_required: Vec<String> = original_required.clone();
+    let mut conditionals: Vec<Value> = Vec::new();
 
     for (key, value) in map {
-        // Skip UCP annotations in output
-        if UCP_ANNOTATIONS.contains(&key.as_str()) {
+        // Skip UCP annotations in output, unless asked to keep them
+        if is_annotation(key) {
+            keep_annotation(
+                &mut result,
+                key,
+                value,
+                &options.keep_annotations,
+                options.preserve_annotations,
+            );
             continue;
         }
 
@@ -282,36 +867,82 @@ fn resolve_object(
 
         match key.as_str() {
             "properties" => {
-                let resolved = resolve_properties(value, options, &child_path, &mut new_required)?;
+                let resolved = resolve_properties(
+                    value,
+                    options,
+                    &child_path,
+                    &mut new_required,
+                    &mut conditionals,
+                    recorder,
+                )?;
                 result.insert(key.clone(), resolved);
             }
-            "items" => {
-                // Array items - recurse
-                let resolved = resolve_value(value, options, &child_path)?;
+            "items" | "prefixItems" | "contains" | "propertyNames" => {
+                // Array items, tuple positions, the schema some item must
+                // match and the schema every property name must match - recurse
+                let resolved = resolve_value(value, options, &child_path, recorder)?;
+                result.insert(key.clone(), resolved);
+            }
+            "patternProperties" => {
+                let resolved = resolve_pattern_properties(value, options, &child_path, recorder)?;
                 result.insert(key.clone(), resolved);
             }
             "$defs" | "definitions" => {
-                // Definitions - recurse into each definition
-                let resolved = resolve_defs(value, options, &child_path)?;
+                // Maps of name -> subschema: recurse into each entry, so entry
+                // names (which may collide with keywords like "required")
+                // are never interpreted as schema keywords
+                let resolved = resolve_defs(value, options, &child_path, recorder)?;
+                result.insert(key.clone(), resolved);
+            }
+            "dependentSchemas" | "dependencies" => {
+                // Applied to the same instance as this object, so entries
+                // inherit the visibility of this object's properties (legacy
+                // `dependencies` may also hold name lists, left as they are)
+                let entries = match value.as_object() {
+                    Some(entries) => Value::Object(
+                        entries
+                            .iter()
+                            .map(|(name, entry)| (name.clone(), inherit_annotations(entry, map)))
+                            .collect(),
+                    ),
+                    None => value.clone(),
+                };
+                let resolved = resolve_defs(&entries, options, &child_path, recorder)?;
+                result.insert(key.clone(), resolved);
+            }
+            "then" | "else" => {
+                // Conditional branches constrain the same instance as this
+                // object: inherit its property visibility, so a branch can't
+                // bring back a field the object omits
+                let branch = inherit_annotations(value, map);
+                let resolved = resolve_value(&branch, options, &child_path, recorder)?;
+                result.insert(key.clone(), resolved);
+            }
+            "if" | "not" => {
+                // Tests and negations resolve like any other schema, without
+                // inheriting: dropping an omitted field from them would
+                // change which payloads they match
+                let resolved = resolve_value(value, options, &child_path, recorder)?;
                 result.insert(key.clone(), resolved);
             }
             "allOf" => {
                 // allOf gets special handling: annotations from later branches
                 // propagate to earlier branches (last-writer-wins), enabling
                 // extension schemas to control visibility of inherited fields.
-                let resolved = resolve_allof(value, options, &child_path)?;
+                let resolved = resolve_allof(value, options, &child_path, recorder)?;
                 result.insert(key.clone(), resolved);
             }
             "anyOf" | "oneOf" => {
                 // anyOf/oneOf branches are independent alternatives —
-                // no annotation propagation across branches.
-                let resolved = resolve_composition(value, options, &child_path)?;
+                // no annotation propagation across branches, unless
+                // `propagate_variants` asks for it.
+                let resolved = resolve_composition(value, options, &child_path, recorder)?;
                 result.insert(key.clone(), resolved);
             }
             "additionalProperties" => {
                 // If it's a schema (object), recurse; otherwise keep as-is
                 if value.is_object() {
-                    let resolved = resolve_value(value, options, &child_path)?;
+                    let resolved = resolve_value(value, options, &child_path, recorder)?;
                     result.insert(key.clone(), resolved);
                 } else {
                     result.insert(key.clone(), value.clone());
@@ -321,14 +952,43 @@ fn resolve_object(
                 // Will be handled at the end after processing properties
                 continue;
             }
+            k if META_KEYWORDS.contains(&k) => {
+                // Identifiers, dialect declarations and comments are never
+                // subschemas; copy them untouched
+                result.insert(key.clone(), value.clone());
+            }
+            "type" => match options.denullify {
+                Some(mode) => denullify_type(value, mode, &child_path, &mut result)?,
+                None => {
+                    let resolved = resolve_value(value, options, &child_path, recorder)?;
+                    result.insert(key.clone(), resolved);
+                }
+            },
             _ => {
                 // Other keys - recurse if object/array, otherwise copy
-                let resolved = resolve_value(value, options, &child_path)?;
+                let resolved = resolve_value(value, options, &child_path, recorder)?;
                 result.insert(key.clone(), resolved);
             }
         }
     }
 
+    // Properties resolution dropped mustn't linger in dependency keywords
+    let omitted: Vec<&String> = map
+        .get("properties")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flat_map(|props| props.keys())
+        .filter(|name| {
+            result
+                .get("properties")
+                .and_then(|props| props.get(name.as_str()))
+                .is_none()
+        })
+        .collect();
+    if !omitted.is_empty() {
+        prune_dependencies(&mut result, &omitted);
+    }
+
     // Add updated required array if non-empty or if original existed
     if !new_required.is_empty() || map.contains_key("required") {
         result.insert(
@@ -337,14 +997,103 @@ fn resolve_object(
         );
     }
 
+    attach_conditionals(&mut result, conditionals);
+    apply_property_names_narrowing(&mut result, map, options, path)?;
+
     Ok(Value::Object(result))
 }
 
+/// Remove omitted properties from dependency keywords: entries of
+/// `dependentRequired`, `dependentSchemas` and legacy `dependencies` keyed by
+/// them go, and they leave the name lists other entries require. Entries and
+/// keywords emptied this way are dropped.
+fn prune_dependencies(result: &mut Map<String, Value>, omitted: &[&String]) {
+    let is_omitted = |name: &str| omitted.iter().any(|o| *o == name);
+    for keyword in ["dependentRequired", "dependentSchemas", "dependencies"] {
+        let Some(Value::Object(entries)) = result.get_mut(keyword) else {
+            continue;
+        };
+        let had_entries = !entries.is_empty();
+        entries.retain(|name, entry| {
+            if is_omitted(name) {
+                return false;
+            }
+            match entry {
+                Value::Array(names) if !names.is_empty() => {
+                    names.retain(|n| !n.as_str().is_some_and(is_omitted));
+                    !names.is_empty()
+                }
+                _ => true,
+            }
+        });
+        if had_entries && entries.is_empty() {
+            result.remove(keyword);
+        }
+    }
+}
+
+/// Copy the UCP annotations of `parent`'s properties onto same-named
+/// properties of `subschema` that have none of their own.
+fn inherit_annotations(subschema: &Value, parent: &Map<String, Value>) -> Value {
+    let mut subschema = subschema.clone();
+    let (Some(parent_props), Some(props)) = (
+        parent.get("properties").and_then(Value::as_object),
+        subschema
+            .get_mut("properties")
+            .and_then(Value::as_object_mut),
+    ) else {
+        return subschema;
+    };
+    for (name, prop) in props.iter_mut() {
+        let (Some(source), Some(prop)) = (parent_props.get(name), prop.as_object_mut()) else {
+            continue;
+        };
+        if UCP_ANNOTATIONS.iter().any(|key| prop.contains_key(*key)) {
+            continue;
+        }
+        for key in UCP_ANNOTATIONS {
+            if let Some(annotation) = source.get(*key) {
+                prop.insert(key.to_string(), annotation.clone());
+            }
+        }
+    }
+    subschema
+}
+
+/// Add conditional-visibility `if`/`then`/`else` schemas to an object schema.
+///
+/// A single conditional goes directly on the object when it has no `if` of
+/// its own; otherwise each one is appended to `allOf` so they don't clash.
+fn attach_conditionals(result: &mut Map<String, Value>, mut conditionals: Vec<Value>) {
+    if conditionals.is_empty() {
+        return;
+    }
+    if conditionals.len() == 1
+        && !["if", "then", "else"]
+            .iter()
+            .any(|k| result.contains_key(*k))
+    {
+        if let Some(Value::Object(cond)) = conditionals.pop() {
+            result.extend(cond);
+        }
+        return;
+    }
+
+    let all_of = result
+        .entry("allOf")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if let Value::Array(branches) = all_of {
+        branches.extend(conditionals);
+    }
+}
+
 fn resolve_properties(
     value: &Value,
     options: &ResolveOptions,
     path: &str,
     required: &mut Vec<String>,
+    conditionals: &mut Vec<Value>,
+    recorder: &mut Recorder,
 ) -> Result<Value, ResolveError> {
     let Some(props) = value.as_object() else {
         return Ok(value.clone());
@@ -354,6 +1103,36 @@ fn resolve_properties(
 
     for (prop_name, prop_value) in props {
         let prop_path = format!("{}/{}", path, prop_name);
+        let was_required = required.contains(prop_name);
+        let dropped = |visibility: &str, reason: &str| PropertyResolution {
+            path: prop_path.clone(),
+            annotation: decisive_annotation(prop_value, options, &prop_path).cloned(),
+            visibility: visibility.to_string(),
+            reason: Some(reason.to_string()),
+            was_required,
+            required: false,
+            transition: None,
+        };
+
+        // readOnly is advisory in JSON Schema; enforce it for requests on demand
+        if options.reject_readonly
+            && options.direction == Direction::Request
+            && prop_value.get("readOnly") == Some(&Value::Bool(true))
+        {
+            recorder.record(|| dropped("rejected", "readOnly"));
+            result.insert(prop_name.clone(), Value::Bool(false));
+            required.retain(|r| r != prop_name);
+            continue;
+        }
+
+        if is_sensitive(prop_value, &prop_path)?
+            && options.omit_sensitive
+            && options.direction == Direction::Response
+        {
+            recorder.record(|| dropped("omit", UCP_SENSITIVE));
+            required.retain(|r| r != prop_name);
+            continue;
+        }
 
         // Get visibility for this property
         let (visibility, transition) = get_visibility(
@@ -362,6 +1141,30 @@ fn resolve_properties(
             &options.operation,
             &prop_path,
         )?;
+        if let Some(conditional) = get_conditional(prop_value, options, &prop_path)? {
+            conditionals.extend(conditional.to_schema(prop_name, options.direction));
+        }
+        recorder.record(|| PropertyResolution {
+            path: prop_path.clone(),
+            annotation: decisive_annotation(prop_value, options, &prop_path).cloned(),
+            visibility: match visibility {
+                Visibility::Omit => "omit",
+                Visibility::Required => "required",
+                Visibility::Optional => "optional",
+                _ => "include",
+            }
+            .to_string(),
+            reason: None,
+            was_required,
+            required: match visibility {
+                Visibility::Omit | Visibility::Optional => false,
+                Visibility::Required => true,
+                _ => was_required,
+            },
+            transition: transition
+                .clone()
+                .filter(|_| visibility != Visibility::Omit),
+        });
 
         match visibility {
             Visibility::Omit => {
@@ -370,28 +1173,26 @@ fn resolve_properties(
             }
             Visibility::Required => {
                 // Keep property, ensure in required
-                let resolved = resolve_value(prop_value, options, &prop_path)?;
-                let mut stripped = strip_annotations(&resolved);
-                apply_transition_metadata(&mut stripped, &transition);
-                result.insert(prop_name.clone(), stripped);
+                let kept =
+                    resolve_kept_property(prop_value, options, &prop_path, &transition, recorder)?;
+                result.insert(prop_name.clone(), kept);
                 if !required.contains(prop_name) {
                     required.push(prop_name.clone());
                 }
             }
             Visibility::Optional => {
                 // Keep property, remove from required
-                let resolved = resolve_value(prop_value, options, &prop_path)?;
-                let mut stripped = strip_annotations(&resolved);
-                apply_transition_metadata(&mut stripped, &transition);
-                result.insert(prop_name.clone(), stripped);
+                let kept =
+                    resolve_kept_property(prop_value, options, &prop_path, &transition, recorder)?;
+                result.insert(prop_name.clone(), kept);
                 required.retain(|r| r != prop_name);
             }
-            Visibility::Include => {
+            // readonly/writeonly arrive as omit/include for the direction
+            Visibility::Include | Visibility::ReadOnly | Visibility::WriteOnly => {
                 // Keep as-is (preserve original required status)
-                let resolved = resolve_value(prop_value, options, &prop_path)?;
-                let mut stripped = strip_annotations(&resolved);
-                apply_transition_metadata(&mut stripped, &transition);
-                result.insert(prop_name.clone(), stripped);
+                let kept =
+                    resolve_kept_property(prop_value, options, &prop_path, &transition, recorder)?;
+                result.insert(prop_name.clone(), kept);
             }
         }
     }
@@ -399,10 +1200,68 @@ fn resolve_properties(
     Ok(Value::Object(result))
 }
 
+/// Resolve a property kept in the view and apply its view-specific
+/// overrides: transition metadata, `ucp_enum` and `ucp_description`.
+fn resolve_kept_property(
+    prop_value: &Value,
+    options: &ResolveOptions,
+    prop_path: &str,
+    transition: &Option<SchemaTransitionInfo>,
+    recorder: &mut Recorder,
+) -> Result<Value, ResolveError> {
+    let resolved = resolve_value(prop_value, options, prop_path, recorder)?;
+    let mut stripped = strip_annotations_recursive(
+        &resolved,
+        &options.keep_annotations,
+        options.preserve_annotations,
+    );
+    apply_transition_metadata(&mut stripped, transition);
+    apply_enum_narrowing(&mut stripped, prop_value, options, prop_path)?;
+    apply_description_override(&mut stripped, prop_value, options, prop_path)?;
+    Ok(stripped)
+}
+
+/// Resolve `patternProperties`. Like property schemas, pattern schemas may
+/// carry annotations: `omit` drops the pattern, so names matching it aren't
+/// part of the view; other visibilities keep it, since a pattern can't be
+/// required.
+fn resolve_pattern_properties(
+    value: &Value,
+    options: &ResolveOptions,
+    path: &str,
+    recorder: &mut Recorder,
+) -> Result<Value, ResolveError> {
+    let Some(patterns) = value.as_object() else {
+        return Ok(value.clone());
+    };
+
+    let mut result = Map::new();
+    for (pattern, schema) in patterns {
+        let pattern_path = format!("{}/{}", path, pattern);
+        let (visibility, _) =
+            get_visibility(schema, options.direction, &options.operation, &pattern_path)?;
+        if visibility == Visibility::Omit {
+            continue;
+        }
+        let resolved = resolve_value(schema, options, &pattern_path, recorder)?;
+        result.insert(
+            pattern.clone(),
+            strip_annotations_recursive(
+                &resolved,
+                &options.keep_annotations,
+                options.preserve_annotations,
+            ),
+        );
+    }
+
+    Ok(Value::Object(result))
+}
+
 fn resolve_defs(
     value: &Value,
     options: &ResolveOptions,
     path: &str,
+    recorder: &mut Recorder,
 ) -> Result<Value, ResolveError> {
     let Some(defs) = value.as_object() else {
         return Ok(value.clone());
@@ -411,7 +1270,7 @@ fn resolve_defs(
     let mut result = Map::new();
     for (name, def) in defs {
         let def_path = format!("{}/{}", path, name);
-        let resolved = resolve_value(def, options, &def_path)?;
+        let resolved = resolve_value(def, options, &def_path, recorder)?;
         result.insert(name.clone(), resolved);
     }
 
@@ -422,11 +1281,12 @@ fn resolve_array(
     arr: &[Value],
     options: &ResolveOptions,
     path: &str,
+    recorder: &mut Recorder,
 ) -> Result<Value, ResolveError> {
     let mut result = Vec::new();
     for (i, item) in arr.iter().enumerate() {
         let item_path = format!("{}/{}", path, i);
-        let resolved = resolve_value(item, options, &item_path)?;
+        let resolved = resolve_value(item, options, &item_path, recorder)?;
         result.push(resolved);
     }
     Ok(Value::Array(result))
@@ -436,15 +1296,29 @@ fn resolve_composition(
     value: &Value,
     options: &ResolveOptions,
     path: &str,
+    recorder: &mut Recorder,
 ) -> Result<Value, ResolveError> {
     let Some(arr) = value.as_array() else {
         return Ok(value.clone());
     };
 
+    // Propagated like allOf annotations, but without the type check:
+    // variants may well differ in type
+    let ann_key = options.direction.annotation_key();
+    let merged = match options.propagate_variants {
+        true => collect_allof_annotations(arr, ann_key),
+        false => Map::new(),
+    };
+
     let mut result = Vec::new();
     for (i, item) in arr.iter().enumerate() {
         let item_path = format!("{}/{}", path, i);
-        let resolved = resolve_value(item, options, &item_path)?;
+        let item = if !merged.is_empty() {
+            inject_annotations(item, &merged, ann_key, options, &item_path)?
+        } else {
+            item.clone()
+        };
+        let resolved = resolve_value(&item, options, &item_path, recorder)?;
         result.push(resolved);
     }
 
@@ -465,6 +1339,7 @@ fn resolve_allof(
     value: &Value,
     options: &ResolveOptions,
     path: &str,
+    recorder: &mut Recorder,
 ) -> Result<Value, ResolveError> {
     let Some(arr) = value.as_array() else {
         return Ok(value.clone());
@@ -482,7 +1357,7 @@ fn resolve_allof(
         } else {
             item.clone()
         };
-        let resolved = resolve_value(&item, options, &item_path)?;
+        let resolved = resolve_value(&item, options, &item_path, recorder)?;
         result.push(resolved);
     }
 
@@ -532,16 +1407,7 @@ fn inject_annotations(
 ) -> Result<Value, ResolveError> {
     let mut branch = branch.clone();
 
-    let base_required: Vec<String> = branch
-        .as_object()
-        .and_then(|o| o.get("required"))
-        .and_then(|r| r.as_array())
-        .map(|arr| {
-            arr.iter()
-                .filter_map(|v| v.as_str().map(String::from))
-                .collect()
-        })
-        .unwrap_or_default();
+    let base_required = required_names(&branch);
 
     if let Some(props) = branch
         .as_object_mut()
@@ -558,24 +1424,11 @@ fn inject_annotations(
 
                     // Monotonicity check: required fields cannot be weakened
                     if base_required.contains(name) {
-                        let (vis, _) = get_visibility_from_annotation(
-                            ann,
-                            &options.operation,
-                            &format!("{}/properties/{}", path, name),
-                        )?;
-                        if matches!(vis, Visibility::Omit | Visibility::Optional) {
-                            return Err(ResolveError::MonotonicityViolation {
-                                path: format!("{}/properties/{}", path, name),
-                                field: name.clone(),
-                                base_status: "required".into(),
-                                attempted: match vis {
-                                    Visibility::Omit => "omit",
-                                    Visibility::Optional => "optional",
-                                    Visibility::Required => "required",
-                                    Visibility::Include => "include",
-                                }
-                                .into(),
-                            });
+                        let prop_path = format!("{}/properties/{}", path, name);
+                        if let Some(violation) =
+                            monotonicity_violation(name, ann, options, &prop_path)?
+                        {
+                            return Err(violation);
                         }
                     }
 
@@ -590,51 +1443,390 @@ fn inject_annotations(
 
 /// Validate that allOf branches don't declare contradictory types on the same property.
 ///
-/// Only checks string-form `"type"` values. Array-form types (e.g. `["string", "null"]`)
-/// are intentionally skipped — they're rare and the semantic comparison is non-trivial.
+/// `type` is treated as the set of types it admits (a name or an array of
+/// names), and a property's sets are intersected across every branch that
+/// declares it; the branch that empties the intersection conflicts.
+/// `["string", "null"]` and `"number"` conflict, `["string", "null"]` and
+/// `"string"` don't, and neither do two of `["string", "null"]`,
+/// `["string", "number"]` and `["number", "null"]` - but all three do.
+/// `integer` counts as a member of `number`.
 fn validate_allof_types(branches: &[Value], path: &str) -> Result<(), ResolveError> {
-    let mut prop_types: std::collections::HashMap<String, String> =
+    // Property name -> (last declaring branch, types every declaration so far admits)
+    let mut admitted: std::collections::HashMap<&str, (usize, Vec<&str>)> =
         std::collections::HashMap::new();
-    for branch in branches {
+    for (i, branch) in branches.iter().enumerate() {
         let props = branch
             .as_object()
             .and_then(|o| o.get("properties"))
             .and_then(|p| p.as_object());
-        if let Some(props) = props {
-            for (name, prop) in props {
-                if let Some(type_val) = prop.as_object().and_then(|p| p.get("type")) {
-                    if let Some(type_str) = type_val.as_str() {
-                        if let Some(existing) = prop_types.get(name) {
-                            if existing != type_str {
-                                return Err(ResolveError::TypeConflict {
-                                    path: format!("{}/properties/{}", path, name),
-                                    base_type: existing.clone(),
-                                    ext_type: type_str.to_string(),
-                                });
-                            }
-                        } else {
-                            prop_types.insert(name.clone(), type_str.to_string());
-                        }
-                    }
-                }
+        for (name, prop) in props.into_iter().flatten() {
+            let Some(type_val) = prop.as_object().and_then(|p| p.get("type")) else {
+                continue;
+            };
+            let Some(types) = type_names(type_val) else {
+                continue;
+            };
+            let Some((last, so_far)) = admitted.get_mut(name.as_str()) else {
+                admitted.insert(name, (i, types));
+                continue;
+            };
+            let narrowed = intersect_types(so_far, &types);
+            if narrowed.is_empty() {
+                return Err(ResolveError::TypeConflict {
+                    path: format!("{}/properties/{}", path, name),
+                    base_branch: *last,
+                    base_type: match &so_far[..] {
+                        [single] => json!(single).to_string(),
+                        many => json!(many).to_string(),
+                    },
+                    ext_branch: i,
+                    ext_type: type_val.to_string(),
+                });
+            }
+            *last = i;
+            *so_far = narrowed;
+        }
+    }
+    Ok(())
+}
+
+/// Type names a `type` value admits, or `None` if it isn't a name or an
+/// array of names.
+fn type_names(type_val: &Value) -> Option<Vec<&str>> {
+    match type_val {
+        Value::String(name) => Some(vec![name.as_str()]),
+        Value::Array(names) => names.iter().map(Value::as_str).collect(),
+        _ => None,
+    }
+}
+
+/// Types admitted by both `a` and `b`, in `a`'s order. `integer` fits
+/// `number`, so together they admit `integer`.
+fn intersect_types<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<&'a str> {
+    let mut both = Vec::new();
+    for x in a {
+        for y in b {
+            let common = match (*x, *y) {
+                (x, y) if x == y => x,
+                ("integer", "number") | ("number", "integer") => "integer",
+                _ => continue,
+            };
+            if !both.contains(&common) {
+                both.push(common);
+            }
+        }
+    }
+    both
+}
+
+/// Returns true for any UCP annotation key (visibility or narrowing).
+pub fn is_annotation(key: &str) -> bool {
+    UCP_ANNOTATIONS.contains(&key)
+        || key == UCP_ENUM
+        || key == UCP_DESCRIPTION
+        || key == UCP_PROPERTY_NAMES
+        || key == UCP_SENSITIVE
+}
+
+/// Whether a property schema is marked `"ucp_sensitive": true`.
+pub(crate) fn is_sensitive(prop: &Value, path: &str) -> Result<bool, ResolveError> {
+    match prop.get(UCP_SENSITIVE) {
+        None => Ok(false),
+        Some(Value::Bool(sensitive)) => Ok(*sensitive),
+        Some(other) => Err(ResolveError::InvalidSensitiveAnnotation {
+            path: path.to_string(),
+            actual: json_type_name(other).to_string(),
+        }),
+    }
+}
+
+/// Replace a property's `enum` with the `ucp_enum` list for this direction
+/// and operation.
+///
+/// `ucp_enum` is keyed by direction (`request`/`response`), by operation, or
+/// by direction then operation (`{ "response": { "read": [...] } }`); an
+/// array applies everywhere. Direction keys take precedence over operation
+/// keys. With no matching entry the schema's own `enum` is kept.
+fn apply_enum_narrowing(
+    resolved: &mut Value,
+    prop: &Value,
+    options: &ResolveOptions,
+    path: &str,
+) -> Result<(), ResolveError> {
+    let Some(annotation) = prop.get(UCP_ENUM) else {
+        return Ok(());
+    };
+    let direction = options.direction.as_str();
+    let ann_path = format!("{}/{}", path, UCP_ENUM);
+
+    let selected = match annotation {
+        Value::Array(_) => Some(annotation),
+        Value::Object(map) => match map.get(direction) {
+            Some(Value::Object(by_op)) => by_op.get(&options.operation),
+            Some(list) => Some(list),
+            None => map.get(&options.operation),
+        },
+        other => {
+            return Err(ResolveError::InvalidEnumAnnotation {
+                path: ann_path,
+                message: format!("expected array or object, got {}", json_type_name(other)),
+            })
+        }
+    };
+
+    match selected {
+        Some(values @ Value::Array(_)) => {
+            if let Value::Object(map) = resolved {
+                map.insert("enum".to_string(), values.clone());
+            }
+            Ok(())
+        }
+        Some(other) => Err(ResolveError::InvalidEnumAnnotation {
+            path: ann_path,
+            message: format!("expected array of values, got {}", json_type_name(other)),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Replace a property's `description` with the `ucp_description` entry for
+/// this operation.
+///
+/// `ucp_description` is keyed by operation, each value a string. With no
+/// entry for the operation the schema's own `description` is kept.
+fn apply_description_override(
+    resolved: &mut Value,
+    prop: &Value,
+    options: &ResolveOptions,
+    path: &str,
+) -> Result<(), ResolveError> {
+    let Some(annotation) = prop.get(UCP_DESCRIPTION) else {
+        return Ok(());
+    };
+    let ann_path = format!("{}/{}", path, UCP_DESCRIPTION);
+    let Value::Object(by_op) = annotation else {
+        return Err(ResolveError::InvalidDescriptionAnnotation {
+            path: ann_path,
+            message: format!("expected object, got {}", json_type_name(annotation)),
+        });
+    };
+
+    match by_op.get(&options.operation) {
+        Some(text @ Value::String(_)) => {
+            if let Value::Object(map) = resolved {
+                map.insert("description".to_string(), text.clone());
+            }
+            Ok(())
+        }
+        Some(other) => Err(ResolveError::InvalidDescriptionAnnotation {
+            path: format!("{}/{}", ann_path, options.operation),
+            message: format!("expected string, got {}", json_type_name(other)),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Merge the `ucp_property_names` entry for this operation into the object's
+/// `propertyNames`.
+///
+/// The annotation is keyed by operation; each entry may only set `pattern`,
+/// `minLength`, `maxLength` or `enum`, which override the same keywords in
+/// the schema's own `propertyNames`. With no entry for the operation the
+/// schema's `propertyNames` is kept.
+fn apply_property_names_narrowing(
+    result: &mut Map<String, Value>,
+    original: &Map<String, Value>,
+    options: &ResolveOptions,
+    path: &str,
+) -> Result<(), ResolveError> {
+    let Some(annotation) = original.get(UCP_PROPERTY_NAMES) else {
+        return Ok(());
+    };
+    let ann_path = format!("{}/{}", path, UCP_PROPERTY_NAMES);
+    let invalid = |message: String| ResolveError::InvalidPropertyNamesAnnotation {
+        path: ann_path.clone(),
+        message,
+    };
+
+    let by_op = annotation.as_object().ok_or_else(|| {
+        invalid(format!(
+            "expected object keyed by operation, got {}",
+            json_type_name(annotation)
+        ))
+    })?;
+    let Some(selected) = by_op.get(&options.operation) else {
+        return Ok(());
+    };
+    let constraints = selected.as_object().ok_or_else(|| {
+        invalid(format!(
+            "expected object for {}, got {}",
+            options.operation,
+            json_type_name(selected)
+        ))
+    })?;
+    if let Some(key) = constraints
+        .keys()
+        .find(|k| !PROPERTY_NAMES_KEYWORDS.contains(&k.as_str()))
+    {
+        return Err(invalid(format!(
+            "unsupported keyword \"{}\" (expected {})",
+            key,
+            PROPERTY_NAMES_KEYWORDS.join(", ")
+        )));
+    }
+
+    let property_names = result
+        .entry("propertyNames")
+        .or_insert_with(|| Value::Object(Map::new()));
+    match property_names {
+        Value::Object(existing) => existing.extend(constraints.clone()),
+        // `false` already admits no keys; anything else is replaced
+        Value::Bool(false) => {}
+        other => *other = Value::Object(constraints.clone()),
+    }
+    Ok(())
+}
+
+/// Rewrite `type: [T, "null"]` to `type: T` (plus `nullable: true` in OpenAPI mode).
+///
+/// Types without a `"null"` member pass through, as does `["null"]` alone.
+fn denullify_type(
+    value: &Value,
+    mode: Denullify,
+    path: &str,
+    result: &mut Map<String, Value>,
+) -> Result<(), ResolveError> {
+    let nullable = value
+        .as_array()
+        .filter(|types| types.iter().any(|t| t == "null"));
+    let Some(types) = nullable else {
+        result.insert("type".to_string(), value.clone());
+        return Ok(());
+    };
+
+    let non_null: Vec<&Value> = types.iter().filter(|t| *t != "null").collect();
+    match non_null.as_slice() {
+        [] => {
+            result.insert("type".to_string(), value.clone());
+        }
+        [single] => {
+            result.insert("type".to_string(), (*single).clone());
+            if mode == Denullify::OpenApi {
+                result.insert("nullable".to_string(), Value::Bool(true));
             }
         }
+        _ => {
+            return Err(ResolveError::AmbiguousNullableType {
+                path: path.to_string(),
+                types: value.to_string(),
+            })
+        }
     }
     Ok(())
 }
 
-fn strip_annotations_recursive(value: &Value) -> Value {
+/// Strip UCP annotations except those listed in `keep`, which are copied
+/// verbatim. With `preserve`, the others are renamed rather than dropped
+/// (see [`preserved_annotation_key`]).
+fn strip_annotations_recursive(value: &Value, keep: &[String], preserve: bool) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut result = Map::new();
+            for (k, v) in map {
+                if is_annotation(k) {
+                    keep_annotation(&mut result, k, v, keep, preserve);
+                } else if META_KEYWORDS.contains(&k.as_str()) {
+                    result.insert(k.clone(), v.clone());
+                } else {
+                    result.insert(k.clone(), strip_annotations_recursive(v, keep, preserve));
+                }
+            }
+            Value::Object(result)
+        }
+        Value::Array(arr) => Value::Array(
+            arr.iter()
+                .map(|item| strip_annotations_recursive(item, keep, preserve))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Copy annotation `key` into `result` if it's listed in `keep`, or under
+/// its preserved name with `preserve`; otherwise leave it out.
+fn keep_annotation(
+    result: &mut Map<String, Value>,
+    key: &str,
+    value: &Value,
+    keep: &[String],
+    preserve: bool,
+) {
+    if keep.iter().any(|k| k == key) {
+        result.insert(key.to_string(), value.clone());
+    } else if preserve {
+        result.insert(preserved_annotation_key(key), value.clone());
+    }
+}
+
+/// Extension keyword an annotation is preserved as in resolved output
+/// (`ucp_request` -> `x-ucp-request`). Validators ignore unknown `x-`
+/// keywords, so the original intent stays visible without taking effect.
+fn preserved_annotation_key(key: &str) -> String {
+    format!("x-{}", key.replace('_', "-"))
+}
+
+/// Keywords whose values are maps of name -> subschema.
+pub(crate) const SCHEMA_MAP_KEYWORDS: &[&str] = &[
+    "properties",
+    "patternProperties",
+    "dependentSchemas",
+    "$defs",
+    "definitions",
+];
+
+/// Keywords whose values are instance data, not subschemas.
+pub(crate) const DATA_KEYWORDS: &[&str] = &["const", "enum", "default", "examples"];
+
+/// Core keywords that identify or describe a schema rather than hold subschemas.
+///
+/// `$vocabulary` maps vocabulary URIs to booleans; walking it as a schema
+/// could rewrite a meta-schema.
+pub(crate) const META_KEYWORDS: &[&str] = &[
+    "$schema",
+    "$id",
+    "$anchor",
+    "$dynamicAnchor",
+    "$vocabulary",
+    "$comment",
+];
+
+fn redact_recursive(value: &Value, keywords: &[String]) -> Value {
     match value {
         Value::Object(map) => {
             let mut result = Map::new();
             for (k, v) in map {
-                if !UCP_ANNOTATIONS.contains(&k.as_str()) {
-                    result.insert(k.clone(), strip_annotations_recursive(v));
+                if keywords.iter().any(|kw| kw == k) {
+                    continue;
                 }
+                let redacted = match v {
+                    Value::Object(entries) if SCHEMA_MAP_KEYWORDS.contains(&k.as_str()) => {
+                        Value::Object(
+                            entries
+                                .iter()
+                                .map(|(name, sub)| (name.clone(), redact_recursive(sub, keywords)))
+                                .collect(),
+                        )
+                    }
+                    _ if DATA_KEYWORDS.contains(&k.as_str()) => v.clone(),
+                    _ => redact_recursive(v, keywords),
+                };
+                result.insert(k.clone(), redacted);
             }
             Value::Object(result)
         }
-        Value::Array(arr) => Value::Array(arr.iter().map(strip_annotations_recursive).collect()),
+        Value::Array(arr) => {
+            Value::Array(arr.iter().map(|v| redact_recursive(v, keywords)).collect())
+        }
         other => other.clone(),
     }
 }
@@ -780,12 +1972,12 @@ mod tests {
     fn get_visibility_unknown_visibility_errors() {
         let prop = json!({
             "type": "string",
-            "ucp_request": "readonly"
+            "ucp_request": "hidden"
         });
         let result = get_visibility(&prop, Direction::Request, "create", "/test");
         assert!(matches!(
             result,
-            Err(ResolveError::UnknownVisibility { value, .. }) if value == "readonly"
+            Err(ResolveError::UnknownVisibility { value, .. }) if value == "hidden"
         ));
     }
 
@@ -891,6 +2083,33 @@ mod tests {
         assert!(!required.contains(&json!("id")));
     }
 
+    #[test]
+    fn resolve_readonly_and_writeonly_cover_both_directions() {
+        let schema = json!({
+            "type": "object",
+            "required": ["id", "password"],
+            "properties": {
+                "id": { "type": "string", "ucp_response": "readonly" },
+                "password": { "type": "string", "ucp_request": { "update": "writeonly" } },
+                "note": { "type": "string", "ucp_request": "readonly", "ucp_response": "required" }
+            }
+        });
+
+        for op in ["create", "refund"] {
+            let request = resolve(&schema, &ResolveOptions::new(Direction::Request, op)).unwrap();
+            assert!(request["properties"].get("id").is_none());
+            assert!(request["properties"].get("note").is_none());
+        }
+        let response = resolve(&schema, &ResolveOptions::new(Direction::Response, "read")).unwrap();
+        assert_eq!(response["required"], json!(["id", "password", "note"]));
+
+        let update = ResolveOptions::new(Direction::Response, "update");
+        let response = resolve(&schema, &update).unwrap();
+        assert!(response["properties"].get("password").is_none());
+        assert_eq!(response["required"], json!(["id", "note"]));
+        assert!(response["properties"]["id"].get("ucp_response").is_none());
+    }
+
     #[test]
     fn resolve_schema_transition_emits_transition_info() {
         let schema = json!({
@@ -1014,6 +2233,205 @@ mod tests {
         assert!(!required.contains(&json!("name")));
     }
 
+    #[test]
+    fn resolve_reject_readonly_request_only() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "readOnly": true },
+                "name": { "type": "string" }
+            },
+            "required": ["id", "name"]
+        });
+
+        let options = ResolveOptions::new(Direction::Request, "create").reject_readonly(true);
+        let result = resolve(&schema, &options).unwrap();
+        assert_eq!(result["properties"]["id"], json!(false));
+        assert_eq!(result["required"], json!(["name"]));
+
+        // Responses carry readOnly fields as usual
+        let options = ResolveOptions::new(Direction::Response, "read").reject_readonly(true);
+        let result = resolve(&schema, &options).unwrap();
+        assert_eq!(result["properties"]["id"]["readOnly"], json!(true));
+        assert_eq!(result["required"], json!(["id", "name"]));
+    }
+
+    #[test]
+    fn resolve_omit_sensitive_response_only() {
+        let schema = json!({
+            "type": "object",
+            "required": ["email", "name"],
+            "properties": {
+                "email": { "type": "string", "ucp_sensitive": true },
+                "name": { "type": "string" }
+            }
+        });
+
+        let options = ResolveOptions::new(Direction::Response, "read").omit_sensitive(true);
+        let result = resolve(&schema, &options).unwrap();
+        assert!(result["properties"].get("email").is_none());
+        assert_eq!(result["required"], json!(["name"]));
+
+        // Without the flag the field stays, and the annotation is stripped
+        let result = resolve(&schema, &ResolveOptions::new(Direction::Response, "read")).unwrap();
+        assert_eq!(result["properties"]["email"], json!({ "type": "string" }));
+
+        // Requests still carry sensitive fields
+        let options = ResolveOptions::new(Direction::Request, "create").omit_sensitive(true);
+        let result = resolve(&schema, &options).unwrap();
+        assert!(result["properties"].get("email").is_some());
+    }
+
+    #[test]
+    fn resolve_with_report_records_each_property_decision() {
+        let schema = json!({
+            "type": "object",
+            "required": ["id"],
+            "properties": {
+                "id": { "type": "string", "ucp_request": "omit" },
+                "created_at": { "type": "string", "ucp_response": "readonly" },
+                "buyer": {
+                    "type": "object",
+                    "properties": {
+                        "email": {
+                            "type": "string",
+                            "ucp_request": { "create": { "transition": {
+                                "from": "optional", "to": "required", "description": "Soon required."
+                            } } }
+                        }
+                    }
+                }
+            }
+        });
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let (resolved, report) = resolve_with_report(&schema, &options).unwrap();
+        assert_eq!(resolved, resolve(&schema, &options).unwrap());
+        assert_eq!(report.operation, "create");
+
+        let paths: Vec<&str> = report.properties.iter().map(|p| p.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "/properties/id",
+                "/properties/created_at",
+                "/properties/buyer",
+                "/properties/buyer/properties/email"
+            ]
+        );
+        let [id, created_at, buyer, email] = &report.properties[..] else {
+            unreachable!()
+        };
+        assert_eq!(id.annotation, Some(json!("omit")));
+        assert_eq!(
+            (id.visibility.as_str(), id.was_required, id.required),
+            ("omit", true, false)
+        );
+        // Decided by the response annotation's readonly
+        assert_eq!(created_at.annotation, Some(json!("readonly")));
+        assert_eq!(created_at.visibility, "omit");
+        assert_eq!(
+            (buyer.annotation.as_ref(), buyer.visibility.as_str()),
+            (None, "include")
+        );
+        assert_eq!(email.visibility, "optional");
+        assert_eq!(email.transition.as_ref().unwrap().to, "required");
+
+        // Rejections record why
+        let options = options.reject_readonly(true);
+        let (_, report) = resolve_with_report(
+            &json!({
+                "properties": { "v": { "readOnly": true } }
+            }),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(report.properties[0].visibility, "rejected");
+        assert_eq!(report.properties[0].reason.as_deref(), Some("readOnly"));
+    }
+
+    #[test]
+    fn redact_keywords_keeps_structure() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "token": { "type": "string", "default": "sk_live_123", "examples": ["sk_x"] },
+                "default": { "type": "string", "default": "x" },
+                "mode": { "enum": ["a", "b"], "const": "a" }
+            },
+            "required": ["token"],
+            "default": { "token": "sk_live_123" }
+        });
+        let keywords = vec!["default".to_string(), "examples".to_string()];
+
+        let result = redact_keywords(&schema, &keywords);
+        assert_eq!(
+            result,
+            json!({
+                "type": "object",
+                "properties": {
+                    "token": { "type": "string" },
+                    "default": { "type": "string" },
+                    "mode": { "enum": ["a", "b"], "const": "a" }
+                },
+                "required": ["token"]
+            })
+        );
+    }
+
+    #[test]
+    fn close_allof_branches_selected_only() {
+        let branch = json!({
+            "type": "object",
+            "properties": {
+                "nested": { "type": "object", "properties": { "a": { "type": "string" } } }
+            }
+        });
+        let mut schema = json!({ "allOf": [branch.clone(), branch.clone()] });
+
+        close_allof_branches(&mut schema, &[0]);
+        assert_eq!(
+            schema["allOf"][0]["properties"]["nested"]["additionalProperties"],
+            json!(false)
+        );
+        assert!(schema["allOf"][1]["properties"]["nested"]
+            .get("additionalProperties")
+            .is_none());
+        assert!(schema.get("unevaluatedProperties").is_none());
+
+        // Every branch selected is plain strict mode
+        let mut schema = json!({ "allOf": [branch.clone(), branch] });
+        close_allof_branches(&mut schema, &[0, 1]);
+        assert_eq!(schema["unevaluatedProperties"], json!(false));
+    }
+
+    #[test]
+    fn find_stray_annotations_outside_properties() {
+        let schema = json!({
+            "type": "object",
+            "ucp_request": "omit",
+            "properties": {
+                "id": { "type": "string", "ucp_request": "omit" },
+                "tags": {
+                    "type": "array",
+                    "items": { "type": "string", "ucp_response": "required" }
+                }
+            },
+            "$defs": {
+                "buyer": { "type": "object", "ucp_request": "optional" }
+            },
+            "examples": [{ "ucp_request": "data, not an annotation" }]
+        });
+
+        assert_eq!(
+            find_stray_annotations(&schema),
+            [
+                "/ucp_request",
+                "/properties/tags/items/ucp_response",
+                "/$defs/buyer/ucp_request"
+            ]
+        );
+    }
+
     #[test]
     fn resolve_strips_annotations() {
         let schema = json!({
@@ -1071,4 +2489,109 @@ mod tests {
         assert!(result["properties"]["id"].get("ucp_request").is_none());
         assert!(result["properties"]["id"].get("ucp_response").is_none());
     }
+
+    #[test]
+    fn resolve_conditional_branches_inherit_visibility() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "kind": { "type": "string" },
+                "tax_id": { "type": "string", "ucp_request": "omit" },
+                "card": { "type": "string" }
+            },
+            "if": { "required": ["tax_id"] },
+            "then": {
+                "required": ["tax_id", "kind"],
+                "properties": { "tax_id": { "minLength": 5 }, "kind": { "ucp_request": "omit" } }
+            },
+            "dependentSchemas": {
+                "card": { "properties": { "tax_id": { "type": "string" } }, "required": ["tax_id"] }
+            }
+        });
+        let mut options = ResolveOptions::new(Direction::Request, "create");
+        options.strict = true;
+        let result = resolve(&schema, &options).unwrap();
+
+        // The test keeps its shape; branches drop the omitted field
+        assert_eq!(result["if"], json!({ "required": ["tax_id"] }));
+        assert_eq!(result["then"], json!({ "required": [], "properties": {} }));
+        assert_eq!(
+            result["dependentSchemas"]["card"],
+            json!({ "properties": {}, "required": [] })
+        );
+        // Closed across the branches rather than per schema
+        assert_eq!(result["unevaluatedProperties"], json!(false));
+        assert!(result.get("additionalProperties").is_none());
+        assert!(result["then"].get("additionalProperties").is_none());
+    }
+
+    #[test]
+    fn resolve_expands_operation_groups() {
+        let schema = json!({
+            "x-ucp-operation-groups": { "write": ["create", "update", "delete"] },
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "ucp_request": { "write": "omit", "read": "required" } },
+                "note": { "type": "string", "ucp_request": { "write": "required" } }
+            }
+        });
+        for op in ["create", "delete"] {
+            let result = resolve(&schema, &ResolveOptions::new(Direction::Request, op)).unwrap();
+            assert!(result["properties"].get("id").is_none());
+            assert_eq!(result["required"], json!(["note"]));
+        }
+        let read = resolve(&schema, &ResolveOptions::new(Direction::Request, "read")).unwrap();
+        assert_eq!(read["required"], json!(["id"]));
+        assert_eq!(
+            annotated_operations(&schema),
+            ["create", "update", "delete", "read"]
+        );
+
+        let mut overlap = schema.clone();
+        overlap["properties"]["id"]["ucp_request"]["update"] = json!("optional");
+        let err = resolve(&overlap, &ResolveOptions::new(Direction::Request, "read")).unwrap_err();
+        assert!(matches!(
+            err,
+            ResolveError::InvalidOperationGroup { ref path, .. }
+                if path == "/properties/id/ucp_request/write"
+        ));
+        assert!(err.to_string().contains("\"update\" is set both directly"));
+
+        let mut malformed = schema;
+        malformed["x-ucp-operation-groups"] = json!({ "create": ["update"] });
+        assert!(resolve(&malformed, &ResolveOptions::new(Direction::Request, "read")).is_err());
+    }
+
+    #[test]
+    fn declared_operations_adds_custom_ops() {
+        let schema = json!({
+            "properties": {
+                "id": { "ucp_request": { "update": "required", "refund": "omit" } },
+                "status": { "enum": [{ "ucp_request": { "bogus": "omit" } }] }
+            }
+        });
+        assert_eq!(
+            declared_operations(&schema),
+            ["create", "update", "complete", "read", "refund"]
+        );
+    }
+
+    #[test]
+    fn annotated_operations_include_declared_ops() {
+        let schema = json!({
+            "x-ucp-operations": ["cancel", "create"],
+            "properties": {
+                "id": { "ucp_request": { "create": "omit", "refund": "required" } },
+                "note": { "ucp_response": "omit" }
+            }
+        });
+        assert_eq!(
+            annotated_operations(&schema),
+            ["cancel", "create", "refund"]
+        );
+        assert_eq!(
+            annotated_operations(&json!({ "properties": { "note": { "ucp_response": "omit" } } })),
+            VALID_OPERATIONS
+        );
+    }
 }
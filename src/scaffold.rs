@@ -0,0 +1,206 @@
+//! Project scaffolding for `ucp-schema init`.
+//!
+//! Lays out a capability the way the composer expects to find it: schema URLs
+//! under `https://example.com/schemas/` map to `schemas/` with the project
+//! directory as `--schema-local-base`, so the example payloads validate in
+//! self-describing mode with no further flags.
+
+use std::path::PathBuf;
+
+use serde_json::{json, Value};
+
+use crate::library::pascal_case;
+
+/// Host the scaffolded schema and profile URLs live under.
+const BASE_URL: &str = "https://example.com";
+
+/// A file to create, relative to the project directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScaffoldFile {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+/// Files for a new capability project named `name` (e.g., "loyalty").
+///
+/// The capability is `{namespace}.{name}` with `-` mapped to `_`, e.g.
+/// `com.example.gift_card` for "gift-card".
+pub fn scaffold(name: &str, namespace: &str) -> Vec<ScaffoldFile> {
+    let key = name.replace('-', "_");
+    let capability = format!("{}.{}", namespace, key);
+    let title = pascal_case(name);
+    let schema_url = format!("{}/schemas/{}.json", BASE_URL, key);
+    let profile_url = format!("{}/profiles/agent.json", BASE_URL);
+    let declaration = json!({
+        capability.clone(): [{ "version": "2026-01-01", "schema": schema_url }]
+    });
+
+    let schema = json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": schema_url,
+        "name": capability,
+        "version": "2026-01-01",
+        "title": title,
+        "description": format!("{} capability.", title),
+        "type": "object",
+        "properties": {
+            "id": {
+                "type": "string",
+                "description": "Assigned by the server; required to address an existing resource.",
+                "ucp_request": { "create": "omit", "update": "required" },
+                "ucp_response": "required"
+            },
+            "status": {
+                "type": "string",
+                "enum": ["open", "closed"],
+                "description": "Server-managed state.",
+                "ucp_request": "omit",
+                "ucp_response": "required"
+            },
+            "line_items": {
+                "type": "array",
+                "items": { "$ref": "types/line_item.json" },
+                "ucp_request": { "create": "required", "update": "optional" },
+                "ucp_response": "required"
+            },
+            "note": {
+                "type": "string",
+                "description": "Optional in every request and response."
+            },
+            "ucp": {
+                "type": "object",
+                "additionalProperties": true,
+                "description": "UCP metadata block",
+                "ucp_request": "omit"
+            }
+        }
+    });
+
+    let line_item = json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": format!("{}/schemas/types/line_item.json", BASE_URL),
+        "title": "Line Item",
+        "type": "object",
+        "properties": {
+            "sku": { "type": "string" },
+            "quantity": { "type": "integer", "minimum": 1 }
+        },
+        "required": ["sku", "quantity"]
+    });
+
+    let profile = json!({ "ucp": { "version": "2026-01-01", "capabilities": declaration } });
+    let request = |body: Value| json!({ "meta": { "profile": profile_url }, key.clone(): body });
+    let response = |body: Value| {
+        let mut payload = json!({ "ucp": { "capabilities": declaration } });
+        if let (Some(payload), Value::Object(body)) = (payload.as_object_mut(), body) {
+            payload.extend(body);
+        }
+        payload
+    };
+
+    let files = [
+        (format!("schemas/{}.json", key), schema),
+        ("schemas/types/line_item.json".to_string(), line_item),
+        ("profiles/agent.json".to_string(), profile),
+        (
+            "payloads/valid/create_request.json".to_string(),
+            request(json!({ "line_items": [{ "sku": "SKU-1", "quantity": 2 }] })),
+        ),
+        (
+            "payloads/valid/read_response.json".to_string(),
+            response(json!({
+                "id": "res_123",
+                "status": "open",
+                "line_items": [{ "sku": "SKU-1", "quantity": 2 }]
+            })),
+        ),
+        (
+            // line_items is required on create
+            "payloads/invalid/create_request.json".to_string(),
+            request(json!({ "note": "missing line_items" })),
+        ),
+        (
+            // status outside its enum, quantity below its minimum
+            "payloads/invalid/read_response.json".to_string(),
+            response(json!({
+                "id": "res_123",
+                "status": "pending",
+                "line_items": [{ "sku": "SKU-1", "quantity": 0 }]
+            })),
+        ),
+    ];
+
+    let mut out: Vec<ScaffoldFile> = files
+        .into_iter()
+        .map(|(path, value)| ScaffoldFile {
+            path: PathBuf::from(path),
+            content: serde_json::to_string_pretty(&value).unwrap_or_default() + "\n",
+        })
+        .collect();
+    out.push(ScaffoldFile {
+        path: PathBuf::from("README.md"),
+        content: readme(&title, &capability, &key),
+    });
+    out
+}
+
+fn readme(title: &str, capability: &str, key: &str) -> String {
+    format!(
+        "# {title}
+
+UCP capability `{capability}`.
+
+- `schemas/{key}.json`: the capability schema, with `ucp_request`/`ucp_response` annotations
+- `schemas/types/`: shared types referenced by relative `$ref`
+- `profiles/agent.json`: a profile declaring the capability
+- `payloads/valid/`, `payloads/invalid/`: example payloads that should pass and fail
+
+Schema and profile URLs under {base} map to this directory, so run commands
+from here with `--schema-local-base .`:
+
+```bash
+ucp-schema lint schemas/
+ucp-schema resolve schemas/{key}.json --request --op create --bundle --pretty
+ucp-schema validate payloads/valid/create_request.json --schema-local-base . --op create
+ucp-schema validate payloads/valid/read_response.json --schema-local-base . --op read
+ucp-schema validate payloads/invalid/read_response.json --schema-local-base . --op read  # exits 1
+```
+
+When publishing, replace {base} in the `$id`, profile and payload URLs
+with the real host.
+",
+        base = BASE_URL,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaffold_names_files_after_capability() {
+        let files = scaffold("gift-card", "com.acme");
+        let paths: Vec<&str> = files.iter().map(|f| f.path.to_str().unwrap()).collect();
+        assert_eq!(
+            paths,
+            [
+                "schemas/gift_card.json",
+                "schemas/types/line_item.json",
+                "profiles/agent.json",
+                "payloads/valid/create_request.json",
+                "payloads/valid/read_response.json",
+                "payloads/invalid/create_request.json",
+                "payloads/invalid/read_response.json",
+                "README.md"
+            ]
+        );
+
+        let schema: Value = serde_json::from_str(&files[0].content).unwrap();
+        assert_eq!(schema["name"], "com.acme.gift_card");
+        assert_eq!(schema["title"], "GiftCard");
+        assert_eq!(schema["$id"], "https://example.com/schemas/gift_card.json");
+
+        let request: Value = serde_json::from_str(&files[3].content).unwrap();
+        assert!(request["gift_card"]["line_items"].is_array());
+    }
+}
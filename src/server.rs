@@ -0,0 +1,1050 @@
+//! HTTP validation service behind `ucp-schema serve`.
+//!
+//! A small HTTP/1.1 server on `std::net`: a fixed pool of worker threads
+//! each serves one keep-alive connection at a time. Endpoints take a JSON body plus query parameters mirroring the
+//! CLI flags and return the CLI's JSON output:
+//!
+//! - `POST /validate?op=create[&direction=request][&strict=true][&schema=...]`
+//! - `POST /resolve?op=create&direction=request[&strict=true][&schema=...]`
+//! - `POST /compose` (body: a self-describing payload)
+//! - `GET /health`
+//!
+//! Schemas and compiled validators are kept in bounded LRU caches, keyed by
+//! schema source (or declared capabilities) and direction/op/strict.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use lru::LruCache;
+use serde_json::{json, Value};
+
+use crate::compose::{
+    compose_schema_with_options, detect_direction, extract_capabilities, extract_jsonrpc_payload,
+    parse_capabilities_object, Capability, ComposeOptions, DetectedDirection, SchemaBaseConfig,
+    UrlMapping,
+};
+use crate::error::{ComposeError, ResolveError, ValidateError};
+use crate::loader::{bundle_refs_within, is_url, load_schema, load_schema_auto, FetchOptions};
+use crate::resolver::resolve;
+use crate::types::{Direction, ResolveOptions};
+use crate::validator::CompiledValidator;
+
+/// Largest request body accepted, in bytes.
+const MAX_BODY: usize = 16 * 1024 * 1024;
+
+/// Longest request line or header line accepted, in bytes.
+const MAX_LINE: usize = 8 * 1024;
+
+/// Connection worker threads by default.
+pub const DEFAULT_WORKERS: usize = 16;
+
+/// Default time a read or write on a connection may block before it's
+/// closed, which also ends idle keep-alive connections.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Schemas, and separately compiled validators, kept by default.
+pub const DEFAULT_CACHE_SIZE: usize = 256;
+
+/// Where the server finds capability schemas.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Local directory containing schema files; `schema=` paths are relative to
+    /// it, and without one `schema=` only takes URLs.
+    pub local_base: Option<PathBuf>,
+    /// URL prefix to strip when mapping to local paths.
+    pub remote_base: Option<String>,
+    /// URL prefix to local directory mappings, tried in order.
+    pub mappings: Vec<UrlMapping>,
+    /// Let `schema=`, and the capability schemas and profiles a payload
+    /// declares, name URLs for the server to fetch. Off by default, since any
+    /// client could otherwise make the server request arbitrary URLs.
+    pub allow_remote_schemas: bool,
+    /// How remote schemas and profiles are fetched.
+    pub fetch: FetchOptions,
+    /// Most schemas, and separately most compiled validators, kept in memory;
+    /// the least recently used are evicted beyond it.
+    pub cache_size: usize,
+    /// Connections served at once; further ones wait to be accepted.
+    pub workers: usize,
+    /// Longest a read or write on a connection may block.
+    pub timeout: Duration,
+    /// Log each request to stderr.
+    pub verbose: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            local_base: None,
+            remote_base: None,
+            mappings: Vec::new(),
+            allow_remote_schemas: false,
+            fetch: FetchOptions::new(),
+            cache_size: DEFAULT_CACHE_SIZE,
+            workers: DEFAULT_WORKERS,
+            timeout: DEFAULT_TIMEOUT,
+            verbose: false,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Create a config with no local base (schemas are fetched by URL).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the local schema directory.
+    pub fn local_base(mut self, local_base: impl Into<PathBuf>) -> Self {
+        self.local_base = Some(local_base.into());
+        self
+    }
+
+    /// Set the URL prefix stripped when mapping to local paths.
+    pub fn remote_base(mut self, remote_base: impl Into<String>) -> Self {
+        self.remote_base = Some(remote_base.into());
+        self
+    }
+
+    /// Set URL prefix to local directory mappings.
+    pub fn mappings(mut self, mappings: Vec<UrlMapping>) -> Self {
+        self.mappings = mappings;
+        self
+    }
+
+    /// Let requests name URLs for the server to fetch.
+    pub fn allow_remote_schemas(mut self, allow: bool) -> Self {
+        self.allow_remote_schemas = allow;
+        self
+    }
+
+    /// Set how remote schemas and profiles are fetched.
+    pub fn fetch(mut self, fetch: FetchOptions) -> Self {
+        self.fetch = fetch;
+        self
+    }
+
+    /// Set how many schemas and compiled validators are kept (at least 1).
+    pub fn cache_size(mut self, cache_size: usize) -> Self {
+        self.cache_size = cache_size;
+        self
+    }
+
+    /// Set how many connections are served at once (at least 1).
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Set how long a read or write on a connection may block.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Log each request to stderr.
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+}
+
+/// HTTP status and JSON body of a handled request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    /// The CLI's `--json` error shape, with a status for the CLI exit code.
+    fn error(exit_code: i32, message: impl std::fmt::Display) -> Self {
+        Self {
+            status: match exit_code {
+                3 => 502,
+                _ => 400,
+            },
+            body: json!({
+                "valid": false,
+                "errors": [{ "path": "", "message": message.to_string() }]
+            }),
+        }
+    }
+}
+
+impl From<ResolveError> for Response {
+    fn from(e: ResolveError) -> Self {
+        Response::error(e.exit_code(), e)
+    }
+}
+
+impl From<ComposeError> for Response {
+    fn from(e: ComposeError) -> Self {
+        Response::error(e.exit_code(), e)
+    }
+}
+
+/// A schema ready for resolution, with the capabilities it was composed from.
+struct SourceSchema {
+    schema: Value,
+    capabilities: Vec<Capability>,
+}
+
+type ValidatorKey = (String, Direction, String, bool);
+
+/// Validation service with schema and validator caches.
+pub struct Server {
+    config: ServerConfig,
+    /// `config.fetch`, with network access disabled unless remote schemas
+    /// are allowed, for the URLs payloads declare.
+    compose_fetch: FetchOptions,
+    schemas: Mutex<LruCache<String, Arc<SourceSchema>>>,
+    validators: Mutex<LruCache<ValidatorKey, Arc<CompiledValidator>>>,
+}
+
+impl Server {
+    /// Create a server with empty caches.
+    pub fn new(config: ServerConfig) -> Self {
+        let size = NonZeroUsize::new(config.cache_size).unwrap_or(NonZeroUsize::MIN);
+        let compose_fetch = config
+            .fetch
+            .clone()
+            .network_disabled(config.fetch.network_disabled || !config.allow_remote_schemas);
+        Self {
+            config,
+            compose_fetch,
+            schemas: Mutex::new(LruCache::new(size)),
+            validators: Mutex::new(LruCache::new(size)),
+        }
+    }
+
+    /// Accept connections until the listener fails, handing each to a pool of
+    /// `workers` threads. When every worker is busy and as many connections
+    /// are queued, accepting pauses until one frees up.
+    ///
+    /// # Errors
+    ///
+    /// Returns the listener's IO error.
+    pub fn serve(self: Arc<Self>, listener: TcpListener) -> std::io::Result<()> {
+        let workers = self.config.workers.max(1);
+        let (sender, receiver) = mpsc::sync_channel::<TcpStream>(workers);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..workers {
+            let server = Arc::clone(&self);
+            let receiver = Arc::clone(&receiver);
+            std::thread::spawn(move || loop {
+                // Statement scope: the lock is released once a connection is taken
+                let Ok(stream) = lock(&receiver).recv() else {
+                    return;
+                };
+                let result =
+                    panic::catch_unwind(AssertUnwindSafe(|| server.handle_connection(stream)));
+                // A panicked request has printed its message; the worker carries on
+                if let Ok(Err(e)) = result {
+                    if server.config.verbose {
+                        eprintln!("[serve] connection error: {}", e);
+                    }
+                }
+            });
+        }
+
+        for stream in listener.incoming() {
+            if sender.send(stream?).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Route one request. `target` is the request path with its query string.
+    pub fn handle(&self, method: &str, target: &str, body: &[u8]) -> Response {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let params = parse_query(query);
+        let param = |name: &str| params.get(name).map(String::as_str);
+
+        let route = match path {
+            "/health" => "GET",
+            "/validate" | "/resolve" | "/compose" => "POST",
+            _ => {
+                return Response {
+                    status: 404,
+                    body: json!({ "error": format!("no endpoint {}", path) }),
+                }
+            }
+        };
+        if method != route {
+            return Response {
+                status: 405,
+                body: json!({ "error": format!("{} requires {}", path, route) }),
+            };
+        }
+
+        let strict = match param("strict") {
+            None | Some("false") => false,
+            Some("true") => true,
+            Some(other) => {
+                return Response::error(2, format!("strict must be true or false, got {}", other))
+            }
+        };
+        let direction = match param("direction") {
+            None => None,
+            Some("request") => Some(Direction::Request),
+            Some("response") => Some(Direction::Response),
+            Some(other) => {
+                return Response::error(
+                    2,
+                    format!("direction must be request or response, got {}", other),
+                )
+            }
+        };
+
+        let result = match path {
+            "/health" => Ok(Response::ok(json!({ "status": "ok" }))),
+            "/validate" => parse_body(body).and_then(|payload| {
+                let op = param("op").ok_or_else(|| missing("op"))?;
+                self.validate(&payload, param("schema"), direction, op, strict)
+            }),
+            "/resolve" => (|| {
+                let op = param("op").ok_or_else(|| missing("op"))?;
+                let direction = direction.ok_or_else(|| missing("direction"))?;
+                let schema = match param("schema") {
+                    Some(source) => self.load_cached(source)?.schema.clone(),
+                    None => parse_body(body)?,
+                };
+                let options = ResolveOptions::new(direction, op).strict(strict);
+                Ok(Response::ok(resolve(&schema, &options)?))
+            })(),
+            _ => parse_body(body).and_then(|payload| {
+                let source = self.compose_cached(&payload)?;
+                Ok(Response::ok(source.schema.clone()))
+            }),
+        };
+        result.unwrap_or_else(|e| e)
+    }
+
+    fn validate(
+        &self,
+        payload: &Value,
+        schema_source: Option<&str>,
+        direction: Option<Direction>,
+        op: &str,
+        strict: bool,
+    ) -> Result<Response, Response> {
+        let detected = detect_direction(payload);
+        let (key, source, instance, direction) = match (schema_source, detected) {
+            (Some(source), _) => {
+                let direction = direction
+                    .or(detected.map(Direction::from))
+                    .unwrap_or(Direction::Request);
+                let schema = self.load_cached(source)?;
+                (format!("schema:{}", source), schema, payload, direction)
+            }
+            (None, Some(DetectedDirection::Response)) => {
+                let schema = self.compose_cached(payload)?;
+                let direction = direction.unwrap_or(Direction::Response);
+                (compose_key(payload), schema, payload, direction)
+            }
+            (None, Some(DetectedDirection::Request)) => {
+                let schema = self.compose_cached(payload)?;
+                let (nested, _) = extract_jsonrpc_payload(payload, &schema.capabilities)?;
+                let direction = direction.unwrap_or(Direction::Request);
+                (compose_key(payload), schema, nested, direction)
+            }
+            (None, None) => {
+                return Err(Response::error(
+                    2,
+                    "cannot infer direction: payload has no ucp.capabilities (response) or meta.profile (request). Pass schema=, or send a self-describing payload.",
+                ))
+            }
+        };
+
+        let op = op.to_lowercase();
+        let validator = cached(
+            &self.validators,
+            (key, direction, op.clone(), strict),
+            || {
+                let options = ResolveOptions::new(direction, &op).strict(strict);
+                CompiledValidator::new(&source.schema, &options)
+            },
+        )
+        .map_err(|e| match e {
+            ValidateError::Resolve(e) => Response::from(e),
+            ValidateError::Invalid { .. } => Response::error(2, e),
+        })?;
+
+        Ok(match validator.validate(instance) {
+            Ok(()) => Response::ok(json!({ "valid": true })),
+            Err(ValidateError::Invalid { errors }) => Response {
+                status: 422,
+                body: json!({ "valid": false, "errors": errors }),
+            },
+            Err(ValidateError::Resolve(e)) => Response::from(e),
+        })
+    }
+
+    /// Load and bundle an explicit schema source, once per source.
+    fn load_cached(&self, source: &str) -> Result<Arc<SourceSchema>, Response> {
+        cached(&self.schemas, format!("schema:{}", source), || {
+            if self.config.verbose {
+                eprintln!("[serve] loading {}", source);
+            }
+            let schema = if is_url(source) {
+                if !self.config.allow_remote_schemas {
+                    return Err(ResolveError::InvalidSchema {
+                        message: format!(
+                            "schema {} is a URL; the server only fetches schemas with --allow-remote-schemas",
+                            source
+                        ),
+                    });
+                }
+                #[cfg(feature = "remote")]
+                {
+                    let mut schema = load_schema_auto(source, &self.config.fetch)?;
+                    crate::loader::bundle_refs_remote(&mut schema, source, &self.config.fetch)?;
+                    schema
+                }
+                #[cfg(not(feature = "remote"))]
+                load_schema_auto(source, &self.config.fetch)?
+            } else {
+                // Requests may only name files under the local base
+                let Some(base) = &self.config.local_base else {
+                    return Err(ResolveError::InvalidSchema {
+                        message: format!(
+                            "schema {} is a path, but the server has no --schema-local-base to read it from",
+                            source
+                        ),
+                    });
+                };
+                let outside = || ResolveError::InvalidSchema {
+                    message: format!("schema {} is outside --schema-local-base", source),
+                };
+                let relative = Path::new(source);
+                if relative.is_absolute()
+                    || relative.components().any(|c| c == Component::ParentDir)
+                {
+                    return Err(outside());
+                }
+                // Symlinks mustn't lead out of the base either, for the
+                // schema or anything it references
+                let root = base
+                    .canonicalize()
+                    .map_err(|source| ResolveError::ReadError {
+                        path: base.clone(),
+                        source,
+                    })?;
+                let path = base.join(relative);
+                if let Ok(real) = path.canonicalize() {
+                    if !real.starts_with(&root) {
+                        return Err(outside());
+                    }
+                }
+                let mut schema = load_schema(&path)?;
+                bundle_refs_within(
+                    &mut schema,
+                    path.parent().unwrap_or(Path::new(".")),
+                    &root,
+                )?;
+                schema
+            };
+            Ok::<_, ResolveError>(SourceSchema {
+                schema,
+                capabilities: Vec::new(),
+            })
+        })
+        .map_err(Response::from)
+    }
+
+    /// Where payload-declared schemas are loaded from: only files under the
+    /// configured bases, and URLs only with `allow_remote_schemas`.
+    fn schema_base(&self) -> SchemaBaseConfig<'_> {
+        SchemaBaseConfig {
+            local_base: self.config.local_base.as_deref(),
+            remote_base: self.config.remote_base.as_deref(),
+            mappings: &self.config.mappings,
+            fetch: &self.compose_fetch,
+            confine: true,
+        }
+    }
+
+    /// Compose the schema a self-describing payload declares, once per declaration.
+    fn compose_cached(&self, payload: &Value) -> Result<Arc<SourceSchema>, Response> {
+        cached(&self.schemas, compose_key(payload), || {
+            let config = self.schema_base();
+            let capabilities = extract_capabilities(payload, &config)?;
+            if self.config.verbose {
+                eprintln!("[serve] composing {} capabilities", capabilities.len());
+            }
+            let composed =
+                compose_schema_with_options(&capabilities, &config, &ComposeOptions::new())?;
+            for warning in &composed.warnings {
+                eprintln!("Warning: {}", warning);
+            }
+            Ok::<_, ComposeError>(SourceSchema {
+                schema: composed.schema,
+                capabilities,
+            })
+        })
+        .map_err(Response::from)
+    }
+
+    fn handle_connection(&self, stream: TcpStream) -> std::io::Result<()> {
+        stream.set_read_timeout(Some(self.config.timeout))?;
+        stream.set_write_timeout(Some(self.config.timeout))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = stream;
+
+        loop {
+            let mut request_line = String::new();
+            match read_line(&mut reader, &mut request_line)? {
+                Line::End => return Ok(()),
+                Line::TooLong => {
+                    let response = line_too_long(414, "request line");
+                    return write_response(&mut writer, &response, false);
+                }
+                Line::Read => {}
+            }
+            let mut parts = request_line.split_whitespace();
+            let (Some(method), Some(target), Some(version)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                return write_response(&mut writer, &bad_request("malformed request line"), false);
+            };
+            let (method, target) = (method.to_string(), target.to_string());
+
+            let mut content_length = 0;
+            let mut keep_alive = version == "HTTP/1.1";
+            loop {
+                let mut line = String::new();
+                match read_line(&mut reader, &mut line)? {
+                    Line::End => return Ok(()),
+                    Line::TooLong => {
+                        let response = line_too_long(431, "header line");
+                        return write_response(&mut writer, &response, false);
+                    }
+                    Line::Read => {}
+                }
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                let Some((name, value)) = line.split_once(':') else {
+                    continue;
+                };
+                let value = value.trim();
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = match value.parse() {
+                        Ok(n) => n,
+                        Err(_) => {
+                            let response = bad_request("invalid Content-Length");
+                            return write_response(&mut writer, &response, false);
+                        }
+                    };
+                } else if name.eq_ignore_ascii_case("connection") {
+                    keep_alive = !value.eq_ignore_ascii_case("close");
+                }
+            }
+            if content_length > MAX_BODY {
+                let response = Response {
+                    status: 413,
+                    body: json!({ "error": format!("body exceeds {} bytes", MAX_BODY) }),
+                };
+                return write_response(&mut writer, &response, false);
+            }
+
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+            let response = self.handle(&method, &target, &body);
+            if self.config.verbose {
+                eprintln!("[serve] {} {} -> {}", method, target, response.status);
+            }
+            write_response(&mut writer, &response, keep_alive)?;
+            if !keep_alive {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Get `key` from `cache`, building and inserting it on a miss, which evicts
+/// the least recently used entry when the cache is full.
+///
+/// The lock isn't held while building, so concurrent misses may build twice;
+/// the first insert wins.
+fn cached<K: Eq + Hash, V, E>(
+    cache: &Mutex<LruCache<K, Arc<V>>>,
+    key: K,
+    build: impl FnOnce() -> Result<V, E>,
+) -> Result<Arc<V>, E> {
+    if let Some(hit) = lock(cache).get(&key) {
+        return Ok(Arc::clone(hit));
+    }
+    let value = Arc::new(build()?);
+    Ok(Arc::clone(lock(cache).get_or_insert(key, || value)))
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    // A panicking request can't leave the cache half-written
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Cache key for the schema a self-describing payload declares.
+///
+/// Inline capabilities are keyed by the fields composition reads, in name
+/// order, so reordered or annotated declarations share one entry.
+fn compose_key(payload: &Value) -> String {
+    match payload.pointer("/ucp/capabilities") {
+        Some(caps) => match parse_capabilities_object(caps) {
+            Ok(mut capabilities) => {
+                capabilities.sort_by(|a, b| a.name.cmp(&b.name));
+                let normalized: Vec<_> = capabilities
+                    .iter()
+                    .map(|c| (&c.name, &c.version, &c.schema_url, &c.extends))
+                    .collect();
+                format!("capabilities:{}", json!(normalized))
+            }
+            // Never inserted: composing fails with the same error
+            Err(_) => "capabilities:".to_string(),
+        },
+        None => format!(
+            "profile:{}",
+            payload
+                .pointer("/meta/profile")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+        ),
+    }
+}
+
+fn parse_body(body: &[u8]) -> Result<Value, Response> {
+    serde_json::from_slice(body).map_err(|e| Response::error(2, format!("invalid JSON: {}", e)))
+}
+
+fn missing(param: &str) -> Response {
+    Response::error(2, format!("missing query parameter {}", param))
+}
+
+/// Outcome of reading one line of a request head.
+enum Line {
+    Read,
+    End,
+    TooLong,
+}
+
+/// Read a line into `line`, giving up once it exceeds `MAX_LINE` bytes
+/// rather than buffering it all.
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> std::io::Result<Line> {
+    let read = reader.take(MAX_LINE as u64 + 1).read_line(line)?;
+    Ok(if read == 0 {
+        Line::End
+    } else if read > MAX_LINE {
+        Line::TooLong
+    } else {
+        Line::Read
+    })
+}
+
+fn line_too_long(status: u16, what: &str) -> Response {
+    Response {
+        status,
+        body: json!({ "error": format!("{} exceeds {} bytes", what, MAX_LINE) }),
+    }
+}
+
+fn bad_request(message: &str) -> Response {
+    Response {
+        status: 400,
+        body: json!({ "error": message }),
+    }
+}
+
+fn write_response(
+    writer: &mut TcpStream,
+    response: &Response,
+    keep_alive: bool,
+) -> std::io::Result<()> {
+    let body = response.body.to_string();
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        414 => "URI Too Long",
+        422 => "Unprocessable Entity",
+        431 => "Request Header Fields Too Large",
+        502 => "Bad Gateway",
+        _ => "Error",
+    };
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: {}\r\n\r\n{}",
+        response.status,
+        reason,
+        body.len(),
+        if keep_alive { "keep-alive" } else { "close" },
+        body
+    )?;
+    writer.flush()
+}
+
+/// Parse `a=1&b=two` with percent-decoding; later duplicates win.
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(name), percent_decode(value))
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(local_base: &str) -> Server {
+        Server::new(ServerConfig::new().local_base(local_base))
+    }
+
+    fn body(value: Value) -> Vec<u8> {
+        value.to_string().into_bytes()
+    }
+
+    #[test]
+    fn handle_routes_and_methods() {
+        let server = server("tests/fixtures");
+        assert_eq!(
+            server.handle("GET", "/health", b""),
+            Response::ok(json!({ "status": "ok" }))
+        );
+        assert_eq!(server.handle("GET", "/nope", b"").status, 404);
+        assert_eq!(server.handle("GET", "/validate", b"").status, 405);
+        assert_eq!(
+            server.handle("POST", "/validate?op=create", b"{").status,
+            400
+        );
+    }
+
+    #[test]
+    fn validate_with_schema_param_caches_validator() {
+        let server = server("tests/fixtures");
+        let target = "/validate?op=create&direction=request&schema=checkout.json";
+
+        let valid = json!({ "line_items": [{ "sku": "A", "quantity": 1 }] });
+        assert_eq!(
+            server.handle("POST", target, &body(valid)),
+            Response::ok(json!({ "valid": true }))
+        );
+
+        let invalid = json!({ "id": "chk_1", "line_items": [] });
+        let response = server.handle("POST", target, &body(invalid));
+        assert_eq!(response.status, 422);
+        assert_eq!(response.body["valid"], false);
+        assert!(!response.body["errors"].as_array().unwrap().is_empty());
+
+        assert_eq!(lock(&server.schemas).len(), 1);
+        assert_eq!(lock(&server.validators).len(), 1);
+    }
+
+    #[test]
+    fn caches_evict_least_recently_used() {
+        let server = Server::new(
+            ServerConfig::new()
+                .local_base("tests/fixtures")
+                .cache_size(1),
+        );
+        let payload = body(json!({ "line_items": [] }));
+        for schema in ["checkout.json", "monotonicity_update_only.json"] {
+            let target = format!("/validate?op=create&direction=request&schema={}", schema);
+            assert_ne!(server.handle("POST", &target, &payload).status, 400);
+        }
+        assert_eq!(lock(&server.schemas).len(), 1);
+        assert_eq!(lock(&server.validators).len(), 1);
+        assert!(lock(&server.schemas).contains("schema:monotonicity_update_only.json"));
+    }
+
+    #[test]
+    fn compose_key_ignores_declaration_order_and_extra_fields() {
+        let checkout =
+            json!([{ "version": "2026-01-11", "schema": "https://ucp.dev/checkout.json" }]);
+        let discount = json!([{
+            "version": "2026-01-11",
+            "schema": "https://ucp.dev/discount.json",
+            "extends": "dev.ucp.shopping.checkout"
+        }]);
+        let mut annotated = discount.clone();
+        annotated[0]["spec"] = json!("https://ucp.dev/specs/discount");
+
+        let a = json!({ "ucp": { "capabilities": {
+            "dev.ucp.shopping.checkout": checkout,
+            "dev.ucp.shopping.discount": discount
+        }}});
+        let b = json!({ "ucp": { "capabilities": {
+            "dev.ucp.shopping.discount": annotated,
+            "dev.ucp.shopping.checkout": checkout
+        }}});
+        assert_eq!(compose_key(&a), compose_key(&b));
+
+        let mut newer = a.clone();
+        newer["ucp"]["capabilities"]["dev.ucp.shopping.checkout"][0]["version"] =
+            json!("2026-02-01");
+        assert_ne!(compose_key(&a), compose_key(&newer));
+    }
+
+    #[test]
+    fn validate_self_describing_response() {
+        let server = server("tests/fixtures/compose");
+        let payload: Value = serde_json::from_str(
+            &std::fs::read_to_string("tests/fixtures/compose/response_with_extensions.json")
+                .unwrap(),
+        )
+        .unwrap();
+
+        let response = server.handle("POST", "/validate?op=read", &body(payload.clone()));
+        assert_eq!(response, Response::ok(json!({ "valid": true })));
+
+        let composed = server.handle("POST", "/compose", &body(payload));
+        assert_eq!(composed.status, 200);
+        assert!(composed.body["allOf"].is_array());
+        // Compose reused the schema validate composed
+        assert_eq!(lock(&server.schemas).len(), 1);
+    }
+
+    #[test]
+    fn schema_param_stays_under_local_base() {
+        let server = server("tests/fixtures");
+        let response = server.handle(
+            "POST",
+            "/resolve?op=create&direction=request&schema=..%2FCargo.toml",
+            b"",
+        );
+        assert_eq!(response.status, 400);
+        assert!(response.body["errors"][0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("outside --schema-local-base"));
+    }
+
+    #[test]
+    fn schema_param_urls_need_opt_in() {
+        let target =
+            "/resolve?op=create&direction=request&schema=http%3A%2F%2F127.0.0.1%3A1%2Fs.json";
+        let response = server("tests/fixtures").handle("POST", target, b"");
+        assert_eq!(response.status, 400);
+        assert!(response.body["errors"][0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("--allow-remote-schemas"));
+
+        // Allowed, the fetch is attempted (and fails: nothing listens there)
+        let server = Server::new(ServerConfig::new().allow_remote_schemas(true));
+        assert_eq!(server.handle("POST", target, b"").status, 502);
+    }
+
+    #[test]
+    fn schema_param_paths_need_local_base() {
+        let server = Server::new(ServerConfig::new());
+        let response = server.handle(
+            "POST",
+            "/resolve?op=create&direction=request&schema=tests%2Ffixtures%2Fcheckout.json",
+            b"",
+        );
+        assert_eq!(response.status, 400);
+        assert!(response.body["errors"][0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("no --schema-local-base"));
+    }
+
+    #[test]
+    fn schema_refs_stay_under_local_base() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let outside = Path::new("tests/fixtures/checkout.json")
+            .canonicalize()
+            .unwrap();
+        std::fs::write(
+            dir.path().join("escape.json"),
+            json!({ "$ref": outside.display().to_string() }).to_string(),
+        )
+        .unwrap();
+        let server = server(dir.path().to_str().unwrap());
+
+        let response = server.handle(
+            "POST",
+            "/resolve?op=create&direction=request&schema=escape.json",
+            b"",
+        );
+        assert_eq!(response.status, 400);
+        assert!(response.body["errors"][0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("resolves outside"));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&outside, dir.path().join("link.json")).unwrap();
+            let response = server.handle(
+                "POST",
+                "/resolve?op=create&direction=request&schema=link.json",
+                b"",
+            );
+            assert!(response.body["errors"][0]["message"]
+                .as_str()
+                .unwrap()
+                .contains("outside --schema-local-base"));
+        }
+    }
+
+    #[test]
+    fn payload_schema_urls_stay_under_local_base() {
+        let server = server("tests/fixtures/compose");
+        // Maps to tests/fixtures/checkout.json, outside the base
+        let payload = json!({ "ucp": { "capabilities": {
+            "dev.ucp.shopping.checkout": [{
+                "version": "2026-01-11",
+                "schema": "https://ucp.dev/../checkout.json"
+            }]
+        }}});
+        let response = server.handle("POST", "/compose", &body(payload));
+        assert_ne!(response.status, 200);
+        assert!(response.body.to_string().contains("resolves outside"));
+    }
+
+    #[test]
+    fn payload_schema_urls_need_opt_in() {
+        let payload = json!({ "ucp": { "capabilities": {
+            "dev.ucp.shopping.checkout": [{
+                "version": "2026-01-11",
+                "schema": "http://127.0.0.1:1/checkout.json"
+            }]
+        }}});
+        let response =
+            Server::new(ServerConfig::new()).handle("POST", "/compose", &body(payload.clone()));
+        assert!(response
+            .body
+            .to_string()
+            .contains("network access disabled"));
+
+        // Nor may they name files when there's no base to confine them to
+        let mut path = payload.clone();
+        path["ucp"]["capabilities"]["dev.ucp.shopping.checkout"][0]["schema"] =
+            json!("tests/fixtures/checkout.json");
+        let response = Server::new(ServerConfig::new()).handle("POST", "/compose", &body(path));
+        assert!(response.body.to_string().contains("no local base"));
+
+        // Allowed, the fetch is attempted (and fails: nothing listens there)
+        let server = Server::new(ServerConfig::new().allow_remote_schemas(true));
+        let response = server.handle("POST", "/compose", &body(payload));
+        assert!(!response
+            .body
+            .to_string()
+            .contains("network access disabled"));
+    }
+
+    #[test]
+    fn resolve_requires_direction() {
+        let server = server("tests/fixtures");
+        let response = server.handle("POST", "/resolve?op=create", &body(json!({})));
+        assert_eq!(response.status, 400);
+        assert!(response.body["errors"][0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("missing query parameter direction"));
+    }
+
+    #[test]
+    fn worker_pool_serves_queued_connection_after_idle_one_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let timeout = Duration::from_millis(200);
+        let server = Arc::new(Server::new(ServerConfig::new().workers(1).timeout(timeout)));
+        std::thread::spawn(move || server.serve(listener));
+
+        // Takes the only worker without ever sending a request
+        let started = std::time::Instant::now();
+        let _idle = TcpStream::connect(addr).unwrap();
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        client
+            .write_all(b"GET /health HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with(r#"{"status":"ok"}"#));
+        assert!(started.elapsed() >= timeout);
+    }
+
+    #[test]
+    fn overlong_header_lines_are_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Arc::new(Server::new(ServerConfig::new().workers(1)));
+        std::thread::spawn(move || server.serve(listener));
+
+        // Exactly one byte over, so the server reads everything sent
+        let over = |head: &str| {
+            let mut request = head.as_bytes().to_vec();
+            request.resize(head.len() + MAX_LINE + 1, b'a');
+            let mut client = TcpStream::connect(addr).unwrap();
+            client
+                .set_read_timeout(Some(Duration::from_secs(10)))
+                .unwrap();
+            client.write_all(&request).unwrap();
+            let mut response = String::new();
+            client.read_to_string(&mut response).unwrap();
+            response
+        };
+        assert!(over("").starts_with("HTTP/1.1 414 URI Too Long"));
+        assert!(over("GET /health HTTP/1.1\r\n")
+            .starts_with("HTTP/1.1 431 Request Header Fields Too Large"));
+    }
+
+    #[test]
+    fn parse_query_decodes() {
+        let params = parse_query("schema=a%2Fb.json&op=create&x=1+2&bad=%zz");
+        assert_eq!(params["schema"], "a/b.json");
+        assert_eq!(params["op"], "create");
+        assert_eq!(params["x"], "1 2");
+        assert_eq!(params["bad"], "%zz");
+    }
+}
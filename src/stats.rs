@@ -0,0 +1,197 @@
+//! Schema complexity metrics - property counts, nesting, references,
+//! annotations and resolved sizes, for tracking schema growth over time.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::ResolveError;
+use crate::linter::collect_schema_files;
+use crate::loader::load_schema;
+use crate::resolver::{declared_operations, resolve, DATA_KEYWORDS};
+use crate::types::{Direction, ResolveOptions};
+
+/// Metrics of one schema document.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SchemaStats {
+    /// Property definitions, counting nested objects, items and `$defs`.
+    pub properties: usize,
+    /// Deepest property nesting: top-level properties are at depth 1.
+    pub max_depth: usize,
+    /// `$ref`s within the document (`#...`).
+    pub local_refs: usize,
+    /// `$ref`s to other documents, by URL or relative path.
+    pub remote_refs: usize,
+    /// Schemas carrying a `ucp_request` annotation.
+    pub request_annotations: usize,
+    /// Schemas carrying a `ucp_response` annotation.
+    pub response_annotations: usize,
+    /// Size of the resolved schema per operation, in order.
+    pub resolved: Vec<ResolvedSize>,
+}
+
+/// Compact JSON size, in bytes, of a schema resolved for one operation.
+/// `$ref`s are left as they are.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResolvedSize {
+    pub operation: String,
+    pub request: usize,
+    pub response: usize,
+}
+
+/// Metrics for one file.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileStats {
+    pub file: PathBuf,
+    #[serde(flatten)]
+    pub stats: Option<SchemaStats>,
+    /// Set when the file couldn't be read, parsed or resolved.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Measure `schema`, resolving it for each of its declared operations.
+///
+/// # Errors
+///
+/// Returns `ResolveError` if the schema contains invalid annotations.
+pub fn schema_stats(schema: &Value) -> Result<SchemaStats, ResolveError> {
+    let mut stats = SchemaStats {
+        properties: 0,
+        max_depth: 0,
+        local_refs: 0,
+        remote_refs: 0,
+        request_annotations: 0,
+        response_annotations: 0,
+        resolved: Vec::new(),
+    };
+    measure(schema, 0, &mut stats);
+
+    for operation in declared_operations(schema) {
+        let size = |direction| {
+            resolve(schema, &ResolveOptions::new(direction, &operation))
+                .map(|resolved| resolved.to_string().len())
+        };
+        stats.resolved.push(ResolvedSize {
+            request: size(Direction::Request)?,
+            response: size(Direction::Response)?,
+            operation,
+        });
+    }
+    Ok(stats)
+}
+
+/// Measure every schema file under `path` (a file or directory).
+pub fn file_stats(path: &Path) -> Vec<FileStats> {
+    collect_schema_files(path)
+        .into_iter()
+        .map(|file| {
+            let stats = load_schema(&file)
+                .map_err(|e| e.to_string())
+                .and_then(|schema| schema_stats(&schema).map_err(|e| e.to_string()));
+            match stats {
+                Ok(stats) => FileStats {
+                    file,
+                    stats: Some(stats),
+                    error: None,
+                },
+                Err(message) => FileStats {
+                    file,
+                    stats: None,
+                    error: Some(message),
+                },
+            }
+        })
+        .collect()
+}
+
+/// `depth` is the nesting depth of the property `value` belongs to.
+fn measure(value: &Value, depth: usize, stats: &mut SchemaStats) {
+    let Value::Object(map) = value else {
+        if let Value::Array(items) = value {
+            for item in items {
+                measure(item, depth, stats);
+            }
+        }
+        return;
+    };
+    for (key, child) in map {
+        match key.as_str() {
+            "$ref" => match child.as_str() {
+                Some(reference) if reference.starts_with('#') => stats.local_refs += 1,
+                Some(_) => stats.remote_refs += 1,
+                None => {}
+            },
+            "ucp_request" => stats.request_annotations += 1,
+            "ucp_response" => stats.response_annotations += 1,
+            "properties" | "patternProperties" => {
+                if let Value::Object(properties) = child {
+                    stats.properties += properties.len();
+                    if !properties.is_empty() {
+                        stats.max_depth = stats.max_depth.max(depth + 1);
+                    }
+                    for property in properties.values() {
+                        measure(property, depth + 1, stats);
+                    }
+                }
+            }
+            "$defs" | "definitions" => {
+                if let Value::Object(defs) = child {
+                    for def in defs.values() {
+                        measure(def, 0, stats);
+                    }
+                }
+            }
+            key if DATA_KEYWORDS.contains(&key) => {}
+            _ => measure(child, depth, stats),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn stats_count_properties_refs_and_annotations() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "ucp_request": { "create": "omit" } },
+                "buyer": {
+                    "type": "object",
+                    "properties": {
+                        "email": { "type": "string", "ucp_response": "omit" },
+                        "address": { "$ref": "types/address.json" }
+                    }
+                },
+                "items": { "type": "array", "items": { "$ref": "#/$defs/item" } }
+            },
+            "$defs": {
+                "item": { "properties": { "sku": { "type": "string" } } }
+            },
+            "examples": [{ "properties": { "not": "counted" } }]
+        });
+        let stats = schema_stats(&schema).unwrap();
+
+        assert_eq!(stats.properties, 6);
+        assert_eq!(stats.max_depth, 2);
+        assert_eq!((stats.local_refs, stats.remote_refs), (1, 1));
+        assert_eq!(
+            (stats.request_annotations, stats.response_annotations),
+            (1, 1)
+        );
+
+        let ops: Vec<&str> = stats
+            .resolved
+            .iter()
+            .map(|r| r.operation.as_str())
+            .collect();
+        assert_eq!(ops, ["create", "update", "complete", "read"]);
+        // "id" is omitted from create requests, "email" from every response
+        assert!(stats.resolved[0].request < stats.resolved[1].request);
+        assert!(stats.resolved[3].response < stats.resolved[3].request);
+    }
+}
@@ -0,0 +1,414 @@
+//! Schema test suites - example payloads declared alongside a schema.
+//!
+//! A schema lists its examples under a root `x-ucp-tests` array, or in a
+//! sibling `<name>.tests.json` file holding the same array:
+//!
+//! ```json
+//! "x-ucp-tests": [
+//!   {
+//!     "description": "create needs line items",
+//!     "direction": "request",
+//!     "op": "create",
+//!     "valid": [{ "line_items": [] }],
+//!     "invalid": [{}]
+//!   }
+//! ]
+//! ```
+//!
+//! Each payload is validated against the schema resolved for that direction
+//! and operation; `valid` payloads must pass and `invalid` ones must fail.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::{SchemaError, ValidateError};
+use crate::junit::{junit_xml, JunitCase, JunitOutcome, JunitSuite};
+use crate::linter::collect_schema_files;
+use crate::loader::{bundle_refs, load_schema};
+use crate::types::{Direction, ResolveOptions};
+use crate::validator::CompiledValidator;
+
+/// Root keyword holding a schema's test cases.
+pub const TESTS_KEY: &str = "x-ucp-tests";
+
+/// File name suffix for test cases kept next to a schema.
+pub const TESTS_SUFFIX: &str = ".tests.json";
+
+/// Outcome of one example payload.
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseResult {
+    /// e.g. "request/create valid[0]", or the group's description in place
+    /// of "request/create".
+    pub name: String,
+    pub passed: bool,
+    /// Why the case failed, when not a validation error list
+    /// (e.g., expected failure but the payload validated).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Validation errors of a `valid` payload that failed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<SchemaError>,
+}
+
+/// Test results for one schema file.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileTests {
+    pub file: PathBuf,
+    pub passed: usize,
+    pub failed: usize,
+    /// Set when the file or its test cases couldn't be loaded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub cases: Vec<CaseResult>,
+}
+
+/// Results of running every test suite under a path.
+#[derive(Debug, Clone, Serialize)]
+pub struct TestReport {
+    pub path: PathBuf,
+    /// Schema files that declared tests.
+    pub files_checked: usize,
+    pub passed: usize,
+    pub failed: usize,
+    /// Files whose tests couldn't be run.
+    pub errors: usize,
+    pub results: Vec<FileTests>,
+}
+
+impl TestReport {
+    /// Returns true if every case passed and every suite could be run.
+    pub fn is_ok(&self) -> bool {
+        self.failed == 0 && self.errors == 0
+    }
+
+    /// Render as JUnit XML: a test suite per schema file and a test case per
+    /// example payload. A file whose tests couldn't be loaded becomes a
+    /// single errored case.
+    pub fn to_junit(&self) -> String {
+        let suites: Vec<JunitSuite> = self
+            .results
+            .iter()
+            .map(|file| {
+                let classname = file.file.display().to_string();
+                let mut cases: Vec<JunitCase> = file
+                    .cases
+                    .iter()
+                    .map(|case| JunitCase {
+                        name: case.name.clone(),
+                        classname: classname.clone(),
+                        outcome: case_outcome(case),
+                    })
+                    .collect();
+                if let Some(message) = &file.error {
+                    cases.push(JunitCase {
+                        name: TESTS_KEY.to_string(),
+                        classname: classname.clone(),
+                        outcome: JunitOutcome::Error {
+                            message: message.clone(),
+                        },
+                    });
+                }
+                JunitSuite {
+                    name: classname,
+                    cases,
+                }
+            })
+            .collect();
+        junit_xml(&self.path.display().to_string(), &suites)
+    }
+}
+
+fn case_outcome(case: &CaseResult) -> JunitOutcome {
+    if case.passed {
+        return JunitOutcome::Passed;
+    }
+    let details: Vec<String> = case
+        .message
+        .iter()
+        .cloned()
+        .chain(
+            case.errors
+                .iter()
+                .map(|e| format!("{}: {}", e.path, e.message)),
+        )
+        .collect();
+    JunitOutcome::Failure {
+        message: details.first().cloned().unwrap_or_default(),
+        details: details.join("\n"),
+    }
+}
+
+/// Run the test suites of every schema file under `path`.
+///
+/// Files without an `x-ucp-tests` block or `.tests.json` sibling are
+/// skipped. File `$ref`s are bundled relative to each schema before
+/// resolving.
+pub fn run_schema_tests(path: &Path) -> TestReport {
+    let base = if path.is_file() {
+        path.parent().unwrap_or(Path::new("."))
+    } else {
+        path
+    };
+
+    let mut results = Vec::new();
+    for file in collect_schema_files(path) {
+        let sibling = sibling_tests_path(&file);
+        let mut tests = FileTests {
+            file: file.strip_prefix(base).unwrap_or(&file).to_path_buf(),
+            passed: 0,
+            failed: 0,
+            error: None,
+            cases: Vec::new(),
+        };
+        match load_suite(&file, sibling.as_deref()) {
+            Ok(None) => continue,
+            Ok(Some((schema, groups))) => {
+                tests.cases = run_groups(&schema, &groups);
+                tests.passed = tests.cases.iter().filter(|c| c.passed).count();
+                tests.failed = tests.cases.len() - tests.passed;
+            }
+            Err(message) => tests.error = Some(message),
+        }
+        results.push(tests);
+    }
+
+    TestReport {
+        path: path.to_path_buf(),
+        files_checked: results.len(),
+        passed: results.iter().map(|r| r.passed).sum(),
+        failed: results.iter().map(|r| r.failed).sum(),
+        errors: results.iter().filter(|r| r.error.is_some()).count(),
+        results,
+    }
+}
+
+/// A group of example payloads for one direction and operation.
+struct TestGroup {
+    label: String,
+    direction: Direction,
+    op: String,
+    valid: Vec<Value>,
+    invalid: Vec<Value>,
+}
+
+fn sibling_tests_path(schema_file: &Path) -> Option<PathBuf> {
+    let stem = schema_file.file_stem()?.to_str()?;
+    let sibling = schema_file.with_file_name(format!("{}{}", stem, TESTS_SUFFIX));
+    sibling.is_file().then_some(sibling)
+}
+
+/// Load a schema (without its test block, refs bundled) and its test
+/// groups, or `None` when it declares no tests.
+fn load_suite(
+    file: &Path,
+    sibling: Option<&Path>,
+) -> Result<Option<(Value, Vec<TestGroup>)>, String> {
+    let mut schema = load_schema(file).map_err(|e| e.to_string())?;
+    let embedded = schema.as_object_mut().and_then(|map| map.remove(TESTS_KEY));
+    if embedded.is_none() && sibling.is_none() {
+        return Ok(None);
+    }
+
+    let mut groups = Vec::new();
+    if let Some(embedded) = &embedded {
+        groups.extend(parse_groups(embedded, TESTS_KEY)?);
+    }
+    if let Some(sibling) = sibling {
+        let cases = load_schema(sibling).map_err(|e| e.to_string())?;
+        let name = sibling.file_name().unwrap_or_default().to_string_lossy();
+        groups.extend(parse_groups(&cases, &name)?);
+    }
+
+    let dir = file.parent().unwrap_or(Path::new("."));
+    bundle_refs(&mut schema, dir).map_err(|e| e.to_string())?;
+    Ok(Some((schema, groups)))
+}
+
+fn parse_groups(value: &Value, source: &str) -> Result<Vec<TestGroup>, String> {
+    let Value::Array(entries) = value else {
+        return Err(format!("{} must be an array of test groups", source));
+    };
+
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let at = format!("{}[{}]", source, i);
+            let field = |name: &str| entry.get(name).and_then(Value::as_str);
+            let direction = match field("direction") {
+                Some("request") => Direction::Request,
+                Some("response") => Direction::Response,
+                _ => {
+                    return Err(format!(
+                        "{}: \"direction\" must be \"request\" or \"response\"",
+                        at
+                    ))
+                }
+            };
+            let op = field("op")
+                .filter(|op| !op.is_empty())
+                .ok_or_else(|| format!("{}: missing \"op\"", at))?
+                .to_lowercase();
+            let payloads = |name: &str| match entry.get(name) {
+                None => Ok(Vec::new()),
+                Some(Value::Array(items)) => Ok(items.clone()),
+                Some(_) => Err(format!("{}: \"{}\" must be an array of payloads", at, name)),
+            };
+            let (valid, invalid) = (payloads("valid")?, payloads("invalid")?);
+            if valid.is_empty() && invalid.is_empty() {
+                return Err(format!("{}: no \"valid\" or \"invalid\" payloads", at));
+            }
+
+            let label = match field("description") {
+                Some(description) => description.to_string(),
+                None => format!("{}/{}", direction.as_str(), op),
+            };
+            Ok(TestGroup {
+                label,
+                direction,
+                op,
+                valid,
+                invalid,
+            })
+        })
+        .collect()
+}
+
+fn run_groups(schema: &Value, groups: &[TestGroup]) -> Vec<CaseResult> {
+    let mut validators: HashMap<(Direction, &str), Result<CompiledValidator, String>> =
+        HashMap::new();
+    // Numbered per label, so groups sharing one don't repeat case names
+    let mut counts: HashMap<(&str, bool), usize> = HashMap::new();
+    let mut cases = Vec::new();
+
+    for group in groups {
+        let validator = validators
+            .entry((group.direction, group.op.as_str()))
+            .or_insert_with(|| {
+                let options = ResolveOptions::new(group.direction, &group.op);
+                CompiledValidator::new(schema, &options).map_err(|e| e.to_string())
+            });
+
+        let expectations = [(true, &group.valid), (false, &group.invalid)];
+        for (expect_valid, payloads) in expectations {
+            for payload in payloads {
+                let count = counts.entry((&group.label, expect_valid)).or_default();
+                let i = *count;
+                *count += 1;
+                let kind = if expect_valid { "valid" } else { "invalid" };
+                let mut case = CaseResult {
+                    name: format!("{} {}[{}]", group.label, kind, i),
+                    passed: false,
+                    message: None,
+                    errors: Vec::new(),
+                };
+                match (
+                    validator.as_ref().map(|v| v.validate(payload)),
+                    expect_valid,
+                ) {
+                    (Err(message), _) => case.message = Some(message.clone()),
+                    (Ok(Ok(())), true) | (Ok(Err(ValidateError::Invalid { .. })), false) => {
+                        case.passed = true
+                    }
+                    (Ok(Ok(())), false) => {
+                        case.message = Some("expected validation to fail".to_string())
+                    }
+                    (Ok(Err(ValidateError::Invalid { errors })), true) => case.errors = errors,
+                    (Ok(Err(e)), _) => case.message = Some(e.to_string()),
+                }
+                cases.push(case);
+            }
+        }
+    }
+    cases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "ucp_request": { "create": "omit", "update": "required" } },
+                "qty": { "type": "integer", "minimum": 1 }
+            },
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn run_embedded_and_sibling_tests() {
+        let dir = tempdir().unwrap();
+        let mut with_tests = schema();
+        with_tests[TESTS_KEY] = json!([
+            {
+                "direction": "request",
+                "op": "create",
+                "valid": [{ "qty": 1 }],
+                "invalid": [{ "id": "x" }, { "qty": 2 }]
+            }
+        ]);
+        fs::write(dir.path().join("order.json"), with_tests.to_string()).unwrap();
+        fs::write(
+            dir.path().join("order.tests.json"),
+            json!([
+                {
+                    "description": "update needs id",
+                    "direction": "request",
+                    "op": "Update",
+                    "valid": [{ "qty": 0 }]
+                }
+            ])
+            .to_string(),
+        )
+        .unwrap();
+        fs::write(dir.path().join("untested.json"), schema().to_string()).unwrap();
+
+        let report = run_schema_tests(dir.path());
+        assert_eq!(report.files_checked, 1);
+        assert_eq!((report.passed, report.failed, report.errors), (2, 2, 0));
+        assert!(!report.is_ok());
+
+        let cases = &report.results[0].cases;
+        let names: Vec<&str> = cases.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "request/create valid[0]",
+                "request/create invalid[0]",
+                "request/create invalid[1]",
+                "update needs id valid[0]"
+            ]
+        );
+        assert!(cases[1].passed);
+        assert_eq!(
+            cases[2].message.as_deref(),
+            Some("expected validation to fail")
+        );
+        let keywords: Vec<&str> = cases[3].errors.iter().map(|e| e.keyword.as_str()).collect();
+        assert_eq!(keywords, ["minimum", "required"]);
+    }
+
+    #[test]
+    fn malformed_tests_are_file_errors() {
+        let dir = tempdir().unwrap();
+        let mut with_tests = schema();
+        with_tests[TESTS_KEY] = json!([{ "direction": "inbound", "op": "create", "valid": [{}] }]);
+        fs::write(dir.path().join("order.json"), with_tests.to_string()).unwrap();
+
+        let report = run_schema_tests(dir.path());
+        assert_eq!(report.errors, 1);
+        assert_eq!(
+            report.results[0].error.as_deref(),
+            Some("x-ucp-tests[0]: \"direction\" must be \"request\" or \"response\"")
+        );
+    }
+}
@@ -0,0 +1,44 @@
+//! Fixtures shared by unit tests across modules.
+
+use serde_json::{json, Value};
+
+/// A small checkout capability schema: per-operation visibility on `id`, an
+/// enum, a nullable field, local `$ref`s to an object and to array items,
+/// and a free-form map.
+pub(crate) fn checkout() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": "https://ucp.dev/schemas/shopping/checkout.json",
+        "type": "object",
+        "description": "A checkout session.",
+        "properties": {
+            "id": {
+                "type": "string",
+                "description": "Unique identifier.",
+                "ucp_request": { "create": "omit", "update": "required" },
+                "ucp_response": "required"
+            },
+            "line_items": {
+                "type": "array",
+                "items": { "$ref": "#/$defs/line_item" }
+            },
+            "status": { "type": "string", "enum": ["open", "closed"] },
+            "note": { "type": ["string", "null"] },
+            "total": { "type": "integer" },
+            "buyer": { "$ref": "#/$defs/buyer" },
+            "metadata": { "type": "object" }
+        },
+        "required": ["line_items", "status"],
+        "$defs": {
+            "line_item": {
+                "type": "object",
+                "properties": { "sku": { "type": "string" } },
+                "required": ["sku"]
+            },
+            "buyer": {
+                "type": "object",
+                "properties": { "email": { "type": "string" } }
+            }
+        }
+    })
+}
@@ -18,6 +18,35 @@ pub const VALID_OPERATIONS: &[&str] = &["create", "update", "complete", "read"];
 /// UCP annotation keys.
 pub const UCP_ANNOTATIONS: &[&str] = &["ucp_request", "ucp_response"];
 
+/// Per-direction/per-operation enum narrowing annotation. Unlike the
+/// visibility annotations it applies to both directions, so it isn't listed
+/// in `UCP_ANNOTATIONS`.
+pub const UCP_ENUM: &str = "ucp_enum";
+
+/// Per-operation `description` override. Its values are text rather than
+/// visibilities, so like `UCP_ENUM` it isn't listed in `UCP_ANNOTATIONS`.
+pub const UCP_DESCRIPTION: &str = "ucp_description";
+
+/// Per-operation `propertyNames` narrowing annotation, placed on a map-like
+/// object schema rather than on a property.
+pub const UCP_PROPERTY_NAMES: &str = "ucp_property_names";
+
+/// Marks a property whose values mustn't be logged (`"ucp_sensitive": true`).
+/// It applies to the property in every direction and operation, so it isn't
+/// listed in `UCP_ANNOTATIONS`.
+pub const UCP_SENSITIVE: &str = "ucp_sensitive";
+
+/// Schema extension listing operations the schema supports, for operations
+/// no annotation names (e.g. one where every field keeps its default).
+pub const UCP_OPERATIONS: &str = "x-ucp-operations";
+
+/// Root schema extension naming groups of operations (e.g.
+/// `"write": ["create", "update"]`) that annotation objects may use as keys.
+pub const UCP_OPERATION_GROUPS: &str = "x-ucp-operation-groups";
+
+/// Keywords `ucp_property_names` may merge into `propertyNames`.
+pub const PROPERTY_NAMES_KEYWORDS: &[&str] = &["pattern", "minLength", "maxLength", "enum"];
+
 /// Returns the JSON type name for error messages.
 pub fn json_type_name(value: &Value) -> &'static str {
     match value {
@@ -41,6 +70,14 @@ pub enum Direction {
 }
 
 impl Direction {
+    /// Lowercase name of this direction ("request" or "response").
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Request => "request",
+            Direction::Response => "response",
+        }
+    }
+
     /// Returns the annotation key for this direction.
     pub fn annotation_key(&self) -> &'static str {
         match self {
@@ -49,6 +86,14 @@ impl Direction {
         }
     }
 
+    /// The other direction.
+    pub fn opposite(&self) -> Self {
+        match self {
+            Direction::Request => Direction::Response,
+            Direction::Response => Direction::Request,
+        }
+    }
+
     /// Create direction from a request flag (true = Request, false = Response).
     pub fn from_request_flag(is_request: bool) -> Self {
         if is_request {
@@ -63,6 +108,7 @@ impl Direction {
 ///
 /// Determines how a field is transformed in the output schema.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
 pub enum Visibility {
     /// No transformation - keep field as-is with original required status.
     #[default]
@@ -73,6 +119,10 @@ pub enum Visibility {
     Required,
     /// Keep field but remove from required array.
     Optional,
+    /// Server-populated: included in responses, omitted from requests.
+    ReadOnly,
+    /// Client-supplied: included in requests, omitted from responses.
+    WriteOnly,
 }
 
 impl Visibility {
@@ -84,15 +134,56 @@ impl Visibility {
             "omit" => Some(Visibility::Omit),
             "required" => Some(Visibility::Required),
             "optional" => Some(Visibility::Optional),
+            "readonly" => Some(Visibility::ReadOnly),
+            "writeonly" => Some(Visibility::WriteOnly),
             _ => None,
         }
     }
+
+    /// What this visibility means for `direction`: `readonly` and
+    /// `writeonly` become `omit` or `include`; the rest are unchanged.
+    pub fn in_direction(self, direction: Direction) -> Self {
+        match (self, direction) {
+            (Visibility::ReadOnly, Direction::Request)
+            | (Visibility::WriteOnly, Direction::Response) => Visibility::Omit,
+            (Visibility::ReadOnly, Direction::Response)
+            | (Visibility::WriteOnly, Direction::Request) => Visibility::Include,
+            (other, _) => other,
+        }
+    }
+
+    /// True for `readonly` and `writeonly`, which describe both directions.
+    pub fn is_bidirectional(self) -> bool {
+        matches!(self, Visibility::ReadOnly | Visibility::WriteOnly)
+    }
 }
 
 /// Returns true if (from, to) is a valid schema transition: both are visibility
 /// values (omit, optional, required) and from != to.
 pub fn is_valid_schema_transition(from: &str, to: &str) -> bool {
-    from != to && Visibility::parse(from).is_some() && Visibility::parse(to).is_some()
+    let transitional = |s: &str| Visibility::parse(s).is_some_and(|v| !v.is_bidirectional());
+    from != to && transitional(from) && transitional(to)
+}
+
+/// Rewrite of array-form `type` containing `"null"`, for tools that only
+/// accept a single type name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Denullify {
+    /// `["string", "null"]` becomes `type: "string", nullable: true` (OpenAPI 3.0).
+    OpenApi,
+    /// `["string", "null"]` becomes `type: "string"`.
+    DropNull,
+}
+
+impl Denullify {
+    /// Parse a mode name ("openapi" or "drop-null").
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "openapi" => Some(Denullify::OpenApi),
+            "drop-null" => Some(Denullify::DropNull),
+            _ => None,
+        }
+    }
 }
 
 /// Options for schema resolution.
@@ -106,6 +197,26 @@ pub struct ResolveOptions {
     /// When true, sets `additionalProperties: false` on all object schemas
     /// to reject unknown fields. Defaults to false to respect schema extensibility.
     pub strict: bool,
+    /// When true and resolving for requests, `readOnly: true` properties are
+    /// replaced with the `false` schema so payloads carrying them are rejected.
+    pub reject_readonly: bool,
+    /// When set, array-form `type` with a `"null"` member is rewritten to
+    /// its single non-null member. Arrays without `"null"` are left alone.
+    pub denullify: Option<Denullify>,
+    /// UCP annotation keys to copy into the output instead of stripping.
+    /// They still take effect; only their removal is skipped.
+    pub keep_annotations: Vec<String>,
+    /// When true and resolving for responses, `ucp_sensitive` properties are
+    /// omitted, for audiences that mustn't receive them.
+    pub omit_sensitive: bool,
+    /// When true, annotations propagate across `anyOf`/`oneOf` branches as
+    /// they do across `allOf` ones, for schemas whose variants are the same
+    /// entity per channel. Off by default: variants are independent.
+    pub propagate_variants: bool,
+    /// When true, UCP annotations that would be stripped are kept in the
+    /// output as `x-` extension keywords (`ucp_request` -> `x-ucp-request`),
+    /// for doc generators that want the original intent.
+    pub preserve_annotations: bool,
 }
 
 impl ResolveOptions {
@@ -119,6 +230,12 @@ impl ResolveOptions {
             direction,
             operation: operation.into().to_lowercase(),
             strict: false,
+            reject_readonly: false,
+            denullify: None,
+            keep_annotations: Vec::new(),
+            omit_sensitive: false,
+            propagate_variants: false,
+            preserve_annotations: false,
         }
     }
 
@@ -127,6 +244,76 @@ impl ResolveOptions {
         self.strict = strict;
         self
     }
+
+    /// Reject `readOnly` properties in request payloads (ignored for responses).
+    pub fn reject_readonly(mut self, reject_readonly: bool) -> Self {
+        self.reject_readonly = reject_readonly;
+        self
+    }
+
+    /// Rewrite nullable array-form types (see `Denullify`).
+    pub fn denullify(mut self, mode: Option<Denullify>) -> Self {
+        self.denullify = mode;
+        self
+    }
+
+    /// Keep these UCP annotations in the output (e.g., `ucp_request`).
+    pub fn keep_annotations(mut self, keys: Vec<String>) -> Self {
+        self.keep_annotations = keys;
+        self
+    }
+
+    /// Omit `ucp_sensitive` properties from response schemas (ignored for
+    /// requests).
+    pub fn omit_sensitive(mut self, omit_sensitive: bool) -> Self {
+        self.omit_sensitive = omit_sensitive;
+        self
+    }
+
+    /// Propagate annotations across `anyOf`/`oneOf` branches like `allOf`.
+    pub fn propagate_variants(mut self, propagate_variants: bool) -> Self {
+        self.propagate_variants = propagate_variants;
+        self
+    }
+
+    /// Keep stripped annotations as `x-ucp-request`/`x-ucp-response`.
+    pub fn preserve_annotations(mut self, preserve_annotations: bool) -> Self {
+        self.preserve_annotations = preserve_annotations;
+        self
+    }
+}
+
+/// What resolution did to one property (see `resolve_with_report`).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PropertyResolution {
+    /// Resolver path of the property (e.g., `/properties/buyer/properties/email`).
+    pub path: String,
+    /// The annotation that decided its visibility, after `allOf` propagation
+    /// and inheritance: the direction's own, or the other direction's
+    /// `readonly`/`writeonly`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotation: Option<Value>,
+    /// `omit`, `required`, `optional` or `include`; `rejected` for `readOnly`
+    /// properties replaced with `false` (`reject_readonly`).
+    pub visibility: String,
+    /// Why it was dropped regardless of its annotation (`readOnly`,
+    /// `ucp_sensitive`), if it was.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// In the object's `required` list before and after resolution.
+    pub was_required: bool,
+    pub required: bool,
+    /// The `x-ucp-schema-transition` emitted on the property, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transition: Option<SchemaTransitionInfo>,
+}
+
+/// Every property decision of one resolution, parents before their children.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResolutionReport {
+    pub direction: Direction,
+    pub operation: String,
+    pub properties: Vec<PropertyResolution>,
 }
 
 #[cfg(test)]
@@ -139,17 +326,49 @@ mod tests {
         assert_eq!(Direction::Response.annotation_key(), "ucp_response");
     }
 
+    #[test]
+    fn direction_as_str_matches_serde_name() {
+        for direction in [Direction::Request, Direction::Response] {
+            assert_eq!(serde_json::to_value(direction).unwrap(), direction.as_str());
+        }
+    }
+
     #[test]
     fn visibility_parse_valid() {
         assert_eq!(Visibility::parse("omit"), Some(Visibility::Omit));
         assert_eq!(Visibility::parse("required"), Some(Visibility::Required));
         assert_eq!(Visibility::parse("optional"), Some(Visibility::Optional));
+        assert_eq!(Visibility::parse("readonly"), Some(Visibility::ReadOnly));
+        assert_eq!(Visibility::parse("writeonly"), Some(Visibility::WriteOnly));
+    }
+
+    #[test]
+    fn visibility_in_direction() {
+        let readonly = Visibility::ReadOnly;
+        assert_eq!(readonly.in_direction(Direction::Request), Visibility::Omit);
+        assert_eq!(
+            readonly.in_direction(Direction::Response),
+            Visibility::Include
+        );
+        let writeonly = Visibility::WriteOnly;
+        assert_eq!(
+            writeonly.in_direction(Direction::Request),
+            Visibility::Include
+        );
+        assert_eq!(
+            writeonly.in_direction(Direction::Response),
+            Visibility::Omit
+        );
+        assert_eq!(
+            Visibility::Required.in_direction(Direction::Request),
+            Visibility::Required
+        );
     }
 
     #[test]
     fn visibility_parse_invalid() {
         assert_eq!(Visibility::parse("include"), None);
-        assert_eq!(Visibility::parse("readonly"), None);
+        assert_eq!(Visibility::parse("read-only"), None);
         assert_eq!(Visibility::parse(""), None);
     }
 
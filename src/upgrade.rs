@@ -0,0 +1,228 @@
+//! Annotation syntax upgrades - rewriting older annotation forms into the
+//! current one, so large schema repos converge on a single dialect.
+//!
+//! The annotation syntax grew from shorthand strings (`"ucp_request":
+//! "omit"`) to per-operation objects, then to transition objects. Upgrading
+//! a schema:
+//!
+//! - expands shorthand strings to the per-operation object form;
+//! - expands a shorthand transition (`{ "transition": ... }`) into a
+//!   transition per operation it covers;
+//! - lowercases operation keys, which the resolver matches lowercase only.
+//!
+//! Unlike `normalize`, key order and everything outside annotations are
+//! left as they are.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::format::format_schema;
+use crate::linter::collect_schema_files;
+use crate::loader::{escape_pointer_token, load_schema};
+use crate::normalize::{expansion_operations, SCHEMA_MAPS};
+use crate::types::UCP_ANNOTATIONS;
+
+/// One annotation rewritten into the current syntax.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AnnotationUpgrade {
+    /// JSON Pointer to the annotation (e.g., `/properties/id/ucp_request`).
+    pub path: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+/// Result of upgrading a schema.
+#[derive(Debug, Clone)]
+pub struct Upgraded {
+    pub schema: Value,
+    /// Annotations rewritten, in document order.
+    pub changes: Vec<AnnotationUpgrade>,
+}
+
+/// Upgrade outcome for one file.
+#[derive(Debug, Clone, Serialize)]
+pub struct UpgradedFile {
+    pub file: PathBuf,
+    pub changes: Vec<AnnotationUpgrade>,
+    /// Set when the file couldn't be read, parsed or written.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Upgrade every schema file under `path`, rewriting changed files in the
+/// `fmt` layout when `write` is set (otherwise only reporting them).
+pub fn upgrade_files(path: &Path, write: bool) -> Vec<UpgradedFile> {
+    collect_schema_files(path)
+        .into_iter()
+        .map(|file| {
+            let mut result = UpgradedFile {
+                file: file.clone(),
+                changes: Vec::new(),
+                error: None,
+            };
+            match load_schema(&file) {
+                Ok(schema) => {
+                    let upgraded = upgrade_annotations(&schema);
+                    if !upgraded.changes.is_empty() && write {
+                        if let Err(e) = std::fs::write(&file, format_schema(&upgraded.schema)) {
+                            result.error = Some(format!("cannot write: {}", e));
+                        }
+                    }
+                    result.changes = upgraded.changes;
+                }
+                Err(e) => result.error = Some(e.to_string()),
+            }
+            result
+        })
+        .collect()
+}
+
+/// Rewrite every annotation in `schema` into the current syntax.
+///
+/// Shorthands expand over the same operations as in `normalize_schema`:
+/// the standard ones plus every operation named elsewhere in the schema or
+/// declared in `x-ucp-operations`. Upgrading is idempotent, and annotations
+/// the resolver would reject are left for it to report.
+pub fn upgrade_annotations(schema: &Value) -> Upgraded {
+    let mut ops: Vec<String> = Vec::new();
+    for op in expansion_operations(schema) {
+        let op = op.to_lowercase();
+        if !ops.contains(&op) {
+            ops.push(op);
+        }
+    }
+    let mut changes = Vec::new();
+    let schema = upgrade_value(schema, false, "", &ops, &mut changes);
+    Upgraded { schema, changes }
+}
+
+/// `names` is set when `value` maps names to schemas, so its keys are never
+/// annotation keywords.
+fn upgrade_value(
+    value: &Value,
+    names: bool,
+    path: &str,
+    ops: &[String],
+    changes: &mut Vec<AnnotationUpgrade>,
+) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut out = Map::new();
+            for (key, child) in map {
+                let child_path = format!("{}/{}", path, escape_pointer_token(key));
+                let upgraded = if !names && UCP_ANNOTATIONS.contains(&key.as_str()) {
+                    let after = upgrade_annotation(child, ops);
+                    if after != *child {
+                        changes.push(AnnotationUpgrade {
+                            path: child_path,
+                            before: child.clone(),
+                            after: after.clone(),
+                        });
+                    }
+                    after
+                } else {
+                    let names = !names && SCHEMA_MAPS.contains(&key.as_str());
+                    upgrade_value(child, names, &child_path, ops, changes)
+                };
+                out.insert(key.clone(), upgraded);
+            }
+            Value::Object(out)
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    upgrade_value(item, false, &format!("{}/{}", path, i), ops, changes)
+                })
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn upgrade_annotation(annotation: &Value, ops: &[String]) -> Value {
+    match annotation {
+        Value::String(_) => Value::Object(
+            ops.iter()
+                .map(|op| (op.clone(), annotation.clone()))
+                .collect(),
+        ),
+        Value::Object(map) => {
+            let mut upgraded = Map::new();
+            for (op, entry) in map.iter().filter(|(op, _)| *op != "transition") {
+                let lower = op.to_lowercase();
+                // Keep both spellings rather than pick one of two entries
+                if lower != *op && map.contains_key(&lower) {
+                    upgraded.insert(op.clone(), entry.clone());
+                } else {
+                    upgraded.insert(lower, entry.clone());
+                }
+            }
+            match map.get("transition") {
+                Some(transition) if transition.is_object() => {
+                    for op in ops {
+                        upgraded
+                            .entry(op.clone())
+                            .or_insert_with(|| serde_json::json!({ "transition": transition }));
+                    }
+                }
+                Some(other) => {
+                    upgraded.insert("transition".to_string(), other.clone());
+                }
+                None => {}
+            }
+            Value::Object(upgraded)
+        }
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn upgrade_expands_shorthands_and_lowercases_ops() {
+        let transition = json!({ "from": "required", "to": "omit", "description": "x" });
+        let schema = json!({
+            "properties": {
+                "id": { "ucp_request": "omit", "ucp_response": { "Refund": "required" } },
+                "sku": { "ucp_request": { "create": "required", "transition": transition } },
+                "name": { "ucp_request": { "create": "omit" } },
+                "ucp_request": { "type": "string" }
+            }
+        });
+        let upgraded = upgrade_annotations(&schema);
+
+        let paths: Vec<&str> = upgraded.changes.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "/properties/id/ucp_request",
+                "/properties/id/ucp_response",
+                "/properties/sku/ucp_request"
+            ]
+        );
+        let id = &upgraded.schema["properties"]["id"];
+        assert_eq!(
+            id["ucp_request"],
+            json!({ "create": "omit", "update": "omit", "complete": "omit", "read": "omit", "refund": "omit" })
+        );
+        assert_eq!(id["ucp_response"], json!({ "refund": "required" }));
+        let sku = &upgraded.schema["properties"]["sku"]["ucp_request"];
+        assert_eq!(sku["create"], "required");
+        assert_eq!(sku["read"], json!({ "transition": transition }));
+        assert!(sku.get("transition").is_none());
+        // A property named like an annotation is a schema, not an annotation
+        assert_eq!(
+            upgraded.schema["properties"]["ucp_request"],
+            json!({ "type": "string" })
+        );
+
+        assert!(upgrade_annotations(&upgraded.schema).changes.is_empty());
+    }
+}
@@ -1,9 +1,10 @@
 //! Payload validation against resolved schemas.
 
-use serde_json::Value;
+use serde_json::{json, Map, Value};
 
 use crate::error::{ResolveError, SchemaError, ValidateError};
-use crate::resolver::resolve;
+use crate::loader::navigate_fragment;
+use crate::resolver::{resolve, DATA_KEYWORDS, META_KEYWORDS, SCHEMA_MAP_KEYWORDS};
 use crate::types::ResolveOptions;
 
 /// Validate a payload against a UCP schema.
@@ -31,21 +32,59 @@ pub fn validate(
 ///
 /// Use this when you've already resolved the schema and want to validate
 /// multiple payloads against it.
+///
+/// `oneOf` variants tagged by a `const` discriminator are pre-selected by the
+/// payload's discriminator value, so a payload that picks a variant gets that
+/// variant's specific errors instead of a generic "not valid under any" error.
 pub fn validate_against_schema(schema: &Value, payload: &Value) -> Result<(), ValidateError> {
-    let validator = jsonschema::validator_for(schema).map_err(|e| {
-        ValidateError::Resolve(ResolveError::InvalidSchema {
-            message: e.to_string(),
+    let validator = compile(schema)?;
+    check(&validator, payload)
+}
+
+/// A schema resolved for one direction and operation and compiled once.
+///
+/// Keep one around to validate many payloads without re-resolving, e.g.
+/// across requests in a long-running service.
+pub struct CompiledValidator {
+    validator: jsonschema::Validator,
+}
+
+impl CompiledValidator {
+    /// Resolve `schema` for `options` and compile the result.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ValidateError::Resolve` if resolution or compilation fails.
+    pub fn new(schema: &Value, options: &ResolveOptions) -> Result<Self, ValidateError> {
+        let resolved = resolve(schema, options)?;
+        Ok(Self {
+            validator: compile(&resolved)?,
         })
-    })?;
+    }
+
+    /// Validate a payload against the compiled schema.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ValidateError::Invalid` if the payload doesn't match.
+    pub fn validate(&self, payload: &Value) -> Result<(), ValidateError> {
+        check(&self.validator, payload)
+    }
+}
+
+/// Compile a resolved schema, pre-selecting discriminated `oneOf` branches.
+fn compile(schema: &Value) -> Result<jsonschema::Validator, ValidateError> {
+    let selected = select_discriminated_branches(schema, schema);
 
-    let errors: Vec<SchemaError> = validator
-        .iter_errors(payload)
-        .map(|e| SchemaError {
-            path: e.instance_path.to_string(),
+    jsonschema::validator_for(selected.as_ref().unwrap_or(schema)).map_err(|e| {
+        ValidateError::Resolve(ResolveError::InvalidSchema {
             message: e.to_string(),
         })
-        .collect();
+    })
+}
 
+fn check(validator: &jsonschema::Validator, payload: &Value) -> Result<(), ValidateError> {
+    let errors = schema_errors(validator, payload);
     if errors.is_empty() {
         Ok(())
     } else {
@@ -53,6 +92,433 @@ pub fn validate_against_schema(schema: &Value, payload: &Value) -> Result<(), Va
     }
 }
 
+/// Every error `validator` reports for `instance`.
+pub(crate) fn schema_errors(
+    validator: &jsonschema::Validator,
+    instance: &Value,
+) -> Vec<SchemaError> {
+    validator
+        .iter_errors(instance)
+        .map(|e| {
+            let schema_path = e.schema_path.as_str().to_string();
+            // A `false` subschema fails on its own, not via a keyword
+            let keyword = match e.kind {
+                jsonschema::error::ValidationErrorKind::FalseSchema => "false".to_string(),
+                _ => schema_path
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+            };
+            SchemaError {
+                path: e.instance_path.to_string(),
+                schema_path,
+                keyword,
+                message: e.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Rewrite discriminated `oneOf` compositions into `if`/`then` branch selection.
+///
+/// A `oneOf` is discriminated when every branch requires the same property and
+/// pins it with a distinct `const` (the property may also be named explicitly
+/// via an OpenAPI-style sibling `discriminator.propertyName`). For such a
+/// composition, `oneOf: [A, B]` is equivalent to requiring the discriminator,
+/// constraining it to the known values, and applying exactly the branch whose
+/// `const` matches — which is what the rewrite produces. Validation then
+/// reports errors from the selected branch only.
+///
+/// Only schema positions are searched, so instance data (`const`, `enum`,
+/// `default`, `examples`) is never rewritten. Returns `None`, without copying
+/// anything, when there is nothing to rewrite.
+fn select_discriminated_branches(value: &Value, root: &Value) -> Option<Value> {
+    match value {
+        Value::Object(map) => {
+            let mut changed: Option<Map<String, Value>> = None;
+            for (k, v) in map {
+                if DATA_KEYWORDS.contains(&k.as_str()) || META_KEYWORDS.contains(&k.as_str()) {
+                    continue;
+                }
+                let selected = match v {
+                    // Entry names are never keywords; each entry is a subschema
+                    Value::Object(entries) if SCHEMA_MAP_KEYWORDS.contains(&k.as_str()) => {
+                        let mut changed_entries: Option<Map<String, Value>> = None;
+                        for (name, sub) in entries {
+                            if let Some(selected) = select_discriminated_branches(sub, root) {
+                                changed_entries
+                                    .get_or_insert_with(|| entries.clone())
+                                    .insert(name.clone(), selected);
+                            }
+                        }
+                        changed_entries.map(Value::Object)
+                    }
+                    _ => select_discriminated_branches(v, root),
+                };
+                if let Some(selected) = selected {
+                    changed
+                        .get_or_insert_with(|| map.clone())
+                        .insert(k.clone(), selected);
+                }
+            }
+
+            let Some(rewritten) = discriminated_one_of(changed.as_ref().unwrap_or(map), root)
+            else {
+                return changed.map(Value::Object);
+            };
+            let mut map = changed.unwrap_or_else(|| map.clone());
+            map.remove("oneOf");
+            map.remove("discriminator");
+            match map.get_mut("allOf").and_then(|v| v.as_array_mut()) {
+                Some(all_of) => all_of.push(rewritten),
+                None => {
+                    map.insert("allOf".to_string(), json!([rewritten]));
+                }
+            }
+            Some(Value::Object(map))
+        }
+        Value::Array(arr) => {
+            let mut changed: Option<Vec<Value>> = None;
+            for (i, item) in arr.iter().enumerate() {
+                if let Some(selected) = select_discriminated_branches(item, root) {
+                    changed.get_or_insert_with(|| arr.clone())[i] = selected;
+                }
+            }
+            changed.map(Value::Array)
+        }
+        _ => None,
+    }
+}
+
+/// Build the branch-selecting replacement for a discriminated `oneOf`, if any.
+fn discriminated_one_of(map: &Map<String, Value>, root: &Value) -> Option<Value> {
+    let branches = map.get("oneOf")?.as_array()?;
+    if branches.len() < 2 {
+        return None;
+    }
+
+    // Look through local $refs so branches defined in $defs are recognized
+    let resolved: Vec<Value> = branches
+        .iter()
+        .map(|b| match b.get("$ref").and_then(|r| r.as_str()) {
+            Some(r) if r.starts_with("#/") => navigate_fragment(root, r).unwrap_or(b.clone()),
+            _ => b.clone(),
+        })
+        .collect();
+
+    let explicit = map
+        .get("discriminator")
+        .and_then(|d| d.get("propertyName"))
+        .and_then(|p| p.as_str());
+    let candidates: Vec<String> = match explicit {
+        Some(name) => vec![name.to_string()],
+        None => resolved[0]
+            .get("properties")
+            .and_then(|p| p.as_object())
+            .map(|props| props.keys().cloned().collect())
+            .unwrap_or_default(),
+    };
+
+    for name in candidates {
+        let mut consts = Vec::new();
+        for branch in &resolved {
+            let required = branch
+                .get("required")
+                .and_then(|r| r.as_array())
+                .is_some_and(|r| r.iter().any(|v| v.as_str() == Some(name.as_str())));
+            let Some(c) = branch
+                .get("properties")
+                .and_then(|p| p.get(&name))
+                .and_then(|p| p.get("const"))
+            else {
+                break;
+            };
+            if !required || consts.contains(c) {
+                break;
+            }
+            consts.push(c.clone());
+        }
+        if consts.len() != branches.len() {
+            continue;
+        }
+
+        let selectors: Vec<Value> = branches
+            .iter()
+            .zip(&consts)
+            .map(|(branch, c)| {
+                json!({
+                    "if": { "properties": { name.as_str(): { "const": c } } },
+                    "then": branch
+                })
+            })
+            .collect();
+        return Some(json!({
+            "required": [name.as_str()],
+            "properties": { name.as_str(): { "enum": consts } },
+            "allOf": selectors
+        }));
+    }
+
+    None
+}
+
+/// Key of the per-field status map `annotate_payload` adds to each object.
+const STATUS_KEY: &str = "x-ucp-status";
+
+/// Overlay per-field validation status onto a payload, for debugging.
+///
+/// Every object in `payload` gains an `x-ucp-status` map from each of its
+/// field names to one of:
+/// - `"ok"`: declared by the schema, no errors at the field
+/// - `"unexpected"`: not declared by the schema's `properties`
+/// - `"type-error"`: failed a `type` check
+/// - `"invalid"`: failed any other keyword
+///
+/// `schema` is the resolved schema and `errors` the result of validating
+/// `payload` against it. Fields of free-form objects (no `properties`, or a
+/// schema-valued `additionalProperties`) are never unexpected.
+pub fn annotate_payload(schema: &Value, payload: &Value, errors: &[SchemaError]) -> Value {
+    annotate_node(payload, Some(schema), schema, "", errors)
+}
+
+fn annotate_node(
+    value: &Value,
+    schema: Option<&Value>,
+    root: &Value,
+    path: &str,
+    errors: &[SchemaError],
+) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut declared = Map::new();
+            let extra_allowed = schema.is_some_and(|s| collect_properties(s, root, &mut declared));
+            let open = extra_allowed || declared.is_empty();
+
+            let mut result = Map::new();
+            let mut statuses = Map::new();
+            for (key, child) in map {
+                let child_path = format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+                let child_schema = declared.get(key);
+                let status = field_status(&child_path, errors).unwrap_or(
+                    if child_schema.is_some() || open {
+                        "ok"
+                    } else {
+                        "unexpected"
+                    },
+                );
+                statuses.insert(key.clone(), json!(status));
+                result.insert(
+                    key.clone(),
+                    annotate_node(child, child_schema, root, &child_path, errors),
+                );
+            }
+            result.insert(STATUS_KEY.to_string(), Value::Object(statuses));
+            Value::Object(result)
+        }
+        Value::Array(arr) => {
+            let items = schema.and_then(|s| follow_local_ref(s, root).get("items").cloned());
+            Value::Array(
+                arr.iter()
+                    .enumerate()
+                    .map(|(i, item)| {
+                        let item_path = format!("{}/{}", path, i);
+                        annotate_node(item, items.as_ref(), root, &item_path, errors)
+                    })
+                    .collect(),
+            )
+        }
+        other => other.clone(),
+    }
+}
+
+/// A payload with the fields its schema doesn't declare removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scrubbed {
+    pub payload: Value,
+    /// JSON pointers of the removed fields, in payload order.
+    pub removed: Vec<String>,
+}
+
+/// Remove every field the resolved `schema` doesn't declare, producing the
+/// payload as a server for that direction and operation should see it.
+///
+/// Fields an operation omits are gone from its resolved schema, so they
+/// are dropped along with unknown ones. Objects are kept whole where the
+/// schema is free-form (no `properties`, or a schema-valued
+/// `additionalProperties`), as in [`annotate_payload`].
+pub fn scrub_payload(schema: &Value, payload: &Value) -> Scrubbed {
+    let mut removed = Vec::new();
+    let payload = scrub_node(payload, Some(schema), schema, "", &mut removed);
+    Scrubbed { payload, removed }
+}
+
+fn scrub_node(
+    value: &Value,
+    schema: Option<&Value>,
+    root: &Value,
+    path: &str,
+    removed: &mut Vec<String>,
+) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut declared = Map::new();
+            let extra_allowed = schema.is_some_and(|s| collect_properties(s, root, &mut declared));
+            let open = extra_allowed || declared.is_empty();
+
+            let mut result = Map::new();
+            for (key, child) in map {
+                let child_path = format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+                let child_schema = declared.get(key);
+                if child_schema.is_none() && !open {
+                    removed.push(child_path);
+                    continue;
+                }
+                result.insert(
+                    key.clone(),
+                    scrub_node(child, child_schema, root, &child_path, removed),
+                );
+            }
+            Value::Object(result)
+        }
+        Value::Array(arr) => {
+            let items = schema.and_then(|s| follow_local_ref(s, root).get("items").cloned());
+            Value::Array(
+                arr.iter()
+                    .enumerate()
+                    .map(|(i, item)| {
+                        let item_path = format!("{}/{}", path, i);
+                        scrub_node(item, items.as_ref(), root, &item_path, removed)
+                    })
+                    .collect(),
+            )
+        }
+        other => other.clone(),
+    }
+}
+
+/// Gather the properties `schema` declares, through composition branches and
+/// local `$ref`s. Returns true if any part admits arbitrary extra fields.
+///
+/// A property declared more than once gets an `allOf` of its declarations.
+pub(crate) fn collect_properties(
+    schema: &Value,
+    root: &Value,
+    declared: &mut Map<String, Value>,
+) -> bool {
+    let schema = follow_local_ref(schema, root);
+    let mut open = schema
+        .get("additionalProperties")
+        .is_some_and(|a| a.is_object())
+        || schema.get("patternProperties").is_some();
+
+    if let Some(props) = schema.get("properties").and_then(|p| p.as_object()) {
+        for (name, sub) in props {
+            match declared.get_mut(name) {
+                Some(existing) => *existing = json!({ "allOf": [existing.take(), sub] }),
+                None => {
+                    declared.insert(name.clone(), sub.clone());
+                }
+            }
+        }
+    }
+    for keyword in ["allOf", "anyOf", "oneOf"] {
+        let branches = schema.get(keyword).and_then(|b| b.as_array());
+        for branch in branches.into_iter().flatten() {
+            open |= collect_properties(branch, root, declared);
+        }
+    }
+    open
+}
+
+/// Expand data-level JSON References in a payload before validation.
+///
+/// Every object whose `$ref` is a `#`-relative JSON Pointer is replaced by
+/// the value it points to in the same document, itself expanded; other
+/// members of a reference object are ignored, as in JSON Reference. `$ref`s
+/// that aren't `#`-relative are left as data.
+///
+/// # Errors
+///
+/// Returns `ResolveError::InvalidPayloadRef` if a pointer doesn't exist in the
+/// payload or the references form a cycle.
+pub fn deref_payload(payload: &Value) -> Result<Value, ResolveError> {
+    deref_node(payload, payload, "", &mut Vec::new())
+}
+
+/// `expanding` holds the pointers being expanded on the way to `value`.
+fn deref_node(
+    value: &Value,
+    root: &Value,
+    path: &str,
+    expanding: &mut Vec<String>,
+) -> Result<Value, ResolveError> {
+    match value {
+        Value::Object(map) => {
+            let target = map
+                .get("$ref")
+                .and_then(|r| r.as_str())
+                .and_then(|r| r.strip_prefix('#'));
+            if let Some(target) = target {
+                let error = |message: String| ResolveError::InvalidPayloadRef {
+                    path: path.to_string(),
+                    message,
+                };
+                if expanding.iter().any(|p| p == target) {
+                    expanding.push(target.to_string());
+                    let cycle: Vec<String> = expanding.iter().map(|p| format!("#{}", p)).collect();
+                    return Err(error(format!("cycle {}", cycle.join(" -> "))));
+                }
+                let referenced = root
+                    .pointer(target)
+                    .ok_or_else(|| error(format!("#{} not found in payload", target)))?;
+                expanding.push(target.to_string());
+                let expanded = deref_node(referenced, root, path, expanding)?;
+                expanding.pop();
+                return Ok(expanded);
+            }
+            let mut result = Map::new();
+            for (key, child) in map {
+                let child_path = format!("{}/{}", path, key);
+                result.insert(
+                    key.clone(),
+                    deref_node(child, root, &child_path, expanding)?,
+                );
+            }
+            Ok(Value::Object(result))
+        }
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| deref_node(item, root, &format!("{}/{}", path, i), expanding))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        other => Ok(other.clone()),
+    }
+}
+
+/// Resolve a `#/...` `$ref`, returning the schema itself otherwise.
+pub(crate) fn follow_local_ref(schema: &Value, root: &Value) -> Value {
+    match schema.get("$ref").and_then(|r| r.as_str()) {
+        Some(r) if r.starts_with('#') => {
+            navigate_fragment(root, r).unwrap_or_else(|_| schema.clone())
+        }
+        _ => schema.clone(),
+    }
+}
+
+/// Status for a field from the errors reported at its path, if any.
+fn field_status(path: &str, errors: &[SchemaError]) -> Option<&'static str> {
+    let at_field: Vec<&SchemaError> = errors.iter().filter(|e| e.path == path).collect();
+    if at_field.is_empty() {
+        None
+    } else if at_field.iter().any(|e| e.keyword == "type") {
+        Some("type-error")
+    } else {
+        Some("invalid")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -235,4 +701,234 @@ mod tests {
             "should allow unknown properties in non-strict mode"
         );
     }
+
+    #[test]
+    fn validate_boolean_property_schemas() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "legacy": false,
+                "extra": true
+            }
+        });
+        let options = ResolveOptions::new(Direction::Request, "create").strict(true);
+
+        let payload = json!({ "name": "test", "extra": [1, 2] });
+        assert!(validate(&schema, &payload, &options).is_ok());
+
+        let payload = json!({ "name": "test", "legacy": "x" });
+        assert!(matches!(
+            validate(&schema, &payload, &options),
+            Err(ValidateError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn validate_errors_carry_schema_path_and_keyword() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string", "ucp_request": "required" },
+                "legacy": false
+            }
+        });
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let payload = json!({ "legacy": 1 });
+
+        let Err(ValidateError::Invalid { errors }) = validate(&schema, &payload, &options) else {
+            panic!("expected validation failure");
+        };
+        let required = errors.iter().find(|e| e.keyword == "required").unwrap();
+        assert_eq!(required.schema_path, "/required");
+        let legacy = errors.iter().find(|e| e.path == "/legacy").unwrap();
+        assert_eq!(legacy.keyword, "false");
+        assert_eq!(legacy.schema_path, "/properties/legacy");
+    }
+
+    #[test]
+    fn validate_discriminated_one_of_reports_selected_branch_errors() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "payment": {
+                    "oneOf": [
+                        {
+                            "type": "object",
+                            "properties": {
+                                "kind": { "const": "card" },
+                                "number": { "type": "string" }
+                            },
+                            "required": ["kind", "number"]
+                        },
+                        {
+                            "type": "object",
+                            "properties": {
+                                "kind": { "const": "bank" },
+                                "iban": { "type": "string" }
+                            },
+                            "required": ["kind", "iban"]
+                        }
+                    ]
+                }
+            }
+        });
+        let payload = json!({ "payment": { "kind": "card", "number": 4242 } });
+        let options = ResolveOptions::new(Direction::Request, "create");
+
+        match validate(&schema, &payload, &options) {
+            Err(ValidateError::Invalid { errors }) => {
+                assert_eq!(errors.len(), 1, "errors: {:?}", errors);
+                assert_eq!(errors[0].path, "/payment/number");
+                assert!(errors[0].message.contains("string"));
+            }
+            other => panic!("expected targeted branch error, got {:?}", other),
+        }
+
+        // Unknown discriminator values are still rejected
+        let payload = json!({ "payment": { "kind": "cash" } });
+        let result = validate(&schema, &payload, &options);
+        assert!(matches!(result, Err(ValidateError::Invalid { .. })));
+
+        // Payloads matching the selected branch pass
+        let payload = json!({ "payment": { "kind": "bank", "iban": "DE00" } });
+        assert!(validate(&schema, &payload, &options).is_ok());
+    }
+
+    #[test]
+    fn validate_one_of_without_discriminator_unchanged() {
+        let schema = json!({
+            "oneOf": [
+                { "type": "string" },
+                { "type": "integer" }
+            ]
+        });
+        let options = ResolveOptions::new(Direction::Request, "create");
+
+        assert!(validate(&schema, &json!("x"), &options).is_ok());
+        assert!(validate(&schema, &json!(1.5), &options).is_err());
+    }
+
+    #[test]
+    fn discriminated_one_of_rewrite_skips_data_and_unneeded_copies() {
+        let tagged = json!({
+            "oneOf": [
+                { "required": ["kind"], "properties": { "kind": { "const": "a" } } },
+                { "required": ["kind"], "properties": { "kind": { "const": "b" } } }
+            ]
+        });
+        // Nothing to rewrite: no copy
+        assert!(select_discriminated_branches(&json!({ "oneOf": [{}, {}] }), &json!({})).is_none());
+        // A discriminated oneOf as instance data stays as written
+        let schema = json!({ "type": "object", "examples": [tagged.clone()], "default": tagged });
+        assert!(select_discriminated_branches(&schema, &schema).is_none());
+
+        let schema = json!({ "properties": { "item": tagged } });
+        let selected = select_discriminated_branches(&schema, &schema).unwrap();
+        assert!(selected["properties"]["item"].get("oneOf").is_none());
+        assert!(selected["properties"]["item"]["allOf"].is_array());
+    }
+
+    #[test]
+    fn deref_payload_expands_internal_refs() {
+        let payload = json!({
+            "shared": { "street": "1 Main St", "city": "Anytown" },
+            "billing": { "$ref": "#/shared" },
+            "shipping": { "address": { "$ref": "#/billing" } },
+            "links": [{ "$ref": "https://example.com/x" }]
+        });
+        let expanded = deref_payload(&payload).unwrap();
+        assert_eq!(expanded["billing"], payload["shared"]);
+        assert_eq!(expanded["shipping"]["address"], payload["shared"]);
+        assert_eq!(expanded["links"], payload["links"]);
+
+        let missing = json!({ "a": { "$ref": "#/nope" } });
+        assert!(matches!(
+            deref_payload(&missing),
+            Err(ResolveError::InvalidPayloadRef { ref path, .. }) if path == "/a"
+        ));
+
+        let cyclic = json!({ "a": { "next": { "$ref": "#/a" } } });
+        let err = deref_payload(&cyclic).unwrap_err().to_string();
+        assert!(err.contains("cycle #/a -> #/a"), "{}", err);
+    }
+
+    #[test]
+    fn annotate_payload_marks_fields() {
+        let schema = json!({
+            "type": "object",
+            "allOf": [
+                { "properties": { "id": { "type": "string" } } },
+                {
+                    "properties": {
+                        "tags": { "type": "array", "items": { "$ref": "#/$defs/tag" } },
+                        "meta": { "type": "object" }
+                    }
+                }
+            ],
+            "$defs": {
+                "tag": { "type": "object", "properties": { "label": { "type": "string" } } }
+            }
+        });
+        let payload = json!({
+            "id": 7,
+            "tags": [{ "label": "a", "color": "red" }],
+            "meta": { "anything": true },
+            "extra": null
+        });
+        let errors = match validate_against_schema(&schema, &payload) {
+            Err(ValidateError::Invalid { errors, .. }) => errors,
+            other => panic!("expected invalid, got {:?}", other),
+        };
+
+        let annotated = annotate_payload(&schema, &payload, &errors);
+        assert_eq!(
+            annotated[STATUS_KEY],
+            json!({ "id": "type-error", "tags": "ok", "meta": "ok", "extra": "unexpected" })
+        );
+        assert_eq!(
+            annotated["tags"][0][STATUS_KEY],
+            json!({ "label": "ok", "color": "unexpected" })
+        );
+        // Free-form objects accept any field
+        assert_eq!(annotated["meta"][STATUS_KEY], json!({ "anything": "ok" }));
+        assert_eq!(annotated["id"], json!(7));
+    }
+
+    #[test]
+    fn scrub_payload_drops_omitted_fields() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "ucp_request": { "update": "required", "create": "omit" } },
+                "items": {
+                    "type": "array",
+                    "items": {
+                        "properties": {
+                            "sku": { "type": "string" },
+                            "price": { "type": "integer", "ucp_request": "omit" }
+                        }
+                    }
+                },
+                "meta": { "type": "object" }
+            }
+        });
+        let payload = json!({
+            "id": "o_1",
+            "items": [{ "sku": "A", "price": 100 }],
+            "meta": { "anything": true },
+            "debug": 1
+        });
+
+        let update = resolve(&schema, &ResolveOptions::new(Direction::Request, "update")).unwrap();
+        let scrubbed = scrub_payload(&update, &payload);
+        assert_eq!(
+            scrubbed.payload,
+            json!({ "id": "o_1", "items": [{ "sku": "A" }], "meta": { "anything": true } })
+        );
+        assert_eq!(scrubbed.removed, ["/items/0/price", "/debug"]);
+
+        let create = resolve(&schema, &ResolveOptions::new(Direction::Request, "create")).unwrap();
+        assert!(scrub_payload(&create, &payload).payload.get("id").is_none());
+    }
 }
@@ -0,0 +1,280 @@
+//! Schema vendoring - mirroring the remote schemas a payload needs into a
+//! local directory, for air-gapped use.
+//!
+//! Files are laid out by URL path: `https://ucp.dev/schemas/shopping/checkout.json`
+//! becomes `schemas/shopping/checkout.json`, which is where
+//! `--schema-local-base <dir>` looks for it. Local bundling resolves `$ref`s
+//! against the referencing file's directory, so absolute `$ref` URLs are
+//! rewritten to relative paths.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::compose::{extract_capabilities, extract_url_path, SchemaBaseConfig};
+use crate::error::ComposeError;
+use crate::loader::{is_url, load_schema_auto, resolve_url, FetchOptions};
+
+/// A fetched document and where it goes, relative to the vendor directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VendoredFile {
+    pub url: String,
+    pub path: PathBuf,
+    /// The document, with absolute `$ref` URLs made relative.
+    pub content: Value,
+}
+
+/// Fetch the profile (from `profile`, or the payload's `meta.profile`),
+/// every capability schema, and every document their `$ref`s reach.
+///
+/// A payload with inline `ucp.capabilities` needs no profile; `profile`
+/// takes precedence when given.
+///
+/// # Errors
+///
+/// Returns `ComposeError::ProfileFetch` or `ComposeError::SchemaFetch` for
+/// documents that can't be fetched, `ComposeError::NotSelfDescribing` when
+/// there are no capabilities to follow, and `ComposeError::InvalidUrl` for
+/// URLs without a file path or two URLs mapping to the same file.
+pub fn vendor_schemas(
+    payload: &Value,
+    profile: Option<&str>,
+    fetch: &FetchOptions,
+) -> Result<Vec<VendoredFile>, ComposeError> {
+    let mut vendor = Vendor::new(fetch);
+    let schema_base = SchemaBaseConfig {
+        fetch,
+        ..Default::default()
+    };
+    let inline = payload.pointer("/ucp/capabilities").is_some();
+    let profile_url = profile.or_else(|| {
+        payload
+            .pointer("/meta/profile")
+            .and_then(Value::as_str)
+            .filter(|_| !inline)
+    });
+
+    let capabilities = match profile_url {
+        Some(url) => {
+            let document =
+                load_schema_auto(url, fetch).map_err(|e| ComposeError::ProfileFetch {
+                    url: url.to_string(),
+                    message: e.to_string(),
+                })?;
+            if document.pointer("/ucp/capabilities").is_none() {
+                return Err(ComposeError::ProfileFetch {
+                    url: url.to_string(),
+                    message: "profile missing ucp.capabilities".to_string(),
+                });
+            }
+            let capabilities = extract_capabilities(&document, &schema_base)?;
+            vendor.add(url, document)?;
+            capabilities
+        }
+        None => extract_capabilities(payload, &schema_base)?,
+    };
+
+    for capability in &capabilities {
+        vendor.fetch(&capability.schema_url)?;
+    }
+    Ok(vendor.files)
+}
+
+struct Vendor<'a> {
+    fetch: &'a FetchOptions,
+    files: Vec<VendoredFile>,
+    /// Index into `files` by URL (without fragment).
+    seen: HashMap<String, usize>,
+}
+
+impl<'a> Vendor<'a> {
+    fn new(fetch: &'a FetchOptions) -> Self {
+        Self {
+            fetch,
+            files: Vec::new(),
+            seen: HashMap::new(),
+        }
+    }
+
+    fn fetch(&mut self, url: &str) -> Result<(), ComposeError> {
+        if self.seen.contains_key(url) {
+            return Ok(());
+        }
+        let document =
+            load_schema_auto(url, self.fetch).map_err(|e| ComposeError::SchemaFetch {
+                url: url.to_string(),
+                message: e.to_string(),
+            })?;
+        self.add(url, document)
+    }
+
+    /// Record `document` fetched from `url`, then fetch what it references.
+    fn add(&mut self, url: &str, mut document: Value) -> Result<(), ComposeError> {
+        let path = local_path(url)?;
+        if let Some(other) = self.files.iter().find(|f| f.path == path) {
+            return Err(ComposeError::InvalidUrl {
+                url: url.to_string(),
+                message: format!("maps to {} like {}", path.display(), other.url),
+            });
+        }
+        // Registered before following refs, so cycles end here
+        self.seen.insert(url.to_string(), self.files.len());
+        self.files.push(VendoredFile {
+            url: url.to_string(),
+            path: path.clone(),
+            content: Value::Null,
+        });
+
+        let mut targets = Vec::new();
+        rewrite_refs(&mut document, url, &path, &mut targets)?;
+        let index = self.seen[url];
+        self.files[index].content = document;
+        for target in targets {
+            self.fetch(&target)?;
+        }
+        Ok(())
+    }
+}
+
+/// Collect the documents `value`'s external `$ref`s point at (as absolute
+/// URLs) into `targets`, making absolute refs relative to `path`.
+fn rewrite_refs(
+    value: &mut Value,
+    url: &str,
+    path: &Path,
+    targets: &mut Vec<String>,
+) -> Result<(), ComposeError> {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::String(reference)) = map.get_mut("$ref") {
+                let (file, fragment) = match reference.find('#') {
+                    Some(i) => reference.split_at(i),
+                    None => (reference.as_str(), ""),
+                };
+                if !file.is_empty() {
+                    let target = collapse_dots(&resolve_url(file, url));
+                    if is_url(&target) {
+                        if is_url(file) {
+                            let relative = relative_path(path, &local_path(&target)?);
+                            *reference = format!("{}{}", relative, fragment);
+                        }
+                        targets.push(target);
+                    }
+                }
+            }
+            for child in map.values_mut() {
+                rewrite_refs(child, url, path, targets)?;
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                rewrite_refs(item, url, path, targets)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// `url` with `.` and `..` path segments collapsed, as a server would.
+fn collapse_dots(url: &str) -> String {
+    let host_end = url
+        .find("://")
+        .and_then(|i| url[i + 3..].find('/').map(|j| i + 3 + j));
+    let Some(host_end) = host_end else {
+        return url.to_string();
+    };
+    let (prefix, path) = url.split_at(host_end);
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/').skip(1) {
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    format!("{}/{}", prefix, segments.join("/"))
+}
+
+/// Where `url` is vendored: its path, without query or fragment.
+fn local_path(url: &str) -> Result<PathBuf, ComposeError> {
+    let path = extract_url_path(url)?;
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let path = path.trim_start_matches('/');
+    if path.is_empty() || path.ends_with('/') || path.split('/').any(|s| s == "..") {
+        return Err(ComposeError::InvalidUrl {
+            url: url.to_string(),
+            message: "no file path to vendor it under".to_string(),
+        });
+    }
+    Ok(PathBuf::from(path))
+}
+
+/// `to` as a `/`-separated path relative to the directory of `from`.
+fn relative_path(from: &Path, to: &Path) -> String {
+    let from_dir: Vec<_> = from.parent().into_iter().flat_map(|p| p.iter()).collect();
+    let to_parts: Vec<_> = to.iter().collect();
+    let common = from_dir
+        .iter()
+        .zip(&to_parts)
+        .take_while(|(a, b)| a == b)
+        .count()
+        .min(to_parts.len() - 1);
+
+    let mut parts = vec![".."; from_dir.len() - common];
+    parts.extend(to_parts[common..].iter().filter_map(|p| p.to_str()));
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn relative_paths_between_vendored_files() {
+        let checkout = Path::new("schemas/shopping/checkout.json");
+        assert_eq!(
+            relative_path(Path::new("schemas/shopping/ext/discount.json"), checkout),
+            "../checkout.json"
+        );
+        assert_eq!(
+            relative_path(checkout, Path::new("schemas/shopping/types/buyer.json")),
+            "types/buyer.json"
+        );
+        assert_eq!(
+            relative_path(Path::new("profile.json"), checkout),
+            "schemas/shopping/checkout.json"
+        );
+    }
+
+    #[test]
+    fn rewrite_refs_makes_absolute_refs_relative() {
+        let url = "https://ucp.dev/schemas/shopping/discount.json";
+        let mut schema = json!({
+            "allOf": [{ "$ref": "https://ucp.dev/schemas/shopping/checkout.json#/$defs/line" }],
+            "properties": {
+                "buyer": { "$ref": "types/buyer.json" },
+                "root": { "$ref": "../ucp.json" },
+                "self": { "$ref": "#/$defs/code" }
+            }
+        });
+        let mut targets = Vec::new();
+        rewrite_refs(&mut schema, url, &local_path(url).unwrap(), &mut targets).unwrap();
+
+        assert_eq!(schema["allOf"][0]["$ref"], "checkout.json#/$defs/line");
+        assert_eq!(schema["properties"]["buyer"]["$ref"], "types/buyer.json");
+        assert_eq!(
+            targets,
+            [
+                "https://ucp.dev/schemas/shopping/checkout.json",
+                "https://ucp.dev/schemas/shopping/types/buyer.json",
+                "https://ucp.dev/schemas/ucp.json"
+            ]
+        );
+        assert!(local_path("https://ucp.dev/").is_err());
+    }
+}
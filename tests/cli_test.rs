@@ -47,6 +47,245 @@ mod resolve_command {
             .stdout(predicate::str::contains(r#""required":["id"]"#));
     }
 
+    #[test]
+    fn resolve_redact_removes_default_values() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{
+                "type": "object",
+                "properties": {
+                    "api_key": { "type": "string", "default": "sk_internal_42", "ucp_request": "required" }
+                }
+            }"#,
+        );
+
+        let assert = cmd()
+            .args([
+                "resolve",
+                schema.to_str().unwrap(),
+                "--request",
+                "--op",
+                "create",
+                "--redact",
+                "default,examples",
+            ])
+            .assert()
+            .success();
+
+        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+        let resolved: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        assert!(!stdout.contains("sk_internal_42"));
+        assert_eq!(resolved["properties"]["api_key"]["type"], "string");
+        assert_eq!(resolved["required"], serde_json::json!(["api_key"]));
+    }
+
+    #[test]
+    fn resolve_denullify_modes() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{"type":"object","properties":{"note":{"type":["string","null"]}}}"#,
+        );
+        let args = [
+            "resolve",
+            schema.to_str().unwrap(),
+            "--request",
+            "--op",
+            "create",
+            "--denullify",
+        ];
+
+        cmd()
+            .args(args)
+            .arg("openapi")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                r#""note":{"type":"string","nullable":true}"#,
+            ));
+
+        cmd()
+            .args(args)
+            .arg("drop-null")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(r#""note":{"type":"string"}"#));
+
+        cmd()
+            .args(args)
+            .arg("nullable")
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("expected openapi or drop-null"));
+    }
+
+    #[test]
+    fn resolve_output_dir_name_template() {
+        let dir = TempDir::new().unwrap();
+        let out = dir.path().join("out");
+
+        cmd()
+            .args([
+                "resolve",
+                "tests/fixtures/checkout.json",
+                "--ops",
+                "create,read",
+                "--both",
+                "--output-dir",
+                out.to_str().unwrap(),
+                "--name-template",
+                "{name}-{op}-{direction}.json",
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::is_empty());
+
+        let mut names: Vec<String> = fs::read_dir(&out)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "checkout-create-request.json",
+                "checkout-create-response.json",
+                "checkout-read-request.json",
+                "checkout-read-response.json"
+            ]
+        );
+
+        // Each file matches the single-output resolve for its op/direction
+        for (op, direction) in [("create", "request"), ("read", "response")] {
+            let single = cmd()
+                .args([
+                    "resolve",
+                    "tests/fixtures/checkout.json",
+                    &format!("--{}", direction),
+                    "--op",
+                    op,
+                ])
+                .assert()
+                .success();
+            let expected: serde_json::Value =
+                serde_json::from_slice(&single.get_output().stdout).unwrap();
+            let path = out.join(format!("checkout-{}-{}.json", op, direction));
+            let written: serde_json::Value =
+                serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+            assert_eq!(written, expected);
+        }
+    }
+
+    #[test]
+    fn resolve_output_dir_names_stdin_input() {
+        let dir = TempDir::new().unwrap();
+        let out = dir.path().join("out");
+
+        cmd()
+            .args([
+                "resolve",
+                "-",
+                "--request",
+                "--ops",
+                "create",
+                "--output-dir",
+            ])
+            .arg(&out)
+            .pipe_stdin("tests/fixtures/checkout.json")
+            .unwrap()
+            .assert()
+            .success();
+        assert!(out.join("stdin-create-request.json").is_file());
+    }
+
+    #[test]
+    fn resolve_all_ops() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "order.json",
+            r#"{
+                "type": "object",
+                "x-ucp-operations": ["create", "cancel"],
+                "properties": {
+                    "id": { "type": "string", "ucp_request": { "create": "omit", "refund": "required" } }
+                }
+            }"#,
+        );
+
+        let output = cmd()
+            .arg("resolve")
+            .arg(&schema)
+            .args(["--request", "--all-ops"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let by_op: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let ops: Vec<&String> = by_op.as_object().unwrap().keys().collect();
+        assert_eq!(ops, ["create", "cancel", "refund"]);
+        assert!(by_op["create"]["properties"].get("id").is_none());
+        assert_eq!(by_op["refund"]["required"], serde_json::json!(["id"]));
+
+        let out = dir.path().join("out");
+        cmd()
+            .arg("resolve")
+            .arg(&schema)
+            .args(["--both", "--all-ops", "--output-dir"])
+            .arg(&out)
+            .assert()
+            .success();
+        let mut names: Vec<String> = fs::read_dir(&out)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 6);
+        assert_eq!(names[0], "order-cancel-request.json");
+
+        cmd()
+            .arg("resolve")
+            .arg(&schema)
+            .args(["--request", "--all-ops", "--op", "create"])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+
+    #[test]
+    fn resolve_name_template_errors() {
+        let dir = TempDir::new().unwrap();
+        let out = dir.path().join("out");
+        let args = [
+            "resolve",
+            "tests/fixtures/checkout.json",
+            "--ops",
+            "create,read",
+            "--both",
+            "--output-dir",
+            out.to_str().unwrap(),
+            "--name-template",
+        ];
+
+        cmd()
+            .args(args)
+            .arg("{name}-{operation}.json")
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("unknown placeholder {operation}"));
+
+        // Names that collide across outputs are rejected before writing
+        cmd()
+            .args(args)
+            .arg("{name}-{op}.json")
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("more than once"));
+        assert!(!out.exists());
+    }
+
     #[test]
     fn resolve_with_pretty() {
         let dir = TempDir::new().unwrap();
@@ -215,166 +454,238 @@ mod resolve_command {
             .success()
             .stdout(predicate::str::contains(r#""required":["id"]"#));
     }
-}
 
-mod validate_command {
-    use super::*;
+    #[test]
+    fn large_schema_output_round_trips() {
+        let dir = TempDir::new().unwrap();
+        let properties: serde_json::Map<String, serde_json::Value> = (0..4_000)
+            .map(|i| {
+                let prop = serde_json::json!({
+                    "type": "string",
+                    "description": format!("field {} with \"quotes\" and unicode \u{e9}", i),
+                    "ucp_request": if i % 2 == 0 { "required" } else { "omit" }
+                });
+                (format!("field_{}", i), prop)
+            })
+            .collect();
+        let schema = serde_json::json!({ "type": "object", "properties": properties });
+        let path = write_temp_file(&dir, "large.json", &schema.to_string());
+        let output = dir.path().join("resolved.json");
+        let args = [
+            "resolve",
+            path.to_str().unwrap(),
+            "--request",
+            "--op",
+            "create",
+        ];
+
+        let assert = cmd().args(args).assert().success();
+        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+        assert!(stdout.ends_with("}\n"));
+        let from_stdout: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+
+        cmd()
+            .args(args)
+            .args(["--pretty", "--output", output.to_str().unwrap()])
+            .assert()
+            .success();
+        let from_file: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+
+        assert_eq!(from_stdout, from_file);
+        assert_eq!(from_file["properties"].as_object().unwrap().len(), 2_000);
+        assert_eq!(from_file["required"].as_array().unwrap().len(), 2_000);
+        assert_eq!(
+            from_file["properties"]["field_0"]["description"],
+            "field 0 with \"quotes\" and unicode \u{e9}"
+        );
+    }
 
     #[test]
-    fn validate_valid_payload() {
+    fn input_format_yaml_from_stdin() {
         let dir = TempDir::new().unwrap();
-        let schema = write_temp_file(
+        let path = write_temp_file(
             &dir,
-            "schema.json",
-            r#"{
-                "type": "object",
-                "properties": {
-                    "name": { "type": "string", "ucp_request": "required" }
-                }
-            }"#,
+            "schema.txt",
+            "type: object\nproperties:\n  id:\n    type: string\n    ucp_request: omit\n  note:\n    type: string\n",
         );
-        let payload = write_temp_file(&dir, "payload.json", r#"{"name": "test"}"#);
+
+        let assert = cmd()
+            .args(["resolve", "-", "--request", "--op", "create"])
+            .args(["--input-format", "yaml"])
+            .pipe_stdin(&path)
+            .unwrap()
+            .assert()
+            .success();
+        let resolved: serde_json::Value =
+            serde_json::from_slice(&assert.get_output().stdout).unwrap();
+        assert!(resolved["properties"].get("id").is_none());
+        assert_eq!(resolved["properties"]["note"]["type"], "string");
 
         cmd()
-            .args([
-                "validate",
-                payload.to_str().unwrap(),
-                "--schema",
-                schema.to_str().unwrap(),
-                "--request",
-                "--op",
-                "create",
-            ])
+            .args(["resolve", "-", "--request", "--op", "create"])
+            .args(["--input-format", "json"])
+            .pipe_stdin(&path)
+            .unwrap()
             .assert()
-            .success()
-            .stdout(predicate::str::contains("Valid"));
+            .code(2)
+            .stderr(predicate::str::contains("invalid JSON"));
     }
 
     #[test]
-    fn validate_missing_required_field() {
+    fn summary_json_lists_every_job() {
         let dir = TempDir::new().unwrap();
-        let schema = write_temp_file(
-            &dir,
-            "schema.json",
-            r#"{
-                "type": "object",
-                "properties": {
-                    "name": { "type": "string", "ucp_request": "required" }
-                }
-            }"#,
-        );
-        let payload = write_temp_file(&dir, "payload.json", r#"{}"#);
+        let schema = r#"{
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "ucp_request": { "create": "omit", "update": "bogus" } }
+            }
+        }"#;
+        let path = write_temp_file(&dir, "order.json", schema);
+        let out = dir.path().join("out");
+        let summary_path = dir.path().join("summary.json");
 
         cmd()
             .args([
-                "validate",
-                payload.to_str().unwrap(),
-                "--schema",
-                schema.to_str().unwrap(),
-                "--request",
-                "--op",
-                "create",
+                "resolve",
+                path.to_str().unwrap(),
+                "--ops",
+                "create,update",
+                "--both",
             ])
+            .args(["--output-dir", out.to_str().unwrap()])
+            .args(["--summary-json", summary_path.to_str().unwrap()])
             .assert()
-            .code(1)
-            .stderr(predicate::str::contains("Validation failed"));
+            .code(2);
+
+        let summary: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&summary_path).unwrap()).unwrap();
+        assert_eq!(summary["total"], 4);
+        assert_eq!(summary["failed"], 1);
+        let statuses: Vec<(&str, &str, &str)> = summary["jobs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|job| {
+                (
+                    job["op"].as_str().unwrap(),
+                    job["direction"].as_str().unwrap(),
+                    job["status"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("create", "request", "ok"),
+                ("create", "response", "ok"),
+                ("update", "request", "failed"),
+                ("update", "response", "ok"),
+            ]
+        );
+        let failed = &summary["jobs"][2];
+        assert!(failed["error"]
+            .as_str()
+            .unwrap()
+            .contains("unknown visibility"));
+        assert!(failed["duration_ms"].is_u64());
+        assert!(out.join("order-update-response.json").exists());
+        assert!(!out.join("order-update-request.json").exists());
     }
 
     #[test]
-    fn validate_wrong_type() {
-        let dir = TempDir::new().unwrap();
-        let schema = write_temp_file(
-            &dir,
-            "schema.json",
-            r#"{
-                "type": "object",
-                "properties": {
-                    "age": { "type": "number" }
-                }
-            }"#,
-        );
-        let payload = write_temp_file(&dir, "payload.json", r#"{"age": "not-a-number"}"#);
+    fn check_monotonicity_all_ops_reports_update_violation() {
+        cmd()
+            .args([
+                "resolve",
+                "tests/fixtures/monotonicity_update_only.json",
+                "--check-monotonicity-all-ops",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(
+                "request/update: monotonicity violation at /allOf/0/properties/email",
+            ))
+            .stderr(predicate::str::contains("request/create").not())
+            .stderr(predicate::str::contains("1 monotonicity violation(s)"));
 
+        // Resolving create alone succeeds; the pre-flight gate still fails it
+        cmd()
+            .args(["resolve", "tests/fixtures/monotonicity_update_only.json"])
+            .args(["--request", "--op", "create"])
+            .assert()
+            .success();
         cmd()
+            .args(["resolve", "tests/fixtures/monotonicity_update_only.json"])
             .args([
-                "validate",
-                payload.to_str().unwrap(),
-                "--schema",
-                schema.to_str().unwrap(),
                 "--request",
                 "--op",
                 "create",
+                "--check-monotonicity-all-ops",
             ])
             .assert()
-            .code(1)
-            .stderr(predicate::str::contains("Validation failed"));
+            .code(2)
+            .stdout(predicate::str::is_empty());
     }
 
     #[test]
-    fn validate_additional_property_rejected() {
+    fn dedupe_allof_drops_redundant_branch() {
         let dir = TempDir::new().unwrap();
-        let schema = write_temp_file(
-            &dir,
-            "schema.json",
-            r#"{
-                "type": "object",
-                "additionalProperties": false,
-                "properties": {
-                    "id": { "type": "string", "ucp_request": "omit" },
-                    "name": { "type": "string" }
-                }
-            }"#,
-        );
-        // Try to send "id" which is omitted - should be rejected as additional property
-        let payload = write_temp_file(&dir, "payload.json", r#"{"name": "test", "id": "123"}"#);
+        let branch = r#"{"type": "object", "properties": {"id": {"type": "string", "ucp_request": "omit"}}}"#;
+        let schema = format!(r#"{{"allOf": [{branch}, {{}}, {branch}]}}"#);
+        let path = write_temp_file(&dir, "dup.json", &schema);
 
-        cmd()
+        let assert = cmd()
             .args([
-                "validate",
-                payload.to_str().unwrap(),
-                "--schema",
-                schema.to_str().unwrap(),
+                "resolve",
+                path.to_str().unwrap(),
                 "--request",
                 "--op",
                 "create",
             ])
+            .arg("--dedupe-allof")
             .assert()
-            .code(1);
+            .success();
+        let resolved: serde_json::Value =
+            serde_json::from_slice(&assert.get_output().stdout).unwrap();
+        assert!(resolved.get("allOf").is_none());
+        assert_eq!(resolved["type"], "object");
+        assert!(resolved["properties"].get("id").is_none());
     }
 
     #[test]
-    fn validate_json_output_valid() {
+    fn propagate_variants_applies_annotations_across_one_of() {
         let dir = TempDir::new().unwrap();
         let schema = write_temp_file(
             &dir,
             "schema.json",
             r#"{
-                "type": "object",
-                "properties": {
-                    "name": { "type": "string" }
-                }
+                "oneOf": [
+                    { "type": "object", "properties": { "secret": { "type": "string", "ucp_response": "omit" } } },
+                    { "type": "object", "properties": { "secret": { "type": "string" } } }
+                ]
             }"#,
         );
-        let payload = write_temp_file(&dir, "payload.json", r#"{"name": "test"}"#);
 
         cmd()
-            .args([
-                "validate",
-                payload.to_str().unwrap(),
-                "--schema",
-                schema.to_str().unwrap(),
-                "--request",
-                "--op",
-                "create",
-                "--json",
-            ])
+            .arg("resolve")
+            .arg(&schema)
+            .args(["--response", "--op", "read"])
             .assert()
             .success()
-            .stdout(predicate::str::contains(r#"{"valid":true}"#));
+            .stdout(predicate::str::contains("secret"));
+
+        cmd()
+            .arg("resolve")
+            .arg(&schema)
+            .args(["--response", "--op", "read", "--propagate-variants"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("secret").not());
     }
 
     #[test]
-    fn validate_json_output_invalid() {
+    fn preserve_annotations_renames_instead_of_stripping() {
         let dir = TempDir::new().unwrap();
         let schema = write_temp_file(
             &dir,
@@ -382,91 +693,430 @@ mod validate_command {
             r#"{
                 "type": "object",
                 "properties": {
-                    "name": { "type": "string", "ucp_request": "required" }
+                    "id": { "type": "string", "ucp_request": "omit" },
+                    "note": { "type": "string", "ucp_request": "required" }
                 }
             }"#,
         );
-        let payload = write_temp_file(&dir, "payload.json", r#"{}"#);
 
-        cmd()
-            .args([
-                "validate",
-                payload.to_str().unwrap(),
-                "--schema",
-                schema.to_str().unwrap(),
-                "--request",
-                "--op",
-                "create",
-                "--json",
-            ])
+        let assert = cmd()
+            .arg("resolve")
+            .arg(&schema)
+            .args(["--request", "--op", "create", "--preserve-annotations"])
             .assert()
-            .code(1)
-            .stdout(predicate::str::contains(r#""valid":false"#))
-            .stdout(predicate::str::contains(r#""errors":"#));
+            .success();
+        let resolved: serde_json::Value =
+            serde_json::from_slice(&assert.get_output().stdout).unwrap();
+        assert!(resolved["properties"].get("id").is_none());
+        assert_eq!(
+            resolved["properties"]["note"],
+            serde_json::json!({ "type": "string", "x-ucp-request": "required" })
+        );
     }
 
     #[test]
-    fn validate_json_output_file_error() {
+    fn report_records_property_decisions() {
         let dir = TempDir::new().unwrap();
-        let payload = write_temp_file(&dir, "payload.json", r#"{}"#);
+        let report = dir.path().join("report.json");
 
         cmd()
             .args([
-                "validate",
-                payload.to_str().unwrap(),
-                "--schema",
-                "/nonexistent/schema.json",
+                "resolve",
+                "tests/fixtures/checkout.json",
                 "--request",
                 "--op",
                 "create",
-                "--json",
             ])
+            .arg("--report")
+            .arg(&report)
             .assert()
-            .code(3)
-            .stdout(predicate::str::contains(r#""valid":false"#))
-            .stdout(predicate::str::contains(r#""errors":"#));
+            .success();
+        let report: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+        assert_eq!(report["direction"], "request");
+        assert_eq!(report["operation"], "create");
+        let id = report["properties"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|p| p["path"] == "/properties/id")
+            .expect("id is reported");
+        assert_eq!(id["visibility"], "omit");
+        assert_eq!(id["was_required"], true);
+        assert_eq!(id["required"], false);
+
+        cmd()
+            .args(["resolve", "tests/fixtures/checkout.json", "--request"])
+            .args(["--output-dir", "out", "--report", "report.json"])
+            .assert()
+            .code(2);
     }
 }
 
-mod error_handling {
+mod validate_command {
     use super::*;
 
     #[test]
-    fn file_not_found() {
+    fn validate_reads_payload_or_schema_from_stdin() {
+        let dir = TempDir::new().unwrap();
+        let schema_json =
+            r#"{"type": "object", "required": ["id"], "properties": {"id": {"type": "string"}}}"#;
+        let schema = write_temp_file(&dir, "schema.json", schema_json);
+        let payload = write_temp_file(&dir, "payload.json", r#"{"id": "x"}"#);
+
+        cmd()
+            .args(["validate", "-", "--schema"])
+            .arg(&schema)
+            .args(["--request", "--op", "create"])
+            .write_stdin(r#"{"id": 5}"#)
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("/id: 5 is not of type"));
+        cmd()
+            .arg("validate")
+            .arg(&payload)
+            .args(["--schema", "-", "--request", "--op", "create"])
+            .write_stdin(schema_json)
+            .assert()
+            .success()
+            .stdout("Valid\n");
         cmd()
             .args([
-                "resolve",
-                "/nonexistent/schema.json",
+                "validate",
+                "-",
+                "--schema",
+                "-",
                 "--request",
                 "--op",
                 "create",
             ])
+            .write_stdin(schema_json)
             .assert()
-            .code(3)
-            .stderr(
-                predicate::str::contains("not found").or(predicate::str::contains("No such file")),
-            );
+            .code(2)
+            .stderr(predicate::str::contains(
+                "only one input can be read from stdin",
+            ));
     }
 
     #[test]
-    fn invalid_json_schema() {
+    fn validate_many_payloads_with_summary() {
         let dir = TempDir::new().unwrap();
-        let schema = write_temp_file(&dir, "bad.json", r#"{ not valid json"#);
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{"type": "object", "required": ["id"], "properties": {"id": {"type": "string"}}}"#,
+        );
+        let payloads = dir.path().join("payloads");
+        fs::create_dir(&payloads).unwrap();
+        fs::write(payloads.join("a.json"), r#"{"id": "x"}"#).unwrap();
+        fs::write(payloads.join("b.json"), r#"{"id": 5}"#).unwrap();
+        fs::write(payloads.join("notes.txt"), "not a payload").unwrap();
+        let extra = write_temp_file(&dir, "extra.json", r#"{"id": "y"}"#);
+
+        cmd()
+            .arg("validate")
+            .arg(payloads.join("*.json"))
+            .arg(&extra)
+            .arg("--schema")
+            .arg(&schema)
+            .args(["--request", "--op", "create"])
+            .assert()
+            .code(1)
+            .stdout(predicate::str::contains("a.json"))
+            .stdout(predicate::str::contains("    /id: 5 is not of type"))
+            .stdout(predicate::str::contains("notes.txt").not())
+            .stdout(predicate::str::contains(
+                "3 payload(s) validated: 2 valid, 1 failed",
+            ));
+
+        let output = cmd()
+            .arg("validate")
+            .arg(payloads.join("a.json"))
+            .arg(&extra)
+            .arg(dir.path().join("missing.json"))
+            .arg("--schema")
+            .arg(&schema)
+            .args(["--request", "--op", "create", "--json"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(report["valid"], false);
+        assert_eq!(
+            (report["total"].as_u64(), report["failed"].as_u64()),
+            (Some(3), Some(1))
+        );
+        assert_eq!(report["files"][0]["valid"], true);
+        assert!(report["files"][2]["error"]
+            .as_str()
+            .unwrap()
+            .contains("loading payload"));
+    }
+
+    #[test]
+    fn validate_many_payloads_on_threads_keeps_input_order() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{"type": "object", "required": ["id"], "properties": {"id": {"type": "string"}}}"#,
+        );
+        let paths: Vec<std::path::PathBuf> = (0..24)
+            .map(|i| {
+                let body = if i % 5 == 3 {
+                    format!(r#"{{"id": {}}}"#, i)
+                } else {
+                    format!(r#"{{"id": "p{}"}}"#, i)
+                };
+                write_temp_file(&dir, &format!("p{:02}.json", i), &body)
+            })
+            .collect();
+
+        let output = cmd()
+            .arg("validate")
+            .args(&paths)
+            .arg("--schema")
+            .arg(&schema)
+            .args(["--request", "--op", "create", "--threads", "4", "--json"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(
+            (report["total"].as_u64(), report["failed"].as_u64()),
+            (Some(24), Some(5))
+        );
+        let files = report["files"].as_array().unwrap();
+        for (i, (file, path)) in files.iter().zip(&paths).enumerate() {
+            assert_eq!(file["file"], path.display().to_string());
+            assert_eq!(file["valid"], i % 5 != 3);
+        }
+
+        // All valid on every thread: exit 0
+        cmd()
+            .arg("validate")
+            .args(&paths[..3])
+            .arg("--schema")
+            .arg(&schema)
+            .args(["--request", "--op", "create", "--threads", "0"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "3 payload(s) validated, all valid",
+            ));
+    }
+
+    #[test]
+    fn validate_junit_report_has_a_case_per_payload() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{"type": "object", "required": ["id"], "properties": {"id": {"type": "string"}}}"#,
+        );
+        let ok = write_temp_file(&dir, "ok.json", r#"{"id": "a"}"#);
+        let bad = write_temp_file(&dir, "bad.json", r#"{"id": 5}"#);
+
+        cmd()
+            .arg("validate")
+            .args([&ok, &bad])
+            .arg("--schema")
+            .arg(&schema)
+            .args(["--request", "--op", "create", "--format", "junit"])
+            .assert()
+            .code(1)
+            .stdout(predicate::str::contains(r#"tests="2" failures="1" errors="0""#))
+            .stdout(predicate::str::contains(format!(
+                r#"<testcase name="{}" classname="request/create"/>"#,
+                ok.display()
+            )))
+            .stdout(predicate::str::contains(
+                r#"<failure message="1 validation error">/id: 5 is not of type &quot;string&quot;</failure>"#,
+            ));
+
+        // A single payload still gets a report
+        cmd()
+            .arg("validate")
+            .arg(&ok)
+            .arg("--schema")
+            .arg(&schema)
+            .args(["--request", "--op", "create", "--format", "junit"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(r#"tests="1" failures="0""#));
+    }
+
+    #[test]
+    fn validate_problem_json_on_failure() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{"type": "object", "required": ["id"], "properties": {"id": {"type": "string"}}}"#,
+        );
+        let bad = write_temp_file(&dir, "bad.json", r#"{"id": 5}"#);
+
+        let output = cmd()
+            .arg("validate")
+            .arg(&bad)
+            .arg("--schema")
+            .arg(&schema)
+            .args(["--request", "--op", "create", "--format", "problem-json"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        let problem: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(problem["status"], 422);
+        assert_eq!(problem["type"], "urn:ucp-schema:problem:validation-failed");
+        assert_eq!(problem["errors"][0]["pointer"], "/id");
+
+        let output = cmd()
+            .arg("validate")
+            .arg(&bad)
+            .args(["--schema", "missing.json"])
+            .args(["--request", "--op", "create", "--format", "problem-json"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        let problem: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(problem["status"], 503);
+        assert!(problem["detail"].as_str().unwrap().contains("missing.json"));
+        assert!(problem.get("errors").is_none());
+    }
+
+    #[test]
+    fn validate_ndjson_streams_one_result_per_line() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{"type": "object", "required": ["id"], "properties": {"id": {"type": "string"}}}"#,
+        );
+        let records = "{\"id\": \"a\"}\n\n{\"id\": 5}\nnot json\n{\"id\": \"b\"}\n";
+
+        let assert = cmd()
+            .args(["validate", "--ndjson", "-", "--schema"])
+            .arg(&schema)
+            .args(["--request", "--op", "create"])
+            .write_stdin(records)
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("Validated 4 record(s): 2 failed"));
+        let lines: Vec<serde_json::Value> = String::from_utf8_lossy(&assert.get_output().stdout)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["valid"], true);
+        assert_eq!(lines[1]["line"], 3);
+        assert_eq!(lines[1]["errors"][0]["path"], "/id");
+        assert!(lines[2]["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("invalid JSON"));
+        assert_eq!(lines[3]["line"], 5);
+
+        cmd()
+            .args(["validate", "--ndjson", "-", "--op", "create"])
+            .write_stdin(records)
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(
+                "--ndjson needs --schema or --profile",
+            ));
+    }
+
+    #[test]
+    fn validate_many_payloads_needs_schema_source() {
+        let dir = TempDir::new().unwrap();
+        let a = write_temp_file(&dir, "a.json", "{}");
+
+        cmd()
+            .arg("validate")
+            .args([&a, &a])
+            .args(["--op", "read"])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("needs --schema or --profile"));
+        cmd()
+            .arg("validate")
+            .arg(dir.path().join("*.yaml"))
+            .args(["--schema", "tests/fixtures/checkout.json", "--op", "read"])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("no files match"));
+    }
 
+    #[test]
+    fn validate_deref_payload_expands_shared_object() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r##"{
+                "type": "object",
+                "properties": {
+                    "addresses": { "type": "object" },
+                    "billing": { "$ref": "#/$defs/address" },
+                    "shipping": { "$ref": "#/$defs/address" }
+                },
+                "$defs": {
+                    "address": {
+                        "type": "object",
+                        "required": ["street", "city"],
+                        "properties": {
+                            "street": { "type": "string" },
+                            "city": { "type": "string" }
+                        }
+                    }
+                }
+            }"##,
+        );
+        let payload = write_temp_file(
+            &dir,
+            "payload.json",
+            r##"{
+                "addresses": { "home": { "street": "1 Main St", "city": "Anytown" } },
+                "billing": { "$ref": "#/addresses/home" },
+                "shipping": { "$ref": "#/addresses/home" }
+            }"##,
+        );
+        let args = [
+            "validate",
+            payload.to_str().unwrap(),
+            "--schema",
+            schema.to_str().unwrap(),
+            "--request",
+            "--op",
+            "create",
+        ];
+
+        // As stored, the reference objects lack the address fields
+        cmd().args(args).assert().code(1);
+        cmd().args(args).arg("--deref-payload").assert().success();
+
+        let cyclic = write_temp_file(
+            &dir,
+            "cyclic.json",
+            r##"{"billing": {"next": {"$ref": "#/billing"}}}"##,
+        );
         cmd()
+            .args(["validate", cyclic.to_str().unwrap()])
             .args([
-                "resolve",
+                "--schema",
                 schema.to_str().unwrap(),
                 "--request",
                 "--op",
                 "create",
             ])
+            .arg("--deref-payload")
             .assert()
-            .code(2);
+            .code(2)
+            .stderr(predicate::str::contains("cycle"));
     }
 
     #[test]
-    fn invalid_annotation_type() {
+    fn validate_annotate_payload_marks_unexpected_field() {
         let dir = TempDir::new().unwrap();
         let schema = write_temp_file(
             &dir,
@@ -474,26 +1124,40 @@ mod error_handling {
             r#"{
                 "type": "object",
                 "properties": {
-                    "id": { "type": "string", "ucp_request": 123 }
+                    "name": { "type": "string", "ucp_request": "required" }
                 }
             }"#,
         );
+        let payload = write_temp_file(&dir, "payload.json", r#"{"name": "test", "nickname": "t"}"#);
 
-        cmd()
+        let assert = cmd()
             .args([
-                "resolve",
+                "validate",
+                payload.to_str().unwrap(),
+                "--schema",
                 schema.to_str().unwrap(),
                 "--request",
                 "--op",
                 "create",
+                "--annotate-payload",
             ])
             .assert()
-            .code(2)
-            .stderr(predicate::str::contains("annotation"));
+            .success();
+
+        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+        let annotated: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        assert_eq!(
+            annotated,
+            serde_json::json!({
+                "name": "test",
+                "nickname": "t",
+                "x-ucp-status": { "name": "ok", "nickname": "unexpected" }
+            })
+        );
     }
 
     #[test]
-    fn unknown_visibility_value() {
+    fn validate_valid_payload() {
         let dir = TempDir::new().unwrap();
         let schema = write_temp_file(
             &dir,
@@ -501,205 +1165,308 @@ mod error_handling {
             r#"{
                 "type": "object",
                 "properties": {
-                    "id": { "type": "string", "ucp_request": "readonly" }
+                    "name": { "type": "string", "ucp_request": "required" }
                 }
             }"#,
         );
+        let payload = write_temp_file(&dir, "payload.json", r#"{"name": "test"}"#);
 
         cmd()
             .args([
-                "resolve",
+                "validate",
+                payload.to_str().unwrap(),
+                "--schema",
                 schema.to_str().unwrap(),
                 "--request",
                 "--op",
                 "create",
             ])
             .assert()
-            .code(2)
-            .stderr(predicate::str::contains("unknown visibility"));
-    }
-}
-
-mod required_args {
-    use super::*;
-
-    #[test]
-    fn missing_direction_flag() {
-        let dir = TempDir::new().unwrap();
-        let schema = write_temp_file(&dir, "schema.json", r#"{"type":"object"}"#);
-
-        cmd()
-            .args(["resolve", schema.to_str().unwrap(), "--op", "create"])
-            .assert()
-            .failure()
-            .stderr(
-                predicate::str::contains("--request").or(predicate::str::contains("--response")),
-            );
+            .success()
+            .stdout(predicate::str::contains("Valid"));
     }
 
     #[test]
-    fn missing_op_flag() {
+    fn validate_reject_readonly() {
         let dir = TempDir::new().unwrap();
-        let schema = write_temp_file(&dir, "schema.json", r#"{"type":"object"}"#);
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{
+                "type": "object",
+                "properties": {
+                    "id": { "type": "string", "readOnly": true },
+                    "name": { "type": "string" }
+                }
+            }"#,
+        );
+        let payload = write_temp_file(&dir, "payload.json", r#"{"id": "x", "name": "test"}"#);
+        let args = [
+            "validate",
+            payload.to_str().unwrap(),
+            "--schema",
+            schema.to_str().unwrap(),
+            "--request",
+            "--op",
+            "create",
+        ];
+
+        // readOnly is advisory by default
+        cmd().args(args).assert().success();
 
         cmd()
-            .args(["resolve", schema.to_str().unwrap(), "--request"])
+            .args(args)
+            .arg("--reject-readonly")
             .assert()
-            .failure()
-            .stderr(predicate::str::contains("--op"));
+            .code(1)
+            .stderr(predicate::str::contains("/id"));
     }
 
     #[test]
-    fn conflicting_direction_flags() {
+    fn validate_error_format_template() {
         let dir = TempDir::new().unwrap();
-        let schema = write_temp_file(&dir, "schema.json", r#"{"type":"object"}"#);
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{
+                "type": "object",
+                "properties": {
+                    "quantity": { "type": "integer" }
+                }
+            }"#,
+        );
+        let payload = write_temp_file(&dir, "payload.json", r#"{"quantity": "two"}"#);
+        let args = [
+            "validate",
+            payload.to_str().unwrap(),
+            "--schema",
+            schema.to_str().unwrap(),
+            "--request",
+            "--op",
+            "create",
+        ];
 
         cmd()
+            .args(args)
             .args([
-                "resolve",
-                schema.to_str().unwrap(),
-                "--request",
-                "--response",
-                "--op",
-                "create",
+                "--error-format",
+                "{path} [{schemaPath}] {keyword}: {message}",
             ])
             .assert()
-            .failure()
-            .stderr(predicate::str::contains("cannot be used with"));
-    }
+            .code(1)
+            .stderr(predicate::str::diff(
+                "/quantity [/properties/quantity/type] type: \"two\" is not of type \"integer\"\n",
+            ));
 
-    #[test]
-    fn missing_schema_path() {
         cmd()
-            .args(["resolve", "--request", "--op", "create"])
+            .args(args)
+            .args(["--error-format", "{path}: {severity}"])
             .assert()
-            .failure();
+            .code(2)
+            .stderr(predicate::str::contains("unknown placeholder {severity}"));
     }
 
     #[test]
-    fn missing_payload_for_validate() {
-        // Payload is now required positional argument
-        cmd()
-            .args(["validate", "--request", "--op", "create"])
-            .assert()
-            .failure()
-            .stderr(predicate::str::contains("PAYLOAD"));
-    }
-}
+    fn validate_op_any() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{
+                "type": "object",
+                "properties": {
+                    "id": { "type": "string", "ucp_request": { "create": "omit", "update": "required" } },
+                    "name": { "type": "string", "ucp_request": { "create": "required" } }
+                }
+            }"#,
+        );
+        let payload = write_temp_file(&dir, "payload.json", r#"{"id": "chk_1"}"#);
+        let base = [
+            "validate",
+            payload.to_str().unwrap(),
+            "--schema",
+            schema.to_str().unwrap(),
+            "--request",
+        ];
 
-mod help_and_version {
-    use super::*;
+        cmd().args(base).args(["--op", "create"]).assert().code(1);
 
-    #[test]
-    fn help_flag() {
         cmd()
-            .arg("--help")
+            .args(base)
+            .args(["--op-any", "create,update"])
             .assert()
             .success()
-            .stdout(predicate::str::contains("Resolve and validate UCP schema"));
-    }
+            .stdout(predicate::str::contains("Valid (update)"));
 
-    #[test]
-    fn version_flag() {
+        // No match: errors are reported for every attempted op
+        let empty = write_temp_file(&dir, "empty.json", r#"{}"#);
         cmd()
-            .arg("--version")
+            .args([
+                "validate",
+                empty.to_str().unwrap(),
+                "--schema",
+                schema.to_str().unwrap(),
+                "--request",
+                "--op-any",
+                "create,update",
+            ])
             .assert()
-            .success()
-            .stdout(predicate::str::contains("ucp-schema"));
+            .code(1)
+            .stderr(predicate::str::contains("[create]"))
+            .stderr(predicate::str::contains("[update]"));
     }
 
     #[test]
-    fn resolve_help() {
-        cmd()
-            .args(["resolve", "--help"])
-            .assert()
-            .success()
-            .stdout(predicate::str::contains("--request"))
-            .stdout(predicate::str::contains("--response"))
-            .stdout(predicate::str::contains("--op"))
-            .stdout(predicate::str::contains("--schema-local-base"))
-            .stdout(predicate::str::contains("--schema-remote-base"));
-    }
+    fn validate_op_any_json_reports_matched_ops() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{
+                "type": "object",
+                "properties": {
+                    "id": { "type": "string", "ucp_request": { "create": "omit", "update": "required" } },
+                    "name": { "type": "string", "ucp_request": { "create": "required" } }
+                }
+            }"#,
+        );
+        let run = |payload: &str, ops: &str| {
+            let payload = write_temp_file(&dir, "payload.json", payload);
+            let assert = cmd()
+                .args([
+                    "validate",
+                    payload.to_str().unwrap(),
+                    "--schema",
+                    schema.to_str().unwrap(),
+                    "--request",
+                    "--op-any",
+                    ops,
+                    "--json",
+                ])
+                .assert();
+            let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+            serde_json::from_str::<serde_json::Value>(&stdout).unwrap()
+        };
+
+        // Valid only under update
+        let output = run(r#"{"id": "chk_1"}"#, "create,update");
+        assert_eq!(output["valid"], true);
+        assert_eq!(output["matched_op"], "update");
+        assert_eq!(output["matched_ops"], serde_json::json!(["update"]));
+
+        // Valid under every op; the first listed is matched_op
+        let output = run(r#"{"id": "chk_1", "name": "n"}"#, "create,update,complete");
+        assert_eq!(output["matched_op"], "create");
+        assert_eq!(
+            output["matched_ops"],
+            serde_json::json!(["create", "update", "complete"])
+        );
 
-    #[test]
-    fn validate_help() {
-        cmd()
-            .args(["validate", "--help"])
-            .assert()
-            .success()
-            .stdout(predicate::str::contains("--request"))
-            .stdout(predicate::str::contains("--response"))
-            .stdout(predicate::str::contains("--op"));
+        let output = run(r#"{"id": 5}"#, "create,update");
+        assert_eq!(output["valid"], false);
+        assert!(output["matched_op"].is_null());
+        assert_eq!(output["matched_ops"], serde_json::json!([]));
     }
-}
-
-mod fixtures {
-    use super::*;
 
     #[test]
-    fn resolve_checkout_fixture_create() {
-        let fixture = "tests/fixtures/checkout.json";
+    fn validate_op_any_with_one_op_reports_matched_op() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{"type": "object", "properties": {"id": {"type": "string", "ucp_request": {"update": "required"}}}}"#,
+        );
+        let payload = write_temp_file(&dir, "payload.json", r#"{"id": "chk_1"}"#);
+        let output = cmd()
+            .arg("validate")
+            .arg(&payload)
+            .arg("--schema")
+            .arg(&schema)
+            .args(["--request", "--op-any", "update", "--json"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let output: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(output["matched_op"], "update");
+        assert_eq!(output["matched_ops"], serde_json::json!(["update"]));
 
         cmd()
-            .args(["resolve", fixture, "--request", "--op", "create"])
+            .arg("validate")
+            .arg(&payload)
+            .arg("--schema")
+            .arg(&schema)
+            .args(["--request", "--op-any", "update"])
             .assert()
             .success()
-            // line_items is required for create
-            .stdout(predicate::str::contains("line_items"));
+            .stdout(predicate::str::contains("Valid (update)"));
     }
 
     #[test]
-    fn resolve_checkout_fixture_update() {
-        let fixture = "tests/fixtures/checkout.json";
-
+    fn validate_op_any_conflicts_with_op() {
         cmd()
-            .args(["resolve", fixture, "--request", "--op", "update"])
+            .args([
+                "validate",
+                "payload.json",
+                "--op",
+                "create",
+                "--op-any",
+                "create,update",
+            ])
             .assert()
-            .success()
-            // id is required for update
-            .stdout(predicate::str::contains(r#""required":["id"]"#));
+            .code(2);
     }
 
     #[test]
-    fn validate_checkout_create_valid() {
+    fn validate_missing_required_field() {
         let dir = TempDir::new().unwrap();
-        let payload = write_temp_file(
+        let schema = write_temp_file(
             &dir,
-            "payload.json",
+            "schema.json",
             r#"{
-                "line_items": [
-                    { "sku": "ABC123", "quantity": 2 }
-                ]
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "ucp_request": "required" }
+                }
             }"#,
         );
+        let payload = write_temp_file(&dir, "payload.json", r#"{}"#);
 
         cmd()
             .args([
                 "validate",
                 payload.to_str().unwrap(),
                 "--schema",
-                "tests/fixtures/checkout.json",
+                schema.to_str().unwrap(),
                 "--request",
                 "--op",
                 "create",
             ])
             .assert()
-            .success()
-            .stdout(predicate::str::contains("Valid"));
+            .code(1)
+            .stderr(predicate::str::contains("Validation failed"));
     }
 
     #[test]
-    fn validate_checkout_create_missing_required() {
+    fn validate_wrong_type() {
         let dir = TempDir::new().unwrap();
-        // Missing line_items which is required for create
-        let payload = write_temp_file(&dir, "payload.json", r#"{}"#);
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{
+                "type": "object",
+                "properties": {
+                    "age": { "type": "number" }
+                }
+            }"#,
+        );
+        let payload = write_temp_file(&dir, "payload.json", r#"{"age": "not-a-number"}"#);
 
         cmd()
             .args([
                 "validate",
                 payload.to_str().unwrap(),
                 "--schema",
-                "tests/fixtures/checkout.json",
+                schema.to_str().unwrap(),
                 "--request",
                 "--op",
                 "create",
@@ -708,280 +1475,216 @@ mod fixtures {
             .code(1)
             .stderr(predicate::str::contains("Validation failed"));
     }
-}
-
-/// Bundle flag tests - resolve external $refs
-mod bundle {
-    use super::*;
 
     #[test]
-    fn bundle_resolves_external_ref() {
+    fn validate_additional_property_rejected() {
         let dir = TempDir::new().unwrap();
-
-        // Create a referenced type schema
-        fs::create_dir_all(dir.path().join("types")).unwrap();
-        fs::write(
-            dir.path().join("types/buyer.json"),
-            r#"{"type":"object","properties":{"email":{"type":"string"}}}"#,
-        )
-        .unwrap();
-
-        // Create main schema with $ref
         let schema = write_temp_file(
             &dir,
             "schema.json",
             r#"{
                 "type": "object",
+                "additionalProperties": false,
                 "properties": {
-                    "buyer": { "$ref": "types/buyer.json" }
+                    "id": { "type": "string", "ucp_request": "omit" },
+                    "name": { "type": "string" }
                 }
             }"#,
         );
+        // Try to send "id" which is omitted - should be rejected as additional property
+        let payload = write_temp_file(&dir, "payload.json", r#"{"name": "test", "id": "123"}"#);
 
         cmd()
             .args([
-                "resolve",
+                "validate",
+                payload.to_str().unwrap(),
+                "--schema",
                 schema.to_str().unwrap(),
                 "--request",
                 "--op",
                 "create",
-                "--bundle",
             ])
             .assert()
-            .success()
-            // $ref should be resolved, email property should be present
-            .stdout(predicate::str::contains(r#""email""#))
-            // No $ref should remain (except self-refs)
-            .stdout(predicate::str::contains(r#""$ref":"types/buyer.json""#).not());
+            .code(1);
     }
 
     #[test]
-    fn bundle_resolves_fragment_ref() {
+    fn validate_json_output_valid() {
         let dir = TempDir::new().unwrap();
-
-        // Create schema with $defs
-        fs::create_dir_all(dir.path().join("types")).unwrap();
-        fs::write(
-            dir.path().join("types/common.json"),
-            r#"{
-                "$defs": {
-                    "address": {
-                        "type": "object",
-                        "properties": {
-                            "street": { "type": "string" }
-                        }
-                    }
-                }
-            }"#,
-        )
-        .unwrap();
-
-        // Reference specific $def with fragment
         let schema = write_temp_file(
             &dir,
             "schema.json",
             r#"{
                 "type": "object",
                 "properties": {
-                    "shipping": { "$ref": "types/common.json#/$defs/address" }
+                    "name": { "type": "string" }
                 }
             }"#,
         );
+        let payload = write_temp_file(&dir, "payload.json", r#"{"name": "test"}"#);
 
         cmd()
             .args([
-                "resolve",
+                "validate",
+                payload.to_str().unwrap(),
+                "--schema",
                 schema.to_str().unwrap(),
                 "--request",
                 "--op",
                 "create",
-                "--bundle",
+                "--json",
             ])
             .assert()
             .success()
-            // Fragment should be resolved, street property should be present
-            .stdout(predicate::str::contains(r#""street""#));
+            .stdout(predicate::str::contains(r#"{"valid":true}"#));
     }
 
     #[test]
-    fn bundle_preserves_self_root_ref() {
+    fn validate_json_output_invalid() {
         let dir = TempDir::new().unwrap();
-
-        // Create schema with self-root ref (recursive type)
-        fs::create_dir_all(dir.path().join("types")).unwrap();
-        fs::write(
-            dir.path().join("types/node.json"),
-            r##"{
-                "type": "object",
-                "properties": {
-                    "value": { "type": "string" },
-                    "children": {
-                        "type": "array",
-                        "items": { "$ref": "#" }
-                    }
-                }
-            }"##,
-        )
-        .unwrap();
-
         let schema = write_temp_file(
             &dir,
             "schema.json",
             r#"{
                 "type": "object",
                 "properties": {
-                    "tree": { "$ref": "types/node.json" }
+                    "name": { "type": "string", "ucp_request": "required" }
                 }
             }"#,
         );
+        let payload = write_temp_file(&dir, "payload.json", r#"{}"#);
 
         cmd()
             .args([
-                "resolve",
+                "validate",
+                payload.to_str().unwrap(),
+                "--schema",
                 schema.to_str().unwrap(),
                 "--request",
                 "--op",
                 "create",
-                "--bundle",
+                "--json",
             ])
             .assert()
-            .success()
-            // Self-root ref should be preserved (can't inline recursive)
-            .stdout(predicate::str::contains(r##""$ref":"#""##));
+            .code(1)
+            .stdout(predicate::str::contains(r#""valid":false"#))
+            .stdout(predicate::str::contains(r#""errors":"#));
     }
 
     #[test]
-    fn bundle_resolves_internal_refs_in_external_files() {
+    fn validate_json_output_file_error() {
         let dir = TempDir::new().unwrap();
+        let payload = write_temp_file(&dir, "payload.json", r#"{}"#);
 
-        // External file with internal $defs reference
-        fs::create_dir_all(dir.path().join("types")).unwrap();
-        fs::write(
-            dir.path().join("types/wrapper.json"),
-            r##"{
-                "$defs": {
-                    "inner": {
-                        "type": "string",
-                        "minLength": 1
-                    }
-                },
-                "type": "object",
-                "properties": {
-                    "data": { "$ref": "#/$defs/inner" }
-                }
-            }"##,
-        )
-        .unwrap();
-
+        cmd()
+            .args([
+                "validate",
+                payload.to_str().unwrap(),
+                "--schema",
+                "/nonexistent/schema.json",
+                "--request",
+                "--op",
+                "create",
+                "--json",
+            ])
+            .assert()
+            .code(3)
+            .stdout(predicate::str::contains(r#""valid":false"#))
+            .stdout(predicate::str::contains(r#""errors":"#));
+    }
+
+    #[test]
+    fn validate_show_strict_delta_warns_on_extra_field() {
+        let dir = TempDir::new().unwrap();
         let schema = write_temp_file(
             &dir,
             "schema.json",
             r#"{
                 "type": "object",
                 "properties": {
-                    "wrapped": { "$ref": "types/wrapper.json" }
+                    "id": { "type": "string" }
                 }
             }"#,
         );
+        let payload = write_temp_file(&dir, "payload.json", r#"{"id": "a", "nickname": "b"}"#);
+        let args = [
+            "validate",
+            payload.to_str().unwrap(),
+            "--schema",
+            schema.to_str().unwrap(),
+            "--request",
+            "--op",
+            "create",
+        ];
+
+        // Lenient pass still succeeds; the strict finding is only a warning
+        cmd()
+            .args(args)
+            .arg("--show-strict-delta")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Valid"))
+            .stderr(predicate::str::contains(
+                "Warning: strict mode would reject:",
+            ))
+            .stderr(predicate::str::contains("'nickname' was unexpected"));
+
+        // Without the flag nothing is reported
+        cmd()
+            .args(args)
+            .assert()
+            .success()
+            .stderr(predicate::str::is_empty());
+    }
+}
+
+mod error_handling {
+    use super::*;
 
+    #[test]
+    fn file_not_found() {
         cmd()
             .args([
                 "resolve",
-                schema.to_str().unwrap(),
+                "/nonexistent/schema.json",
                 "--request",
                 "--op",
                 "create",
-                "--bundle",
             ])
             .assert()
-            .success()
-            // Internal ref should be resolved
-            .stdout(predicate::str::contains(r#""minLength""#));
+            .code(3)
+            .stderr(
+                predicate::str::contains("not found").or(predicate::str::contains("No such file")),
+            );
     }
 
     #[test]
-    fn bundle_resolves_internal_defs_in_root_schema() {
+    fn invalid_json_schema() {
         let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "bad.json", r#"{ not valid json"#);
 
-        // Root schema with $defs and an internal $ref — the ref should be inlined
-        let schema = write_temp_file(
-            &dir,
-            "schema.json",
-            r##"{
-                "$defs": {
-                    "search_filter": {
-                        "type": "object",
-                        "properties": {
-                            "available": { "type": "boolean" }
-                        }
-                    }
-                },
-                "type": "object",
-                "properties": {
-                    "search_filters": {
-                        "allOf": [
-                            { "$ref": "#/$defs/search_filter" }
-                        ]
-                    }
-                }
-            }"##,
-        );
-
-        let assert = cmd()
+        cmd()
             .args([
                 "resolve",
                 schema.to_str().unwrap(),
                 "--request",
                 "--op",
                 "create",
-                "--bundle",
             ])
             .assert()
-            .success();
-
-        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
-        let bundled: serde_json::Value = serde_json::from_str(&stdout).unwrap();
-
-        // The internal #/$defs/ ref should be inlined
-        let all_of = &bundled["properties"]["search_filters"]["allOf"];
-        let first_entry = &all_of[0];
-
-        // $ref should be removed (inlined)
-        assert!(
-            first_entry.get("$ref").is_none(),
-            "Internal #/$defs/ ref should be inlined, but $ref still present: {first_entry}"
-        );
-        // The inlined content should have the 'available' property
-        assert!(
-            first_entry["properties"]["available"]["type"].as_str() == Some("boolean"),
-            "Inlined def should contain 'available: boolean', got: {first_entry}"
-        );
+            .code(2);
     }
 
     #[test]
-    fn bundle_detects_circular_refs() {
+    fn invalid_annotation_type() {
         let dir = TempDir::new().unwrap();
-
-        // Create circular reference: a.json -> b.json -> a.json
-        fs::create_dir_all(dir.path().join("types")).unwrap();
-        fs::write(
-            dir.path().join("types/a.json"),
-            r#"{"type":"object","properties":{"b":{"$ref":"b.json"}}}"#,
-        )
-        .unwrap();
-        fs::write(
-            dir.path().join("types/b.json"),
-            r#"{"type":"object","properties":{"a":{"$ref":"a.json"}}}"#,
-        )
-        .unwrap();
-
         let schema = write_temp_file(
             &dir,
             "schema.json",
             r#"{
                 "type": "object",
                 "properties": {
-                    "start": { "$ref": "types/a.json" }
+                    "id": { "type": "string", "ucp_request": 123 }
                 }
             }"#,
         );
@@ -993,37 +1696,26 @@ mod bundle {
                 "--request",
                 "--op",
                 "create",
-                "--bundle",
             ])
             .assert()
-            .failure()
-            .stderr(predicate::str::contains("circular"));
+            .code(2)
+            .stderr(predicate::str::contains("annotation"));
     }
 
     #[test]
-    fn bundle_output_is_valid_json() {
+    fn unknown_visibility_value() {
         let dir = TempDir::new().unwrap();
-
-        fs::create_dir_all(dir.path().join("types")).unwrap();
-        fs::write(
-            dir.path().join("types/item.json"),
-            r#"{"type":"object","properties":{"id":{"type":"string"}}}"#,
-        )
-        .unwrap();
-
         let schema = write_temp_file(
             &dir,
             "schema.json",
             r#"{
                 "type": "object",
                 "properties": {
-                    "item": { "$ref": "types/item.json" }
+                    "id": { "type": "string", "ucp_request": "hidden" }
                 }
             }"#,
         );
 
-        let output = dir.path().join("bundled.json");
-
         cmd()
             .args([
                 "resolve",
@@ -1031,220 +1723,190 @@ mod bundle {
                 "--request",
                 "--op",
                 "create",
-                "--bundle",
-                "--output",
-                output.to_str().unwrap(),
             ])
             .assert()
-            .success();
-
-        // Verify output is valid JSON
-        let content = fs::read_to_string(&output).unwrap();
-        let parsed: Result<serde_json::Value, _> = serde_json::from_str(&content);
-        assert!(parsed.is_ok(), "Bundle output should be valid JSON");
+            .code(2)
+            .stderr(predicate::str::contains("unknown visibility"));
     }
 }
 
-/// Remote schema loading tests — use local mock server (no external dependencies)
-mod remote {
+mod required_args {
     use super::*;
 
     #[test]
-    fn resolve_from_url() {
-        let mut server = mockito::Server::new();
-        let mock = server
-            .mock("GET", "/schema.json")
-            .with_body(r#"{"type": "object", "properties": {"name": {"type": "string"}}}"#)
-            .create();
+    fn missing_direction_flag() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "schema.json", r#"{"type":"object"}"#);
 
         cmd()
-            .args([
-                "resolve",
-                &format!("{}/schema.json", server.url()),
-                "--request",
-                "--op",
-                "create",
-            ])
+            .args(["resolve", schema.to_str().unwrap(), "--op", "create"])
             .assert()
-            .success()
-            .stdout(predicate::str::contains("name"));
-
-        mock.assert();
+            .failure()
+            .stderr(
+                predicate::str::contains("--request").or(predicate::str::contains("--response")),
+            );
     }
 
     #[test]
-    fn resolve_url_404() {
-        let mut server = mockito::Server::new();
-        let mock = server
-            .mock("GET", "/missing.json")
-            .with_status(404)
-            .create();
+    fn missing_op_flag() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "schema.json", r#"{"type":"object"}"#);
 
         cmd()
-            .args([
-                "resolve",
-                &format!("{}/missing.json", server.url()),
-                "--request",
-                "--op",
-                "create",
-            ])
+            .args(["resolve", schema.to_str().unwrap(), "--request"])
             .assert()
-            .code(3)
-            .stderr(
-                predicate::str::contains("failed to fetch").or(predicate::str::contains("404")),
-            );
-
-        mock.assert();
+            .failure()
+            .stderr(predicate::str::contains("--op"));
     }
 
     #[test]
-    fn resolve_url_invalid_host() {
-        // DNS failure is local — no mock needed
+    fn conflicting_direction_flags() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "schema.json", r#"{"type":"object"}"#);
+
         cmd()
             .args([
                 "resolve",
-                "https://this-domain-does-not-exist-12345.invalid/schema.json",
+                schema.to_str().unwrap(),
                 "--request",
+                "--response",
                 "--op",
                 "create",
             ])
             .assert()
-            .code(3);
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
     }
 
     #[test]
-    fn validate_with_remote_schema() {
-        let mut server = mockito::Server::new();
-        let mock = server
-            .mock("GET", "/schema.json")
-            .with_body(r#"{"type": "object", "properties": {"name": {"type": "string"}}}"#)
-            .create();
-
-        let dir = TempDir::new().unwrap();
-        let payload = write_temp_file(&dir, "payload.json", r#"{"name": "test"}"#);
-
+    fn missing_schema_path() {
         cmd()
-            .args([
-                "validate",
-                payload.to_str().unwrap(),
-                "--schema",
-                &format!("{}/schema.json", server.url()),
-                "--request",
-                "--op",
-                "create",
-            ])
+            .args(["resolve", "--request", "--op", "create"])
             .assert()
-            .success();
+            .failure();
+    }
 
-        mock.assert();
+    #[test]
+    fn missing_payload_for_validate() {
+        // Payload is now required positional argument
+        cmd()
+            .args(["validate", "--request", "--op", "create"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("PAYLOAD"));
     }
 }
 
-/// Schema composition tests - self-describing payloads
-mod compose {
+mod help_and_version {
     use super::*;
 
     #[test]
-    fn self_describing_checkout_only() {
-        // Validate a self-describing response against local schemas
-        // Note: --strict=false because strict mode + allOf composition conflict
+    fn help_flag() {
         cmd()
-            .args([
-                "validate",
-                "tests/fixtures/compose/response_checkout_only.json",
-                "--schema-local-base",
-                "tests/fixtures/compose",
-                "--response",
-                "--op",
-                "read",
-                "--strict=false",
-            ])
+            .arg("--help")
             .assert()
             .success()
-            .stdout(predicate::str::contains("Valid"));
+            .stdout(predicate::str::contains("Resolve and validate UCP schema"));
     }
 
     #[test]
-    fn self_describing_with_extensions() {
-        // Validate a self-describing response with discount + fulfillment extensions
-        // Note: --strict=false because strict mode + allOf composition conflict
+    fn version_flag() {
         cmd()
-            .args([
-                "validate",
-                "tests/fixtures/compose/response_with_extensions.json",
-                "--schema-local-base",
-                "tests/fixtures/compose",
-                "--response",
-                "--op",
-                "read",
-                "--strict=false",
-            ])
+            .arg("--version")
             .assert()
             .success()
-            .stdout(predicate::str::contains("Valid"));
+            .stdout(predicate::str::contains("ucp-schema"));
     }
 
     #[test]
-    fn direction_auto_inferred_response() {
-        // Direction should be auto-inferred from ucp.capabilities
-        // Note: --strict=false because strict mode + allOf composition conflict
+    fn version_json() {
+        let assert = cmd().args(["version", "--json"]).assert().success();
+        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+        let info: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+
+        assert_eq!(info["name"], "ucp-schema");
+        let version = info["version"].as_str().unwrap();
+        let parts: Vec<&str> = version.split('.').collect();
+        assert_eq!(parts.len(), 3, "not semver: {}", version);
+        assert!(parts.iter().all(|p| p.parse::<u64>().is_ok()));
+        assert!(info["git_sha"].is_string());
+        assert!(info["built"].is_string());
+    }
+
+    #[test]
+    fn resolve_help() {
         cmd()
-            .args([
-                "validate",
-                "tests/fixtures/compose/response_checkout_only.json",
-                "--schema-local-base",
-                "tests/fixtures/compose",
-                "--op",
-                "read",
-                "--strict=false",
-            ])
+            .args(["resolve", "--help"])
             .assert()
             .success()
-            .stdout(predicate::str::contains("Valid"));
+            .stdout(predicate::str::contains("--request"))
+            .stdout(predicate::str::contains("--response"))
+            .stdout(predicate::str::contains("--op"))
+            .stdout(predicate::str::contains("--schema-local-base"))
+            .stdout(predicate::str::contains("--schema-remote-base"));
     }
 
     #[test]
-    fn schema_remote_base_maps_url_prefix() {
-        // Test that --schema-remote-base strips URL prefix when mapping to local
-        // Fixture has schema URL like https://ucp.dev/schemas/shopping/checkout.json
-        // With remote base, this maps to tests/fixtures/compose/schemas/shopping/checkout.json
+    fn validate_help() {
+        cmd()
+            .args(["validate", "--help"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("--request"))
+            .stdout(predicate::str::contains("--response"))
+            .stdout(predicate::str::contains("--op"));
+    }
+}
+
+mod fixtures {
+    use super::*;
+
+    #[test]
+    fn resolve_checkout_fixture_create() {
+        let fixture = "tests/fixtures/checkout.json";
+
+        cmd()
+            .args(["resolve", fixture, "--request", "--op", "create"])
+            .assert()
+            .success()
+            // line_items is required for create
+            .stdout(predicate::str::contains("line_items"));
+    }
+
+    #[test]
+    fn resolve_checkout_fixture_update() {
+        let fixture = "tests/fixtures/checkout.json";
+
+        cmd()
+            .args(["resolve", fixture, "--request", "--op", "update"])
+            .assert()
+            .success()
+            // id is required for update
+            .stdout(predicate::str::contains(r#""required":["id"]"#));
+    }
+
+    #[test]
+    fn validate_checkout_create_valid() {
         let dir = TempDir::new().unwrap();
         let payload = write_temp_file(
             &dir,
             "payload.json",
             r#"{
-                "ucp": {
-                    "capabilities": {
-                        "dev.ucp.shopping.checkout": [{
-                            "version": "2026-01-11",
-                            "schema": "https://ucp.dev/versioned/schemas/shopping/checkout.json"
-                        }]
-                    },
-                    "payment_handlers": {}
-                },
-                "id": "123",
-                "line_items": [],
-                "status": "incomplete",
-                "currency": "USD",
-                "totals": [],
-                "links": []
+                "line_items": [
+                    { "sku": "ABC123", "quantity": 2 }
+                ]
             }"#,
         );
 
-        // Without remote base, would try to extract /versioned/schemas/... path
-        // With remote base https://ucp.dev/versioned, strips prefix leaving /schemas/...
         cmd()
             .args([
                 "validate",
                 payload.to_str().unwrap(),
-                "--schema-local-base",
-                "tests/fixtures/compose",
-                "--schema-remote-base",
-                "https://ucp.dev/versioned",
-                "--response",
+                "--schema",
+                "tests/fixtures/checkout.json",
+                "--request",
                 "--op",
-                "read",
-                "--strict=false",
+                "create",
             ])
             .assert()
             .success()
@@ -1252,635 +1914,4545 @@ mod compose {
     }
 
     #[test]
-    fn schema_remote_base_requires_local_base() {
-        // --schema-remote-base requires --schema-local-base
+    fn validate_checkout_create_missing_required() {
+        let dir = TempDir::new().unwrap();
+        // Missing line_items which is required for create
+        let payload = write_temp_file(&dir, "payload.json", r#"{}"#);
+
         cmd()
             .args([
                 "validate",
-                "tests/fixtures/compose/response_checkout_only.json",
-                "--schema-remote-base",
-                "https://ucp.dev/draft",
+                payload.to_str().unwrap(),
+                "--schema",
+                "tests/fixtures/checkout.json",
+                "--request",
                 "--op",
-                "read",
+                "create",
             ])
             .assert()
-            .failure()
-            .stderr(predicate::str::contains("schema-local-base"));
+            .code(1)
+            .stderr(predicate::str::contains("Validation failed"));
     }
+}
+
+/// Bundle flag tests - resolve external $refs
+mod bundle {
+    use super::*;
 
     #[test]
-    fn not_self_describing_requires_schema() {
+    fn bundle_resolves_external_ref() {
         let dir = TempDir::new().unwrap();
-        // Payload without UCP metadata
-        let payload = write_temp_file(&dir, "payload.json", r#"{"name": "test"}"#);
+
+        // Create a referenced type schema
+        fs::create_dir_all(dir.path().join("types")).unwrap();
+        fs::write(
+            dir.path().join("types/buyer.json"),
+            r#"{"type":"object","properties":{"email":{"type":"string"}}}"#,
+        )
+        .unwrap();
+
+        // Create main schema with $ref
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{
+                "type": "object",
+                "properties": {
+                    "buyer": { "$ref": "types/buyer.json" }
+                }
+            }"#,
+        );
 
         cmd()
-            .args(["validate", payload.to_str().unwrap(), "--op", "create"])
+            .args([
+                "resolve",
+                schema.to_str().unwrap(),
+                "--request",
+                "--op",
+                "create",
+                "--bundle",
+            ])
             .assert()
-            .code(2)
-            .stderr(predicate::str::contains("cannot infer direction"));
+            .success()
+            // $ref should be resolved, email property should be present
+            .stdout(predicate::str::contains(r#""email""#))
+            // No $ref should remain (except self-refs)
+            .stdout(predicate::str::contains(r#""$ref":"types/buyer.json""#).not());
     }
 
     #[test]
-    fn explicit_schema_overrides_self_describing() {
+    fn bundle_emits_source_map() {
         let dir = TempDir::new().unwrap();
-        // Self-describing payload but we override with explicit schema
+        fs::create_dir_all(dir.path().join("types")).unwrap();
+        fs::write(
+            dir.path().join("types/common.json"),
+            r#"{"$defs":{"buyer":{"type":"object","properties":{"email":{"type":"string"}}}}}"#,
+        )
+        .unwrap();
         let schema = write_temp_file(
             &dir,
             "schema.json",
-            r#"{"type": "object", "properties": {"custom": {"type": "string"}}}"#,
+            r#"{
+                "type": "object",
+                "properties": {
+                    "buyer": { "$ref": "types/common.json#/$defs/buyer" }
+                }
+            }"#,
         );
-        let payload = write_temp_file(&dir, "payload.json", r#"{"custom": "value"}"#);
+        let map_path = dir.path().join("map.json");
 
         cmd()
             .args([
-                "validate",
-                payload.to_str().unwrap(),
-                "--schema",
+                "resolve",
                 schema.to_str().unwrap(),
                 "--request",
                 "--op",
                 "create",
+                "--bundle",
+                "--emit-source-map",
+                map_path.to_str().unwrap(),
             ])
             .assert()
             .success()
-            .stdout(predicate::str::contains("Valid"));
+            .stdout(predicate::str::contains(r#""email""#));
+
+        let map: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&map_path).unwrap()).unwrap();
+        let entries = map.as_array().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["pointer"], "/properties/buyer");
+        assert!(entries[0]["source"]
+            .as_str()
+            .unwrap()
+            .ends_with("common.json"));
+        assert_eq!(entries[0]["source_pointer"], "/$defs/buyer");
     }
 
     #[test]
-    fn missing_schema_base_error() {
-        // Self-describing payload without --schema-local-base, no network (simulated by invalid path)
+    fn source_map_requires_bundle() {
         cmd()
             .args([
-                "validate",
-                "tests/fixtures/compose/response_checkout_only.json",
-                "--schema-local-base",
-                "/nonexistent/schemas",
-                "--response",
+                "resolve",
+                "schema.json",
+                "--request",
                 "--op",
-                "read",
+                "create",
+                "--emit-source-map",
+                "map.json",
             ])
             .assert()
-            .failure()
-            .stderr(predicate::str::contains("failed to fetch schema"));
+            .code(2);
     }
 
     #[test]
-    fn empty_capabilities_error() {
+    fn bundle_print_deps_lists_inlined_files() {
         let dir = TempDir::new().unwrap();
-        let payload = write_temp_file(
+        write_temp_file(
             &dir,
-            "payload.json",
+            "address.json",
+            r#"{ "type": "object", "properties": { "street": { "type": "string" } } }"#,
+        );
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
             r#"{
-                "ucp": {
-                    "capabilities": {}
-                },
-                "id": "123"
+                "type": "object",
+                "properties": {
+                    "shipping": { "$ref": "address.json" }
+                }
             }"#,
         );
+        let output = dir.path().join("resolved.json");
+        let deps_path = dir.path().join("deps.txt");
+        let make_path = dir.path().join("resolved.d");
 
         cmd()
             .args([
-                "validate",
-                payload.to_str().unwrap(),
-                "--schema-local-base",
-                "tests/fixtures/compose",
-                "--response",
+                "resolve",
+                schema.to_str().unwrap(),
+                "--request",
                 "--op",
-                "read",
+                "create",
+                "--bundle",
+                "--output",
+                output.to_str().unwrap(),
+                "--print-deps",
+                deps_path.to_str().unwrap(),
+                "--deps-make",
+                make_path.to_str().unwrap(),
             ])
             .assert()
-            .code(2)
-            .stderr(predicate::str::contains("no capabilities"));
+            .success();
+
+        let deps = fs::read_to_string(&deps_path).unwrap();
+        let lines: Vec<&str> = deps.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], schema.to_str().unwrap());
+        assert!(lines[1].ends_with("address.json"));
+
+        let rule = fs::read_to_string(&make_path).unwrap();
+        assert!(rule.starts_with(&format!("{}: ", output.display())));
+        assert!(rule.trim_end().ends_with("address.json"));
     }
 
     #[test]
-    fn unknown_parent_error() {
+    fn bundle_resolves_fragment_ref() {
         let dir = TempDir::new().unwrap();
-        // Extension references parent not in capabilities (but has a root)
-        let payload = write_temp_file(
-            &dir,
-            "payload.json",
+
+        // Create schema with $defs
+        fs::create_dir_all(dir.path().join("types")).unwrap();
+        fs::write(
+            dir.path().join("types/common.json"),
             r#"{
-                "ucp": {
-                    "capabilities": {
-                        "dev.ucp.shopping.checkout": [{
-                            "version": "2026-01-11",
-                            "schema": "https://ucp.dev/schemas/shopping/checkout.json"
-                        }],
-                        "dev.ucp.shopping.discount": [{
-                            "version": "2026-01-11",
-                            "schema": "https://ucp.dev/schemas/shopping/discount.json",
-                            "extends": "dev.ucp.shopping.nonexistent"
-                        }]
+                "$defs": {
+                    "address": {
+                        "type": "object",
+                        "properties": {
+                            "street": { "type": "string" }
+                        }
                     }
                 }
             }"#,
+        )
+        .unwrap();
+
+        // Reference specific $def with fragment
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{
+                "type": "object",
+                "properties": {
+                    "shipping": { "$ref": "types/common.json#/$defs/address" }
+                }
+            }"#,
         );
 
         cmd()
             .args([
-                "validate",
-                payload.to_str().unwrap(),
-                "--schema-local-base",
-                "tests/fixtures/compose",
-                "--response",
+                "resolve",
+                schema.to_str().unwrap(),
+                "--request",
                 "--op",
-                "read",
+                "create",
+                "--bundle",
             ])
             .assert()
-            .code(2)
-            .stderr(predicate::str::contains("unknown parent"));
+            .success()
+            // Fragment should be resolved, street property should be present
+            .stdout(predicate::str::contains(r#""street""#));
     }
 
     #[test]
-    fn json_output_compose_error() {
+    fn bundle_preserves_self_root_ref() {
         let dir = TempDir::new().unwrap();
-        let payload = write_temp_file(
+
+        // Create schema with self-root ref (recursive type)
+        fs::create_dir_all(dir.path().join("types")).unwrap();
+        fs::write(
+            dir.path().join("types/node.json"),
+            r##"{
+                "type": "object",
+                "properties": {
+                    "value": { "type": "string" },
+                    "children": {
+                        "type": "array",
+                        "items": { "$ref": "#" }
+                    }
+                }
+            }"##,
+        )
+        .unwrap();
+
+        let schema = write_temp_file(
             &dir,
-            "payload.json",
+            "schema.json",
             r#"{
-                "ucp": {
-                    "capabilities": {}
+                "type": "object",
+                "properties": {
+                    "tree": { "$ref": "types/node.json" }
                 }
             }"#,
         );
 
         cmd()
             .args([
-                "validate",
-                payload.to_str().unwrap(),
-                "--schema-local-base",
-                "tests/fixtures/compose",
-                "--response",
+                "resolve",
+                schema.to_str().unwrap(),
+                "--request",
                 "--op",
-                "read",
-                "--json",
+                "create",
+                "--bundle",
             ])
             .assert()
-            .code(2)
-            .stdout(predicate::str::contains(r#""valid":false"#))
-            .stdout(predicate::str::contains(r#""errors":"#));
+            .success()
+            // Self-root ref should be preserved (can't inline recursive)
+            .stdout(predicate::str::contains(r##""$ref":"#""##));
     }
-}
 
-/// Compose subcommand tests — output composed schemas (annotations preserved)
-mod compose_command {
-    use super::*;
+    #[test]
+    fn bundle_resolves_internal_refs_in_external_files() {
+        let dir = TempDir::new().unwrap();
+
+        // External file with internal $defs reference
+        fs::create_dir_all(dir.path().join("types")).unwrap();
+        fs::write(
+            dir.path().join("types/wrapper.json"),
+            r##"{
+                "$defs": {
+                    "inner": {
+                        "type": "string",
+                        "minLength": 1
+                    }
+                },
+                "type": "object",
+                "properties": {
+                    "data": { "$ref": "#/$defs/inner" }
+                }
+            }"##,
+        )
+        .unwrap();
+
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{
+                "type": "object",
+                "properties": {
+                    "wrapped": { "$ref": "types/wrapper.json" }
+                }
+            }"#,
+        );
+
+        cmd()
+            .args([
+                "resolve",
+                schema.to_str().unwrap(),
+                "--request",
+                "--op",
+                "create",
+                "--bundle",
+            ])
+            .assert()
+            .success()
+            // Internal ref should be resolved
+            .stdout(predicate::str::contains(r#""minLength""#));
+    }
 
     #[test]
-    fn compose_checkout_only() {
+    fn bundle_resolves_internal_defs_in_root_schema() {
+        let dir = TempDir::new().unwrap();
+
+        // Root schema with $defs and an internal $ref — the ref should be inlined
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r##"{
+                "$defs": {
+                    "search_filter": {
+                        "type": "object",
+                        "properties": {
+                            "available": { "type": "boolean" }
+                        }
+                    }
+                },
+                "type": "object",
+                "properties": {
+                    "search_filters": {
+                        "allOf": [
+                            { "$ref": "#/$defs/search_filter" }
+                        ]
+                    }
+                }
+            }"##,
+        );
+
         let assert = cmd()
             .args([
-                "compose",
-                "tests/fixtures/compose/response_checkout_only.json",
-                "--schema-local-base",
-                "tests/fixtures/compose",
+                "resolve",
+                schema.to_str().unwrap(),
+                "--request",
+                "--op",
+                "create",
+                "--bundle",
             ])
             .assert()
             .success();
 
         let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
-        let schema: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        let bundled: serde_json::Value = serde_json::from_str(&stdout).unwrap();
 
-        // Should produce a composed schema with properties
-        assert!(schema["properties"]["id"].is_object());
-        assert!(schema["properties"]["status"].is_object());
-        // UCP annotations should be PRESERVED (compose is pure composition)
+        // The internal #/$defs/ ref should be inlined
+        let all_of = &bundled["properties"]["search_filters"]["allOf"];
+        let first_entry = &all_of[0];
+
+        // $ref should be removed (inlined)
         assert!(
-            schema["properties"]["id"].get("ucp_response").is_some()
-                || schema["properties"]["id"].get("ucp_request").is_some(),
-            "compose should preserve UCP annotations"
+            first_entry.get("$ref").is_none(),
+            "Internal #/$defs/ ref should be inlined, but $ref still present: {first_entry}"
+        );
+        // The inlined content should have the 'available' property
+        assert!(
+            first_entry["properties"]["available"]["type"].as_str() == Some("boolean"),
+            "Inlined def should contain 'available: boolean', got: {first_entry}"
+        );
+    }
+
+    #[test]
+    fn bundle_detects_circular_refs() {
+        let dir = TempDir::new().unwrap();
+
+        // Create circular reference: a.json -> b.json -> a.json
+        fs::create_dir_all(dir.path().join("types")).unwrap();
+        fs::write(
+            dir.path().join("types/a.json"),
+            r#"{"type":"object","properties":{"b":{"$ref":"b.json"}}}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("types/b.json"),
+            r#"{"type":"object","properties":{"a":{"$ref":"a.json"}}}"#,
+        )
+        .unwrap();
+
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{
+                "type": "object",
+                "properties": {
+                    "start": { "$ref": "types/a.json" }
+                }
+            }"#,
+        );
+
+        cmd()
+            .args([
+                "resolve",
+                schema.to_str().unwrap(),
+                "--request",
+                "--op",
+                "create",
+                "--bundle",
+            ])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("circular"));
+    }
+
+    #[test]
+    fn bundle_output_is_valid_json() {
+        let dir = TempDir::new().unwrap();
+
+        fs::create_dir_all(dir.path().join("types")).unwrap();
+        fs::write(
+            dir.path().join("types/item.json"),
+            r#"{"type":"object","properties":{"id":{"type":"string"}}}"#,
+        )
+        .unwrap();
+
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{
+                "type": "object",
+                "properties": {
+                    "item": { "$ref": "types/item.json" }
+                }
+            }"#,
+        );
+
+        let output = dir.path().join("bundled.json");
+
+        cmd()
+            .args([
+                "resolve",
+                schema.to_str().unwrap(),
+                "--request",
+                "--op",
+                "create",
+                "--bundle",
+                "--output",
+                output.to_str().unwrap(),
+            ])
+            .assert()
+            .success();
+
+        // Verify output is valid JSON
+        let content = fs::read_to_string(&output).unwrap();
+        let parsed: Result<serde_json::Value, _> = serde_json::from_str(&content);
+        assert!(parsed.is_ok(), "Bundle output should be valid JSON");
+    }
+}
+
+/// Remote schema loading tests — use local mock server (no external dependencies)
+mod remote {
+    use super::*;
+
+    #[test]
+    fn resolve_from_url() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("GET", "/schema.json")
+            .with_body(r#"{"type": "object", "properties": {"name": {"type": "string"}}}"#)
+            .create();
+
+        cmd()
+            .args([
+                "resolve",
+                &format!("{}/schema.json", server.url()),
+                "--request",
+                "--op",
+                "create",
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("name"));
+
+        mock.assert();
+    }
+
+    #[test]
+    fn no_network_refuses_url_input() {
+        let mut server = mockito::Server::new();
+        let mock = server.mock("GET", "/schema.json").expect(0).create();
+
+        cmd()
+            .args([
+                "resolve",
+                &format!("{}/schema.json", server.url()),
+                "--request",
+                "--op",
+                "create",
+                "--no-network",
+            ])
+            .assert()
+            .code(3)
+            .stderr(predicate::str::contains("network access disabled"));
+
+        mock.assert();
+    }
+
+    #[test]
+    fn no_network_env_refuses_profile_fetch() {
+        let mut server = mockito::Server::new();
+        let mock = server.mock("GET", "/profile.json").expect(0).create();
+        let dir = TempDir::new().unwrap();
+        let payload = write_temp_file(&dir, "payload.json", r#"{"id": "x"}"#);
+
+        cmd()
+            .env("UCP_SCHEMA_NO_NETWORK", "1")
+            .args([
+                "validate",
+                payload.to_str().unwrap(),
+                "--profile",
+                &format!("{}/profile.json", server.url()),
+                "--op",
+                "create",
+            ])
+            .assert()
+            .code(3)
+            .stderr(predicate::str::contains("network access disabled"));
+
+        mock.assert();
+    }
+
+    #[test]
+    fn resolve_url_404() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("GET", "/missing.json")
+            .with_status(404)
+            .create();
+
+        cmd()
+            .args([
+                "resolve",
+                &format!("{}/missing.json", server.url()),
+                "--request",
+                "--op",
+                "create",
+            ])
+            .assert()
+            .code(3)
+            .stderr(
+                predicate::str::contains("failed to fetch").or(predicate::str::contains("404")),
+            );
+
+        mock.assert();
+    }
+
+    #[test]
+    fn resolve_url_invalid_host() {
+        // DNS failure is local — no mock needed
+        cmd()
+            .args([
+                "resolve",
+                "https://this-domain-does-not-exist-12345.invalid/schema.json",
+                "--request",
+                "--op",
+                "create",
+            ])
+            .assert()
+            .code(3);
+    }
+
+    #[test]
+    fn validate_with_remote_schema() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("GET", "/schema.json")
+            .with_body(r#"{"type": "object", "properties": {"name": {"type": "string"}}}"#)
+            .create();
+
+        let dir = TempDir::new().unwrap();
+        let payload = write_temp_file(&dir, "payload.json", r#"{"name": "test"}"#);
+
+        cmd()
+            .args([
+                "validate",
+                payload.to_str().unwrap(),
+                "--schema",
+                &format!("{}/schema.json", server.url()),
+                "--request",
+                "--op",
+                "create",
+            ])
+            .assert()
+            .success();
+
+        mock.assert();
+    }
+
+    #[test]
+    fn lock_pins_schema_and_refs() {
+        let mut server = mockito::Server::new();
+        let schema = server
+            .mock("GET", "/order.json")
+            .with_body(
+                r#"{"version": "2026-01-11", "properties": {"buyer": {"$ref": "buyer.json"}}}"#,
+            )
+            .create();
+        let buyer = server
+            .mock("GET", "/buyer.json")
+            .with_body(r#"{"type": "object"}"#)
+            .create();
+        let dir = TempDir::new().unwrap();
+        let lockfile = dir.path().join("ucp-schema.lock");
+
+        cmd()
+            .args([
+                "lock",
+                &format!("{}/order.json", server.url()),
+                "-o",
+                lockfile.to_str().unwrap(),
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Locked 2 remote schema(s)"));
+        schema.assert();
+        buyer.assert();
+
+        let lock: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&lockfile).unwrap()).unwrap();
+        assert_eq!(lock["lock_version"], 1);
+        assert_eq!(
+            lock["schemas"][0]["url"],
+            format!("{}/buyer.json", server.url())
+        );
+        // sha256 of {"type": "object"}
+        assert_eq!(
+            lock["schemas"][0]["sha256"],
+            "ff419ebbeba438f66900abe77818ce940702bdfe70fa173cb94beecee8d3f112"
+        );
+        assert_eq!(lock["schemas"][1]["version"], "2026-01-11");
+    }
+
+    #[test]
+    fn vendor_mirrors_profile_schemas_and_refs() {
+        let mut server = mockito::Server::new();
+        let base = server.url();
+        let profile = server
+            .mock("GET", "/profiles/agent.json")
+            .with_body(format!(
+                r#"{{"ucp": {{"version": "2026-01-11", "capabilities": {{"dev.ucp.shopping.checkout": [{{"version": "2026-01-11", "schema": "{}/schemas/checkout.json"}}]}}}}}}"#,
+                base
+            ))
+            .create();
+        let checkout = server
+            .mock("GET", "/schemas/checkout.json")
+            .with_body(format!(
+                r#"{{"type": "object", "required": ["buyer"], "properties": {{"buyer": {{"$ref": "{}/schemas/types/buyer.json"}}}}}}"#,
+                base
+            ))
+            .create();
+        let buyer = server
+            .mock("GET", "/schemas/types/buyer.json")
+            .with_body(r#"{"type": "object", "properties": {"email": {"$ref": "../common.json#/$defs/email"}}}"#)
+            .create();
+        let common = server
+            .mock("GET", "/schemas/common.json")
+            .with_body(r#"{"$defs": {"email": {"type": "string"}}}"#)
+            .create();
+
+        let dir = TempDir::new().unwrap();
+        let payload = write_temp_file(
+            &dir,
+            "request.json",
+            &format!(
+                r#"{{"meta": {{"profile": "{}/profiles/agent.json"}}, "checkout": {{"buyer": {{"email": "a@example.com"}}}}}}"#,
+                base
+            ),
+        );
+        let out = dir.path().join("vendor");
+        cmd()
+            .args(["vendor", payload.to_str().unwrap(), "--out"])
+            .arg(&out)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Vendored 4 file(s)"));
+        for mock in [profile, checkout, buyer, common] {
+            mock.assert();
+        }
+
+        let vendored: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(out.join("schemas/checkout.json")).unwrap())
+                .unwrap();
+        assert_eq!(vendored["properties"]["buyer"]["$ref"], "types/buyer.json");
+
+        // The mirror stands in for the server
+        cmd()
+            .args(["validate", payload.to_str().unwrap(), "--op", "create"])
+            .arg("--schema-local-base")
+            .arg(&out)
+            .arg("--no-network")
+            .assert()
+            .success();
+    }
+
+    #[test]
+    fn lockfile_rejects_changed_content() {
+        let mut server = mockito::Server::new();
+        let url = format!("{}/schema.json", server.url());
+        let original = server
+            .mock("GET", "/schema.json")
+            .with_body(r#"{"type": "object"}"#)
+            .create();
+        let dir = TempDir::new().unwrap();
+        let lockfile = dir.path().join("ucp-schema.lock");
+        cmd()
+            .args(["lock", &url, "-o", lockfile.to_str().unwrap()])
+            .assert()
+            .success();
+        cmd()
+            .args(["resolve", &url, "--request", "--op", "create", "--lockfile"])
+            .arg(&lockfile)
+            .assert()
+            .success();
+        original.remove();
+
+        server
+            .mock("GET", "/schema.json")
+            .with_body(r#"{"type": "string"}"#)
+            .create();
+        cmd()
+            .args(["resolve", &url, "--request", "--op", "create", "--lockfile"])
+            .arg(&lockfile)
+            .assert()
+            .code(3)
+            .stderr(predicate::str::contains("does not match the lockfile"));
+    }
+}
+
+/// Schema composition tests - self-describing payloads
+mod compose {
+    use super::*;
+
+    #[test]
+    fn self_describing_checkout_only() {
+        // Validate a self-describing response against local schemas
+        // Note: --strict=false because strict mode + allOf composition conflict
+        cmd()
+            .args([
+                "validate",
+                "tests/fixtures/compose/response_checkout_only.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--response",
+                "--op",
+                "read",
+                "--strict=false",
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Valid"));
+    }
+
+    #[test]
+    fn unwrap_pointer_validates_wrapped_payload() {
+        let dir = TempDir::new().unwrap();
+        let inner =
+            fs::read_to_string("tests/fixtures/compose/response_checkout_only.json").unwrap();
+        let wrapped = write_temp_file(
+            &dir,
+            "wrapped.json",
+            &format!(r#"{{"status": 200, "body": {}}}"#, inner),
+        );
+        let args = [
+            "validate",
+            wrapped.to_str().unwrap(),
+            "--schema-local-base",
+            "tests/fixtures/compose",
+            "--op",
+            "read",
+        ];
+
+        // Without unwrapping, the wrapper isn't self-describing
+        cmd()
+            .args(args)
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("cannot infer direction"));
+
+        cmd()
+            .args(args)
+            .args(["--unwrap-pointer", "#/body"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Valid"));
+
+        cmd()
+            .args(args)
+            .args(["--unwrap-pointer", "#/missing"])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("not found in payload"));
+    }
+
+    #[test]
+    fn jsonrpc_wrong_params_key_lists_candidates() {
+        cmd()
+            .args([
+                "validate",
+                "tests/fixtures/compose/request_wrong_params.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--op",
+                "create",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(
+                "JSONRPC request has no params matching any capability (expected one of: checkout)",
+            ));
+    }
+
+    #[test]
+    fn self_describing_with_extensions() {
+        // Validate a self-describing response with discount + fulfillment extensions
+        // Note: --strict=false because strict mode + allOf composition conflict
+        cmd()
+            .args([
+                "validate",
+                "tests/fixtures/compose/response_with_extensions.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--response",
+                "--op",
+                "read",
+                "--strict=false",
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Valid"));
+    }
+
+    #[test]
+    fn self_describing_extension_adds_item_fields() {
+        // item_discount adds `discount` to each line item; the base item
+        // constraints (sku, quantity) and the extension's apply together
+        let args = [
+            "validate",
+            "--schema-local-base",
+            "tests/fixtures/compose",
+            "--op",
+            "read",
+            "--strict=false",
+        ];
+        cmd()
+            .args(args)
+            .arg("tests/fixtures/compose/response_item_discount.json")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Valid"));
+
+        let dir = TempDir::new().unwrap();
+        let payload = fs::read_to_string("tests/fixtures/compose/response_item_discount.json")
+            .unwrap()
+            .replace("\"discount\": 150", "\"discount\": -1");
+        let path = write_temp_file(&dir, "negative.json", &payload);
+        cmd()
+            .args(args)
+            .arg(&path)
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("/line_items/0/discount"));
+    }
+
+    #[test]
+    fn direction_auto_inferred_response() {
+        // Direction should be auto-inferred from ucp.capabilities
+        // Note: --strict=false because strict mode + allOf composition conflict
+        cmd()
+            .args([
+                "validate",
+                "tests/fixtures/compose/response_checkout_only.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--op",
+                "read",
+                "--strict=false",
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Valid"));
+    }
+
+    #[test]
+    fn schema_remote_base_maps_url_prefix() {
+        // Test that --schema-remote-base strips URL prefix when mapping to local
+        // Fixture has schema URL like https://ucp.dev/schemas/shopping/checkout.json
+        // With remote base, this maps to tests/fixtures/compose/schemas/shopping/checkout.json
+        let dir = TempDir::new().unwrap();
+        let payload = write_temp_file(
+            &dir,
+            "payload.json",
+            r#"{
+                "ucp": {
+                    "capabilities": {
+                        "dev.ucp.shopping.checkout": [{
+                            "version": "2026-01-11",
+                            "schema": "https://ucp.dev/versioned/schemas/shopping/checkout.json"
+                        }]
+                    },
+                    "payment_handlers": {}
+                },
+                "id": "123",
+                "line_items": [],
+                "status": "incomplete",
+                "currency": "USD",
+                "totals": [],
+                "links": []
+            }"#,
+        );
+
+        // Without remote base, would try to extract /versioned/schemas/... path
+        // With remote base https://ucp.dev/versioned, strips prefix leaving /schemas/...
+        cmd()
+            .args([
+                "validate",
+                payload.to_str().unwrap(),
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--schema-remote-base",
+                "https://ucp.dev/versioned",
+                "--response",
+                "--op",
+                "read",
+                "--strict=false",
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Valid"));
+    }
+
+    #[test]
+    fn schema_remote_base_requires_local_base() {
+        // --schema-remote-base requires --schema-local-base
+        cmd()
+            .args([
+                "validate",
+                "tests/fixtures/compose/response_checkout_only.json",
+                "--schema-remote-base",
+                "https://ucp.dev/draft",
+                "--op",
+                "read",
+            ])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("schema-local-base"));
+    }
+
+    #[test]
+    fn not_self_describing_requires_schema() {
+        let dir = TempDir::new().unwrap();
+        // Payload without UCP metadata
+        let payload = write_temp_file(&dir, "payload.json", r#"{"name": "test"}"#);
+
+        cmd()
+            .args(["validate", payload.to_str().unwrap(), "--op", "create"])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("cannot infer direction"));
+    }
+
+    #[test]
+    fn explicit_schema_overrides_self_describing() {
+        let dir = TempDir::new().unwrap();
+        // Self-describing payload but we override with explicit schema
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{"type": "object", "properties": {"custom": {"type": "string"}}}"#,
+        );
+        let payload = write_temp_file(&dir, "payload.json", r#"{"custom": "value"}"#);
+
+        cmd()
+            .args([
+                "validate",
+                payload.to_str().unwrap(),
+                "--schema",
+                schema.to_str().unwrap(),
+                "--request",
+                "--op",
+                "create",
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Valid"));
+    }
+
+    #[test]
+    fn missing_schema_base_error() {
+        // Self-describing payload without --schema-local-base, no network (simulated by invalid path)
+        cmd()
+            .args([
+                "validate",
+                "tests/fixtures/compose/response_checkout_only.json",
+                "--schema-local-base",
+                "/nonexistent/schemas",
+                "--response",
+                "--op",
+                "read",
+            ])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("failed to fetch schema"));
+    }
+
+    #[test]
+    fn empty_capabilities_error() {
+        let dir = TempDir::new().unwrap();
+        let payload = write_temp_file(
+            &dir,
+            "payload.json",
+            r#"{
+                "ucp": {
+                    "capabilities": {}
+                },
+                "id": "123"
+            }"#,
+        );
+
+        cmd()
+            .args([
+                "validate",
+                payload.to_str().unwrap(),
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--response",
+                "--op",
+                "read",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("no capabilities"));
+    }
+
+    #[test]
+    fn unknown_parent_error() {
+        let dir = TempDir::new().unwrap();
+        // Extension references parent not in capabilities (but has a root)
+        let payload = write_temp_file(
+            &dir,
+            "payload.json",
+            r#"{
+                "ucp": {
+                    "capabilities": {
+                        "dev.ucp.shopping.checkout": [{
+                            "version": "2026-01-11",
+                            "schema": "https://ucp.dev/schemas/shopping/checkout.json"
+                        }],
+                        "dev.ucp.shopping.discount": [{
+                            "version": "2026-01-11",
+                            "schema": "https://ucp.dev/schemas/shopping/discount.json",
+                            "extends": "dev.ucp.shopping.nonexistent"
+                        }]
+                    }
+                }
+            }"#,
+        );
+
+        cmd()
+            .args([
+                "validate",
+                payload.to_str().unwrap(),
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--response",
+                "--op",
+                "read",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("unknown parent"));
+    }
+
+    #[test]
+    fn json_output_compose_error() {
+        let dir = TempDir::new().unwrap();
+        let payload = write_temp_file(
+            &dir,
+            "payload.json",
+            r#"{
+                "ucp": {
+                    "capabilities": {}
+                }
+            }"#,
+        );
+
+        cmd()
+            .args([
+                "validate",
+                payload.to_str().unwrap(),
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--response",
+                "--op",
+                "read",
+                "--json",
+            ])
+            .assert()
+            .code(2)
+            .stdout(predicate::str::contains(r#""valid":false"#))
+            .stdout(predicate::str::contains(r#""errors":"#));
+    }
+}
+
+/// Compose subcommand tests — output composed schemas (annotations preserved)
+mod compose_command {
+    use super::*;
+
+    #[test]
+    fn compose_checkout_only() {
+        let assert = cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_checkout_only.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+            ])
+            .assert()
+            .success();
+
+        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+        let schema: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+
+        // Should produce a composed schema with properties
+        assert!(schema["properties"]["id"].is_object());
+        assert!(schema["properties"]["status"].is_object());
+        // UCP annotations should be PRESERVED (compose is pure composition)
+        assert!(
+            schema["properties"]["id"].get("ucp_response").is_some()
+                || schema["properties"]["id"].get("ucp_request").is_some(),
+            "compose should preserve UCP annotations"
+        );
+    }
+
+    #[test]
+    fn compose_with_extensions() {
+        let assert = cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_with_extensions.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--pretty",
+            ])
+            .assert()
+            .success();
+
+        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+        let schema: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+
+        // Should produce an allOf composition
+        let all_of = schema["allOf"].as_array().unwrap();
+        assert_eq!(all_of.len(), 2); // discount + fulfillment
+
+        // Both branches should have their specific properties
+        let has_discounts = all_of
+            .iter()
+            .any(|s| s["properties"]["discounts"].is_object());
+        let has_fulfillment = all_of
+            .iter()
+            .any(|s| s["properties"]["fulfillment"].is_object());
+        assert!(has_discounts, "Should include discount properties");
+        assert!(has_fulfillment, "Should include fulfillment properties");
+
+        // UCP annotations should be PRESERVED in compose output
+        assert!(
+            stdout.contains("ucp_response") || stdout.contains("ucp_request"),
+            "compose should preserve UCP annotations"
+        );
+    }
+
+    #[test]
+    fn compose_needs_no_direction_or_op() {
+        // compose is pure composition — no --op, no --request/--response needed
+        cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_checkout_only.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("properties"));
+    }
+
+    #[test]
+    fn compose_non_payload_error() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "schema.json", r#"{"name": "test"}"#);
+
+        // compose requires a self-describing payload; plain schema should error
+        cmd()
+            .args(["compose", schema.to_str().unwrap()])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("not a self-describing payload"));
+    }
+
+    #[test]
+    fn compose_empty_capabilities_error() {
+        let dir = TempDir::new().unwrap();
+        let payload = write_temp_file(&dir, "payload.json", r#"{"ucp": {"capabilities": {}}}"#);
+
+        cmd()
+            .args(["compose", payload.to_str().unwrap()])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("no capabilities"));
+    }
+
+    #[test]
+    fn compose_missing_schema_base_error() {
+        cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_checkout_only.json",
+                "--schema-local-base",
+                "/nonexistent/schemas",
+            ])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("failed to fetch schema"));
+    }
+
+    #[test]
+    fn compose_remote_base_mismatch_error() {
+        let dir = TempDir::new().unwrap();
+        let payload = write_temp_file(
+            &dir,
+            "payload.json",
+            r#"{"ucp": {"capabilities": {"dev.ucp.shopping.checkout": [
+                {"version": "2026-01-11", "schema": "https://ucp.dev/v2/schemas/shopping/checkout.json"}
+            ]}}}"#,
+        );
+
+        cmd()
+            .args([
+                "compose",
+                payload.to_str().unwrap(),
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--schema-remote-base",
+                "https://ucp.dev/draft",
+            ])
+            .assert()
+            .code(3)
+            .stderr(predicate::str::contains(
+                "does not start with remote base https://ucp.dev/draft",
+            ));
+    }
+
+    #[test]
+    fn compose_warn_on_unused_schema_base() {
+        // Fallback path mapping still finds the file, but the base was never used
+        cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_checkout_only.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--schema-remote-base",
+                "https://ucp.dev/draft",
+                "--warn-on-unused-schema-base",
+            ])
+            .assert()
+            .success()
+            .stderr(predicate::str::contains(
+                "Warning: schema URL https://ucp.dev/schemas/shopping/checkout.json \
+                 does not start with remote base https://ucp.dev/draft",
+            ));
+    }
+
+    #[test]
+    fn compose_orphan_extension_errors_by_default() {
+        cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_orphan_extension.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("no root capability"));
+    }
+
+    #[test]
+    fn compose_allow_orphan_extensions() {
+        let assert = cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_orphan_extension.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--allow-orphan-extensions",
+            ])
+            .assert()
+            .success()
+            .stderr(predicate::str::contains(
+                "Warning: extension 'dev.ucp.shopping.discount' extends unknown parent 'dev.ucp.shopping.checkout'",
+            ));
+
+        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+        let schema: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        assert!(schema["allOf"][0]["properties"]["discounts"].is_object());
+    }
+
+    #[test]
+    fn compose_min_version_drops_older_capabilities() {
+        let assert = cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_mixed_versions.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--min-version",
+                "2026-01-01",
+            ])
+            .assert()
+            .success()
+            .stderr(predicate::str::contains(
+                "Warning: dropped dev.ucp.shopping.discount (version 2025-06-01 is older than 2026-01-01)",
+            ));
+
+        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+        let schema: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        let branches = schema["allOf"].as_array().unwrap();
+        assert_eq!(branches.len(), 1);
+        assert!(branches[0]["properties"]["fulfillment"].is_object());
+        assert!(!stdout.contains("discounts"));
+    }
+
+    #[test]
+    fn compose_min_version_rejects_non_date() {
+        cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_mixed_versions.json",
+                "--min-version",
+                "latest",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("expected an ISO date"));
+    }
+
+    #[test]
+    fn compose_resolve_strips_annotations() {
+        let args = [
+            "compose",
+            "tests/fixtures/compose/response_with_extensions.json",
+            "--schema-local-base",
+            "tests/fixtures/compose",
+        ];
+
+        let annotated = cmd().args(args).assert().success();
+        let stdout = String::from_utf8(annotated.get_output().stdout.clone()).unwrap();
+        assert!(stdout.contains("ucp_response"));
+
+        let resolved = cmd()
+            .args(args)
+            .args(["--resolve", "--op", "read"])
+            .assert()
+            .success();
+        let stdout = String::from_utf8(resolved.get_output().stdout.clone()).unwrap();
+        assert!(!stdout.contains("ucp_response"));
+        let schema: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        let required = schema["allOf"][0]["required"].as_array().unwrap();
+        assert!(required.contains(&serde_json::json!("id")));
+    }
+
+    #[test]
+    fn compose_root_only_skips_extensions() {
+        let assert = cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_with_extensions.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--root-only",
+            ])
+            .assert()
+            .success()
+            .stderr(predicate::str::contains(
+                "skipping extension dev.ucp.shopping.discount",
+            ));
+        let schema: serde_json::Value =
+            serde_json::from_slice(&assert.get_output().stdout).unwrap();
+        assert!(schema.get("allOf").is_none());
+        assert_eq!(schema["name"], "dev.ucp.shopping.checkout");
+        assert!(schema["properties"].get("id").is_some());
+        assert!(schema["properties"].get("discounts").is_none());
+    }
+
+    #[test]
+    fn compose_split_directions_emits_both_shapes() {
+        let assert = cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_checkout_only.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--split-directions",
+                "--op",
+                "update",
+            ])
+            .assert()
+            .success();
+        let split: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+
+        let request = &split["request"];
+        assert_eq!(request["required"], serde_json::json!(["id"]));
+        assert!(request["properties"].get("status").is_none());
+        assert!(!request.to_string().contains("ucp_request"));
+
+        let response = &split["response"];
+        assert_eq!(response["required"], serde_json::json!(["id", "status"]));
+        assert!(response["properties"].get("status").is_some());
+    }
+
+    #[test]
+    fn compose_split_directions_conflicts_with_resolve() {
+        cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_checkout_only.json",
+                "--split-directions",
+                "--resolve",
+                "--op",
+                "update",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+
+    #[test]
+    fn compose_op_requires_resolve() {
+        cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_with_extensions.json",
+                "--op",
+                "read",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("--resolve"));
+    }
+
+    #[test]
+    fn compose_maps_two_hosts_to_subtrees() {
+        let assert = cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_multi_host.json",
+                "--schema-local-base",
+                "tests/fixtures/compose/multi_host",
+                "--map",
+                "https://ucp.dev/versioned/2026-01-11=ucp",
+                "--map",
+                "https://partner.example=partner",
+            ])
+            .assert()
+            .success();
+
+        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+        let schema: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        let branches = schema["allOf"].as_array().unwrap();
+        assert_eq!(branches.len(), 2);
+        assert!(branches[0]["properties"]["discounts"].is_object());
+        assert!(branches[1]["properties"]["loyalty"].is_object());
+    }
+
+    #[test]
+    fn compose_unmapped_host_errors() {
+        cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_multi_host.json",
+                "--schema-local-base",
+                "tests/fixtures/compose/multi_host",
+                "--map",
+                "https://ucp.dev/versioned/2026-01-11=ucp",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(
+                "schema URL https://partner.example/ucp/loyalty.json matches no --map prefix",
+            ));
+    }
+
+    #[test]
+    fn compose_warns_on_stale_schema_version() {
+        cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_stale_version.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+            ])
+            .assert()
+            .success()
+            .stderr(predicate::str::contains(
+                "Warning: dev.ucp.shopping.discount advertises version 2026-04-08 but its schema (https://ucp.dev/schemas/shopping/discount.json) declares 2026-01-11",
+            ));
+    }
+
+    #[test]
+    fn validate_strict_version_rejects_stale_schema() {
+        cmd()
+            .args([
+                "validate",
+                "tests/fixtures/compose/response_stale_version.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--op",
+                "read",
+                "--strict-version",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(
+                "dev.ucp.shopping.discount advertises version 2026-04-08",
+            ));
+    }
+
+    #[test]
+    fn validate_capability_filter_composes_matching_subset() {
+        cmd()
+            .args([
+                "validate",
+                "tests/fixtures/compose/response_with_extensions.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--op",
+                "read",
+                "--capability-filter",
+                r"^dev\.ucp\.shopping\.discount$",
+                "-v",
+            ])
+            .assert()
+            .success()
+            .stderr(predicate::str::contains(
+                "skipping extension dev.ucp.shopping.fulfillment (--capability-filter)",
+            ))
+            .stderr(predicate::str::contains("skipping extension dev.ucp.shopping.checkout").not());
+
+        cmd()
+            .args([
+                "validate",
+                "tests/fixtures/compose/response_with_extensions.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--op",
+                "read",
+                "--capability-filter",
+                "^com\\.example\\.",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(
+                "no capability name matches --capability-filter",
+            ));
+    }
+
+    #[test]
+    fn compose_with_output_file() {
+        let dir = TempDir::new().unwrap();
+        let output = dir.path().join("composed.json");
+
+        cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_checkout_only.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--output",
+                output.to_str().unwrap(),
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::is_empty());
+
+        let content = fs::read_to_string(&output).unwrap();
+        let schema: serde_json::Value = serde_json::from_str(&content).unwrap();
+        assert!(schema["properties"]["id"].is_object());
+    }
+
+    #[test]
+    fn compose_with_pretty() {
+        cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_checkout_only.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--pretty",
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("{\n"));
+    }
+
+    #[test]
+    fn compose_schema_remote_base() {
+        let dir = TempDir::new().unwrap();
+        let payload = write_temp_file(
+            &dir,
+            "payload.json",
+            r#"{
+                "ucp": {
+                    "capabilities": {
+                        "dev.ucp.shopping.checkout": [{
+                            "version": "2026-01-11",
+                            "schema": "https://ucp.dev/versioned/schemas/shopping/checkout.json"
+                        }]
+                    }
+                }
+            }"#,
+        );
+
+        cmd()
+            .args([
+                "compose",
+                payload.to_str().unwrap(),
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--schema-remote-base",
+                "https://ucp.dev/versioned",
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("properties"));
+    }
+
+    #[test]
+    fn compose_help() {
+        cmd()
+            .args(["compose", "--help"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("--schema-local-base"))
+            .stdout(predicate::str::contains("--pretty"))
+            .stdout(predicate::str::contains("--output"))
+            // direction and op only apply once --resolve runs the resolve step
+            .stdout(predicate::str::contains("--resolve"))
+            .stdout(predicate::str::contains(
+                "Operation to resolve for (with --resolve or --split-directions)",
+            ));
+    }
+}
+
+/// Resolve auto-composes when given a self-describing payload
+mod resolve_payload {
+    use super::*;
+
+    #[test]
+    fn resolve_auto_composes_payload() {
+        // resolve with a payload should auto-compose + resolve in one step
+        let assert = cmd()
+            .args([
+                "resolve",
+                "tests/fixtures/compose/response_checkout_only.json",
+                "--response",
+                "--op",
+                "read",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+            ])
+            .assert()
+            .success();
+
+        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+        let schema: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+
+        // Should have resolved properties (annotations stripped)
+        assert!(schema["properties"]["id"].is_object());
+        // UCP annotations should be STRIPPED (resolve goes beyond compose)
+        assert!(
+            !stdout.contains("ucp_response") && !stdout.contains("ucp_request"),
+            "resolve should strip UCP annotations"
+        );
+    }
+
+    #[test]
+    fn resolve_bundle_payload_inlines_external_refs() {
+        // The item_discount extension refs checkout.json from its $defs entry
+        let assert = cmd()
+            .args([
+                "resolve",
+                "tests/fixtures/compose/response_item_discount.json",
+                "--op",
+                "read",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--bundle",
+            ])
+            .assert()
+            .success();
+
+        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+        assert!(!stdout.contains("$ref"));
+        let schema: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        let inlined = &schema["allOf"][0]["allOf"][0];
+        assert_eq!(inlined["title"], "Checkout");
+        assert!(inlined["properties"]["status"].is_object());
+    }
+
+    #[test]
+    fn resolve_auto_infers_direction_from_payload() {
+        // Direction is auto-inferred from payload's ucp.capabilities
+        let assert = cmd()
+            .args([
+                "resolve",
+                "tests/fixtures/compose/response_checkout_only.json",
+                "--op",
+                "read",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+            ])
+            .assert()
+            .success();
+
+        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+        let schema: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        assert!(schema["properties"]["id"].is_object());
+    }
+
+    #[test]
+    fn resolve_strict_only_closes_matching_capabilities() {
+        // discount (dev.ucp.shopping.*) is closed; the third-party loyalty
+        // extension stays open
+        let assert = cmd()
+            .args([
+                "resolve",
+                "tests/fixtures/compose/response_mixed_trust.json",
+                "--op",
+                "read",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--strict-only",
+                "dev.ucp.shopping.*",
+            ])
+            .assert()
+            .success();
+
+        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+        let schema: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        let branches = schema["allOf"].as_array().unwrap();
+        assert_eq!(branches.len(), 2);
+        assert_eq!(
+            branches[0]["properties"]["discounts"]["additionalProperties"],
+            serde_json::json!(false)
+        );
+        assert!(branches[1]["properties"]["loyalty"]
+            .get("additionalProperties")
+            .is_none());
+        // Top level stays open since not every branch is strict
+        assert!(schema.get("unevaluatedProperties").is_none());
+    }
+
+    #[test]
+    fn resolve_strict_only_rejects_schema_input() {
+        cmd()
+            .args([
+                "resolve",
+                "tests/fixtures/checkout.json",
+                "--response",
+                "--op",
+                "read",
+                "--strict-only",
+                "dev.ucp.shopping.*",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(
+                "--strict-only only applies to payload input",
+            ));
+    }
+
+    #[test]
+    fn payload_print_deps_lists_capability_schemas() {
+        let dir = TempDir::new().unwrap();
+        let deps_path = dir.path().join("deps.txt");
+
+        cmd()
+            .args([
+                "resolve",
+                "tests/fixtures/compose/response_with_extensions.json",
+                "--op",
+                "read",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--print-deps",
+                deps_path.to_str().unwrap(),
+            ])
+            .assert()
+            .success();
+
+        let deps = fs::read_to_string(&deps_path).unwrap();
+        assert!(deps.contains("response_with_extensions.json"));
+        assert!(deps.contains("discount.json"));
+        assert!(deps.contains("fulfillment.json"));
+    }
+
+    #[test]
+    fn resolve_no_warning_on_schema_input() {
+        cmd()
+            .args([
+                "resolve",
+                "tests/fixtures/checkout.json",
+                "--response",
+                "--op",
+                "read",
+            ])
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("compose").not());
+    }
+}
+
+/// Flag/input-type compatibility: reject flags that don't apply
+mod flag_validation {
+    use super::*;
+
+    // resolve: --emit-source-map rejected for payload input
+    #[test]
+    fn resolve_source_map_rejected_for_payload() {
+        cmd()
+            .args([
+                "resolve",
+                "tests/fixtures/compose/response_checkout_only.json",
+                "--bundle",
+                "--emit-source-map",
+                "map.json",
+                "--op",
+                "read",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(
+                "--emit-source-map only applies to schema input",
+            ));
+    }
+
+    // resolve: --keep-annotation only accepts UCP annotation keys
+    #[test]
+    fn resolve_keep_annotation_rejects_non_ucp_key() {
+        cmd()
+            .args([
+                "resolve",
+                "tests/fixtures/checkout.json",
+                "--request",
+                "--op",
+                "create",
+                "--keep-annotation",
+                "description",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("expected a UCP annotation"));
+    }
+
+    // resolve: --schema-local-base rejected for schema input
+    #[test]
+    fn resolve_schema_local_base_rejected_for_schema() {
+        cmd()
+            .args([
+                "resolve",
+                "tests/fixtures/checkout.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--request",
+                "--op",
+                "create",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("only apply to payload input"));
+    }
+
+    // resolve: --schema-remote-base rejected for schema input
+    #[test]
+    fn resolve_schema_remote_base_rejected_for_schema() {
+        cmd()
+            .args([
+                "resolve",
+                "tests/fixtures/checkout.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--schema-remote-base",
+                "https://ucp.dev/draft",
+                "--request",
+                "--op",
+                "create",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("only apply to payload input"));
+    }
+
+    // validate: --schema-local-base rejected with explicit --schema
+    #[test]
+    fn validate_schema_local_base_rejected_with_explicit_schema() {
+        cmd()
+            .args([
+                "validate",
+                "tests/fixtures/compose/response_checkout_only.json",
+                "--schema",
+                "tests/fixtures/checkout.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--response",
+                "--op",
+                "read",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(
+                "do not apply with explicit --schema",
+            ));
+    }
+
+    // validate: --schema-remote-base rejected with explicit --schema
+    #[test]
+    fn validate_schema_remote_base_rejected_with_explicit_schema() {
+        cmd()
+            .args([
+                "validate",
+                "tests/fixtures/compose/response_checkout_only.json",
+                "--schema",
+                "tests/fixtures/checkout.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--schema-remote-base",
+                "https://ucp.dev/draft",
+                "--response",
+                "--op",
+                "read",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(
+                "do not apply with explicit --schema",
+            ));
+    }
+}
+
+/// --fail-on-warning turns any emitted warning into exit code 4
+mod fail_on_warning {
+    use super::*;
+
+    const STRAY_SCHEMA: &str = r#"{
+        "type": "object",
+        "properties": {
+            "tags": {
+                "type": "array",
+                "items": { "type": "string", "ucp_request": "omit" }
+            }
+        }
+    }"#;
+
+    #[test]
+    fn stray_annotation_warns_without_flag() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "schema.json", STRAY_SCHEMA);
+
+        cmd()
+            .args([
+                "resolve",
+                schema.to_str().unwrap(),
+                "--request",
+                "--op",
+                "create",
+            ])
+            .assert()
+            .success()
+            .stderr(predicate::str::contains(
+                "Warning: /properties/tags/items/ucp_request has no effect",
+            ));
+    }
+
+    #[test]
+    fn stray_annotation_fails_with_flag() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "schema.json", STRAY_SCHEMA);
+
+        cmd()
+            .args([
+                "resolve",
+                schema.to_str().unwrap(),
+                "--request",
+                "--op",
+                "create",
+                "--fail-on-warning",
+            ])
+            .assert()
+            .code(4)
+            .stderr(predicate::str::contains("1 warning(s) emitted"));
+    }
+
+    #[test]
+    fn no_warnings_passes_with_flag() {
+        cmd()
+            .args([
+                "resolve",
+                "tests/fixtures/checkout.json",
+                "--request",
+                "--op",
+                "create",
+                "--fail-on-warning",
+            ])
+            .assert()
+            .success();
+    }
+
+    #[test]
+    fn hard_errors_keep_their_code() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "schema.json", STRAY_SCHEMA);
+        let payload = write_temp_file(&dir, "payload.json", r#"{"tags": [1]}"#);
+
+        cmd()
+            .args([
+                "validate",
+                payload.to_str().unwrap(),
+                "--schema",
+                schema.to_str().unwrap(),
+                "--request",
+                "--op",
+                "create",
+                "--fail-on-warning",
+            ])
+            .assert()
+            .code(1);
+    }
+}
+
+/// Verbose mode tests
+mod verbose {
+    use super::*;
+
+    #[test]
+    fn resolve_verbose_shows_pipeline_stages() {
+        cmd()
+            .args([
+                "resolve",
+                "tests/fixtures/compose/response_checkout_only.json",
+                "--op",
+                "read",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--verbose",
+            ])
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("[load]"))
+            .stderr(predicate::str::contains("[detect]"))
+            .stderr(predicate::str::contains("[compose]"))
+            .stderr(predicate::str::contains("[resolve]"));
+    }
+
+    #[test]
+    fn compose_verbose_shows_annotations_preserved() {
+        cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_checkout_only.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--verbose",
+            ])
+            .assert()
+            .success()
+            .stderr(predicate::str::contains(
+                "[compose] composing schemas (annotations preserved)",
+            ));
+    }
+
+    #[test]
+    fn resolve_verbose_schema_input() {
+        cmd()
+            .args([
+                "resolve",
+                "tests/fixtures/checkout.json",
+                "--request",
+                "--op",
+                "create",
+                "--verbose",
+            ])
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("[detect] input is a schema file"))
+            .stderr(predicate::str::contains(
+                "[resolve] resolving for request/create",
+            ));
+    }
+
+    #[test]
+    fn no_verbose_output_by_default() {
+        cmd()
+            .args([
+                "resolve",
+                "tests/fixtures/checkout.json",
+                "--request",
+                "--op",
+                "create",
+            ])
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("[load]").not())
+            .stderr(predicate::str::contains("[resolve]").not());
+    }
+}
+
+/// Library subcommand tests — every op × direction under $defs
+mod bundle_command {
+    use super::*;
+
+    #[test]
+    fn bundle_inlines_refs_and_keeps_annotations() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("types")).unwrap();
+        fs::write(
+            dir.path().join("types/buyer.json"),
+            r#"{"type":"object","properties":{"email":{"type":"string","ucp_response":"required"}}}"#,
+        )
+        .unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "checkout.json",
+            r#"{
+                "type": "object",
+                "properties": {
+                    "id": { "type": "string", "ucp_request": { "create": "omit" } },
+                    "buyer": { "$ref": "types/buyer.json" }
+                }
+            }"#,
+        );
+        let output = dir.path().join("bundled.json");
+
+        cmd()
+            .args([
+                "bundle",
+                schema.to_str().unwrap(),
+                "--output",
+                output.to_str().unwrap(),
+            ])
+            .assert()
+            .success();
+
+        let bundled: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+        assert_eq!(
+            bundled["properties"]["id"]["ucp_request"],
+            serde_json::json!({ "create": "omit" })
+        );
+        assert_eq!(
+            bundled["properties"]["buyer"]["properties"]["email"]["ucp_response"],
+            "required"
+        );
+        assert!(bundled["properties"]["buyer"].get("$ref").is_none());
+
+        // The bundled artifact still resolves per operation
+        cmd()
+            .args([
+                "resolve",
+                output.to_str().unwrap(),
+                "--request",
+                "--op",
+                "create",
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(r#""id""#).not());
+    }
+
+    #[test]
+    fn bundle_mode_defs_handles_mutual_recursion() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("types")).unwrap();
+        fs::write(
+            dir.path().join("types/buyer.json"),
+            r#"{"type":"object","required":["email"],"properties":{"email":{"type":"string"},"org":{"$ref":"org.json"}}}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("types/org.json"),
+            r#"{"type":"object","properties":{"owner":{"$ref":"buyer.json"}}}"#,
+        )
+        .unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "checkout.json",
+            r#"{"type":"object","properties":{"buyer":{"$ref":"types/buyer.json"},"seller":{"$ref":"types/buyer.json"}}}"#,
+        );
+        let output = dir.path().join("bundled.json");
+
+        // Inlining can't represent the cycle
+        cmd()
+            .args(["bundle", schema.to_str().unwrap()])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("circular reference"));
+
+        cmd()
+            .args([
+                "bundle",
+                schema.to_str().unwrap(),
+                "--bundle-mode",
+                "defs",
+                "--output",
+                output.to_str().unwrap(),
+            ])
+            .assert()
+            .success();
+
+        let bundled: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+        assert_eq!(bundled["properties"]["buyer"]["$ref"], "#/$defs/buyer");
+        assert_eq!(bundled["properties"]["seller"]["$ref"], "#/$defs/buyer");
+        assert_eq!(
+            bundled["$defs"]["org"]["properties"]["owner"]["$ref"],
+            "#/$defs/buyer"
+        );
+
+        let payload = write_temp_file(
+            &dir,
+            "payload.json",
+            r#"{"buyer":{"email":"a@b.c","org":{"owner":{}}}}"#,
+        );
+        cmd()
+            .args([
+                "validate",
+                payload.to_str().unwrap(),
+                "--schema",
+                output.to_str().unwrap(),
+                "--response",
+                "--op",
+                "read",
+            ])
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("/buyer/org/owner"));
+    }
+
+    #[test]
+    fn bundle_missing_ref_fails() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "checkout.json",
+            r#"{"properties":{"buyer":{"$ref":"types/missing.json"}}}"#,
+        );
+
+        cmd()
+            .args(["bundle", schema.to_str().unwrap()])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("missing.json"));
+    }
+}
+
+mod merge_command {
+    use super::*;
+
+    const SCHEMAS: &str = "tests/fixtures/compose/schemas/shopping";
+
+    #[test]
+    fn merge_matches_payload_composition() {
+        let composed = cmd()
+            .args([
+                "compose",
+                "tests/fixtures/compose/response_with_extensions.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+                "--schema-remote-base",
+                "https://ucp.dev",
+            ])
+            .output()
+            .unwrap();
+        assert!(composed.status.success());
+
+        cmd()
+            .args([
+                "merge",
+                &format!("{}/checkout.json", SCHEMAS),
+                &format!("{}/discount.json", SCHEMAS),
+                &format!("{}/fulfillment.json", SCHEMAS),
+                "--extends",
+                "discount=checkout",
+                "--extends",
+                "dev.ucp.shopping.fulfillment=checkout",
+            ])
+            .assert()
+            .success()
+            .stdout(String::from_utf8(composed.stdout).unwrap());
+    }
+
+    #[test]
+    fn merge_requires_extends_for_extensions() {
+        // Without --extends both files are roots
+        cmd()
+            .args([
+                "merge",
+                &format!("{}/checkout.json", SCHEMAS),
+                &format!("{}/discount.json", SCHEMAS),
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("multiple root capabilities"));
+
+        cmd()
+            .args([
+                "merge",
+                &format!("{}/checkout.json", SCHEMAS),
+                "--extends",
+                "discount=checkout",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(
+                "'discount': matches none of the given schema files",
+            ));
+    }
+}
+
+mod library_command {
+    use super::*;
+
+    #[test]
+    fn library_emits_defs_per_op_and_direction() {
+        let assert = cmd()
+            .args([
+                "library",
+                "tests/fixtures/checkout.json",
+                "--ops",
+                "create,read,update",
+            ])
+            .assert()
+            .success();
+
+        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+        let library: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        let defs = library["$defs"].as_object().unwrap();
+        for name in [
+            "CheckoutCreateRequest",
+            "CheckoutCreateResponse",
+            "CheckoutReadRequest",
+            "CheckoutReadResponse",
+            "CheckoutUpdateRequest",
+            "CheckoutUpdateResponse",
+        ] {
+            assert!(defs.contains_key(name), "missing {}", name);
+        }
+        assert!(defs["CheckoutCreateRequest"]["properties"]
+            .get("id")
+            .is_none());
+        assert!(!stdout.contains("ucp_request"));
+        assert!(!stdout.contains("ucp_response"));
+    }
+
+    #[test]
+    fn library_name_override() {
+        cmd()
+            .args([
+                "library",
+                "tests/fixtures/checkout.json",
+                "--ops",
+                "create",
+                "--name",
+                "order",
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("OrderCreateRequest"));
+    }
+
+    #[test]
+    fn library_requires_ops() {
+        cmd()
+            .args(["library", "tests/fixtures/checkout.json"])
+            .assert()
+            .code(2);
+    }
+}
+
+/// Codegen subcommand tests — typed bindings from resolved schemas
+mod codegen_command {
+    use super::*;
+
+    #[test]
+    fn codegen_rust_emits_module_per_op_and_direction() {
+        let assert = cmd()
+            .args([
+                "codegen",
+                "rust",
+                "tests/fixtures/checkout.json",
+                "--ops",
+                "create,read",
+                "--strict",
+                "true",
+            ])
+            .assert()
+            .success();
+        let code = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+
+        for module in [
+            "pub mod create_request {",
+            "pub mod create_response {",
+            "pub mod read_request {",
+            "pub mod read_response {",
+        ] {
+            assert!(code.contains(module), "missing {}", module);
+        }
+        assert!(code.contains("#[serde(deny_unknown_fields)]\n    pub struct Checkout {"));
+        assert!(code.contains("pub buyer: Option<CheckoutBuyer>,"));
+        assert!(!code.contains("ucp_request"));
+    }
+
+    #[test]
+    fn codegen_go_suffixes_types_per_op_and_direction() {
+        let assert = cmd()
+            .args([
+                "codegen",
+                "go",
+                "tests/fixtures/checkout.json",
+                "--ops",
+                "create,update",
+            ])
+            .assert()
+            .success();
+        let code = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+
+        assert!(
+            code.starts_with("// Code generated by ucp-schema codegen from checkout. DO NOT EDIT.")
+        );
+        assert!(code.contains("\npackage checkout\n"));
+        for name in [
+            "type CheckoutCreateRequest struct {",
+            "type CheckoutCreateResponse struct {",
+            "type CheckoutUpdateRequest struct {",
+            "type CheckoutUpdateResponse struct {",
+        ] {
+            assert!(code.contains(name), "missing {}", name);
+        }
+        // Optional struct field: pointer with omitempty
+        assert!(code.contains("*CheckoutBuyerCreateRequest `json:\"buyer,omitempty\"`"));
+    }
+
+    #[test]
+    fn codegen_rejects_unknown_target() {
+        cmd()
+            .args([
+                "codegen",
+                "cobol",
+                "tests/fixtures/checkout.json",
+                "--ops",
+                "create",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("expected rust or go"));
+    }
+}
+
+/// Convert subcommand tests — OpenAPI export
+mod convert_command {
+    use super::*;
+
+    #[test]
+    fn convert_to_openapi_emits_components() {
+        let assert = cmd()
+            .args([
+                "convert",
+                "--to",
+                "openapi",
+                "tests/fixtures/checkout.json",
+                "--ops",
+                "create,read",
+                "--title",
+                "Shop",
+            ])
+            .assert()
+            .success();
+        let doc: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+
+        assert_eq!(doc["openapi"], "3.1.0");
+        assert_eq!(doc["info"]["title"], "Shop");
+        let schemas = doc["components"]["schemas"].as_object().unwrap();
+        let names: Vec<&str> = schemas.keys().map(String::as_str).collect();
+        assert_eq!(
+            names,
+            [
+                "CheckoutCreateRequest",
+                "CheckoutCreateResponse",
+                "CheckoutReadRequest",
+                "CheckoutReadResponse"
+            ]
+        );
+        assert!(!doc.to_string().contains("ucp_request"));
+        assert!(!doc.to_string().contains("\"#/$defs/"));
+    }
+
+    #[test]
+    fn convert_from_openapi_round_trips_through_resolve() {
+        let dir = TempDir::new().unwrap();
+        let schema = dir.path().join("checkout.json");
+        cmd()
+            .args([
+                "convert",
+                "--from",
+                "openapi",
+                "tests/fixtures/openapi/checkouts.yaml",
+                "--output",
+            ])
+            .arg(&schema)
+            .assert()
+            .success();
+
+        let assert = cmd()
+            .arg("resolve")
+            .arg(&schema)
+            .args(["--request", "--op", "create"])
+            .assert()
+            .success();
+        let resolved: serde_json::Value =
+            serde_json::from_slice(&assert.get_output().stdout).unwrap();
+        let props: Vec<&str> = resolved["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(props, ["line_items"]);
+        assert_eq!(resolved["required"], serde_json::json!(["line_items"]));
+
+        let assert = cmd()
+            .arg("resolve")
+            .arg(&schema)
+            .args(["--response", "--op", "read"])
+            .assert()
+            .success();
+        let resolved: serde_json::Value =
+            serde_json::from_slice(&assert.get_output().stdout).unwrap();
+        assert_eq!(
+            resolved["required"],
+            serde_json::json!(["line_items", "id", "status"])
+        );
+    }
+
+    #[test]
+    fn convert_from_openapi_rejects_export_flags() {
+        cmd()
+            .args([
+                "convert",
+                "--from",
+                "openapi",
+                "tests/fixtures/openapi/checkouts.yaml",
+                "--ops",
+                "create",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+
+    #[test]
+    fn convert_rejects_unknown_target() {
+        cmd()
+            .args([
+                "convert",
+                "--to",
+                "raml",
+                "tests/fixtures/checkout.json",
+                "--ops",
+                "create",
+            ])
+            .assert()
+            .code(2);
+    }
+}
+
+/// Serve subcommand tests — HTTP endpoints over a real socket
+mod serve_command {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpStream;
+    use std::process::{Child, Stdio};
+
+    /// Kills the server when the test ends, pass or fail.
+    struct Running(Child);
+
+    impl Drop for Running {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    fn start() -> (Running, String) {
+        let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin!("ucp-schema"))
+            .args([
+                "serve",
+                "--schema-local-base",
+                "tests/fixtures",
+                "--port",
+                "0",
+            ])
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(child.stderr.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let addr = line
+            .trim()
+            .strip_prefix("Listening on http://")
+            .expect("listening line")
+            .to_string();
+        (Running(child), addr)
+    }
+
+    /// Send one request on `stream` and return (status, body).
+    fn request(stream: &mut TcpStream, method: &str, target: &str, body: &str) -> (u16, String) {
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: test\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            target,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut status_line = String::new();
+        reader.read_line(&mut status_line).unwrap();
+        let status = status_line
+            .split_whitespace()
+            .nth(1)
+            .unwrap()
+            .parse()
+            .unwrap();
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Content-Length:") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        (status, String::from_utf8(body).unwrap())
+    }
+
+    #[test]
+    fn serve_validates_over_keep_alive_connection() {
+        let (_server, addr) = start();
+        let mut stream = TcpStream::connect(&addr).unwrap();
+        let target = "/validate?op=create&direction=request&schema=checkout.json";
+
+        let (status, body) = request(&mut stream, "POST", target, r#"{"line_items":[]}"#);
+        assert_eq!((status, body.as_str()), (200, r#"{"valid":true}"#));
+
+        // Same connection, cached validator
+        let (status, body) = request(&mut stream, "POST", target, r#"{"id":"x","line_items":[]}"#);
+        assert_eq!(status, 422);
+        assert!(body.starts_with(r#"{"valid":false,"errors":[{"#));
+
+        let (status, _) = request(&mut stream, "GET", "/health", "");
+        assert_eq!(status, 200);
+    }
+}
+
+/// Watch subcommand tests
+mod watch_command {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn watch_rejects_other_commands() {
+        cmd()
+            .args(["watch", "tests/fixtures", "--", "compose", "x.json"])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(
+                "watch runs one of: resolve, lint, validate",
+            ));
+    }
+
+    #[test]
+    fn watch_reruns_when_output_changes() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{"type": "object", "properties": {"a": {"type": "string"}}}"#,
+        );
+        let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin!("ucp-schema"))
+            .arg("watch")
+            .arg(dir.path())
+            .args(["--interval", "20", "--debounce", "50", "--", "resolve"])
+            .arg(&schema)
+            .args(["--request", "--op", "create"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let stderr = child.stderr.take().unwrap();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let next = || rx.recv_timeout(Duration::from_secs(10));
+
+        let first = next();
+        fs::write(
+            &schema,
+            r#"{"type": "object", "properties": {"bb": {"type": "string"}}}"#,
+        )
+        .unwrap();
+        let second = next();
+        let _ = child.kill();
+        let _ = child.wait();
+
+        assert!(first.unwrap().starts_with("[watch] resolve "));
+        assert!(second.unwrap().contains("schema.json changed; resolve "));
+    }
+}
+
+/// Init subcommand tests — scaffolded projects validate out of the box
+mod init_command {
+    use super::*;
+
+    #[test]
+    fn init_scaffold_payloads_validate() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path().join("loyalty");
+        cmd()
+            .args(["init", "loyalty", "--dir"])
+            .arg(&dir)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "Scaffolded com.example.loyalty in",
+            ));
+
+        cmd()
+            .arg("lint")
+            .arg(dir.join("schemas"))
+            .assert()
+            .success();
+        for (payload, op, code) in [
+            ("valid/create_request.json", "create", 0),
+            ("valid/read_response.json", "read", 0),
+            ("invalid/create_request.json", "create", 1),
+            ("invalid/read_response.json", "read", 1),
+        ] {
+            cmd()
+                .arg("validate")
+                .arg(dir.join("payloads").join(payload))
+                .arg("--schema-local-base")
+                .arg(&dir)
+                .args(["--op", op])
+                .assert()
+                .code(code);
+        }
+
+        // Refuses to overwrite
+        cmd()
+            .args(["init", "loyalty", "--dir"])
+            .arg(&dir)
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("already exists and is not empty"));
+    }
+
+    #[test]
+    fn init_rejects_invalid_name() {
+        cmd()
+            .args(["init", "9lives"])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("invalid capability name"));
+    }
+}
+
+/// Scrub subcommand tests
+mod scrub_command {
+    use super::*;
+
+    #[test]
+    fn scrub_removes_omitted_and_unknown_fields() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{
+                "type": "object",
+                "properties": {
+                    "id": { "type": "string", "ucp_request": { "create": "omit", "update": "required" } },
+                    "items": { "type": "array", "items": { "properties": { "sku": { "type": "string" } } } }
+                }
+            }"#,
+        );
+        let payload = write_temp_file(
+            &dir,
+            "payload.json",
+            r#"{"id": "o_1", "items": [{"sku": "A", "note": "x"}], "debug": true}"#,
+        );
+
+        cmd()
+            .arg("scrub")
+            .arg(&payload)
+            .arg("--schema")
+            .arg(&schema)
+            .args(["--request", "--op", "create", "-v"])
+            .assert()
+            .success()
+            .stdout(r#"{"items":[{"sku":"A"}]}"#.to_string() + "\n")
+            .stderr(predicate::str::contains("[scrub] removed /id"))
+            .stderr(predicate::str::contains("[scrub] removed /items/0/note"));
+
+        cmd()
+            .arg("scrub")
+            .arg(&payload)
+            .arg("--schema")
+            .arg(&schema)
+            .args(["--request", "--op", "update"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(r#""id":"o_1""#))
+            .stdout(predicate::str::contains("debug").not());
+    }
+}
+
+/// Redact subcommand tests
+mod redact_command {
+    use super::*;
+
+    const SCHEMA: &str = r#"{
+        "type": "object",
+        "required": ["email"],
+        "properties": {
+            "email": { "type": "string", "ucp_sensitive": true },
+            "name": { "type": "string" }
+        }
+    }"#;
+
+    #[test]
+    fn redact_masks_or_removes_sensitive_values() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "schema.json", SCHEMA);
+        let payload = write_temp_file(&dir, "payload.json", r#"{"email": "a@b.c", "name": "Ann"}"#);
+
+        cmd()
+            .arg("redact")
+            .arg(&payload)
+            .arg("--schema")
+            .arg(&schema)
+            .assert()
+            .success()
+            .stdout("{\"email\":\"[REDACTED]\",\"name\":\"Ann\"}\n");
+
+        cmd()
+            .arg("redact")
+            .arg(&payload)
+            .arg("--schema")
+            .arg(&schema)
+            .arg("--remove")
+            .assert()
+            .success()
+            .stdout("{\"name\":\"Ann\"}\n");
+    }
+
+    #[test]
+    fn resolve_omit_sensitive_drops_response_fields() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "schema.json", SCHEMA);
+
+        cmd()
+            .arg("resolve")
+            .arg(&schema)
+            .args(["--response", "--op", "read", "--omit-sensitive"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("email").not());
+
+        cmd()
+            .arg("resolve")
+            .arg(&schema)
+            .args(["--response", "--op", "read"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(r#""email":{"type":"string"}"#));
+    }
+}
+
+/// Explain subcommand tests
+mod explain_command {
+    use super::*;
+
+    const SCHEMA: &str = r##"{
+        "type": "object",
+        "additionalProperties": false,
+        "properties": {
+            "id": { "type": "string", "ucp_request": { "create": "omit", "update": "required" } },
+            "items": { "type": "array", "items": { "$ref": "#/$defs/item" } }
+        },
+        "allOf": [
+            { "title": "Discount", "properties": { "code": { "type": "string", "ucp_request": "required" } } }
+        ],
+        "$defs": {
+            "item": { "type": "object", "required": ["sku"], "properties": { "sku": { "type": "string" } } }
+        }
+    }"##;
+
+    #[test]
+    fn explain_traces_each_error() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "schema.json", SCHEMA);
+        let payload = write_temp_file(&dir, "payload.json", r#"{"id": "x", "items": [{}]}"#);
+
+        cmd()
+            .arg("explain")
+            .arg(&payload)
+            .arg("--schema")
+            .arg(&schema)
+            .args(["--request", "--op", "create"])
+            .assert()
+            .code(1)
+            .stdout(predicate::str::contains("schema:     /$defs/item"))
+            .stdout(predicate::str::contains("allOf:      /allOf/0 (Discount)"))
+            .stdout(predicate::str::contains(
+                "annotation: id omitted by ucp_request {\"create\":\"omit\",\"update\":\"required\"} at /properties/id",
+            ))
+            .stdout(predicate::str::contains("3 error(s) for request/create"));
+
+        let output = cmd()
+            .arg("explain")
+            .arg(&payload)
+            .arg("--schema")
+            .arg(&schema)
+            .args(["--request", "--op", "update", "--format", "json"])
+            .output()
+            .unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(report["valid"], false);
+        let errors = report["errors"].as_array().unwrap();
+        assert_eq!(errors[0]["location"], "/$defs/item");
+        assert_eq!(errors[1]["annotations"][0]["property"], "code");
+    }
+
+    #[test]
+    fn explain_valid_payload() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "schema.json", SCHEMA);
+        let payload = write_temp_file(&dir, "payload.json", r#"{"id": "x", "items": []}"#);
+
+        cmd()
+            .arg("explain")
+            .arg(&payload)
+            .arg("--schema")
+            .arg(&schema)
+            .args(["--response", "--op", "read"])
+            .assert()
+            .success()
+            .stdout("Valid for response/read\n");
+    }
+}
+
+/// Coverage subcommand tests
+mod coverage_command {
+    use super::*;
+
+    const SCHEMA: &str = r#"{
+        "type": "object",
+        "properties": {
+            "id": { "type": "string", "ucp_request": { "create": "omit" } },
+            "status": { "type": "string", "enum": ["open", "closed"] },
+            "payment": {
+                "oneOf": [
+                    { "title": "Card", "required": ["card"] },
+                    { "title": "Bank", "required": ["iban"] }
+                ]
+            }
+        }
+    }"#;
+
+    #[test]
+    fn coverage_reports_unexercised_locations() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "schema.json", SCHEMA);
+        let a = write_temp_file(&dir, "a.json", r#"{"status": "open"}"#);
+        let b = write_temp_file(&dir, "b.json", r#"{"payment": {"card": "4242"}}"#);
+
+        cmd()
+            .arg("coverage")
+            .arg("--schema")
+            .arg(&schema)
+            .args([&a, &b])
+            .args(["--request", "--op", "create"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("by 2 payload(s)"))
+            .stdout(predicate::str::contains(
+                "properties          2/2     100.0%",
+            ))
+            .stdout(predicate::str::contains(
+                "enum value    /properties/status (\"closed\")",
+            ))
+            .stdout(predicate::str::contains(
+                "oneOf branch  /properties/payment/oneOf/1 (\"Bank\")",
+            ));
+
+        let output = cmd()
+            .arg("coverage")
+            .arg("--schema")
+            .arg(&schema)
+            .arg(&a)
+            .args(["--response", "--op", "read", "--format", "json"])
+            .output()
+            .unwrap();
+        let coverage: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(coverage["direction"], "response");
+        assert_eq!(coverage["entries"][0]["path"], "/properties/id");
+        assert_eq!(coverage["entries"][0]["hits"], 0);
+    }
+}
+
+/// Test subcommand tests
+mod inspect_command {
+    use super::*;
+
+    const SCHEMA: &str = r#"{
+        "type": "object",
+        "required": ["status"],
+        "properties": {
+            "id": { "type": "string", "ucp_request": { "create": "omit", "update": "required" } },
+            "status": { "type": "string" },
+            "sku": {
+                "type": "string",
+                "ucp_request": { "transition": { "from": "required", "to": "omit", "description": "use product_id" } }
+            }
+        }
+    }"#;
+
+    #[test]
+    fn inspect_prints_matrix_per_direction() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "schema.json", SCHEMA);
+
+        cmd()
+            .arg("inspect")
+            .arg(&schema)
+            .args(["--op", "create", "--op", "update"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "request:\n  property  create         update",
+            ))
+            .stdout(predicate::str::contains(
+                "  id        omit           required",
+            ))
+            .stdout(predicate::str::contains(
+                "  status    (required)     (required)",
+            ))
+            .stdout(predicate::str::contains(
+                "  sku       required→omit  required→omit",
+            ))
+            .stdout(predicate::str::contains("response:"));
+    }
+
+    #[test]
+    fn inspect_json_lists_cells() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "schema.json", SCHEMA);
+
+        let output = cmd()
+            .arg("inspect")
+            .arg(&schema)
+            .args(["--format", "json"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let matrix: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(
+            matrix["operations"],
+            serde_json::json!(["create", "update", "complete", "read"])
+        );
+        let id = &matrix["properties"][0];
+        assert_eq!(id["name"], "id");
+        assert_eq!(id["request"][0]["visibility"], "omit");
+        assert_eq!(id["request"][2]["annotated"], false);
+        assert_eq!(
+            matrix["properties"][2]["request"][0]["transition"]["to"],
+            "omit"
+        );
+    }
+}
+
+mod test_command {
+    use super::*;
+
+    const SCHEMA: &str = r#"{
+        "$id": "https://example.com/order.json",
+        "type": "object",
+        "properties": {
+            "id": { "type": "string", "ucp_request": { "create": "omit", "update": "required" } },
+            "item": { "$ref": "item.json" }
+        },
+        "additionalProperties": false,
+        "x-ucp-tests": [
+            { "direction": "request", "op": "create", "valid": [{ "item": { "sku": "A" } }], "invalid": [{ "id": "x" }] }
+        ]
+    }"#;
+
+    #[test]
+    fn test_runs_embedded_and_sibling_suites() {
+        let dir = TempDir::new().unwrap();
+        write_temp_file(&dir, "order.json", SCHEMA);
+        write_temp_file(
+            &dir,
+            "item.json",
+            r#"{ "$id": "https://example.com/item.json", "type": "object", "required": ["sku"] }"#,
+        );
+        write_temp_file(
+            &dir,
+            "order.tests.json",
+            r#"[{ "direction": "request", "op": "update", "valid": [{ "id": "o_1" }], "invalid": [{}] }]"#,
+        );
+
+        cmd()
+            .arg("test")
+            .arg(dir.path())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("order.json (4 passed, 0 failed)"))
+            .stdout(predicate::str::contains("4 tests in 1 files, all passed"));
+
+        // The sibling suite isn't linted as a schema
+        cmd()
+            .args(["lint", "--strict"])
+            .arg(dir.path())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("2 files checked"));
+    }
+
+    #[test]
+    fn test_reports_failures() {
+        let dir = TempDir::new().unwrap();
+        write_temp_file(&dir, "order.json", SCHEMA);
+        write_temp_file(
+            &dir,
+            "item.json",
+            r#"{ "$id": "https://example.com/item.json", "type": "object", "required": ["sku"] }"#,
+        );
+        write_temp_file(
+            &dir,
+            "order.tests.json",
+            r#"[{ "direction": "request", "op": "create", "valid": [{ "item": {} }], "invalid": [{ "item": { "sku": "A" } }] }]"#,
+        );
+
+        cmd()
+            .arg("test")
+            .arg(dir.path())
+            .assert()
+            .code(1)
+            .stdout(predicate::str::contains("request/create valid[1]"))
+            .stdout(predicate::str::contains(
+                "/item: \"sku\" is a required property",
+            ))
+            .stdout(predicate::str::contains("request/create invalid[1]"))
+            .stdout(predicate::str::contains("expected validation to fail"));
+
+        let output = cmd()
+            .args(["test", "--format", "json"])
+            .arg(dir.path())
+            .output()
+            .unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(report["passed"], 2);
+        assert_eq!(report["failed"], 2);
+
+        cmd()
+            .args(["test", "--format", "junit"])
+            .arg(dir.path())
+            .assert()
+            .code(1)
+            .stdout(predicate::str::contains(
+                r#"<testsuite name="order.json" tests="4" failures="2" errors="0">"#,
+            ))
+            .stdout(predicate::str::contains(
+                r#"<failure message="expected validation to fail">"#,
+            ));
+    }
+}
+
+/// Graph subcommand tests
+mod fuzz_command {
+    use super::*;
+
+    const SCHEMA: &str = r#"{
+        "type": "object",
+        "required": ["qty"],
+        "properties": {
+            "id": { "type": "string", "ucp_request": { "create": "omit" } },
+            "qty": { "type": "integer", "minimum": 1 }
+        }
+    }"#;
+
+    #[test]
+    fn fuzz_validate_flags_open_objects() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "order.json", SCHEMA);
+        let example = write_temp_file(&dir, "example.json", r#"{"qty": 2}"#);
+
+        cmd()
+            .args([
+                "fuzz",
+                schema.to_str().unwrap(),
+                "--example",
+                example.to_str().unwrap(),
+                "--request",
+                "--op",
+                "create",
+                "--validate",
+            ])
+            .assert()
+            .code(1)
+            .stdout(predicate::str::contains("/qty: below minimum 1"))
+            .stdout(predicate::str::contains(
+                "accepted\x1b[0m (root): extra property \"x_fuzz_extra\"",
+            ))
+            .stdout(predicate::str::contains(
+                "4 of 5 mutation(s) rejected for request/create",
+            ));
+    }
+
+    #[test]
+    fn fuzz_writes_payloads_and_skips_invalid_examples() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "order.json", SCHEMA);
+        // Below the minimum, so this example can't seed mutations
+        let invalid = write_temp_file(&dir, "invalid.json", r#"{"qty": 0}"#);
+        let valid = write_temp_file(&dir, "valid.json", r#"{"qty": 2}"#);
+
+        let output = cmd()
+            .args([
+                "fuzz",
+                schema.to_str().unwrap(),
+                "--example",
+                invalid.to_str().unwrap(),
+                "--example",
+                valid.to_str().unwrap(),
+                "--request",
+                "--op",
+                "create",
+                "--count",
+                "2",
+            ])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("invalid.json is not valid"));
+        let payloads: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(payloads, serde_json::json!(["fuzz", {}]));
+    }
+
+    #[test]
+    fn fuzz_without_examples_fails() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "order.json", SCHEMA);
+
+        cmd()
+            .args([
+                "fuzz",
+                schema.to_str().unwrap(),
+                "--response",
+                "--op",
+                "read",
+            ])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("no examples to mutate"));
+    }
+}
+
+mod completions_command {
+    use super::*;
+
+    #[test]
+    fn completions_hook_op_values() {
+        for (shell, hook) in [
+            ("bash", "complete -F _ucp_schema_ops"),
+            ("zsh", "compdef _ucp_schema_ops"),
+            ("fish", "-l op -x -a '(ucp-schema __complete-ops"),
+            ("powershell", "__complete-ops @ucpArgs"),
+        ] {
+            cmd()
+                .args(["completions", shell])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains(hook));
+        }
+    }
+
+    #[test]
+    fn complete_ops_lists_schema_operations() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "order.json",
+            r#"{"properties": {"id": {"ucp_request": {"refund": "omit"}}}}"#,
+        );
+        let payload = write_temp_file(&dir, "payload.json", r#"{"id": "x"}"#);
+
+        cmd()
+            .args([
+                "__complete-ops",
+                "resolve",
+                schema.to_str().unwrap(),
+                "--op",
+            ])
+            .assert()
+            .success()
+            .stdout("create\nupdate\ncomplete\nread\nrefund\n");
+        // validate's positional is the payload; --schema names the schema
+        cmd()
+            .args([
+                "__complete-ops",
+                "validate",
+                payload.to_str().unwrap(),
+                "--schema",
+                schema.to_str().unwrap(),
+                "--op",
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("refund"));
+        cmd()
+            .args(["__complete-ops", "resolve", "missing.json", "--op"])
+            .assert()
+            .success()
+            .stdout("");
+    }
+}
+
+mod graph_command {
+    use super::*;
+
+    #[test]
+    fn graph_dot_and_mermaid() {
+        cmd()
+            .args(["graph", "tests/fixtures/compose/schemas"])
+            .assert()
+            .success()
+            .stdout(predicate::str::starts_with("digraph schemas {"))
+            .stdout(predicate::str::contains(
+                "n1 [label=\"shopping/checkout.json\\n(fan-in 4)\"];",
+            ))
+            .stdout(predicate::str::contains(
+                "n2 -> n1 [style=dashed, label=\"extends\"];",
+            ));
+
+        let dir = TempDir::new().unwrap();
+        let output = dir.path().join("graph.mmd");
+        cmd()
+            .args([
+                "graph",
+                "tests/fixtures/compose/schemas",
+                "--format",
+                "mermaid",
+                "-o",
+            ])
+            .arg(&output)
+            .assert()
+            .success();
+        let mermaid = fs::read_to_string(&output).unwrap();
+        assert!(mermaid.starts_with("flowchart LR\n"));
+        assert!(mermaid.contains("n4 --> n1"));
+    }
+
+    #[test]
+    fn graph_missing_path() {
+        cmd()
+            .args(["graph", "no/such/dir"])
+            .assert()
+            .code(3)
+            .stderr(predicate::str::contains("not found"));
+    }
+}
+
+/// Migrate subcommand tests
+mod migrate_command {
+    use super::*;
+
+    const SCHEMA: &str = r#"{
+        "type": "object",
+        "properties": {
+            "id": {
+                "type": "string",
+                "ucp_request": {
+                    "create": "omit",
+                    "update": { "transition": { "from": "required", "to": "optional", "description": "Inferred from path." } }
+                }
+            },
+            "legacy": {
+                "type": "string",
+                "ucp_response": { "transition": { "from": "optional", "to": "omit", "description": "Removed in v2." } }
+            }
+        }
+    }"#;
+
+    #[test]
+    fn migrate_dry_run_shows_diff_without_writing() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "schema.json", SCHEMA);
+
+        cmd()
+            .arg("migrate")
+            .arg(&schema)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "  /properties/id/ucp_request/update\n-   {\"transition\"",
+            ))
+            .stdout(predicate::str::contains("+   \"optional\""))
+            .stdout(predicate::str::contains("2 transition(s) to apply"));
+
+        assert_eq!(fs::read_to_string(&schema).unwrap(), SCHEMA);
+    }
+
+    #[test]
+    fn migrate_apply_completes_transitions() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(&dir, "schema.json", SCHEMA);
+
+        cmd()
+            .args(["migrate", "--apply"])
+            .arg(&schema)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Applied 2 transition(s)"));
+
+        let migrated: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&schema).unwrap()).unwrap();
+        assert_eq!(
+            migrated["properties"]["id"]["ucp_request"],
+            serde_json::json!({ "create": "omit", "update": "optional" })
+        );
+        assert_eq!(migrated["properties"]["legacy"]["ucp_response"], "omit");
+
+        cmd()
+            .arg("migrate")
+            .arg(&schema)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("No schema transitions to apply"));
+    }
+}
+
+/// Upgrade subcommand tests
+mod upgrade_command {
+    use super::*;
+
+    #[test]
+    fn upgrade_rewrites_shorthands_unless_dry_run() {
+        let dir = TempDir::new().unwrap();
+        let original = r#"{"type": "object", "properties": {"id": {"type": "string", "ucp_request": "omit"}}}"#;
+        let schema = write_temp_file(&dir, "order.json", original);
+
+        cmd()
+            .args(["upgrade", "--dry-run", "-v"])
+            .arg(dir.path())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Would upgrade"))
+            .stdout(predicate::str::contains(
+                r#"+   {"create":"omit","update":"omit","complete":"omit","read":"omit"}"#,
+            ));
+        assert_eq!(fs::read_to_string(&schema).unwrap(), original);
+
+        cmd()
+            .arg("upgrade")
+            .arg(dir.path())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "1 annotation(s) in 1 file(s) upgraded",
+            ));
+        let upgraded: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&schema).unwrap()).unwrap();
+        assert_eq!(
+            upgraded["properties"]["id"]["ucp_request"]["update"],
+            "omit"
+        );
+
+        cmd()
+            .arg("upgrade")
+            .arg(dir.path())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("1 file(s) already current"));
+    }
+}
+
+/// Docs subcommand tests — static HTML site per capability
+mod normalize_command {
+    use super::*;
+
+    #[test]
+    fn normalize_is_byte_stable() {
+        let dir = TempDir::new().unwrap();
+        let a = write_temp_file(
+            &dir,
+            "a.json",
+            r#"{"type":"object","required":["id","email"],"properties":{"id":{"type":"string","ucp_request":"omit"},"qty":{"minimum":1.0}}}"#,
+        );
+        let b = write_temp_file(
+            &dir,
+            "b.json",
+            r#"{
+                "properties": {
+                    "qty": { "minimum": 1 },
+                    "id": { "ucp_request": { "update": "omit", "read": "omit", "create": "omit", "complete": "omit" }, "type": "string" }
+                },
+                "required": ["email", "id", "email"],
+                "type": "object"
+            }"#,
         );
+
+        let normalize = |path: &std::path::Path| {
+            let output = cmd()
+                .args(["normalize", path.to_str().unwrap(), "--pretty"])
+                .output()
+                .unwrap();
+            assert!(output.status.success());
+            output.stdout
+        };
+        let normalized = normalize(&a);
+        assert_eq!(normalized, normalize(&b));
+        assert!(String::from_utf8(normalized)
+            .unwrap()
+            .starts_with("{\n  \"properties\": {\n    \"id\""));
     }
+}
+
+mod docs_command {
+    use super::*;
 
     #[test]
-    fn compose_with_extensions() {
-        let assert = cmd()
+    fn docs_html_writes_page_per_capability() {
+        let dir = TempDir::new().unwrap();
+        let site = dir.path().join("site");
+
+        cmd()
             .args([
-                "compose",
+                "docs",
                 "tests/fixtures/compose/response_with_extensions.json",
                 "--schema-local-base",
                 "tests/fixtures/compose",
-                "--pretty",
+                "--output-dir",
             ])
+            .arg(&site)
             .assert()
             .success();
 
-        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
-        let schema: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        let index = fs::read_to_string(site.join("index.html")).unwrap();
+        assert!(index.contains("href=\"dev.ucp.shopping.checkout.html\""));
+        for page in [
+            "dev.ucp.shopping.checkout.html",
+            "dev.ucp.shopping.discount.html",
+            "dev.ucp.shopping.fulfillment.html",
+        ] {
+            assert!(site.join(page).exists(), "missing {}", page);
+        }
+
+        let discount = fs::read_to_string(site.join("dev.ucp.shopping.discount.html")).unwrap();
+        // Extends link back to the parent, and a resolved view per op/direction
+        assert!(discount.contains("href=\"dev.ucp.shopping.checkout.html\""));
+        assert!(discount.contains("id=\"create-request\""));
+        assert!(discount.contains("id=\"create-response\""));
+    }
+}
 
-        // Should produce an allOf composition
-        let all_of = schema["allOf"].as_array().unwrap();
-        assert_eq!(all_of.len(), 2); // discount + fulfillment
+/// Diff subcommand tests — breaking-change classification between schema versions
+mod diff_command {
+    use super::*;
 
-        // Both branches should have their specific properties
-        let has_discounts = all_of
-            .iter()
-            .any(|s| s["properties"]["discounts"].is_object());
-        let has_fulfillment = all_of
-            .iter()
-            .any(|s| s["properties"]["fulfillment"].is_object());
-        assert!(has_discounts, "Should include discount properties");
-        assert!(has_fulfillment, "Should include fulfillment properties");
+    /// Checkout fixture with buyer.email required on create and a new optional field.
+    fn write_checkout_v2(dir: &TempDir) -> std::path::PathBuf {
+        let mut schema: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string("tests/fixtures/checkout.json").unwrap())
+                .unwrap();
+        schema["properties"]["buyer"]["properties"]["email"]["ucp_request"]["create"] =
+            serde_json::json!("required");
+        schema["properties"]["gift_note"] = serde_json::json!({ "type": "string" });
+        write_temp_file(dir, "checkout_v2.json", &schema.to_string())
+    }
 
-        // UCP annotations should be PRESERVED in compose output
-        assert!(
-            stdout.contains("ucp_response") || stdout.contains("ucp_request"),
-            "compose should preserve UCP annotations"
+    #[test]
+    fn diff_reports_breaking_changes_as_json() {
+        let dir = TempDir::new().unwrap();
+        let new = write_checkout_v2(&dir);
+
+        let assert = cmd()
+            .args(["diff", "tests/fixtures/checkout.json"])
+            .arg(&new)
+            .args(["--request", "--op", "create", "--format", "json"])
+            .assert()
+            .code(1);
+        let diff: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+        assert_eq!(diff["breaking"], 1);
+        assert_eq!(diff["non_breaking"], 1);
+        assert_eq!(
+            diff["changes"][0],
+            serde_json::json!({
+                "path": "/properties/buyer/properties/email",
+                "kind": "visibility_changed",
+                "from": "optional",
+                "to": "required",
+                "breaking": true
+            })
         );
+        assert_eq!(diff["changes"][1]["kind"], "added");
+        assert_eq!(diff["changes"][1]["breaking"], false);
     }
 
     #[test]
-    fn compose_needs_no_direction_or_op() {
-        // compose is pure composition — no --op, no --request/--response needed
+    fn diff_text_output_and_non_breaking_exit() {
+        let dir = TempDir::new().unwrap();
+        let new = write_checkout_v2(&dir);
+
+        // Responses only gain a field: non-breaking, exit 0
+        cmd()
+            .args(["diff", "tests/fixtures/checkout.json"])
+            .arg(&new)
+            .args(["--response", "--op", "create"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "non-breaking  /properties/gift_note: added (optional)",
+            ))
+            .stdout(predicate::str::contains("0 breaking, 1 non-breaking"));
+
         cmd()
             .args([
-                "compose",
-                "tests/fixtures/compose/response_checkout_only.json",
-                "--schema-local-base",
-                "tests/fixtures/compose",
+                "diff",
+                "tests/fixtures/checkout.json",
+                "tests/fixtures/checkout.json",
+                "--request",
+                "--op",
+                "update",
             ])
             .assert()
             .success()
-            .stdout(predicate::str::contains("properties"));
+            .stdout(predicate::str::contains("No changes for request/update"));
     }
 
     #[test]
-    fn compose_non_payload_error() {
-        let dir = TempDir::new().unwrap();
-        let schema = write_temp_file(&dir, "schema.json", r#"{"name": "test"}"#);
-
-        // compose requires a self-describing payload; plain schema should error
+    fn diff_requires_direction() {
         cmd()
-            .args(["compose", schema.to_str().unwrap()])
+            .args([
+                "diff",
+                "tests/fixtures/checkout.json",
+                "tests/fixtures/checkout.json",
+                "--op",
+                "create",
+            ])
             .assert()
-            .code(2)
-            .stderr(predicate::str::contains("not a self-describing payload"));
+            .code(2);
     }
+}
+
+/// Lint subcommand tests — directory-level analysis
+mod stats_command {
+    use super::*;
 
     #[test]
-    fn compose_empty_capabilities_error() {
+    fn stats_reports_metrics_per_file() {
         let dir = TempDir::new().unwrap();
-        let payload = write_temp_file(&dir, "payload.json", r#"{"ucp": {"capabilities": {}}}"#);
+        write_temp_file(
+            &dir,
+            "checkout.json",
+            r##"{
+                "type": "object",
+                "properties": {
+                    "id": { "type": "string", "ucp_request": { "create": "omit" } },
+                    "buyer": { "$ref": "types/buyer.json" },
+                    "total": { "$ref": "#/$defs/amount" }
+                },
+                "$defs": { "amount": { "type": "integer" } }
+            }"##,
+        );
+        write_temp_file(&dir, "broken.json", "{ not json");
 
         cmd()
-            .args(["compose", payload.to_str().unwrap()])
+            .arg("stats")
+            .arg(dir.path())
             .assert()
-            .code(2)
-            .stderr(predicate::str::contains("no capabilities"));
+            .code(1)
+            .stdout(predicate::str::contains("  properties   3"))
+            .stdout(predicate::str::contains("  $refs        1 local, 1 remote"))
+            .stdout(predicate::str::contains(
+                "  annotations  1 request, 0 response",
+            ))
+            .stdout(predicate::str::contains("1 file(s) measured, 1 failed"));
     }
 
     #[test]
-    fn compose_missing_schema_base_error() {
-        cmd()
-            .args([
-                "compose",
-                "tests/fixtures/compose/response_checkout_only.json",
-                "--schema-local-base",
-                "/nonexistent/schemas",
-            ])
-            .assert()
-            .failure()
-            .stderr(predicate::str::contains("failed to fetch schema"));
+    fn stats_json_has_resolved_sizes() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "schema.json",
+            r#"{"properties": {"id": {"type": "string", "ucp_request": {"create": "omit"}}}}"#,
+        );
+
+        let output = cmd()
+            .args(["stats", "--format", "json"])
+            .arg(&schema)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stats: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let file = &stats[0];
+        assert_eq!(file["max_depth"], 1);
+        let create = &file["resolved"][0];
+        assert_eq!(create["operation"], "create");
+        assert!(create["request"].as_u64() < create["response"].as_u64());
     }
+}
+
+mod fmt_command {
+    use super::*;
 
     #[test]
-    fn compose_with_output_file() {
+    fn fmt_check_then_write() {
         let dir = TempDir::new().unwrap();
-        let output = dir.path().join("composed.json");
+        let schema = write_temp_file(
+            &dir,
+            "checkout.json",
+            r#"{"properties":{"id":{"ucp_request":"omit","type":"string"}},"type":"object"}"#,
+        );
 
         cmd()
-            .args([
-                "compose",
-                "tests/fixtures/compose/response_checkout_only.json",
-                "--schema-local-base",
-                "tests/fixtures/compose",
-                "--output",
-                output.to_str().unwrap(),
-            ])
+            .args(["fmt", dir.path().to_str().unwrap(), "--check"])
+            .assert()
+            .code(1)
+            .stdout(predicate::str::contains("Would reformat"))
+            .stdout(predicate::str::contains("1 file(s) would be reformatted"));
+        // --check leaves files alone
+        assert!(fs::read_to_string(&schema)
+            .unwrap()
+            .starts_with(r#"{"properties""#));
+
+        cmd()
+            .args(["fmt", dir.path().to_str().unwrap()])
             .assert()
             .success()
-            .stdout(predicate::str::is_empty());
+            .stdout(predicate::str::contains("Formatted"));
+        assert_eq!(
+            fs::read_to_string(&schema).unwrap(),
+            "{\n  \"type\": \"object\",\n  \"properties\": {\n    \"id\": {\n      \"type\": \"string\",\n      \"ucp_request\": \"omit\"\n    }\n  }\n}\n"
+        );
 
-        let content = fs::read_to_string(&output).unwrap();
-        let schema: serde_json::Value = serde_json::from_str(&content).unwrap();
-        assert!(schema["properties"]["id"].is_object());
+        cmd()
+            .args(["fmt", dir.path().to_str().unwrap(), "--check"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "0 file(s) would be reformatted, 1 unchanged",
+            ));
     }
 
     #[test]
-    fn compose_with_pretty() {
+    fn fmt_reports_invalid_json() {
+        let dir = TempDir::new().unwrap();
+        write_temp_file(&dir, "broken.json", "{ not json");
+
         cmd()
-            .args([
-                "compose",
-                "tests/fixtures/compose/response_checkout_only.json",
-                "--schema-local-base",
-                "tests/fixtures/compose",
-                "--pretty",
-            ])
+            .args(["fmt", dir.path().to_str().unwrap()])
             .assert()
-            .success()
-            .stdout(predicate::str::contains("{\n"));
+            .code(1)
+            .stderr(predicate::str::contains("broken.json"));
     }
+}
+
+mod lint_command {
+    use super::*;
 
     #[test]
-    fn compose_schema_remote_base() {
+    fn detect_orphans_reports_unreachable_schema() {
         let dir = TempDir::new().unwrap();
-        let payload = write_temp_file(
+        write_temp_file(
             &dir,
-            "payload.json",
-            r#"{
-                "ucp": {
-                    "capabilities": {
-                        "dev.ucp.shopping.checkout": [{
-                            "version": "2026-01-11",
-                            "schema": "https://ucp.dev/versioned/schemas/shopping/checkout.json"
-                        }]
-                    }
-                }
-            }"#,
+            "checkout.json",
+            r#"{"$id": "https://example.com/checkout.json", "name": "dev.example.checkout",
+                "properties": {"buyer": {"$ref": "buyer.json"}}}"#,
+        );
+        write_temp_file(
+            &dir,
+            "buyer.json",
+            r#"{"$id": "https://example.com/buyer.json", "type": "object"}"#,
         );
+        write_temp_file(
+            &dir,
+            "legacy.json",
+            r#"{"$id": "https://example.com/legacy.json", "type": "object"}"#,
+        );
+        let base = dir.path().to_str().unwrap();
 
         cmd()
             .args([
-                "compose",
-                payload.to_str().unwrap(),
+                "lint",
+                base,
+                "--detect-orphans",
                 "--schema-local-base",
-                "tests/fixtures/compose",
-                "--schema-remote-base",
-                "https://ucp.dev/versioned",
+                base,
             ])
             .assert()
             .success()
-            .stdout(predicate::str::contains("properties"));
+            .stdout(predicate::str::contains("warning[UCP240]"))
+            .stdout(predicate::str::contains("legacy.json"))
+            .stdout(predicate::str::contains("buyer.json").count(1));
+
+        // Plain lint doesn't analyze reachability
+        cmd()
+            .args(["lint", base])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("UCP240").not());
     }
 
     #[test]
-    fn compose_help() {
+    fn detect_orphans_requires_schema_base() {
         cmd()
-            .args(["compose", "--help"])
+            .args(["lint", "tests/fixtures", "--detect-orphans"])
             .assert()
-            .success()
-            .stdout(predicate::str::contains("--schema-local-base"))
-            .stdout(predicate::str::contains("--pretty"))
-            .stdout(predicate::str::contains("--output"))
-            // compose is pure composition — no direction or op flags
-            .stdout(predicate::str::contains("--request").not())
-            .stdout(predicate::str::contains("--response").not())
-            .stdout(predicate::str::contains("--op").not());
+            .code(2)
+            .stderr(predicate::str::contains("--schema-local-base"));
     }
 }
 
-/// Resolve auto-composes when given a self-describing payload
-mod resolve_payload {
+/// Meta-schema subcommand tests
+mod meta_schema_command {
     use super::*;
 
     #[test]
-    fn resolve_auto_composes_payload() {
-        // resolve with a payload should auto-compose + resolve in one step
-        let assert = cmd()
+    fn meta_schema_backs_lint() {
+        let output = cmd().args(["meta-schema"]).output().unwrap();
+        assert!(output.status.success());
+        let meta: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(meta["$id"], "urn:ucp-schema:meta-schema");
+
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "checkout.json",
+            r#"{"$id": "https://example.com/checkout.json",
+                "properties": {"card": {"type": "string", "ucp_sensitive": "yes"}}}"#,
+        );
+        cmd()
+            .args(["lint", schema.to_str().unwrap()])
+            .assert()
+            .code(1)
+            .stdout(predicate::str::contains("E006"))
+            .stdout(predicate::str::contains("/properties/card/ucp_sensitive"));
+    }
+}
+
+/// Extract subcommand tests
+mod extract_command {
+    use super::*;
+
+    #[test]
+    fn extract_prints_nested_payload() {
+        let dir = TempDir::new().unwrap();
+        let envelope = write_temp_file(
+            &dir,
+            "request.json",
+            r#"{"meta": {"profile": "https://ucp.dev/profiles/agent.json"},
+                "checkout": {"line_items": [{"id": "li_1"}]}}"#,
+        );
+        let args = [
+            "extract",
+            envelope.to_str().unwrap(),
+            "--schema-local-base",
+            "tests/fixtures/compose",
+        ];
+
+        let output = cmd().args(args).output().unwrap();
+        assert!(output.status.success());
+        let payload: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(payload, serde_json::json!({"line_items": [{"id": "li_1"}]}));
+
+        let output = cmd().args(args).arg("--with-key").output().unwrap();
+        let wrapped: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(wrapped["key"], "checkout");
+        assert_eq!(wrapped["payload"], payload);
+
+        cmd()
             .args([
-                "resolve",
-                "tests/fixtures/compose/response_checkout_only.json",
-                "--response",
-                "--op",
-                "read",
+                "extract",
+                "tests/fixtures/compose/request_wrong_params.json",
                 "--schema-local-base",
                 "tests/fixtures/compose",
             ])
             .assert()
-            .success();
+            .code(2)
+            .stderr(predicate::str::contains("expected one of: checkout"));
+    }
+}
 
-        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
-        let schema: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+/// Envelope subcommand tests
+mod envelope_command {
+    use super::*;
 
-        // Should have resolved properties (annotations stripped)
-        assert!(schema["properties"]["id"].is_object());
-        // UCP annotations should be STRIPPED (resolve goes beyond compose)
-        assert!(
-            !stdout.contains("ucp_response") && !stdout.contains("ucp_request"),
-            "resolve should strip UCP annotations"
+    #[test]
+    fn envelope_builds_request_that_validates() {
+        let dir = TempDir::new().unwrap();
+        let payload = write_temp_file(
+            &dir,
+            "checkout.json",
+            r#"{"line_items": [{"item": {"id": "sku"}, "quantity": 1}]}"#,
+        );
+        let output = cmd()
+            .args([
+                "envelope",
+                payload.to_str().unwrap(),
+                "--profile",
+                "https://ucp.dev/profiles/agent.json",
+                "--op",
+                "create",
+                "--id",
+                "req-7",
+                "--schema-local-base",
+                "tests/fixtures/compose",
+            ])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let request: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(request["jsonrpc"], "2.0");
+        assert_eq!(request["id"], "req-7");
+        assert_eq!(request["method"], "create_checkout");
+        assert_eq!(
+            request["params"]["meta"]["profile"],
+            "https://ucp.dev/profiles/agent.json"
         );
-    }
 
-    #[test]
-    fn resolve_auto_infers_direction_from_payload() {
-        // Direction is auto-inferred from payload's ucp.capabilities
-        let assert = cmd()
+        let envelope = write_temp_file(&dir, "request.json", &request.to_string());
+        cmd()
             .args([
-                "resolve",
-                "tests/fixtures/compose/response_checkout_only.json",
+                "validate",
+                envelope.to_str().unwrap(),
+                "--unwrap-pointer",
+                "#/params",
                 "--op",
-                "read",
+                "create",
                 "--schema-local-base",
                 "tests/fixtures/compose",
             ])
             .assert()
             .success();
+    }
+}
 
-        let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
-        let schema: serde_json::Value = serde_json::from_str(&stdout).unwrap();
-        assert!(schema["properties"]["id"].is_object());
+/// Profile subcommand tests
+mod profile_command {
+    use super::*;
+
+    #[test]
+    fn generate_lists_capabilities_with_extends() {
+        let output = cmd()
+            .args([
+                "profile",
+                "generate",
+                "tests/fixtures/compose/schemas/shopping",
+                "--base-url",
+                "https://ucp.dev/schemas/shopping/",
+            ])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let profile: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let capabilities = &profile["ucp"]["capabilities"];
+        assert_eq!(
+            capabilities["dev.ucp.shopping.checkout"][0]["schema"],
+            "https://ucp.dev/schemas/shopping/checkout.json"
+        );
+        assert!(capabilities["dev.ucp.shopping.checkout"][0]
+            .get("extends")
+            .is_none());
+        assert_eq!(
+            capabilities["dev.ucp.shopping.discount"][0]["extends"],
+            "dev.ucp.shopping.checkout"
+        );
+        assert_eq!(profile["ucp"]["version"], "2026-01-11");
+
+        cmd()
+            .args(["profile", "generate", "tests/fixtures/invalid"])
+            .args(["--base-url", "https://ucp.dev/schemas"])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("no capability schemas"));
     }
 
     #[test]
-    fn resolve_no_warning_on_schema_input() {
+    fn check_reports_profile_issues() {
         cmd()
             .args([
-                "resolve",
-                "tests/fixtures/checkout.json",
-                "--response",
-                "--op",
-                "read",
+                "profile",
+                "check",
+                "tests/fixtures/compose/profiles/agent.json",
+                "--schema-local-base",
+                "tests/fixtures/compose",
             ])
             .assert()
             .success()
-            .stderr(predicate::str::contains("compose").not());
+            .stdout(predicate::str::contains(
+                "2 capabilities checked, no issues",
+            ));
+
+        let dir = TempDir::new().unwrap();
+        let profile = write_temp_file(
+            &dir,
+            "profile.json",
+            r#"{"ucp": {"capabilities": {
+                "dev.ucp.shopping.checkout": [{"version": "2026-01-11", "schema": "https://ucp.dev/schemas/shopping/checkout.json"}],
+                "dev.ucp.shopping.discount": [{"version": "Jan 2026", "schema": "https://ucp.dev/schemas/shopping/discount.json", "extends": "dev.ucp.shopping.cart"}]
+            }}}"#,
+        );
+        let output = cmd()
+            .args(["profile", "check", profile.to_str().unwrap()])
+            .args(["--schema-local-base", "tests/fixtures/compose"])
+            .args(["--format", "json"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        let check: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let issues = check["issues"].as_array().unwrap();
+        assert!(issues
+            .iter()
+            .all(|i| i["capability"] == "dev.ucp.shopping.discount"));
+        let messages: Vec<&str> = issues
+            .iter()
+            .map(|i| i["message"].as_str().unwrap())
+            .collect();
+        assert!(messages.iter().any(|m| m.contains("YYYY-MM-DD")));
+        assert!(messages
+            .iter()
+            .any(|m| m.contains("unknown capability 'dev.ucp.shopping.cart'")));
     }
 }
 
-/// Flag/input-type compatibility: reject flags that don't apply
-mod flag_validation {
+/// Query subcommand tests
+mod query_command {
     use super::*;
 
-    // resolve: --bundle rejected for payload input
     #[test]
-    fn resolve_bundle_rejected_for_payload() {
+    fn query_prints_fragment_of_each_stage() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "checkout.json",
+            r#"{"type": "object", "properties": {
+                "id": {"type": "string", "description": "Checkout id", "ucp_request": {"create": "omit"}},
+                "buyer": {"$ref": "buyer.json"}
+            }}"#,
+        );
+        write_temp_file(
+            &dir,
+            "buyer.json",
+            r#"{"type": "object", "title": "Buyer"}"#,
+        );
+        let schema = schema.to_str().unwrap();
+
         cmd()
             .args([
-                "resolve",
-                "tests/fixtures/compose/response_checkout_only.json",
+                "query",
+                schema,
+                "--path",
+                "/properties/id/description",
+                "--raw",
+            ])
+            .assert()
+            .success()
+            .stdout("Checkout id\n");
+        cmd()
+            .args([
+                "query",
+                schema,
+                "--path",
+                "#/properties/buyer/title",
                 "--bundle",
-                "--op",
-                "read",
-                "--schema-local-base",
-                "tests/fixtures/compose",
             ])
             .assert()
+            .success()
+            .stdout("\"Buyer\"\n");
+
+        let output = cmd()
+            .args(["query", schema, "--path", "/properties"])
+            .args(["--op", "create", "--request"])
+            .output()
+            .unwrap();
+        let properties: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert!(properties.get("id").is_none());
+
+        cmd()
+            .args(["query", schema, "--path", "/properties/total"])
+            .assert()
             .code(2)
-            .stderr(predicate::str::contains(
-                "--bundle does not apply to payload input",
-            ));
+            .stderr(predicate::str::contains("path /properties/total not found"));
     }
 
-    // resolve: --schema-local-base rejected for schema input
     #[test]
-    fn resolve_schema_local_base_rejected_for_schema() {
+    fn query_composes_payloads() {
         cmd()
             .args([
-                "resolve",
-                "tests/fixtures/checkout.json",
+                "query",
+                "tests/fixtures/compose/response_with_extensions.json",
                 "--schema-local-base",
                 "tests/fixtures/compose",
-                "--request",
-                "--op",
-                "create",
+                "--path",
+                "/allOf/0/properties/discounts/type",
+                "--raw",
             ])
             .assert()
-            .code(2)
-            .stderr(predicate::str::contains("only apply to payload input"));
+            .success()
+            .stdout("object\n");
     }
+}
+
+/// Explore subcommand tests
+mod explore_command {
+    use super::*;
 
-    // resolve: --schema-remote-base rejected for schema input
     #[test]
-    fn resolve_schema_remote_base_rejected_for_schema() {
+    fn explore_runs_commands_from_stdin() {
+        let dir = TempDir::new().unwrap();
+        let schema = write_temp_file(
+            &dir,
+            "checkout.json",
+            r##"{
+                "type": "object",
+                "properties": {
+                    "id": { "type": "string", "ucp_request": { "create": "omit", "update": "required" } },
+                    "buyer": { "$ref": "#/$defs/buyer" }
+                },
+                "$defs": { "buyer": { "properties": { "email": { "type": "string" } } } }
+            }"##,
+        );
+
         cmd()
-            .args([
-                "resolve",
-                "tests/fixtures/checkout.json",
-                "--schema-local-base",
-                "tests/fixtures/compose",
-                "--schema-remote-base",
-                "https://ucp.dev/draft",
-                "--request",
-                "--op",
-                "create",
-            ])
+            .args(["explore", schema.to_str().unwrap()])
+            .write_stdin("op update\ncd buyer\nref\npwd\nfrobnicate\nquit\nls\n")
             .assert()
-            .code(2)
-            .stderr(predicate::str::contains("only apply to payload input"));
+            .success()
+            .stdout(predicate::str::contains("id     string    omit*"))
+            .stdout(predicate::str::contains("id     string    required*"))
+            .stdout(predicate::str::contains("email  string    optional"))
+            .stdout(predicate::str::contains("/$defs/buyer\n"))
+            .stderr(predicate::str::contains("unknown command \"frobnicate\""));
     }
+}
+
+/// Probe subcommand tests
+mod probe_command {
+    use super::*;
 
-    // validate: --schema-local-base rejected with explicit --schema
     #[test]
-    fn validate_schema_local_base_rejected_with_explicit_schema() {
+    fn probe_reports_conformance_of_live_responses() {
+        let mut server = mockito::Server::new();
+        let conformant = server
+            .mock("GET", "/checkouts/c_1")
+            .match_header("authorization", "Bearer t")
+            .with_body(r#"{"id": "c_1", "status": "incomplete"}"#)
+            .expect(2)
+            .create();
+        let missing_status = server
+            .mock("GET", "/checkouts/c_2")
+            .with_body(r#"{"id": "c_2"}"#)
+            .expect(2)
+            .create();
+        let not_found = server
+            .mock("GET", "/checkouts/c_3")
+            .with_status(404)
+            .expect(2)
+            .create();
+
+        let dir = TempDir::new().unwrap();
+        let templates = write_temp_file(
+            &dir,
+            "requests.json",
+            r#"[{"path": "/checkouts/c_1"}, {"path": "/checkouts/c_2"}, {"path": "checkouts/c_3"}]"#,
+        );
+        let base_url = server.url();
+        let args = [
+            "probe",
+            base_url.as_str(),
+            "--profile",
+            "https://ucp.dev/profiles/agent.json",
+            "--schema-local-base",
+            "tests/fixtures/compose",
+            "--op",
+            "read",
+            "--template",
+            templates.to_str().unwrap(),
+            "--header",
+            "Authorization: Bearer t",
+        ];
+
         cmd()
-            .args([
-                "validate",
-                "tests/fixtures/compose/response_checkout_only.json",
-                "--schema",
-                "tests/fixtures/checkout.json",
-                "--schema-local-base",
-                "tests/fixtures/compose",
-                "--response",
-                "--op",
-                "read",
-            ])
+            .args(args)
             .assert()
-            .code(2)
-            .stderr(predicate::str::contains(
-                "do not apply with explicit --schema",
-            ));
+            .code(1)
+            .stdout(predicate::str::contains(format!(
+                "✓\x1b[0m GET {}/checkouts/c_1 (200)",
+                base_url
+            )))
+            .stdout(predicate::str::contains(
+                "\"status\" is a required property",
+            ))
+            .stdout(predicate::str::contains("HTTP 404"))
+            .stdout(predicate::str::contains("3 probed: 2 failed"));
+
+        let output = cmd()
+            .args(args)
+            .args(["--format", "json"])
+            .output()
+            .unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(report["probed"], 3);
+        assert_eq!(report["failed"], 2);
+        assert_eq!(report["results"][1]["violations"][0]["keyword"], "required");
+        assert_eq!(report["results"][2]["status"], 404);
+
+        conformant.assert();
+        missing_status.assert();
+        not_found.assert();
+    }
+}
+
+/// Replay subcommand tests
+mod replay_command {
+    use super::*;
+
+    fn entry(
+        method: &str,
+        url: &str,
+        request: Option<&str>,
+        status: u16,
+        response: &str,
+    ) -> serde_json::Value {
+        let mut entry = serde_json::json!({
+            "request": {
+                "method": method,
+                "url": url,
+                "headers": [{ "name": "UCP-Agent", "value": "profile=\"https://ucp.dev/profiles/agent.json\"" }]
+            },
+            "response": { "status": status, "content": { "mimeType": "application/json", "text": response } }
+        });
+        if let Some(body) = request {
+            entry["request"]["postData"] =
+                serde_json::json!({ "mimeType": "application/json", "text": body });
+        }
+        entry
     }
 
-    // validate: --schema-remote-base rejected with explicit --schema
     #[test]
-    fn validate_schema_remote_base_rejected_with_explicit_schema() {
+    fn replay_validates_bodies_and_summarizes_by_endpoint() {
+        let capabilities = r#""ucp": {"capabilities": {"dev.ucp.shopping.checkout": [{"version": "2026-01-11", "schema": "https://ucp.dev/schemas/shopping/checkout.json"}]}}"#;
+        let har = serde_json::json!({ "log": { "entries": [
+            entry(
+                "GET",
+                "https://m.example/checkouts/chk_123",
+                None,
+                200,
+                &format!(r#"{{{}, "id": "chk_123", "status": "incomplete"}}"#, capabilities),
+            ),
+            entry(
+                "POST",
+                "https://m.example/checkouts",
+                Some(r#"{"line_items": [], "discounts": "SAVE10"}"#),
+                201,
+                &format!(r#"{{{}, "id": "chk_124"}}"#, capabilities),
+            ),
+            entry("GET", "https://m.example/health", None, 200, r#"{"status": "ok"}"#),
+            entry("OPTIONS", "https://m.example/checkouts", None, 204, ""),
+        ] } });
+        let dir = TempDir::new().unwrap();
+        let har_file = write_temp_file(&dir, "traffic.har", &har.to_string());
+        let args = [
+            "replay",
+            har_file.to_str().unwrap(),
+            "--schema-local-base",
+            "tests/fixtures/compose",
+        ];
+
         cmd()
-            .args([
-                "validate",
-                "tests/fixtures/compose/response_checkout_only.json",
-                "--schema",
-                "tests/fixtures/checkout.json",
-                "--schema-local-base",
-                "tests/fixtures/compose",
-                "--schema-remote-base",
-                "https://ucp.dev/draft",
-                "--response",
-                "--op",
-                "read",
-            ])
+            .args(args)
             .assert()
-            .code(2)
-            .stderr(predicate::str::contains(
-                "do not apply with explicit --schema",
+            .code(1)
+            .stdout(predicate::str::contains(
+                "#2 POST https://m.example/checkouts (create request)",
+            ))
+            .stdout(predicate::str::contains(
+                "#2 POST https://m.example/checkouts (create response)",
+            ))
+            .stdout(predicate::str::contains(
+                "4 exchanges replayed: 1 failed (2 skipped)",
             ));
+
+        let output = cmd()
+            .args(args)
+            .args(["--format", "json"])
+            .output()
+            .unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(report["failed"], 1);
+        assert_eq!(report["skipped"], 2);
+        assert_eq!(
+            report["endpoints"][0],
+            serde_json::json!({ "endpoint": "GET /checkouts/{id}", "exchanges": 1, "failed": 0, "skipped": 0 })
+        );
+        assert_eq!(report["failures"][0]["errors"][0]["path"], "/discounts");
+
+        // A custom rule takes precedence over the defaults
+        cmd()
+            .args(args)
+            .args(["--rule", "GET /checkouts/=create", "--format", "json"])
+            .assert()
+            .stdout(predicate::str::contains("\"operation\": \"create\""));
     }
 }
 
-/// Verbose mode tests
-mod verbose {
+/// Cache subcommand tests
+mod cache_command {
     use super::*;
 
     #[test]
-    fn resolve_verbose_shows_pipeline_stages() {
+    fn cached_fetches_are_reused_listed_and_cleared() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("GET", "/schema.json")
+            .with_body(r#"{"type": "object", "properties": {"name": {"type": "string"}}}"#)
+            .expect(1)
+            .create();
+        let url = format!("{}/schema.json", server.url());
+        let dir = TempDir::new().unwrap();
+        let cache = dir.path().join("cache");
+        let cache = cache.to_str().unwrap();
+
+        for extra in [None, Some("--no-network")] {
+            cmd()
+                .args(["resolve", &url, "--request", "--op", "create"])
+                .args(["--cache-dir", cache])
+                .args(extra)
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("name"));
+        }
+        mock.assert();
+
         cmd()
-            .args([
-                "resolve",
-                "tests/fixtures/compose/response_checkout_only.json",
-                "--op",
-                "read",
-                "--schema-local-base",
-                "tests/fixtures/compose",
-                "--verbose",
-            ])
+            .args(["cache", "ls", "--cache-dir", cache])
             .assert()
             .success()
-            .stderr(predicate::str::contains("[load]"))
-            .stderr(predicate::str::contains("[detect]"))
-            .stderr(predicate::str::contains("[compose]"))
-            .stderr(predicate::str::contains("[resolve]"));
-    }
-
-    #[test]
-    fn compose_verbose_shows_annotations_preserved() {
+            .stdout(predicate::str::contains(url.as_str()));
         cmd()
-            .args([
-                "compose",
-                "tests/fixtures/compose/response_checkout_only.json",
-                "--schema-local-base",
-                "tests/fixtures/compose",
-                "--verbose",
-            ])
+            .args(["cache", "prune", "--max-age", "1d", "--cache-dir", cache])
             .assert()
             .success()
-            .stderr(predicate::str::contains(
-                "[compose] composing schemas (annotations preserved)",
+            .stdout(predicate::str::contains(
+                "removed 0 cache entries older than 1d",
             ));
+        cmd()
+            .args(["cache", "clear"])
+            .env("UCP_SCHEMA_CACHE_DIR", cache)
+            .assert()
+            .success()
+            .stdout("removed 1 cache entries\n");
+        cmd()
+            .args(["cache", "ls"])
+            .env_remove("UCP_SCHEMA_CACHE_DIR")
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("no cache directory"));
     }
+}
+
+/// Push and pull subcommand tests
+mod registry_command {
+    use super::*;
+
+    const CHECKOUT: &str = "tests/fixtures/compose/schemas/shopping/checkout.json";
 
     #[test]
-    fn resolve_verbose_schema_input() {
+    fn push_publishes_new_versions_once() {
+        let body = std::fs::read_to_string(CHECKOUT).unwrap();
+        let sha256 = ucp_schema::sha256_hex(body.as_bytes());
+        let dir = TempDir::new().unwrap();
+        write_temp_file(&dir, "checkout.json", &body);
+
+        let mut server = mockito::Server::new();
+        let path = "/dev.ucp.shopping.checkout/2026-01-11";
+        let missing = server.mock("GET", path).with_status(404).create();
+        let put = server
+            .mock("PUT", path)
+            .match_header("authorization", "Bearer t")
+            .match_header("x-ucp-schema-sha256", sha256.as_str())
+            .match_body(body.as_str())
+            .with_status(201)
+            .create();
+        let registry = server.url();
+        let args = [
+            "push",
+            dir.path().to_str().unwrap(),
+            "--registry",
+            registry.as_str(),
+            "--token",
+            "t",
+        ];
+
         cmd()
-            .args([
-                "resolve",
-                "tests/fixtures/checkout.json",
-                "--request",
-                "--op",
-                "create",
-                "--verbose",
-            ])
+            .args(args)
             .assert()
             .success()
-            .stderr(predicate::str::contains("[detect] input is a schema file"))
-            .stderr(predicate::str::contains(
-                "[resolve] resolving for request/create",
+            .stdout(predicate::str::contains(
+                "published     dev.ucp.shopping.checkout 2026-01-11",
             ));
+        missing.assert();
+        put.assert();
+        missing.remove();
+
+        let published = server.mock("GET", path).with_body(&body).create();
+        cmd()
+            .args(args)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("unchanged"));
+        published.remove();
+
+        // Same version, different content
+        server.mock("GET", path).with_body("{}").create();
+        cmd()
+            .args(args)
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("immutable"));
     }
 
     #[test]
-    fn no_verbose_output_by_default() {
+    fn pull_writes_schemas_and_verifies_hashes() {
+        let body = std::fs::read_to_string(CHECKOUT).unwrap();
+        let sha256 = ucp_schema::sha256_hex(body.as_bytes());
+        let mut server = mockito::Server::new();
+        server
+            .mock("GET", "/dev.ucp.shopping.checkout/2026-01-11")
+            .with_header("X-Ucp-Schema-Sha256", &sha256)
+            .with_body(&body)
+            .create();
+        server
+            .mock("GET", "/dev.ucp.shopping.discount/2026-01-11")
+            .with_header("X-Ucp-Schema-Sha256", &sha256)
+            .with_body("{}")
+            .create();
+        let registry = server.url();
+        let out = TempDir::new().unwrap();
+
         cmd()
-            .args([
-                "resolve",
-                "tests/fixtures/checkout.json",
-                "--request",
-                "--op",
-                "create",
-            ])
+            .args(["pull", "dev.ucp.shopping.checkout@2026-01-11"])
+            .args(["--registry", registry.as_str()])
+            .args(["--sha256", sha256.as_str()])
+            .arg("--out")
+            .arg(out.path())
             .assert()
             .success()
-            .stderr(predicate::str::contains("[load]").not())
-            .stderr(predicate::str::contains("[resolve]").not());
+            .stdout(predicate::str::contains("pulled dev.ucp.shopping.checkout"));
+        let written = out
+            .path()
+            .join("dev.ucp.shopping.checkout")
+            .join("2026-01-11.json");
+        assert_eq!(std::fs::read_to_string(written).unwrap(), body);
+
+        cmd()
+            .args(["pull", "dev.ucp.shopping.discount@2026-01-11"])
+            .args(["--registry", registry.as_str()])
+            .arg("--out")
+            .arg(out.path())
+            .assert()
+            .code(3)
+            .stderr(predicate::str::contains("does not match"));
+        assert!(!out.path().join("dev.ucp.shopping.discount").exists());
+
+        cmd()
+            .args(["pull", "dev.ucp.shopping.checkout"])
+            .args(["--registry", registry.as_str()])
+            .assert()
+            .code(2);
     }
 }
@@ -1,7 +1,7 @@
 //! Integration tests for schema resolution.
 
 use serde_json::{json, Value};
-use ucp_schema::{resolve, Direction, ResolveError, ResolveOptions};
+use ucp_schema::{resolve, strip_annotations, Denullify, Direction, ResolveError, ResolveOptions};
 
 // === Visibility Parsing Tests ===
 
@@ -142,7 +142,7 @@ mod error_handling {
         let schema = json!({
             "type": "object",
             "properties": {
-                "id": { "type": "string", "ucp_request": "readonly" }
+                "id": { "type": "string", "ucp_request": "hidden" }
             }
         });
         let options = ResolveOptions::new(Direction::Request, "create");
@@ -150,7 +150,7 @@ mod error_handling {
 
         assert!(matches!(
             result,
-            Err(ResolveError::UnknownVisibility { value, .. }) if value == "readonly"
+            Err(ResolveError::UnknownVisibility { value, .. }) if value == "hidden"
         ));
     }
 
@@ -402,6 +402,46 @@ mod required_array {
 mod recursion {
     use super::*;
 
+    #[test]
+    fn applicator_keywords() {
+        let schema = json!({
+            "type": "object",
+            "patternProperties": {
+                "^x-": { "type": "string", "ucp_request": "omit" },
+                "required": {
+                    "type": "object",
+                    "properties": { "secret": { "type": "string", "ucp_request": "omit" } }
+                }
+            },
+            "propertyNames": { "pattern": "^[a-z]", "ucp_response": "omit" },
+            "prefixItems": [{
+                "type": "object",
+                "properties": { "a": { "type": "string", "ucp_request": "required" } }
+            }],
+            "contains": {
+                "properties": { "b": { "type": "string", "ucp_request": "omit" } }
+            }
+        });
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let result = resolve(&schema, &options).unwrap();
+
+        // Pattern keys are names, not keywords; an omitted pattern is dropped
+        assert_eq!(
+            result["patternProperties"],
+            json!({ "required": { "type": "object", "properties": {} } })
+        );
+        assert_eq!(result["prefixItems"][0]["required"], json!(["a"]));
+        assert_eq!(result["contains"], json!({ "properties": {} }));
+        assert!(!result.to_string().contains("ucp_"));
+
+        let options = ResolveOptions::new(Direction::Response, "read");
+        let result = resolve(&schema, &options).unwrap();
+        assert_eq!(
+            result["patternProperties"]["^x-"],
+            json!({ "type": "string" })
+        );
+    }
+
     #[test]
     fn nested_properties() {
         let schema = json!({
@@ -460,87 +500,806 @@ mod recursion {
         let options = ResolveOptions::new(Direction::Request, "create");
         let result = resolve(&schema, &options).unwrap();
 
-        // sku in items should be present and required
-        assert!(result["properties"]["line_items"]["items"]["properties"]
-            .get("sku")
-            .is_some());
-
-        // price in items should be omitted
-        assert!(result["properties"]["line_items"]["items"]["properties"]
-            .get("price")
-            .is_none());
+        // sku in items should be present and required
+        assert!(result["properties"]["line_items"]["items"]["properties"]
+            .get("sku")
+            .is_some());
+
+        // price in items should be omitted
+        assert!(result["properties"]["line_items"]["items"]["properties"]
+            .get("price")
+            .is_none());
+    }
+
+    #[test]
+    fn defs() {
+        let schema = json!({
+            "type": "object",
+            "$defs": {
+                "address": {
+                    "type": "object",
+                    "properties": {
+                        "street": { "type": "string" },
+                        "internal_id": { "type": "string", "ucp_request": "omit" }
+                    }
+                }
+            },
+            "properties": {
+                "shipping": { "$ref": "#/$defs/address" }
+            }
+        });
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let result = resolve(&schema, &options).unwrap();
+
+        // $defs should be transformed
+        assert!(result["$defs"]["address"]["properties"]
+            .get("street")
+            .is_some());
+        assert!(result["$defs"]["address"]["properties"]
+            .get("internal_id")
+            .is_none());
+    }
+
+    #[test]
+    fn deep_nesting_five_levels() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "level1": {
+                    "type": "object",
+                    "properties": {
+                        "level2": {
+                            "type": "object",
+                            "properties": {
+                                "level3": {
+                                    "type": "object",
+                                    "properties": {
+                                        "level4": {
+                                            "type": "object",
+                                            "properties": {
+                                                "level5": {
+                                                    "type": "string",
+                                                    "ucp_request": "omit"
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let result = resolve(&schema, &options).unwrap();
+
+        // Deep nested field should be omitted
+        assert!(
+            result["properties"]["level1"]["properties"]["level2"]["properties"]["level3"]
+                ["properties"]["level4"]["properties"]
+                .get("level5")
+                .is_none()
+        );
+    }
+}
+
+// === Conditional Subschema Tests ===
+
+mod conditional_subschemas {
+    use super::*;
+
+    fn gift_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "gift": { "type": "boolean" }
+            },
+            "dependentSchemas": {
+                "gift": {
+                    "properties": {
+                        "note": {
+                            "type": "string",
+                            "ucp_request": { "create": "required", "update": "omit" }
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn dependent_schemas_required() {
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let result = resolve(&gift_schema(), &options).unwrap();
+
+        let branch = &result["dependentSchemas"]["gift"];
+        assert_eq!(branch["properties"]["note"], json!({ "type": "string" }));
+        assert_eq!(branch["required"], json!(["note"]));
+    }
+
+    #[test]
+    fn dependent_schemas_omit() {
+        let options = ResolveOptions::new(Direction::Request, "update");
+        let result = resolve(&gift_schema(), &options).unwrap();
+
+        let branch = &result["dependentSchemas"]["gift"];
+        assert!(branch["properties"].get("note").is_none());
+        assert!(!result.to_string().contains("ucp_request"));
+    }
+
+    #[test]
+    fn dependent_schemas_keyword_named_entry() {
+        // Entry names are property names, not keywords
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "required": { "type": "boolean" }
+            },
+            "dependentSchemas": {
+                "required": {
+                    "properties": {
+                        "reason": { "type": "string", "ucp_request": "required" }
+                    }
+                }
+            }
+        });
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let result = resolve(&schema, &options).unwrap();
+
+        assert_eq!(
+            result["dependentSchemas"]["required"]["required"],
+            json!(["reason"])
+        );
+        assert!(result["dependentSchemas"]["required"]["properties"]["reason"].is_object());
+    }
+
+    #[test]
+    fn if_then_else_resolved() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "method": { "type": "string" }
+            },
+            "if": {
+                "properties": { "method": { "const": "shipping" } }
+            },
+            "then": {
+                "properties": {
+                    "address": { "type": "object", "ucp_request": "required" }
+                }
+            },
+            "else": {
+                "properties": {
+                    "address": { "type": "object", "ucp_request": "omit" }
+                }
+            }
+        });
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let result = resolve(&schema, &options).unwrap();
+
+        assert_eq!(result["then"]["required"], json!(["address"]));
+        assert!(result["else"]["properties"].get("address").is_none());
+        assert!(!result.to_string().contains("ucp_request"));
+    }
+
+    #[test]
+    fn not_resolved() {
+        let schema = json!({
+            "type": "object",
+            "not": {
+                "properties": {
+                    "legacy_id": { "type": "string", "ucp_response": "required" }
+                }
+            }
+        });
+        let options = ResolveOptions::new(Direction::Response, "read");
+        let result = resolve(&schema, &options).unwrap();
+
+        assert_eq!(result["not"]["required"], json!(["legacy_id"]));
+        assert!(!result.to_string().contains("ucp_response"));
+    }
+
+    #[test]
+    fn dependencies_pruned_when_property_omitted() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "card": { "type": "string" },
+                "cvv": { "type": "string", "ucp_response": "omit" },
+                "expiry": { "type": "string" },
+                "token": { "type": "string", "ucp_response": "omit" }
+            },
+            "dependentRequired": {
+                "card": ["cvv", "expiry"],
+                "cvv": ["card"],
+                "token": ["cvv"]
+            },
+            "dependencies": {
+                "expiry": ["cvv"],
+                "cvv": { "required": ["card"] }
+            }
+        });
+        let options = ResolveOptions::new(Direction::Response, "read");
+        let result = resolve(&schema, &options).unwrap();
+
+        assert_eq!(result["dependentRequired"], json!({ "card": ["expiry"] }));
+        assert!(result.get("dependencies").is_none());
+
+        // Nothing to prune for requests
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let result = resolve(&schema, &options).unwrap();
+        assert_eq!(result["dependentRequired"], schema["dependentRequired"]);
+        assert_eq!(result["dependencies"], schema["dependencies"]);
+    }
+}
+
+// === Enum Narrowing Tests ===
+
+mod enum_narrowing {
+    use super::*;
+
+    fn status_schema(ucp_enum: Value) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "status": {
+                    "type": "string",
+                    "enum": ["incomplete", "ready", "completed", "fraud_review"],
+                    "ucp_enum": ucp_enum
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn direction_keyed() {
+        let schema = status_schema(json!({
+            "request": ["incomplete", "ready"],
+            "response": ["incomplete", "ready", "completed", "fraud_review"]
+        }));
+
+        let options = ResolveOptions::new(Direction::Request, "update");
+        let result = resolve(&schema, &options).unwrap();
+        assert_eq!(
+            result["properties"]["status"],
+            json!({ "type": "string", "enum": ["incomplete", "ready"] })
+        );
+
+        let options = ResolveOptions::new(Direction::Response, "read");
+        let result = resolve(&schema, &options).unwrap();
+        assert_eq!(
+            result["properties"]["status"]["enum"],
+            json!(["incomplete", "ready", "completed", "fraud_review"])
+        );
+    }
+
+    #[test]
+    fn operation_keyed() {
+        let schema = status_schema(json!({ "create": ["incomplete"] }));
+
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let result = resolve(&schema, &options).unwrap();
+        assert_eq!(
+            result["properties"]["status"]["enum"],
+            json!(["incomplete"])
+        );
+
+        // No entry for this op: the schema's own enum is kept
+        let options = ResolveOptions::new(Direction::Request, "update");
+        let result = resolve(&schema, &options).unwrap();
+        assert_eq!(
+            result["properties"]["status"]["enum"],
+            json!(["incomplete", "ready", "completed", "fraud_review"])
+        );
+    }
+
+    #[test]
+    fn direction_then_operation() {
+        let schema = status_schema(json!({
+            "request": ["incomplete", "ready"],
+            "response": {
+                "create": ["incomplete"],
+                "read": ["incomplete", "ready", "completed", "fraud_review"]
+            }
+        }));
+
+        let options = ResolveOptions::new(Direction::Response, "create");
+        let result = resolve(&schema, &options).unwrap();
+        assert_eq!(
+            result["properties"]["status"]["enum"],
+            json!(["incomplete"])
+        );
+
+        let options = ResolveOptions::new(Direction::Response, "complete");
+        let result = resolve(&schema, &options).unwrap();
+        assert_eq!(
+            result["properties"]["status"]["enum"],
+            json!(["incomplete", "ready", "completed", "fraud_review"])
+        );
+
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let result = resolve(&schema, &options).unwrap();
+        assert_eq!(
+            result["properties"]["status"]["enum"],
+            json!(["incomplete", "ready"])
+        );
+    }
+
+    #[test]
+    fn annotation_stripped() {
+        let schema = status_schema(json!(["ready"]));
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let result = resolve(&schema, &options).unwrap();
+
+        assert_eq!(result["properties"]["status"]["enum"], json!(["ready"]));
+        assert!(!result.to_string().contains("ucp_enum"));
+    }
+
+    #[test]
+    fn invalid_shape_errors() {
+        let schema = status_schema(json!({ "response": { "read": "completed" } }));
+        let options = ResolveOptions::new(Direction::Response, "read");
+
+        let err = resolve(&schema, &options).unwrap_err();
+        assert!(matches!(err, ResolveError::InvalidEnumAnnotation { .. }));
+        assert_eq!(err.exit_code(), 2);
+    }
+}
+
+// === Description Override Tests ===
+
+mod description_override {
+    use super::*;
+
+    fn status_schema(ucp_description: Value) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "status": {
+                    "type": "string",
+                    "description": "Checkout status.",
+                    "ucp_description": ucp_description
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn operation_selects_description() {
+        let schema = status_schema(json!({
+            "create": "Ignored on create; the server assigns the initial status.",
+            "read": "Current status of the checkout."
+        }));
+
+        let create = resolve(&schema, &ResolveOptions::new(Direction::Request, "create")).unwrap();
+        let read = resolve(&schema, &ResolveOptions::new(Direction::Response, "read")).unwrap();
+        assert_eq!(
+            create["properties"]["status"],
+            json!({
+                "type": "string",
+                "description": "Ignored on create; the server assigns the initial status."
+            })
+        );
+        assert_eq!(
+            read["properties"]["status"]["description"],
+            "Current status of the checkout."
+        );
+
+        // No entry for the operation: base description kept
+        let update = resolve(&schema, &ResolveOptions::new(Direction::Request, "update")).unwrap();
+        assert_eq!(
+            update["properties"]["status"],
+            json!({ "type": "string", "description": "Checkout status." })
+        );
+    }
+
+    #[test]
+    fn non_string_entry_errors() {
+        let schema = status_schema(json!({ "read": ["not", "text"] }));
+        let result = resolve(&schema, &ResolveOptions::new(Direction::Response, "read"));
+        assert!(matches!(
+            result,
+            Err(ResolveError::InvalidDescriptionAnnotation { ref path, .. })
+                if path == "/properties/status/ucp_description/read"
+        ));
+    }
+}
+
+// === Property Names Narrowing Tests ===
+
+mod property_names_narrowing {
+    use super::*;
+
+    fn metadata_schema(ucp_property_names: Value) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "metadata": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                    "propertyNames": { "maxLength": 64 },
+                    "ucp_property_names": ucp_property_names
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn create_stricter_than_read() {
+        let schema = metadata_schema(json!({ "create": { "pattern": "^[a-z]+$" } }));
+
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let result = resolve(&schema, &options).unwrap();
+        assert_eq!(
+            result["properties"]["metadata"]["propertyNames"],
+            json!({ "maxLength": 64, "pattern": "^[a-z]+$" })
+        );
+        assert!(!result.to_string().contains("ucp_property_names"));
+
+        let options = ResolveOptions::new(Direction::Response, "read");
+        let result = resolve(&schema, &options).unwrap();
+        assert_eq!(
+            result["properties"]["metadata"]["propertyNames"],
+            json!({ "maxLength": 64 })
+        );
+        assert!(!result.to_string().contains("ucp_property_names"));
+    }
+
+    #[test]
+    fn adds_property_names_when_absent() {
+        let schema = json!({
+            "type": "object",
+            "ucp_property_names": { "update": { "enum": ["a", "b"] } }
+        });
+        let options = ResolveOptions::new(Direction::Request, "update");
+        let result = resolve(&schema, &options).unwrap();
+
+        assert_eq!(
+            result,
+            json!({ "type": "object", "propertyNames": { "enum": ["a", "b"] } })
+        );
+    }
+
+    #[test]
+    fn unsupported_keyword_errors() {
+        let schema = metadata_schema(json!({ "create": { "format": "uuid" } }));
+        let options = ResolveOptions::new(Direction::Request, "create");
+
+        let err = resolve(&schema, &options).unwrap_err();
+        assert!(matches!(
+            err,
+            ResolveError::InvalidPropertyNamesAnnotation { .. }
+        ));
+        assert!(err.to_string().contains("\"format\""));
+        assert_eq!(err.exit_code(), 2);
+    }
+}
+
+// === Denullify Tests ===
+
+mod denullify {
+    use super::*;
+
+    fn nullable_schema(types: Value) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "note": { "type": types, "maxLength": 200 }
+            }
+        })
+    }
+
+    fn options(mode: Denullify) -> ResolveOptions {
+        ResolveOptions::new(Direction::Request, "create").denullify(Some(mode))
+    }
+
+    #[test]
+    fn openapi_sets_nullable() {
+        let schema = nullable_schema(json!(["string", "null"]));
+        let result = resolve(&schema, &options(Denullify::OpenApi)).unwrap();
+        assert_eq!(
+            result["properties"]["note"],
+            json!({ "type": "string", "nullable": true, "maxLength": 200 })
+        );
+    }
+
+    #[test]
+    fn drop_null_keeps_non_null_member() {
+        let schema = nullable_schema(json!(["string", "null"]));
+        let result = resolve(&schema, &options(Denullify::DropNull)).unwrap();
+        assert_eq!(
+            result["properties"]["note"],
+            json!({ "type": "string", "maxLength": 200 })
+        );
+    }
+
+    #[test]
+    fn non_nullable_arrays_left_alone() {
+        let schema = nullable_schema(json!(["string", "integer"]));
+        for mode in [Denullify::OpenApi, Denullify::DropNull] {
+            let result = resolve(&schema, &options(mode)).unwrap();
+            assert_eq!(
+                result["properties"]["note"]["type"],
+                json!(["string", "integer"])
+            );
+            assert!(result["properties"]["note"].get("nullable").is_none());
+        }
+    }
+
+    #[test]
+    fn multiple_non_null_members_error() {
+        let schema = nullable_schema(json!(["string", "integer", "null"]));
+        let err = resolve(&schema, &options(Denullify::DropNull)).unwrap_err();
+        assert!(matches!(
+            err,
+            ResolveError::AmbiguousNullableType { ref path, .. } if path == "/properties/note/type"
+        ));
+        assert_eq!(err.exit_code(), 2);
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let schema = nullable_schema(json!(["string", "null"]));
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let result = resolve(&schema, &options).unwrap();
+        assert_eq!(
+            result["properties"]["note"]["type"],
+            json!(["string", "null"])
+        );
+    }
+}
+
+// === Conditional Visibility Tests ===
+
+mod conditional_visibility {
+    use super::*;
+
+    fn buyer_schema(create: Value) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "type": { "type": "string", "enum": ["individual", "business"] },
+                "tax_id": {
+                    "type": "string",
+                    "ucp_request": { "create": create, "update": "omit" }
+                }
+            },
+            "required": ["type"]
+        })
+    }
+
+    fn is_business() -> Value {
+        json!({ "properties": { "type": { "const": "business" } }, "required": ["type"] })
+    }
+
+    #[test]
+    fn create_with_condition_emits_if_then_else() {
+        let schema = buyer_schema(json!({
+            "when": is_business(),
+            "then": "required",
+            "else": "optional"
+        }));
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let result = resolve(&schema, &options).unwrap();
+
+        assert_eq!(result["properties"]["tax_id"], json!({ "type": "string" }));
+        assert_eq!(result["required"], json!(["type"]));
+        assert_eq!(result["if"], is_business());
+        assert_eq!(result["then"], json!({ "required": ["tax_id"] }));
+        assert!(result.get("else").is_none());
+    }
+
+    #[test]
+    fn condition_only_applies_to_its_operation() {
+        let schema = buyer_schema(json!({ "when": is_business(), "then": "required" }));
+        let options = ResolveOptions::new(Direction::Request, "update");
+        let result = resolve(&schema, &options).unwrap();
+
+        assert!(result["properties"].get("tax_id").is_none());
+        assert!(result.get("if").is_none());
+    }
+
+    #[test]
+    fn omit_branch_forbids_property() {
+        let schema = buyer_schema(json!({
+            "when": is_business(),
+            "then": "required",
+            "else": "omit"
+        }));
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let result = resolve(&schema, &options).unwrap();
+
+        assert_eq!(result["else"], json!({ "properties": { "tax_id": false } }));
     }
 
     #[test]
-    fn defs() {
+    fn multiple_conditions_go_to_all_of() {
         let schema = json!({
             "type": "object",
-            "$defs": {
-                "address": {
-                    "type": "object",
-                    "properties": {
-                        "street": { "type": "string" },
-                        "internal_id": { "type": "string", "ucp_request": "omit" }
-                    }
+            "properties": {
+                "type": { "type": "string" },
+                "tax_id": {
+                    "type": "string",
+                    "ucp_request": { "create": { "when": is_business(), "then": "required" } }
+                },
+                "company": {
+                    "type": "string",
+                    "ucp_request": { "create": { "when": is_business(), "then": "required" } }
                 }
+            }
+        });
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let result = resolve(&schema, &options).unwrap();
+
+        let all_of = result["allOf"].as_array().unwrap();
+        assert_eq!(all_of.len(), 2);
+        assert_eq!(all_of[0]["then"], json!({ "required": ["tax_id"] }));
+        assert_eq!(all_of[1]["then"], json!({ "required": ["company"] }));
+        assert!(result.get("if").is_none());
+    }
+
+    #[test]
+    fn invalid_branch_visibility_errors() {
+        let schema = buyer_schema(json!({ "when": is_business(), "then": "sometimes" }));
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let err = resolve(&schema, &options).unwrap_err();
+
+        assert!(matches!(
+            err,
+            ResolveError::UnknownVisibility { ref path, .. }
+                if path == "/properties/tax_id/create/then"
+        ));
+    }
+}
+
+// === Meta Keyword Tests ===
+
+mod meta_keywords {
+    use super::*;
+
+    /// A custom dialect: a meta-schema declaring `$vocabulary`, with vendor
+    /// keywords and core identifiers throughout.
+    fn dialect_schema() -> Value {
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$id": "https://example.com/schemas/order.json",
+            "$vocabulary": {
+                "https://json-schema.org/draft/2020-12/vocab/core": true,
+                "https://json-schema.org/draft/2020-12/vocab/applicator": true,
+                "https://example.com/vocab/ucp_request": false
             },
+            "$comment": "properties: required, ucp_request: omit",
+            "type": "object",
+            "x-vendor-rank": { "properties": { "ucp_request": "omit" } },
             "properties": {
-                "shipping": { "$ref": "#/$defs/address" }
+                "id": {
+                    "$anchor": "orderId",
+                    "$comment": "assigned by the server",
+                    "type": "string",
+                    "ucp_request": "omit"
+                },
+                "note": { "$id": "note.json", "type": "string" }
             }
-        });
+        })
+    }
+
+    #[test]
+    fn survive_resolve_unchanged() {
+        let schema = dialect_schema();
+        let options = ResolveOptions::new(Direction::Response, "read");
+        let result = resolve(&schema, &options).unwrap();
+
+        for key in ["$schema", "$id", "$vocabulary", "$comment"] {
+            assert_eq!(result[key], schema[key], "{} changed", key);
+        }
+        assert_eq!(result["properties"]["id"]["$anchor"], "orderId");
+        assert_eq!(
+            result["properties"]["id"]["$comment"],
+            "assigned by the server"
+        );
+        assert_eq!(result["properties"]["note"]["$id"], "note.json");
+        // Vendor keywords pass through
+        assert_eq!(result["x-vendor-rank"], schema["x-vendor-rank"]);
+    }
+
+    #[test]
+    fn survive_omitting_operation() {
+        let schema = dialect_schema();
         let options = ResolveOptions::new(Direction::Request, "create");
         let result = resolve(&schema, &options).unwrap();
 
-        // $defs should be transformed
-        assert!(result["$defs"]["address"]["properties"]
-            .get("street")
-            .is_some());
-        assert!(result["$defs"]["address"]["properties"]
-            .get("internal_id")
-            .is_none());
+        assert!(result["properties"].get("id").is_none());
+        assert_eq!(result["$vocabulary"], schema["$vocabulary"]);
+        assert_eq!(result["$comment"], schema["$comment"]);
     }
 
     #[test]
-    fn deep_nesting_five_levels() {
-        let schema = json!({
+    fn survive_strip_annotations() {
+        let schema = dialect_schema();
+        let stripped = strip_annotations(&schema);
+
+        for key in ["$schema", "$id", "$vocabulary", "$comment"] {
+            assert_eq!(stripped[key], schema[key], "{} changed", key);
+        }
+        assert_eq!(stripped["properties"]["id"]["$anchor"], "orderId");
+        assert!(stripped["properties"]["id"].get("ucp_request").is_none());
+    }
+}
+
+// === Keep Annotation Tests ===
+
+mod keep_annotations {
+    use super::*;
+
+    fn order_schema() -> Value {
+        json!({
             "type": "object",
+            "required": ["id"],
             "properties": {
-                "level1": {
+                "id": { "type": "string", "ucp_request": "omit" },
+                "note": { "type": "string", "ucp_request": "required", "ucp_response": "optional" },
+                "shipping": {
                     "type": "object",
                     "properties": {
-                        "level2": {
-                            "type": "object",
-                            "properties": {
-                                "level3": {
-                                    "type": "object",
-                                    "properties": {
-                                        "level4": {
-                                            "type": "object",
-                                            "properties": {
-                                                "level5": {
-                                                    "type": "string",
-                                                    "ucp_request": "omit"
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
+                        "method": { "type": "string", "ucp_request": "optional" }
                     }
                 }
             }
-        });
+        })
+    }
+
+    #[test]
+    fn kept_annotation_survives_and_still_applies() {
+        let options = ResolveOptions::new(Direction::Request, "create")
+            .keep_annotations(vec!["ucp_request".to_string()]);
+        let result = resolve(&order_schema(), &options).unwrap();
+
+        assert!(result["properties"].get("id").is_none());
+        assert_eq!(result["required"], json!(["note"]));
+        assert_eq!(result["properties"]["note"]["ucp_request"], "required");
+        assert!(result["properties"]["note"].get("ucp_response").is_none());
+        assert_eq!(
+            result["properties"]["shipping"]["properties"]["method"]["ucp_request"],
+            "optional"
+        );
+    }
+
+    #[test]
+    fn default_strips_all_annotations() {
         let options = ResolveOptions::new(Direction::Request, "create");
-        let result = resolve(&schema, &options).unwrap();
+        let result = resolve(&order_schema(), &options).unwrap();
 
-        // Deep nested field should be omitted
-        assert!(
-            result["properties"]["level1"]["properties"]["level2"]["properties"]["level3"]
-                ["properties"]["level4"]["properties"]
-                .get("level5")
-                .is_none()
+        assert!(result["properties"]["note"].get("ucp_request").is_none());
+        assert!(result["properties"]["shipping"]["properties"]["method"]
+            .get("ucp_request")
+            .is_none());
+    }
+
+    #[test]
+    fn preserved_annotations_become_extension_keywords() {
+        let options = ResolveOptions::new(Direction::Request, "create").preserve_annotations(true);
+        let result = resolve(&order_schema(), &options).unwrap();
+
+        // Still applied
+        assert!(result["properties"].get("id").is_none());
+        assert_eq!(result["required"], json!(["note"]));
+        let note = &result["properties"]["note"];
+        assert_eq!(note["x-ucp-request"], "required");
+        assert_eq!(note["x-ucp-response"], "optional");
+        assert!(note.get("ucp_request").is_none());
+        assert_eq!(
+            result["properties"]["shipping"]["properties"]["method"]["x-ucp-request"],
+            "optional"
         );
+
+        // Kept annotations stay verbatim; the rest are renamed
+        let options = options.keep_annotations(vec!["ucp_request".to_string()]);
+        let result = resolve(&order_schema(), &options).unwrap();
+        let note = &result["properties"]["note"];
+        assert_eq!(note["ucp_request"], "required");
+        assert!(note.get("x-ucp-request").is_none());
+        assert_eq!(note["x-ucp-response"], "optional");
     }
 }
 
@@ -702,6 +1461,43 @@ mod composition {
         assert!(result["oneOf"][0]["properties"].get("number").is_some());
         assert!(result["oneOf"][1]["properties"].get("routing").is_some());
     }
+
+    #[test]
+    fn variant_annotations_propagate_only_when_asked() {
+        // The same entity per channel: only the web variant annotates "id"
+        let schema = json!({
+            "oneOf": [
+                {
+                    "title": "Web",
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string", "ucp_request": { "create": "omit" } },
+                        "url": { "type": "string" }
+                    }
+                },
+                {
+                    "title": "App",
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string" },
+                        "bundle": { "type": "string" }
+                    }
+                }
+            ]
+        });
+
+        // Default: variants are independent
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let result = resolve(&schema, &options).unwrap();
+        assert!(result["oneOf"][0]["properties"].get("id").is_none());
+        assert!(result["oneOf"][1]["properties"].get("id").is_some());
+
+        let options = options.propagate_variants(true);
+        let result = resolve(&schema, &options).unwrap();
+        assert!(result["oneOf"][0]["properties"].get("id").is_none());
+        assert!(result["oneOf"][1]["properties"].get("id").is_none());
+        assert!(result["oneOf"][1]["properties"].get("bundle").is_some());
+    }
 }
 
 // === allOf Annotation Propagation Tests ===
@@ -1052,6 +1848,66 @@ mod allof_propagation {
         ));
     }
 
+    #[test]
+    fn array_type_conflicts_use_set_intersection() {
+        let schema = |base: Value, ext: Value| {
+            json!({
+                "allOf": [
+                    { "properties": { "count": { "type": base } } },
+                    { "properties": { "name": { "type": "string" } } },
+                    { "properties": { "count": { "type": ext } } }
+                ]
+            })
+        };
+        let opts = ResolveOptions::new(Direction::Response, "read");
+
+        let err = resolve(&schema(json!(["string", "null"]), json!("number")), &opts).unwrap_err();
+        assert!(matches!(
+            err,
+            ResolveError::TypeConflict {
+                base_branch: 0,
+                ext_branch: 2,
+                ..
+            }
+        ));
+        assert!(err.to_string().contains(
+            r#"allOf branch 2 declares "number", but the branches before it (last: branch 0) only admit ["string","null"]"#
+        ));
+
+        for (base, ext) in [
+            (json!(["string", "null"]), json!("string")),
+            (json!("number"), json!(["integer", "null"])),
+        ] {
+            assert!(resolve(&schema(base, ext), &opts).is_ok());
+        }
+    }
+
+    #[test]
+    fn type_conflicts_intersect_across_all_branches() {
+        // Each pair shares a type, but no type fits all three
+        let schema = json!({
+            "allOf": [
+                { "properties": { "value": { "type": ["string", "null"] } } },
+                { "properties": { "value": { "type": ["string", "number"] } } },
+                { "properties": { "value": { "type": ["number", "null"] } } }
+            ]
+        });
+        let opts = ResolveOptions::new(Direction::Response, "read");
+
+        let err = resolve(&schema, &opts).unwrap_err();
+        assert!(matches!(
+            err,
+            ResolveError::TypeConflict {
+                base_branch: 1,
+                ext_branch: 2,
+                ..
+            }
+        ));
+        assert!(err
+            .to_string()
+            .contains(r#"branch 2 declares ["number","null"], but the branches before it (last: branch 1) only admit "string""#));
+    }
+
     #[test]
     fn same_type_no_conflict() {
         let schema = json!({
@@ -1490,6 +2346,27 @@ mod integration {
 mod strict_mode {
     use super::*;
 
+    #[test]
+    fn closes_applicator_subschemas() {
+        let object = json!({ "type": "object", "properties": { "a": { "type": "string" } } });
+        let schema = json!({
+            "type": "array",
+            "prefixItems": [object],
+            "contains": object,
+            "items": {
+                "type": "object",
+                "patternProperties": { "^x-": object }
+            }
+        });
+        let options = ResolveOptions::new(Direction::Request, "create").strict(true);
+        let result = resolve(&schema, &options).unwrap();
+
+        assert_eq!(result["prefixItems"][0]["additionalProperties"], false);
+        assert_eq!(result["contains"]["additionalProperties"], false);
+        let pattern = &result["items"]["patternProperties"]["^x-"];
+        assert_eq!(pattern["additionalProperties"], false);
+    }
+
     #[test]
     fn default_is_not_strict() {
         // Default options should have strict=false (respects schema extensibility)
@@ -1704,3 +2581,142 @@ mod strict_mode {
         assert_eq!(result["additionalProperties"], json!(false));
     }
 }
+
+// === Boolean Schema Tests ===
+
+mod boolean_schemas {
+    use super::*;
+
+    #[test]
+    fn whole_schema_true_and_false_pass_through() {
+        for strict in [false, true] {
+            let options = ResolveOptions::new(Direction::Request, "create").strict(strict);
+            assert_eq!(resolve(&json!(true), &options).unwrap(), json!(true));
+            assert_eq!(resolve(&json!(false), &options).unwrap(), json!(false));
+        }
+    }
+
+    #[test]
+    fn false_property_schema_passes_through() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "legacy": false,
+                "anything": true
+            },
+            "required": ["name"]
+        });
+        for strict in [false, true] {
+            let options = ResolveOptions::new(Direction::Request, "create").strict(strict);
+            let result = resolve(&schema, &options).unwrap();
+
+            assert_eq!(result["properties"]["legacy"], json!(false));
+            assert_eq!(result["properties"]["anything"], json!(true));
+            assert_eq!(result["required"], json!(["name"]));
+        }
+    }
+
+    #[test]
+    fn boolean_property_schema_in_required_preserved() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "flag": true
+            },
+            "required": ["flag"]
+        });
+        let options = ResolveOptions::new(Direction::Response, "read");
+        let result = resolve(&schema, &options).unwrap();
+
+        assert_eq!(result["properties"]["flag"], json!(true));
+        assert_eq!(result["required"], json!(["flag"]));
+    }
+
+    #[test]
+    fn additional_properties_true_left_alone() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "meta": {
+                    "type": "object",
+                    "additionalProperties": true
+                }
+            },
+            "additionalProperties": true
+        });
+        let options = ResolveOptions::new(Direction::Request, "create");
+        let result = resolve(&schema, &options).unwrap();
+
+        assert_eq!(result["additionalProperties"], json!(true));
+        assert_eq!(
+            result["properties"]["meta"]["additionalProperties"],
+            json!(true)
+        );
+    }
+
+    #[test]
+    fn items_false_tuple_survives_strict_closure() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "pair": {
+                    "type": "array",
+                    "prefixItems": [{ "type": "string" }, { "type": "integer" }],
+                    "items": false
+                }
+            }
+        });
+        for strict in [false, true] {
+            let options = ResolveOptions::new(Direction::Request, "create").strict(strict);
+            let result = resolve(&schema, &options).unwrap();
+
+            let pair = &result["properties"]["pair"];
+            assert_eq!(pair["items"], json!(false));
+            assert_eq!(pair["prefixItems"].as_array().unwrap().len(), 2);
+        }
+    }
+
+    #[test]
+    fn boolean_composition_branches() {
+        let schema = json!({
+            "allOf": [
+                true,
+                {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string", "ucp_request": "omit" }
+                    }
+                }
+            ],
+            "anyOf": [false, { "type": "object" }]
+        });
+        for strict in [false, true] {
+            let options = ResolveOptions::new(Direction::Request, "create").strict(strict);
+            let result = resolve(&schema, &options).unwrap();
+
+            assert_eq!(result["allOf"][0], json!(true));
+            assert!(result["allOf"][1]["properties"].get("id").is_none());
+            assert_eq!(result["anyOf"][0], json!(false));
+        }
+    }
+
+    #[test]
+    fn boolean_defs_pass_through() {
+        let schema = json!({
+            "$defs": {
+                "never": false,
+                "always": true
+            },
+            "type": "object",
+            "properties": {
+                "x": { "$ref": "#/$defs/never" }
+            }
+        });
+        let options = ResolveOptions::new(Direction::Request, "create").strict(true);
+        let result = resolve(&schema, &options).unwrap();
+
+        assert_eq!(result["$defs"]["never"], json!(false));
+        assert_eq!(result["$defs"]["always"], json!(true));
+    }
+}